@@ -293,8 +293,8 @@ Options:
   -n --requests REQUESTS   Send the given number of identical requests [default: 1].
   --send-priority-update   Send HTTP/3 priority updates if the query string params 'u' or 'i' are present in URLs
   --max-field-section-size BYTES    Max size of uncompressed field section. Default is unlimited.
-  --qpack-max-table-capacity BYTES  Max capacity of dynamic QPACK decoding.. Any value other that 0 is currently unsupported.
-  --qpack-blocked-streams STREAMS   Limit of blocked streams while decoding. Any value other that 0 is currently unsupported.
+  --qpack-max-table-capacity BYTES  Max capacity of dynamic QPACK decoding.
+  --qpack-blocked-streams STREAMS   Limit of blocked streams while decoding.
   --session-file PATH      File used to cache a TLS session for resumption.
   --source-port PORT       Source port to use when connecting to the server [default: 0].
   --initial-rtt MILLIS     The initial RTT in milliseconds [default: 333].
@@ -469,8 +469,8 @@ Options:
   --max-active-cids NUM       The maximum number of active Connection IDs we can support [default: 2].
   --enable-active-migration   Enable active connection migration.
   --max-field-section-size BYTES    Max size of uncompressed HTTP/3 field section. Default is unlimited.
-  --qpack-max-table-capacity BYTES  Max capacity of QPACK dynamic table decoding. Any value other that 0 is currently unsupported.
-  --qpack-blocked-streams STREAMS   Limit of streams that can be blocked while decoding. Any value other that 0 is currently unsupported.
+  --qpack-max-table-capacity BYTES  Max capacity of QPACK dynamic table decoding.
+  --qpack-blocked-streams STREAMS   Limit of streams that can be blocked while decoding.
   --disable-gso               Disable GSO (linux only).
   --disable-pacing            Disable pacing (linux only).
   --initial-rtt MILLIS     The initial RTT in milliseconds [default: 333].
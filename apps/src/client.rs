@@ -311,6 +311,7 @@ pub fn connect(
                 let recv_info = quiche::RecvInfo {
                     to: local_addr,
                     from,
+                    ecn: quiche::Ecn::NotEct,
                 };
 
                 // Process potentially coalesced packets.
@@ -454,6 +455,15 @@ pub fn connect(
                 },
 
                 quiche::PathEvent::PeerMigrated(..) => unreachable!(),
+
+                quiche::PathEvent::MigratedToPreferredAddress(
+                    local_addr,
+                    peer_addr,
+                ) => {
+                    info!(
+                        "Migrated to server's preferred address ({local_addr}, {peer_addr})"
+                    );
+                },
             }
         }
 
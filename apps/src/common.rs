@@ -741,13 +741,11 @@ fn make_h3_config(
     }
 
     if let Some(v) = qpack_max_table_capacity {
-        // quiche doesn't support dynamic QPACK, so clamp to 0 for now.
-        config.set_qpack_max_table_capacity(v.clamp(0, 0));
+        config.set_qpack_max_table_capacity(v);
     }
 
     if let Some(v) = qpack_blocked_streams {
-        // quiche doesn't support dynamic QPACK, so clamp to 0 for now.
-        config.set_qpack_blocked_streams(v.clamp(0, 0));
+        config.set_qpack_blocked_streams(v);
     }
 
     config
@@ -1347,6 +1345,18 @@ impl HttpConn for Http3Conn {
                     );
                 },
 
+                // Server push is never enabled.
+                Ok((_, quiche::h3::Event::PushPromise { .. })) |
+                Ok((_, quiche::h3::Event::PushStream { .. })) |
+                Ok((_, quiche::h3::Event::CancelPush)) => unreachable!(),
+
+                // WebTransport is never enabled.
+                Ok((_, quiche::h3::Event::WebTransportStream { .. })) |
+                Ok((_, quiche::h3::Event::WebTransportDatagram { .. })) |
+                Ok((_, quiche::h3::Event::WebTransportSessionClosed { .. })) |
+                Ok((_, quiche::h3::Event::WebTransportSessionDrain)) =>
+                    unreachable!(),
+
                 Err(quiche::h3::Error::Done) => {
                     break;
                 },
@@ -1544,6 +1554,18 @@ impl HttpConn for Http3Conn {
                         .send_goaway(conn, self.largest_processed_request)?;
                 },
 
+                Ok((_push_id, quiche::h3::Event::CancelPush)) => (),
+
+                Ok((_, quiche::h3::Event::PushPromise { .. })) |
+                Ok((_, quiche::h3::Event::PushStream { .. })) => unreachable!(),
+
+                // WebTransport is never enabled.
+                Ok((_, quiche::h3::Event::WebTransportStream { .. })) |
+                Ok((_, quiche::h3::Event::WebTransportDatagram { .. })) |
+                Ok((_, quiche::h3::Event::WebTransportSessionClosed { .. })) |
+                Ok((_, quiche::h3::Event::WebTransportSessionDrain)) =>
+                    unreachable!(),
+
                 Err(quiche::h3::Error::Done) => {
                     break;
                 },
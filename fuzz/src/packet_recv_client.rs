@@ -53,7 +53,11 @@ fuzz_target!(|data: &[u8]| {
     )
     .unwrap();
 
-    let info = quiche::RecvInfo { from, to };
+    let info = quiche::RecvInfo {
+        from,
+        to,
+        ecn: quiche::Ecn::NotEct,
+    };
 
     conn.recv(&mut buf, info).ok();
 
@@ -191,6 +191,7 @@ fn main() {
             let recv_info = quiche::RecvInfo {
                 to: local_addr,
                 from,
+                ecn: quiche::Ecn::NotEct,
             };
 
             // Process potentially coalesced packets.
@@ -280,6 +281,21 @@ fn main() {
                         info!("GOAWAY id={goaway_id}");
                     },
 
+                    // Server push is never enabled.
+                    Ok((_, quiche::h3::Event::PushPromise { .. })) |
+                    Ok((_, quiche::h3::Event::PushStream { .. })) |
+                    Ok((_, quiche::h3::Event::CancelPush)) => unreachable!(),
+
+                    // WebTransport is never enabled.
+                    Ok((_, quiche::h3::Event::WebTransportStream { .. })) |
+                    Ok((_, quiche::h3::Event::WebTransportDatagram { .. })) |
+                    Ok((
+                        _,
+                        quiche::h3::Event::WebTransportSessionClosed { .. },
+                    )) |
+                    Ok((_, quiche::h3::Event::WebTransportSessionDrain)) =>
+                        unreachable!(),
+
                     Err(quiche::h3::Error::Done) => {
                         break;
                     },
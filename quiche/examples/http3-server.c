@@ -463,6 +463,15 @@ static void recv_cb(EV_P_ ev_io *w, int revents) {
                         fprintf(stderr, "got GOAWAY\n");
                         break;
                     }
+
+                    case QUICHE_H3_EVENT_PUSH_PROMISE:
+                    case QUICHE_H3_EVENT_PUSH_STREAM:
+                    case QUICHE_H3_EVENT_CANCEL_PUSH:
+                    case QUICHE_H3_EVENT_WEBTRANSPORT_STREAM:
+                    case QUICHE_H3_EVENT_WEBTRANSPORT_DATAGRAM:
+                    case QUICHE_H3_EVENT_WEBTRANSPORT_SESSION_CLOSED:
+                    case QUICHE_H3_EVENT_WEBTRANSPORT_SESSION_DRAIN:
+                        break;
                 }
 
                 quiche_h3_event_free(ev);
@@ -292,6 +292,7 @@ fn main() {
             let recv_info = quiche::RecvInfo {
                 to: socket.local_addr().unwrap(),
                 from,
+                ecn: quiche::Ecn::NotEct,
             };
 
             // Process potentially coalesced packets.
@@ -374,6 +375,27 @@ fn main() {
 
                         Ok((_goaway_id, quiche::h3::Event::GoAway)) => (),
 
+                        Ok((_push_id, quiche::h3::Event::CancelPush)) => (),
+
+                        Ok((_, quiche::h3::Event::PushPromise { .. })) |
+                        Ok((_, quiche::h3::Event::PushStream { .. })) =>
+                            unreachable!(),
+
+                        // WebTransport is never enabled.
+                        Ok((_, quiche::h3::Event::WebTransportStream { .. })) |
+                        Ok((
+                            _,
+                            quiche::h3::Event::WebTransportDatagram { .. },
+                        )) |
+                        Ok((
+                            _,
+                            quiche::h3::Event::WebTransportSessionClosed {
+                                ..
+                            },
+                        )) |
+                        Ok((_, quiche::h3::Event::WebTransportSessionDrain)) =>
+                            unreachable!(),
+
                         Err(quiche::h3::Error::Done) => {
                             break;
                         },
@@ -36,8 +36,6 @@ use quiche::h3::NameValue;
 use quiche::h3::qpack;
 
 fn main() {
-    // TODO: parse params from file name.
-
     let mut args = std::env::args();
 
     let cmd = &args.next().unwrap();
@@ -47,9 +45,23 @@ fn main() {
         return;
     }
 
-    let mut file = File::open(args.next().unwrap()).unwrap();
+    let path = args.next().unwrap();
+
+    // Encoded files are named "<name>.out.<table size>.<blocked streams>.<ack
+    // mode>", parse the decoder limits from there if present.
+    let params: Vec<&str> = path.rsplit('.').take(3).collect();
+
+    let (max_table_capacity, max_blocked_streams) = match params[..] {
+        [_, blocked, capacity] =>
+            (capacity.parse().unwrap_or(0), blocked.parse().unwrap_or(0)),
+
+        _ => (0, 0),
+    };
+
+    let mut file = File::open(path).unwrap();
 
-    let mut dec = qpack::Decoder::new();
+    let mut dec =
+        qpack::Decoder::with_limits(max_table_capacity, max_blocked_streams);
 
     loop {
         let mut stream_id: [u8; 8] = [0; 8];
@@ -72,7 +84,7 @@ fn main() {
         debug!("Got stream={stream_id} len={len}");
 
         if stream_id == 0 {
-            dec.control(&mut data[..len]).unwrap();
+            dec.control(&data[..len]).unwrap();
             continue;
         }
 
@@ -134,6 +134,14 @@ enum quiche_error {
     // The peer send an ACK frame for a skipped packet used for Optimistic ACK
     // mitigation.
     QUICHE_ERR_OPTIMISTIC_ACK_DETECTED = -22,
+
+    // The peer's version negotiation information is inconsistent with the
+    // version used for the connection.
+    QUICHE_ERR_VERSION_NEGOTIATION = -23,
+
+    // The confidentiality or integrity limit of the AEAD algorithm was
+    // reached.
+    QUICHE_ERR_AEAD_LIMIT_REACHED = -24,
 };
 
 // Returns a human readable string with the quiche version number.
@@ -174,12 +182,35 @@ void quiche_config_grease(quiche_config *config, bool v);
 // Configures whether to do path MTU discovery.
 void quiche_config_discover_pmtu(quiche_config *config, bool v);
 
+// Configures whether to mark outgoing packets with ECN.
+void quiche_config_enable_ecn(quiche_config *config, bool v);
+
 // Enables logging of secrets.
 void quiche_config_log_keys(quiche_config *config);
 
 // Enables sending or receiving early data.
 void quiche_config_enable_early_data(quiche_config *config);
 
+// Enables detection of replayed early data, using an in-memory store
+// remembering up to |capacity| early data attempts.
+void quiche_config_enable_anti_replay(quiche_config *config, size_t capacity);
+
+// Enables sending address validation tokens in NEW_TOKEN frames, authenticated
+// with the given 32-byte key.
+int quiche_config_enable_new_token(quiche_config *config, const uint8_t *key,
+                                   size_t key_len);
+
+// Enables storing the address validation tokens received from servers, up to
+// |capacity| tokens, and using them in later connections.
+void quiche_config_enable_token_cache(quiche_config *config, size_t capacity);
+
+// Configures the Encrypted Client Hello configurations offered by clients.
+void quiche_config_set_ech_config_list(quiche_config *config,
+                                       const uint8_t *list, size_t list_len);
+
+// Configures whether clients send a GREASE ECH extension.
+void quiche_config_set_ech_grease(quiche_config *config, bool v);
+
 // Configures the list of supported application protocols.
 int quiche_config_set_application_protos(quiche_config *config,
                                          const uint8_t *protos,
@@ -236,6 +267,8 @@ enum quiche_cc_algorithm {
     QUICHE_CC_CUBIC = 1,
     QUICHE_CC_BBR = 2,
     QUICHE_CC_BBR2 = 3,
+    QUICHE_CC_LEDBAT = 5,
+    QUICHE_CC_BBR3 = 6,
 };
 
 // Sets the congestion control algorithm used.
@@ -270,6 +303,17 @@ void quiche_config_set_stateless_reset_token(quiche_config *config, const uint8_
 // Sets whether the QUIC connection should avoid reusing DCIDs over different paths.
 void quiche_config_set_disable_dcid_reuse(quiche_config *config, bool v);
 
+// Sets the `preferred_address` transport parameter. Either |v4| or |v6| can be
+// NULL. |reset_token| must contain 16 bytes, otherwise the behaviour is undefined.
+int quiche_config_set_preferred_address(quiche_config *config,
+                                        const struct sockaddr *v4, socklen_t v4_len,
+                                        const struct sockaddr *v6, socklen_t v6_len,
+                                        const uint8_t *cid, size_t cid_len,
+                                        const uint8_t *reset_token);
+
+// Sets whether clients should ignore the server's preferred address.
+void quiche_config_set_disable_preferred_address_migration(quiche_config *config, bool v);
+
 // Configures the session ticket key material.
 int quiche_config_set_ticket_key(quiche_config *config, const uint8_t *key, size_t key_len);
 
@@ -313,6 +357,18 @@ ssize_t quiche_retry(const uint8_t *scid, size_t scid_len,
                      const uint8_t *token, size_t token_len,
                      uint32_t version, uint8_t *out, size_t out_len);
 
+// Writes a stateless reset packet in response to a packet of |len_hint| bytes.
+// |reset_token| must contain 16 bytes, otherwise the behaviour is undefined.
+ssize_t quiche_stateless_reset(const uint8_t *dcid, size_t dcid_len,
+                               const uint8_t *reset_token, size_t len_hint,
+                               uint8_t *out, size_t out_len);
+
+// Derives the stateless reset token of a connection ID from a static key.
+// |out| must be able to hold 16 bytes, otherwise the behaviour is undefined.
+int quiche_stateless_reset_token(const uint8_t *key, size_t key_len,
+                                 const uint8_t *cid, size_t cid_len,
+                                 uint8_t *out);
+
 // Returns true if the given protocol version is supported.
 bool quiche_version_is_supported(uint32_t version);
 
@@ -340,6 +396,11 @@ void quiche_conn_set_qlog_fd(quiche_conn *conn, int fd, const char *log_title,
 // Configures the given session for resumption.
 int quiche_conn_set_session(quiche_conn *conn, const uint8_t *buf, size_t buf_len);
 
+// Configures the Encrypted Client Hello configurations offered by the client,
+// overriding the ones set on the configuration.
+int quiche_conn_set_ech_config_list(quiche_conn *conn, const uint8_t *list,
+                                    size_t list_len);
+
 // Sets the `max_idle_timeout` transport parameter, in milliseconds, default is
 // no timeout.
 int quiche_conn_set_max_idle_timeout(quiche_conn *conn, uint64_t v);
@@ -352,6 +413,9 @@ typedef struct {
     // The local address the packet was received on.
     struct sockaddr *to;
     socklen_t to_len;
+
+    // The ECN codepoint of the received IP packet.
+    uint8_t ecn;
 } quiche_recv_info;
 
 // Processes QUIC packets received from the peer.
@@ -369,6 +433,9 @@ typedef struct {
 
     // The time to send the packet out.
     struct timespec at;
+
+    // The ECN codepoint to mark the IP packet with.
+    uint8_t ecn;
 } quiche_send_info;
 
 // Writes a single QUIC packet to be sent to the peer.
@@ -490,6 +557,9 @@ void quiche_conn_destination_id(const quiche_conn *conn, const uint8_t **out, si
 void quiche_conn_application_proto(const quiche_conn *conn, const uint8_t **out,
                                    size_t *out_len);
 
+// Returns the QUIC version used by the connection.
+uint32_t quiche_conn_version(const quiche_conn *conn);
+
 // Returns the peer's leaf certificate (if any) as a DER-encoded buffer.
 void quiche_conn_peer_cert(const quiche_conn *conn, const uint8_t **out, size_t *out_len);
 
@@ -505,6 +575,14 @@ bool quiche_conn_is_established(const quiche_conn *conn);
 // Returns true if the connection is resumed.
 bool quiche_conn_is_resumed(const quiche_conn *conn);
 
+// Returns true if Encrypted Client Hello was negotiated.
+bool quiche_conn_ech_accepted(const quiche_conn *conn);
+
+// Returns true if the server rejected Encrypted Client Hello, in which case the
+// configurations to retry with are returned in |out|.
+bool quiche_conn_ech_retry_configs(const quiche_conn *conn, const uint8_t **out,
+                                   size_t *out_len);
+
 // Returns true if the connection has a pending handshake that has progressed
 // enough to send or receive early data.
 bool quiche_conn_is_in_early_data(const quiche_conn *conn);
@@ -594,6 +672,12 @@ typedef struct {
 
     // The number of streams stopped by remote.
     uint64_t stopped_stream_count_remote;
+
+    // The number of key updates initiated by local.
+    uint64_t key_update_count_local;
+
+    // The number of key updates initiated by remote.
+    uint64_t key_update_count_remote;
 } quiche_stats;
 
 // Collects and returns statistics about the connection.
@@ -751,6 +835,9 @@ bool quiche_conn_is_dgram_recv_queue_full(const quiche_conn *conn);
 // Schedule an ack-eliciting packet on the active path.
 ssize_t quiche_conn_send_ack_eliciting(quiche_conn *conn);
 
+// Initiates an update of the 1-RTT packet protection keys.
+int quiche_conn_initiate_key_update(quiche_conn *conn);
+
 // Schedule an ack-eliciting packet on the specified path.
 ssize_t quiche_conn_send_ack_eliciting_on_path(quiche_conn *conn,
                            const struct sockaddr *local, socklen_t local_len,
@@ -802,6 +889,7 @@ enum quiche_path_event_type {
     QUICHE_PATH_EVENT_CLOSED,
     QUICHE_PATH_EVENT_REUSED_SOURCE_CONNECTION_ID,
     QUICHE_PATH_EVENT_PEER_MIGRATED,
+    QUICHE_PATH_EVENT_MIGRATED_TO_PREFERRED_ADDRESS,
 };
 
 typedef struct quiche_path_event quiche_path_event;
@@ -840,6 +928,11 @@ void quiche_path_event_peer_migrated(const quiche_path_event *ev,
                            struct sockaddr_storage *local, socklen_t *local_len,
                            struct sockaddr_storage *peer, socklen_t *peer_len);
 
+// Should be called if the quiche_path_event_type(...) returns QUICHE_PATH_EVENT_MIGRATED_TO_PREFERRED_ADDRESS.
+void quiche_path_event_migrated_to_preferred_address(const quiche_path_event *ev,
+                           struct sockaddr_storage *local, socklen_t *local_len,
+                           struct sockaddr_storage *peer, socklen_t *peer_len);
+
 // Frees the path event object.
 void quiche_path_event_free(quiche_path_event *ev);
 
@@ -951,6 +1044,16 @@ enum quiche_h3_error {
     // over HTTP/1.1.
     QUICHE_H3_ERR_VERSION_FALLBACK = -20,
 
+    // Error on the QPACK encoder stream.
+    QUICHE_H3_ERR_QPACK_ENCODER_STREAM_ERROR = -21,
+
+    // Error on the QPACK decoder stream.
+    QUICHE_H3_ERR_QPACK_DECODER_STREAM_ERROR = -22,
+
+    // The peer violated the protocol in a way not covered by a more specific
+    // error.
+    QUICHE_H3_ERR_GENERAL_PROTOCOL_ERROR = -23,
+
     // The following QUICHE_H3_TRANSPORT_ERR_* errors are propagated
     // from the QUIC transport layer.
 
@@ -1019,6 +1122,12 @@ enum quiche_h3_error {
 
     // See QUICHE_ERR_OPTIMISTIC_ACK_DETECTED.
     QUICHE_H3_TRANSPORT_ERR_OPTIMISTIC_ACK_DETECTED = QUICHE_ERR_OPTIMISTIC_ACK_DETECTED - 1000,
+
+    // See QUICHE_ERR_VERSION_NEGOTIATION.
+    QUICHE_H3_TRANSPORT_ERR_VERSION_NEGOTIATION = QUICHE_ERR_VERSION_NEGOTIATION - 1000,
+
+    // See QUICHE_ERR_AEAD_LIMIT_REACHED.
+    QUICHE_H3_TRANSPORT_ERR_AEAD_LIMIT_REACHED = QUICHE_ERR_AEAD_LIMIT_REACHED - 1000,
 };
 
 // Stores configuration shared between multiple connections.
@@ -1036,9 +1145,27 @@ void quiche_h3_config_set_qpack_max_table_capacity(quiche_h3_config *config, uin
 // Sets the `SETTINGS_QPACK_BLOCKED_STREAMS` setting.
 void quiche_h3_config_set_qpack_blocked_streams(quiche_h3_config *config, uint64_t v);
 
+enum quiche_h3_qpack_insertion_policy {
+    QUICHE_H3_QPACK_INSERTION_DISABLED = 0,
+    QUICHE_H3_QPACK_INSERTION_NEVER_BLOCK = 1,
+    QUICHE_H3_QPACK_INSERTION_MAXIMIZE_COMPRESSION = 2,
+};
+
+// Sets the policy used by the QPACK encoder to populate the dynamic table.
+void quiche_h3_config_set_qpack_insertion_policy(quiche_h3_config *config,
+                                                 enum quiche_h3_qpack_insertion_policy v);
+
+// Sets the upper bound for the capacity of the QPACK encoder's dynamic table.
+void quiche_h3_config_set_qpack_encoder_max_table_capacity(quiche_h3_config *config,
+                                                           uint64_t v);
+
 // Sets the `SETTINGS_ENABLE_CONNECT_PROTOCOL` setting.
 void quiche_h3_config_enable_extended_connect(quiche_h3_config *config, bool enabled);
 
+// Sets the `SETTINGS_WT_MAX_SESSIONS` setting.
+void quiche_h3_config_set_webtransport_max_sessions(quiche_h3_config *config,
+                                                    uint64_t v);
+
 // Frees the HTTP/3 config object.
 void quiche_h3_config_free(quiche_h3_config *config);
 
@@ -1056,6 +1183,13 @@ enum quiche_h3_event_type {
     QUICHE_H3_EVENT_GOAWAY,
     QUICHE_H3_EVENT_RESET,
     QUICHE_H3_EVENT_PRIORITY_UPDATE,
+    QUICHE_H3_EVENT_PUSH_PROMISE,
+    QUICHE_H3_EVENT_PUSH_STREAM,
+    QUICHE_H3_EVENT_CANCEL_PUSH,
+    QUICHE_H3_EVENT_WEBTRANSPORT_STREAM,
+    QUICHE_H3_EVENT_WEBTRANSPORT_DATAGRAM,
+    QUICHE_H3_EVENT_WEBTRANSPORT_SESSION_CLOSED,
+    QUICHE_H3_EVENT_WEBTRANSPORT_SESSION_DRAIN,
 };
 
 typedef struct quiche_h3_event quiche_h3_event;
@@ -1092,9 +1226,30 @@ int quiche_h3_for_each_setting(quiche_h3_conn *conn,
 // Check whether more frames will follow the headers on the stream.
 bool quiche_h3_event_headers_has_more_frames(quiche_h3_event *ev);
 
+// Returns the push ID of a PUSH_PROMISE or PUSH_STREAM event.
+uint64_t quiche_h3_event_push_id(quiche_h3_event *ev);
+
+// Returns the session ID of a WEBTRANSPORT_STREAM event.
+uint64_t quiche_h3_event_webtransport_session_id(quiche_h3_event *ev);
+
+// Returns the payload of a WEBTRANSPORT_DATAGRAM event.
+void quiche_h3_event_webtransport_datagram(quiche_h3_event *ev,
+                                           const uint8_t **out,
+                                           size_t *out_len);
+
+// Returns the error code and reason of a WEBTRANSPORT_SESSION_CLOSED event.
+void quiche_h3_event_webtransport_session_closed(quiche_h3_event *ev,
+                                                 uint32_t *error_code,
+                                                 const uint8_t **reason,
+                                                 size_t *reason_len);
+
 // Check whether or not extended connection is enabled by the peer
 bool quiche_h3_extended_connect_enabled_by_peer(quiche_h3_conn *conn);
 
+// Check whether or not WebTransport is enabled by the peer.
+bool quiche_h3_webtransport_enabled_by_peer(quiche_h3_conn *conn,
+                                            quiche_conn *quic_conn);
+
 // Frees the HTTP/3 event object.
 void quiche_h3_event_free(quiche_h3_event *ev);
 
@@ -1147,6 +1302,64 @@ ssize_t quiche_h3_recv_body(quiche_h3_conn *conn, quiche_conn *quic_conn,
 int quiche_h3_send_goaway(quiche_h3_conn *conn, quiche_conn *quic_conn,
                           uint64_t id);
 
+// Sends a MAX_PUSH_ID frame to allow the server to push responses.
+int quiche_h3_send_max_push_id(quiche_h3_conn *conn, quiche_conn *quic_conn,
+                               uint64_t push_id);
+
+// Sends a PUSH_PROMISE frame on the specified request stream.
+int64_t quiche_h3_send_push_promise(quiche_h3_conn *conn, quiche_conn *quic_conn,
+                                    uint64_t stream_id,
+                                    const quiche_h3_header *headers,
+                                    size_t headers_len);
+
+// Sends the response of a promised push on a new push stream.
+int64_t quiche_h3_send_push_response(quiche_h3_conn *conn,
+                                     quiche_conn *quic_conn, uint64_t push_id,
+                                     const quiche_h3_header *headers,
+                                     size_t headers_len, bool fin);
+
+// Cancels a server push.
+int quiche_h3_cancel_push(quiche_h3_conn *conn, quiche_conn *quic_conn,
+                          uint64_t push_id);
+
+// Opens a WebTransport session with an extended CONNECT request.
+int64_t quiche_h3_open_webtransport_session(quiche_h3_conn *conn,
+                                            quiche_conn *quic_conn,
+                                            const quiche_h3_header *headers,
+                                            size_t headers_len);
+
+// Accepts a WebTransport session by responding to its CONNECT request.
+int quiche_h3_accept_webtransport_session(quiche_h3_conn *conn,
+                                          quiche_conn *quic_conn,
+                                          uint64_t stream_id,
+                                          const quiche_h3_header *headers,
+                                          size_t headers_len);
+
+// Opens a new stream associated with a WebTransport session.
+int64_t quiche_h3_open_webtransport_stream(quiche_h3_conn *conn,
+                                           quiche_conn *quic_conn,
+                                           uint64_t session_id, bool bidi);
+
+// Sends a datagram associated with a WebTransport session.
+int quiche_h3_send_webtransport_datagram(quiche_h3_conn *conn,
+                                         quiche_conn *quic_conn,
+                                         uint64_t session_id,
+                                         const uint8_t *payload,
+                                         size_t payload_len);
+
+// Closes a WebTransport session.
+int quiche_h3_close_webtransport_session(quiche_h3_conn *conn,
+                                         quiche_conn *quic_conn,
+                                         uint64_t session_id,
+                                         uint32_t error_code,
+                                         const uint8_t *reason,
+                                         size_t reason_len);
+
+// Asks the peer to gracefully wind down a WebTransport session.
+int quiche_h3_drain_webtransport_session(quiche_h3_conn *conn,
+                                         quiche_conn *quic_conn,
+                                         uint64_t session_id);
+
 // Try to parse an Extensible Priority field value.
 int quiche_h3_parse_extensible_priority(uint8_t *priority,
                                         size_t priority_len,
@@ -0,0 +1,466 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::Error;
+use crate::Result;
+
+use crate::frame;
+use crate::packet;
+use crate::recovery;
+
+/// The ack-eliciting threshold used until the peer requests another one.
+const DEFAULT_ACK_ELICITING_THRESHOLD: u64 = 1;
+
+/// The reordering threshold used until the peer requests another one.
+const DEFAULT_REORDERING_THRESHOLD: u64 = 1;
+
+/// The state of the ACK Frequency extension.
+///
+/// As a receiver, ACKs are sent immediately until the peer sends an
+/// ACK_FREQUENCY frame, after which they are delayed according to the
+/// requested thresholds. As a sender, the frequency requested by the
+/// congestion controller is forwarded to the peer.
+pub struct AckFrequency {
+    /// The minimum ACK delay we advertised, if we accept ACK_FREQUENCY frames.
+    local_min_ack_delay: Option<Duration>,
+
+    /// The minimum ACK delay advertised by the peer, if it accepts
+    /// ACK_FREQUENCY frames.
+    peer_min_ack_delay: Option<Duration>,
+
+    /// The maximum ACK delay advertised by the peer.
+    peer_max_ack_delay: Duration,
+
+    /// The largest sequence number of the ACK_FREQUENCY frames received.
+    recv_seq_num: Option<u64>,
+
+    /// The number of ack-eliciting packets we can receive before sending an
+    /// ACK.
+    ack_eliciting_threshold: u64,
+
+    /// The maximum time we can delay an ACK.
+    max_ack_delay: Duration,
+
+    /// The number of out-of-order packets that trigger an immediate ACK.
+    reordering_threshold: u64,
+
+    /// The sequence number of the next ACK_FREQUENCY frame to send.
+    next_seq_num: u64,
+
+    /// The last frequency requested from the peer, if any.
+    requested: Option<recovery::AckFrequency>,
+
+    /// Whether an ACK_FREQUENCY frame needs to be sent.
+    frame_pending: bool,
+
+    /// The maximum ACK delay of the peer used for loss recovery.
+    recovery_max_ack_delay: Duration,
+
+    /// A new maximum ACK delay of the peer to be used for loss recovery.
+    recovery_max_ack_delay_update: Option<Duration>,
+}
+
+impl AckFrequency {
+    pub fn new(local_min_ack_delay: Option<u64>) -> Self {
+        AckFrequency {
+            local_min_ack_delay: local_min_ack_delay.map(Duration::from_micros),
+            peer_min_ack_delay: None,
+            peer_max_ack_delay: Duration::ZERO,
+            recv_seq_num: None,
+            ack_eliciting_threshold: DEFAULT_ACK_ELICITING_THRESHOLD,
+            max_ack_delay: Duration::ZERO,
+            reordering_threshold: DEFAULT_REORDERING_THRESHOLD,
+            next_seq_num: 0,
+            requested: None,
+            frame_pending: false,
+            recovery_max_ack_delay: Duration::ZERO,
+            recovery_max_ack_delay_update: None,
+        }
+    }
+
+    /// Records the peer's transport parameters.
+    ///
+    /// `max_ack_delay` is in milliseconds and `min_ack_delay` in microseconds,
+    /// as on the wire.
+    pub fn on_peer_params(
+        &mut self, min_ack_delay: Option<u64>, max_ack_delay: u64,
+    ) {
+        self.peer_min_ack_delay = min_ack_delay.map(Duration::from_micros);
+        self.peer_max_ack_delay = Duration::from_millis(max_ack_delay);
+        self.recovery_max_ack_delay = self.peer_max_ack_delay;
+    }
+
+    /// Returns whether we accept ACK_FREQUENCY and IMMEDIATE_ACK frames.
+    pub fn recv_enabled(&self) -> bool {
+        self.local_min_ack_delay.is_some()
+    }
+
+    /// Returns whether both endpoints support the extension, which is
+    /// required to send ACK_FREQUENCY frames.
+    pub fn send_enabled(&self) -> bool {
+        self.local_min_ack_delay.is_some() && self.peer_min_ack_delay.is_some()
+    }
+
+    /// Processes an ACK_FREQUENCY frame.
+    ///
+    /// `request_max_ack_delay` is in microseconds.
+    pub fn on_frame_received(
+        &mut self, seq_num: u64, ack_eliciting_threshold: u64,
+        request_max_ack_delay: u64, reordering_threshold: u64,
+    ) -> Result<()> {
+        let min_ack_delay =
+            self.local_min_ack_delay.ok_or(Error::InvalidState)?;
+
+        let max_ack_delay = Duration::from_micros(request_max_ack_delay);
+
+        if max_ack_delay < min_ack_delay {
+            return Err(Error::InvalidState);
+        }
+
+        // Ignore frames that were reordered.
+        if self.recv_seq_num.is_some_and(|s| s >= seq_num) {
+            return Ok(());
+        }
+
+        self.recv_seq_num = Some(seq_num);
+        self.ack_eliciting_threshold = ack_eliciting_threshold;
+        self.max_ack_delay = max_ack_delay;
+        self.reordering_threshold = reordering_threshold;
+
+        Ok(())
+    }
+
+    /// Updates the reordering state of `space` for the received packet `pn`,
+    /// before it is added to the packets to acknowledge.
+    ///
+    /// Returns whether the packet needs to be acknowledged immediately
+    /// because of reordering.
+    pub fn on_packet_received(
+        &self, space: &mut packet::PktNumSpace, pn: u64,
+    ) -> bool {
+        let largest = match space.recv_pkt_need_ack.last() {
+            Some(v) => v,
+
+            None => return false,
+        };
+
+        if space.first_unreported_missing == Some(pn) {
+            space.first_unreported_missing = None;
+        }
+
+        if pn > largest + 1 && space.first_unreported_missing.is_none() {
+            space.first_unreported_missing = Some(largest + 1);
+        }
+
+        match space.first_unreported_missing {
+            Some(missing) if self.reordering_threshold > 0 =>
+                largest.max(pn) - missing >= self.reordering_threshold,
+
+            _ => false,
+        }
+    }
+
+    /// Records that an ack-eliciting packet was received on `space`, and
+    /// decides whether the ACK can be delayed.
+    pub fn on_ack_eliciting_received(
+        &self, space: &mut packet::PktNumSpace, immediate: bool, now: Instant,
+    ) {
+        // Until the peer asks otherwise, ACKs are sent right away.
+        if self.recv_seq_num.is_none() || immediate {
+            space.ack_elicited = true;
+            return;
+        }
+
+        space.ack_eliciting_since_ack += 1;
+
+        if space.ack_eliciting_since_ack > self.ack_eliciting_threshold {
+            space.ack_elicited = true;
+            return;
+        }
+
+        space.ack_timer.get_or_insert(now + self.max_ack_delay);
+    }
+
+    /// Updates the frequency to request from the peer, as reported by the
+    /// congestion controller.
+    pub fn update(&mut self, requested: Option<recovery::AckFrequency>) {
+        let peer_min_ack_delay = match self.peer_min_ack_delay {
+            Some(v) if self.send_enabled() => v,
+
+            _ => return,
+        };
+
+        let default = self.default_frequency();
+
+        let mut frequency = requested.unwrap_or(default);
+        frequency.max_ack_delay = frequency.max_ack_delay.max(peer_min_ack_delay);
+
+        if frequency != self.requested.unwrap_or(default) {
+            self.requested = Some(frequency);
+            self.frame_pending = true;
+        }
+    }
+
+    /// Returns the ACK_FREQUENCY frame to send, if any.
+    pub fn next_frame(&self) -> Option<frame::Frame> {
+        if !self.frame_pending {
+            return None;
+        }
+
+        let frequency = self.requested?;
+
+        Some(frame::Frame::AckFrequency {
+            seq_num: self.next_seq_num,
+            ack_eliciting_threshold: frequency.ack_eliciting_threshold,
+            request_max_ack_delay: frequency.max_ack_delay.as_micros() as u64,
+            reordering_threshold: frequency.reordering_threshold,
+        })
+    }
+
+    pub fn on_frame_sent(&mut self) {
+        self.next_seq_num += 1;
+        self.frame_pending = false;
+
+        // The peer might start delaying ACKs before our frame is acked, so
+        // account for the larger delay right away.
+        if let Some(frequency) = self.requested {
+            if frequency.max_ack_delay > self.recovery_max_ack_delay {
+                self.recovery_max_ack_delay_update =
+                    Some(frequency.max_ack_delay);
+            }
+        }
+    }
+
+    pub fn on_frame_lost(&mut self, seq_num: u64) {
+        // Only the latest frame matters to the peer.
+        if seq_num + 1 == self.next_seq_num {
+            self.frame_pending = true;
+        }
+    }
+
+    pub fn on_frame_acked(&mut self, seq_num: u64, request_max_ack_delay: u64) {
+        let max_ack_delay = Duration::from_micros(request_max_ack_delay);
+
+        if seq_num + 1 == self.next_seq_num &&
+            max_ack_delay != self.recovery_max_ack_delay
+        {
+            self.recovery_max_ack_delay_update = Some(max_ack_delay);
+        }
+    }
+
+    /// Returns the new maximum ACK delay of the peer to use for loss
+    /// recovery, if it changed.
+    pub fn take_recovery_max_ack_delay(&mut self) -> Option<Duration> {
+        let max_ack_delay = self.recovery_max_ack_delay_update.take()?;
+
+        self.recovery_max_ack_delay = max_ack_delay;
+
+        Some(max_ack_delay)
+    }
+
+    /// Returns whether loss probes should carry an IMMEDIATE_ACK frame, as
+    /// the peer was asked to delay ACKs.
+    pub fn immediate_ack_enabled(&self) -> bool {
+        self.requested.is_some()
+    }
+
+    fn default_frequency(&self) -> recovery::AckFrequency {
+        recovery::AckFrequency {
+            ack_eliciting_threshold: DEFAULT_ACK_ELICITING_THRESHOLD,
+            max_ack_delay: self.peer_max_ack_delay,
+            reordering_threshold: DEFAULT_REORDERING_THRESHOLD,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frequency(
+        ack_eliciting_threshold: u64, max_ack_delay: u64,
+    ) -> recovery::AckFrequency {
+        recovery::AckFrequency {
+            ack_eliciting_threshold,
+            max_ack_delay: Duration::from_millis(max_ack_delay),
+            reordering_threshold: 3,
+        }
+    }
+
+    #[test]
+    fn delayed_acks() {
+        let mut ack_freq = AckFrequency::new(Some(1000));
+        let mut space = packet::PktNumSpace::new();
+        let now = Instant::now();
+
+        // ACKs are immediate by default.
+        ack_freq.on_ack_eliciting_received(&mut space, false, now);
+        assert!(space.ack_elicited);
+        space.on_ack_sent();
+
+        // The requested delay can't be smaller than the advertised one.
+        assert_eq!(
+            ack_freq.on_frame_received(0, 2, 999, 0),
+            Err(Error::InvalidState)
+        );
+
+        assert_eq!(ack_freq.on_frame_received(1, 2, 20_000, 0), Ok(()));
+
+        // Reordered frames are ignored.
+        assert_eq!(ack_freq.on_frame_received(0, 0, 1000, 0), Ok(()));
+
+        ack_freq.on_ack_eliciting_received(&mut space, false, now);
+        assert!(!space.ack_elicited);
+        assert_eq!(space.ack_timer, Some(now + Duration::from_millis(20)));
+        assert!(space.ack_pending());
+
+        ack_freq.on_ack_eliciting_received(&mut space, false, now);
+        assert!(!space.ack_elicited);
+
+        // The threshold is exceeded.
+        ack_freq.on_ack_eliciting_received(&mut space, false, now);
+        assert!(space.ack_elicited);
+
+        space.on_ack_sent();
+        assert!(!space.ack_pending());
+
+        // IMMEDIATE_ACK frames bypass the threshold.
+        ack_freq.on_ack_eliciting_received(&mut space, true, now);
+        assert!(space.ack_elicited);
+    }
+
+    #[test]
+    fn disabled() {
+        let mut ack_freq = AckFrequency::new(None);
+
+        assert!(!ack_freq.recv_enabled());
+        assert_eq!(
+            ack_freq.on_frame_received(0, 2, 20_000, 0),
+            Err(Error::InvalidState)
+        );
+
+        // The peer supports the extension, but we don't.
+        ack_freq.on_peer_params(Some(1000), 25);
+        ack_freq.update(Some(frequency(10, 5)));
+        assert_eq!(ack_freq.next_frame(), None);
+    }
+
+    #[test]
+    fn reordering() {
+        let mut ack_freq = AckFrequency::new(Some(1000));
+        let mut space = packet::PktNumSpace::new();
+
+        assert_eq!(ack_freq.on_frame_received(0, 10, 20_000, 2), Ok(()));
+
+        for pn in 0..3 {
+            assert!(!ack_freq.on_packet_received(&mut space, pn));
+            space.recv_pkt_need_ack.push_item(pn);
+        }
+
+        // Packet 3 is missing.
+        assert!(!ack_freq.on_packet_received(&mut space, 4));
+        space.recv_pkt_need_ack.push_item(4);
+        assert_eq!(space.first_unreported_missing, Some(3));
+
+        assert!(ack_freq.on_packet_received(&mut space, 5));
+        space.recv_pkt_need_ack.push_item(5);
+
+        // The missing packet arrives.
+        assert!(!ack_freq.on_packet_received(&mut space, 3));
+        space.recv_pkt_need_ack.push_item(3);
+        assert_eq!(space.first_unreported_missing, None);
+    }
+
+    #[test]
+    fn requests() {
+        let mut ack_freq = AckFrequency::new(Some(1000));
+        ack_freq.on_peer_params(Some(2000), 25);
+
+        // Nothing to request while the default frequency is used.
+        ack_freq.update(None);
+        assert_eq!(ack_freq.next_frame(), None);
+        assert!(!ack_freq.immediate_ack_enabled());
+
+        ack_freq.update(Some(frequency(10, 5)));
+        assert_eq!(
+            ack_freq.next_frame(),
+            Some(frame::Frame::AckFrequency {
+                seq_num: 0,
+                ack_eliciting_threshold: 10,
+                request_max_ack_delay: 5000,
+                reordering_threshold: 3,
+            })
+        );
+        ack_freq.on_frame_sent();
+        assert!(ack_freq.immediate_ack_enabled());
+
+        // The same frequency is not requested twice.
+        ack_freq.update(Some(frequency(10, 5)));
+        assert_eq!(ack_freq.next_frame(), None);
+
+        // The delay can't be smaller than the peer's minimum.
+        ack_freq.update(Some(frequency(4, 1)));
+        let frame = ack_freq.next_frame().unwrap();
+        assert_eq!(frame, frame::Frame::AckFrequency {
+            seq_num: 1,
+            ack_eliciting_threshold: 4,
+            request_max_ack_delay: 2000,
+            reordering_threshold: 3,
+        });
+        ack_freq.on_frame_sent();
+
+        // Only the latest frame is retransmitted.
+        ack_freq.on_frame_lost(0);
+        assert_eq!(ack_freq.next_frame(), None);
+
+        ack_freq.on_frame_lost(1);
+        assert!(ack_freq.next_frame().is_some());
+        ack_freq.on_frame_sent();
+
+        // Once acked, the requested delay is used for loss recovery.
+        ack_freq.on_frame_acked(2, 2000);
+        assert_eq!(
+            ack_freq.take_recovery_max_ack_delay(),
+            Some(Duration::from_millis(2))
+        );
+        assert_eq!(ack_freq.take_recovery_max_ack_delay(), None);
+
+        // Going back to the default frequency is requested explicitly.
+        ack_freq.update(None);
+        assert_eq!(
+            ack_freq.next_frame(),
+            Some(frame::Frame::AckFrequency {
+                seq_num: 3,
+                ack_eliciting_threshold: 1,
+                request_max_ack_delay: 25_000,
+                reordering_threshold: 1,
+            })
+        );
+    }
+}
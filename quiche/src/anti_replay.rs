@@ -0,0 +1,237 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Anti-replay protection for 0-RTT.
+//!
+//! Early data is not protected against replay by TLS itself: an attacker can
+//! capture a client's first flight and send it again, possibly to another
+//! server instance sharing the same session ticket key. Servers can detect
+//! such replays by recording the ClientHello messages that offer early data,
+//! as described in [RFC 8446 Section 8.2].
+//!
+//! [RFC 8446 Section 8.2]: https://www.rfc-editor.org/rfc/rfc8446#section-8.2
+
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::crypto;
+use crate::rand;
+
+/// The default amount of time a [`StrikeRegister`] remembers early data
+/// attempts for.
+///
+/// Early data is rejected when the ticket age reported by the client is off by
+/// more than 60 seconds, so a replayed ClientHello can't lead to early data
+/// being accepted more than 120 seconds after the original one.
+pub const DEFAULT_ANTI_REPLAY_WINDOW: Duration = Duration::from_secs(120);
+
+/// The length of the digests recorded by a [`StrikeRegister`].
+const STRIKE_LEN: usize = 16;
+
+/// A store of early data attempts, used by servers to detect replayed 0-RTT.
+///
+/// When configured with [`Config::set_anti_replay()`], a server records every
+/// ClientHello offering early data before deciding whether to accept it. The
+/// key identifying an attempt is made of the PSK identity (i.e. the session
+/// ticket) followed by its obfuscated ticket age, both of which are covered by
+/// the PSK binder, so they can't be altered without the handshake failing.
+///
+/// Implementations backed by a store shared by multiple server instances let
+/// them detect replays across each other.
+///
+/// [`Config::set_anti_replay()`]: struct.Config.html#method.set_anti_replay
+pub trait AntiReplay: Send + Sync {
+    /// Records the early data attempt identified by `key`.
+    ///
+    /// Returns `true` if the attempt was not seen before, in which case early
+    /// data may be accepted. Returns `false` if the attempt is a replay, or if
+    /// it can't be recorded (e.g. because the store is full or unreachable),
+    /// in which case early data is rejected and the handshake falls back to
+    /// 1-RTT.
+    fn record(&self, key: &[u8]) -> bool;
+}
+
+/// A bounded in-memory [`AntiReplay`] store.
+///
+/// Attempts are remembered for the duration of the anti-replay window, after
+/// which the client's ticket age no longer allows early data to be accepted.
+/// Once `capacity` attempts are being remembered, further ones are rejected
+/// until the oldest expire.
+pub struct StrikeRegister {
+    secret: [u8; 32],
+
+    window: Duration,
+
+    capacity: usize,
+
+    strikes: Mutex<Strikes>,
+}
+
+#[derive(Default)]
+struct Strikes {
+    seen: HashSet<[u8; STRIKE_LEN]>,
+
+    expiries: VecDeque<(Instant, [u8; STRIKE_LEN])>,
+}
+
+impl StrikeRegister {
+    /// Creates a strike register remembering up to `capacity` attempts for
+    /// [`DEFAULT_ANTI_REPLAY_WINDOW`].
+    pub fn new(capacity: usize) -> StrikeRegister {
+        StrikeRegister::with_window(capacity, DEFAULT_ANTI_REPLAY_WINDOW)
+    }
+
+    /// Creates a strike register remembering up to `capacity` attempts for
+    /// the given `window`.
+    pub fn with_window(capacity: usize, window: Duration) -> StrikeRegister {
+        let mut secret = [0; 32];
+        rand::rand_bytes(&mut secret);
+
+        StrikeRegister {
+            secret,
+            window,
+            capacity,
+            strikes: Mutex::new(Strikes::default()),
+        }
+    }
+
+    /// Returns the number of attempts currently remembered.
+    pub fn len(&self) -> usize {
+        self.strikes.lock().map(|s| s.seen.len()).unwrap_or(0)
+    }
+
+    /// Returns true if no attempt is currently remembered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn record_at(&self, key: &[u8], now: Instant) -> bool {
+        // Keys are stored as keyed digests, which keeps the memory used by
+        // each attempt bounded and the set's hashing unpredictable.
+        let digest = match crypto::hmac_sha256(&self.secret, key) {
+            Ok(v) => v,
+
+            Err(_) => return false,
+        };
+
+        let mut strike = [0; STRIKE_LEN];
+        strike.copy_from_slice(&digest[..STRIKE_LEN]);
+
+        let mut strikes = match self.strikes.lock() {
+            Ok(v) => v,
+
+            Err(_) => return false,
+        };
+
+        while let Some(&(expiry, old)) = strikes.expiries.front() {
+            if expiry > now {
+                break;
+            }
+
+            strikes.seen.remove(&old);
+            strikes.expiries.pop_front();
+        }
+
+        if strikes.seen.contains(&strike) || strikes.seen.len() >= self.capacity {
+            return false;
+        }
+
+        strikes.seen.insert(strike);
+        strikes.expiries.push_back((now + self.window, strike));
+
+        true
+    }
+}
+
+impl AntiReplay for StrikeRegister {
+    fn record(&self, key: &[u8]) -> bool {
+        self.record_at(key, Instant::now())
+    }
+}
+
+impl std::fmt::Debug for StrikeRegister {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("StrikeRegister")
+            .field("window", &self.window)
+            .field("capacity", &self.capacity)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay() {
+        let register = StrikeRegister::new(16);
+        let now = Instant::now();
+
+        assert!(register.record_at(b"ticket1", now));
+        assert!(register.record_at(b"ticket2", now));
+        assert_eq!(register.len(), 2);
+
+        assert!(!register.record_at(b"ticket1", now));
+        assert!(!register.record_at(b"ticket2", now + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn window() {
+        let window = Duration::from_secs(10);
+        let register = StrikeRegister::with_window(16, window);
+        let now = Instant::now();
+
+        assert!(register.record_at(b"ticket1", now));
+        assert!(!register.record_at(b"ticket1", now + window / 2));
+
+        // The attempt is forgotten once the window elapsed.
+        assert!(register.record_at(b"ticket1", now + window));
+        assert_eq!(register.len(), 1);
+    }
+
+    #[test]
+    fn capacity() {
+        let window = Duration::from_secs(10);
+        let register = StrikeRegister::with_window(2, window);
+        let now = Instant::now();
+
+        assert!(register.record_at(b"ticket1", now));
+        assert!(register.record_at(b"ticket2", now + Duration::from_secs(1)));
+
+        // The register is full, so new attempts are rejected.
+        assert!(!register.record_at(b"ticket3", now + Duration::from_secs(2)));
+        assert_eq!(register.len(), 2);
+
+        // Space is reclaimed as soon as the oldest attempt expires.
+        assert!(register.record_at(b"ticket3", now + window));
+        assert!(!register.record_at(b"ticket4", now + window));
+        assert_eq!(register.len(), 2);
+    }
+}
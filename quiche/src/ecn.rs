@@ -0,0 +1,325 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Explicit Congestion Notification (ECN) support.
+//!
+//! Outgoing packets are marked with ECT(0) while the path is being tested and
+//! after ECN has been validated on it, following the procedure described in
+//! [RFC 9000, Section 13.4.2].
+//!
+//! [RFC 9000, Section 13.4.2]: https://www.rfc-editor.org/rfc/rfc9000#section-13.4.2
+
+use crate::frame::EcnCounts;
+
+/// The number of packets marked with ECT(0) while testing a path, before
+/// waiting for the marks to be validated.
+const ECN_TESTING_PACKETS: usize = 10;
+
+/// The ECN codepoint of an IP packet, as defined in [RFC 3168].
+///
+/// [RFC 3168]: https://www.rfc-editor.org/rfc/rfc3168#section-5
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Ecn {
+    /// Not ECN-Capable Transport.
+    #[default]
+    NotEct = 0b00,
+
+    /// ECN-Capable Transport, ECT(1).
+    Ect1   = 0b01,
+
+    /// ECN-Capable Transport, ECT(0).
+    Ect0   = 0b10,
+
+    /// Congestion Experienced.
+    Ce     = 0b11,
+}
+
+impl From<u8> for Ecn {
+    /// Extracts the ECN codepoint from the two least significant bits of the
+    /// given value (e.g. the IPv4 TOS or the IPv6 Traffic Class field).
+    fn from(v: u8) -> Self {
+        match v & 0b11 {
+            0b01 => Ecn::Ect1,
+            0b10 => Ecn::Ect0,
+            0b11 => Ecn::Ce,
+            _ => Ecn::NotEct,
+        }
+    }
+}
+
+impl From<Ecn> for u8 {
+    fn from(v: Ecn) -> Self {
+        v as u8
+    }
+}
+
+/// Increments the counter in `counts` matching the given codepoint.
+pub fn on_packet_received(counts: &mut EcnCounts, ecn: Ecn) {
+    match ecn {
+        Ecn::NotEct => (),
+        Ecn::Ect0 => counts.ect0_count += 1,
+        Ecn::Ect1 => counts.ect1_count += 1,
+        Ecn::Ce => counts.ecn_ce_count += 1,
+    }
+}
+
+/// Validates the ECN counts reported by the peer in an ACK frame that newly
+/// acknowledged `newly_acked_ect0` packets sent with ECT(0).
+///
+/// On success `largest` is updated with the reported counts and the increase
+/// of the CE count is returned. `None` is returned if validation failed.
+pub fn validate_counts(
+    largest: &mut EcnCounts, counts: Option<&EcnCounts>, newly_acked_ect0: u64,
+) -> Option<u64> {
+    let counts = match counts {
+        Some(v) => v,
+
+        // Newly acknowledged ECT(0) packets must be accounted for.
+        None if newly_acked_ect0 > 0 => return None,
+
+        None => return Some(0),
+    };
+
+    // ACK frames can be reordered, so counts older than the ones already
+    // processed are simply ignored.
+    if counts.ect0_count < largest.ect0_count ||
+        counts.ect1_count < largest.ect1_count ||
+        counts.ecn_ce_count < largest.ecn_ce_count
+    {
+        return Some(0);
+    }
+
+    // Packets are never sent with ECT(1), so the peer or the network is
+    // remarking them.
+    if counts.ect1_count > largest.ect1_count {
+        return None;
+    }
+
+    let ect0_increase = counts.ect0_count - largest.ect0_count;
+    let ce_increase = counts.ecn_ce_count - largest.ecn_ce_count;
+
+    if ect0_increase + ce_increase < newly_acked_ect0 {
+        return None;
+    }
+
+    *largest = *counts;
+
+    Some(ce_increase)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    /// ECN is disabled on the path.
+    Disabled,
+
+    /// The first packets on the path are being marked.
+    Testing,
+
+    /// Enough packets were marked, waiting for them to be acknowledged.
+    Unknown,
+
+    /// ECN counts were successfully validated.
+    Capable,
+
+    /// Validation failed, packets are not marked anymore.
+    Failed,
+}
+
+/// The ECN validation state of a path.
+#[derive(Debug)]
+pub struct EcnValidation {
+    state: State,
+
+    /// The number of ECT(0) packets sent while testing.
+    testing_sent: usize,
+}
+
+impl EcnValidation {
+    /// Creates a new validation state, marking packets only if `enabled` is
+    /// true.
+    pub fn new(enabled: bool) -> Self {
+        let state = if enabled {
+            State::Testing
+        } else {
+            State::Disabled
+        };
+
+        EcnValidation {
+            state,
+            testing_sent: 0,
+        }
+    }
+
+    /// Returns the codepoint to use for the next packet sent on the path.
+    pub fn codepoint(&self) -> Ecn {
+        match self.state {
+            State::Testing | State::Capable => Ecn::Ect0,
+
+            _ => Ecn::NotEct,
+        }
+    }
+
+    /// Records a packet sent on the path with the given codepoint.
+    pub fn on_packet_sent(&mut self, ecn: Ecn) {
+        if self.state == State::Testing && ecn == Ecn::Ect0 {
+            self.testing_sent += 1;
+
+            if self.testing_sent >= ECN_TESTING_PACKETS {
+                self.state = State::Unknown;
+            }
+        }
+    }
+
+    /// Records the outcome of the validation of an ACK frame that newly
+    /// acknowledged ECT(0) packets sent on the path.
+    pub fn on_validation(&mut self, valid: bool) {
+        match (self.state, valid) {
+            (State::Disabled, _) | (State::Failed, _) => (),
+
+            (_, false) => self.state = State::Failed,
+
+            (_, true) => self.state = State::Capable,
+        }
+    }
+
+    /// Returns whether ECN was validated on the path.
+    pub fn is_capable(&self) -> bool {
+        self.state == State::Capable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(ect0: u64, ect1: u64, ce: u64) -> EcnCounts {
+        EcnCounts {
+            ect0_count: ect0,
+            ect1_count: ect1,
+            ecn_ce_count: ce,
+        }
+    }
+
+    #[test]
+    fn codepoint_conversion() {
+        for v in 0..4u8 {
+            assert_eq!(u8::from(Ecn::from(v)), v);
+        }
+
+        assert_eq!(Ecn::from(0xff), Ecn::Ce);
+        assert_eq!(Ecn::from(0x02), Ecn::Ect0);
+        assert_eq!(Ecn::from(0xfc), Ecn::NotEct);
+    }
+
+    #[test]
+    fn received_counts() {
+        let mut c = EcnCounts::default();
+
+        on_packet_received(&mut c, Ecn::NotEct);
+        on_packet_received(&mut c, Ecn::Ect0);
+        on_packet_received(&mut c, Ecn::Ect0);
+        on_packet_received(&mut c, Ecn::Ect1);
+        on_packet_received(&mut c, Ecn::Ce);
+
+        assert_eq!(c, counts(2, 1, 1));
+    }
+
+    #[test]
+    fn validate() {
+        let mut largest = EcnCounts::default();
+
+        // No ECT(0) packets acknowledged, no counts.
+        assert_eq!(validate_counts(&mut largest, None, 0), Some(0));
+
+        // Missing counts.
+        assert_eq!(validate_counts(&mut largest, None, 1), None);
+
+        // All marks accounted for.
+        assert_eq!(
+            validate_counts(&mut largest, Some(&counts(3, 0, 0)), 3),
+            Some(0)
+        );
+        assert_eq!(largest, counts(3, 0, 0));
+
+        // Some packets were marked CE by the network.
+        assert_eq!(
+            validate_counts(&mut largest, Some(&counts(4, 0, 2)), 3),
+            Some(2)
+        );
+        assert_eq!(largest, counts(4, 0, 2));
+
+        // Reordered ACK frame.
+        assert_eq!(
+            validate_counts(&mut largest, Some(&counts(3, 0, 0)), 1),
+            Some(0)
+        );
+        assert_eq!(largest, counts(4, 0, 2));
+
+        // Marks were cleared on the path.
+        assert_eq!(
+            validate_counts(&mut largest, Some(&counts(5, 0, 2)), 2),
+            None
+        );
+
+        // Packets were remarked with ECT(1).
+        assert_eq!(
+            validate_counts(&mut largest, Some(&counts(6, 1, 2)), 2),
+            None
+        );
+    }
+
+    #[test]
+    fn validation_state() {
+        let mut v = EcnValidation::new(false);
+        assert_eq!(v.codepoint(), Ecn::NotEct);
+        v.on_validation(true);
+        assert!(!v.is_capable());
+
+        let mut v = EcnValidation::new(true);
+
+        for _ in 0..ECN_TESTING_PACKETS {
+            assert_eq!(v.codepoint(), Ecn::Ect0);
+            v.on_packet_sent(Ecn::Ect0);
+        }
+
+        // Testing is over, stop marking until validated.
+        assert_eq!(v.codepoint(), Ecn::NotEct);
+
+        v.on_validation(true);
+        assert!(v.is_capable());
+        assert_eq!(v.codepoint(), Ecn::Ect0);
+
+        v.on_validation(false);
+        assert!(!v.is_capable());
+        assert_eq!(v.codepoint(), Ecn::NotEct);
+
+        // Failure is permanent.
+        v.on_validation(true);
+        assert!(!v.is_capable());
+        assert_eq!(v.codepoint(), Ecn::NotEct);
+    }
+}
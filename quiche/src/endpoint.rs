@@ -0,0 +1,1045 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Sans-IO server endpoint.
+//!
+//! An [`Endpoint`] owns all the server-side connections bound to a socket,
+//! routes incoming datagrams to them by destination connection ID, and takes
+//! care of the packets that are sent without any connection state, such as
+//! Version Negotiation, Retry and Stateless Reset packets.
+//!
+//! The endpoint never touches sockets: the application feeds it the datagrams
+//! it receives, sends out the datagrams it returns, and arms a timer based on
+//! [`Endpoint::timeout()`].
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use slab::Slab;
+
+use crate::crypto;
+use crate::packet;
+use crate::rand;
+use crate::token;
+
+use crate::BufFactory;
+use crate::Config;
+use crate::Connection;
+use crate::ConnectionId;
+use crate::DefaultBufFactory;
+use crate::Ecn;
+use crate::Error;
+use crate::Header;
+use crate::RecvInfo;
+use crate::Result;
+use crate::SendInfo;
+use crate::Type;
+
+/// The length of the connection IDs generated by the endpoint.
+const CID_LEN: usize = crate::MAX_CONN_ID_LEN;
+
+/// The length of the endpoint's static key.
+const KEY_LEN: usize = 32;
+
+/// The minimum length of the destination connection ID chosen by a client for
+/// its first Initial packet, as per RFC 9000 Section 7.2.
+const MIN_INITIAL_DCID_LEN: usize = 8;
+
+/// How long a Retry token is accepted for after being minted.
+const RETRY_TOKEN_LIFETIME: Duration = Duration::from_secs(10);
+
+/// The length of the authentication tag appended to Retry tokens.
+const RETRY_TOKEN_TAG_LEN: usize = 16;
+
+/// The maximum length of a Version Negotiation or Retry packet.
+const MAX_STATELESS_PKT_LEN: usize = 256;
+
+/// The maximum number of stateless packets waiting to be sent. Packets
+/// generated while the queue is full are dropped.
+const MAX_PENDING_STATELESS: usize = 64;
+
+/// An opaque identifier for a connection owned by an [`Endpoint`].
+///
+/// Handles of connections that have been removed from the endpoint (see
+/// [`Endpoint::closed_next()`]) may be reused for new connections.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ConnectionHandle(usize);
+
+struct Entry<F: BufFactory> {
+    conn: Connection<F>,
+
+    /// The connection IDs routed to the connection.
+    cids: Vec<ConnectionId<'static>>,
+}
+
+/// A sans-IO QUIC server endpoint.
+///
+/// The endpoint accepts new connections, issues connection IDs to them and
+/// routes incoming datagrams accordingly. Datagrams that don't belong to any
+/// connection are answered with Version Negotiation, Retry or Stateless Reset
+/// packets as appropriate.
+///
+/// Retry tokens and stateless reset tokens are derived from a static key that
+/// is randomly generated when the endpoint is created, the latter as done by
+/// [`stateless_reset_token()`]. Endpoints that need to recognize each other's
+/// tokens, for example across restarts or across servers sharing the same
+/// address, should be configured with the same key using [`set_key()`].
+///
+/// ## Examples:
+///
+/// ```no_run
+/// # let mut buf = [0; 65535];
+/// # let mut out = [0; 1350];
+/// # let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+/// # let local = socket.local_addr().unwrap();
+/// let config = quiche::Config::new(quiche::PROTOCOL_VERSION)?;
+/// let mut endpoint = quiche::Endpoint::new(config);
+///
+/// loop {
+///     let (len, from) = socket.recv_from(&mut buf).unwrap();
+///
+///     let info = quiche::RecvInfo {
+///         from,
+///         to: local,
+///         ecn: quiche::Ecn::NotEct,
+///     };
+///
+///     if let Ok(handle) = endpoint.recv(&mut buf[..len], info) {
+///         let conn = endpoint.conn_mut(handle).unwrap();
+///         // Process readable streams...
+///     }
+///
+///     loop {
+///         let (write, send_info) = match endpoint.send(&mut out) {
+///             Ok(v) => v,
+///
+///             Err(quiche::Error::Done) => break,
+///
+///             Err(e) => return Err(e),
+///         };
+///
+///         socket.send_to(&out[..write], send_info.to).unwrap();
+///     }
+///
+///     while let Some((handle, conn)) = endpoint.closed_next() {
+///         // Release state associated with the connection...
+///     }
+/// }
+/// # Ok::<(), quiche::Error>(())
+/// ```
+///
+/// [`stateless_reset_token()`]: fn.stateless_reset_token.html
+/// [`set_key()`]: struct.Endpoint.html#method.set_key
+pub struct Endpoint<F = DefaultBufFactory>
+where
+    F: BufFactory,
+{
+    config: Config,
+
+    key: [u8; KEY_LEN],
+
+    retry: bool,
+
+    conns: Slab<Entry<F>>,
+
+    routes: HashMap<ConnectionId<'static>, usize>,
+
+    stateless: VecDeque<(Vec<u8>, SendInfo)>,
+
+    send_cursor: usize,
+}
+
+impl Endpoint {
+    /// Creates a new endpoint that accepts connections using the given
+    /// configuration.
+    pub fn new(config: Config) -> Endpoint {
+        Endpoint::with_buf_factory(config)
+    }
+}
+
+impl<F: BufFactory> Endpoint<F> {
+    /// Creates a new endpoint, with a custom buffer generation method for its
+    /// connections.
+    ///
+    /// See [`accept_with_buf_factory()`] for more info.
+    ///
+    /// [`accept_with_buf_factory()`]: fn.accept_with_buf_factory.html
+    pub fn with_buf_factory(config: Config) -> Endpoint<F> {
+        let mut key = [0; KEY_LEN];
+        rand::rand_bytes(&mut key);
+
+        Endpoint {
+            config,
+
+            key,
+
+            retry: false,
+
+            conns: Slab::new(),
+
+            routes: HashMap::new(),
+
+            stateless: VecDeque::new(),
+
+            send_cursor: 0,
+        }
+    }
+
+    /// Sets the static key used to authenticate Retry tokens and to derive
+    /// stateless reset tokens.
+    ///
+    /// The key must be kept secret, and should only be shared with endpoints
+    /// that are expected to recognize each other's tokens.
+    pub fn set_key(&mut self, key: &[u8; KEY_LEN]) {
+        self.key = *key;
+    }
+
+    /// Configures whether to validate client addresses with a Retry packet
+    /// before creating new connections.
+    ///
+    /// Clients presenting a valid token received in a NEW_TOKEN frame, see
+    /// [`Config::set_token_validator()`], are accepted without a Retry.
+    ///
+    /// The default value is `false`.
+    ///
+    /// [`Config::set_token_validator()`]: struct.Config.html#method.set_token_validator
+    pub fn enable_retry(&mut self, v: bool) {
+        self.retry = v;
+    }
+
+    /// Returns a mutable reference to the configuration used for new
+    /// connections.
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    /// Processes a datagram received from the network.
+    ///
+    /// On success the handle of the connection the datagram was delivered to
+    /// is returned. The [`Done`] error is returned when the datagram didn't
+    /// belong to any connection, in which case a stateless packet might have
+    /// been queued in response to it.
+    ///
+    /// Errors returned by the connection while processing the datagram are
+    /// propagated to the caller.
+    ///
+    /// [`Done`]: enum.Error.html#variant.Done
+    pub fn recv(
+        &mut self, buf: &mut [u8], info: RecvInfo,
+    ) -> Result<ConnectionHandle> {
+        let hdr = Header::from_slice(buf, CID_LEN)?;
+
+        if let Some(&idx) = self.routes.get(&hdr.dcid) {
+            let res = self.conns[idx].conn.recv(buf, info);
+
+            self.update_routes(idx)?;
+
+            res?;
+
+            return Ok(ConnectionHandle(idx));
+        }
+
+        if hdr.ty == Type::Short {
+            self.queue_stateless_reset(&hdr.dcid, buf.len(), &info)?;
+
+            return Err(Error::Done);
+        }
+
+        if hdr.ty == Type::VersionNegotiation {
+            return Err(Error::Done);
+        }
+
+        // Only datagrams large enough to carry a client Initial are answered,
+        // to avoid amplification.
+        if buf.len() < crate::MIN_CLIENT_INITIAL_LEN {
+            return Err(Error::Done);
+        }
+
+        if !crate::version_is_supported(hdr.version) {
+            let mut out = [0; MAX_STATELESS_PKT_LEN];
+
+            let len = packet::negotiate_version(&hdr.scid, &hdr.dcid, &mut out)?;
+
+            self.queue_stateless(out[..len].to_vec(), &info);
+
+            return Err(Error::Done);
+        }
+
+        if hdr.ty != Type::Initial || hdr.dcid.len() < MIN_INITIAL_DCID_LEN {
+            return Err(Error::Done);
+        }
+
+        let new_token_valid = token::is_new_token_valid(
+            self.config.token_validator.as_deref(),
+            hdr.token.as_deref(),
+            &info.from,
+        );
+
+        let (scid, odcid) = if self.retry && !new_token_valid {
+            let token = hdr.token.as_deref().unwrap_or_default();
+
+            // Invalid NEW_TOKEN tokens are treated as if no token was sent.
+            // Retry tokens start with their expiry time, so they can't be
+            // mistaken for one.
+            if token.is_empty() || token[0] == token::NEW_TOKEN_PREFIX {
+                let new_scid = new_cid();
+
+                let token =
+                    self.mint_retry_token(&hdr.dcid, &new_scid, &info.from)?;
+
+                let mut out = [0; MAX_STATELESS_PKT_LEN];
+
+                let len = packet::retry(
+                    &hdr.scid,
+                    &hdr.dcid,
+                    &new_scid,
+                    &token,
+                    hdr.version,
+                    &mut out,
+                )?;
+
+                self.queue_stateless(out[..len].to_vec(), &info);
+
+                return Err(Error::Done);
+            }
+
+            let odcid =
+                match self.validate_retry_token(token, &hdr.dcid, &info.from) {
+                    Some(v) => v,
+
+                    None => {
+                        trace!("dropping Initial with invalid retry token");
+
+                        return Err(Error::Done);
+                    },
+                };
+
+            (hdr.dcid.clone().into_owned(), Some(odcid))
+        } else {
+            (new_cid(), None)
+        };
+
+        let reset_token = packet::stateless_reset_token(&self.key, &scid)?;
+        self.config.set_stateless_reset_token(Some(reset_token));
+
+        let conn = crate::accept_with_buf_factory(
+            &scid,
+            odcid.as_ref(),
+            info.to,
+            info.from,
+            &mut self.config,
+        )?;
+
+        // Without address validation the client keeps using the connection
+        // ID it picked until it receives the server's first packet, so that
+        // also needs to be routed to the new connection.
+        let mut cids = vec![scid];
+
+        if odcid.is_none() {
+            cids.push(hdr.dcid.into_owned());
+        }
+
+        let idx = self.conns.vacant_entry().key();
+
+        for cid in &cids {
+            self.routes.insert(cid.clone(), idx);
+        }
+
+        self.conns.insert(Entry { conn, cids });
+
+        let res = self.conns[idx].conn.recv(buf, info);
+
+        self.update_routes(idx)?;
+
+        res?;
+
+        Ok(ConnectionHandle(idx))
+    }
+
+    /// Writes a single datagram to be sent to the network.
+    ///
+    /// Pending stateless packets are returned first, followed by packets
+    /// generated by the connections in a round-robin fashion. The [`Done`]
+    /// error is returned when there is nothing to send.
+    ///
+    /// Connections that fail to generate a packet are closed.
+    ///
+    /// [`Done`]: enum.Error.html#variant.Done
+    pub fn send(&mut self, out: &mut [u8]) -> Result<(usize, SendInfo)> {
+        if let Some((pkt, info)) = self.stateless.pop_front() {
+            if out.len() < pkt.len() {
+                self.stateless.push_front((pkt, info));
+
+                return Err(Error::BufferTooShort);
+            }
+
+            out[..pkt.len()].copy_from_slice(&pkt);
+
+            return Ok((pkt.len(), info));
+        }
+
+        let cap = self.conns.capacity();
+
+        for i in 0..cap {
+            let idx = (self.send_cursor + i) % cap;
+
+            let entry = match self.conns.get_mut(idx) {
+                Some(v) => v,
+
+                None => continue,
+            };
+
+            match entry.conn.send(out) {
+                Ok(v) => {
+                    self.send_cursor = idx + 1;
+
+                    return Ok(v);
+                },
+
+                Err(Error::Done) => (),
+
+                Err(e) => {
+                    trace!("{} send failed: {:?}", entry.conn.trace_id(), e);
+
+                    entry.conn.close(false, 0x1, b"fail").ok();
+                },
+            }
+        }
+
+        Err(Error::Done)
+    }
+
+    /// Returns the earliest instant at which [`on_timeout()`] should be
+    /// called.
+    ///
+    /// A `None` value means that no timer needs to be armed.
+    ///
+    /// [`on_timeout()`]: struct.Endpoint.html#method.on_timeout
+    pub fn timeout(&self) -> Option<Instant> {
+        self.conns
+            .iter()
+            .filter_map(|(_, e)| e.conn.timeout_instant())
+            .min()
+    }
+
+    /// Processes the timeout events of all the connections whose timer has
+    /// expired.
+    pub fn on_timeout(&mut self) {
+        let now = Instant::now();
+
+        for (_, e) in self.conns.iter_mut() {
+            if e.conn.timeout_instant().is_some_and(|t| t <= now) {
+                e.conn.on_timeout();
+            }
+        }
+    }
+
+    /// Returns a reference to the connection with the given handle.
+    pub fn conn(&self, handle: ConnectionHandle) -> Option<&Connection<F>> {
+        self.conns.get(handle.0).map(|e| &e.conn)
+    }
+
+    /// Returns a mutable reference to the connection with the given handle.
+    pub fn conn_mut(
+        &mut self, handle: ConnectionHandle,
+    ) -> Option<&mut Connection<F>> {
+        self.conns.get_mut(handle.0).map(|e| &mut e.conn)
+    }
+
+    /// Returns an iterator over the handles of all the connections owned by
+    /// the endpoint.
+    pub fn handles(&self) -> impl Iterator<Item = ConnectionHandle> + '_ {
+        self.conns.iter().map(|(idx, _)| ConnectionHandle(idx))
+    }
+
+    /// Returns the number of connections owned by the endpoint.
+    pub fn len(&self) -> usize {
+        self.conns.len()
+    }
+
+    /// Returns true if the endpoint doesn't own any connection.
+    pub fn is_empty(&self) -> bool {
+        self.conns.is_empty()
+    }
+
+    /// Removes a closed connection from the endpoint and returns it.
+    ///
+    /// The application should call this method until it returns `None`, to
+    /// release the state associated with closed connections. Packets sent to
+    /// the connection IDs of a removed connection are answered with a
+    /// Stateless Reset.
+    pub fn closed_next(&mut self) -> Option<(ConnectionHandle, Connection<F>)> {
+        let idx = self
+            .conns
+            .iter()
+            .find(|(_, e)| e.conn.is_closed())
+            .map(|(idx, _)| idx)?;
+
+        let entry = self.conns.remove(idx);
+
+        for cid in &entry.cids {
+            if self.routes.get(cid) == Some(&idx) {
+                self.routes.remove(cid);
+            }
+        }
+
+        Some((ConnectionHandle(idx), entry.conn))
+    }
+
+    /// Removes routes for connection IDs retired by the peer, and issues new
+    /// connection IDs up to the limit advertised by the peer.
+    fn update_routes(&mut self, idx: usize) -> Result<()> {
+        let entry = &mut self.conns[idx];
+
+        while let Some(cid) = entry.conn.retired_scid_next() {
+            if self.routes.get(&cid) == Some(&idx) {
+                self.routes.remove(&cid);
+            }
+
+            entry.cids.retain(|c| *c != cid);
+        }
+
+        if !entry.conn.is_established() || entry.conn.is_closed() {
+            return Ok(());
+        }
+
+        while entry.conn.scids_left() > 0 {
+            let cid = new_cid();
+
+            let reset_token = packet::stateless_reset_token(&self.key, &cid)?;
+
+            entry.conn.new_scid(&cid, reset_token, false)?;
+
+            self.routes.insert(cid.clone(), idx);
+            entry.cids.push(cid);
+        }
+
+        Ok(())
+    }
+
+    fn queue_stateless(&mut self, pkt: Vec<u8>, info: &RecvInfo) {
+        if self.stateless.len() >= MAX_PENDING_STATELESS {
+            trace!("dropping stateless packet, queue is full");
+            return;
+        }
+
+        let send_info = SendInfo {
+            from: info.to,
+            to: info.from,
+            at: Instant::now(),
+            ecn: Ecn::NotEct,
+        };
+
+        self.stateless.push_back((pkt, send_info));
+    }
+
+    fn queue_stateless_reset(
+        &mut self, dcid: &ConnectionId, trigger_len: usize, info: &RecvInfo,
+    ) -> Result<()> {
+        let reset_token = packet::stateless_reset_token(&self.key, dcid)?;
+
+        let mut out = vec![0; trigger_len];
+
+        let len =
+            match packet::stateless_reset(reset_token, trigger_len, &mut out) {
+                Ok(v) => v,
+
+                Err(Error::Done) => return Ok(()),
+
+                Err(e) => return Err(e),
+            };
+
+        out.truncate(len);
+
+        self.queue_stateless(out, info);
+
+        Ok(())
+    }
+
+    fn mint_retry_token(
+        &self, odcid: &ConnectionId, new_scid: &ConnectionId, peer: &SocketAddr,
+    ) -> Result<Vec<u8>> {
+        let expiry = unix_time_secs() + RETRY_TOKEN_LIFETIME.as_secs();
+
+        let mut token =
+            Vec::with_capacity(8 + 1 + odcid.len() + RETRY_TOKEN_TAG_LEN);
+
+        token.extend_from_slice(&expiry.to_be_bytes());
+        token.push(odcid.len() as u8);
+        token.extend_from_slice(odcid);
+
+        let tag = crypto::hmac_sha256(
+            &self.key,
+            &[&b"retry"[..], &ip_octets(peer), &token, new_scid.as_ref()]
+                .concat(),
+        )?;
+
+        token.extend_from_slice(&tag[..RETRY_TOKEN_TAG_LEN]);
+
+        Ok(token)
+    }
+
+    fn validate_retry_token(
+        &self, token: &[u8], dcid: &ConnectionId, peer: &SocketAddr,
+    ) -> Option<ConnectionId<'static>> {
+        if token.len() < RETRY_TOKEN_TAG_LEN {
+            return None;
+        }
+
+        let (body, tag) = token.split_at(token.len() - RETRY_TOKEN_TAG_LEN);
+
+        let expected = crypto::hmac_sha256(
+            &self.key,
+            &[&b"retry"[..], &ip_octets(peer), body, dcid.as_ref()].concat(),
+        )
+        .ok()?;
+
+        crypto::verify_slices_are_equal(&expected[..RETRY_TOKEN_TAG_LEN], tag)
+            .ok()?;
+
+        let mut b = octets::Octets::with_slice(body);
+
+        let expiry = b.get_u64().ok()?;
+
+        if unix_time_secs() > expiry {
+            return None;
+        }
+
+        let odcid = b.get_bytes_with_u8_length().ok()?;
+
+        if b.cap() > 0 || odcid.len() > crate::MAX_CONN_ID_LEN {
+            return None;
+        }
+
+        Some(ConnectionId::from_vec(odcid.to_vec()))
+    }
+}
+
+/// Generates a random connection ID.
+fn new_cid() -> ConnectionId<'static> {
+    let mut cid = vec![0; CID_LEN];
+    rand::rand_bytes(&mut cid);
+
+    ConnectionId::from_vec(cid)
+}
+
+pub(crate) fn ip_octets(addr: &SocketAddr) -> Vec<u8> {
+    match addr.ip() {
+        IpAddr::V4(ip) => ip.octets().to_vec(),
+
+        IpAddr::V6(ip) => ip.octets().to_vec(),
+    }
+}
+
+pub(crate) fn unix_time_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use crate::test_utils::Pipe;
+    use crate::HmacTokenValidator;
+    use crate::TokenCache;
+
+    fn config(version: u32) -> Config {
+        let mut config = Config::new(version).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config
+            .set_application_protos(&[b"proto1", b"proto2"])
+            .unwrap();
+        config.set_initial_max_data(30);
+        config.set_initial_max_stream_data_bidi_local(15);
+        config.set_initial_max_stream_data_bidi_remote(15);
+        config.set_initial_max_streams_bidi(3);
+        config.set_max_idle_timeout(180_000);
+        config.verify_peer(false);
+        config
+    }
+
+    fn new_client(version: u32) -> Connection {
+        let mut scid = [0; 16];
+        rand::rand_bytes(&mut scid);
+
+        crate::connect(
+            Some("quic.tech"),
+            &ConnectionId::from_ref(&scid),
+            Pipe::client_addr(),
+            Pipe::server_addr(),
+            &mut config(version),
+        )
+        .unwrap()
+    }
+
+    fn client_info() -> RecvInfo {
+        RecvInfo {
+            from: Pipe::client_addr(),
+            to: Pipe::server_addr(),
+            ecn: Ecn::NotEct,
+        }
+    }
+
+    fn server_info() -> RecvInfo {
+        RecvInfo {
+            from: Pipe::server_addr(),
+            to: Pipe::client_addr(),
+            ecn: Ecn::NotEct,
+        }
+    }
+
+    /// Exchanges packets between the client and the endpoint until neither
+    /// has anything left to send.
+    fn advance(client: &mut Connection, endpoint: &mut Endpoint) {
+        let mut buf = [0; 65535];
+
+        loop {
+            let mut done = true;
+
+            while let Ok((len, _)) = client.send(&mut buf) {
+                endpoint.recv(&mut buf[..len], client_info()).ok();
+                done = false;
+            }
+
+            while let Ok((len, _)) = endpoint.send(&mut buf) {
+                client.recv(&mut buf[..len], server_info()).ok();
+                done = false;
+            }
+
+            if done {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn handshake() {
+        let mut endpoint = Endpoint::new(config(crate::PROTOCOL_VERSION));
+        let mut client = new_client(crate::PROTOCOL_VERSION);
+
+        advance(&mut client, &mut endpoint);
+
+        assert!(client.is_established());
+        assert_eq!(endpoint.len(), 1);
+
+        let handle = endpoint.handles().next().unwrap();
+        assert!(endpoint.conn(handle).unwrap().is_established());
+
+        // New connection IDs were issued to the client, and are routed to
+        // the connection.
+        assert!(client.available_dcids() > 0);
+
+        let stream = client.stream_send(0, b"hello", true);
+        assert_eq!(stream, Ok(5));
+
+        let mut buf = [0; 65535];
+        let (len, _) = client.send(&mut buf).unwrap();
+        assert_eq!(endpoint.recv(&mut buf[..len], client_info()), Ok(handle));
+
+        let conn = endpoint.conn_mut(handle).unwrap();
+        let mut b = [0; 15];
+        assert_eq!(conn.stream_recv(0, &mut b), Ok((5, true)));
+        assert_eq!(&b[..5], b"hello");
+    }
+
+    #[test]
+    fn version_negotiation() {
+        let mut endpoint = Endpoint::new(config(crate::PROTOCOL_VERSION));
+        let mut client = new_client(0xbabababa);
+
+        let mut buf = [0; 65535];
+        let (len, _) = client.send(&mut buf).unwrap();
+        assert_eq!(
+            endpoint.recv(&mut buf[..len], client_info()),
+            Err(Error::Done)
+        );
+        assert!(endpoint.is_empty());
+
+        let (len, info) = endpoint.send(&mut buf).unwrap();
+        assert_eq!(info.to, Pipe::client_addr());
+
+        let hdr = Header::from_slice(&mut buf[..len], 0).unwrap();
+        assert_eq!(hdr.ty, Type::VersionNegotiation);
+
+        assert_eq!(endpoint.send(&mut buf), Err(Error::Done));
+
+        // Datagrams too small to carry an Initial are not answered.
+        let mut client = new_client(0xbabababa);
+        let (len, _) = client.send(&mut buf).unwrap();
+        assert_eq!(
+            endpoint.recv(&mut buf[..len / 2], client_info()),
+            Err(Error::Done)
+        );
+        assert_eq!(endpoint.send(&mut buf), Err(Error::Done));
+    }
+
+    #[test]
+    fn retry() {
+        let mut endpoint = Endpoint::new(config(crate::PROTOCOL_VERSION));
+        endpoint.enable_retry(true);
+
+        let mut client = new_client(crate::PROTOCOL_VERSION);
+
+        let mut buf = [0; 65535];
+        let (len, _) = client.send(&mut buf).unwrap();
+        assert_eq!(
+            endpoint.recv(&mut buf[..len], client_info()),
+            Err(Error::Done)
+        );
+        assert!(endpoint.is_empty());
+
+        let (len, _) = endpoint.send(&mut buf).unwrap();
+        let hdr = Header::from_slice(&mut buf[..len], 0).unwrap();
+        assert_eq!(hdr.ty, Type::Retry);
+
+        assert_eq!(client.recv(&mut buf[..len], server_info()), Ok(len));
+
+        advance(&mut client, &mut endpoint);
+
+        assert!(client.is_established());
+        assert_eq!(endpoint.len(), 1);
+    }
+
+    #[test]
+    fn retry_invalid_token() {
+        let mut endpoint = Endpoint::new(config(crate::PROTOCOL_VERSION));
+        endpoint.enable_retry(true);
+
+        let mut client = new_client(crate::PROTOCOL_VERSION);
+
+        let mut buf = [0; 65535];
+        let (len, _) = client.send(&mut buf).unwrap();
+        endpoint.recv(&mut buf[..len], client_info()).ok();
+
+        let (len, _) = endpoint.send(&mut buf).unwrap();
+
+        // A different endpoint can't validate the token.
+        let mut endpoint = Endpoint::new(config(crate::PROTOCOL_VERSION));
+        endpoint.enable_retry(true);
+
+        assert_eq!(client.recv(&mut buf[..len], server_info()), Ok(len));
+
+        let (len, _) = client.send(&mut buf).unwrap();
+        assert_eq!(
+            endpoint.recv(&mut buf[..len], client_info()),
+            Err(Error::Done)
+        );
+        assert!(endpoint.is_empty());
+        assert_eq!(endpoint.send(&mut buf), Err(Error::Done));
+
+        // Neither can one that received the token from a different address.
+        let mut info = client_info();
+        info.from = "127.0.0.2:1234".parse().unwrap();
+
+        let mut endpoint = Endpoint::new(config(crate::PROTOCOL_VERSION));
+        endpoint.enable_retry(true);
+
+        let mut client = new_client(crate::PROTOCOL_VERSION);
+
+        let (len, _) = client.send(&mut buf).unwrap();
+        endpoint.recv(&mut buf[..len], info).ok();
+
+        let (len, _) = endpoint.send(&mut buf).unwrap();
+        assert_eq!(client.recv(&mut buf[..len], server_info()), Ok(len));
+
+        let (len, _) = client.send(&mut buf).unwrap();
+        assert_eq!(
+            endpoint.recv(&mut buf[..len], client_info()),
+            Err(Error::Done)
+        );
+        assert!(endpoint.is_empty());
+    }
+
+    #[test]
+    fn retry_new_token() {
+        let mut server_config = config(crate::PROTOCOL_VERSION);
+        server_config
+            .set_token_validator(Arc::new(HmacTokenValidator::new(&[0xa; 32])));
+
+        let mut endpoint = Endpoint::new(server_config);
+        endpoint.enable_retry(true);
+
+        let cache = Arc::new(TokenCache::new(8));
+
+        let new_client = || {
+            let mut config = config(crate::PROTOCOL_VERSION);
+            config.set_token_cache(Arc::clone(&cache));
+
+            let mut scid = [0; 16];
+            rand::rand_bytes(&mut scid);
+
+            crate::connect(
+                Some("quic.tech"),
+                &ConnectionId::from_ref(&scid),
+                Pipe::client_addr(),
+                Pipe::server_addr(),
+                &mut config,
+            )
+            .unwrap()
+        };
+
+        // The first connection goes through a Retry, and gets a token once
+        // the handshake is confirmed.
+        let mut client = new_client();
+        advance(&mut client, &mut endpoint);
+
+        assert!(client.is_established());
+        assert_eq!(cache.len(), 1);
+
+        // The next connection is accepted right away.
+        let mut client = new_client();
+        assert!(cache.is_empty());
+
+        let mut buf = [0; 65535];
+        let (len, _) = client.send(&mut buf).unwrap();
+        let handle = endpoint.recv(&mut buf[..len], client_info()).unwrap();
+        assert_eq!(endpoint.len(), 2);
+
+        let (len, _) = endpoint.send(&mut buf).unwrap();
+        let hdr = Header::from_slice(&mut buf[..len], 0).unwrap();
+        assert_eq!(hdr.ty, Type::Initial);
+
+        assert_eq!(client.recv(&mut buf[..len], server_info()), Ok(len));
+
+        advance(&mut client, &mut endpoint);
+
+        assert!(client.is_established());
+        assert!(endpoint.conn(handle).unwrap().is_established());
+
+        // A token that can't be validated results in a Retry.
+        let mut endpoint = Endpoint::new(config(crate::PROTOCOL_VERSION));
+        endpoint.enable_retry(true);
+
+        let mut client = new_client();
+        assert!(cache.is_empty());
+
+        let (len, _) = client.send(&mut buf).unwrap();
+        assert_eq!(
+            endpoint.recv(&mut buf[..len], client_info()),
+            Err(Error::Done)
+        );
+
+        let (len, _) = endpoint.send(&mut buf).unwrap();
+        let hdr = Header::from_slice(&mut buf[..len], 0).unwrap();
+        assert_eq!(hdr.ty, Type::Retry);
+    }
+
+    #[test]
+    fn stateless_reset() {
+        let mut key = [0; KEY_LEN];
+        rand::rand_bytes(&mut key);
+
+        let mut endpoint = Endpoint::new(config(crate::PROTOCOL_VERSION));
+        endpoint.set_key(&key);
+
+        let mut client = new_client(crate::PROTOCOL_VERSION);
+
+        advance(&mut client, &mut endpoint);
+        assert!(client.is_established());
+
+        // The endpoint restarts and loses all connection state.
+        let mut endpoint = Endpoint::new(config(crate::PROTOCOL_VERSION));
+        endpoint.set_key(&key);
+
+        assert_eq!(client.stream_send(0, b"hello", true), Ok(5));
+
+        let mut buf = [0; 65535];
+        let (len, _) = client.send(&mut buf).unwrap();
+        assert_eq!(
+            endpoint.recv(&mut buf[..len], client_info()),
+            Err(Error::Done)
+        );
+
+        let (reset_len, info) = endpoint.send(&mut buf).unwrap();
+        assert!(reset_len < len);
+        assert_eq!(info.to, Pipe::client_addr());
+
+        let token = crate::stateless_reset_token(&key, &client.destination_id());
+        assert_eq!(
+            &buf[reset_len - 16..reset_len],
+            &token.unwrap().to_be_bytes()
+        );
+
+        assert_eq!(
+            client.recv(&mut buf[..reset_len], server_info()),
+            Ok(reset_len)
+        );
+        assert!(client.is_closed());
+
+        // Packets too small to be answered with a smaller reset are dropped.
+        let mut pkt = [0x40; packet::MIN_STATELESS_RESET_LEN];
+        assert_eq!(endpoint.recv(&mut pkt, client_info()), Err(Error::Done));
+        assert_eq!(endpoint.send(&mut buf), Err(Error::Done));
+    }
+
+    #[test]
+    fn closed_connection() {
+        let mut endpoint = Endpoint::new(config(crate::PROTOCOL_VERSION));
+        let mut client = new_client(crate::PROTOCOL_VERSION);
+
+        advance(&mut client, &mut endpoint);
+
+        let handle = endpoint.handles().next().unwrap();
+        assert!(endpoint.closed_next().is_none());
+
+        assert_eq!(client.close(false, 0x0, b""), Ok(()));
+        advance(&mut client, &mut endpoint);
+
+        // The connection is draining until its timer expires.
+        assert!(endpoint.conn(handle).unwrap().is_draining());
+        assert!(endpoint.timeout().is_some());
+
+        let timer = endpoint.timeout().unwrap();
+        std::thread::sleep(
+            timer.saturating_duration_since(Instant::now()) +
+                Duration::from_millis(1),
+        );
+        endpoint.on_timeout();
+
+        let (closed, conn) = endpoint.closed_next().unwrap();
+        assert_eq!(closed, handle);
+        assert!(conn.is_closed());
+
+        assert!(endpoint.is_empty());
+        assert!(endpoint.routes.is_empty());
+        assert_eq!(endpoint.timeout(), None);
+    }
+}
@@ -215,6 +215,11 @@ pub extern "C" fn quiche_config_discover_pmtu(config: &mut Config, v: bool) {
     config.discover_pmtu(v);
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_config_enable_ecn(config: &mut Config, v: bool) {
+    config.enable_ecn(v);
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_config_log_keys(config: &mut Config) {
     config.log_keys();
@@ -225,6 +230,51 @@ pub extern "C" fn quiche_config_enable_early_data(config: &mut Config) {
     config.enable_early_data();
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_config_enable_anti_replay(
+    config: &mut Config, capacity: size_t,
+) {
+    config.set_anti_replay(Arc::new(StrikeRegister::new(capacity)));
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_config_enable_new_token(
+    config: &mut Config, key: *const u8, key_len: size_t,
+) -> c_int {
+    let key = unsafe { slice::from_raw_parts(key, key_len) };
+
+    let key = match key.try_into() {
+        Ok(v) => v,
+
+        Err(_) => return Error::InvalidState.to_c() as c_int,
+    };
+
+    config.set_token_validator(Arc::new(HmacTokenValidator::new(key)));
+
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_config_enable_token_cache(
+    config: &mut Config, capacity: size_t,
+) {
+    config.set_token_cache(Arc::new(TokenCache::new(capacity)));
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_config_set_ech_config_list(
+    config: &mut Config, list: *const u8, list_len: size_t,
+) {
+    let list = unsafe { slice::from_raw_parts(list, list_len) };
+
+    config.set_ech_config_list(list);
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_config_set_ech_grease(config: &mut Config, v: bool) {
+    config.set_ech_grease(v);
+}
+
 #[no_mangle]
 /// Corresponds to the `Config::set_application_protos_wire_format` Rust
 /// function.
@@ -419,6 +469,54 @@ pub extern "C" fn quiche_config_set_disable_dcid_reuse(
     config.set_disable_dcid_reuse(v);
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_config_set_preferred_address(
+    config: &mut Config, v4: *const sockaddr, v4_len: socklen_t,
+    v6: *const sockaddr, v6_len: socklen_t, cid: *const u8, cid_len: size_t,
+    reset_token: *const u8,
+) -> c_int {
+    let v4 = match unsafe { v4.as_ref() } {
+        Some(addr) => match std_addr_from_c(addr, v4_len) {
+            SocketAddr::V4(addr) => Some(addr),
+
+            SocketAddr::V6(_) => return Error::InvalidState.to_c() as c_int,
+        },
+
+        None => None,
+    };
+
+    let v6 = match unsafe { v6.as_ref() } {
+        Some(addr) => match std_addr_from_c(addr, v6_len) {
+            SocketAddr::V6(addr) => Some(addr),
+
+            SocketAddr::V4(_) => return Error::InvalidState.to_c() as c_int,
+        },
+
+        None => None,
+    };
+
+    let cid = unsafe { slice::from_raw_parts(cid, cid_len) };
+    let cid = ConnectionId::from_ref(cid);
+
+    let reset_token = unsafe { slice::from_raw_parts(reset_token, 16) };
+    let reset_token = match reset_token.try_into() {
+        Ok(rt) => rt,
+        Err(_) => unreachable!(),
+    };
+    let reset_token = u128::from_be_bytes(reset_token);
+
+    config.set_preferred_address(v4, v6, &cid, reset_token);
+
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_config_set_disable_preferred_address_migration(
+    config: &mut Config, v: bool,
+) {
+    config.set_disable_preferred_address_migration(v);
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_config_set_ticket_key(
     config: &mut Config, key: *const u8, key_len: size_t,
@@ -574,6 +672,54 @@ pub extern "C" fn quiche_negotiate_version(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_stateless_reset(
+    dcid: *const u8, dcid_len: size_t, reset_token: *const u8, len_hint: size_t,
+    out: *mut u8, out_len: size_t,
+) -> ssize_t {
+    let dcid = unsafe { slice::from_raw_parts(dcid, dcid_len) };
+    let dcid = ConnectionId::from_ref(dcid);
+
+    let reset_token = unsafe { slice::from_raw_parts(reset_token, 16) };
+    let reset_token = match reset_token.try_into() {
+        Ok(rt) => rt,
+        Err(_) => unreachable!(),
+    };
+    let reset_token = u128::from_be_bytes(reset_token);
+
+    let out = unsafe { slice::from_raw_parts_mut(out, out_len) };
+
+    let secret = StatelessResetSecret::Token(reset_token);
+
+    match stateless_reset(&dcid, secret, len_hint, out) {
+        Ok(v) => v as ssize_t,
+
+        Err(e) => e.to_c(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_stateless_reset_token(
+    key: *const u8, key_len: size_t, cid: *const u8, cid_len: size_t,
+    out: *mut u8,
+) -> c_int {
+    let key = unsafe { slice::from_raw_parts(key, key_len) };
+
+    let cid = unsafe { slice::from_raw_parts(cid, cid_len) };
+    let cid = ConnectionId::from_ref(cid);
+
+    let out = unsafe { slice::from_raw_parts_mut(out, 16) };
+
+    match stateless_reset_token(key, &cid) {
+        Ok(v) => {
+            out.copy_from_slice(&v.to_be_bytes());
+            0
+        },
+
+        Err(e) => e.to_c() as c_int,
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_version_is_supported(version: u32) -> bool {
     version_is_supported(version)
@@ -735,6 +881,19 @@ pub extern "C" fn quiche_conn_set_session(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_conn_set_ech_config_list(
+    conn: &mut Connection, list: *const u8, list_len: size_t,
+) -> c_int {
+    let list = unsafe { slice::from_raw_parts(list, list_len) };
+
+    match conn.set_ech_config_list(list) {
+        Ok(_) => 0,
+
+        Err(e) => e.to_c() as c_int,
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_conn_set_max_idle_timeout(
     conn: &mut Connection, v: u64,
@@ -752,6 +911,8 @@ pub struct RecvInfo<'a> {
     from_len: socklen_t,
     to: &'a sockaddr,
     to_len: socklen_t,
+
+    ecn: u8,
 }
 
 impl From<&RecvInfo<'_>> for crate::RecvInfo {
@@ -759,6 +920,7 @@ impl From<&RecvInfo<'_>> for crate::RecvInfo {
         crate::RecvInfo {
             from: std_addr_from_c(info.from, info.from_len),
             to: std_addr_from_c(info.to, info.to_len),
+            ecn: info.ecn.into(),
         }
     }
 }
@@ -788,6 +950,8 @@ pub struct SendInfo {
     to_len: socklen_t,
 
     at: timespec,
+
+    ecn: u8,
 }
 
 #[no_mangle]
@@ -807,6 +971,8 @@ pub extern "C" fn quiche_conn_send(
 
             std_time_to_c(&info.at, &mut out_info.at);
 
+            out_info.ecn = info.ecn.into();
+
             v as ssize_t
         },
 
@@ -835,6 +1001,8 @@ pub extern "C" fn quiche_conn_send_on_path(
 
             std_time_to_c(&info.at, &mut out_info.at);
 
+            out_info.ecn = info.ecn.into();
+
             v as ssize_t
         },
 
@@ -1124,6 +1292,11 @@ pub extern "C" fn quiche_conn_application_proto(
     *out_len = proto.len();
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_conn_version(conn: &Connection) -> u32 {
+    conn.version()
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_conn_peer_cert(
     conn: &Connection, out: &mut *const u8, out_len: &mut size_t,
@@ -1176,6 +1349,31 @@ pub extern "C" fn quiche_conn_is_resumed(conn: &Connection) -> bool {
     conn.is_resumed()
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_conn_ech_accepted(conn: &Connection) -> bool {
+    conn.ech_accepted()
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_conn_ech_retry_configs(
+    conn: &Connection, out: &mut *const u8, out_len: &mut size_t,
+) -> bool {
+    match conn.ech_retry_configs() {
+        Some(configs) => {
+            *out = configs.as_ptr();
+            *out_len = configs.len();
+
+            true
+        },
+
+        None => {
+            *out_len = 0;
+
+            false
+        },
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_conn_is_in_early_data(conn: &Connection) -> bool {
     conn.is_in_early_data()
@@ -1267,6 +1465,8 @@ pub struct Stats {
     stopped_stream_count_local: u64,
     reset_stream_count_remote: u64,
     stopped_stream_count_remote: u64,
+    key_update_count_local: u64,
+    key_update_count_remote: u64,
 }
 
 pub struct TransportParams {
@@ -1303,6 +1503,8 @@ pub extern "C" fn quiche_conn_stats(conn: &Connection, out: &mut Stats) {
     out.stopped_stream_count_local = stats.stopped_stream_count_local;
     out.reset_stream_count_remote = stats.reset_stream_count_remote;
     out.stopped_stream_count_remote = stats.stopped_stream_count_remote;
+    out.key_update_count_local = stats.key_update_count_local;
+    out.key_update_count_remote = stats.key_update_count_remote;
 }
 
 #[no_mangle]
@@ -1513,6 +1715,16 @@ pub extern "C" fn quiche_conn_send_ack_eliciting(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_conn_initiate_key_update(
+    conn: &mut Connection,
+) -> c_int {
+    match conn.initiate_key_update() {
+        Ok(()) => 0,
+        Err(e) => e.to_c() as c_int,
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_conn_send_ack_eliciting_on_path(
     conn: &mut Connection, local: &sockaddr, local_len: socklen_t,
@@ -1737,6 +1949,8 @@ pub extern "C" fn quiche_path_event_type(ev: &PathEvent) -> u32 {
         PathEvent::ReusedSourceConnectionId { .. } => 4,
 
         PathEvent::PeerMigrated { .. } => 5,
+
+        PathEvent::MigratedToPreferredAddress { .. } => 6,
     }
 }
 
@@ -1842,6 +2056,22 @@ pub extern "C" fn quiche_path_event_peer_migrated(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_path_event_migrated_to_preferred_address(
+    ev: &PathEvent, local_addr: &mut sockaddr_storage,
+    local_addr_len: &mut socklen_t, peer_addr: &mut sockaddr_storage,
+    peer_addr_len: &mut socklen_t,
+) {
+    match ev {
+        PathEvent::MigratedToPreferredAddress(local, peer) => {
+            *local_addr_len = std_addr_to_c(local, local_addr);
+            *peer_addr_len = std_addr_to_c(peer, peer_addr);
+        },
+
+        _ => unreachable!(),
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_path_event_free(ev: *mut PathEvent) {
     drop(unsafe { Box::from_raw(ev) });
@@ -47,11 +47,27 @@ pub const MAX_DGRAM_OVERHEAD: usize = 2;
 pub const MAX_STREAM_OVERHEAD: usize = 12;
 pub const MAX_STREAM_SIZE: u64 = 1 << 62;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+// ACK Frequency extension frame types.
+const ACK_FREQUENCY: u64 = 0xaf;
+const IMMEDIATE_ACK: u64 = 0x1f;
+
+// Multipath extension frame types.
+const PATH_ACK: u64 = 0x15228c00;
+const PATH_ACK_ECN: u64 = 0x15228c01;
+const PATH_ABANDON: u64 = 0x15228c05;
+const PATH_STATUS_BACKUP: u64 = 0x15228c07;
+const PATH_STATUS_AVAILABLE: u64 = 0x15228c08;
+const PATH_NEW_CONNECTION_ID: u64 = 0x15228c09;
+const PATH_RETIRE_CONNECTION_ID: u64 = 0x15228c0a;
+const MAX_PATH_ID: u64 = 0x15228c0c;
+const PATHS_BLOCKED: u64 = 0x15228c0d;
+const PATH_CIDS_BLOCKED: u64 = 0x15228c0e;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct EcnCounts {
-    ect0_count: u64,
-    ect1_count: u64,
-    ecn_ce_count: u64,
+    pub ect0_count: u64,
+    pub ect1_count: u64,
+    pub ecn_ce_count: u64,
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -184,6 +200,63 @@ pub enum Frame {
     DatagramHeader {
         length: usize,
     },
+
+    AckFrequency {
+        seq_num: u64,
+        ack_eliciting_threshold: u64,
+        request_max_ack_delay: u64,
+        reordering_threshold: u64,
+    },
+
+    ImmediateAck,
+
+    PathAck {
+        path_id: u64,
+        ack_delay: u64,
+        ranges: ranges::RangeSet,
+        ecn_counts: Option<EcnCounts>,
+    },
+
+    PathAbandon {
+        path_id: u64,
+        error_code: u64,
+    },
+
+    PathStatusBackup {
+        path_id: u64,
+        seq_num: u64,
+    },
+
+    PathStatusAvailable {
+        path_id: u64,
+        seq_num: u64,
+    },
+
+    PathNewConnectionId {
+        path_id: u64,
+        seq_num: u64,
+        retire_prior_to: u64,
+        conn_id: Vec<u8>,
+        reset_token: [u8; 16],
+    },
+
+    PathRetireConnectionId {
+        path_id: u64,
+        seq_num: u64,
+    },
+
+    MaxPathId {
+        max: u64,
+    },
+
+    PathsBlocked {
+        max: u64,
+    },
+
+    PathCidsBlocked {
+        path_id: u64,
+        next_seq_num: u64,
+    },
 }
 
 impl Frame {
@@ -331,6 +404,73 @@ impl Frame {
 
             0x30 | 0x31 => parse_datagram_frame(frame_type, b)?,
 
+            ACK_FREQUENCY => Frame::AckFrequency {
+                seq_num: b.get_varint()?,
+                ack_eliciting_threshold: b.get_varint()?,
+                request_max_ack_delay: b.get_varint()?,
+                reordering_threshold: b.get_varint()?,
+            },
+
+            IMMEDIATE_ACK => Frame::ImmediateAck,
+
+            PATH_ACK | PATH_ACK_ECN => parse_path_ack_frame(frame_type, b)?,
+
+            PATH_ABANDON => Frame::PathAbandon {
+                path_id: b.get_varint()?,
+                error_code: b.get_varint()?,
+            },
+
+            PATH_STATUS_BACKUP => Frame::PathStatusBackup {
+                path_id: b.get_varint()?,
+                seq_num: b.get_varint()?,
+            },
+
+            PATH_STATUS_AVAILABLE => Frame::PathStatusAvailable {
+                path_id: b.get_varint()?,
+                seq_num: b.get_varint()?,
+            },
+
+            PATH_NEW_CONNECTION_ID => {
+                let path_id = b.get_varint()?;
+                let seq_num = b.get_varint()?;
+                let retire_prior_to = b.get_varint()?;
+                let conn_id_len = b.get_u8()?;
+
+                if !(1..=packet::MAX_CID_LEN).contains(&conn_id_len) {
+                    return Err(Error::InvalidFrame);
+                }
+
+                Frame::PathNewConnectionId {
+                    path_id,
+                    seq_num,
+                    retire_prior_to,
+                    conn_id: b.get_bytes(conn_id_len as usize)?.to_vec(),
+                    reset_token: b
+                        .get_bytes(16)?
+                        .buf()
+                        .try_into()
+                        .map_err(|_| Error::BufferTooShort)?,
+                }
+            },
+
+            PATH_RETIRE_CONNECTION_ID => Frame::PathRetireConnectionId {
+                path_id: b.get_varint()?,
+                seq_num: b.get_varint()?,
+            },
+
+            MAX_PATH_ID => Frame::MaxPathId {
+                max: b.get_varint()?,
+            },
+
+            PATHS_BLOCKED => Frame::PathsBlocked {
+                max: b.get_varint()?,
+            },
+
+            PATH_CIDS_BLOCKED => Frame::PathCidsBlocked {
+                path_id: b.get_varint()?,
+                next_seq_num: b.get_varint()?,
+            },
+
             _ => return Err(Error::InvalidFrame),
         };
 
@@ -348,6 +488,9 @@ impl Frame {
             (packet::Type::ZeroRTT, Frame::RetireConnectionId { .. }) => false,
             (packet::Type::ZeroRTT, Frame::ConnectionClose { .. }) => false,
 
+            // Multipath frames can only be sent on 1-RTT packets.
+            (packet::Type::ZeroRTT, f) if f.is_multipath() => false,
+
             // ACK, CRYPTO and CONNECTION_CLOSE can be sent on all other packet
             // types.
             (_, Frame::ACK { .. }) => true,
@@ -398,33 +541,7 @@ impl Frame {
                     b.put_varint(0x03)?;
                 }
 
-                let mut it = ranges.iter().rev();
-
-                let first = it.next().unwrap();
-                let ack_block = (first.end - 1) - first.start;
-
-                b.put_varint(first.end - 1)?;
-                b.put_varint(*ack_delay)?;
-                b.put_varint(it.len() as u64)?;
-                b.put_varint(ack_block)?;
-
-                let mut smallest_ack = first.start;
-
-                for block in it {
-                    let gap = smallest_ack - block.end - 1;
-                    let ack_block = (block.end - 1) - block.start;
-
-                    b.put_varint(gap)?;
-                    b.put_varint(ack_block)?;
-
-                    smallest_ack = block.start;
-                }
-
-                if let Some(ecn) = ecn_counts {
-                    b.put_varint(ecn.ect0_count)?;
-                    b.put_varint(ecn.ect1_count)?;
-                    b.put_varint(ecn.ecn_ce_count)?;
-                }
+                encode_ack_body(*ack_delay, ranges, ecn_counts.as_ref(), b)?;
             },
 
             Frame::ResetStream {
@@ -593,52 +710,129 @@ impl Frame {
             },
 
             Frame::DatagramHeader { .. } => (),
-        }
 
-        Ok(before - b.cap())
-    }
+            Frame::AckFrequency {
+                seq_num,
+                ack_eliciting_threshold,
+                request_max_ack_delay,
+                reordering_threshold,
+            } => {
+                b.put_varint(ACK_FREQUENCY)?;
 
-    pub fn wire_len(&self) -> usize {
-        match self {
-            Frame::Padding { len } => *len,
+                b.put_varint(*seq_num)?;
+                b.put_varint(*ack_eliciting_threshold)?;
+                b.put_varint(*request_max_ack_delay)?;
+                b.put_varint(*reordering_threshold)?;
+            },
 
-            Frame::Ping { .. } => 1,
+            Frame::ImmediateAck => {
+                b.put_varint(IMMEDIATE_ACK)?;
+            },
 
-            Frame::ACK {
+            Frame::PathAck {
+                path_id,
                 ack_delay,
                 ranges,
                 ecn_counts,
             } => {
-                let mut it = ranges.iter().rev();
+                if ecn_counts.is_none() {
+                    b.put_varint(PATH_ACK)?;
+                } else {
+                    b.put_varint(PATH_ACK_ECN)?;
+                }
 
-                let first = it.next().unwrap();
-                let ack_block = (first.end - 1) - first.start;
+                b.put_varint(*path_id)?;
 
-                let mut len = 1 + // frame type
-                    octets::varint_len(first.end - 1) + // largest_ack
-                    octets::varint_len(*ack_delay) + // ack_delay
-                    octets::varint_len(it.len() as u64) + // block_count
-                    octets::varint_len(ack_block); // first_block
+                encode_ack_body(*ack_delay, ranges, ecn_counts.as_ref(), b)?;
+            },
 
-                let mut smallest_ack = first.start;
+            Frame::PathAbandon {
+                path_id,
+                error_code,
+            } => {
+                b.put_varint(PATH_ABANDON)?;
 
-                for block in it {
-                    let gap = smallest_ack - block.end - 1;
-                    let ack_block = (block.end - 1) - block.start;
+                b.put_varint(*path_id)?;
+                b.put_varint(*error_code)?;
+            },
 
-                    len += octets::varint_len(gap) + // gap
-                           octets::varint_len(ack_block); // ack_block
+            Frame::PathStatusBackup { path_id, seq_num } => {
+                b.put_varint(PATH_STATUS_BACKUP)?;
 
-                    smallest_ack = block.start;
-                }
+                b.put_varint(*path_id)?;
+                b.put_varint(*seq_num)?;
+            },
 
-                if let Some(ecn) = ecn_counts {
-                    len += octets::varint_len(ecn.ect0_count) +
-                        octets::varint_len(ecn.ect1_count) +
-                        octets::varint_len(ecn.ecn_ce_count);
-                }
+            Frame::PathStatusAvailable { path_id, seq_num } => {
+                b.put_varint(PATH_STATUS_AVAILABLE)?;
+
+                b.put_varint(*path_id)?;
+                b.put_varint(*seq_num)?;
+            },
+
+            Frame::PathNewConnectionId {
+                path_id,
+                seq_num,
+                retire_prior_to,
+                conn_id,
+                reset_token,
+            } => {
+                b.put_varint(PATH_NEW_CONNECTION_ID)?;
+
+                b.put_varint(*path_id)?;
+                b.put_varint(*seq_num)?;
+                b.put_varint(*retire_prior_to)?;
+                b.put_u8(conn_id.len() as u8)?;
+                b.put_bytes(conn_id.as_ref())?;
+                b.put_bytes(reset_token.as_ref())?;
+            },
+
+            Frame::PathRetireConnectionId { path_id, seq_num } => {
+                b.put_varint(PATH_RETIRE_CONNECTION_ID)?;
+
+                b.put_varint(*path_id)?;
+                b.put_varint(*seq_num)?;
+            },
+
+            Frame::MaxPathId { max } => {
+                b.put_varint(MAX_PATH_ID)?;
+
+                b.put_varint(*max)?;
+            },
+
+            Frame::PathsBlocked { max } => {
+                b.put_varint(PATHS_BLOCKED)?;
+
+                b.put_varint(*max)?;
+            },
+
+            Frame::PathCidsBlocked {
+                path_id,
+                next_seq_num,
+            } => {
+                b.put_varint(PATH_CIDS_BLOCKED)?;
 
-                len
+                b.put_varint(*path_id)?;
+                b.put_varint(*next_seq_num)?;
+            },
+        }
+
+        Ok(before - b.cap())
+    }
+
+    pub fn wire_len(&self) -> usize {
+        match self {
+            Frame::Padding { len } => *len,
+
+            Frame::Ping { .. } => 1,
+
+            Frame::ACK {
+                ack_delay,
+                ranges,
+                ecn_counts,
+            } => {
+                1 + // frame type
+                ack_body_wire_len(*ack_delay, ranges, ecn_counts.as_ref())
             },
 
             Frame::ResetStream {
@@ -808,6 +1002,96 @@ impl Frame {
                 2 + // length, always encode as 2-byte varint
                 *length // data
             },
+
+            Frame::AckFrequency {
+                seq_num,
+                ack_eliciting_threshold,
+                request_max_ack_delay,
+                reordering_threshold,
+            } => {
+                octets::varint_len(ACK_FREQUENCY) + // frame type
+                octets::varint_len(*seq_num) + // seq_num
+                octets::varint_len(*ack_eliciting_threshold) + // threshold
+                octets::varint_len(*request_max_ack_delay) + // max_ack_delay
+                octets::varint_len(*reordering_threshold) // reordering
+            },
+
+            Frame::ImmediateAck => {
+                1 // frame type
+            },
+
+            Frame::PathAck {
+                path_id,
+                ack_delay,
+                ranges,
+                ecn_counts,
+            } => {
+                octets::varint_len(PATH_ACK) + // frame type
+                octets::varint_len(*path_id) + // path_id
+                ack_body_wire_len(*ack_delay, ranges, ecn_counts.as_ref())
+            },
+
+            Frame::PathAbandon {
+                path_id,
+                error_code,
+            } => {
+                octets::varint_len(PATH_ABANDON) + // frame type
+                octets::varint_len(*path_id) + // path_id
+                octets::varint_len(*error_code) // error_code
+            },
+
+            Frame::PathStatusBackup { path_id, seq_num } => {
+                octets::varint_len(PATH_STATUS_BACKUP) + // frame type
+                octets::varint_len(*path_id) + // path_id
+                octets::varint_len(*seq_num) // seq_num
+            },
+
+            Frame::PathStatusAvailable { path_id, seq_num } => {
+                octets::varint_len(PATH_STATUS_AVAILABLE) + // frame type
+                octets::varint_len(*path_id) + // path_id
+                octets::varint_len(*seq_num) // seq_num
+            },
+
+            Frame::PathNewConnectionId {
+                path_id,
+                seq_num,
+                retire_prior_to,
+                conn_id,
+                reset_token,
+            } => {
+                octets::varint_len(PATH_NEW_CONNECTION_ID) + // frame type
+                octets::varint_len(*path_id) + // path_id
+                octets::varint_len(*seq_num) + // seq_num
+                octets::varint_len(*retire_prior_to) + // retire_prior_to
+                1 + // conn_id length
+                conn_id.len() + // conn_id
+                reset_token.len() // reset_token
+            },
+
+            Frame::PathRetireConnectionId { path_id, seq_num } => {
+                octets::varint_len(PATH_RETIRE_CONNECTION_ID) + // frame type
+                octets::varint_len(*path_id) + // path_id
+                octets::varint_len(*seq_num) // seq_num
+            },
+
+            Frame::MaxPathId { max } => {
+                octets::varint_len(MAX_PATH_ID) + // frame type
+                octets::varint_len(*max) // max
+            },
+
+            Frame::PathsBlocked { max } => {
+                octets::varint_len(PATHS_BLOCKED) + // frame type
+                octets::varint_len(*max) // max
+            },
+
+            Frame::PathCidsBlocked {
+                path_id,
+                next_seq_num,
+            } => {
+                octets::varint_len(PATH_CIDS_BLOCKED) + // frame type
+                octets::varint_len(*path_id) + // path_id
+                octets::varint_len(*next_seq_num) // next_seq_num
+            },
         }
     }
 
@@ -817,6 +1101,7 @@ impl Frame {
             self,
             Frame::Padding { .. } |
                 Frame::ACK { .. } |
+                Frame::PathAck { .. } |
                 Frame::ApplicationClose { .. } |
                 Frame::ConnectionClose { .. }
         )
@@ -832,6 +1117,22 @@ impl Frame {
         )
     }
 
+    /// Returns whether the frame is part of the multipath extension.
+    pub fn is_multipath(&self) -> bool {
+        matches!(
+            self,
+            Frame::PathAck { .. } |
+                Frame::PathAbandon { .. } |
+                Frame::PathStatusBackup { .. } |
+                Frame::PathStatusAvailable { .. } |
+                Frame::PathNewConnectionId { .. } |
+                Frame::PathRetireConnectionId { .. } |
+                Frame::MaxPathId { .. } |
+                Frame::PathsBlocked { .. } |
+                Frame::PathCidsBlocked { .. }
+        )
+    }
+
     #[cfg(feature = "qlog")]
     pub fn to_qlog(&self) -> QuicFrame {
         match self {
@@ -1033,6 +1334,41 @@ impl Frame {
                 length: *length as u64,
                 raw: None,
             },
+
+            // qlog doesn't define ACK frequency frames yet, so just log their
+            // type.
+            Frame::AckFrequency { .. } => unknown_qlog_frame(ACK_FREQUENCY),
+
+            Frame::ImmediateAck => unknown_qlog_frame(IMMEDIATE_ACK),
+
+            // qlog doesn't define multipath frames yet, so just log their type.
+            Frame::PathAck { ecn_counts, .. } =>
+                unknown_qlog_frame(if ecn_counts.is_some() {
+                    PATH_ACK_ECN
+                } else {
+                    PATH_ACK
+                }),
+
+            Frame::PathAbandon { .. } => unknown_qlog_frame(PATH_ABANDON),
+
+            Frame::PathStatusBackup { .. } =>
+                unknown_qlog_frame(PATH_STATUS_BACKUP),
+
+            Frame::PathStatusAvailable { .. } =>
+                unknown_qlog_frame(PATH_STATUS_AVAILABLE),
+
+            Frame::PathNewConnectionId { .. } =>
+                unknown_qlog_frame(PATH_NEW_CONNECTION_ID),
+
+            Frame::PathRetireConnectionId { .. } =>
+                unknown_qlog_frame(PATH_RETIRE_CONNECTION_ID),
+
+            Frame::MaxPathId { .. } => unknown_qlog_frame(MAX_PATH_ID),
+
+            Frame::PathsBlocked { .. } => unknown_qlog_frame(PATHS_BLOCKED),
+
+            Frame::PathCidsBlocked { .. } =>
+                unknown_qlog_frame(PATH_CIDS_BLOCKED),
         }
     }
 }
@@ -1200,23 +1536,132 @@ impl std::fmt::Debug for Frame {
             Frame::DatagramHeader { length } => {
                 write!(f, "DATAGRAM len={length}")?;
             },
-        }
 
-        Ok(())
-    }
-}
+            Frame::AckFrequency {
+                seq_num,
+                ack_eliciting_threshold,
+                request_max_ack_delay,
+                reordering_threshold,
+            } => {
+                write!(
+                    f,
+                    "ACK_FREQUENCY seq_num={seq_num} threshold={ack_eliciting_threshold} max_ack_delay={request_max_ack_delay} reordering_threshold={reordering_threshold}"
+                )?;
+            },
 
-fn parse_ack_frame(ty: u64, b: &mut octets::Octets) -> Result<Frame> {
-    let first = ty as u8;
+            Frame::ImmediateAck => {
+                write!(f, "IMMEDIATE_ACK")?;
+            },
 
-    let largest_ack = b.get_varint()?;
-    let ack_delay = b.get_varint()?;
-    let block_count = b.get_varint()?;
-    let ack_block = b.get_varint()?;
+            Frame::PathAck {
+                path_id,
+                ack_delay,
+                ranges,
+                ecn_counts,
+            } => {
+                write!(
+                    f,
+                    "PATH_ACK path_id={path_id} delay={ack_delay} blocks={ranges:?} ecn_counts={ecn_counts:?}"
+                )?;
+            },
 
-    if largest_ack < ack_block {
-        return Err(Error::InvalidFrame);
-    }
+            Frame::PathAbandon {
+                path_id,
+                error_code,
+            } => {
+                write!(f, "PATH_ABANDON path_id={path_id} err={error_code:x}")?;
+            },
+
+            Frame::PathStatusBackup { path_id, seq_num } => {
+                write!(f, "PATH_STATUS_BACKUP path_id={path_id} seq={seq_num}")?;
+            },
+
+            Frame::PathStatusAvailable { path_id, seq_num } => {
+                write!(
+                    f,
+                    "PATH_STATUS_AVAILABLE path_id={path_id} seq={seq_num}"
+                )?;
+            },
+
+            Frame::PathNewConnectionId {
+                path_id,
+                seq_num,
+                retire_prior_to,
+                conn_id,
+                reset_token,
+            } => {
+                write!(
+                    f,
+                    "PATH_NEW_CONNECTION_ID path_id={path_id} seq_num={seq_num} retire_prior_to={retire_prior_to} conn_id={conn_id:02x?} reset_token={reset_token:02x?}",
+                )?;
+            },
+
+            Frame::PathRetireConnectionId { path_id, seq_num } => {
+                write!(
+                    f,
+                    "PATH_RETIRE_CONNECTION_ID path_id={path_id} seq_num={seq_num}"
+                )?;
+            },
+
+            Frame::MaxPathId { max } => {
+                write!(f, "MAX_PATH_ID max={max}")?;
+            },
+
+            Frame::PathsBlocked { max } => {
+                write!(f, "PATHS_BLOCKED max={max}")?;
+            },
+
+            Frame::PathCidsBlocked {
+                path_id,
+                next_seq_num,
+            } => {
+                write!(
+                    f,
+                    "PATH_CIDS_BLOCKED path_id={path_id} next_seq_num={next_seq_num}"
+                )?;
+            },
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_ack_frame(ty: u64, b: &mut octets::Octets) -> Result<Frame> {
+    let (ack_delay, ranges, ecn_counts) = parse_ack_body(ty, b)?;
+
+    Ok(Frame::ACK {
+        ack_delay,
+        ranges,
+        ecn_counts,
+    })
+}
+
+fn parse_path_ack_frame(ty: u64, b: &mut octets::Octets) -> Result<Frame> {
+    let path_id = b.get_varint()?;
+
+    let (ack_delay, ranges, ecn_counts) = parse_ack_body(ty, b)?;
+
+    Ok(Frame::PathAck {
+        path_id,
+        ack_delay,
+        ranges,
+        ecn_counts,
+    })
+}
+
+fn parse_ack_body(
+    ty: u64, b: &mut octets::Octets,
+) -> Result<(u64, ranges::RangeSet, Option<EcnCounts>)> {
+    let first = ty as u8;
+
+    let largest_ack = b.get_varint()?;
+    let ack_delay = b.get_varint()?;
+    let block_count = b.get_varint()?;
+    let ack_block = b.get_varint()?;
+
+    if largest_ack < ack_block {
+        return Err(Error::InvalidFrame);
+    }
 
     let mut smallest_ack = largest_ack - ack_block;
 
@@ -1255,11 +1700,85 @@ fn parse_ack_frame(ty: u64, b: &mut octets::Octets) -> Result<Frame> {
         None
     };
 
-    Ok(Frame::ACK {
-        ack_delay,
-        ranges,
-        ecn_counts,
-    })
+    Ok((ack_delay, ranges, ecn_counts))
+}
+
+fn encode_ack_body(
+    ack_delay: u64, ranges: &ranges::RangeSet, ecn_counts: Option<&EcnCounts>,
+    b: &mut octets::OctetsMut,
+) -> Result<()> {
+    let mut it = ranges.iter().rev();
+
+    let first = it.next().unwrap();
+    let ack_block = (first.end - 1) - first.start;
+
+    b.put_varint(first.end - 1)?;
+    b.put_varint(ack_delay)?;
+    b.put_varint(it.len() as u64)?;
+    b.put_varint(ack_block)?;
+
+    let mut smallest_ack = first.start;
+
+    for block in it {
+        let gap = smallest_ack - block.end - 1;
+        let ack_block = (block.end - 1) - block.start;
+
+        b.put_varint(gap)?;
+        b.put_varint(ack_block)?;
+
+        smallest_ack = block.start;
+    }
+
+    if let Some(ecn) = ecn_counts {
+        b.put_varint(ecn.ect0_count)?;
+        b.put_varint(ecn.ect1_count)?;
+        b.put_varint(ecn.ecn_ce_count)?;
+    }
+
+    Ok(())
+}
+
+fn ack_body_wire_len(
+    ack_delay: u64, ranges: &ranges::RangeSet, ecn_counts: Option<&EcnCounts>,
+) -> usize {
+    let mut it = ranges.iter().rev();
+
+    let first = it.next().unwrap();
+    let ack_block = (first.end - 1) - first.start;
+
+    let mut len = octets::varint_len(first.end - 1) + // largest_ack
+        octets::varint_len(ack_delay) + // ack_delay
+        octets::varint_len(it.len() as u64) + // block_count
+        octets::varint_len(ack_block); // first_block
+
+    let mut smallest_ack = first.start;
+
+    for block in it {
+        let gap = smallest_ack - block.end - 1;
+        let ack_block = (block.end - 1) - block.start;
+
+        len += octets::varint_len(gap) + // gap
+               octets::varint_len(ack_block); // ack_block
+
+        smallest_ack = block.start;
+    }
+
+    if let Some(ecn) = ecn_counts {
+        len += octets::varint_len(ecn.ect0_count) +
+            octets::varint_len(ecn.ect1_count) +
+            octets::varint_len(ecn.ecn_ce_count);
+    }
+
+    len
+}
+
+#[cfg(feature = "qlog")]
+fn unknown_qlog_frame(ty: u64) -> QuicFrame {
+    QuicFrame::Unknown {
+        raw_frame_type: ty,
+        frame_type_value: None,
+        raw: None,
+    }
 }
 
 pub fn encode_crypto_header(
@@ -2119,4 +2638,380 @@ mod tests {
 
         assert_eq!(frame_data, data);
     }
+
+    #[test]
+    fn ack_frequency() {
+        let mut d = [42; 128];
+
+        let frame = Frame::AckFrequency {
+            seq_num: 1,
+            ack_eliciting_threshold: 10,
+            request_max_ack_delay: 25_000,
+            reordering_threshold: 1,
+        };
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, 9);
+        assert_eq!(frame.wire_len(), 9);
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert_eq!(Frame::from_bytes(&mut b, packet::Type::Short), Ok(frame));
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Initial).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::ZeroRTT).is_ok());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
+    }
+
+    #[test]
+    fn immediate_ack() {
+        let mut d = [42; 128];
+
+        let frame = Frame::ImmediateAck;
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, 1);
+        assert_eq!(frame.wire_len(), 1);
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert_eq!(Frame::from_bytes(&mut b, packet::Type::Short), Ok(frame));
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Initial).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::ZeroRTT).is_ok());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
+    }
+
+    #[test]
+    fn path_ack() {
+        let mut d = [42; 128];
+
+        let mut ranges = ranges::RangeSet::default();
+        ranges.insert(4..7);
+        ranges.insert(9..12);
+        ranges.insert(15..19);
+        ranges.insert(3000..5000);
+
+        let frame = Frame::PathAck {
+            path_id: 3,
+            ack_delay: 874_656_534,
+            ranges,
+            ecn_counts: None,
+        };
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, 21);
+        assert_eq!(frame.wire_len(), 21);
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert_eq!(Frame::from_bytes(&mut b, packet::Type::Short), Ok(frame));
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Initial).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::ZeroRTT).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
+    }
+
+    #[test]
+    fn path_ack_ecn() {
+        let mut d = [42; 128];
+
+        let mut ranges = ranges::RangeSet::default();
+        ranges.insert(4..7);
+        ranges.insert(9..12);
+        ranges.insert(15..19);
+        ranges.insert(3000..5000);
+
+        let frame = Frame::PathAck {
+            path_id: 3,
+            ack_delay: 874_656_534,
+            ranges,
+            ecn_counts: Some(EcnCounts {
+                ect0_count: 100,
+                ect1_count: 200,
+                ecn_ce_count: 300,
+            }),
+        };
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, 27);
+        assert_eq!(frame.wire_len(), 27);
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert_eq!(Frame::from_bytes(&mut b, packet::Type::Short), Ok(frame));
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Initial).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::ZeroRTT).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
+    }
+
+    #[test]
+    fn path_abandon() {
+        let mut d = [42; 128];
+
+        let frame = Frame::PathAbandon {
+            path_id: 1,
+            error_code: 0x42,
+        };
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, 7);
+        assert_eq!(frame.wire_len(), 7);
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert_eq!(Frame::from_bytes(&mut b, packet::Type::Short), Ok(frame));
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Initial).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::ZeroRTT).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
+    }
+
+    #[test]
+    fn path_status_backup() {
+        let mut d = [42; 128];
+
+        let frame = Frame::PathStatusBackup {
+            path_id: 1,
+            seq_num: 2,
+        };
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, 6);
+        assert_eq!(frame.wire_len(), 6);
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert_eq!(Frame::from_bytes(&mut b, packet::Type::Short), Ok(frame));
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Initial).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::ZeroRTT).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
+    }
+
+    #[test]
+    fn path_status_available() {
+        let mut d = [42; 128];
+
+        let frame = Frame::PathStatusAvailable {
+            path_id: 1,
+            seq_num: 3,
+        };
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, 6);
+        assert_eq!(frame.wire_len(), 6);
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert_eq!(Frame::from_bytes(&mut b, packet::Type::Short), Ok(frame));
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Initial).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::ZeroRTT).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
+    }
+
+    #[test]
+    fn path_new_connection_id() {
+        let mut d = [42; 128];
+
+        let frame = Frame::PathNewConnectionId {
+            path_id: 2,
+            seq_num: 123_213,
+            retire_prior_to: 122_211,
+            conn_id: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+            reset_token: [0x42; 16],
+        };
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, 45);
+        assert_eq!(frame.wire_len(), 45);
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert_eq!(Frame::from_bytes(&mut b, packet::Type::Short), Ok(frame));
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Initial).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::ZeroRTT).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
+    }
+
+    #[test]
+    fn path_retire_connection_id() {
+        let mut d = [42; 128];
+
+        let frame = Frame::PathRetireConnectionId {
+            path_id: 2,
+            seq_num: 3,
+        };
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, 6);
+        assert_eq!(frame.wire_len(), 6);
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert_eq!(Frame::from_bytes(&mut b, packet::Type::Short), Ok(frame));
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Initial).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::ZeroRTT).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
+    }
+
+    #[test]
+    fn max_path_id() {
+        let mut d = [42; 128];
+
+        let frame = Frame::MaxPathId { max: 5 };
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, 5);
+        assert_eq!(frame.wire_len(), 5);
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert_eq!(Frame::from_bytes(&mut b, packet::Type::Short), Ok(frame));
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Initial).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::ZeroRTT).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
+    }
+
+    #[test]
+    fn paths_blocked() {
+        let mut d = [42; 128];
+
+        let frame = Frame::PathsBlocked { max: 5 };
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, 5);
+        assert_eq!(frame.wire_len(), 5);
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert_eq!(Frame::from_bytes(&mut b, packet::Type::Short), Ok(frame));
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Initial).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::ZeroRTT).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
+    }
+
+    #[test]
+    fn path_cids_blocked() {
+        let mut d = [42; 128];
+
+        let frame = Frame::PathCidsBlocked {
+            path_id: 1,
+            next_seq_num: 4,
+        };
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            frame.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, 6);
+        assert_eq!(frame.wire_len(), 6);
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert_eq!(Frame::from_bytes(&mut b, packet::Type::Short), Ok(frame));
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Initial).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::ZeroRTT).is_err());
+
+        let mut b = octets::Octets::with_slice(&d);
+        assert!(Frame::from_bytes(&mut b, packet::Type::Handshake).is_err());
+    }
 }
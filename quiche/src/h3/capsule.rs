@@ -0,0 +1,662 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! HTTP Capsule Protocol.
+//!
+//! Protocols built on top of extended CONNECT, such as CONNECT-UDP,
+//! CONNECT-IP and WebTransport, exchange capsules inside the DATA frames of
+//! the request stream, as defined in [RFC 9297].
+//!
+//! Capsules are sent using the [`send_capsule()`] method. Since a capsule can
+//! be split across multiple DATA frames, received data returned by
+//! [`recv_body()`] should be fed to a [`CapsuleDecoder`], which returns
+//! capsules as they are completed.
+//!
+//! [RFC 9297]: https://www.rfc-editor.org/rfc/rfc9297.html
+//! [`send_capsule()`]: ../struct.Connection.html#method.send_capsule
+//! [`recv_body()`]: ../struct.Connection.html#method.recv_body
+//! [`CapsuleDecoder`]: struct.CapsuleDecoder.html
+
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+
+use super::Error;
+use super::Result;
+
+/// The DATAGRAM capsule type.
+pub const DATAGRAM_CAPSULE_TYPE_ID: u64 = 0x00;
+/// The ADDRESS_ASSIGN capsule type.
+pub const ADDRESS_ASSIGN_CAPSULE_TYPE_ID: u64 = 0x01;
+/// The ADDRESS_REQUEST capsule type.
+pub const ADDRESS_REQUEST_CAPSULE_TYPE_ID: u64 = 0x02;
+/// The ROUTE_ADVERTISEMENT capsule type.
+pub const ROUTE_ADVERTISEMENT_CAPSULE_TYPE_ID: u64 = 0x03;
+/// The CLOSE_WEBTRANSPORT_SESSION capsule type.
+pub const CLOSE_WEBTRANSPORT_SESSION_CAPSULE_TYPE_ID: u64 = 0x2843;
+/// The DRAIN_WEBTRANSPORT_SESSION capsule type.
+pub const DRAIN_WEBTRANSPORT_SESSION_CAPSULE_TYPE_ID: u64 = 0x78ae;
+
+/// The maximum length of the reason sent in a CLOSE_WEBTRANSPORT_SESSION
+/// capsule.
+pub const MAX_CLOSE_REASON_LEN: usize = 1024;
+
+/// The default maximum capsule payload length accepted by a
+/// [`CapsuleDecoder`].
+///
+/// This is large enough to carry any UDP payload in a DATAGRAM capsule.
+///
+/// [`CapsuleDecoder`]: struct.CapsuleDecoder.html
+pub const DEFAULT_MAX_PAYLOAD_LEN: usize = 65535;
+
+/// An IP address, or prefix, assigned or requested using the ADDRESS_ASSIGN
+/// and ADDRESS_REQUEST capsules.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AssignedAddress {
+    /// The ID of the request this address relates to, or `0` for
+    /// unsolicited assignments.
+    pub request_id: u64,
+
+    /// The IP address.
+    pub ip: IpAddr,
+
+    /// The number of leading bits of the address that form the prefix.
+    pub prefix_len: u8,
+}
+
+/// A range of IP addresses advertised using the ROUTE_ADVERTISEMENT capsule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IpAddressRange {
+    /// The first address of the range.
+    pub start: IpAddr,
+
+    /// The last address of the range.
+    pub end: IpAddr,
+
+    /// The IP protocol allowed in the range, or `0` for all protocols.
+    pub ip_protocol: u8,
+}
+
+/// An HTTP capsule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Capsule {
+    /// DATAGRAM capsule, as defined in [RFC 9297].
+    ///
+    /// [RFC 9297]: https://www.rfc-editor.org/rfc/rfc9297.html#section-3.5
+    Datagram {
+        /// The HTTP Datagram payload.
+        payload: Vec<u8>,
+    },
+
+    /// ADDRESS_ASSIGN capsule, as defined in [RFC 9484].
+    ///
+    /// [RFC 9484]: https://www.rfc-editor.org/rfc/rfc9484.html#section-4.7.1
+    AddressAssign {
+        /// The assigned addresses.
+        addresses: Vec<AssignedAddress>,
+    },
+
+    /// ADDRESS_REQUEST capsule, as defined in [RFC 9484].
+    ///
+    /// [RFC 9484]: https://www.rfc-editor.org/rfc/rfc9484.html#section-4.7.2
+    AddressRequest {
+        /// The requested addresses.
+        addresses: Vec<AssignedAddress>,
+    },
+
+    /// ROUTE_ADVERTISEMENT capsule, as defined in [RFC 9484].
+    ///
+    /// [RFC 9484]: https://www.rfc-editor.org/rfc/rfc9484.html#section-4.7.3
+    RouteAdvertisement {
+        /// The advertised address ranges.
+        ranges: Vec<IpAddressRange>,
+    },
+
+    /// CLOSE_WEBTRANSPORT_SESSION capsule, as defined in
+    /// [WebTransport over HTTP/3].
+    ///
+    /// [WebTransport over HTTP/3]: https://datatracker.ietf.org/doc/html/draft-ietf-webtrans-http3
+    CloseWebTransportSession {
+        /// The application error code.
+        error_code: u32,
+
+        /// The reason for closing the session.
+        reason: Vec<u8>,
+    },
+
+    /// DRAIN_WEBTRANSPORT_SESSION capsule, as defined in
+    /// [WebTransport over HTTP/3].
+    ///
+    /// [WebTransport over HTTP/3]: https://datatracker.ietf.org/doc/html/draft-ietf-webtrans-http3
+    DrainWebTransportSession,
+
+    /// A capsule of a type not known to this library.
+    Unknown {
+        /// The capsule type.
+        ty: u64,
+
+        /// The raw capsule payload.
+        payload: Vec<u8>,
+    },
+}
+
+impl Capsule {
+    /// Returns the capsule's type.
+    pub fn ty(&self) -> u64 {
+        match self {
+            Capsule::Datagram { .. } => DATAGRAM_CAPSULE_TYPE_ID,
+
+            Capsule::AddressAssign { .. } => ADDRESS_ASSIGN_CAPSULE_TYPE_ID,
+
+            Capsule::AddressRequest { .. } => ADDRESS_REQUEST_CAPSULE_TYPE_ID,
+
+            Capsule::RouteAdvertisement { .. } =>
+                ROUTE_ADVERTISEMENT_CAPSULE_TYPE_ID,
+
+            Capsule::CloseWebTransportSession { .. } =>
+                CLOSE_WEBTRANSPORT_SESSION_CAPSULE_TYPE_ID,
+
+            Capsule::DrainWebTransportSession =>
+                DRAIN_WEBTRANSPORT_SESSION_CAPSULE_TYPE_ID,
+
+            Capsule::Unknown { ty, .. } => *ty,
+        }
+    }
+
+    /// Parses a capsule of the given type from its payload.
+    ///
+    /// The [`MessageError`] error is returned when the payload is malformed.
+    ///
+    /// [`MessageError`]: ../enum.Error.html#variant.MessageError
+    pub fn from_bytes(ty: u64, payload: &[u8]) -> Result<Capsule> {
+        let mut b = octets::Octets::with_slice(payload);
+
+        let capsule = match ty {
+            DATAGRAM_CAPSULE_TYPE_ID => Capsule::Datagram {
+                payload: payload.to_vec(),
+            },
+
+            ADDRESS_ASSIGN_CAPSULE_TYPE_ID => Capsule::AddressAssign {
+                addresses: parse_addresses(&mut b)?,
+            },
+
+            ADDRESS_REQUEST_CAPSULE_TYPE_ID => Capsule::AddressRequest {
+                addresses: parse_addresses(&mut b)?,
+            },
+
+            ROUTE_ADVERTISEMENT_CAPSULE_TYPE_ID => Capsule::RouteAdvertisement {
+                ranges: parse_ranges(&mut b)?,
+            },
+
+            CLOSE_WEBTRANSPORT_SESSION_CAPSULE_TYPE_ID => {
+                let error_code = b.get_u32().map_err(|_| Error::MessageError)?;
+
+                if b.cap() > MAX_CLOSE_REASON_LEN {
+                    return Err(Error::MessageError);
+                }
+
+                Capsule::CloseWebTransportSession {
+                    error_code,
+                    reason: b.as_ref().to_vec(),
+                }
+            },
+
+            DRAIN_WEBTRANSPORT_SESSION_CAPSULE_TYPE_ID =>
+                Capsule::DrainWebTransportSession,
+
+            _ => Capsule::Unknown {
+                ty,
+                payload: payload.to_vec(),
+            },
+        };
+
+        Ok(capsule)
+    }
+
+    /// Serializes the capsule, including its type and length, into the given
+    /// buffer.
+    ///
+    /// On success the number of bytes written is returned.
+    pub fn to_bytes(&self, b: &mut octets::OctetsMut) -> Result<usize> {
+        let before = b.cap();
+
+        b.put_varint(self.ty())?;
+        b.put_varint(self.payload_len() as u64)?;
+
+        match self {
+            Capsule::Datagram { payload } | Capsule::Unknown { payload, .. } => {
+                b.put_bytes(payload)?;
+            },
+
+            Capsule::AddressAssign { addresses } |
+            Capsule::AddressRequest { addresses } =>
+                for address in addresses {
+                    b.put_varint(address.request_id)?;
+                    put_ip(b, &address.ip, true)?;
+                    b.put_u8(address.prefix_len)?;
+                },
+
+            Capsule::RouteAdvertisement { ranges } =>
+                for range in ranges {
+                    put_ip(b, &range.start, true)?;
+                    put_ip(b, &range.end, false)?;
+                    b.put_u8(range.ip_protocol)?;
+                },
+
+            Capsule::CloseWebTransportSession { error_code, reason } => {
+                b.put_u32(*error_code)?;
+                b.put_bytes(reason)?;
+            },
+
+            Capsule::DrainWebTransportSession => (),
+        }
+
+        Ok(before - b.cap())
+    }
+
+    /// Returns the length of the serialized capsule, including its type and
+    /// length.
+    pub fn wire_len(&self) -> usize {
+        let payload_len = self.payload_len();
+
+        octets::varint_len(self.ty()) +
+            octets::varint_len(payload_len as u64) +
+            payload_len
+    }
+
+    fn payload_len(&self) -> usize {
+        match self {
+            Capsule::Datagram { payload } | Capsule::Unknown { payload, .. } =>
+                payload.len(),
+
+            Capsule::AddressAssign { addresses } |
+            Capsule::AddressRequest { addresses } => addresses
+                .iter()
+                .map(|a| octets::varint_len(a.request_id) + ip_len(&a.ip) + 1 + 1)
+                .sum(),
+
+            Capsule::RouteAdvertisement { ranges } => ranges
+                .iter()
+                .map(|r| 1 + ip_len(&r.start) + ip_len(&r.end) + 1)
+                .sum(),
+
+            Capsule::CloseWebTransportSession { reason, .. } => 4 + reason.len(),
+
+            Capsule::DrainWebTransportSession => 0,
+        }
+    }
+}
+
+/// A streaming capsule decoder.
+///
+/// Data received on a stream is buffered until complete capsules can be
+/// parsed from it, so capsules split across multiple DATA frames, or across
+/// multiple reads, are handled transparently.
+pub struct CapsuleDecoder {
+    /// Data that was not parsed yet.
+    buf: Vec<u8>,
+
+    /// The maximum accepted capsule payload length.
+    max_payload_len: usize,
+}
+
+impl Default for CapsuleDecoder {
+    fn default() -> Self {
+        CapsuleDecoder::new(DEFAULT_MAX_PAYLOAD_LEN)
+    }
+}
+
+impl CapsuleDecoder {
+    /// Creates a new decoder that accepts capsules with a payload of up to
+    /// `max_payload_len` bytes.
+    pub fn new(max_payload_len: usize) -> CapsuleDecoder {
+        CapsuleDecoder {
+            buf: Vec::new(),
+            max_payload_len,
+        }
+    }
+
+    /// Buffers data received on the stream.
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Returns the next complete capsule.
+    ///
+    /// The [`Done`] error is returned when not enough data was buffered to
+    /// parse a whole capsule.
+    ///
+    /// The [`ExcessiveLoad`] error is returned when the capsule's payload is
+    /// longer than the decoder's limit, and the [`MessageError`] error is
+    /// returned when it is malformed. In either case the stream can't be
+    /// decoded anymore.
+    ///
+    /// [`Done`]: ../enum.Error.html#variant.Done
+    /// [`ExcessiveLoad`]: ../enum.Error.html#variant.ExcessiveLoad
+    /// [`MessageError`]: ../enum.Error.html#variant.MessageError
+    pub fn next_capsule(&mut self) -> Result<Capsule> {
+        let mut b = octets::Octets::with_slice(&self.buf);
+
+        let (ty, len) = match (b.get_varint(), b.get_varint()) {
+            (Ok(ty), Ok(len)) => (ty, len),
+
+            _ => return Err(Error::Done),
+        };
+
+        if len > self.max_payload_len as u64 {
+            return Err(Error::ExcessiveLoad);
+        }
+
+        let payload = b.get_bytes(len as usize).map_err(|_| Error::Done)?;
+
+        let capsule = Capsule::from_bytes(ty, payload.as_ref());
+
+        let off = b.off();
+        self.buf.drain(..off);
+
+        capsule
+    }
+
+    /// Returns whether there is no buffered data.
+    ///
+    /// When the stream is finished, buffered data indicates that the last
+    /// capsule was truncated.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+}
+
+fn ip_len(ip: &IpAddr) -> usize {
+    match ip {
+        IpAddr::V4(_) => 4,
+
+        IpAddr::V6(_) => 16,
+    }
+}
+
+fn put_ip(
+    b: &mut octets::OctetsMut, ip: &IpAddr, with_version: bool,
+) -> Result<()> {
+    match ip {
+        IpAddr::V4(v4) => {
+            if with_version {
+                b.put_u8(4)?;
+            }
+
+            b.put_bytes(&v4.octets())?;
+        },
+
+        IpAddr::V6(v6) => {
+            if with_version {
+                b.put_u8(6)?;
+            }
+
+            b.put_bytes(&v6.octets())?;
+        },
+    }
+
+    Ok(())
+}
+
+fn get_ip(b: &mut octets::Octets, version: u8) -> Result<IpAddr> {
+    let ip = match version {
+        4 => {
+            let bytes: [u8; 4] = b
+                .get_bytes(4)
+                .map_err(|_| Error::MessageError)?
+                .as_ref()
+                .try_into()
+                .unwrap();
+
+            IpAddr::V4(Ipv4Addr::from(bytes))
+        },
+
+        6 => {
+            let bytes: [u8; 16] = b
+                .get_bytes(16)
+                .map_err(|_| Error::MessageError)?
+                .as_ref()
+                .try_into()
+                .unwrap();
+
+            IpAddr::V6(Ipv6Addr::from(bytes))
+        },
+
+        _ => return Err(Error::MessageError),
+    };
+
+    Ok(ip)
+}
+
+fn parse_addresses(b: &mut octets::Octets) -> Result<Vec<AssignedAddress>> {
+    let mut addresses = Vec::new();
+
+    while b.cap() > 0 {
+        let request_id = b.get_varint().map_err(|_| Error::MessageError)?;
+        let version = b.get_u8().map_err(|_| Error::MessageError)?;
+        let ip = get_ip(b, version)?;
+        let prefix_len = b.get_u8().map_err(|_| Error::MessageError)?;
+
+        if prefix_len as usize > ip_len(&ip) * 8 {
+            return Err(Error::MessageError);
+        }
+
+        addresses.push(AssignedAddress {
+            request_id,
+            ip,
+            prefix_len,
+        });
+    }
+
+    Ok(addresses)
+}
+
+fn parse_ranges(b: &mut octets::Octets) -> Result<Vec<IpAddressRange>> {
+    let mut ranges = Vec::new();
+
+    while b.cap() > 0 {
+        let version = b.get_u8().map_err(|_| Error::MessageError)?;
+        let start = get_ip(b, version)?;
+        let end = get_ip(b, version)?;
+        let ip_protocol = b.get_u8().map_err(|_| Error::MessageError)?;
+
+        if start > end {
+            return Err(Error::MessageError);
+        }
+
+        ranges.push(IpAddressRange {
+            start,
+            end,
+            ip_protocol,
+        });
+    }
+
+    Ok(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(capsule: Capsule) {
+        let mut d = [42; 256];
+
+        let wire_len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            capsule.to_bytes(&mut b).unwrap()
+        };
+
+        assert_eq!(wire_len, capsule.wire_len());
+
+        let mut decoder = CapsuleDecoder::default();
+        decoder.push(&d[..wire_len]);
+
+        assert_eq!(decoder.next_capsule(), Ok(capsule));
+        assert_eq!(decoder.next_capsule(), Err(Error::Done));
+        assert!(decoder.is_empty());
+    }
+
+    #[test]
+    fn datagram() {
+        round_trip(Capsule::Datagram {
+            payload: vec![0, 1, 2, 3, 4],
+        });
+    }
+
+    #[test]
+    fn address_assign() {
+        round_trip(Capsule::AddressAssign {
+            addresses: vec![
+                AssignedAddress {
+                    request_id: 1,
+                    ip: IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+                    prefix_len: 32,
+                },
+                AssignedAddress {
+                    request_id: 0,
+                    ip: IpAddr::V6(Ipv6Addr::new(
+                        0x2001, 0xdb8, 0, 0, 0, 0, 0, 0,
+                    )),
+                    prefix_len: 64,
+                },
+            ],
+        });
+
+        round_trip(Capsule::AddressRequest {
+            addresses: vec![AssignedAddress {
+                request_id: 1,
+                ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                prefix_len: 0,
+            }],
+        });
+    }
+
+    #[test]
+    fn route_advertisement() {
+        round_trip(Capsule::RouteAdvertisement {
+            ranges: vec![IpAddressRange {
+                start: IpAddr::V4(Ipv4Addr::new(192, 0, 2, 0)),
+                end: IpAddr::V4(Ipv4Addr::new(192, 0, 2, 255)),
+                ip_protocol: 17,
+            }],
+        });
+
+        // Ranges must not be reversed.
+        let mut d = vec![4];
+        d.extend_from_slice(&[192, 0, 2, 255]);
+        d.extend_from_slice(&[192, 0, 2, 0]);
+        d.push(0);
+
+        assert_eq!(
+            Capsule::from_bytes(ROUTE_ADVERTISEMENT_CAPSULE_TYPE_ID, &d),
+            Err(Error::MessageError)
+        );
+    }
+
+    #[test]
+    fn webtransport() {
+        round_trip(Capsule::CloseWebTransportSession {
+            error_code: 42,
+            reason: b"bye".to_vec(),
+        });
+
+        round_trip(Capsule::DrainWebTransportSession);
+
+        let reason = vec![0; MAX_CLOSE_REASON_LEN + 1];
+
+        let mut d = 42u32.to_be_bytes().to_vec();
+        d.extend_from_slice(&reason);
+
+        assert_eq!(
+            Capsule::from_bytes(CLOSE_WEBTRANSPORT_SESSION_CAPSULE_TYPE_ID, &d),
+            Err(Error::MessageError)
+        );
+    }
+
+    #[test]
+    fn unknown() {
+        round_trip(Capsule::Unknown {
+            ty: 0x1234,
+            payload: vec![1, 2, 3],
+        });
+    }
+
+    #[test]
+    fn decoder_partial() {
+        let mut d = [0; 64];
+
+        let first = Capsule::Datagram {
+            payload: vec![1; 10],
+        };
+        let second = Capsule::DrainWebTransportSession;
+
+        let len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            first.to_bytes(&mut b).unwrap() + second.to_bytes(&mut b).unwrap()
+        };
+
+        let mut decoder = CapsuleDecoder::default();
+
+        // Feed one byte at a time.
+        for (i, byte) in d[..len].iter().enumerate() {
+            decoder.push(&[*byte]);
+
+            match i + 1 {
+                12 => assert_eq!(decoder.next_capsule(), Ok(first.clone())),
+
+                17 => assert_eq!(decoder.next_capsule(), Ok(second.clone())),
+
+                _ => assert_eq!(decoder.next_capsule(), Err(Error::Done)),
+            }
+        }
+
+        assert!(decoder.is_empty());
+    }
+
+    #[test]
+    fn decoder_limit() {
+        let mut d = [0; 64];
+
+        let capsule = Capsule::Datagram {
+            payload: vec![1; 11],
+        };
+
+        let len = {
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            capsule.to_bytes(&mut b).unwrap()
+        };
+
+        let mut decoder = CapsuleDecoder::new(10);
+
+        // The limit is enforced as soon as the length is known.
+        decoder.push(&d[..2]);
+        assert_eq!(decoder.next_capsule(), Err(Error::ExcessiveLoad));
+
+        let mut decoder = CapsuleDecoder::new(11);
+        decoder.push(&d[..len]);
+        assert_eq!(decoder.next_capsule(), Ok(capsule));
+    }
+}
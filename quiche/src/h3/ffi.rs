@@ -70,6 +70,20 @@ pub extern "C" fn quiche_h3_config_set_qpack_blocked_streams(
     config.set_qpack_blocked_streams(v);
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_h3_config_set_qpack_insertion_policy(
+    config: &mut h3::Config, v: h3::QpackInsertionPolicy,
+) {
+    config.set_qpack_insertion_policy(v);
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_h3_config_set_qpack_encoder_max_table_capacity(
+    config: &mut h3::Config, v: u64,
+) {
+    config.set_qpack_encoder_max_table_capacity(v);
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_h3_config_enable_extended_connect(
     config: &mut h3::Config, enabled: bool,
@@ -77,6 +91,13 @@ pub extern "C" fn quiche_h3_config_enable_extended_connect(
     config.enable_extended_connect(enabled);
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_h3_config_set_webtransport_max_sessions(
+    config: &mut h3::Config, v: u64,
+) {
+    config.set_webtransport_max_sessions(v);
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_h3_config_free(config: *mut h3::Config) {
     drop(unsafe { Box::from_raw(config) });
@@ -148,6 +169,20 @@ pub extern "C" fn quiche_h3_event_type(ev: &h3::Event) -> u32 {
         h3::Event::Reset { .. } => 4,
 
         h3::Event::PriorityUpdate => 5,
+
+        h3::Event::PushPromise { .. } => 6,
+
+        h3::Event::PushStream { .. } => 7,
+
+        h3::Event::CancelPush => 8,
+
+        h3::Event::WebTransportStream { .. } => 9,
+
+        h3::Event::WebTransportDatagram { .. } => 10,
+
+        h3::Event::WebTransportSessionClosed { .. } => 11,
+
+        h3::Event::WebTransportSessionDrain => 12,
     }
 }
 
@@ -166,7 +201,7 @@ pub extern "C" fn quiche_h3_event_for_each_header(
     argp: *mut c_void,
 ) -> c_int {
     match ev {
-        h3::Event::Headers { list, .. } =>
+        h3::Event::Headers { list, .. } | h3::Event::PushPromise { list, .. } =>
             for h in list {
                 let rc = cb(
                     h.name().as_ptr(),
@@ -198,6 +233,58 @@ pub extern "C" fn quiche_h3_event_headers_has_more_frames(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_h3_event_push_id(ev: &h3::Event) -> u64 {
+    match ev {
+        h3::Event::PushPromise { push_id, .. } |
+        h3::Event::PushStream { push_id } => *push_id,
+
+        _ => unreachable!(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_h3_event_webtransport_session_id(ev: &h3::Event) -> u64 {
+    match ev {
+        h3::Event::WebTransportStream { session_id } => *session_id,
+
+        _ => unreachable!(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_h3_event_webtransport_datagram(
+    ev: &h3::Event, out: &mut *const u8, out_len: &mut size_t,
+) {
+    match ev {
+        h3::Event::WebTransportDatagram { payload } => {
+            *out = payload.as_ptr();
+            *out_len = payload.len();
+        },
+
+        _ => unreachable!(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_h3_event_webtransport_session_closed(
+    ev: &h3::Event, error_code: &mut u32, reason: &mut *const u8,
+    reason_len: &mut size_t,
+) {
+    match ev {
+        h3::Event::WebTransportSessionClosed {
+            error_code: code,
+            reason: r,
+        } => {
+            *error_code = *code;
+            *reason = r.as_ptr();
+            *reason_len = r.len();
+        },
+
+        _ => unreachable!(),
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_h3_extended_connect_enabled_by_peer(
     conn: &h3::Connection,
@@ -205,6 +292,13 @@ pub extern "C" fn quiche_h3_extended_connect_enabled_by_peer(
     conn.extended_connect_enabled_by_peer()
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_h3_webtransport_enabled_by_peer(
+    conn: &h3::Connection, quic_conn: &Connection,
+) -> bool {
+    conn.webtransport_enabled_by_peer(quic_conn)
+}
+
 #[no_mangle]
 pub extern "C" fn quiche_h3_event_free(ev: *mut h3::Event) {
     drop(unsafe { Box::from_raw(ev) });
@@ -335,6 +429,137 @@ pub extern "C" fn quiche_h3_send_goaway(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn quiche_h3_send_max_push_id(
+    conn: &mut h3::Connection, quic_conn: &mut Connection, push_id: u64,
+) -> c_int {
+    match conn.send_max_push_id(quic_conn, push_id) {
+        Ok(()) => 0,
+
+        Err(e) => e.to_c() as c_int,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_h3_send_push_promise(
+    conn: &mut h3::Connection, quic_conn: &mut Connection, stream_id: u64,
+    headers: *const Header, headers_len: size_t,
+) -> i64 {
+    let req_headers = headers_from_ptr(headers, headers_len);
+
+    match conn.send_push_promise(quic_conn, stream_id, &req_headers) {
+        Ok(v) => v as i64,
+
+        Err(e) => e.to_c() as i64,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_h3_send_push_response(
+    conn: &mut h3::Connection, quic_conn: &mut Connection, push_id: u64,
+    headers: *const Header, headers_len: size_t, fin: bool,
+) -> i64 {
+    let resp_headers = headers_from_ptr(headers, headers_len);
+
+    match conn.send_push_response(quic_conn, push_id, &resp_headers, fin) {
+        Ok(v) => v as i64,
+
+        Err(e) => e.to_c() as i64,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_h3_cancel_push(
+    conn: &mut h3::Connection, quic_conn: &mut Connection, push_id: u64,
+) -> c_int {
+    match conn.cancel_push(quic_conn, push_id) {
+        Ok(()) => 0,
+
+        Err(e) => e.to_c() as c_int,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_h3_open_webtransport_session(
+    conn: &mut h3::Connection, quic_conn: &mut Connection,
+    headers: *const Header, headers_len: size_t,
+) -> i64 {
+    let req_headers = headers_from_ptr(headers, headers_len);
+
+    match conn.open_webtransport_session(quic_conn, &req_headers) {
+        Ok(v) => v as i64,
+
+        Err(e) => e.to_c() as i64,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_h3_accept_webtransport_session(
+    conn: &mut h3::Connection, quic_conn: &mut Connection, stream_id: u64,
+    headers: *const Header, headers_len: size_t,
+) -> c_int {
+    let resp_headers = headers_from_ptr(headers, headers_len);
+
+    match conn.accept_webtransport_session(quic_conn, stream_id, &resp_headers) {
+        Ok(()) => 0,
+
+        Err(e) => e.to_c() as c_int,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_h3_open_webtransport_stream(
+    conn: &mut h3::Connection, quic_conn: &mut Connection, session_id: u64,
+    bidi: bool,
+) -> i64 {
+    match conn.open_webtransport_stream(quic_conn, session_id, bidi) {
+        Ok(v) => v as i64,
+
+        Err(e) => e.to_c() as i64,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_h3_send_webtransport_datagram(
+    conn: &mut h3::Connection, quic_conn: &mut Connection, session_id: u64,
+    payload: *const u8, payload_len: size_t,
+) -> c_int {
+    let payload = unsafe { slice::from_raw_parts(payload, payload_len) };
+
+    match conn.send_webtransport_datagram(quic_conn, session_id, payload) {
+        Ok(()) => 0,
+
+        Err(e) => e.to_c() as c_int,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_h3_close_webtransport_session(
+    conn: &mut h3::Connection, quic_conn: &mut Connection, session_id: u64,
+    error_code: u32, reason: *const u8, reason_len: size_t,
+) -> c_int {
+    let reason = unsafe { slice::from_raw_parts(reason, reason_len) };
+
+    match conn
+        .close_webtransport_session(quic_conn, session_id, error_code, reason)
+    {
+        Ok(()) => 0,
+
+        Err(e) => e.to_c() as c_int,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn quiche_h3_drain_webtransport_session(
+    conn: &mut h3::Connection, quic_conn: &mut Connection, session_id: u64,
+) -> c_int {
+    match conn.drain_webtransport_session(quic_conn, session_id) {
+        Ok(()) => 0,
+
+        Err(e) => e.to_c() as c_int,
+    }
+}
+
 #[no_mangle]
 #[cfg(feature = "sfv")]
 pub extern "C" fn quiche_h3_parse_extensible_priority(
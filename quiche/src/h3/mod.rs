@@ -178,6 +178,16 @@
 //!              // Peer signalled it is going away, handle it.
 //!         },
 //!
+//!         Ok((_push_id, quiche::h3::Event::CancelPush)) => (),
+//!
+//!         Ok((_, quiche::h3::Event::PushPromise { .. })) |
+//!         Ok((_, quiche::h3::Event::PushStream { .. })) => unreachable!(),
+//!
+//!         Ok((_, quiche::h3::Event::WebTransportStream { .. })) |
+//!         Ok((_, quiche::h3::Event::WebTransportDatagram { .. })) |
+//!         Ok((_, quiche::h3::Event::WebTransportSessionClosed { .. })) |
+//!         Ok((_, quiche::h3::Event::WebTransportSessionDrain)) => unreachable!(),
+//!
 //!         Err(quiche::h3::Error::Done) => {
 //!             // Done reading.
 //!             break;
@@ -239,6 +249,23 @@
 //!              // Peer signalled it is going away, handle it.
 //!         },
 //!
+//!         Ok((stream_id, quiche::h3::Event::PushPromise { push_id, list })) => {
+//!             // Server promised to push a response, handle it.
+//!         },
+//!
+//!         Ok((stream_id, quiche::h3::Event::PushStream { push_id })) => {
+//!             // Pushed response will follow on the stream, handle it.
+//!         },
+//!
+//!         Ok((push_id, quiche::h3::Event::CancelPush)) => {
+//!             // Server won't push the promised response, handle it.
+//!         },
+//!
+//!         Ok((_, quiche::h3::Event::WebTransportStream { .. })) |
+//!         Ok((_, quiche::h3::Event::WebTransportDatagram { .. })) |
+//!         Ok((_, quiche::h3::Event::WebTransportSessionClosed { .. })) |
+//!         Ok((_, quiche::h3::Event::WebTransportSessionDrain)) => unreachable!(),
+//!
 //!         Err(quiche::h3::Error::Done) => {
 //!             // Done reading.
 //!             break;
@@ -260,6 +287,28 @@
 //! repeatedly will generate an [`Event`] for each of these. The application may
 //! use these event to do additional HTTP semantic validation.
 //!
+//! ## Server push
+//!
+//! A server can push responses only once the client allowed it to, using
+//! [`send_max_push_id()`]. The server first promises a request using
+//! [`send_push_promise()`] on the stream of the request that triggered the
+//! push, and then sends the pushed response using [`send_push_response()`] and
+//! [`send_body()`]. The client is notified of these with the [`PushPromise`]
+//! and [`PushStream`] events, respectively. Either endpoint can cancel a push
+//! using [`cancel_push()`].
+//!
+//! ## WebTransport
+//!
+//! WebTransport support is enabled with [`set_webtransport_max_sessions()`],
+//! along with extended CONNECT and DATAGRAM frames. A client opens a session
+//! using [`open_webtransport_session()`], which the server accepts using
+//! [`accept_webtransport_session()`] after receiving the CONNECT request's
+//! [`Headers`] event. Both endpoints can then open streams using
+//! [`open_webtransport_stream()`], send datagrams using
+//! [`send_webtransport_datagram()`], and end the session using
+//! [`close_webtransport_session()`]. Streams, datagrams and session closure
+//! initiated by the peer are reported with dedicated [`Event`] variants.
+//!
 //! ## HTTP/3 protocol errors
 //!
 //! Quiche is responsible for managing the HTTP/3 connection, ensuring it is in
@@ -281,7 +330,21 @@
 //! [`send_request()`]: struct.Connection.html#method.send_response
 //! [`send_response()`]: struct.Connection.html#method.send_response
 //! [`send_body()`]: struct.Connection.html#method.send_body
-
+//! [`send_max_push_id()`]: struct.Connection.html#method.send_max_push_id
+//! [`send_push_promise()`]: struct.Connection.html#method.send_push_promise
+//! [`send_push_response()`]: struct.Connection.html#method.send_push_response
+//! [`cancel_push()`]: struct.Connection.html#method.cancel_push
+//! [`PushPromise`]: enum.Event.html#variant.PushPromise
+//! [`PushStream`]: enum.Event.html#variant.PushStream
+//! [`Headers`]: enum.Event.html#variant.Headers
+//! [`set_webtransport_max_sessions()`]: struct.Config.html#method.set_webtransport_max_sessions
+//! [`open_webtransport_session()`]: struct.Connection.html#method.open_webtransport_session
+//! [`accept_webtransport_session()`]: struct.Connection.html#method.accept_webtransport_session
+//! [`open_webtransport_stream()`]: struct.Connection.html#method.open_webtransport_stream
+//! [`send_webtransport_datagram()`]: struct.Connection.html#method.send_webtransport_datagram
+//! [`close_webtransport_session()`]: struct.Connection.html#method.close_webtransport_session
+
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
 
@@ -290,6 +353,8 @@ use std::convert::TryFrom;
 use std::fmt;
 use std::fmt::Write;
 
+use std::sync::Arc;
+
 #[cfg(feature = "qlog")]
 use qlog::events::h3::H3FrameCreated;
 #[cfg(feature = "qlog")]
@@ -314,8 +379,14 @@ use qlog::events::EventImportance;
 use qlog::events::EventType;
 
 use crate::range_buf::BufFactory;
+use crate::ranges;
 use crate::BufSplit;
 
+use capsule::Capsule;
+
+pub use qpack::InsertionFilter as QpackInsertionFilter;
+pub use qpack::InsertionPolicy as QpackInsertionPolicy;
+
 /// List of ALPN tokens of supported HTTP/3 versions.
 ///
 /// This can be passed directly to the [`Config::set_application_protos()`]
@@ -336,6 +407,15 @@ const PRIORITY_URGENCY_UPPER_BOUND: u8 = 7;
 const PRIORITY_URGENCY_DEFAULT: u8 = 3;
 const PRIORITY_INCREMENTAL_DEFAULT: bool = false;
 
+// Error codes as specified in [WebTransport over HTTP/3].
+//
+// [WebTransport over HTTP/3]: https://datatracker.ietf.org/doc/html/draft-ietf-webtrans-http3
+const WT_BUFFERED_STREAM_REJECTED: u64 = 0x3994bd84;
+const WT_SESSION_GONE: u64 = 0x170d7b68;
+
+// The maximum number of ranges of completed push IDs tracked by clients.
+const MAX_FINISHED_PUSH_RANGES: usize = 64;
+
 #[cfg(feature = "qlog")]
 const QLOG_FRAME_CREATED: EventType =
     EventType::Http3EventType(Http3EventType::FrameCreated);
@@ -424,6 +504,17 @@ pub enum Error {
     /// The requested operation cannot be served over HTTP/3. Peer should retry
     /// over HTTP/1.1.
     VersionFallback,
+
+    /// Error on the QPACK encoder stream.
+    QpackEncoderStreamError,
+
+    /// Error on the QPACK decoder stream.
+    QpackDecoderStreamError,
+
+    /// The peer violated the protocol in a way not covered by a more specific
+    /// error, for example by promising the same push with different header
+    /// fields.
+    GeneralProtocolError,
 }
 
 /// HTTP/3 error codes sent on the wire.
@@ -494,6 +585,10 @@ impl Error {
             Error::IdError => WireErrorCode::IdError as u64,
             Error::MissingSettings => WireErrorCode::MissingSettings as u64,
             Error::QpackDecompressionFailed => 0x200,
+            Error::QpackEncoderStreamError => 0x201,
+            Error::QpackDecoderStreamError => 0x202,
+            Error::GeneralProtocolError =>
+                WireErrorCode::GeneralProtocolError as u64,
             Error::BufferTooShort => 0x999,
             Error::TransportError { .. } | Error::StreamBlocked => 0xFF,
             Error::SettingsError => WireErrorCode::SettingsError as u64,
@@ -529,6 +624,9 @@ impl Error {
             Error::MessageError => -18,
             Error::ConnectError => -19,
             Error::VersionFallback => -20,
+            Error::QpackEncoderStreamError => -21,
+            Error::QpackDecoderStreamError => -22,
+            Error::GeneralProtocolError => -23,
 
             Error::TransportError(quic_error) => quic_error.to_c() - 1000,
         }
@@ -568,7 +666,11 @@ pub struct Config {
     max_field_section_size: Option<u64>,
     qpack_max_table_capacity: Option<u64>,
     qpack_blocked_streams: Option<u64>,
+    qpack_insertion_policy: QpackInsertionPolicy,
+    qpack_insertion_filter: Option<Arc<dyn QpackInsertionFilter>>,
+    qpack_encoder_max_table_capacity: u64,
     connect_protocol_enabled: Option<u64>,
+    wt_max_sessions: Option<u64>,
     /// additional settings are settings that are not part of the H3
     /// settings explicitly handled above
     additional_settings: Option<Vec<(u64, u64)>>,
@@ -581,7 +683,11 @@ impl Config {
             max_field_section_size: None,
             qpack_max_table_capacity: None,
             qpack_blocked_streams: None,
+            qpack_insertion_policy: QpackInsertionPolicy::Disabled,
+            qpack_insertion_filter: None,
+            qpack_encoder_max_table_capacity: qpack::DEFAULT_MAX_TABLE_CAPACITY,
             connect_protocol_enabled: None,
+            wt_max_sessions: None,
             additional_settings: None,
         })
     }
@@ -601,6 +707,9 @@ impl Config {
 
     /// Sets the `SETTINGS_QPACK_MAX_TABLE_CAPACITY` setting.
     ///
+    /// This limits the size of the dynamic table that the peer's QPACK encoder
+    /// can use to compress headers sent to us.
+    ///
     /// The default value is `0`.
     pub fn set_qpack_max_table_capacity(&mut self, v: u64) {
         self.qpack_max_table_capacity = Some(v);
@@ -608,11 +717,58 @@ impl Config {
 
     /// Sets the `SETTINGS_QPACK_BLOCKED_STREAMS` setting.
     ///
+    /// This limits the number of streams whose headers can't be decoded until
+    /// more QPACK dynamic table updates are received.
+    ///
     /// The default value is `0`.
     pub fn set_qpack_blocked_streams(&mut self, v: u64) {
         self.qpack_blocked_streams = Some(v);
     }
 
+    /// Sets the policy used by the QPACK encoder to populate and reference the
+    /// dynamic table.
+    ///
+    /// The dynamic table is only used if the peer advertises a non-zero
+    /// `SETTINGS_QPACK_MAX_TABLE_CAPACITY` setting. In that case,
+    /// [`NeverBlock`] trades some compression for never making the peer
+    /// wait for encoder instructions, while [`MaximizeCompression`]
+    /// references new entries right away, as long as the peer's
+    /// `SETTINGS_QPACK_BLOCKED_STREAMS` allows it.
+    ///
+    /// The default value is [`Disabled`].
+    ///
+    /// [`NeverBlock`]: enum.QpackInsertionPolicy.html#variant.NeverBlock
+    /// [`MaximizeCompression`]: enum.QpackInsertionPolicy.html#variant.MaximizeCompression
+    /// [`Disabled`]: enum.QpackInsertionPolicy.html#variant.Disabled
+    pub fn set_qpack_insertion_policy(&mut self, v: QpackInsertionPolicy) {
+        self.qpack_insertion_policy = v;
+    }
+
+    /// Sets the filter deciding which fields the QPACK encoder inserts into
+    /// the dynamic table.
+    ///
+    /// The filter is only used when the insertion policy isn't [`Disabled`].
+    /// By default, fields are inserted the second time they are sent, unless
+    /// they are expected to change on every message or are sensitive, see
+    /// [`RepeatedFieldFilter`].
+    ///
+    /// [`Disabled`]: enum.QpackInsertionPolicy.html#variant.Disabled
+    /// [`RepeatedFieldFilter`]: qpack/struct.RepeatedFieldFilter.html
+    pub fn set_qpack_insertion_filter(
+        &mut self, filter: Arc<dyn QpackInsertionFilter>,
+    ) {
+        self.qpack_insertion_filter = Some(filter);
+    }
+
+    /// Sets the upper bound for the capacity of the dynamic table used by the
+    /// QPACK encoder, regardless of the peer's
+    /// `SETTINGS_QPACK_MAX_TABLE_CAPACITY` setting.
+    ///
+    /// The default value is `16384`.
+    pub fn set_qpack_encoder_max_table_capacity(&mut self, v: u64) {
+        self.qpack_encoder_max_table_capacity = v;
+    }
+
     /// Sets or omits the `SETTINGS_ENABLE_CONNECT_PROTOCOL` setting.
     ///
     /// The default value is `false`.
@@ -624,6 +780,22 @@ impl Config {
         }
     }
 
+    /// Sets the `SETTINGS_WT_MAX_SESSIONS` setting.
+    ///
+    /// This limits the number of concurrent WebTransport sessions the peer
+    /// can establish. A non-zero value enables WebTransport support, which
+    /// also requires extended CONNECT to be enabled with
+    /// [`enable_extended_connect()`], and DATAGRAM frames to be enabled on the
+    /// transport's [`Config`].
+    ///
+    /// The default value is `0`, that is, WebTransport is disabled.
+    ///
+    /// [`enable_extended_connect()`]: struct.Config.html#method.enable_extended_connect
+    /// [`Config`]: ../struct.Config.html
+    pub fn set_webtransport_max_sessions(&mut self, v: u64) {
+        self.wt_max_sessions = Some(v);
+    }
+
     /// Sets additional HTTP/3 settings.
     ///
     /// The default value is no additional settings.
@@ -635,6 +807,7 @@ impl Config {
     /// - SETTINGS_QPACK_BLOCKED_STREAMS
     /// - SETTINGS_ENABLE_CONNECT_PROTOCOL
     /// - SETTINGS_H3_DATAGRAM
+    /// - SETTINGS_WT_MAX_SESSIONS
     ///
     /// If such a setting is present in the `additional_settings`,
     /// the method will return the [`Error::SettingsError`] error.
@@ -653,6 +826,7 @@ impl Config {
             frame::SETTINGS_ENABLE_CONNECT_PROTOCOL,
             frame::SETTINGS_H3_DATAGRAM,
             frame::SETTINGS_H3_DATAGRAM_00,
+            frame::SETTINGS_WT_MAX_SESSIONS,
         ]);
 
         let dedup_settings: HashSet<u64> =
@@ -803,6 +977,80 @@ pub enum Event {
 
     /// GOAWAY was received.
     GoAway,
+
+    /// PUSH_PROMISE was received.
+    ///
+    /// This is returned with the ID of the request stream the promise was
+    /// received on. The pushed response will arrive on a push stream, which
+    /// is reported by the [`PushStream`] event.
+    ///
+    /// [`PushStream`]: enum.Event.html#variant.PushStream
+    PushPromise {
+        /// The push ID of the promised response.
+        push_id: u64,
+
+        /// The list of header fields of the promised request.
+        list: Vec<Header>,
+    },
+
+    /// A push stream carrying a pushed response was opened by the server.
+    ///
+    /// This is returned with the ID of the push stream, which is then used
+    /// to report the pushed response's [`Headers`], [`Data`] and [`Finished`]
+    /// events, just like for responses on request streams.
+    ///
+    /// [`Headers`]: enum.Event.html#variant.Headers
+    /// [`Data`]: enum.Event.html#variant.Data
+    /// [`Finished`]: enum.Event.html#variant.Finished
+    PushStream {
+        /// The push ID of the pushed response.
+        push_id: u64,
+    },
+
+    /// CANCEL_PUSH was received.
+    ///
+    /// This is returned with the push ID of the cancelled push.
+    CancelPush,
+
+    /// The peer opened a stream associated with a WebTransport session.
+    ///
+    /// This is returned with the ID of the new stream, which is then used to
+    /// report [`Data`] and [`Finished`] events. Data received on the stream is
+    /// not framed, and is returned as is by the [`recv_body()`] method.
+    ///
+    /// [`Data`]: enum.Event.html#variant.Data
+    /// [`Finished`]: enum.Event.html#variant.Finished
+    /// [`recv_body()`]: struct.Connection.html#method.recv_body
+    WebTransportStream {
+        /// The ID of the session the stream belongs to.
+        session_id: u64,
+    },
+
+    /// A datagram associated with a WebTransport session was received.
+    ///
+    /// This is returned with the session ID.
+    WebTransportDatagram {
+        /// The datagram's payload, without the session's quarter stream ID.
+        payload: Vec<u8>,
+    },
+
+    /// A WebTransport session was closed by the peer.
+    ///
+    /// This is returned with the session ID. All the streams associated with
+    /// the session are reset by the time this event is returned.
+    WebTransportSessionClosed {
+        /// The application error code sent by the peer, or `0` if the
+        /// session's CONNECT stream was closed without one.
+        error_code: u32,
+
+        /// The reason for closing the session sent by the peer.
+        reason: Vec<u8>,
+    },
+
+    /// The peer asked to gracefully wind down a WebTransport session.
+    ///
+    /// This is returned with the session ID.
+    WebTransportSessionDrain,
 }
 
 /// Extensible Priorities parameters.
@@ -909,10 +1157,34 @@ struct ConnectionSettings {
     pub qpack_blocked_streams: Option<u64>,
     pub connect_protocol_enabled: Option<u64>,
     pub h3_datagram: Option<u64>,
+    pub wt_max_sessions: Option<u64>,
     pub additional_settings: Option<Vec<(u64, u64)>>,
     pub raw: Option<Vec<(u64, u64)>>,
 }
 
+/// The state of a WebTransport session, as seen by either endpoint.
+#[derive(Default)]
+struct WebTransportSession {
+    /// Decoder for capsules received on the session's CONNECT stream.
+    capsules: capsule::CapsuleDecoder,
+
+    /// Whether the session was closed, either locally or by the peer.
+    closed: bool,
+}
+
+/// The state of a server push, as seen by either endpoint.
+#[derive(Default)]
+struct Push {
+    /// The ID of the push stream carrying the pushed response, once opened.
+    stream_id: Option<u64>,
+
+    /// Whether the push was cancelled.
+    cancelled: bool,
+
+    /// The header fields of the promised request, as received by the client.
+    promise: Option<Vec<Header>>,
+}
+
 #[derive(Default)]
 struct QpackStreams {
     pub encoder_stream_id: Option<u64>,
@@ -977,7 +1249,18 @@ pub struct Connection {
     local_qpack_streams: QpackStreams,
     peer_qpack_streams: QpackStreams,
 
-    max_push_id: u64,
+    max_push_id: Option<u64>,
+    next_push_id: u64,
+
+    pushes: HashMap<u64, Push>,
+
+    /// Push IDs whose push stream was received and then completed or
+    /// cancelled, so that late promises for them can be ignored.
+    finished_pushes: ranges::RangeSet,
+
+    webtransport_sessions: crate::stream::StreamIdHashMap<WebTransportSession>,
+    pending_webtransport_streams: Vec<(u64, u64)>,
+    webtransport_events: VecDeque<(u64, Event)>,
 
     finished_streams: VecDeque<u64>,
 
@@ -991,13 +1274,15 @@ impl Connection {
     fn new(
         config: &Config, is_server: bool, enable_dgram: bool,
     ) -> Result<Connection> {
+        // Servers only open bidirectional streams for WebTransport.
+        let initial_request_stream_id = if is_server { 0x1 } else { 0x0 };
         let initial_uni_stream_id = if is_server { 0x3 } else { 0x2 };
         let h3_datagram = if enable_dgram { Some(1) } else { None };
 
         Ok(Connection {
             is_server,
 
-            next_request_stream_id: 0,
+            next_request_stream_id: initial_request_stream_id,
 
             next_uni_stream_id: initial_uni_stream_id,
 
@@ -1009,6 +1294,7 @@ impl Connection {
                 qpack_blocked_streams: config.qpack_blocked_streams,
                 connect_protocol_enabled: config.connect_protocol_enabled,
                 h3_datagram,
+                wt_max_sessions: config.wt_max_sessions,
                 additional_settings: config.additional_settings.clone(),
                 raw: Default::default(),
             },
@@ -1019,6 +1305,7 @@ impl Connection {
                 qpack_blocked_streams: None,
                 h3_datagram: None,
                 connect_protocol_enabled: None,
+                wt_max_sessions: None,
                 additional_settings: Default::default(),
                 raw: Default::default(),
             },
@@ -1026,13 +1313,38 @@ impl Connection {
             control_stream_id: None,
             peer_control_stream_id: None,
 
-            qpack_encoder: qpack::Encoder::new(),
-            qpack_decoder: qpack::Decoder::new(),
+            qpack_encoder: {
+                let mut encoder =
+                    qpack::Encoder::with_policy(config.qpack_insertion_policy);
+
+                encoder.set_max_table_capacity(
+                    config.qpack_encoder_max_table_capacity,
+                );
+
+                if let Some(filter) = &config.qpack_insertion_filter {
+                    encoder.set_insertion_filter(Arc::clone(filter));
+                }
+
+                encoder
+            },
+            qpack_decoder: qpack::Decoder::with_limits(
+                config.qpack_max_table_capacity.unwrap_or(0),
+                config.qpack_blocked_streams.unwrap_or(0),
+            ),
 
             local_qpack_streams: Default::default(),
             peer_qpack_streams: Default::default(),
 
-            max_push_id: 0,
+            max_push_id: None,
+            next_push_id: 0,
+
+            pushes: HashMap::new(),
+
+            finished_pushes: ranges::RangeSet::new(MAX_FINISHED_PUSH_RANGES),
+
+            webtransport_sessions: Default::default(),
+            pending_webtransport_streams: Vec::new(),
+            webtransport_events: VecDeque::new(),
 
             finished_streams: VecDeque::new(),
 
@@ -1121,8 +1433,13 @@ impl Connection {
 
         let stream_id = self.next_request_stream_id;
 
-        self.streams
-            .insert(stream_id, <stream::Stream>::new(stream_id, true));
+        let mut stream = <stream::Stream>::new(stream_id, true);
+
+        if is_extended_connect(headers) {
+            stream.set_extended_connect();
+        }
+
+        self.streams.insert(stream_id, stream);
 
         // The underlying QUIC stream does not exist yet, so calls to e.g.
         // stream_capacity() will fail. By writing a 0-length buffer, we force
@@ -1393,8 +1710,190 @@ impl Connection {
         Ok(())
     }
 
+    /// Sends a PUSH_PROMISE frame on the specified request stream.
+    ///
+    /// The promised request is encoded from the provided list of headers, and
+    /// associated with a newly allocated push ID. The pushed response can then
+    /// be sent using [`send_push_response()`] with the same push ID.
+    ///
+    /// On success the newly allocated push ID is returned.
+    ///
+    /// The [`IdError`] error is returned when the push ID would exceed the
+    /// limit set by the client using MAX_PUSH_ID, or the limit implied by the
+    /// client's GOAWAY. Until a MAX_PUSH_ID frame is received from the client,
+    /// no pushes are allowed.
+    ///
+    /// The [`FrameUnexpected`] error is returned when this method is called by
+    /// a client, or on a stream that isn't an open request stream.
+    ///
+    /// The [`StreamBlocked`] error is returned when the underlying QUIC stream
+    /// doesn't have enough capacity for the operation to complete. When this
+    /// happens the application should retry the operation once the stream is
+    /// reported as writable again.
+    ///
+    /// [`send_push_response()`]:
+    ///     struct.Connection.html#method.send_push_response
+    /// [`IdError`]: enum.Error.html#variant.IdError
+    /// [`FrameUnexpected`]: enum.Error.html#variant.FrameUnexpected
+    /// [`StreamBlocked`]: enum.Error.html#variant.StreamBlocked
+    pub fn send_push_promise<T: NameValue, F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, stream_id: u64, headers: &[T],
+    ) -> Result<u64> {
+        if !self.is_server {
+            return Err(Error::FrameUnexpected);
+        }
+
+        match self.streams.get(&stream_id) {
+            Some(s) if s.ty() == Some(stream::Type::Request) => (),
+
+            _ => return Err(Error::FrameUnexpected),
+        };
+
+        let push_id = self.next_push_id;
+
+        if self.max_push_id.is_none_or(|max| push_id > max) ||
+            self.peer_goaway_id.is_some_and(|id| push_id >= id)
+        {
+            return Err(Error::IdError);
+        }
+
+        let header_block = self.encode_header_block(stream_id, headers)?;
+
+        let payload_len = octets::varint_len(push_id) + header_block.len();
+
+        let overhead = octets::varint_len(frame::PUSH_PROMISE_FRAME_TYPE_ID) +
+            octets::varint_len(payload_len as u64) +
+            octets::varint_len(push_id);
+
+        // The promise needs to be sent atomically, so make sure the stream has
+        // enough capacity.
+        match conn.stream_writable(stream_id, overhead + header_block.len()) {
+            Ok(true) => (),
+
+            Ok(false) => {
+                self.qpack_encoder.abort_section(stream_id);
+
+                return Err(Error::StreamBlocked);
+            },
+
+            Err(e) => {
+                self.qpack_encoder.abort_section(stream_id);
+
+                return Err(e.into());
+            },
+        };
+
+        self.send_qpack_encoder_instructions(conn)?;
+
+        let mut d = [42; 24];
+        let mut b = octets::OctetsMut::with_slice(&mut d);
+
+        b.put_varint(frame::PUSH_PROMISE_FRAME_TYPE_ID)?;
+        b.put_varint(payload_len as u64)?;
+        b.put_varint(push_id)?;
+        let off = b.off();
+        conn.stream_send(stream_id, &d[..off], false)?;
+
+        // Sending header block separately avoids unnecessary copy.
+        conn.stream_send(stream_id, &header_block, false)?;
+
+        trace!(
+            "{} tx frm PUSH_PROMISE stream={} push_id={} len={}",
+            conn.trace_id(),
+            stream_id,
+            push_id,
+            header_block.len(),
+        );
+
+        qlog_with_type!(QLOG_FRAME_CREATED, conn.qlog, q, {
+            let qlog_headers = headers
+                .iter()
+                .map(|h| qlog::events::h3::HttpHeader {
+                    name: String::from_utf8_lossy(h.name()).into_owned(),
+                    value: String::from_utf8_lossy(h.value()).into_owned(),
+                })
+                .collect();
+
+            let frame = Http3Frame::PushPromise {
+                push_id,
+                headers: qlog_headers,
+            };
+            let ev_data = EventData::H3FrameCreated(H3FrameCreated {
+                stream_id,
+                length: Some(payload_len as u64),
+                frame,
+                ..Default::default()
+            });
+
+            q.add_event_data_now(ev_data).ok();
+        });
+
+        self.pushes.insert(push_id, Push::default());
+
+        self.next_push_id += 1;
+
+        Ok(push_id)
+    }
+
+    /// Sends the response of a promised push on a new push stream.
+    ///
+    /// This opens a push stream for the given `push_id`, previously returned by
+    /// [`send_push_promise()`], and sends the provided `headers` on it as the
+    /// pushed response. To include a body, set `fin` as `false` and
+    /// subsequently call [`send_body()`] with the same `conn` and the stream ID
+    /// returned from this method.
+    ///
+    /// On success the push stream ID is returned.
+    ///
+    /// The [`FrameUnexpected`] error is returned when this method is called by
+    /// a client, or when the push ID wasn't promised, was cancelled, or its
+    /// response headers were already sent.
+    ///
+    /// The [`StreamBlocked`] error is returned when the underlying QUIC stream
+    /// doesn't have enough capacity for the operation to complete. When this
+    /// happens the application should retry the operation with the same push
+    /// ID once the stream is reported as writable again.
+    ///
+    /// [`send_push_promise()`]:
+    ///     struct.Connection.html#method.send_push_promise
+    /// [`send_body()`]: struct.Connection.html#method.send_body
+    /// [`FrameUnexpected`]: enum.Error.html#variant.FrameUnexpected
+    /// [`StreamBlocked`]: enum.Error.html#variant.StreamBlocked
+    pub fn send_push_response<T: NameValue, F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, push_id: u64, headers: &[T],
+        fin: bool,
+    ) -> Result<u64> {
+        if !self.is_server {
+            return Err(Error::FrameUnexpected);
+        }
+
+        let push = self.pushes.get(&push_id).ok_or(Error::FrameUnexpected)?;
+
+        let stream_id = match push.stream_id {
+            // The push stream was already opened by a previous call that
+            // couldn't send the headers, so reuse it.
+            Some(stream_id) => {
+                if self
+                    .streams
+                    .get(&stream_id)
+                    .is_none_or(|s| s.local_initialized())
+                {
+                    return Err(Error::FrameUnexpected);
+                }
+
+                stream_id
+            },
+
+            None => self.open_push_stream(conn, push_id)?,
+        };
+
+        self.send_headers(conn, stream_id, headers, fin)?;
+
+        Ok(stream_id)
+    }
+
     fn encode_header_block<T: NameValue>(
-        &mut self, headers: &[T],
+        &mut self, stream_id: u64, headers: &[T],
     ) -> Result<Vec<u8>> {
         let headers_len = headers
             .iter()
@@ -1403,7 +1902,7 @@ impl Connection {
         let mut header_block = vec![0; headers_len];
         let len = self
             .qpack_encoder
-            .encode(headers, &mut header_block)
+            .encode_stream(stream_id, headers, &mut header_block)
             .map_err(|_| Error::InternalError)?;
 
         header_block.truncate(len);
@@ -1423,7 +1922,7 @@ impl Connection {
             self.frames_greased = true;
         }
 
-        let header_block = self.encode_header_block(headers)?;
+        let header_block = self.encode_header_block(stream_id, headers)?;
 
         let overhead = octets::varint_len(frame::HEADERS_FRAME_TYPE_ID) +
             octets::varint_len(header_block.len() as u64);
@@ -1433,9 +1932,15 @@ impl Connection {
         match conn.stream_writable(stream_id, overhead + header_block.len()) {
             Ok(true) => (),
 
-            Ok(false) => return Err(Error::StreamBlocked),
+            Ok(false) => {
+                self.qpack_encoder.abort_section(stream_id);
+
+                return Err(Error::StreamBlocked);
+            },
 
             Err(e) => {
+                self.qpack_encoder.abort_section(stream_id);
+
                 if conn.stream_finished(stream_id) {
                     self.streams.remove(&stream_id);
                 }
@@ -1444,6 +1949,10 @@ impl Connection {
             },
         };
 
+        // Send any dynamic table updates before the header block referencing
+        // them, so the peer is less likely to be blocked.
+        self.send_qpack_encoder_instructions(conn)?;
+
         b.put_varint(frame::HEADERS_FRAME_TYPE_ID)?;
         b.put_varint(header_block.len() as u64)?;
         let off = b.off();
@@ -1486,8 +1995,8 @@ impl Connection {
             s.initialize_local();
         }
 
-        if fin && conn.stream_finished(stream_id) {
-            self.streams.remove(&stream_id);
+        if fin && self.local_stream_finished(conn, stream_id) {
+            self.remove_stream(stream_id);
         }
 
         Ok(())
@@ -1615,8 +2124,9 @@ impl Connection {
 
         let len = body.as_ref().len();
 
-        // Validate that it is sane to send data on the stream.
-        if stream_id % 4 != 0 {
+        // Validate that it is sane to send data on the stream. Other than
+        // request streams, only servers can send data on their push streams.
+        if stream_id % 4 != 0 && !(self.is_server && stream_id % 4 == 3) {
             return Err(Error::FrameUnexpected);
         }
 
@@ -1714,8 +2224,8 @@ impl Connection {
             let _ = conn.stream_writable(stream_id, overhead + 1);
         }
 
-        if fin && written == len && conn.stream_finished(stream_id) {
-            self.streams.remove(&stream_id);
+        if fin && written == len && self.local_stream_finished(conn, stream_id) {
+            self.remove_stream(stream_id);
         }
 
         Ok(ret)
@@ -1746,6 +2256,22 @@ impl Connection {
         self.peer_settings.connect_protocol_enabled == Some(1)
     }
 
+    /// Returns whether the peer enabled WebTransport support.
+    ///
+    /// This requires the peer to allow at least one WebTransport session, and
+    /// to support both extended CONNECT and HTTP/3 DATAGRAM frames. Support is
+    /// signalled by the peer's SETTINGS, so this method always returns false
+    /// until they have been processed using the [`poll()`] method.
+    ///
+    /// [`poll()`]: struct.Connection.html#method.poll
+    pub fn webtransport_enabled_by_peer<F: BufFactory>(
+        &self, conn: &super::Connection<F>,
+    ) -> bool {
+        self.peer_settings.wt_max_sessions.is_some_and(|v| v > 0) &&
+            self.extended_connect_enabled_by_peer() &&
+            self.dgram_enabled_by_peer(conn)
+    }
+
     /// Reads request or response body data into the provided buffer.
     ///
     /// Applications should call this method whenever the [`poll()`] method
@@ -1818,51 +2344,163 @@ impl Connection {
         Ok(total)
     }
 
-    /// Sends a PRIORITY_UPDATE frame on the control stream with specified
-    /// request stream ID and priority.
+    /// Sends a capsule on a request stream.
     ///
-    /// The `priority` parameter represents [Extensible Priority]
-    /// parameters. If the urgency is outside the range 0-7, it will be clamped
-    /// to 7.
+    /// The capsule is sent in a single DATA frame, and is either sent in full,
+    /// or not at all. This is meant to be used on streams established with an
+    /// extended CONNECT request, whose body consists of a sequence of
+    /// capsules, as defined in [RFC 9297].
+    ///
+    /// The [`FrameUnexpected`] error is returned when the stream's request
+    /// isn't an extended CONNECT request, i.e. doesn't have a `:protocol`
+    /// pseudo-header, when the stream's headers weren't sent yet, or when
+    /// trailers were already sent.
     ///
     /// The [`StreamBlocked`] error is returned when the underlying QUIC stream
     /// doesn't have enough capacity for the operation to complete. When this
     /// happens the application should retry the operation once the stream is
     /// reported as writable again.
     ///
+    /// [`FrameUnexpected`]: enum.Error.html#variant.FrameUnexpected
     /// [`StreamBlocked`]: enum.Error.html#variant.StreamBlocked
-    /// [Extensible Priority]: https://www.rfc-editor.org/rfc/rfc9218.html#section-4.
-    pub fn send_priority_update_for_request<F: BufFactory>(
+    /// [RFC 9297]: https://www.rfc-editor.org/rfc/rfc9297.html
+    pub fn send_capsule<F: BufFactory>(
         &mut self, conn: &mut super::Connection<F>, stream_id: u64,
-        priority: &Priority,
+        capsule: &Capsule, fin: bool,
     ) -> Result<()> {
-        let mut d = [42; 20];
-        let mut b = octets::OctetsMut::with_slice(&mut d);
-
-        // Validate that it is sane to send PRIORITY_UPDATE.
-        if self.is_server {
-            return Err(Error::FrameUnexpected);
-        }
-
         if stream_id % 4 != 0 {
             return Err(Error::FrameUnexpected);
         }
 
-        let control_stream_id =
-            self.control_stream_id.ok_or(Error::FrameUnexpected)?;
+        match self.streams.get(&stream_id) {
+            Some(s)
+                if s.is_extended_connect() &&
+                    s.local_initialized() &&
+                    !s.trailers_sent() =>
+                (),
 
-        let urgency = priority
-            .urgency
-            .clamp(PRIORITY_URGENCY_LOWER_BOUND, PRIORITY_URGENCY_UPPER_BOUND);
+            _ => return Err(Error::FrameUnexpected),
+        };
 
-        let mut field_value = format!("u={urgency}");
+        let capsule_len = capsule.wire_len();
 
-        if priority.incremental {
-            field_value.push_str(",i");
-        }
+        let len = octets::varint_len(frame::DATA_FRAME_TYPE_ID) +
+            octets::varint_len(capsule_len as u64) +
+            capsule_len;
 
-        let priority_field_value = field_value.as_bytes();
-        let frame_payload_len =
+        let mut d = vec![0; len];
+        let mut b = octets::OctetsMut::with_slice(&mut d);
+
+        b.put_varint(frame::DATA_FRAME_TYPE_ID)?;
+        b.put_varint(capsule_len as u64)?;
+        capsule.to_bytes(&mut b)?;
+
+        match conn.stream_writable(stream_id, len) {
+            Ok(true) => (),
+
+            Ok(false) => return Err(Error::StreamBlocked),
+
+            Err(e) => return Err(e.into()),
+        };
+
+        conn.stream_send(stream_id, &d, fin)?;
+
+        trace!(
+            "{} tx capsule type={} stream={} len={} fin={}",
+            conn.trace_id(),
+            capsule.ty(),
+            stream_id,
+            capsule_len,
+            fin
+        );
+
+        Ok(())
+    }
+
+    /// Sends an HTTP Datagram associated with a request stream.
+    ///
+    /// When the peer enabled HTTP/3 DATAGRAM frames, the payload is sent in a
+    /// QUIC DATAGRAM frame prefixed with the request's Quarter Stream ID.
+    /// Otherwise it falls back to sending a DATAGRAM capsule on the request
+    /// stream, as defined in [RFC 9297].
+    ///
+    /// Errors from the transport's [`dgram_send()`] method, or from the
+    /// [`send_capsule()`] method, are returned as is.
+    ///
+    /// [`dgram_send()`]: ../struct.Connection.html#method.dgram_send
+    /// [`send_capsule()`]: struct.Connection.html#method.send_capsule
+    /// [RFC 9297]: https://www.rfc-editor.org/rfc/rfc9297.html
+    pub fn send_http_datagram<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, stream_id: u64,
+        payload: &[u8],
+    ) -> Result<()> {
+        if !self.dgram_enabled_by_peer(conn) {
+            let capsule = Capsule::Datagram {
+                payload: payload.to_vec(),
+            };
+
+            return self.send_capsule(conn, stream_id, &capsule, false);
+        }
+
+        let quarter_stream_id = stream_id / 4;
+
+        let mut d =
+            vec![0; octets::varint_len(quarter_stream_id) + payload.len()];
+        let mut b = octets::OctetsMut::with_slice(&mut d);
+
+        b.put_varint(quarter_stream_id)?;
+        b.put_bytes(payload)?;
+
+        conn.dgram_send_vec(d)?;
+
+        Ok(())
+    }
+
+    /// Sends a PRIORITY_UPDATE frame on the control stream with specified
+    /// request stream ID and priority.
+    ///
+    /// The `priority` parameter represents [Extensible Priority]
+    /// parameters. If the urgency is outside the range 0-7, it will be clamped
+    /// to 7.
+    ///
+    /// The [`StreamBlocked`] error is returned when the underlying QUIC stream
+    /// doesn't have enough capacity for the operation to complete. When this
+    /// happens the application should retry the operation once the stream is
+    /// reported as writable again.
+    ///
+    /// [`StreamBlocked`]: enum.Error.html#variant.StreamBlocked
+    /// [Extensible Priority]: https://www.rfc-editor.org/rfc/rfc9218.html#section-4.
+    pub fn send_priority_update_for_request<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, stream_id: u64,
+        priority: &Priority,
+    ) -> Result<()> {
+        let mut d = [42; 20];
+        let mut b = octets::OctetsMut::with_slice(&mut d);
+
+        // Validate that it is sane to send PRIORITY_UPDATE.
+        if self.is_server {
+            return Err(Error::FrameUnexpected);
+        }
+
+        if stream_id % 4 != 0 {
+            return Err(Error::FrameUnexpected);
+        }
+
+        let control_stream_id =
+            self.control_stream_id.ok_or(Error::FrameUnexpected)?;
+
+        let urgency = priority
+            .urgency
+            .clamp(PRIORITY_URGENCY_LOWER_BOUND, PRIORITY_URGENCY_UPPER_BOUND);
+
+        let mut field_value = format!("u={urgency}");
+
+        if priority.incremental {
+            field_value.push_str(",i");
+        }
+
+        let priority_field_value = field_value.as_bytes();
+        let frame_payload_len =
             octets::varint_len(stream_id) + priority_field_value.len();
 
         let overhead =
@@ -1965,6 +2603,16 @@ impl Connection {
     /// prioritized element ID that is used in the method
     /// [`take_last_priority_update()`], which rearms the event for that ID.
     ///
+    /// The events [`PushPromise`] and [`PushStream`] only occur at clients. The
+    /// former returns the ID of the request stream the promise was received
+    /// on, while the latter returns the ID of the push stream carrying the
+    /// pushed response. The event [`CancelPush`] returns a push ID.
+    ///
+    /// The events [`WebTransportStream`], [`WebTransportDatagram`],
+    /// [`WebTransportSessionClosed`] and [`WebTransportSessionDrain`] only
+    /// occur when WebTransport support is enabled. The first returns the ID of
+    /// the stream opened by the peer, while the others return a session ID.
+    ///
     /// If an error occurs while processing data, the connection is closed with
     /// the appropriate error code, using the transport's [`close()`] method.
     ///
@@ -1975,6 +2623,13 @@ impl Connection {
     /// [`Finished`]: enum.Event.html#variant.Finished
     /// [`GoAway`]: enum.Event.html#variant.GoAWay
     /// [`PriorityUpdate`]: enum.Event.html#variant.PriorityUpdate
+    /// [`PushPromise`]: enum.Event.html#variant.PushPromise
+    /// [`PushStream`]: enum.Event.html#variant.PushStream
+    /// [`CancelPush`]: enum.Event.html#variant.CancelPush
+    /// [`WebTransportStream`]: enum.Event.html#variant.WebTransportStream
+    /// [`WebTransportDatagram`]: enum.Event.html#variant.WebTransportDatagram
+    /// [`WebTransportSessionClosed`]: enum.Event.html#variant.WebTransportSessionClosed
+    /// [`WebTransportSessionDrain`]: enum.Event.html#variant.WebTransportSessionDrain
     /// [`recv_body()`]: struct.Connection.html#method.recv_body
     /// [`send_response()`]: struct.Connection.html#method.send_response
     /// [`send_body()`]: struct.Connection.html#method.send_body
@@ -2012,6 +2667,20 @@ impl Connection {
             };
         }
 
+        // Decode header blocks that were waiting for QPACK dynamic table
+        // updates.
+        while let Some(stream_id) = self.qpack_decoder.next_unblocked_stream() {
+            match self.process_unblocked_stream(conn, stream_id) {
+                Ok(ev) => return Ok(ev),
+
+                Err(Error::Done) => (),
+
+                Err(e) => return Err(e),
+            };
+        }
+
+        self.send_qpack_decoder_instructions(conn)?;
+
         if let Some(stream_id) = self.peer_qpack_streams.decoder_stream_id {
             match self.process_control_stream(conn, stream_id) {
                 Ok(ev) => return Ok(ev),
@@ -2022,9 +2691,22 @@ impl Connection {
             };
         }
 
+        // Retry encoder instructions that didn't fit in the stream earlier.
+        self.send_qpack_encoder_instructions(conn)?;
+
         // Process finished streams list.
         if let Some(finished) = self.finished_streams.pop_front() {
-            return Ok((finished, Event::Finished));
+            return Ok((finished, self.finished_stream_event(conn, finished)));
+        }
+
+        // Report queued WebTransport events, such as streams for sessions
+        // that were just accepted.
+        if let Some(ev) = self.webtransport_events.pop_front() {
+            return Ok(ev);
+        }
+
+        if let Some(ev) = self.process_webtransport_datagram(conn) {
+            return Ok(ev);
         }
 
         // Process HTTP/3 data from readable streams.
@@ -2038,8 +2720,13 @@ impl Connection {
 
                 // Return early if the stream was reset, to avoid returning
                 // a Finished event later as well.
-                Err(Error::TransportError(crate::Error::StreamReset(e))) =>
-                    return Ok((s, Event::Reset(e))),
+                Err(Error::TransportError(crate::Error::StreamReset(e))) => {
+                    self.cancel_qpack_stream(s);
+                    self.cancel_webtransport_session(conn, s);
+                    self.collect_push_stream(s);
+
+                    return Ok((s, Event::Reset(e)));
+                },
 
                 Err(e) => return Err(e),
             };
@@ -2064,10 +2751,15 @@ impl Connection {
                 if let Err(crate::Error::StreamReset(e)) =
                     conn.stream_recv(finished, &mut [])
                 {
+                    self.cancel_qpack_stream(finished);
+                    self.cancel_webtransport_session(conn, finished);
+                    self.collect_push_stream(finished);
+
                     return Ok((finished, Event::Reset(e)));
                 }
             }
-            return Ok((finished, Event::Finished));
+
+            return Ok((finished, self.finished_stream_event(conn, finished)));
         }
 
         Err(Error::Done)
@@ -2077,8 +2769,10 @@ impl Connection {
     ///
     /// When quiche is used in the server role, the `id` parameter is the stream
     /// ID of the highest processed request. This can be any valid ID between 0
-    /// and 2^62-4. However, the ID cannot be increased. Failure to satisfy
-    /// these conditions will return an error.
+    /// and 2^62-4. When quiche is used in the client role, the `id` parameter
+    /// is the smallest push ID the server is not allowed to use anymore.
+    /// In either case the ID cannot be increased. Failure to satisfy these
+    /// conditions will return an error.
     ///
     /// This method does not close the QUIC connection. Applications are
     /// required to call [`close()`] themselves.
@@ -2087,15 +2781,6 @@ impl Connection {
     pub fn send_goaway<F: BufFactory>(
         &mut self, conn: &mut super::Connection<F>, id: u64,
     ) -> Result<()> {
-        let mut id = id;
-
-        // TODO: server push
-        //
-        // In the meantime always send 0 from client.
-        if !self.is_server {
-            id = 0;
-        }
-
         if self.is_server && id % 4 != 0 {
             return Err(Error::IdError);
         }
@@ -2141,134 +2826,570 @@ impl Connection {
         Ok(())
     }
 
-    /// Gets the raw settings from peer including unknown and reserved types.
+    /// Sends a MAX_PUSH_ID frame to allow the server to push responses.
     ///
-    /// The order of settings is the same as received in the SETTINGS frame.
-    pub fn peer_settings_raw(&self) -> Option<&[(u64, u64)]> {
-        self.peer_settings.raw.as_deref()
-    }
+    /// The `push_id` parameter is the largest push ID the server is allowed to
+    /// use. Server push is disabled until this method is first called, and
+    /// the limit can't be reduced afterwards, in which case the [`IdError`]
+    /// error is returned.
+    ///
+    /// The [`FrameUnexpected`] error is returned when this method is called by
+    /// a server.
+    ///
+    /// The [`StreamBlocked`] error is returned when the control stream doesn't
+    /// have enough capacity for the operation to complete. When this happens
+    /// the application should retry the operation later on.
+    ///
+    /// [`IdError`]: enum.Error.html#variant.IdError
+    /// [`FrameUnexpected`]: enum.Error.html#variant.FrameUnexpected
+    /// [`StreamBlocked`]: enum.Error.html#variant.StreamBlocked
+    pub fn send_max_push_id<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, push_id: u64,
+    ) -> Result<()> {
+        if self.is_server {
+            return Err(Error::FrameUnexpected);
+        }
 
-    fn open_uni_stream<F: BufFactory>(
-        &mut self, conn: &mut super::Connection<F>, ty: u64,
-    ) -> Result<u64> {
-        let stream_id = self.next_uni_stream_id;
+        if self.max_push_id.is_some_and(|max| push_id < max) {
+            return Err(Error::IdError);
+        }
 
-        let mut d = [0; 8];
-        let mut b = octets::OctetsMut::with_slice(&mut d);
+        self.send_control_frame(conn, &frame::Frame::MaxPushId { push_id })?;
 
-        match ty {
-            // Control and QPACK streams are the most important to schedule.
-            stream::HTTP3_CONTROL_STREAM_TYPE_ID |
-            stream::QPACK_ENCODER_STREAM_TYPE_ID |
-            stream::QPACK_DECODER_STREAM_TYPE_ID => {
-                conn.stream_priority(stream_id, 0, false)?;
-            },
+        self.max_push_id = Some(push_id);
 
-            // TODO: Server push
-            stream::HTTP3_PUSH_STREAM_TYPE_ID => (),
+        Ok(())
+    }
 
-            // Anything else is a GREASE stream, so make it the least important.
-            _ => {
-                conn.stream_priority(stream_id, 255, false)?;
-            },
+    /// Cancels a server push.
+    ///
+    /// If the push stream carrying the pushed response wasn't opened yet, a
+    /// CANCEL_PUSH frame is sent to the peer. Otherwise the push stream is
+    /// shut down with the H3_REQUEST_CANCELLED error code, which causes the
+    /// server to stop sending the pushed response, or the client to stop
+    /// receiving it.
+    ///
+    /// The [`IdError`] error is returned when the push ID was never promised by
+    /// a server, or is above the MAX_PUSH_ID limit sent by a client.
+    ///
+    /// The [`Done`] error is returned when the push was already cancelled or
+    /// completed.
+    ///
+    /// The [`StreamBlocked`] error is returned when the control stream doesn't
+    /// have enough capacity for the operation to complete. When this happens
+    /// the application should retry the operation later on.
+    ///
+    /// [`IdError`]: enum.Error.html#variant.IdError
+    /// [`Done`]: enum.Error.html#variant.Done
+    /// [`StreamBlocked`]: enum.Error.html#variant.StreamBlocked
+    pub fn cancel_push<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, push_id: u64,
+    ) -> Result<()> {
+        if !self.is_push_id_valid(push_id) {
+            return Err(Error::IdError);
         }
 
-        conn.stream_send(stream_id, b.put_varint(ty)?, false)?;
+        let push_stream_id = match self.pushes.get(&push_id) {
+            Some(v) if v.cancelled => return Err(Error::Done),
 
-        // To avoid skipping stream IDs, we only calculate the next available
-        // stream ID when data has been successfully buffered.
-        self.next_uni_stream_id = self
-            .next_uni_stream_id
-            .checked_add(4)
-            .ok_or(Error::IdError)?;
+            Some(v) => v.stream_id,
 
-        Ok(stream_id)
-    }
+            // Clients might cancel a push before receiving its promise.
+            None if !self.is_server && !self.is_push_finished(push_id) => None,
 
-    fn open_qpack_encoder_stream<F: BufFactory>(
-        &mut self, conn: &mut super::Connection<F>,
-    ) -> Result<()> {
-        let stream_id =
-            self.open_uni_stream(conn, stream::QPACK_ENCODER_STREAM_TYPE_ID)?;
+            None => return Err(Error::Done),
+        };
 
-        self.local_qpack_streams.encoder_stream_id = Some(stream_id);
+        match push_stream_id {
+            Some(stream_id) => {
+                let direction = if self.is_server {
+                    crate::Shutdown::Write
+                } else {
+                    crate::Shutdown::Read
+                };
 
-        qlog_with_type!(QLOG_STREAM_TYPE_SET, conn.qlog, q, {
-            let ev_data = EventData::H3StreamTypeSet(H3StreamTypeSet {
-                stream_id,
-                owner: Some(H3Owner::Local),
-                stream_type: H3StreamType::QpackEncode,
-                ..Default::default()
-            });
+                // The stream might have already been completed, so ignore
+                // errors.
+                conn.stream_shutdown(
+                    stream_id,
+                    direction,
+                    Error::RequestCancelled.to_wire(),
+                )
+                .ok();
 
-            q.add_event_data_now(ev_data).ok();
-        });
+                if self.is_server {
+                    self.streams.remove(&stream_id);
+                } else if let Some(s) = self.streams.get_mut(&stream_id) {
+                    // Don't report any more events for the stream.
+                    s.finished();
+                }
+            },
+
+            None => self.send_control_frame(conn, &frame::Frame::CancelPush {
+                push_id,
+            })?,
+        }
+
+        self.cancel_push_state(push_id);
 
         Ok(())
     }
 
-    fn open_qpack_decoder_stream<F: BufFactory>(
-        &mut self, conn: &mut super::Connection<F>,
-    ) -> Result<()> {
-        let stream_id =
-            self.open_uni_stream(conn, stream::QPACK_DECODER_STREAM_TYPE_ID)?;
-
-        self.local_qpack_streams.decoder_stream_id = Some(stream_id);
+    /// Opens a WebTransport session.
+    ///
+    /// The `headers` are sent as an extended CONNECT request, which must
+    /// include the `:protocol` pseudo-header set to `webtransport`. The
+    /// request stream is left open and its ID, which is also the session ID,
+    /// is returned. The session is established once the server responds with
+    /// a 2xx status, which is reported with a [`Headers`] event.
+    ///
+    /// The [`FrameUnexpected`] error is returned when the peer didn't enable
+    /// WebTransport support, as reported by [`webtransport_enabled_by_peer()`].
+    ///
+    /// [`Headers`]: enum.Event.html#variant.Headers
+    /// [`FrameUnexpected`]: enum.Error.html#variant.FrameUnexpected
+    /// [`webtransport_enabled_by_peer()`]: struct.Connection.html#method.webtransport_enabled_by_peer
+    pub fn open_webtransport_session<T: NameValue, F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, headers: &[T],
+    ) -> Result<u64> {
+        if self.is_server || !self.webtransport_enabled_by_peer(conn) {
+            return Err(Error::FrameUnexpected);
+        }
 
-        qlog_with_type!(QLOG_STREAM_TYPE_SET, conn.qlog, q, {
-            let ev_data = EventData::H3StreamTypeSet(H3StreamTypeSet {
-                stream_id,
-                owner: Some(H3Owner::Local),
-                stream_type: H3StreamType::QpackDecode,
-                ..Default::default()
-            });
+        let session_id = self.send_request(conn, headers, false)?;
 
-            q.add_event_data_now(ev_data).ok();
-        });
+        self.webtransport_sessions
+            .insert(session_id, WebTransportSession::default());
 
-        Ok(())
+        Ok(session_id)
     }
 
-    /// Send GREASE frames on the provided stream ID.
-    fn send_grease_frames<F: BufFactory>(
-        &mut self, conn: &mut super::Connection<F>, stream_id: u64,
+    /// Accepts a WebTransport session.
+    ///
+    /// The `headers` are sent as the response to the extended CONNECT request
+    /// received on `stream_id`, and should include a 2xx status. The stream is
+    /// left open and its ID is used as the session ID from then on. Streams
+    /// the peer opened for the session before it was accepted are reported by
+    /// [`poll()`] right after.
+    ///
+    /// The [`FrameUnexpected`] error is returned when WebTransport isn't
+    /// enabled locally, or when the session was already accepted.
+    ///
+    /// The [`RequestRejected`] error is returned when accepting the session
+    /// would exceed the limit set with [`set_webtransport_max_sessions()`].
+    /// The application should respond to the request with an error status
+    /// instead.
+    ///
+    /// [`poll()`]: struct.Connection.html#method.poll
+    /// [`FrameUnexpected`]: enum.Error.html#variant.FrameUnexpected
+    /// [`RequestRejected`]: enum.Error.html#variant.RequestRejected
+    /// [`set_webtransport_max_sessions()`]: struct.Config.html#method.set_webtransport_max_sessions
+    pub fn accept_webtransport_session<T: NameValue, F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, stream_id: u64, headers: &[T],
     ) -> Result<()> {
-        let mut d = [0; 8];
+        if !self.is_server ||
+            !self.webtransport_enabled() ||
+            self.webtransport_sessions.contains_key(&stream_id)
+        {
+            return Err(Error::FrameUnexpected);
+        }
 
-        let stream_cap = match conn.stream_capacity(stream_id) {
-            Ok(v) => v,
+        let max_sessions = self.local_settings.wt_max_sessions.unwrap_or(0);
 
-            Err(e) => {
-                if conn.stream_finished(stream_id) {
-                    self.streams.remove(&stream_id);
-                }
+        if self.webtransport_sessions.len() as u64 >= max_sessions {
+            return Err(Error::RequestRejected);
+        }
 
-                return Err(e.into());
-            },
-        };
+        self.send_response(conn, stream_id, headers, false)?;
 
-        let grease_frame1 = grease_value();
-        let grease_frame2 = grease_value();
-        let grease_payload = b"GREASE is the word";
+        self.webtransport_sessions
+            .insert(stream_id, WebTransportSession::default());
 
-        let overhead = octets::varint_len(grease_frame1) + // frame type
-            1 + // payload len
-            octets::varint_len(grease_frame2) + // frame type
-            1 + // payload len
-            grease_payload.len(); // payload
+        let (accepted, pending): (Vec<_>, Vec<_>) = self
+            .pending_webtransport_streams
+            .drain(..)
+            .partition(|(_, session_id)| *session_id == stream_id);
 
-        // Don't send GREASE if there is not enough capacity for it. Greasing
-        // will _not_ be attempted again later on.
-        if stream_cap < overhead {
-            return Ok(());
-        }
+        self.pending_webtransport_streams = pending;
+        self.webtransport_events.extend(accepted.into_iter().map(
+            |(stream_id, session_id)| {
+                (stream_id, Event::WebTransportStream { session_id })
+            },
+        ));
 
-        // Empty GREASE frame.
-        let mut b = octets::OctetsMut::with_slice(&mut d);
-        conn.stream_send(stream_id, b.put_varint(grease_frame1)?, false)?;
+        Ok(())
+    }
 
-        let mut b = octets::OctetsMut::with_slice(&mut d);
-        conn.stream_send(stream_id, b.put_varint(0)?, false)?;
+    /// Opens a new stream associated with a WebTransport session.
+    ///
+    /// On success the new stream's ID is returned. Data is sent on the stream
+    /// using the transport's [`stream_send()`] method, without any HTTP/3
+    /// framing. Data received on a bidirectional stream is reported with
+    /// [`Data`] events, and read using the [`recv_body()`] method.
+    ///
+    /// The [`IdError`] error is returned when `session_id` doesn't refer to an
+    /// established session.
+    ///
+    /// The [`StreamBlocked`] error is returned when the peer's stream limit
+    /// doesn't allow a new stream to be opened. When this happens the
+    /// application should retry the operation later on.
+    ///
+    /// [`stream_send()`]: ../struct.Connection.html#method.stream_send
+    /// [`Data`]: enum.Event.html#variant.Data
+    /// [`recv_body()`]: struct.Connection.html#method.recv_body
+    /// [`IdError`]: enum.Error.html#variant.IdError
+    /// [`StreamBlocked`]: enum.Error.html#variant.StreamBlocked
+    pub fn open_webtransport_stream<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, session_id: u64, bidi: bool,
+    ) -> Result<u64> {
+        if !self.webtransport_session_open(session_id) {
+            return Err(Error::IdError);
+        }
+
+        let mut d = [0; 16];
+        let mut b = octets::OctetsMut::with_slice(&mut d);
+
+        let stream_id = if bidi {
+            let stream_id = self.next_request_stream_id;
+
+            b.put_varint(frame::WEBTRANSPORT_STREAM_FRAME_TYPE_ID)?;
+            b.put_varint(session_id)?;
+
+            let off = b.off();
+
+            match conn.stream_send(stream_id, &d[..off], false) {
+                Ok(_) => (),
+
+                Err(super::Error::Done | super::Error::StreamLimit) =>
+                    return Err(Error::StreamBlocked),
+
+                Err(e) => return Err(e.into()),
+            };
+
+            self.next_request_stream_id = self
+                .next_request_stream_id
+                .checked_add(4)
+                .ok_or(Error::IdError)?;
+
+            stream_id
+        } else {
+            let stream_id = match self
+                .open_uni_stream(conn, stream::WEBTRANSPORT_STREAM_TYPE_ID)
+            {
+                Ok(v) => v,
+
+                Err(Error::TransportError(
+                    super::Error::Done | super::Error::StreamLimit,
+                )) => return Err(Error::StreamBlocked),
+
+                Err(e) => return Err(e),
+            };
+
+            conn.stream_send(stream_id, b.put_varint(session_id)?, false)?;
+
+            stream_id
+        };
+
+        self.streams.insert(
+            stream_id,
+            stream::Stream::new_webtransport(stream_id, session_id),
+        );
+
+        Ok(stream_id)
+    }
+
+    /// Sends a datagram associated with a WebTransport session.
+    ///
+    /// The [`IdError`] error is returned when `session_id` doesn't refer to an
+    /// established session. Errors from the transport's [`dgram_send()`]
+    /// method are returned as is.
+    ///
+    /// [`IdError`]: enum.Error.html#variant.IdError
+    /// [`dgram_send()`]: ../struct.Connection.html#method.dgram_send
+    pub fn send_webtransport_datagram<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, session_id: u64,
+        payload: &[u8],
+    ) -> Result<()> {
+        if !self.webtransport_session_open(session_id) {
+            return Err(Error::IdError);
+        }
+
+        self.send_http_datagram(conn, session_id, payload)
+    }
+
+    /// Closes a WebTransport session.
+    ///
+    /// A CLOSE_WEBTRANSPORT_SESSION capsule carrying `error_code` and `reason`
+    /// is sent on the session's CONNECT stream, which is then finished, and
+    /// all the streams associated with the session are reset.
+    ///
+    /// The [`Done`] error is returned when the session doesn't exist or was
+    /// already closed.
+    ///
+    /// The [`MessageError`] error is returned when `reason` is longer than 1024
+    /// bytes.
+    ///
+    /// The [`StreamBlocked`] error is returned when the CONNECT stream doesn't
+    /// have enough capacity for the operation to complete. When this happens
+    /// the application should retry the operation later on.
+    ///
+    /// [`Done`]: enum.Error.html#variant.Done
+    /// [`MessageError`]: enum.Error.html#variant.MessageError
+    /// [`StreamBlocked`]: enum.Error.html#variant.StreamBlocked
+    pub fn close_webtransport_session<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, session_id: u64,
+        error_code: u32, reason: &[u8],
+    ) -> Result<()> {
+        if !self.webtransport_session_open(session_id) {
+            return Err(Error::Done);
+        }
+
+        if reason.len() > capsule::MAX_CLOSE_REASON_LEN {
+            return Err(Error::MessageError);
+        }
+
+        let capsule = Capsule::CloseWebTransportSession {
+            error_code,
+            reason: reason.to_vec(),
+        };
+
+        self.send_capsule(conn, session_id, &capsule, true)?;
+
+        if let Some(session) = self.webtransport_sessions.get_mut(&session_id) {
+            session.closed = true;
+        }
+
+        self.reset_webtransport_streams(conn, session_id);
+
+        Ok(())
+    }
+
+    /// Asks the peer to gracefully wind down a WebTransport session.
+    ///
+    /// A DRAIN_WEBTRANSPORT_SESSION capsule is sent on the session's CONNECT
+    /// stream. The session can still be used after this, until it's closed.
+    ///
+    /// The [`Done`] error is returned when the session doesn't exist or was
+    /// already closed.
+    ///
+    /// The [`StreamBlocked`] error is returned when the CONNECT stream doesn't
+    /// have enough capacity for the operation to complete. When this happens
+    /// the application should retry the operation later on.
+    ///
+    /// [`Done`]: enum.Error.html#variant.Done
+    /// [`StreamBlocked`]: enum.Error.html#variant.StreamBlocked
+    pub fn drain_webtransport_session<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, session_id: u64,
+    ) -> Result<()> {
+        if !self.webtransport_session_open(session_id) {
+            return Err(Error::Done);
+        }
+
+        self.send_capsule(
+            conn,
+            session_id,
+            &Capsule::DrainWebTransportSession,
+            false,
+        )
+    }
+
+    /// Gets the raw settings from peer including unknown and reserved types.
+    ///
+    /// The order of settings is the same as received in the SETTINGS frame.
+    pub fn peer_settings_raw(&self) -> Option<&[(u64, u64)]> {
+        self.peer_settings.raw.as_deref()
+    }
+
+    fn open_uni_stream<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, ty: u64,
+    ) -> Result<u64> {
+        let stream_id = self.next_uni_stream_id;
+
+        let mut d = [0; 8];
+        let mut b = octets::OctetsMut::with_slice(&mut d);
+
+        match ty {
+            // Control and QPACK streams are the most important to schedule.
+            stream::HTTP3_CONTROL_STREAM_TYPE_ID |
+            stream::QPACK_ENCODER_STREAM_TYPE_ID |
+            stream::QPACK_DECODER_STREAM_TYPE_ID => {
+                conn.stream_priority(stream_id, 0, false)?;
+            },
+
+            // Push and WebTransport streams carry application data, so they
+            // use the default priority like request streams do.
+            stream::HTTP3_PUSH_STREAM_TYPE_ID |
+            stream::WEBTRANSPORT_STREAM_TYPE_ID => (),
+
+            // Anything else is a GREASE stream, so make it the least important.
+            _ => {
+                conn.stream_priority(stream_id, 255, false)?;
+            },
+        }
+
+        conn.stream_send(stream_id, b.put_varint(ty)?, false)?;
+
+        // To avoid skipping stream IDs, we only calculate the next available
+        // stream ID when data has been successfully buffered.
+        self.next_uni_stream_id = self
+            .next_uni_stream_id
+            .checked_add(4)
+            .ok_or(Error::IdError)?;
+
+        Ok(stream_id)
+    }
+
+    /// Opens a push stream for the given push ID.
+    fn open_push_stream<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, push_id: u64,
+    ) -> Result<u64> {
+        let stream_id =
+            self.open_uni_stream(conn, stream::HTTP3_PUSH_STREAM_TYPE_ID)?;
+
+        let mut d = [0; 8];
+        let mut b = octets::OctetsMut::with_slice(&mut d);
+
+        conn.stream_send(stream_id, b.put_varint(push_id)?, false)?;
+
+        self.streams
+            .insert(stream_id, stream::Stream::new_push(stream_id, push_id));
+
+        if let Some(push) = self.pushes.get_mut(&push_id) {
+            push.stream_id = Some(stream_id);
+        }
+
+        qlog_with_type!(QLOG_STREAM_TYPE_SET, conn.qlog, q, {
+            let ev_data = EventData::H3StreamTypeSet(H3StreamTypeSet {
+                stream_id,
+                owner: Some(H3Owner::Local),
+                stream_type: H3StreamType::Push,
+                associated_push_id: Some(push_id),
+                ..Default::default()
+            });
+
+            q.add_event_data_now(ev_data).ok();
+        });
+
+        Ok(stream_id)
+    }
+
+    fn open_qpack_encoder_stream<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>,
+    ) -> Result<()> {
+        let stream_id =
+            self.open_uni_stream(conn, stream::QPACK_ENCODER_STREAM_TYPE_ID)?;
+
+        self.local_qpack_streams.encoder_stream_id = Some(stream_id);
+
+        qlog_with_type!(QLOG_STREAM_TYPE_SET, conn.qlog, q, {
+            let ev_data = EventData::H3StreamTypeSet(H3StreamTypeSet {
+                stream_id,
+                owner: Some(H3Owner::Local),
+                stream_type: H3StreamType::QpackEncode,
+                ..Default::default()
+            });
+
+            q.add_event_data_now(ev_data).ok();
+        });
+
+        Ok(())
+    }
+
+    fn open_qpack_decoder_stream<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>,
+    ) -> Result<()> {
+        let stream_id =
+            self.open_uni_stream(conn, stream::QPACK_DECODER_STREAM_TYPE_ID)?;
+
+        self.local_qpack_streams.decoder_stream_id = Some(stream_id);
+
+        qlog_with_type!(QLOG_STREAM_TYPE_SET, conn.qlog, q, {
+            let ev_data = EventData::H3StreamTypeSet(H3StreamTypeSet {
+                stream_id,
+                owner: Some(H3Owner::Local),
+                stream_type: H3StreamType::QpackDecode,
+                ..Default::default()
+            });
+
+            q.add_event_data_now(ev_data).ok();
+        });
+
+        Ok(())
+    }
+
+    /// Sends a frame on the local control stream.
+    fn send_control_frame<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, frame: &frame::Frame,
+    ) -> Result<()> {
+        let stream_id = self.control_stream_id.ok_or(Error::InternalError)?;
+
+        let mut d = [42; 16];
+        let mut b = octets::OctetsMut::with_slice(&mut d);
+
+        let wire_len = frame.to_bytes(&mut b)?;
+        let stream_cap = conn.stream_capacity(stream_id)?;
+
+        if stream_cap < wire_len {
+            return Err(Error::StreamBlocked);
+        }
+
+        trace!("{} tx frm {:?}", conn.trace_id(), frame);
+
+        qlog_with_type!(QLOG_FRAME_CREATED, conn.qlog, q, {
+            let ev_data = EventData::H3FrameCreated(H3FrameCreated {
+                stream_id,
+                frame: frame.to_qlog(),
+                ..Default::default()
+            });
+
+            q.add_event_data_now(ev_data).ok();
+        });
+
+        conn.stream_send(stream_id, &d[..wire_len], false)?;
+
+        Ok(())
+    }
+
+    /// Send GREASE frames on the provided stream ID.
+    fn send_grease_frames<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, stream_id: u64,
+    ) -> Result<()> {
+        let mut d = [0; 8];
+
+        let stream_cap = match conn.stream_capacity(stream_id) {
+            Ok(v) => v,
+
+            Err(e) => {
+                if conn.stream_finished(stream_id) {
+                    self.streams.remove(&stream_id);
+                }
+
+                return Err(e.into());
+            },
+        };
+
+        let grease_frame1 = grease_value();
+        let grease_frame2 = grease_value();
+        let grease_payload = b"GREASE is the word";
+
+        let overhead = octets::varint_len(grease_frame1) + // frame type
+            1 + // payload len
+            octets::varint_len(grease_frame2) + // frame type
+            1 + // payload len
+            grease_payload.len(); // payload
+
+        // Don't send GREASE if there is not enough capacity for it. Greasing
+        // will _not_ be attempted again later on.
+        if stream_cap < overhead {
+            return Ok(());
+        }
+
+        // Empty GREASE frame.
+        let mut b = octets::OctetsMut::with_slice(&mut d);
+        conn.stream_send(stream_id, b.put_varint(grease_frame1)?, false)?;
+
+        let mut b = octets::OctetsMut::with_slice(&mut d);
+        conn.stream_send(stream_id, b.put_varint(0)?, false)?;
 
         trace!(
             "{} tx frm GREASE stream={} len=0",
@@ -2397,7 +3518,25 @@ impl Connection {
             None
         };
 
-        let frame = frame::Frame::Settings {
+        // WebTransport settings are not known to the frame codec, so they are
+        // sent along with the application's additional settings.
+        let additional_settings = match self.local_settings.wt_max_sessions {
+            Some(v) => {
+                let mut settings = self
+                    .local_settings
+                    .additional_settings
+                    .clone()
+                    .unwrap_or_default();
+
+                settings.push((frame::SETTINGS_WT_MAX_SESSIONS, v));
+
+                Some(settings)
+            },
+
+            None => self.local_settings.additional_settings.clone(),
+        };
+
+        let frame = frame::Frame::Settings {
             max_field_section_size: self.local_settings.max_field_section_size,
             qpack_max_table_capacity: self
                 .local_settings
@@ -2408,7 +3547,7 @@ impl Connection {
                 .connect_protocol_enabled,
             h3_datagram: self.local_settings.h3_datagram,
             grease,
-            additional_settings: self.local_settings.additional_settings.clone(),
+            additional_settings,
             raw: Default::default(),
         };
 
@@ -2489,7 +3628,18 @@ impl Connection {
                         Err(_) => continue,
                     };
 
-                    let ty = stream::Type::deserialize(varint)?;
+                    let ty = match stream::Type::deserialize(varint)? {
+                        // WebTransport streams are treated like unknown
+                        // streams unless support was enabled.
+                        stream::Type::WebTransport
+                            if self
+                                .local_settings
+                                .wt_max_sessions
+                                .is_none_or(|v| v == 0) =>
+                            stream::Type::Unknown,
+
+                        ty => ty,
+                    };
 
                     if let Err(e) = stream.set_ty(ty) {
                         conn.close(true, e.to_wire(), b"")?;
@@ -2601,6 +3751,9 @@ impl Connection {
                             // TODO: we MAY send STOP_SENDING
                         },
 
+                        // The session ID is read next.
+                        stream::Type::WebTransport => (),
+
                         stream::Type::Request => unreachable!(),
                     }
                 },
@@ -2618,6 +3771,26 @@ impl Connection {
                         conn.close(true, e.to_wire(), b"")?;
                         return Err(e);
                     }
+
+                    return self.process_push_stream(conn, stream_id, varint);
+                },
+
+                stream::State::WebTransportSessionId => {
+                    stream.try_fill_buffer(conn)?;
+
+                    let varint = match stream.try_consume_varint() {
+                        Ok(v) => v,
+
+                        Err(_) => continue,
+                    };
+
+                    if let Err(e) = stream.set_webtransport_session_id(varint) {
+                        conn.close(true, e.to_wire(), b"")?;
+                        return Err(e);
+                    }
+
+                    return self
+                        .process_webtransport_stream(conn, stream_id, varint);
                 },
 
                 stream::State::FrameType => {
@@ -2629,7 +3802,18 @@ impl Connection {
                         Err(_) => continue,
                     };
 
-                    match stream.set_frame_type(varint) {
+                    let res = if varint ==
+                        frame::WEBTRANSPORT_STREAM_FRAME_TYPE_ID &&
+                        self.local_settings
+                            .wt_max_sessions
+                            .is_some_and(|v| v > 0)
+                    {
+                        stream.set_webtransport()
+                    } else {
+                        stream.set_frame_type(varint)
+                    };
+
+                    match res {
                         Err(Error::FrameUnexpected) => {
                             let msg = format!("Unexpected frame type {varint}");
 
@@ -2738,12 +3922,53 @@ impl Connection {
                     };
                 },
 
+                stream::State::Data
+                    if self.webtransport_sessions.contains_key(&stream_id) =>
+                {
+                    // Do not emit events when not polling.
+                    if !polling {
+                        break;
+                    }
+
+                    // DATA frames on a WebTransport session's CONNECT stream
+                    // carry capsules, which are processed internally.
+                    let mut d = [0; 1024];
+
+                    let read = match stream.try_consume_data(conn, &mut d) {
+                        Ok((0, _)) | Err(Error::Done) => break,
+
+                        Ok((read, _)) => read,
+
+                        Err(e) => return Err(e),
+                    };
+
+                    self.process_webtransport_capsules(
+                        conn,
+                        stream_id,
+                        &d[..read],
+                    )?;
+
+                    if let Some(ev) = self.webtransport_events.pop_front() {
+                        return Ok(ev);
+                    }
+                },
+
                 stream::State::Data => {
                     // Do not emit events when not polling.
                     if !polling {
                         break;
                     }
 
+                    // Data on streams of WebTransport sessions that weren't
+                    // accepted yet is not reported.
+                    if stream.webtransport_session_id().is_some_and(|id| {
+                        self.webtransport_sessions
+                            .get(&id)
+                            .is_none_or(|s| s.closed)
+                    }) {
+                        break;
+                    }
+
                     if !stream.try_trigger_data_event() {
                         break;
                     }
@@ -2754,17 +3979,65 @@ impl Connection {
                 stream::State::QpackInstruction => {
                     let mut d = [0; 4096];
 
-                    // Read data from the stream and discard immediately.
+                    let ty = stream.ty();
+
                     loop {
-                        let (recv, fin) = conn.stream_recv(stream_id, &mut d)?;
+                        let (recv, fin) =
+                            match conn.stream_recv(stream_id, &mut d) {
+                                Ok(v) => v,
+
+                                Err(crate::Error::Done) => break,
 
-                        match stream.ty() {
-                            Some(stream::Type::QpackEncoder) =>
+                                Err(e) => return Err(e.into()),
+                            };
+
+                        match ty {
+                            Some(stream::Type::QpackEncoder) => {
                                 self.peer_qpack_streams.encoder_stream_bytes +=
-                                    recv as u64,
-                            Some(stream::Type::QpackDecoder) =>
+                                    recv as u64;
+
+                                if let Err(e) =
+                                    self.qpack_decoder.control(&d[..recv])
+                                {
+                                    trace!(
+                                        "{} QPACK encoder stream error: {:?}",
+                                        conn.trace_id(),
+                                        e
+                                    );
+
+                                    conn.close(
+                                        true,
+                                        Error::QpackEncoderStreamError.to_wire(),
+                                        b"Error processing QPACK encoder stream.",
+                                    )?;
+
+                                    return Err(Error::QpackEncoderStreamError);
+                                }
+                            },
+
+                            Some(stream::Type::QpackDecoder) => {
                                 self.peer_qpack_streams.decoder_stream_bytes +=
-                                    recv as u64,
+                                    recv as u64;
+
+                                if let Err(e) =
+                                    self.qpack_encoder.control(&d[..recv])
+                                {
+                                    trace!(
+                                        "{} QPACK decoder stream error: {:?}",
+                                        conn.trace_id(),
+                                        e
+                                    );
+
+                                    conn.close(
+                                        true,
+                                        Error::QpackDecoderStreamError.to_wire(),
+                                        b"Error processing QPACK decoder stream.",
+                                    )?;
+
+                                    return Err(Error::QpackDecoderStreamError);
+                                }
+                            },
+
                             _ => unreachable!(),
                         };
 
@@ -2772,6 +4045,21 @@ impl Connection {
                             close_conn_critical_stream(conn)?;
                         }
                     }
+
+                    break;
+                },
+
+                stream::State::QpackBlocked => {
+                    // No frames can be processed until the header block is
+                    // decoded, but the stream might have been reset in the
+                    // meantime.
+                    if let Err(e @ crate::Error::StreamReset(_)) =
+                        conn.stream_recv(stream_id, &mut [])
+                    {
+                        return Err(e.into());
+                    }
+
+                    break;
                 },
 
                 stream::State::Drain => {
@@ -2799,12 +4087,19 @@ impl Connection {
             None => return,
         };
 
-        if stream.state() == stream::State::Finished {
+        // Streams blocked on QPACK are finished once their headers are
+        // decoded.
+        if matches!(
+            stream.state(),
+            stream::State::Finished | stream::State::QpackBlocked
+        ) {
             return;
         }
 
         match stream.ty() {
-            Some(stream::Type::Request) | Some(stream::Type::Push) => {
+            Some(stream::Type::Request) |
+            Some(stream::Type::Push) |
+            Some(stream::Type::WebTransport) => {
                 stream.finished();
 
                 self.finished_streams.push_back(stream_id);
@@ -2814,226 +4109,890 @@ impl Connection {
         };
     }
 
-    fn process_frame<F: BufFactory>(
+    fn process_headers<F: BufFactory>(
         &mut self, conn: &mut super::Connection<F>, stream_id: u64,
-        frame: frame::Frame, payload_len: u64,
+        header_block: Vec<u8>, payload_len: u64,
     ) -> Result<(u64, Event)> {
-        trace!(
-            "{} rx frm {:?} stream={} payload_len={}",
-            conn.trace_id(),
-            frame,
+        let headers = self.decode_header_block(
+            conn,
             stream_id,
-            payload_len
-        );
-
-        qlog_with_type!(QLOG_FRAME_PARSED, conn.qlog, q, {
-            // HEADERS frames are special case and will be logged below.
-            if !matches!(frame, frame::Frame::Headers { .. }) {
-                let frame = frame.to_qlog();
-                let ev_data = EventData::H3FrameParsed(H3FrameParsed {
-                    stream_id,
-                    length: Some(payload_len),
-                    frame,
-                    ..Default::default()
-                });
+            frame::Frame::Headers { header_block },
+            payload_len,
+        )?;
 
-                q.add_event_data_now(ev_data).ok();
+        if self.is_server && is_extended_connect(&headers) {
+            if let Some(s) = self.streams.get_mut(&stream_id) {
+                s.set_extended_connect();
             }
-        });
+        }
 
-        match frame {
-            frame::Frame::Settings {
-                max_field_section_size,
-                qpack_max_table_capacity,
-                qpack_blocked_streams,
-                connect_protocol_enabled,
-                h3_datagram,
-                additional_settings,
-                raw,
-                ..
-            } => {
-                self.peer_settings = ConnectionSettings {
-                    max_field_section_size,
-                    qpack_max_table_capacity,
-                    qpack_blocked_streams,
-                    connect_protocol_enabled,
-                    h3_datagram,
-                    additional_settings,
-                    raw,
-                };
+        let more_frames = !conn.stream_finished(stream_id);
 
-                if let Some(1) = h3_datagram {
-                    // The peer MUST have also enabled DATAGRAM with a TP
-                    if conn.dgram_max_writable_len().is_none() {
-                        conn.close(
-                            true,
-                            Error::SettingsError.to_wire(),
-                            b"H3_DATAGRAM sent with value 1 but max_datagram_frame_size TP not set.",
-                        )?;
+        Ok((stream_id, Event::Headers {
+            list: headers,
+            more_frames,
+        }))
+    }
 
-                        return Err(Error::SettingsError);
-                    }
-                }
+    fn process_push_promise<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, stream_id: u64, push_id: u64,
+        header_block: Vec<u8>, payload_len: u64,
+    ) -> Result<(u64, Event)> {
+        let headers = self.decode_header_block(
+            conn,
+            stream_id,
+            frame::Frame::PushPromise {
+                push_id,
+                header_block,
             },
+            payload_len,
+        )?;
 
-            frame::Frame::Headers { header_block } => {
-                if Some(stream_id) == self.peer_control_stream_id {
-                    conn.close(
-                        true,
-                        Error::FrameUnexpected.to_wire(),
-                        b"HEADERS received on control stream",
-                    )?;
+        // The push already completed, so there is nothing left to promise.
+        if self.is_push_finished(push_id) {
+            return Err(Error::Done);
+        }
 
-                    return Err(Error::FrameUnexpected);
-                }
+        let push = self.pushes.entry(push_id).or_default();
 
-                // Servers reject too many HEADERS frames.
-                if let Some(s) = self.streams.get_mut(&stream_id) {
-                    if self.is_server && s.headers_received_count() == 2 {
-                        conn.close(
-                            true,
-                            Error::FrameUnexpected.to_wire(),
-                            b"Too many HEADERS frames",
-                        )?;
-                        return Err(Error::FrameUnexpected);
-                    }
+        match &push.promise {
+            // The same push can be promised on multiple request streams, but
+            // always with the same header fields.
+            Some(promise) if *promise != headers => {
+                conn.close(
+                    true,
+                    Error::GeneralProtocolError.to_wire(),
+                    b"PUSH_PROMISE received with mismatched header fields",
+                )?;
 
-                    s.increment_headers_received();
-                }
+                return Err(Error::GeneralProtocolError);
+            },
 
-                // Use "infinite" as default value for max_field_section_size if
-                // it is not configured by the application.
-                let max_size = self
-                    .local_settings
-                    .max_field_section_size
-                    .unwrap_or(u64::MAX);
+            Some(_) => (),
 
-                let headers = match self
-                    .qpack_decoder
-                    .decode(&header_block[..], max_size)
-                {
-                    Ok(v) => v,
+            None => push.promise = Some(headers.clone()),
+        }
 
-                    Err(e) => {
-                        let e = match e {
-                            qpack::Error::HeaderListTooLarge =>
-                                Error::ExcessiveLoad,
+        // Promises of pushes cancelled by the application are not reported.
+        if push.cancelled {
+            return Err(Error::Done);
+        }
 
-                            _ => Error::QpackDecompressionFailed,
-                        };
+        Ok((stream_id, Event::PushPromise {
+            push_id,
+            list: headers,
+        }))
+    }
 
-                        conn.close(true, e.to_wire(), b"Error parsing headers.")?;
+    /// Validates the push ID of a push stream opened by the peer.
+    fn process_push_stream<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, stream_id: u64, push_id: u64,
+    ) -> Result<(u64, Event)> {
+        if !self.is_push_id_valid(push_id) {
+            conn.close(
+                true,
+                Error::IdError.to_wire(),
+                b"Push stream opened with push ID above limit",
+            )?;
 
-                        return Err(e);
-                    },
-                };
+            return Err(Error::IdError);
+        }
 
-                qlog_with_type!(QLOG_FRAME_PARSED, conn.qlog, q, {
-                    let qlog_headers = headers
-                        .iter()
-                        .map(|h| qlog::events::h3::HttpHeader {
-                            name: String::from_utf8_lossy(h.name()).into_owned(),
-                            value: String::from_utf8_lossy(h.value())
-                                .into_owned(),
-                        })
-                        .collect();
-
-                    let frame = Http3Frame::Headers {
-                        headers: qlog_headers,
-                    };
+        let duplicate = self.is_push_finished(push_id) ||
+            self.pushes
+                .get(&push_id)
+                .is_some_and(|p| p.stream_id.is_some());
 
-                    let ev_data = EventData::H3FrameParsed(H3FrameParsed {
-                        stream_id,
-                        length: Some(payload_len),
-                        frame,
-                        ..Default::default()
-                    });
+        if duplicate {
+            conn.close(
+                true,
+                Error::IdError.to_wire(),
+                b"Push stream opened with duplicate push ID",
+            )?;
 
-                    q.add_event_data_now(ev_data).ok();
-                });
+            return Err(Error::IdError);
+        }
 
-                let more_frames = !conn.stream_finished(stream_id);
+        let push = self.pushes.entry(push_id).or_default();
 
-                return Ok((stream_id, Event::Headers {
-                    list: headers,
-                    more_frames,
-                }));
-            },
+        push.stream_id = Some(stream_id);
 
-            frame::Frame::Data { .. } => {
-                if Some(stream_id) == self.peer_control_stream_id {
-                    conn.close(
-                        true,
-                        Error::FrameUnexpected.to_wire(),
-                        b"DATA received on control stream",
-                    )?;
+        // Pushed responses that were cancelled by the application are
+        // discarded without being reported.
+        if push.cancelled {
+            conn.stream_shutdown(
+                stream_id,
+                crate::Shutdown::Read,
+                Error::RequestCancelled.to_wire(),
+            )?;
 
-                    return Err(Error::FrameUnexpected);
-                }
+            if let Some(s) = self.streams.get_mut(&stream_id) {
+                s.finished();
+            }
 
-                // Do nothing. The Data event is returned separately.
-            },
+            self.finish_push(push_id);
 
-            frame::Frame::GoAway { id } => {
-                if Some(stream_id) != self.peer_control_stream_id {
-                    conn.close(
-                        true,
-                        Error::FrameUnexpected.to_wire(),
-                        b"GOAWAY received on non-control stream",
-                    )?;
+            return Err(Error::Done);
+        }
 
-                    return Err(Error::FrameUnexpected);
-                }
+        Ok((stream_id, Event::PushStream { push_id }))
+    }
 
-                if !self.is_server && id % 4 != 0 {
-                    conn.close(
-                        true,
-                        Error::FrameUnexpected.to_wire(),
-                        b"GOAWAY received with ID of non-request stream",
-                    )?;
+    /// Decodes the header block of a HEADERS or PUSH_PROMISE frame.
+    ///
+    /// If the header block references QPACK dynamic table entries that haven't
+    /// been received yet, the frame is stored in the stream until they are,
+    /// and [`Done`] is returned.
+    ///
+    /// [`Done`]: enum.Error.html#variant.Done
+    fn decode_header_block<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, stream_id: u64,
+        frame: frame::Frame, payload_len: u64,
+    ) -> Result<Vec<Header>> {
+        let header_block = match &frame {
+            frame::Frame::Headers { header_block } |
+            frame::Frame::PushPromise { header_block, .. } => header_block,
 
-                    return Err(Error::IdError);
-                }
+            _ => unreachable!(),
+        };
 
-                if let Some(received_id) = self.peer_goaway_id {
-                    if id > received_id {
-                        conn.close(
-                            true,
-                            Error::IdError.to_wire(),
-                            b"GOAWAY received with ID larger than previously received",
-                        )?;
+        // Use "infinite" as default value for max_field_section_size if
+        // it is not configured by the application.
+        let max_size = self
+            .local_settings
+            .max_field_section_size
+            .unwrap_or(u64::MAX);
 
-                        return Err(Error::IdError);
-                    }
-                }
+        let headers = match self.qpack_decoder.decode_stream(
+            stream_id,
+            header_block,
+            max_size,
+        ) {
+            Ok(v) => v,
 
-                self.peer_goaway_id = Some(id);
+            // The header block references dynamic table entries that haven't
+            // been received yet, so put it aside until they are.
+            Err(qpack::Error::Blocked) => {
+                trace!(
+                    "{} stream {} blocked on QPACK dynamic table",
+                    conn.trace_id(),
+                    stream_id
+                );
 
-                return Ok((id, Event::GoAway));
-            },
+                if let Some(s) = self.streams.get_mut(&stream_id) {
+                    s.set_qpack_blocked(frame, payload_len);
+                }
 
-            frame::Frame::MaxPushId { push_id } => {
-                if Some(stream_id) != self.peer_control_stream_id {
-                    conn.close(
-                        true,
-                        Error::FrameUnexpected.to_wire(),
-                        b"MAX_PUSH_ID received on non-control stream",
-                    )?;
+                return Err(Error::Done);
+            },
 
-                    return Err(Error::FrameUnexpected);
-                }
+            Err(e) => {
+                let e = match e {
+                    qpack::Error::HeaderListTooLarge => Error::ExcessiveLoad,
 
-                if !self.is_server {
-                    conn.close(
-                        true,
-                        Error::FrameUnexpected.to_wire(),
-                        b"MAX_PUSH_ID received by client",
-                    )?;
+                    _ => Error::QpackDecompressionFailed,
+                };
 
-                    return Err(Error::FrameUnexpected);
-                }
+                conn.close(true, e.to_wire(), b"Error parsing headers.")?;
 
-                if push_id < self.max_push_id {
+                return Err(e);
+            },
+        };
+
+        self.send_qpack_decoder_instructions(conn)?;
+
+        qlog_with_type!(QLOG_FRAME_PARSED, conn.qlog, q, {
+            let qlog_headers = headers
+                .iter()
+                .map(|h| qlog::events::h3::HttpHeader {
+                    name: String::from_utf8_lossy(h.name()).into_owned(),
+                    value: String::from_utf8_lossy(h.value()).into_owned(),
+                })
+                .collect();
+
+            let frame = match frame {
+                frame::Frame::PushPromise { push_id, .. } =>
+                    Http3Frame::PushPromise {
+                        push_id,
+                        headers: qlog_headers,
+                    },
+
+                _ => Http3Frame::Headers {
+                    headers: qlog_headers,
+                },
+            };
+
+            let ev_data = EventData::H3FrameParsed(H3FrameParsed {
+                stream_id,
+                length: Some(payload_len),
+                frame,
+                ..Default::default()
+            });
+
+            q.add_event_data_now(ev_data).ok();
+        });
+
+        Ok(headers)
+    }
+
+    /// Decodes the header block of a stream that was blocked on QPACK dynamic
+    /// table updates.
+    fn process_unblocked_stream<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, stream_id: u64,
+    ) -> Result<(u64, Event)> {
+        let blocked = self
+            .streams
+            .get_mut(&stream_id)
+            .and_then(|s| s.take_qpack_blocked());
+
+        let (frame, payload_len) = match blocked {
+            Some(v) => v,
+
+            // The stream is gone, so stop waiting for it.
+            None => {
+                self.qpack_decoder.cancel_stream(stream_id);
+
+                return Err(Error::Done);
+            },
+        };
+
+        let ev = match frame {
+            frame::Frame::PushPromise {
+                push_id,
+                header_block,
+            } => self.process_push_promise(
+                conn,
+                stream_id,
+                push_id,
+                header_block,
+                payload_len,
+            ),
+
+            frame::Frame::Headers { header_block } =>
+                self.process_headers(conn, stream_id, header_block, payload_len),
+
+            _ => unreachable!(),
+        };
+
+        // The frame might have been the last one on the stream.
+        if conn.stream_finished(stream_id) {
+            self.process_finished_stream(stream_id);
+        }
+
+        ev
+    }
+
+    /// Returns whether the push ID is within the limits of the connection.
+    ///
+    /// Servers can only refer to push IDs they already promised, while clients
+    /// can refer to any push ID allowed by their MAX_PUSH_ID.
+    fn is_push_id_valid(&self, push_id: u64) -> bool {
+        if self.is_server {
+            push_id < self.next_push_id
+        } else {
+            self.max_push_id.is_some_and(|max| push_id <= max)
+        }
+    }
+
+    /// Records that a push was cancelled.
+    ///
+    /// Servers forget about the push entirely, while clients keep track of it
+    /// until its push stream is received, so that the stream can be discarded.
+    fn cancel_push_state(&mut self, push_id: u64) {
+        if self.is_server {
+            self.pushes.remove(&push_id);
+            return;
+        }
+
+        if self.is_push_finished(push_id) {
+            return;
+        }
+
+        let push = self.pushes.entry(push_id).or_default();
+
+        if push.stream_id.is_some() {
+            self.finish_push(push_id);
+        } else {
+            push.cancelled = true;
+        }
+    }
+
+    /// Forgets about a push whose push stream was received by the client.
+    fn finish_push(&mut self, push_id: u64) {
+        self.pushes.remove(&push_id);
+
+        if !self.is_server {
+            self.finished_pushes.push_item(push_id);
+        }
+    }
+
+    /// Returns whether the client already received and completed the push
+    /// stream of the given push.
+    fn is_push_finished(&self, push_id: u64) -> bool {
+        self.finished_pushes.iter().any(|r| r.contains(&push_id))
+    }
+
+    /// Removes a push stream received by the client once it completed, as
+    /// nothing else can be received on it.
+    fn collect_push_stream(&mut self, stream_id: u64) {
+        let is_push = self
+            .streams
+            .get(&stream_id)
+            .is_some_and(|s| s.ty() == Some(stream::Type::Push));
+
+        if !self.is_server && is_push {
+            self.remove_stream(stream_id);
+        }
+    }
+
+    /// Returns whether WebTransport support was enabled locally.
+    fn webtransport_enabled(&self) -> bool {
+        self.local_settings.wt_max_sessions.is_some_and(|v| v > 0)
+    }
+
+    /// Returns whether the given WebTransport session exists and wasn't
+    /// closed.
+    fn webtransport_session_open(&self, session_id: u64) -> bool {
+        self.webtransport_sessions
+            .get(&session_id)
+            .is_some_and(|s| !s.closed)
+    }
+
+    /// Resets all the streams associated with a WebTransport session.
+    fn reset_webtransport_streams<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, session_id: u64,
+    ) {
+        let mut stream_ids: Vec<u64> = self
+            .streams
+            .iter()
+            .filter(|(_, s)| s.webtransport_session_id() == Some(session_id))
+            .map(|(id, _)| *id)
+            .collect();
+
+        self.pending_webtransport_streams.retain(|(id, session)| {
+            if *session == session_id {
+                stream_ids.push(*id);
+            }
+
+            *session != session_id
+        });
+
+        self.webtransport_events.retain(|(_, ev)| {
+            !matches!(ev, Event::WebTransportStream { session_id: id }
+                if *id == session_id)
+        });
+
+        for stream_id in stream_ids {
+            // The stream might have already been completed, so ignore errors.
+            conn.stream_shutdown(
+                stream_id,
+                crate::Shutdown::Read,
+                WT_SESSION_GONE,
+            )
+            .ok();
+
+            conn.stream_shutdown(
+                stream_id,
+                crate::Shutdown::Write,
+                WT_SESSION_GONE,
+            )
+            .ok();
+
+            self.streams.remove(&stream_id);
+        }
+    }
+
+    /// Associates a stream opened by the peer with a WebTransport session.
+    fn process_webtransport_stream<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, stream_id: u64,
+        session_id: u64,
+    ) -> Result<(u64, Event)> {
+        trace!(
+            "{} rx WebTransport stream={} session={}",
+            conn.trace_id(),
+            stream_id,
+            session_id
+        );
+
+        // Sessions are always established on client-initiated bidirectional
+        // streams.
+        if session_id % 4 != 0 {
+            conn.close(
+                true,
+                Error::IdError.to_wire(),
+                b"Invalid WebTransport session ID",
+            )?;
+
+            return Err(Error::IdError);
+        }
+
+        if self.webtransport_session_open(session_id) {
+            return Ok((stream_id, Event::WebTransportStream { session_id }));
+        }
+
+        // Servers buffer streams for sessions that weren't accepted yet, as
+        // they might arrive before the session's CONNECT request.
+        if self.is_server && !self.webtransport_sessions.contains_key(&session_id)
+        {
+            self.pending_webtransport_streams
+                .push((stream_id, session_id));
+
+            return Err(Error::Done);
+        }
+
+        conn.stream_shutdown(
+            stream_id,
+            crate::Shutdown::Read,
+            WT_BUFFERED_STREAM_REJECTED,
+        )
+        .ok();
+
+        if crate::stream::is_bidi(stream_id) {
+            conn.stream_shutdown(
+                stream_id,
+                crate::Shutdown::Write,
+                WT_BUFFERED_STREAM_REJECTED,
+            )
+            .ok();
+        }
+
+        self.streams.remove(&stream_id);
+
+        Err(Error::Done)
+    }
+
+    /// Processes capsules received on a WebTransport session's CONNECT
+    /// stream.
+    ///
+    /// Resulting events are queued, to be returned by [`poll()`].
+    ///
+    /// [`poll()`]: struct.Connection.html#method.poll
+    fn process_webtransport_capsules<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, session_id: u64, data: &[u8],
+    ) -> Result<()> {
+        let session = match self.webtransport_sessions.get_mut(&session_id) {
+            Some(v) => v,
+
+            None => return Ok(()),
+        };
+
+        // Nothing is expected from the peer after the session is closed.
+        if session.closed {
+            return Ok(());
+        }
+
+        session.capsules.push(data);
+
+        while !session.closed {
+            let capsule = match session.capsules.next_capsule() {
+                Ok(v) => v,
+
+                Err(Error::Done) => break,
+
+                Err(e) => {
+                    conn.close(true, e.to_wire(), b"Invalid capsule")?;
+
+                    return Err(e);
+                },
+            };
+
+            trace!(
+                "{} rx capsule {:?} stream={}",
+                conn.trace_id(),
+                capsule,
+                session_id
+            );
+
+            let ev = match capsule {
+                Capsule::CloseWebTransportSession { error_code, reason } => {
+                    session.closed = true;
+
+                    Event::WebTransportSessionClosed { error_code, reason }
+                },
+
+                Capsule::DrainWebTransportSession =>
+                    Event::WebTransportSessionDrain,
+
+                Capsule::Datagram { payload } =>
+                    Event::WebTransportDatagram { payload },
+
+                // Other capsules are ignored.
+                _ => continue,
+            };
+
+            self.webtransport_events.push_back((session_id, ev));
+        }
+
+        if session.closed {
+            // Finish the CONNECT stream in turn. It might have already been
+            // finished, so ignore errors.
+            conn.stream_send(session_id, b"", true).ok();
+
+            self.reset_webtransport_streams(conn, session_id);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the first received datagram, if it's associated with a
+    /// WebTransport session.
+    ///
+    /// Other datagrams are left in the transport's queue for the application
+    /// to read.
+    fn process_webtransport_datagram<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>,
+    ) -> Option<(u64, Event)> {
+        if self.webtransport_sessions.is_empty() {
+            return None;
+        }
+
+        let mut d = [0; 8];
+
+        let len = conn.dgram_recv_peek(&mut d, 8).ok()?;
+
+        let mut b = octets::Octets::with_slice(&d[..len]);
+
+        let session_id = b.get_varint().ok()?.checked_mul(4)?;
+
+        if !self.webtransport_session_open(session_id) {
+            return None;
+        }
+
+        let mut payload = conn.dgram_recv_vec().ok()?;
+        payload.drain(..b.off());
+
+        Some((session_id, Event::WebTransportDatagram { payload }))
+    }
+
+    /// Discards a WebTransport session whose CONNECT stream was reset.
+    fn cancel_webtransport_session<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, stream_id: u64,
+    ) {
+        if self.webtransport_sessions.remove(&stream_id).is_some() {
+            self.reset_webtransport_streams(conn, stream_id);
+        }
+    }
+
+    /// Returns the event to report for a finished stream.
+    ///
+    /// When a WebTransport session's CONNECT stream is finished by the peer,
+    /// the session is closed.
+    fn finished_stream_event<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, stream_id: u64,
+    ) -> Event {
+        match self.webtransport_sessions.remove(&stream_id) {
+            Some(session) if !session.closed => {
+                conn.stream_send(stream_id, b"", true).ok();
+
+                self.reset_webtransport_streams(conn, stream_id);
+
+                Event::WebTransportSessionClosed {
+                    error_code: 0,
+                    reason: Vec::new(),
+                }
+            },
+
+            _ => {
+                self.collect_push_stream(stream_id);
+
+                Event::Finished
+            },
+        }
+    }
+
+    /// Returns whether a local stream is finished after sending its FIN.
+    ///
+    /// Nothing is ever received on local push streams, so they are finished
+    /// as soon as the FIN is sent.
+    fn local_stream_finished<F: BufFactory>(
+        &self, conn: &super::Connection<F>, stream_id: u64,
+    ) -> bool {
+        conn.stream_finished(stream_id) || !crate::stream::is_bidi(stream_id)
+    }
+
+    /// Removes a stream, along with the state of the push or WebTransport
+    /// session it carried, if any.
+    fn remove_stream(&mut self, stream_id: u64) {
+        let push_id = self.streams.remove(&stream_id).and_then(|s| s.push_id());
+
+        if let Some(push_id) = push_id {
+            self.finish_push(push_id);
+        }
+
+        self.webtransport_sessions.remove(&stream_id);
+    }
+
+    /// Notifies the QPACK decoder that a request or push stream was reset.
+    fn cancel_qpack_stream(&mut self, stream_id: u64) {
+        let ty = self.streams.get(&stream_id).and_then(|s| s.ty());
+
+        if matches!(ty, Some(stream::Type::Request | stream::Type::Push)) {
+            self.qpack_decoder.cancel_stream(stream_id);
+        }
+    }
+
+    /// Sends pending QPACK decoder instructions on the local decoder stream.
+    fn send_qpack_decoder_instructions<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>,
+    ) -> Result<()> {
+        let stream_id = match self.local_qpack_streams.decoder_stream_id {
+            Some(v) => v,
+
+            None => return Ok(()),
+        };
+
+        let instructions = self.qpack_decoder.pending_instructions();
+
+        if instructions.is_empty() {
+            return Ok(());
+        }
+
+        let written = match conn.stream_send(stream_id, instructions, false) {
+            Ok(v) => v,
+
+            Err(crate::Error::Done) => 0,
+
+            Err(e) => return Err(e.into()),
+        };
+
+        trace!(
+            "{} tx QPACK decoder instructions stream={} len={}",
+            conn.trace_id(),
+            stream_id,
+            written
+        );
+
+        self.qpack_decoder.instructions_sent(written);
+
+        Ok(())
+    }
+
+    /// Sends pending QPACK encoder instructions on the local encoder stream.
+    fn send_qpack_encoder_instructions<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>,
+    ) -> Result<()> {
+        let stream_id = match self.local_qpack_streams.encoder_stream_id {
+            Some(v) => v,
+
+            None => return Ok(()),
+        };
+
+        let instructions = self.qpack_encoder.pending_instructions();
+
+        if instructions.is_empty() {
+            return Ok(());
+        }
+
+        let written = match conn.stream_send(stream_id, instructions, false) {
+            Ok(v) => v,
+
+            Err(crate::Error::Done) => 0,
+
+            Err(e) => return Err(e.into()),
+        };
+
+        trace!(
+            "{} tx QPACK encoder instructions stream={} len={}",
+            conn.trace_id(),
+            stream_id,
+            written
+        );
+
+        self.qpack_encoder.instructions_sent(written);
+
+        Ok(())
+    }
+
+    fn process_frame<F: BufFactory>(
+        &mut self, conn: &mut super::Connection<F>, stream_id: u64,
+        frame: frame::Frame, payload_len: u64,
+    ) -> Result<(u64, Event)> {
+        trace!(
+            "{} rx frm {:?} stream={} payload_len={}",
+            conn.trace_id(),
+            frame,
+            stream_id,
+            payload_len
+        );
+
+        qlog_with_type!(QLOG_FRAME_PARSED, conn.qlog, q, {
+            // HEADERS and PUSH_PROMISE frames are special cases and will be
+            // logged once their header block is decoded.
+            if !matches!(
+                frame,
+                frame::Frame::Headers { .. } | frame::Frame::PushPromise { .. }
+            ) {
+                let frame = frame.to_qlog();
+                let ev_data = EventData::H3FrameParsed(H3FrameParsed {
+                    stream_id,
+                    length: Some(payload_len),
+                    frame,
+                    ..Default::default()
+                });
+
+                q.add_event_data_now(ev_data).ok();
+            }
+        });
+
+        match frame {
+            frame::Frame::Settings {
+                max_field_section_size,
+                qpack_max_table_capacity,
+                qpack_blocked_streams,
+                connect_protocol_enabled,
+                h3_datagram,
+                additional_settings,
+                raw,
+                ..
+            } => {
+                let wt_max_sessions =
+                    additional_settings.as_ref().and_then(|s| {
+                        s.iter()
+                            .find(|(id, _)| {
+                                *id == frame::SETTINGS_WT_MAX_SESSIONS
+                            })
+                            .map(|(_, v)| *v)
+                    });
+
+                self.peer_settings = ConnectionSettings {
+                    max_field_section_size,
+                    qpack_max_table_capacity,
+                    qpack_blocked_streams,
+                    connect_protocol_enabled,
+                    h3_datagram,
+                    wt_max_sessions,
+                    additional_settings,
+                    raw,
+                };
+
+                self.qpack_encoder.set_peer_limits(
+                    qpack_max_table_capacity.unwrap_or(0),
+                    qpack_blocked_streams.unwrap_or(0),
+                );
+
+                self.send_qpack_encoder_instructions(conn)?;
+
+                if let Some(1) = h3_datagram {
+                    // The peer MUST have also enabled DATAGRAM with a TP
+                    if conn.dgram_max_writable_len().is_none() {
+                        conn.close(
+                            true,
+                            Error::SettingsError.to_wire(),
+                            b"H3_DATAGRAM sent with value 1 but max_datagram_frame_size TP not set.",
+                        )?;
+
+                        return Err(Error::SettingsError);
+                    }
+                }
+            },
+
+            frame::Frame::Headers { header_block } => {
+                if Some(stream_id) == self.peer_control_stream_id {
+                    conn.close(
+                        true,
+                        Error::FrameUnexpected.to_wire(),
+                        b"HEADERS received on control stream",
+                    )?;
+
+                    return Err(Error::FrameUnexpected);
+                }
+
+                // Servers reject too many HEADERS frames.
+                if let Some(s) = self.streams.get_mut(&stream_id) {
+                    if self.is_server && s.headers_received_count() == 2 {
+                        conn.close(
+                            true,
+                            Error::FrameUnexpected.to_wire(),
+                            b"Too many HEADERS frames",
+                        )?;
+                        return Err(Error::FrameUnexpected);
+                    }
+
+                    s.increment_headers_received();
+                }
+
+                return self.process_headers(
+                    conn,
+                    stream_id,
+                    header_block,
+                    payload_len,
+                );
+            },
+
+            frame::Frame::Data { .. } => {
+                if Some(stream_id) == self.peer_control_stream_id {
+                    conn.close(
+                        true,
+                        Error::FrameUnexpected.to_wire(),
+                        b"DATA received on control stream",
+                    )?;
+
+                    return Err(Error::FrameUnexpected);
+                }
+
+                // Do nothing. The Data event is returned separately.
+            },
+
+            frame::Frame::GoAway { id } => {
+                if Some(stream_id) != self.peer_control_stream_id {
+                    conn.close(
+                        true,
+                        Error::FrameUnexpected.to_wire(),
+                        b"GOAWAY received on non-control stream",
+                    )?;
+
+                    return Err(Error::FrameUnexpected);
+                }
+
+                if !self.is_server && id % 4 != 0 {
+                    conn.close(
+                        true,
+                        Error::FrameUnexpected.to_wire(),
+                        b"GOAWAY received with ID of non-request stream",
+                    )?;
+
+                    return Err(Error::IdError);
+                }
+
+                if let Some(received_id) = self.peer_goaway_id {
+                    if id > received_id {
+                        conn.close(
+                            true,
+                            Error::IdError.to_wire(),
+                            b"GOAWAY received with ID larger than previously received",
+                        )?;
+
+                        return Err(Error::IdError);
+                    }
+                }
+
+                self.peer_goaway_id = Some(id);
+
+                return Ok((id, Event::GoAway));
+            },
+
+            frame::Frame::MaxPushId { push_id } => {
+                if Some(stream_id) != self.peer_control_stream_id {
+                    conn.close(
+                        true,
+                        Error::FrameUnexpected.to_wire(),
+                        b"MAX_PUSH_ID received on non-control stream",
+                    )?;
+
+                    return Err(Error::FrameUnexpected);
+                }
+
+                if !self.is_server {
+                    conn.close(
+                        true,
+                        Error::FrameUnexpected.to_wire(),
+                        b"MAX_PUSH_ID received by client",
+                    )?;
+
+                    return Err(Error::FrameUnexpected);
+                }
+
+                if self.max_push_id.is_some_and(|max| push_id < max) {
                     conn.close(
                         true,
                         Error::IdError.to_wire(),
@@ -3043,10 +5002,13 @@ impl Connection {
                     return Err(Error::IdError);
                 }
 
-                self.max_push_id = push_id;
+                self.max_push_id = Some(push_id);
             },
 
-            frame::Frame::PushPromise { .. } => {
+            frame::Frame::PushPromise {
+                push_id,
+                header_block,
+            } => {
                 if self.is_server {
                     conn.close(
                         true,
@@ -3067,10 +5029,26 @@ impl Connection {
                     return Err(Error::FrameUnexpected);
                 }
 
-                // TODO: implement more checks and PUSH_PROMISE event
+                if !self.is_push_id_valid(push_id) {
+                    conn.close(
+                        true,
+                        Error::IdError.to_wire(),
+                        b"PUSH_PROMISE received with push ID above limit",
+                    )?;
+
+                    return Err(Error::IdError);
+                }
+
+                return self.process_push_promise(
+                    conn,
+                    stream_id,
+                    push_id,
+                    header_block,
+                    payload_len,
+                );
             },
 
-            frame::Frame::CancelPush { .. } => {
+            frame::Frame::CancelPush { push_id } => {
                 if Some(stream_id) != self.peer_control_stream_id {
                     conn.close(
                         true,
@@ -3078,10 +5056,40 @@ impl Connection {
                         b"CANCEL_PUSH received on non-control stream",
                     )?;
 
-                    return Err(Error::FrameUnexpected);
+                    return Err(Error::FrameUnexpected);
+                }
+
+                if !self.is_push_id_valid(push_id) {
+                    conn.close(
+                        true,
+                        Error::IdError.to_wire(),
+                        b"CANCEL_PUSH received with invalid push ID",
+                    )?;
+
+                    return Err(Error::IdError);
+                }
+
+                // Servers stop sending the pushed response if they already
+                // started.
+                if self.is_server {
+                    let push_stream_id =
+                        self.pushes.get(&push_id).and_then(|p| p.stream_id);
+
+                    if let Some(push_stream_id) = push_stream_id {
+                        conn.stream_shutdown(
+                            push_stream_id,
+                            crate::Shutdown::Write,
+                            Error::RequestCancelled.to_wire(),
+                        )
+                        .ok();
+
+                        self.streams.remove(&push_stream_id);
+                    }
                 }
 
-                // TODO: implement CANCEL_PUSH frame
+                self.cancel_push_state(push_id);
+
+                return Ok((push_id, Event::CancelPush));
             },
 
             frame::Frame::PriorityUpdateRequest {
@@ -3213,6 +5221,12 @@ impl Connection {
     }
 }
 
+/// Returns whether the headers carry an extended CONNECT request, i.e. have a
+/// `:protocol` pseudo-header.
+fn is_extended_connect<T: NameValue>(headers: &[T]) -> bool {
+    headers.iter().any(|h| h.name() == b":protocol")
+}
+
 /// Generates an HTTP/3 GREASE variable length integer.
 pub fn grease_value() -> u64 {
     let n = super::rand::rand_u64_uniform(148_764_065_110_560_899);
@@ -4126,52 +6140,284 @@ mod tests {
 
         assert_eq!(s.send_body_server(0, true), Err(Error::FrameUnexpected));
 
-        assert_eq!(
-            s.send_body_server(s.server.control_stream_id.unwrap(), true),
-            Err(Error::FrameUnexpected)
-        );
+        assert_eq!(
+            s.send_body_server(s.server.control_stream_id.unwrap(), true),
+            Err(Error::FrameUnexpected)
+        );
+
+        assert_eq!(
+            s.send_body_server(
+                s.server.local_qpack_streams.encoder_stream_id.unwrap(),
+                true
+            ),
+            Err(Error::FrameUnexpected)
+        );
+
+        assert_eq!(
+            s.send_body_server(
+                s.server.local_qpack_streams.decoder_stream_id.unwrap(),
+                true
+            ),
+            Err(Error::FrameUnexpected)
+        );
+
+        assert_eq!(
+            s.send_body_server(s.server.peer_control_stream_id.unwrap(), true),
+            Err(Error::FrameUnexpected)
+        );
+
+        assert_eq!(
+            s.send_body_server(
+                s.server.peer_qpack_streams.encoder_stream_id.unwrap(),
+                true
+            ),
+            Err(Error::FrameUnexpected)
+        );
+
+        assert_eq!(
+            s.send_body_server(
+                s.server.peer_qpack_streams.decoder_stream_id.unwrap(),
+                true
+            ),
+            Err(Error::FrameUnexpected)
+        );
+    }
+
+    #[test]
+    /// Client sends request with body and trailers.
+    fn trailers() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let (stream, req) = s.send_request(false).unwrap();
+
+        let body = s.send_body_client(stream, false).unwrap();
+
+        let mut recv_buf = vec![0; body.len()];
+
+        let req_trailers = vec![Header::new(b"foo", b"bar")];
+
+        s.client
+            .send_additional_headers(
+                &mut s.pipe.client,
+                stream,
+                &req_trailers,
+                true,
+                true,
+            )
+            .unwrap();
+
+        s.advance().ok();
+
+        let ev_headers = Event::Headers {
+            list: req,
+            more_frames: true,
+        };
+
+        let ev_trailers = Event::Headers {
+            list: req_trailers,
+            more_frames: false,
+        };
+
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+
+        assert_eq!(s.poll_server(), Ok((stream, Event::Data)));
+        assert_eq!(s.recv_body_server(stream, &mut recv_buf), Ok(body.len()));
+
+        assert_eq!(s.poll_server(), Ok((stream, ev_trailers)));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+    }
+
+    #[test]
+    /// Server responds with a 103, then a 200 with no body.
+    fn informational_response() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let (stream, req) = s.send_request(true).unwrap();
+
+        assert_eq!(stream, 0);
+
+        let ev_headers = Event::Headers {
+            list: req,
+            more_frames: false,
+        };
+
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+
+        let info_resp = vec![
+            Header::new(b":status", b"103"),
+            Header::new(b"link", b"<https://example.com>; rel=\"preconnect\""),
+        ];
+
+        let resp = vec![
+            Header::new(b":status", b"200"),
+            Header::new(b"server", b"quiche-test"),
+        ];
+
+        s.server
+            .send_response(&mut s.pipe.server, stream, &info_resp, false)
+            .unwrap();
+
+        s.server
+            .send_additional_headers(
+                &mut s.pipe.server,
+                stream,
+                &resp,
+                false,
+                true,
+            )
+            .unwrap();
+
+        s.advance().ok();
+
+        let ev_info_headers = Event::Headers {
+            list: info_resp,
+            more_frames: true,
+        };
+
+        let ev_headers = Event::Headers {
+            list: resp,
+            more_frames: false,
+        };
+
+        assert_eq!(s.poll_client(), Ok((stream, ev_info_headers)));
+        assert_eq!(s.poll_client(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_client(), Ok((stream, Event::Finished)));
+        assert_eq!(s.poll_client(), Err(Error::Done));
+    }
+
+    #[test]
+    /// Server responds with a 103, then attempts to send a 200 using
+    /// send_response again, which should fail.
+    fn no_multiple_response() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let (stream, req) = s.send_request(true).unwrap();
+
+        assert_eq!(stream, 0);
+
+        let ev_headers = Event::Headers {
+            list: req,
+            more_frames: false,
+        };
+
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+
+        let info_resp = vec![
+            Header::new(b":status", b"103"),
+            Header::new(b"link", b"<https://example.com>; rel=\"preconnect\""),
+        ];
+
+        let resp = vec![
+            Header::new(b":status", b"200"),
+            Header::new(b"server", b"quiche-test"),
+        ];
+
+        s.server
+            .send_response(&mut s.pipe.server, stream, &info_resp, false)
+            .unwrap();
+
+        assert_eq!(
+            Err(Error::FrameUnexpected),
+            s.server
+                .send_response(&mut s.pipe.server, stream, &resp, true)
+        );
+
+        s.advance().ok();
+
+        let ev_info_headers = Event::Headers {
+            list: info_resp,
+            more_frames: true,
+        };
+
+        assert_eq!(s.poll_client(), Ok((stream, ev_info_headers)));
+        assert_eq!(s.poll_client(), Err(Error::Done));
+    }
+
+    #[test]
+    /// Server attempts to use send_additional_headers before initial response.
+    fn no_send_additional_before_initial_response() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let (stream, req) = s.send_request(true).unwrap();
+
+        assert_eq!(stream, 0);
+
+        let ev_headers = Event::Headers {
+            list: req,
+            more_frames: false,
+        };
+
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+
+        let info_resp = vec![
+            Header::new(b":status", b"103"),
+            Header::new(b"link", b"<https://example.com>; rel=\"preconnect\""),
+        ];
+
+        assert_eq!(
+            Err(Error::FrameUnexpected),
+            s.server.send_additional_headers(
+                &mut s.pipe.server,
+                stream,
+                &info_resp,
+                false,
+                false
+            )
+        );
+
+        s.advance().ok();
+
+        assert_eq!(s.poll_client(), Err(Error::Done));
+    }
+
+    #[test]
+    /// Client sends multiple HEADERS before data.
+    fn additional_headers_before_data_client() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let (stream, req) = s.send_request(false).unwrap();
+
+        let req_trailer = vec![Header::new(b"goodbye", b"world")];
 
         assert_eq!(
-            s.send_body_server(
-                s.server.local_qpack_streams.encoder_stream_id.unwrap(),
-                true
+            s.client.send_additional_headers(
+                &mut s.pipe.client,
+                stream,
+                &req_trailer,
+                true,
+                false
             ),
-            Err(Error::FrameUnexpected)
+            Ok(())
         );
 
-        assert_eq!(
-            s.send_body_server(
-                s.server.local_qpack_streams.decoder_stream_id.unwrap(),
-                true
-            ),
-            Err(Error::FrameUnexpected)
-        );
+        s.advance().ok();
 
-        assert_eq!(
-            s.send_body_server(s.server.peer_control_stream_id.unwrap(), true),
-            Err(Error::FrameUnexpected)
-        );
+        let ev_initial_headers = Event::Headers {
+            list: req,
+            more_frames: true,
+        };
 
-        assert_eq!(
-            s.send_body_server(
-                s.server.peer_qpack_streams.encoder_stream_id.unwrap(),
-                true
-            ),
-            Err(Error::FrameUnexpected)
-        );
+        let ev_trailing_headers = Event::Headers {
+            list: req_trailer,
+            more_frames: true,
+        };
 
-        assert_eq!(
-            s.send_body_server(
-                s.server.peer_qpack_streams.decoder_stream_id.unwrap(),
-                true
-            ),
-            Err(Error::FrameUnexpected)
-        );
+        assert_eq!(s.poll_server(), Ok((stream, ev_initial_headers)));
+        assert_eq!(s.poll_server(), Ok((stream, ev_trailing_headers)));
+        assert_eq!(s.poll_server(), Err(Error::Done));
     }
 
     #[test]
-    /// Client sends request with body and trailers.
-    fn trailers() {
+    /// Client sends multiple HEADERS before data.
+    fn data_after_trailers_client() {
         let mut s = Session::new().unwrap();
         s.handshake().unwrap();
 
@@ -4189,12 +6435,21 @@ mod tests {
                 stream,
                 &req_trailers,
                 true,
-                true,
+                false,
             )
             .unwrap();
 
         s.advance().ok();
 
+        s.send_frame_client(
+            frame::Frame::Data {
+                payload: vec![1, 2, 3, 4],
+            },
+            stream,
+            true,
+        )
+        .unwrap();
+
         let ev_headers = Event::Headers {
             list: req,
             more_frames: true,
@@ -4202,88 +6457,213 @@ mod tests {
 
         let ev_trailers = Event::Headers {
             list: req_trailers,
-            more_frames: false,
+            more_frames: true,
         };
 
         assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
-
         assert_eq!(s.poll_server(), Ok((stream, Event::Data)));
         assert_eq!(s.recv_body_server(stream, &mut recv_buf), Ok(body.len()));
-
         assert_eq!(s.poll_server(), Ok((stream, ev_trailers)));
-        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+        assert_eq!(s.poll_server(), Err(Error::FrameUnexpected));
     }
 
     #[test]
-    /// Server responds with a 103, then a 200 with no body.
-    fn informational_response() {
+    /// Send a MAX_PUSH_ID frame from the client on a valid stream.
+    fn max_push_id_from_client_good() {
         let mut s = Session::new().unwrap();
         s.handshake().unwrap();
 
-        let (stream, req) = s.send_request(true).unwrap();
+        s.send_frame_client(
+            frame::Frame::MaxPushId { push_id: 1 },
+            s.client.control_stream_id.unwrap(),
+            false,
+        )
+        .unwrap();
 
-        assert_eq!(stream, 0);
+        assert_eq!(s.poll_server(), Err(Error::Done));
+    }
+
+    #[test]
+    /// Send a MAX_PUSH_ID frame from the client on an invalid stream.
+    fn max_push_id_from_client_bad_stream() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let (stream, req) = s.send_request(false).unwrap();
+
+        s.send_frame_client(
+            frame::Frame::MaxPushId { push_id: 2 },
+            stream,
+            false,
+        )
+        .unwrap();
 
         let ev_headers = Event::Headers {
             list: req,
-            more_frames: false,
+            more_frames: true,
         };
 
         assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+        assert_eq!(s.poll_server(), Err(Error::FrameUnexpected));
+    }
 
-        let info_resp = vec![
-            Header::new(b":status", b"103"),
-            Header::new(b"link", b"<https://example.com>; rel=\"preconnect\""),
-        ];
+    #[test]
+    /// Send a sequence of MAX_PUSH_ID frames from the client that attempt to
+    /// reduce the limit.
+    fn max_push_id_from_client_limit_reduction() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
 
-        let resp = vec![
-            Header::new(b":status", b"200"),
-            Header::new(b"server", b"quiche-test"),
-        ];
+        s.send_frame_client(
+            frame::Frame::MaxPushId { push_id: 2 },
+            s.client.control_stream_id.unwrap(),
+            false,
+        )
+        .unwrap();
 
-        s.server
-            .send_response(&mut s.pipe.server, stream, &info_resp, false)
-            .unwrap();
+        s.send_frame_client(
+            frame::Frame::MaxPushId { push_id: 1 },
+            s.client.control_stream_id.unwrap(),
+            false,
+        )
+        .unwrap();
 
-        s.server
-            .send_additional_headers(
-                &mut s.pipe.server,
-                stream,
-                &resp,
-                false,
-                true,
-            )
-            .unwrap();
+        assert_eq!(s.poll_server(), Err(Error::IdError));
+    }
 
-        s.advance().ok();
+    #[test]
+    /// Send a MAX_PUSH_ID frame from the server, which is forbidden.
+    fn max_push_id_from_server() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
 
-        let ev_info_headers = Event::Headers {
-            list: info_resp,
+        s.send_frame_server(
+            frame::Frame::MaxPushId { push_id: 1 },
+            s.server.control_stream_id.unwrap(),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(s.poll_client(), Err(Error::FrameUnexpected));
+    }
+
+    #[test]
+    /// Send a PUSH_PROMISE frame from the client, which is forbidden.
+    fn push_promise_from_client() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let (stream, req) = s.send_request(false).unwrap();
+
+        let header_block = s.client.encode_header_block(stream, &req).unwrap();
+
+        s.send_frame_client(
+            frame::Frame::PushPromise {
+                push_id: 1,
+                header_block,
+            },
+            stream,
+            false,
+        )
+        .unwrap();
+
+        let ev_headers = Event::Headers {
+            list: req,
             more_frames: true,
         };
 
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Err(Error::FrameUnexpected));
+    }
+
+    #[test]
+    /// Send a CANCEL_PUSH frame from the client for a push that was never
+    /// promised.
+    fn cancel_push_from_client() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        s.send_frame_client(
+            frame::Frame::CancelPush { push_id: 1 },
+            s.client.control_stream_id.unwrap(),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(s.poll_server(), Err(Error::IdError));
+    }
+
+    #[test]
+    /// Send a CANCEL_PUSH frame from the client on an invalid stream.
+    fn cancel_push_from_client_bad_stream() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let (stream, req) = s.send_request(false).unwrap();
+
+        s.send_frame_client(
+            frame::Frame::CancelPush { push_id: 2 },
+            stream,
+            false,
+        )
+        .unwrap();
+
         let ev_headers = Event::Headers {
-            list: resp,
-            more_frames: false,
+            list: req,
+            more_frames: true,
         };
 
-        assert_eq!(s.poll_client(), Ok((stream, ev_info_headers)));
-        assert_eq!(s.poll_client(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_client(), Ok((stream, Event::Finished)));
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Err(Error::FrameUnexpected));
+    }
+
+    #[test]
+    /// Send a CANCEL_PUSH frame from the server.
+    fn cancel_push_from_server() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        s.client.send_max_push_id(&mut s.pipe.client, 1).unwrap();
+        s.advance().ok();
+
+        s.send_frame_server(
+            frame::Frame::CancelPush { push_id: 1 },
+            s.server.control_stream_id.unwrap(),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(s.poll_client(), Ok((1, Event::CancelPush)));
         assert_eq!(s.poll_client(), Err(Error::Done));
     }
 
     #[test]
-    /// Server responds with a 103, then attempts to send a 200 using
-    /// send_response again, which should fail.
-    fn no_multiple_response() {
+    /// Send a CANCEL_PUSH frame from the server for a push ID above the
+    /// client's limit.
+    fn cancel_push_from_server_above_limit() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        s.send_frame_server(
+            frame::Frame::CancelPush { push_id: 1 },
+            s.server.control_stream_id.unwrap(),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(s.poll_client(), Err(Error::IdError));
+    }
+
+    #[test]
+    /// Push a response from the server.
+    fn server_push() {
         let mut s = Session::new().unwrap();
         s.handshake().unwrap();
 
-        let (stream, req) = s.send_request(true).unwrap();
+        s.client.send_max_push_id(&mut s.pipe.client, 10).unwrap();
+        s.advance().ok();
 
-        assert_eq!(stream, 0);
+        let (stream, req) = s.send_request(true).unwrap();
 
         let ev_headers = Event::Headers {
             list: req,
@@ -4293,329 +6673,431 @@ mod tests {
         assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
         assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
 
-        let info_resp = vec![
-            Header::new(b":status", b"103"),
-            Header::new(b"link", b"<https://example.com>; rel=\"preconnect\""),
+        let push_req = vec![
+            Header::new(b":method", b"GET"),
+            Header::new(b":scheme", b"https"),
+            Header::new(b":authority", b"quic.tech"),
+            Header::new(b":path", b"/style.css"),
         ];
 
-        let resp = vec![
-            Header::new(b":status", b"200"),
-            Header::new(b"server", b"quiche-test"),
-        ];
+        assert_eq!(
+            s.server
+                .send_push_promise(&mut s.pipe.server, stream, &push_req),
+            Ok(0)
+        );
 
-        s.server
-            .send_response(&mut s.pipe.server, stream, &info_resp, false)
+        let resp = s.send_response(stream, true).unwrap();
+
+        let ev_promise = Event::PushPromise {
+            push_id: 0,
+            list: push_req,
+        };
+
+        let ev_headers = Event::Headers {
+            list: resp.clone(),
+            more_frames: false,
+        };
+
+        assert_eq!(s.poll_client(), Ok((stream, ev_promise)));
+        assert_eq!(s.poll_client(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_client(), Ok((stream, Event::Finished)));
+        assert_eq!(s.poll_client(), Err(Error::Done));
+
+        let push_stream = s
+            .server
+            .send_push_response(&mut s.pipe.server, 0, &resp, false)
             .unwrap();
 
+        // Push streams are server-initiated unidirectional streams.
+        assert_eq!(push_stream % 4, 3);
+
+        // Headers can only be sent once.
         assert_eq!(
-            Err(Error::FrameUnexpected),
             s.server
-                .send_response(&mut s.pipe.server, stream, &resp, true)
+                .send_push_response(&mut s.pipe.server, 0, &resp, false),
+            Err(Error::FrameUnexpected)
         );
 
-        s.advance().ok();
+        let body = s.send_body_server(push_stream, true).unwrap();
 
-        let ev_info_headers = Event::Headers {
-            list: info_resp,
+        let ev_headers = Event::Headers {
+            list: resp,
             more_frames: true,
         };
 
-        assert_eq!(s.poll_client(), Ok((stream, ev_info_headers)));
+        assert_eq!(
+            s.poll_client(),
+            Ok((push_stream, Event::PushStream { push_id: 0 }))
+        );
+        assert_eq!(s.poll_client(), Ok((push_stream, ev_headers)));
+        assert_eq!(s.poll_client(), Ok((push_stream, Event::Data)));
+
+        let mut recv_buf = vec![0; body.len()];
+        assert_eq!(
+            s.recv_body_client(push_stream, &mut recv_buf),
+            Ok(body.len())
+        );
+        assert_eq!(recv_buf, body);
+
+        assert_eq!(s.poll_client(), Ok((push_stream, Event::Finished)));
         assert_eq!(s.poll_client(), Err(Error::Done));
+
+        // The push is complete, so it can't be cancelled anymore.
+        assert!(!s.server.streams.contains_key(&push_stream));
+        assert!(s.server.pushes.is_empty());
+        assert_eq!(
+            s.server.cancel_push(&mut s.pipe.server, 0),
+            Err(Error::Done)
+        );
+
+        // The client forgot about the push as well.
+        assert!(!s.client.streams.contains_key(&push_stream));
+        assert!(s.client.pushes.is_empty());
+        assert_eq!(
+            s.client.cancel_push(&mut s.pipe.client, 0),
+            Err(Error::Done)
+        );
     }
 
     #[test]
-    /// Server attempts to use send_additional_headers before initial response.
-    fn no_send_additional_before_initial_response() {
+    /// Promise the same push on multiple request streams.
+    fn server_push_duplicate_promise() {
         let mut s = Session::new().unwrap();
         s.handshake().unwrap();
 
-        let (stream, req) = s.send_request(true).unwrap();
+        s.client.send_max_push_id(&mut s.pipe.client, 10).unwrap();
+        s.advance().ok();
 
-        assert_eq!(stream, 0);
+        let (stream1, req) = s.send_request(false).unwrap();
+        let (stream2, _) = s.send_request(false).unwrap();
 
         let ev_headers = Event::Headers {
-            list: req,
-            more_frames: false,
+            list: req.clone(),
+            more_frames: true,
         };
 
-        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+        assert_eq!(s.poll_server(), Ok((stream1, ev_headers.clone())));
+        assert_eq!(s.poll_server(), Ok((stream2, ev_headers)));
 
-        let info_resp = vec![
-            Header::new(b":status", b"103"),
-            Header::new(b"link", b"<https://example.com>; rel=\"preconnect\""),
+        let push_req = vec![
+            Header::new(b":method", b"GET"),
+            Header::new(b":scheme", b"https"),
+            Header::new(b":authority", b"quic.tech"),
+            Header::new(b":path", b"/style.css"),
         ];
 
-        assert_eq!(
-            Err(Error::FrameUnexpected),
-            s.server.send_additional_headers(
-                &mut s.pipe.server,
-                stream,
-                &info_resp,
-                false,
-                false
-            )
-        );
+        let push_id = s
+            .server
+            .send_push_promise(&mut s.pipe.server, stream1, &push_req)
+            .unwrap();
 
         s.advance().ok();
 
-        assert_eq!(s.poll_client(), Err(Error::Done));
-    }
+        let ev_promise = Event::PushPromise {
+            push_id,
+            list: push_req.clone(),
+        };
 
-    #[test]
-    /// Client sends multiple HEADERS before data.
-    fn additional_headers_before_data_client() {
-        let mut s = Session::new().unwrap();
-        s.handshake().unwrap();
+        assert_eq!(s.poll_client(), Ok((stream1, ev_promise.clone())));
 
-        let (stream, req) = s.send_request(false).unwrap();
+        // Promising the same push with the same header fields is allowed.
+        let header_block =
+            s.server.encode_header_block(stream2, &push_req).unwrap();
 
-        let req_trailer = vec![Header::new(b"goodbye", b"world")];
+        s.send_frame_server(
+            frame::Frame::PushPromise {
+                push_id,
+                header_block,
+            },
+            stream2,
+            false,
+        )
+        .unwrap();
 
-        assert_eq!(
-            s.client.send_additional_headers(
-                &mut s.pipe.client,
-                stream,
-                &req_trailer,
-                true,
-                false
-            ),
-            Ok(())
-        );
+        assert_eq!(s.poll_client(), Ok((stream2, ev_promise)));
 
-        s.advance().ok();
+        // But not with different ones.
+        let mut other_req = push_req;
+        other_req[3] = Header::new(b":path", b"/script.js");
 
-        let ev_initial_headers = Event::Headers {
-            list: req,
-            more_frames: true,
-        };
+        let header_block =
+            s.server.encode_header_block(stream2, &other_req).unwrap();
 
-        let ev_trailing_headers = Event::Headers {
-            list: req_trailer,
-            more_frames: true,
-        };
+        s.send_frame_server(
+            frame::Frame::PushPromise {
+                push_id,
+                header_block,
+            },
+            stream2,
+            false,
+        )
+        .unwrap();
 
-        assert_eq!(s.poll_server(), Ok((stream, ev_initial_headers)));
-        assert_eq!(s.poll_server(), Ok((stream, ev_trailing_headers)));
-        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(s.poll_client(), Err(Error::GeneralProtocolError));
+
+        assert_eq!(
+            s.pipe.client.local_error.as_ref().unwrap().error_code,
+            Error::to_wire(Error::GeneralProtocolError)
+        );
     }
 
     #[test]
-    /// Client sends multiple HEADERS before data.
-    fn data_after_trailers_client() {
+    /// Try to push more responses than allowed by the client.
+    fn server_push_limit() {
         let mut s = Session::new().unwrap();
         s.handshake().unwrap();
 
         let (stream, req) = s.send_request(false).unwrap();
 
-        let body = s.send_body_client(stream, false).unwrap();
-
-        let mut recv_buf = vec![0; body.len()];
+        let ev_headers = Event::Headers {
+            list: req.clone(),
+            more_frames: true,
+        };
 
-        let req_trailers = vec![Header::new(b"foo", b"bar")];
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
 
-        s.client
-            .send_additional_headers(
-                &mut s.pipe.client,
-                stream,
-                &req_trailers,
-                true,
-                false,
-            )
-            .unwrap();
+        // No pushes are allowed until the client sends MAX_PUSH_ID.
+        assert_eq!(
+            s.server.send_push_promise(&mut s.pipe.server, stream, &req),
+            Err(Error::IdError)
+        );
 
-        s.advance().ok();
+        assert_eq!(
+            s.server.send_max_push_id(&mut s.pipe.server, 1),
+            Err(Error::FrameUnexpected)
+        );
 
-        s.send_frame_client(
-            frame::Frame::Data {
-                payload: vec![1, 2, 3, 4],
-            },
-            stream,
-            true,
-        )
-        .unwrap();
+        s.client.send_max_push_id(&mut s.pipe.client, 1).unwrap();
+        assert_eq!(
+            s.client.send_max_push_id(&mut s.pipe.client, 0),
+            Err(Error::IdError)
+        );
 
-        let ev_headers = Event::Headers {
-            list: req,
-            more_frames: true,
-        };
+        s.advance().ok();
+        assert_eq!(s.poll_server(), Err(Error::Done));
 
-        let ev_trailers = Event::Headers {
-            list: req_trailers,
-            more_frames: true,
-        };
+        assert_eq!(
+            s.server.send_push_promise(&mut s.pipe.server, stream, &req),
+            Ok(0)
+        );
+        assert_eq!(
+            s.server.send_push_promise(&mut s.pipe.server, stream, &req),
+            Ok(1)
+        );
+        assert_eq!(
+            s.server.send_push_promise(&mut s.pipe.server, stream, &req),
+            Err(Error::IdError)
+        );
 
-        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_server(), Ok((stream, Event::Data)));
-        assert_eq!(s.recv_body_server(stream, &mut recv_buf), Ok(body.len()));
-        assert_eq!(s.poll_server(), Ok((stream, ev_trailers)));
-        assert_eq!(s.poll_server(), Err(Error::FrameUnexpected));
+        // Clients can't push.
+        assert_eq!(
+            s.client.send_push_promise(&mut s.pipe.client, stream, &req),
+            Err(Error::FrameUnexpected)
+        );
     }
 
     #[test]
-    /// Send a MAX_PUSH_ID frame from the client on a valid stream.
-    fn max_push_id_from_client_good() {
+    /// Send a PUSH_PROMISE frame with a push ID above the client's limit.
+    fn push_promise_above_limit() {
         let mut s = Session::new().unwrap();
         s.handshake().unwrap();
 
-        s.send_frame_client(
-            frame::Frame::MaxPushId { push_id: 1 },
-            s.client.control_stream_id.unwrap(),
+        s.client.send_max_push_id(&mut s.pipe.client, 1).unwrap();
+        s.advance().ok();
+
+        let (stream, req) = s.send_request(true).unwrap();
+
+        let header_block = s.server.encode_header_block(stream, &req).unwrap();
+
+        s.send_frame_server(
+            frame::Frame::PushPromise {
+                push_id: 2,
+                header_block,
+            },
+            stream,
             false,
         )
         .unwrap();
 
-        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(s.poll_client(), Err(Error::IdError));
     }
 
     #[test]
-    /// Send a MAX_PUSH_ID frame from the client on an invalid stream.
-    fn max_push_id_from_client_bad_stream() {
+    /// Cancel a push from the client before the push stream is opened.
+    fn server_push_cancel_from_client() {
         let mut s = Session::new().unwrap();
         s.handshake().unwrap();
 
-        let (stream, req) = s.send_request(false).unwrap();
+        s.client.send_max_push_id(&mut s.pipe.client, 10).unwrap();
+        s.advance().ok();
 
-        s.send_frame_client(
-            frame::Frame::MaxPushId { push_id: 2 },
-            stream,
-            false,
-        )
-        .unwrap();
+        let (stream, req) = s.send_request(true).unwrap();
 
         let ev_headers = Event::Headers {
-            list: req,
-            more_frames: true,
+            list: req.clone(),
+            more_frames: false,
         };
 
         assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_server(), Err(Error::FrameUnexpected));
-    }
+        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
 
-    #[test]
-    /// Send a sequence of MAX_PUSH_ID frames from the client that attempt to
-    /// reduce the limit.
-    fn max_push_id_from_client_limit_reduction() {
-        let mut s = Session::new().unwrap();
-        s.handshake().unwrap();
+        let push_id = s
+            .server
+            .send_push_promise(&mut s.pipe.server, stream, &req)
+            .unwrap();
+
+        s.advance().ok();
+
+        let ev_promise = Event::PushPromise { push_id, list: req };
+
+        assert_eq!(s.poll_client(), Ok((stream, ev_promise)));
+
+        assert_eq!(s.client.cancel_push(&mut s.pipe.client, push_id), Ok(()));
+        assert_eq!(
+            s.client.cancel_push(&mut s.pipe.client, push_id),
+            Err(Error::Done)
+        );
+        assert_eq!(
+            s.client.cancel_push(&mut s.pipe.client, 11),
+            Err(Error::IdError)
+        );
+
+        s.advance().ok();
 
-        s.send_frame_client(
-            frame::Frame::MaxPushId { push_id: 2 },
-            s.client.control_stream_id.unwrap(),
-            false,
-        )
-        .unwrap();
+        assert_eq!(s.poll_server(), Ok((push_id, Event::CancelPush)));
 
-        s.send_frame_client(
-            frame::Frame::MaxPushId { push_id: 1 },
-            s.client.control_stream_id.unwrap(),
-            false,
-        )
-        .unwrap();
+        let resp = vec![Header::new(b":status", b"200")];
 
-        assert_eq!(s.poll_server(), Err(Error::IdError));
+        assert_eq!(
+            s.server
+                .send_push_response(&mut s.pipe.server, push_id, &resp, true),
+            Err(Error::FrameUnexpected)
+        );
     }
 
     #[test]
-    /// Send a MAX_PUSH_ID frame from the server, which is forbidden.
-    fn max_push_id_from_server() {
+    /// Cancel a push from the client after the push stream is opened.
+    fn server_push_cancel_from_client_after_push_stream() {
         let mut s = Session::new().unwrap();
         s.handshake().unwrap();
 
-        s.send_frame_server(
-            frame::Frame::MaxPushId { push_id: 1 },
-            s.server.control_stream_id.unwrap(),
-            false,
-        )
-        .unwrap();
+        s.client.send_max_push_id(&mut s.pipe.client, 10).unwrap();
+        s.advance().ok();
 
-        assert_eq!(s.poll_client(), Err(Error::FrameUnexpected));
-    }
+        let (stream, req) = s.send_request(true).unwrap();
 
-    #[test]
-    /// Send a PUSH_PROMISE frame from the client, which is forbidden.
-    fn push_promise_from_client() {
-        let mut s = Session::new().unwrap();
-        s.handshake().unwrap();
+        let ev_headers = Event::Headers {
+            list: req.clone(),
+            more_frames: false,
+        };
 
-        let (stream, req) = s.send_request(false).unwrap();
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
 
-        let header_block = s.client.encode_header_block(&req).unwrap();
+        let push_id = s
+            .server
+            .send_push_promise(&mut s.pipe.server, stream, &req)
+            .unwrap();
 
-        s.send_frame_client(
-            frame::Frame::PushPromise {
-                push_id: 1,
-                header_block,
-            },
-            stream,
-            false,
-        )
-        .unwrap();
+        let resp = vec![Header::new(b":status", b"200")];
+
+        let push_stream = s
+            .server
+            .send_push_response(&mut s.pipe.server, push_id, &resp, false)
+            .unwrap();
+
+        s.advance().ok();
+
+        let ev_promise = Event::PushPromise {
+            push_id,
+            list: req.clone(),
+        };
 
         let ev_headers = Event::Headers {
-            list: req,
+            list: resp,
             more_frames: true,
         };
 
-        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_server(), Err(Error::FrameUnexpected));
-    }
+        assert_eq!(s.poll_client(), Ok((stream, ev_promise)));
+        assert_eq!(
+            s.poll_client(),
+            Ok((push_stream, Event::PushStream { push_id }))
+        );
+        assert_eq!(s.poll_client(), Ok((push_stream, ev_headers)));
 
-    #[test]
-    /// Send a CANCEL_PUSH frame from the client.
-    fn cancel_push_from_client() {
-        let mut s = Session::new().unwrap();
-        s.handshake().unwrap();
+        assert_eq!(s.client.pushes.len(), 1);
 
-        s.send_frame_client(
-            frame::Frame::CancelPush { push_id: 1 },
-            s.client.control_stream_id.unwrap(),
+        assert_eq!(s.client.cancel_push(&mut s.pipe.client, push_id), Ok(()));
+        assert_eq!(
+            s.client.cancel_push(&mut s.pipe.client, push_id),
+            Err(Error::Done)
+        );
+
+        // The push state is gone, and a late promise isn't reported.
+        assert!(s.client.pushes.is_empty());
+
+        let header_block = s.server.encode_header_block(stream, &req).unwrap();
+
+        s.send_frame_server(
+            frame::Frame::PushPromise {
+                push_id,
+                header_block,
+            },
+            stream,
             false,
         )
         .unwrap();
 
-        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(s.poll_client(), Err(Error::Done));
+        assert!(s.client.pushes.is_empty());
     }
 
     #[test]
-    /// Send a CANCEL_PUSH frame from the client on an invalid stream.
-    fn cancel_push_from_client_bad_stream() {
+    /// Cancel a push from the server after the push stream is opened.
+    fn server_push_cancel_from_server() {
         let mut s = Session::new().unwrap();
         s.handshake().unwrap();
 
-        let (stream, req) = s.send_request(false).unwrap();
+        s.client.send_max_push_id(&mut s.pipe.client, 10).unwrap();
+        s.advance().ok();
 
-        s.send_frame_client(
-            frame::Frame::CancelPush { push_id: 2 },
-            stream,
-            false,
-        )
-        .unwrap();
+        let (stream, req) = s.send_request(true).unwrap();
 
         let ev_headers = Event::Headers {
-            list: req,
-            more_frames: true,
+            list: req.clone(),
+            more_frames: false,
         };
 
         assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_server(), Err(Error::FrameUnexpected));
-    }
+        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
 
-    #[test]
-    /// Send a CANCEL_PUSH frame from the client.
-    fn cancel_push_from_server() {
-        let mut s = Session::new().unwrap();
-        s.handshake().unwrap();
+        let push_id = s
+            .server
+            .send_push_promise(&mut s.pipe.server, stream, &req)
+            .unwrap();
 
-        s.send_frame_server(
-            frame::Frame::CancelPush { push_id: 1 },
-            s.server.control_stream_id.unwrap(),
-            false,
-        )
-        .unwrap();
+        let resp = vec![Header::new(b":status", b"200")];
 
-        assert_eq!(s.poll_client(), Err(Error::Done));
+        let push_stream = s
+            .server
+            .send_push_response(&mut s.pipe.server, push_id, &resp, false)
+            .unwrap();
+
+        assert_eq!(s.server.cancel_push(&mut s.pipe.server, push_id), Ok(()));
+        assert_eq!(
+            s.server.cancel_push(&mut s.pipe.server, push_id),
+            Err(Error::Done)
+        );
+        assert_eq!(
+            s.server.cancel_push(&mut s.pipe.server, push_id + 1),
+            Err(Error::IdError)
+        );
+
+        assert_eq!(
+            s.server
+                .send_body(&mut s.pipe.server, push_stream, b"", true),
+            Err(Error::FrameUnexpected)
+        );
     }
 
     #[test]
@@ -4628,8 +7110,7 @@ mod tests {
 
         s.advance().ok();
 
-        // TODO: server push
-        assert_eq!(s.poll_server(), Ok((0, Event::GoAway)));
+        assert_eq!(s.poll_server(), Ok((100, Event::GoAway)));
     }
 
     #[test]
@@ -5323,7 +7804,8 @@ mod tests {
         s.handshake().unwrap();
 
         let stream_id = s.client.local_qpack_streams.encoder_stream_id.unwrap();
-        let d = [0; 1];
+        // Set Dynamic Table Capacity to 0.
+        let d = [0x20; 1];
 
         s.pipe.client.stream_send(stream_id, &d, false).unwrap();
         s.pipe.client.stream_send(stream_id, &d, true).unwrap();
@@ -5368,7 +7850,8 @@ mod tests {
         s.handshake().unwrap();
 
         let stream_id = s.client.local_qpack_streams.encoder_stream_id.unwrap();
-        let d = [0; 1];
+        // Set Dynamic Table Capacity to 0.
+        let d = [0x20; 1];
 
         s.pipe.client.stream_send(stream_id, &d, false).unwrap();
         s.pipe.client.stream_send(stream_id, &d, false).unwrap();
@@ -5394,16 +7877,19 @@ mod tests {
     #[test]
     /// Client sends QPACK data.
     fn qpack_data() {
-        // TODO: QPACK instructions are ignored until dynamic table support is
-        // added so we just test that the data is safely ignored.
         let mut s = Session::new().unwrap();
         s.handshake().unwrap();
 
         let e_stream_id = s.client.local_qpack_streams.encoder_stream_id.unwrap();
         let d_stream_id = s.client.local_qpack_streams.decoder_stream_id.unwrap();
-        let d = [0; 20];
 
-        s.pipe.client.stream_send(e_stream_id, &d, false).unwrap();
+        // Set Dynamic Table Capacity to 0, which is always allowed.
+        let e = [0x20; 20];
+
+        // Stream Cancellation for stream 0.
+        let d = [0x40; 20];
+
+        s.pipe.client.stream_send(e_stream_id, &e, false).unwrap();
         s.advance().ok();
 
         s.pipe.client.stream_send(d_stream_id, &d, false).unwrap();
@@ -5427,6 +7913,201 @@ mod tests {
         assert_eq!(stats.qpack_decoder_stream_recv_bytes, 20);
     }
 
+    #[test]
+    /// Client sends headers referencing the QPACK dynamic table before the
+    /// corresponding encoder instructions.
+    fn qpack_dynamic_table_blocked() {
+        let mut config = crate::Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config.set_application_protos(&[b"h3"]).unwrap();
+        config.set_initial_max_data(1500);
+        config.set_initial_max_stream_data_bidi_local(150);
+        config.set_initial_max_stream_data_bidi_remote(150);
+        config.set_initial_max_stream_data_uni(150);
+        config.set_initial_max_streams_bidi(5);
+        config.set_initial_max_streams_uni(5);
+        config.verify_peer(false);
+
+        let mut h3_config = Config::new().unwrap();
+        h3_config.set_qpack_max_table_capacity(4096);
+        h3_config.set_qpack_blocked_streams(1);
+        h3_config.set_qpack_insertion_policy(
+            QpackInsertionPolicy::MaximizeCompression,
+        );
+        h3_config.set_qpack_insertion_filter(Arc::new(
+            qpack::RepeatedFieldFilter::new(0),
+        ));
+
+        let mut s = Session::with_configs(&mut config, &h3_config).unwrap();
+        s.handshake().unwrap();
+
+        let headers = vec![
+            Header::new(b":authority", b"www.example.com"),
+            Header::new(b"x-custom", b"some value"),
+        ];
+
+        // Encode the header block with the client's encoder, which inserts
+        // both fields and references them right away, but hold the resulting
+        // encoder instructions back.
+        let mut header_block = vec![0; 64];
+        let len = s
+            .client
+            .qpack_encoder
+            .encode_stream(0, &headers, &mut header_block)
+            .unwrap();
+        header_block.truncate(len);
+
+        assert_eq!(s.client.qpack_encoder.insert_count(), 2);
+        assert_eq!(s.client.qpack_encoder.blocked_streams(), 1);
+
+        let instructions = s.client.qpack_encoder.pending_instructions().to_vec();
+        s.client.qpack_encoder.instructions_sent(instructions.len());
+
+        s.send_frame_client(frame::Frame::Headers { header_block }, 0, true)
+            .unwrap();
+
+        // The dynamic table entries are missing, so the stream is blocked.
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        let e_stream_id = s.client.local_qpack_streams.encoder_stream_id.unwrap();
+        s.send_arbitrary_stream_data_client(&instructions, e_stream_id, false)
+            .unwrap();
+
+        let ev_headers = Event::Headers {
+            list: headers,
+            more_frames: false,
+        };
+
+        assert_eq!(s.poll_server(), Ok((0, ev_headers)));
+        assert_eq!(s.poll_server(), Ok((0, Event::Finished)));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        // The client received the Section Acknowledgement.
+        s.advance().ok();
+        assert_eq!(s.poll_client(), Err(Error::Done));
+        assert_eq!(s.client.stats().qpack_decoder_stream_recv_bytes, 1);
+        assert_eq!(s.client.qpack_encoder.known_received_count(), 2);
+        assert_eq!(s.client.qpack_encoder.blocked_streams(), 0);
+    }
+
+    #[test]
+    /// Client sends an invalid QPACK encoder instruction.
+    fn qpack_encoder_stream_error() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let e_stream_id = s.client.local_qpack_streams.encoder_stream_id.unwrap();
+
+        // Duplicate an entry that doesn't exist.
+        s.send_arbitrary_stream_data_client(&[0x00], e_stream_id, false)
+            .unwrap();
+
+        assert_eq!(s.poll_server(), Err(Error::QpackEncoderStreamError));
+
+        assert_eq!(
+            s.pipe.server.local_error.as_ref().unwrap().error_code,
+            Error::to_wire(Error::QpackEncoderStreamError)
+        );
+    }
+
+    #[test]
+    /// Server compresses response headers using the QPACK dynamic table.
+    fn qpack_dynamic_table_encoding() {
+        let mut config = crate::Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config.set_application_protos(&[b"h3"]).unwrap();
+        config.set_initial_max_data(1500);
+        config.set_initial_max_stream_data_bidi_local(150);
+        config.set_initial_max_stream_data_bidi_remote(150);
+        config.set_initial_max_stream_data_uni(150);
+        config.set_initial_max_streams_bidi(5);
+        config.set_initial_max_streams_uni(5);
+        config.verify_peer(false);
+
+        let mut h3_config = Config::new().unwrap();
+        h3_config.set_qpack_max_table_capacity(4096);
+        h3_config.set_qpack_blocked_streams(16);
+        h3_config.set_qpack_insertion_policy(
+            QpackInsertionPolicy::MaximizeCompression,
+        );
+
+        let mut s = Session::with_configs(&mut config, &h3_config).unwrap();
+        s.handshake().unwrap();
+
+        let resp = vec![
+            Header::new(b":status", b"200"),
+            Header::new(b"server", b"quiche-test"),
+            Header::new(b"x-custom", b"some value"),
+        ];
+
+        for _ in 0..2 {
+            let (stream, req) = s.send_request(true).unwrap();
+
+            let ev_headers = Event::Headers {
+                list: req,
+                more_frames: false,
+            };
+
+            assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+            assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+
+            s.server
+                .send_response(&mut s.pipe.server, stream, &resp, true)
+                .unwrap();
+
+            s.advance().ok();
+
+            let ev_headers = Event::Headers {
+                list: resp.clone(),
+                more_frames: false,
+            };
+
+            assert_eq!(s.poll_client(), Ok((stream, ev_headers)));
+            assert_eq!(s.poll_client(), Ok((stream, Event::Finished)));
+            assert_eq!(s.poll_client(), Err(Error::Done));
+
+            s.advance().ok();
+        }
+
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        // Only the second response inserted entries, as fields are inserted
+        // once repeated, and both were acknowledged by the client.
+        assert_eq!(s.server.qpack_encoder.insert_count(), 2);
+        assert_eq!(s.server.qpack_encoder.known_received_count(), 2);
+        assert_eq!(s.server.qpack_encoder.blocked_streams(), 0);
+    }
+
+    #[test]
+    /// Client sends an invalid QPACK decoder instruction.
+    fn qpack_decoder_stream_error() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let d_stream_id = s.client.local_qpack_streams.decoder_stream_id.unwrap();
+
+        // Section Acknowledgement for a stream without header blocks.
+        s.send_arbitrary_stream_data_client(&[0x80], d_stream_id, false)
+            .unwrap();
+
+        assert_eq!(s.poll_server(), Err(Error::QpackDecoderStreamError));
+
+        assert_eq!(
+            s.pipe.server.local_error.as_ref().unwrap().error_code,
+            Error::to_wire(Error::QpackDecoderStreamError)
+        );
+    }
+
     #[test]
     /// Tests limits for the stream state buffer maximum size.
     fn max_state_buf_size() {
@@ -6442,249 +9123,624 @@ mod tests {
             .load_priv_key_from_pem_file("examples/cert.key")
             .unwrap();
         config.set_application_protos(&[b"h3"]).unwrap();
-        config.set_initial_max_data(70);
+        config.set_initial_max_data(70);
+        config.set_initial_max_stream_data_bidi_local(150);
+        config.set_initial_max_stream_data_bidi_remote(150);
+        config.set_initial_max_stream_data_uni(150);
+        config.set_initial_max_streams_bidi(100);
+        config.set_initial_max_streams_uni(5);
+        config.verify_peer(false);
+        config.grease(false);
+
+        let mut h3_config = Config::new().unwrap();
+        h3_config
+            .set_additional_settings(vec![(42, 43), (44, 45)])
+            .unwrap();
+
+        let mut s = Session::with_configs(&mut config, &h3_config).unwrap();
+        assert_eq!(s.pipe.handshake(), Ok(()));
+
+        assert_eq!(s.pipe.advance(), Ok(()));
+
+        s.client.send_settings(&mut s.pipe.client).unwrap();
+        assert_eq!(s.pipe.advance(), Ok(()));
+        assert_eq!(s.server.poll(&mut s.pipe.server), Err(Error::Done));
+
+        s.server.send_settings(&mut s.pipe.server).unwrap();
+        assert_eq!(s.pipe.advance(), Ok(()));
+        assert_eq!(s.client.poll(&mut s.pipe.client), Err(Error::Done));
+
+        assert_eq!(
+            s.server.peer_settings_raw(),
+            Some(&[(42, 43), (44, 45)][..])
+        );
+        assert_eq!(
+            s.client.peer_settings_raw(),
+            Some(&[(42, 43), (44, 45)][..])
+        );
+    }
+
+    #[test]
+    /// Send a single DATAGRAM.
+    fn single_dgram() {
+        let mut buf = [0; 65535];
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        // We'll send default data of 10 bytes on flow ID 0.
+        let result = (11, 0, 1);
+
+        s.send_dgram_client(0).unwrap();
+
+        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(result));
+
+        s.send_dgram_server(0).unwrap();
+        assert_eq!(s.poll_client(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_client(&mut buf), Ok(result));
+    }
+
+    #[test]
+    /// Send multiple DATAGRAMs.
+    fn multiple_dgram() {
+        let mut buf = [0; 65535];
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        // We'll send default data of 10 bytes on flow ID 0.
+        let result = (11, 0, 1);
+
+        s.send_dgram_client(0).unwrap();
+        s.send_dgram_client(0).unwrap();
+        s.send_dgram_client(0).unwrap();
+
+        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(result));
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(result));
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(result));
+        assert_eq!(s.recv_dgram_server(&mut buf), Err(Error::Done));
+
+        s.send_dgram_server(0).unwrap();
+        s.send_dgram_server(0).unwrap();
+        s.send_dgram_server(0).unwrap();
+
+        assert_eq!(s.poll_client(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_client(&mut buf), Ok(result));
+        assert_eq!(s.recv_dgram_client(&mut buf), Ok(result));
+        assert_eq!(s.recv_dgram_client(&mut buf), Ok(result));
+        assert_eq!(s.recv_dgram_client(&mut buf), Err(Error::Done));
+    }
+
+    #[test]
+    /// Send more DATAGRAMs than the send queue allows.
+    fn multiple_dgram_overflow() {
+        let mut buf = [0; 65535];
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        // We'll send default data of 10 bytes on flow ID 0.
+        let result = (11, 0, 1);
+
+        // Five DATAGRAMs
+        s.send_dgram_client(0).unwrap();
+        s.send_dgram_client(0).unwrap();
+        s.send_dgram_client(0).unwrap();
+        s.send_dgram_client(0).unwrap();
+        s.send_dgram_client(0).unwrap();
+
+        // Only 3 independent DATAGRAMs to read events will fire.
+        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(result));
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(result));
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(result));
+        assert_eq!(s.recv_dgram_server(&mut buf), Err(Error::Done));
+    }
+
+    #[test]
+    /// Send a single DATAGRAM and request.
+    fn poll_datagram_cycling_no_read() {
+        let mut config = crate::Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config.set_application_protos(&[b"h3"]).unwrap();
+        config.set_initial_max_data(1500);
+        config.set_initial_max_stream_data_bidi_local(150);
+        config.set_initial_max_stream_data_bidi_remote(150);
+        config.set_initial_max_stream_data_uni(150);
+        config.set_initial_max_streams_bidi(100);
+        config.set_initial_max_streams_uni(5);
+        config.verify_peer(false);
+        config.enable_dgram(true, 100, 100);
+
+        let h3_config = Config::new().unwrap();
+        let mut s = Session::with_configs(&mut config, &h3_config).unwrap();
+        s.handshake().unwrap();
+
+        // Send request followed by DATAGRAM on client side.
+        let (stream, req) = s.send_request(false).unwrap();
+
+        s.send_body_client(stream, true).unwrap();
+
+        let ev_headers = Event::Headers {
+            list: req,
+            more_frames: true,
+        };
+
+        s.send_dgram_client(0).unwrap();
+
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Data)));
+
+        assert_eq!(s.poll_server(), Err(Error::Done));
+    }
+
+    #[test]
+    /// Send a single DATAGRAM and request.
+    fn poll_datagram_single_read() {
+        let mut buf = [0; 65535];
+
+        let mut config = crate::Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config.set_application_protos(&[b"h3"]).unwrap();
+        config.set_initial_max_data(1500);
+        config.set_initial_max_stream_data_bidi_local(150);
+        config.set_initial_max_stream_data_bidi_remote(150);
+        config.set_initial_max_stream_data_uni(150);
+        config.set_initial_max_streams_bidi(100);
+        config.set_initial_max_streams_uni(5);
+        config.verify_peer(false);
+        config.enable_dgram(true, 100, 100);
+
+        let h3_config = Config::new().unwrap();
+        let mut s = Session::with_configs(&mut config, &h3_config).unwrap();
+        s.handshake().unwrap();
+
+        // We'll send default data of 10 bytes on flow ID 0.
+        let result = (11, 0, 1);
+
+        // Send request followed by DATAGRAM on client side.
+        let (stream, req) = s.send_request(false).unwrap();
+
+        let body = s.send_body_client(stream, true).unwrap();
+
+        let mut recv_buf = vec![0; body.len()];
+
+        let ev_headers = Event::Headers {
+            list: req,
+            more_frames: true,
+        };
+
+        s.send_dgram_client(0).unwrap();
+
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Data)));
+
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(result));
+
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        assert_eq!(s.recv_body_server(stream, &mut recv_buf), Ok(body.len()));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        // Send response followed by DATAGRAM on server side
+        let resp = s.send_response(stream, false).unwrap();
+
+        let body = s.send_body_server(stream, true).unwrap();
+
+        let mut recv_buf = vec![0; body.len()];
+
+        let ev_headers = Event::Headers {
+            list: resp,
+            more_frames: true,
+        };
+
+        s.send_dgram_server(0).unwrap();
+
+        assert_eq!(s.poll_client(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_client(), Ok((stream, Event::Data)));
+
+        assert_eq!(s.poll_client(), Err(Error::Done));
+
+        assert_eq!(s.recv_dgram_client(&mut buf), Ok(result));
+
+        assert_eq!(s.poll_client(), Err(Error::Done));
+
+        assert_eq!(s.recv_body_client(stream, &mut recv_buf), Ok(body.len()));
+
+        assert_eq!(s.poll_client(), Ok((stream, Event::Finished)));
+        assert_eq!(s.poll_client(), Err(Error::Done));
+    }
+
+    #[test]
+    /// Send multiple DATAGRAMs and requests.
+    fn poll_datagram_multi_read() {
+        let mut buf = [0; 65535];
+
+        let mut config = crate::Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config.set_application_protos(&[b"h3"]).unwrap();
+        config.set_initial_max_data(1500);
         config.set_initial_max_stream_data_bidi_local(150);
         config.set_initial_max_stream_data_bidi_remote(150);
         config.set_initial_max_stream_data_uni(150);
         config.set_initial_max_streams_bidi(100);
         config.set_initial_max_streams_uni(5);
         config.verify_peer(false);
-        config.grease(false);
-
-        let mut h3_config = Config::new().unwrap();
-        h3_config
-            .set_additional_settings(vec![(42, 43), (44, 45)])
-            .unwrap();
+        config.enable_dgram(true, 100, 100);
 
+        let h3_config = Config::new().unwrap();
         let mut s = Session::with_configs(&mut config, &h3_config).unwrap();
-        assert_eq!(s.pipe.handshake(), Ok(()));
+        s.handshake().unwrap();
 
-        assert_eq!(s.pipe.advance(), Ok(()));
+        // 10 bytes on flow ID 0 and 2.
+        let flow_0_result = (11, 0, 1);
+        let flow_2_result = (11, 2, 1);
 
-        s.client.send_settings(&mut s.pipe.client).unwrap();
-        assert_eq!(s.pipe.advance(), Ok(()));
-        assert_eq!(s.server.poll(&mut s.pipe.server), Err(Error::Done));
+        // Send requests followed by DATAGRAMs on client side.
+        let (stream, req) = s.send_request(false).unwrap();
 
-        s.server.send_settings(&mut s.pipe.server).unwrap();
-        assert_eq!(s.pipe.advance(), Ok(()));
-        assert_eq!(s.client.poll(&mut s.pipe.client), Err(Error::Done));
+        let body = s.send_body_client(stream, true).unwrap();
 
-        assert_eq!(
-            s.server.peer_settings_raw(),
-            Some(&[(42, 43), (44, 45)][..])
-        );
-        assert_eq!(
-            s.client.peer_settings_raw(),
-            Some(&[(42, 43), (44, 45)][..])
-        );
+        let mut recv_buf = vec![0; body.len()];
+
+        let ev_headers = Event::Headers {
+            list: req,
+            more_frames: true,
+        };
+
+        s.send_dgram_client(0).unwrap();
+        s.send_dgram_client(0).unwrap();
+        s.send_dgram_client(0).unwrap();
+        s.send_dgram_client(0).unwrap();
+        s.send_dgram_client(0).unwrap();
+        s.send_dgram_client(2).unwrap();
+        s.send_dgram_client(2).unwrap();
+        s.send_dgram_client(2).unwrap();
+        s.send_dgram_client(2).unwrap();
+        s.send_dgram_client(2).unwrap();
+
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Data)));
+
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        // Second cycle, start to read
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_0_result));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_0_result));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_0_result));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        assert_eq!(s.recv_body_server(stream, &mut recv_buf), Ok(body.len()));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        // Third cycle.
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_0_result));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_0_result));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_2_result));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_2_result));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_2_result));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_2_result));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_2_result));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        // Send response followed by DATAGRAM on server side
+        let resp = s.send_response(stream, false).unwrap();
+
+        let body = s.send_body_server(stream, true).unwrap();
+
+        let mut recv_buf = vec![0; body.len()];
+
+        let ev_headers = Event::Headers {
+            list: resp,
+            more_frames: true,
+        };
+
+        s.send_dgram_server(0).unwrap();
+        s.send_dgram_server(0).unwrap();
+        s.send_dgram_server(0).unwrap();
+        s.send_dgram_server(0).unwrap();
+        s.send_dgram_server(0).unwrap();
+        s.send_dgram_server(2).unwrap();
+        s.send_dgram_server(2).unwrap();
+        s.send_dgram_server(2).unwrap();
+        s.send_dgram_server(2).unwrap();
+        s.send_dgram_server(2).unwrap();
+
+        assert_eq!(s.poll_client(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_client(), Ok((stream, Event::Data)));
+
+        assert_eq!(s.poll_client(), Err(Error::Done));
+
+        // Second cycle, start to read
+        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_0_result));
+        assert_eq!(s.poll_client(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_0_result));
+        assert_eq!(s.poll_client(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_0_result));
+        assert_eq!(s.poll_client(), Err(Error::Done));
+
+        assert_eq!(s.recv_body_client(stream, &mut recv_buf), Ok(body.len()));
+        assert_eq!(s.poll_client(), Ok((stream, Event::Finished)));
+
+        assert_eq!(s.poll_client(), Err(Error::Done));
+
+        // Third cycle.
+        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_0_result));
+        assert_eq!(s.poll_client(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_0_result));
+        assert_eq!(s.poll_client(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_2_result));
+        assert_eq!(s.poll_client(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_2_result));
+        assert_eq!(s.poll_client(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_2_result));
+        assert_eq!(s.poll_client(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_2_result));
+        assert_eq!(s.poll_client(), Err(Error::Done));
+        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_2_result));
+        assert_eq!(s.poll_client(), Err(Error::Done));
     }
 
     #[test]
-    /// Send a single DATAGRAM.
-    fn single_dgram() {
-        let mut buf = [0; 65535];
+    /// Tests that the Finished event is not issued for streams of unknown type
+    /// (e.g. GREASE).
+    fn finished_is_for_requests() {
         let mut s = Session::new().unwrap();
         s.handshake().unwrap();
 
-        // We'll send default data of 10 bytes on flow ID 0.
-        let result = (11, 0, 1);
+        assert_eq!(s.poll_client(), Err(Error::Done));
+        assert_eq!(s.poll_server(), Err(Error::Done));
 
-        s.send_dgram_client(0).unwrap();
+        assert_eq!(s.client.open_grease_stream(&mut s.pipe.client), Ok(()));
+        assert_eq!(s.pipe.advance(), Ok(()));
 
+        assert_eq!(s.poll_client(), Err(Error::Done));
         assert_eq!(s.poll_server(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(result));
+    }
 
-        s.send_dgram_server(0).unwrap();
-        assert_eq!(s.poll_client(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_client(&mut buf), Ok(result));
+    #[test]
+    /// Tests that streams are marked as finished only once.
+    fn finished_once() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        let (stream, req) = s.send_request(false).unwrap();
+        let body = s.send_body_client(stream, true).unwrap();
+
+        let mut recv_buf = vec![0; body.len()];
+
+        let ev_headers = Event::Headers {
+            list: req,
+            more_frames: true,
+        };
+
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Data)));
+
+        assert_eq!(s.recv_body_server(stream, &mut recv_buf), Ok(body.len()));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+
+        assert_eq!(s.recv_body_server(stream, &mut recv_buf), Err(Error::Done));
+        assert_eq!(s.poll_server(), Err(Error::Done));
     }
 
     #[test]
-    /// Send multiple DATAGRAMs.
-    fn multiple_dgram() {
-        let mut buf = [0; 65535];
+    /// Tests that the Data event is properly re-armed.
+    fn data_event_rearm() {
+        let bytes = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+
         let mut s = Session::new().unwrap();
         s.handshake().unwrap();
 
-        // We'll send default data of 10 bytes on flow ID 0.
-        let result = (11, 0, 1);
+        let (r1_id, r1_hdrs) = s.send_request(false).unwrap();
 
-        s.send_dgram_client(0).unwrap();
-        s.send_dgram_client(0).unwrap();
-        s.send_dgram_client(0).unwrap();
+        let mut recv_buf = vec![0; bytes.len()];
+
+        let r1_ev_headers = Event::Headers {
+            list: r1_hdrs,
+            more_frames: true,
+        };
+
+        // Manually send an incomplete DATA frame (i.e. the frame size is longer
+        // than the actual data sent).
+        {
+            let mut d = [42; 10];
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+
+            b.put_varint(frame::DATA_FRAME_TYPE_ID).unwrap();
+            b.put_varint(bytes.len() as u64).unwrap();
+            let off = b.off();
+            s.pipe.client.stream_send(r1_id, &d[..off], false).unwrap();
+
+            assert_eq!(
+                s.pipe.client.stream_send(r1_id, &bytes[..5], false),
+                Ok(5)
+            );
+
+            s.advance().ok();
+        }
 
+        assert_eq!(s.poll_server(), Ok((r1_id, r1_ev_headers)));
+        assert_eq!(s.poll_server(), Ok((r1_id, Event::Data)));
         assert_eq!(s.poll_server(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(result));
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(result));
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(result));
-        assert_eq!(s.recv_dgram_server(&mut buf), Err(Error::Done));
 
-        s.send_dgram_server(0).unwrap();
-        s.send_dgram_server(0).unwrap();
-        s.send_dgram_server(0).unwrap();
+        // Read the available body data.
+        assert_eq!(s.recv_body_server(r1_id, &mut recv_buf), Ok(5));
 
-        assert_eq!(s.poll_client(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_client(&mut buf), Ok(result));
-        assert_eq!(s.recv_dgram_client(&mut buf), Ok(result));
-        assert_eq!(s.recv_dgram_client(&mut buf), Ok(result));
-        assert_eq!(s.recv_dgram_client(&mut buf), Err(Error::Done));
-    }
+        // Send the remaining DATA payload.
+        assert_eq!(s.pipe.client.stream_send(r1_id, &bytes[5..], false), Ok(5));
+        s.advance().ok();
+
+        assert_eq!(s.poll_server(), Ok((r1_id, Event::Data)));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        // Read the rest of the body data.
+        assert_eq!(s.recv_body_server(r1_id, &mut recv_buf), Ok(5));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        // Send more data.
+        let r1_body = s.send_body_client(r1_id, false).unwrap();
+
+        assert_eq!(s.poll_server(), Ok((r1_id, Event::Data)));
+        assert_eq!(s.poll_server(), Err(Error::Done));
 
-    #[test]
-    /// Send more DATAGRAMs than the send queue allows.
-    fn multiple_dgram_overflow() {
-        let mut buf = [0; 65535];
-        let mut s = Session::new().unwrap();
-        s.handshake().unwrap();
+        assert_eq!(s.recv_body_server(r1_id, &mut recv_buf), Ok(r1_body.len()));
 
-        // We'll send default data of 10 bytes on flow ID 0.
-        let result = (11, 0, 1);
+        // Send a new request to ensure cross-stream events don't break rearming.
+        let (r2_id, r2_hdrs) = s.send_request(false).unwrap();
+        let r2_ev_headers = Event::Headers {
+            list: r2_hdrs,
+            more_frames: true,
+        };
+        let r2_body = s.send_body_client(r2_id, false).unwrap();
 
-        // Five DATAGRAMs
-        s.send_dgram_client(0).unwrap();
-        s.send_dgram_client(0).unwrap();
-        s.send_dgram_client(0).unwrap();
-        s.send_dgram_client(0).unwrap();
-        s.send_dgram_client(0).unwrap();
+        s.advance().ok();
 
-        // Only 3 independent DATAGRAMs to read events will fire.
+        assert_eq!(s.poll_server(), Ok((r2_id, r2_ev_headers)));
+        assert_eq!(s.poll_server(), Ok((r2_id, Event::Data)));
+        assert_eq!(s.recv_body_server(r2_id, &mut recv_buf), Ok(r2_body.len()));
         assert_eq!(s.poll_server(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(result));
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(result));
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(result));
-        assert_eq!(s.recv_dgram_server(&mut buf), Err(Error::Done));
-    }
-
-    #[test]
-    /// Send a single DATAGRAM and request.
-    fn poll_datagram_cycling_no_read() {
-        let mut config = crate::Config::new(crate::PROTOCOL_VERSION).unwrap();
-        config
-            .load_cert_chain_from_pem_file("examples/cert.crt")
-            .unwrap();
-        config
-            .load_priv_key_from_pem_file("examples/cert.key")
-            .unwrap();
-        config.set_application_protos(&[b"h3"]).unwrap();
-        config.set_initial_max_data(1500);
-        config.set_initial_max_stream_data_bidi_local(150);
-        config.set_initial_max_stream_data_bidi_remote(150);
-        config.set_initial_max_stream_data_uni(150);
-        config.set_initial_max_streams_bidi(100);
-        config.set_initial_max_streams_uni(5);
-        config.verify_peer(false);
-        config.enable_dgram(true, 100, 100);
 
-        let h3_config = Config::new().unwrap();
-        let mut s = Session::with_configs(&mut config, &h3_config).unwrap();
-        s.handshake().unwrap();
+        // Send more data on request 1, then trailing HEADERS.
+        let r1_body = s.send_body_client(r1_id, false).unwrap();
 
-        // Send request followed by DATAGRAM on client side.
-        let (stream, req) = s.send_request(false).unwrap();
+        let trailers = vec![Header::new(b"hello", b"world")];
 
-        s.send_body_client(stream, true).unwrap();
+        s.client
+            .send_headers(&mut s.pipe.client, r1_id, &trailers, true)
+            .unwrap();
 
-        let ev_headers = Event::Headers {
-            list: req,
-            more_frames: true,
+        let r1_ev_trailers = Event::Headers {
+            list: trailers.clone(),
+            more_frames: false,
         };
 
-        s.send_dgram_client(0).unwrap();
+        s.advance().ok();
 
-        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_server(), Ok((stream, Event::Data)));
+        assert_eq!(s.poll_server(), Ok((r1_id, Event::Data)));
+        assert_eq!(s.recv_body_server(r1_id, &mut recv_buf), Ok(r1_body.len()));
 
+        assert_eq!(s.poll_server(), Ok((r1_id, r1_ev_trailers)));
+        assert_eq!(s.poll_server(), Ok((r1_id, Event::Finished)));
         assert_eq!(s.poll_server(), Err(Error::Done));
-    }
 
-    #[test]
-    /// Send a single DATAGRAM and request.
-    fn poll_datagram_single_read() {
-        let mut buf = [0; 65535];
+        // Send more data on request 2, then trailing HEADERS.
+        let r2_body = s.send_body_client(r2_id, false).unwrap();
 
-        let mut config = crate::Config::new(crate::PROTOCOL_VERSION).unwrap();
-        config
-            .load_cert_chain_from_pem_file("examples/cert.crt")
-            .unwrap();
-        config
-            .load_priv_key_from_pem_file("examples/cert.key")
+        s.client
+            .send_headers(&mut s.pipe.client, r2_id, &trailers, false)
             .unwrap();
-        config.set_application_protos(&[b"h3"]).unwrap();
-        config.set_initial_max_data(1500);
-        config.set_initial_max_stream_data_bidi_local(150);
-        config.set_initial_max_stream_data_bidi_remote(150);
-        config.set_initial_max_stream_data_uni(150);
-        config.set_initial_max_streams_bidi(100);
-        config.set_initial_max_streams_uni(5);
-        config.verify_peer(false);
-        config.enable_dgram(true, 100, 100);
-
-        let h3_config = Config::new().unwrap();
-        let mut s = Session::with_configs(&mut config, &h3_config).unwrap();
-        s.handshake().unwrap();
 
-        // We'll send default data of 10 bytes on flow ID 0.
-        let result = (11, 0, 1);
+        let r2_ev_trailers = Event::Headers {
+            list: trailers,
+            more_frames: true,
+        };
 
-        // Send request followed by DATAGRAM on client side.
-        let (stream, req) = s.send_request(false).unwrap();
+        s.advance().ok();
 
-        let body = s.send_body_client(stream, true).unwrap();
+        assert_eq!(s.poll_server(), Ok((r2_id, Event::Data)));
+        assert_eq!(s.recv_body_server(r2_id, &mut recv_buf), Ok(r2_body.len()));
+        assert_eq!(s.poll_server(), Ok((r2_id, r2_ev_trailers)));
+        assert_eq!(s.poll_server(), Err(Error::Done));
 
-        let mut recv_buf = vec![0; body.len()];
+        let (r3_id, r3_hdrs) = s.send_request(false).unwrap();
 
-        let ev_headers = Event::Headers {
-            list: req,
+        let r3_ev_headers = Event::Headers {
+            list: r3_hdrs,
             more_frames: true,
         };
 
-        s.send_dgram_client(0).unwrap();
+        // Manually send an incomplete DATA frame (i.e. only the header is sent).
+        {
+            let mut d = [42; 10];
+            let mut b = octets::OctetsMut::with_slice(&mut d);
 
-        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_server(), Ok((stream, Event::Data)));
+            b.put_varint(frame::DATA_FRAME_TYPE_ID).unwrap();
+            b.put_varint(bytes.len() as u64).unwrap();
+            let off = b.off();
+            s.pipe.client.stream_send(r3_id, &d[..off], false).unwrap();
+
+            s.advance().ok();
+        }
 
+        assert_eq!(s.poll_server(), Ok((r3_id, r3_ev_headers)));
+        assert_eq!(s.poll_server(), Ok((r3_id, Event::Data)));
         assert_eq!(s.poll_server(), Err(Error::Done));
 
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(result));
+        assert_eq!(s.recv_body_server(r3_id, &mut recv_buf), Err(Error::Done));
 
-        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(s.pipe.client.stream_send(r3_id, &bytes[..5], false), Ok(5));
 
-        assert_eq!(s.recv_body_server(stream, &mut recv_buf), Ok(body.len()));
-        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+        s.advance().ok();
+
+        assert_eq!(s.poll_server(), Ok((r3_id, Event::Data)));
         assert_eq!(s.poll_server(), Err(Error::Done));
 
-        // Send response followed by DATAGRAM on server side
-        let resp = s.send_response(stream, false).unwrap();
+        assert_eq!(s.recv_body_server(r3_id, &mut recv_buf), Ok(5));
 
-        let body = s.send_body_server(stream, true).unwrap();
+        assert_eq!(s.pipe.client.stream_send(r3_id, &bytes[5..], false), Ok(5));
+        s.advance().ok();
 
-        let mut recv_buf = vec![0; body.len()];
+        assert_eq!(s.poll_server(), Ok((r3_id, Event::Data)));
+        assert_eq!(s.poll_server(), Err(Error::Done));
 
-        let ev_headers = Event::Headers {
-            list: resp,
-            more_frames: true,
-        };
+        assert_eq!(s.recv_body_server(r3_id, &mut recv_buf), Ok(5));
 
-        s.send_dgram_server(0).unwrap();
+        // Buffer multiple data frames.
+        let body = s.send_body_client(r3_id, false).unwrap();
+        s.send_body_client(r3_id, false).unwrap();
+        s.send_body_client(r3_id, false).unwrap();
 
-        assert_eq!(s.poll_client(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_client(), Ok((stream, Event::Data)));
+        assert_eq!(s.poll_server(), Ok((r3_id, Event::Data)));
+        assert_eq!(s.poll_server(), Err(Error::Done));
 
-        assert_eq!(s.poll_client(), Err(Error::Done));
+        {
+            let mut d = [42; 10];
+            let mut b = octets::OctetsMut::with_slice(&mut d);
 
-        assert_eq!(s.recv_dgram_client(&mut buf), Ok(result));
+            b.put_varint(frame::DATA_FRAME_TYPE_ID).unwrap();
+            b.put_varint(0).unwrap();
+            let off = b.off();
+            s.pipe.client.stream_send(r3_id, &d[..off], true).unwrap();
 
-        assert_eq!(s.poll_client(), Err(Error::Done));
+            s.advance().ok();
+        }
 
-        assert_eq!(s.recv_body_client(stream, &mut recv_buf), Ok(body.len()));
+        let mut recv_buf = vec![0; bytes.len() * 3];
 
-        assert_eq!(s.poll_client(), Ok((stream, Event::Finished)));
-        assert_eq!(s.poll_client(), Err(Error::Done));
+        assert_eq!(s.recv_body_server(r3_id, &mut recv_buf), Ok(body.len() * 3));
     }
 
     #[test]
-    /// Send multiple DATAGRAMs and requests.
-    fn poll_datagram_multi_read() {
+    /// Tests that the Datagram event is properly re-armed.
+    fn dgram_event_rearm() {
         let mut buf = [0; 65535];
 
         let mut config = crate::Config::new(crate::PROTOCOL_VERSION).unwrap();
@@ -6726,340 +9782,568 @@ mod tests {
 
         s.send_dgram_client(0).unwrap();
         s.send_dgram_client(0).unwrap();
-        s.send_dgram_client(0).unwrap();
-        s.send_dgram_client(0).unwrap();
-        s.send_dgram_client(0).unwrap();
-        s.send_dgram_client(2).unwrap();
-        s.send_dgram_client(2).unwrap();
-        s.send_dgram_client(2).unwrap();
         s.send_dgram_client(2).unwrap();
         s.send_dgram_client(2).unwrap();
 
         assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
         assert_eq!(s.poll_server(), Ok((stream, Event::Data)));
 
-        assert_eq!(s.poll_server(), Err(Error::Done));
-
-        // Second cycle, start to read
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_0_result));
-        assert_eq!(s.poll_server(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_0_result));
         assert_eq!(s.poll_server(), Err(Error::Done));
         assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_0_result));
-        assert_eq!(s.poll_server(), Err(Error::Done));
-
-        assert_eq!(s.recv_body_server(stream, &mut recv_buf), Ok(body.len()));
-        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
 
-        assert_eq!(s.poll_server(), Err(Error::Done));
-
-        // Third cycle.
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_0_result));
         assert_eq!(s.poll_server(), Err(Error::Done));
         assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_0_result));
+
         assert_eq!(s.poll_server(), Err(Error::Done));
         assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_2_result));
+
         assert_eq!(s.poll_server(), Err(Error::Done));
         assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_2_result));
+
         assert_eq!(s.poll_server(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_2_result));
+
+        s.send_dgram_client(0).unwrap();
+        s.send_dgram_client(2).unwrap();
+
         assert_eq!(s.poll_server(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_2_result));
+
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_0_result));
         assert_eq!(s.poll_server(), Err(Error::Done));
+
         assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_2_result));
         assert_eq!(s.poll_server(), Err(Error::Done));
 
-        // Send response followed by DATAGRAM on server side
-        let resp = s.send_response(stream, false).unwrap();
+        assert_eq!(s.recv_body_server(stream, &mut recv_buf), Ok(body.len()));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+    }
 
-        let body = s.send_body_server(stream, true).unwrap();
+    #[test]
+    fn reset_stream() {
+        let mut buf = [0; 65535];
 
-        let mut recv_buf = vec![0; body.len()];
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        // Client sends request.
+        let (stream, req) = s.send_request(false).unwrap();
 
         let ev_headers = Event::Headers {
-            list: resp,
+            list: req,
             more_frames: true,
         };
 
-        s.send_dgram_server(0).unwrap();
-        s.send_dgram_server(0).unwrap();
-        s.send_dgram_server(0).unwrap();
-        s.send_dgram_server(0).unwrap();
-        s.send_dgram_server(0).unwrap();
-        s.send_dgram_server(2).unwrap();
-        s.send_dgram_server(2).unwrap();
-        s.send_dgram_server(2).unwrap();
-        s.send_dgram_server(2).unwrap();
-        s.send_dgram_server(2).unwrap();
-
+        // Server sends response and closes stream.
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        let resp = s.send_response(stream, true).unwrap();
+
+        let ev_headers = Event::Headers {
+            list: resp,
+            more_frames: false,
+        };
+
         assert_eq!(s.poll_client(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_client(), Ok((stream, Event::Data)));
-
+        assert_eq!(s.poll_client(), Ok((stream, Event::Finished)));
         assert_eq!(s.poll_client(), Err(Error::Done));
 
-        // Second cycle, start to read
-        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_0_result));
-        assert_eq!(s.poll_client(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_0_result));
-        assert_eq!(s.poll_client(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_0_result));
-        assert_eq!(s.poll_client(), Err(Error::Done));
+        // Client sends RESET_STREAM, closing stream.
+        let frames = [crate::frame::Frame::ResetStream {
+            stream_id: stream,
+            error_code: 42,
+            final_size: 68,
+        }];
 
-        assert_eq!(s.recv_body_client(stream, &mut recv_buf), Ok(body.len()));
-        assert_eq!(s.poll_client(), Ok((stream, Event::Finished)));
+        let pkt_type = crate::packet::Type::Short;
+        assert_eq!(
+            s.pipe.send_pkt_to_server(pkt_type, &frames, &mut buf),
+            Ok(39)
+        );
 
-        assert_eq!(s.poll_client(), Err(Error::Done));
+        // Server issues Reset event for the stream.
+        assert_eq!(s.poll_server(), Ok((stream, Event::Reset(42))));
+        assert_eq!(s.poll_server(), Err(Error::Done));
 
-        // Third cycle.
-        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_0_result));
-        assert_eq!(s.poll_client(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_0_result));
-        assert_eq!(s.poll_client(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_2_result));
-        assert_eq!(s.poll_client(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_2_result));
-        assert_eq!(s.poll_client(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_2_result));
-        assert_eq!(s.poll_client(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_2_result));
-        assert_eq!(s.poll_client(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_client(&mut buf), Ok(flow_2_result));
-        assert_eq!(s.poll_client(), Err(Error::Done));
+        // Sending RESET_STREAM again shouldn't trigger another Reset event.
+        assert_eq!(
+            s.pipe.send_pkt_to_server(pkt_type, &frames, &mut buf),
+            Ok(39)
+        );
+
+        assert_eq!(s.poll_server(), Err(Error::Done));
     }
 
     #[test]
-    /// Tests that the Finished event is not issued for streams of unknown type
-    /// (e.g. GREASE).
-    fn finished_is_for_requests() {
+    fn reset_finished_at_server() {
         let mut s = Session::new().unwrap();
         s.handshake().unwrap();
 
-        assert_eq!(s.poll_client(), Err(Error::Done));
-        assert_eq!(s.poll_server(), Err(Error::Done));
+        // Client sends HEADERS and doesn't fin
+        let (stream, _req) = s.send_request(false).unwrap();
+
+        // ..then Client sends RESET_STREAM
+        assert_eq!(
+            s.pipe.client.stream_shutdown(0, crate::Shutdown::Write, 0),
+            Ok(())
+        );
 
-        assert_eq!(s.client.open_grease_stream(&mut s.pipe.client), Ok(()));
         assert_eq!(s.pipe.advance(), Ok(()));
 
-        assert_eq!(s.poll_client(), Err(Error::Done));
+        // Server receives just a reset
+        assert_eq!(s.poll_server(), Ok((stream, Event::Reset(0))));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        // Client sends HEADERS and fin
+        let (stream, req) = s.send_request(true).unwrap();
+
+        // ..then Client sends RESET_STREAM
+        assert_eq!(
+            s.pipe.client.stream_shutdown(4, crate::Shutdown::Write, 0),
+            Ok(())
+        );
+
+        let ev_headers = Event::Headers {
+            list: req,
+            more_frames: false,
+        };
+
+        // Server receives headers and fin.
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
         assert_eq!(s.poll_server(), Err(Error::Done));
     }
 
     #[test]
-    /// Tests that streams are marked as finished only once.
-    fn finished_once() {
+    fn reset_finished_at_server_with_data_pending() {
         let mut s = Session::new().unwrap();
         s.handshake().unwrap();
 
+        // Client sends HEADERS and doesn't fin.
         let (stream, req) = s.send_request(false).unwrap();
-        let body = s.send_body_client(stream, true).unwrap();
 
-        let mut recv_buf = vec![0; body.len()];
+        assert!(s.send_body_client(stream, false).is_ok());
+
+        assert_eq!(s.pipe.advance(), Ok(()));
 
         let ev_headers = Event::Headers {
             list: req,
             more_frames: true,
         };
 
+        // Server receives headers and data...
         assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
         assert_eq!(s.poll_server(), Ok((stream, Event::Data)));
 
-        assert_eq!(s.recv_body_server(stream, &mut recv_buf), Ok(body.len()));
-        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+        // ..then Client sends RESET_STREAM.
+        assert_eq!(
+            s.pipe
+                .client
+                .stream_shutdown(stream, crate::Shutdown::Write, 0),
+            Ok(())
+        );
 
-        assert_eq!(s.recv_body_server(stream, &mut recv_buf), Err(Error::Done));
+        assert_eq!(s.pipe.advance(), Ok(()));
+
+        // Server receives the reset and there are no more readable streams.
+        assert_eq!(s.poll_server(), Ok((stream, Event::Reset(0))));
         assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(s.pipe.server.readable().len(), 0);
     }
 
     #[test]
-    /// Tests that the Data event is properly re-armed.
-    fn data_event_rearm() {
-        let bytes = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
-
+    fn reset_finished_at_client() {
+        let mut buf = [0; 65535];
         let mut s = Session::new().unwrap();
         s.handshake().unwrap();
 
-        let (r1_id, r1_hdrs) = s.send_request(false).unwrap();
-
-        let mut recv_buf = vec![0; bytes.len()];
+        // Client sends HEADERS and doesn't fin
+        let (stream, req) = s.send_request(false).unwrap();
 
-        let r1_ev_headers = Event::Headers {
-            list: r1_hdrs,
+        let ev_headers = Event::Headers {
+            list: req,
             more_frames: true,
         };
 
-        // Manually send an incomplete DATA frame (i.e. the frame size is longer
-        // than the actual data sent).
-        {
-            let mut d = [42; 10];
-            let mut b = octets::OctetsMut::with_slice(&mut d);
+        // Server receives headers.
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Err(Error::Done));
 
-            b.put_varint(frame::DATA_FRAME_TYPE_ID).unwrap();
-            b.put_varint(bytes.len() as u64).unwrap();
-            let off = b.off();
-            s.pipe.client.stream_send(r1_id, &d[..off], false).unwrap();
+        // Server sends response and doesn't fin
+        s.send_response(stream, false).unwrap();
 
-            assert_eq!(
-                s.pipe.client.stream_send(r1_id, &bytes[..5], false),
-                Ok(5)
-            );
+        assert_eq!(s.pipe.advance(), Ok(()));
 
-            s.advance().ok();
-        }
+        // .. then Server sends RESET_STREAM
+        assert_eq!(
+            s.pipe
+                .server
+                .stream_shutdown(stream, crate::Shutdown::Write, 0),
+            Ok(())
+        );
 
-        assert_eq!(s.poll_server(), Ok((r1_id, r1_ev_headers)));
-        assert_eq!(s.poll_server(), Ok((r1_id, Event::Data)));
+        assert_eq!(s.pipe.advance(), Ok(()));
+
+        // Client receives Reset only
+        assert_eq!(s.poll_client(), Ok((stream, Event::Reset(0))));
         assert_eq!(s.poll_server(), Err(Error::Done));
 
-        // Read the available body data.
-        assert_eq!(s.recv_body_server(r1_id, &mut recv_buf), Ok(5));
+        // Client sends headers and fin.
+        let (stream, req) = s.send_request(true).unwrap();
 
-        // Send the remaining DATA payload.
-        assert_eq!(s.pipe.client.stream_send(r1_id, &bytes[5..], false), Ok(5));
-        s.advance().ok();
+        let ev_headers = Event::Headers {
+            list: req,
+            more_frames: false,
+        };
+
+        // Server receives headers and fin.
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+        assert_eq!(s.poll_server(), Err(Error::Done));
+
+        // Server sends response and fin
+        let resp = s.send_response(stream, true).unwrap();
+
+        assert_eq!(s.pipe.advance(), Ok(()));
+
+        // ..then Server sends RESET_STREAM
+        let frames = [crate::frame::Frame::ResetStream {
+            stream_id: stream,
+            error_code: 42,
+            final_size: 68,
+        }];
+
+        let pkt_type = crate::packet::Type::Short;
+        assert_eq!(
+            s.pipe.send_pkt_to_server(pkt_type, &frames, &mut buf),
+            Ok(39)
+        );
+
+        assert_eq!(s.pipe.advance(), Ok(()));
+
+        let ev_headers = Event::Headers {
+            list: resp,
+            more_frames: false,
+        };
+
+        // Client receives headers and fin.
+        assert_eq!(s.poll_client(), Ok((stream, ev_headers)));
+        assert_eq!(s.poll_client(), Ok((stream, Event::Finished)));
+        assert_eq!(s.poll_client(), Err(Error::Done));
+    }
+
+    /// Creates a session where both endpoints enable WebTransport, and
+    /// completes the HTTP/3 handshake.
+    fn webtransport_handshake() -> Session {
+        let mut config = crate::Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config.set_application_protos(&[b"h3"]).unwrap();
+        config.set_initial_max_data(1500);
+        config.set_initial_max_stream_data_bidi_local(150);
+        config.set_initial_max_stream_data_bidi_remote(150);
+        config.set_initial_max_stream_data_uni(150);
+        config.set_initial_max_streams_bidi(5);
+        config.set_initial_max_streams_uni(5);
+        config.enable_dgram(true, 3, 3);
+        config.verify_peer(false);
+
+        let mut h3_config = Config::new().unwrap();
+        h3_config.enable_extended_connect(true);
+        h3_config.set_webtransport_max_sessions(1);
+
+        let mut s = Session::with_configs(&mut config, &h3_config).unwrap();
+        s.handshake().unwrap();
+
+        s
+    }
+
+    fn webtransport_request() -> Vec<Header> {
+        vec![
+            Header::new(b":method", b"CONNECT"),
+            Header::new(b":protocol", b"webtransport"),
+            Header::new(b":scheme", b"https"),
+            Header::new(b":authority", b"quic.tech"),
+            Header::new(b":path", b"/wt"),
+        ]
+    }
+
+    #[test]
+    /// Tests that WebTransport can't be used unless the peer enabled it.
+    fn webtransport_not_enabled_by_peer() {
+        let mut s = Session::new().unwrap();
+        s.handshake().unwrap();
+
+        assert!(!s.client.webtransport_enabled_by_peer(&s.pipe.client));
+
+        assert_eq!(
+            s.client.open_webtransport_session(
+                &mut s.pipe.client,
+                &webtransport_request()
+            ),
+            Err(Error::FrameUnexpected)
+        );
 
-        assert_eq!(s.poll_server(), Ok((r1_id, Event::Data)));
-        assert_eq!(s.poll_server(), Err(Error::Done));
+        let s = webtransport_handshake();
 
-        // Read the rest of the body data.
-        assert_eq!(s.recv_body_server(r1_id, &mut recv_buf), Ok(5));
-        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert!(s.client.webtransport_enabled_by_peer(&s.pipe.client));
+        assert!(s.server.webtransport_enabled_by_peer(&s.pipe.server));
+    }
 
-        // Send more data.
-        let r1_body = s.send_body_client(r1_id, false).unwrap();
+    #[test]
+    /// Exchanges streams and datagrams over a WebTransport session, and closes
+    /// it from the client.
+    fn webtransport_session() {
+        let mut buf = [0; 65535];
+        let mut s = webtransport_handshake();
 
-        assert_eq!(s.poll_server(), Ok((r1_id, Event::Data)));
-        assert_eq!(s.poll_server(), Err(Error::Done));
+        let req = webtransport_request();
 
-        assert_eq!(s.recv_body_server(r1_id, &mut recv_buf), Ok(r1_body.len()));
+        let session_id = s
+            .client
+            .open_webtransport_session(&mut s.pipe.client, &req)
+            .unwrap();
+        s.advance().ok();
 
-        // Send a new request to ensure cross-stream events don't break rearming.
-        let (r2_id, r2_hdrs) = s.send_request(false).unwrap();
-        let r2_ev_headers = Event::Headers {
-            list: r2_hdrs,
+        let ev_headers = Event::Headers {
+            list: req,
             more_frames: true,
         };
-        let r2_body = s.send_body_client(r2_id, false).unwrap();
 
+        assert_eq!(s.poll_server(), Ok((session_id, ev_headers)));
+
+        let resp = vec![Header::new(b":status", b"200")];
+
+        assert_eq!(
+            s.server.accept_webtransport_session(
+                &mut s.pipe.server,
+                session_id,
+                &resp
+            ),
+            Ok(())
+        );
         s.advance().ok();
 
-        assert_eq!(s.poll_server(), Ok((r2_id, r2_ev_headers)));
-        assert_eq!(s.poll_server(), Ok((r2_id, Event::Data)));
-        assert_eq!(s.recv_body_server(r2_id, &mut recv_buf), Ok(r2_body.len()));
-        assert_eq!(s.poll_server(), Err(Error::Done));
+        let ev_headers = Event::Headers {
+            list: resp,
+            more_frames: true,
+        };
 
-        // Send more data on request 1, then trailing HEADERS.
-        let r1_body = s.send_body_client(r1_id, false).unwrap();
+        assert_eq!(s.poll_client(), Ok((session_id, ev_headers)));
 
-        let trailers = vec![Header::new(b"hello", b"world")];
+        // Server opens a unidirectional stream.
+        let uni = s
+            .server
+            .open_webtransport_stream(&mut s.pipe.server, session_id, false)
+            .unwrap();
+        assert_eq!(s.pipe.server.stream_send(uni, b"hello", true), Ok(5));
+        s.advance().ok();
 
-        s.client
-            .send_headers(&mut s.pipe.client, r1_id, &trailers, true)
+        assert_eq!(
+            s.poll_client(),
+            Ok((uni, Event::WebTransportStream { session_id }))
+        );
+        assert_eq!(s.poll_client(), Ok((uni, Event::Data)));
+        assert_eq!(s.recv_body_client(uni, &mut buf), Ok(5));
+        assert_eq!(&buf[..5], b"hello");
+        assert_eq!(s.poll_client(), Ok((uni, Event::Finished)));
+
+        // Client opens a bidirectional stream.
+        let bidi = s
+            .client
+            .open_webtransport_stream(&mut s.pipe.client, session_id, true)
             .unwrap();
+        assert_eq!(bidi, 4);
+        assert_eq!(s.pipe.client.stream_send(bidi, b"ping", false), Ok(4));
+        s.advance().ok();
 
-        let r1_ev_trailers = Event::Headers {
-            list: trailers.clone(),
-            more_frames: false,
-        };
+        assert_eq!(
+            s.poll_server(),
+            Ok((bidi, Event::WebTransportStream { session_id }))
+        );
+        assert_eq!(s.poll_server(), Ok((bidi, Event::Data)));
+        assert_eq!(s.recv_body_server(bidi, &mut buf), Ok(4));
+        assert_eq!(&buf[..4], b"ping");
 
+        assert_eq!(s.pipe.server.stream_send(bidi, b"pong", true), Ok(4));
         s.advance().ok();
 
-        assert_eq!(s.poll_server(), Ok((r1_id, Event::Data)));
-        assert_eq!(s.recv_body_server(r1_id, &mut recv_buf), Ok(r1_body.len()));
+        assert_eq!(s.poll_client(), Ok((bidi, Event::Data)));
+        assert_eq!(s.recv_body_client(bidi, &mut buf), Ok(4));
+        assert_eq!(&buf[..4], b"pong");
+        assert_eq!(s.poll_client(), Ok((bidi, Event::Finished)));
 
-        assert_eq!(s.poll_server(), Ok((r1_id, r1_ev_trailers)));
-        assert_eq!(s.poll_server(), Ok((r1_id, Event::Finished)));
+        // Datagrams are associated with the session.
+        assert_eq!(
+            s.client.send_webtransport_datagram(
+                &mut s.pipe.client,
+                session_id,
+                b"dgram"
+            ),
+            Ok(())
+        );
+        s.advance().ok();
+
+        assert_eq!(
+            s.poll_server(),
+            Ok((session_id, Event::WebTransportDatagram {
+                payload: b"dgram".to_vec()
+            }))
+        );
         assert_eq!(s.poll_server(), Err(Error::Done));
 
-        // Send more data on request 2, then trailing HEADERS.
-        let r2_body = s.send_body_client(r2_id, false).unwrap();
+        // Client closes the session.
+        assert_eq!(
+            s.client.close_webtransport_session(
+                &mut s.pipe.client,
+                session_id,
+                42,
+                b"bye"
+            ),
+            Ok(())
+        );
+        s.advance().ok();
 
-        s.client
-            .send_headers(&mut s.pipe.client, r2_id, &trailers, false)
-            .unwrap();
+        assert_eq!(
+            s.client.open_webtransport_stream(
+                &mut s.pipe.client,
+                session_id,
+                false
+            ),
+            Err(Error::IdError)
+        );
+        assert_eq!(
+            s.client.close_webtransport_session(
+                &mut s.pipe.client,
+                session_id,
+                42,
+                b"bye"
+            ),
+            Err(Error::Done)
+        );
 
-        let r2_ev_trailers = Event::Headers {
-            list: trailers,
-            more_frames: true,
-        };
+        assert_eq!(
+            s.poll_server(),
+            Ok((session_id, Event::WebTransportSessionClosed {
+                error_code: 42,
+                reason: b"bye".to_vec(),
+            }))
+        );
+        assert_eq!(s.poll_server(), Ok((session_id, Event::Finished)));
+        assert_eq!(s.poll_server(), Err(Error::Done));
 
+        // The server finished the CONNECT stream in turn.
         s.advance().ok();
 
-        assert_eq!(s.poll_server(), Ok((r2_id, Event::Data)));
-        assert_eq!(s.recv_body_server(r2_id, &mut recv_buf), Ok(r2_body.len()));
-        assert_eq!(s.poll_server(), Ok((r2_id, r2_ev_trailers)));
-        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(s.poll_client(), Ok((session_id, Event::Finished)));
+        assert_eq!(s.poll_client(), Err(Error::Done));
+    }
 
-        let (r3_id, r3_hdrs) = s.send_request(false).unwrap();
+    #[test]
+    /// Tests that streams received before a session is accepted are reported
+    /// once it is, and that finishing the CONNECT stream closes the session.
+    fn webtransport_stream_before_accept() {
+        let mut buf = [0; 65535];
+        let mut s = webtransport_handshake();
 
-        let r3_ev_headers = Event::Headers {
-            list: r3_hdrs,
-            more_frames: true,
-        };
+        let req = webtransport_request();
 
-        // Manually send an incomplete DATA frame (i.e. only the header is sent).
-        {
-            let mut d = [42; 10];
-            let mut b = octets::OctetsMut::with_slice(&mut d);
+        let session_id = s
+            .client
+            .open_webtransport_session(&mut s.pipe.client, &req)
+            .unwrap();
 
-            b.put_varint(frame::DATA_FRAME_TYPE_ID).unwrap();
-            b.put_varint(bytes.len() as u64).unwrap();
-            let off = b.off();
-            s.pipe.client.stream_send(r3_id, &d[..off], false).unwrap();
+        let uni = s
+            .client
+            .open_webtransport_stream(&mut s.pipe.client, session_id, false)
+            .unwrap();
+        assert_eq!(s.pipe.client.stream_send(uni, b"early", false), Ok(5));
+        s.advance().ok();
 
-            s.advance().ok();
-        }
+        let ev_headers = Event::Headers {
+            list: req,
+            more_frames: true,
+        };
 
-        assert_eq!(s.poll_server(), Ok((r3_id, r3_ev_headers)));
-        assert_eq!(s.poll_server(), Ok((r3_id, Event::Data)));
+        assert_eq!(s.poll_server(), Ok((session_id, ev_headers)));
         assert_eq!(s.poll_server(), Err(Error::Done));
 
-        assert_eq!(s.recv_body_server(r3_id, &mut recv_buf), Err(Error::Done));
+        let resp = vec![Header::new(b":status", b"200")];
 
-        assert_eq!(s.pipe.client.stream_send(r3_id, &bytes[..5], false), Ok(5));
+        assert_eq!(
+            s.server.accept_webtransport_session(
+                &mut s.pipe.server,
+                session_id,
+                &resp
+            ),
+            Ok(())
+        );
+
+        assert_eq!(
+            s.poll_server(),
+            Ok((uni, Event::WebTransportStream { session_id }))
+        );
+        assert_eq!(s.poll_server(), Ok((uni, Event::Data)));
+        assert_eq!(s.recv_body_server(uni, &mut buf), Ok(5));
+        assert_eq!(&buf[..5], b"early");
 
         s.advance().ok();
 
-        assert_eq!(s.poll_server(), Ok((r3_id, Event::Data)));
-        assert_eq!(s.poll_server(), Err(Error::Done));
+        let ev_headers = Event::Headers {
+            list: resp,
+            more_frames: true,
+        };
 
-        assert_eq!(s.recv_body_server(r3_id, &mut recv_buf), Ok(5));
+        assert_eq!(s.poll_client(), Ok((session_id, ev_headers)));
 
-        assert_eq!(s.pipe.client.stream_send(r3_id, &bytes[5..], false), Ok(5));
+        // Server asks the client to wind down the session.
+        assert_eq!(
+            s.server
+                .drain_webtransport_session(&mut s.pipe.server, session_id),
+            Ok(())
+        );
         s.advance().ok();
 
-        assert_eq!(s.poll_server(), Ok((r3_id, Event::Data)));
-        assert_eq!(s.poll_server(), Err(Error::Done));
-
-        assert_eq!(s.recv_body_server(r3_id, &mut recv_buf), Ok(5));
+        assert_eq!(
+            s.poll_client(),
+            Ok((session_id, Event::WebTransportSessionDrain))
+        );
+        assert_eq!(s.poll_client(), Err(Error::Done));
 
-        // Buffer multiple data frames.
-        let body = s.send_body_client(r3_id, false).unwrap();
-        s.send_body_client(r3_id, false).unwrap();
-        s.send_body_client(r3_id, false).unwrap();
+        // Client finishes the CONNECT stream without a capsule.
+        assert_eq!(s.pipe.client.stream_send(session_id, b"", true), Ok(0));
+        s.advance().ok();
 
-        assert_eq!(s.poll_server(), Ok((r3_id, Event::Data)));
+        assert_eq!(
+            s.poll_server(),
+            Ok((session_id, Event::WebTransportSessionClosed {
+                error_code: 0,
+                reason: Vec::new(),
+            }))
+        );
         assert_eq!(s.poll_server(), Err(Error::Done));
+        s.advance().ok();
 
-        {
-            let mut d = [42; 10];
-            let mut b = octets::OctetsMut::with_slice(&mut d);
-
-            b.put_varint(frame::DATA_FRAME_TYPE_ID).unwrap();
-            b.put_varint(0).unwrap();
-            let off = b.off();
-            s.pipe.client.stream_send(r3_id, &d[..off], true).unwrap();
-
-            s.advance().ok();
-        }
-
-        let mut recv_buf = vec![0; bytes.len() * 3];
+        // The server stopped the session's stream, so the client reset it.
+        assert!(s.pipe.client.streams.is_collected(uni));
 
-        assert_eq!(s.recv_body_server(r3_id, &mut recv_buf), Ok(body.len() * 3));
+        assert_eq!(
+            s.poll_client(),
+            Ok((session_id, Event::WebTransportSessionClosed {
+                error_code: 0,
+                reason: Vec::new(),
+            }))
+        );
+        assert_eq!(s.poll_client(), Err(Error::Done));
     }
 
     #[test]
-    /// Tests that the Datagram event is properly re-armed.
-    fn dgram_event_rearm() {
+    /// Tests that HTTP Datagrams fall back to capsules when the peer didn't
+    /// enable HTTP/3 DATAGRAM frames.
+    fn http_datagram_capsule_fallback() {
         let mut buf = [0; 65535];
 
         let mut config = crate::Config::new(crate::PROTOCOL_VERSION).unwrap();
@@ -7074,76 +10358,149 @@ mod tests {
         config.set_initial_max_stream_data_bidi_local(150);
         config.set_initial_max_stream_data_bidi_remote(150);
         config.set_initial_max_stream_data_uni(150);
-        config.set_initial_max_streams_bidi(100);
+        config.set_initial_max_streams_bidi(5);
         config.set_initial_max_streams_uni(5);
         config.verify_peer(false);
-        config.enable_dgram(true, 100, 100);
 
-        let h3_config = Config::new().unwrap();
+        let mut h3_config = Config::new().unwrap();
+        h3_config.enable_extended_connect(true);
+
         let mut s = Session::with_configs(&mut config, &h3_config).unwrap();
         s.handshake().unwrap();
 
-        // 10 bytes on flow ID 0 and 2.
-        let flow_0_result = (11, 0, 1);
-        let flow_2_result = (11, 2, 1);
+        let req = connect_udp_request();
 
-        // Send requests followed by DATAGRAMs on client side.
-        let (stream, req) = s.send_request(false).unwrap();
+        let stream = s
+            .client
+            .send_request(&mut s.pipe.client, &req, false)
+            .unwrap();
+        s.advance().ok();
 
-        let body = s.send_body_client(stream, true).unwrap();
+        // Capsules can't be sent before the response headers.
+        assert_eq!(
+            s.server.send_capsule(
+                &mut s.pipe.server,
+                stream,
+                &Capsule::DrainWebTransportSession,
+                false
+            ),
+            Err(Error::FrameUnexpected)
+        );
 
-        let mut recv_buf = vec![0; body.len()];
+        let ev_headers = Event::Headers {
+            list: req,
+            more_frames: true,
+        };
+
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+
+        let resp = s.send_response(stream, false).unwrap();
+
+        let ev_headers = Event::Headers {
+            list: resp,
+            more_frames: true,
+        };
+
+        assert_eq!(s.poll_client(), Ok((stream, ev_headers)));
+
+        assert!(!s.server.dgram_enabled_by_peer(&s.pipe.server));
+
+        assert_eq!(
+            s.server
+                .send_http_datagram(&mut s.pipe.server, stream, b"hello"),
+            Ok(())
+        );
+        s.advance().ok();
+
+        assert_eq!(s.poll_client(), Ok((stream, Event::Data)));
+
+        let len = s.recv_body_client(stream, &mut buf).unwrap();
+
+        let mut decoder = capsule::CapsuleDecoder::default();
+        decoder.push(&buf[..len]);
+
+        assert_eq!(
+            decoder.next_capsule(),
+            Ok(Capsule::Datagram {
+                payload: b"hello".to_vec()
+            })
+        );
+        assert_eq!(decoder.next_capsule(), Err(Error::Done));
+    }
+
+    fn connect_udp_request() -> Vec<Header> {
+        vec![
+            Header::new(b":method", b"CONNECT"),
+            Header::new(b":protocol", b"connect-udp"),
+            Header::new(b":scheme", b"https"),
+            Header::new(b":authority", b"quic.tech"),
+            Header::new(b":path", b"/.well-known/masque/udp/quic.tech/443/"),
+        ]
+    }
+
+    #[test]
+    /// Tests that capsules sent on an extended CONNECT stream are received as
+    /// the stream's body.
+    fn send_capsule() {
+        let mut buf = [0; 65535];
+
+        let mut s = webtransport_handshake();
+
+        let req = connect_udp_request();
+
+        let stream = s
+            .client
+            .send_request(&mut s.pipe.client, &req, false)
+            .unwrap();
+        s.advance().ok();
 
         let ev_headers = Event::Headers {
             list: req,
             more_frames: true,
         };
 
-        s.send_dgram_client(0).unwrap();
-        s.send_dgram_client(0).unwrap();
-        s.send_dgram_client(2).unwrap();
-        s.send_dgram_client(2).unwrap();
-
         assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_server(), Ok((stream, Event::Data)));
 
-        assert_eq!(s.poll_server(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_0_result));
+        let resp = s.send_response(stream, false).unwrap();
 
-        assert_eq!(s.poll_server(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_0_result));
+        let ev_headers = Event::Headers {
+            list: resp,
+            more_frames: true,
+        };
 
-        assert_eq!(s.poll_server(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_2_result));
+        assert_eq!(s.poll_client(), Ok((stream, ev_headers)));
 
-        assert_eq!(s.poll_server(), Err(Error::Done));
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_2_result));
+        let capsule = Capsule::Datagram {
+            payload: b"hello".to_vec(),
+        };
 
-        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(
+            s.client
+                .send_capsule(&mut s.pipe.client, stream, &capsule, true),
+            Ok(())
+        );
+        s.advance().ok();
 
-        s.send_dgram_client(0).unwrap();
-        s.send_dgram_client(2).unwrap();
+        assert_eq!(s.poll_server(), Ok((stream, Event::Data)));
 
-        assert_eq!(s.poll_server(), Err(Error::Done));
+        let len = s.recv_body_server(stream, &mut buf).unwrap();
 
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_0_result));
-        assert_eq!(s.poll_server(), Err(Error::Done));
+        let mut decoder = capsule::CapsuleDecoder::default();
+        decoder.push(&buf[..len]);
 
-        assert_eq!(s.recv_dgram_server(&mut buf), Ok(flow_2_result));
-        assert_eq!(s.poll_server(), Err(Error::Done));
+        assert_eq!(decoder.next_capsule(), Ok(capsule));
+        assert_eq!(decoder.next_capsule(), Err(Error::Done));
 
-        assert_eq!(s.recv_body_server(stream, &mut recv_buf), Ok(body.len()));
         assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
+        assert_eq!(s.poll_server(), Err(Error::Done));
     }
 
     #[test]
-    fn reset_stream() {
-        let mut buf = [0; 65535];
-
-        let mut s = Session::new().unwrap();
-        s.handshake().unwrap();
+    /// Tests that capsules can't be sent on a stream that doesn't carry an
+    /// extended CONNECT request.
+    fn send_capsule_not_extended_connect() {
+        let mut s = webtransport_handshake();
 
-        // Client sends request.
         let (stream, req) = s.send_request(false).unwrap();
 
         let ev_headers = Event::Headers {
@@ -7151,206 +10508,223 @@ mod tests {
             more_frames: true,
         };
 
-        // Server sends response and closes stream.
         assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_server(), Err(Error::Done));
 
-        let resp = s.send_response(stream, true).unwrap();
+        let resp = s.send_response(stream, false).unwrap();
 
         let ev_headers = Event::Headers {
             list: resp,
-            more_frames: false,
+            more_frames: true,
         };
 
         assert_eq!(s.poll_client(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_client(), Ok((stream, Event::Finished)));
-        assert_eq!(s.poll_client(), Err(Error::Done));
 
-        // Client sends RESET_STREAM, closing stream.
-        let frames = [crate::frame::Frame::ResetStream {
-            stream_id: stream,
-            error_code: 42,
-            final_size: 68,
-        }];
+        let capsule = Capsule::Datagram {
+            payload: b"hello".to_vec(),
+        };
 
-        let pkt_type = crate::packet::Type::Short;
         assert_eq!(
-            s.pipe.send_pkt_to_server(pkt_type, &frames, &mut buf),
-            Ok(39)
+            s.client
+                .send_capsule(&mut s.pipe.client, stream, &capsule, false),
+            Err(Error::FrameUnexpected)
         );
 
-        // Server issues Reset event for the stream.
-        assert_eq!(s.poll_server(), Ok((stream, Event::Reset(42))));
-        assert_eq!(s.poll_server(), Err(Error::Done));
-
-        // Sending RESET_STREAM again shouldn't trigger another Reset event.
         assert_eq!(
-            s.pipe.send_pkt_to_server(pkt_type, &frames, &mut buf),
-            Ok(39)
+            s.server
+                .send_capsule(&mut s.pipe.server, stream, &capsule, false),
+            Err(Error::FrameUnexpected)
         );
 
+        // Nothing was written to the stream.
+        s.advance().ok();
+
+        assert_eq!(s.poll_client(), Err(Error::Done));
         assert_eq!(s.poll_server(), Err(Error::Done));
     }
 
     #[test]
-    fn reset_finished_at_server() {
-        let mut s = Session::new().unwrap();
-        s.handshake().unwrap();
+    /// Tests that HTTP Datagrams are sent in QUIC DATAGRAM frames prefixed
+    /// with the Quarter Stream ID when the peer enabled HTTP/3 DATAGRAM
+    /// frames.
+    fn send_http_datagram() {
+        let mut buf = [0; 65535];
 
-        // Client sends HEADERS and doesn't fin
-        let (stream, _req) = s.send_request(false).unwrap();
+        let mut s = webtransport_handshake();
+
+        let req = connect_udp_request();
+
+        let stream = s
+            .client
+            .send_request(&mut s.pipe.client, &req, false)
+            .unwrap();
+        s.advance().ok();
+
+        let ev_headers = Event::Headers {
+            list: req,
+            more_frames: true,
+        };
+
+        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
+
+        assert!(s.server.dgram_enabled_by_peer(&s.pipe.server));
 
-        // ..then Client sends RESET_STREAM
         assert_eq!(
-            s.pipe.client.stream_shutdown(0, crate::Shutdown::Write, 0),
+            s.server
+                .send_http_datagram(&mut s.pipe.server, stream, b"hello"),
             Ok(())
         );
+        s.advance().ok();
 
-        assert_eq!(s.pipe.advance(), Ok(()));
-
-        // Server receives just a reset
-        assert_eq!(s.poll_server(), Ok((stream, Event::Reset(0))));
-        assert_eq!(s.poll_server(), Err(Error::Done));
+        // The payload isn't sent on the request stream.
+        assert_eq!(s.poll_client(), Err(Error::Done));
 
-        // Client sends HEADERS and fin
-        let (stream, req) = s.send_request(true).unwrap();
+        assert_eq!(s.recv_dgram_client(&mut buf), Ok((6, stream / 4, 1)));
+        assert_eq!(&buf[1..6], b"hello");
 
-        // ..then Client sends RESET_STREAM
         assert_eq!(
-            s.pipe.client.stream_shutdown(4, crate::Shutdown::Write, 0),
+            s.client
+                .send_http_datagram(&mut s.pipe.client, stream, b"world"),
             Ok(())
         );
+        s.advance().ok();
 
-        let ev_headers = Event::Headers {
-            list: req,
-            more_frames: false,
-        };
-
-        // Server receives headers and fin.
-        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
         assert_eq!(s.poll_server(), Err(Error::Done));
+
+        assert_eq!(s.recv_dgram_server(&mut buf), Ok((6, stream / 4, 1)));
+        assert_eq!(&buf[1..6], b"world");
     }
 
     #[test]
-    fn reset_finished_at_server_with_data_pending() {
-        let mut s = Session::new().unwrap();
-        s.handshake().unwrap();
+    /// Tests that HTTP Datagrams can't fall back to capsules on a stream that
+    /// doesn't carry an extended CONNECT request.
+    fn send_http_datagram_fallback_not_extended_connect() {
+        let mut config = crate::Config::new(crate::PROTOCOL_VERSION).unwrap();
+        config
+            .load_cert_chain_from_pem_file("examples/cert.crt")
+            .unwrap();
+        config
+            .load_priv_key_from_pem_file("examples/cert.key")
+            .unwrap();
+        config.set_application_protos(&[b"h3"]).unwrap();
+        config.set_initial_max_data(1500);
+        config.set_initial_max_stream_data_bidi_local(150);
+        config.set_initial_max_stream_data_bidi_remote(150);
+        config.set_initial_max_stream_data_uni(150);
+        config.set_initial_max_streams_bidi(5);
+        config.set_initial_max_streams_uni(5);
+        config.verify_peer(false);
 
-        // Client sends HEADERS and doesn't fin.
-        let (stream, req) = s.send_request(false).unwrap();
+        let h3_config = Config::new().unwrap();
 
-        assert!(s.send_body_client(stream, false).is_ok());
+        let mut s = Session::with_configs(&mut config, &h3_config).unwrap();
+        s.handshake().unwrap();
 
-        assert_eq!(s.pipe.advance(), Ok(()));
+        let (stream, req) = s.send_request(false).unwrap();
 
         let ev_headers = Event::Headers {
             list: req,
             more_frames: true,
         };
 
-        // Server receives headers and data...
         assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_server(), Ok((stream, Event::Data)));
 
-        // ..then Client sends RESET_STREAM.
+        let resp = s.send_response(stream, false).unwrap();
+
+        let ev_headers = Event::Headers {
+            list: resp,
+            more_frames: true,
+        };
+
+        assert_eq!(s.poll_client(), Ok((stream, ev_headers)));
+
+        assert!(!s.server.dgram_enabled_by_peer(&s.pipe.server));
+
         assert_eq!(
-            s.pipe
-                .client
-                .stream_shutdown(stream, crate::Shutdown::Write, 0),
-            Ok(())
+            s.server
+                .send_http_datagram(&mut s.pipe.server, stream, b"hello"),
+            Err(Error::FrameUnexpected)
         );
+        s.advance().ok();
 
-        assert_eq!(s.pipe.advance(), Ok(()));
-
-        // Server receives the reset and there are no more readable streams.
-        assert_eq!(s.poll_server(), Ok((stream, Event::Reset(0))));
-        assert_eq!(s.poll_server(), Err(Error::Done));
-        assert_eq!(s.pipe.server.readable().len(), 0);
+        assert_eq!(s.poll_client(), Err(Error::Done));
     }
 
     #[test]
-    fn reset_finished_at_client() {
-        let mut buf = [0; 65535];
-        let mut s = Session::new().unwrap();
-        s.handshake().unwrap();
+    /// Tests that WebTransport capsules received in a single read are all
+    /// reported.
+    fn webtransport_capsules() {
+        let mut s = webtransport_handshake();
 
-        // Client sends HEADERS and doesn't fin
-        let (stream, req) = s.send_request(false).unwrap();
+        let req = webtransport_request();
+
+        let session_id = s
+            .client
+            .open_webtransport_session(&mut s.pipe.client, &req)
+            .unwrap();
+        s.advance().ok();
 
         let ev_headers = Event::Headers {
             list: req,
             more_frames: true,
         };
 
-        // Server receives headers.
-        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_server(), Err(Error::Done));
-
-        // Server sends response and doesn't fin
-        s.send_response(stream, false).unwrap();
+        assert_eq!(s.poll_server(), Ok((session_id, ev_headers)));
 
-        assert_eq!(s.pipe.advance(), Ok(()));
+        let resp = vec![Header::new(b":status", b"200")];
 
-        // .. then Server sends RESET_STREAM
         assert_eq!(
-            s.pipe
-                .server
-                .stream_shutdown(stream, crate::Shutdown::Write, 0),
+            s.server.accept_webtransport_session(
+                &mut s.pipe.server,
+                session_id,
+                &resp
+            ),
             Ok(())
         );
-
-        assert_eq!(s.pipe.advance(), Ok(()));
-
-        // Client receives Reset only
-        assert_eq!(s.poll_client(), Ok((stream, Event::Reset(0))));
-        assert_eq!(s.poll_server(), Err(Error::Done));
-
-        // Client sends headers and fin.
-        let (stream, req) = s.send_request(true).unwrap();
+        s.advance().ok();
 
         let ev_headers = Event::Headers {
-            list: req,
-            more_frames: false,
+            list: resp,
+            more_frames: true,
         };
 
-        // Server receives headers and fin.
-        assert_eq!(s.poll_server(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_server(), Ok((stream, Event::Finished)));
-        assert_eq!(s.poll_server(), Err(Error::Done));
-
-        // Server sends response and fin
-        let resp = s.send_response(stream, true).unwrap();
-
-        assert_eq!(s.pipe.advance(), Ok(()));
+        assert_eq!(s.poll_client(), Ok((session_id, ev_headers)));
 
-        // ..then Server sends RESET_STREAM
-        let frames = [crate::frame::Frame::ResetStream {
-            stream_id: stream,
-            error_code: 42,
-            final_size: 68,
-        }];
+        let capsule = Capsule::Datagram {
+            payload: b"hello".to_vec(),
+        };
 
-        let pkt_type = crate::packet::Type::Short;
         assert_eq!(
-            s.pipe.send_pkt_to_server(pkt_type, &frames, &mut buf),
-            Ok(39)
+            s.server.send_capsule(
+                &mut s.pipe.server,
+                session_id,
+                &capsule,
+                false
+            ),
+            Ok(())
         );
 
-        assert_eq!(s.pipe.advance(), Ok(()));
-
-        let ev_headers = Event::Headers {
-            list: resp,
-            more_frames: false,
-        };
+        assert_eq!(
+            s.server
+                .drain_webtransport_session(&mut s.pipe.server, session_id),
+            Ok(())
+        );
+        s.advance().ok();
 
-        // Client receives headers and fin.
-        assert_eq!(s.poll_client(), Ok((stream, ev_headers)));
-        assert_eq!(s.poll_client(), Ok((stream, Event::Finished)));
+        assert_eq!(
+            s.poll_client(),
+            Ok((session_id, Event::WebTransportDatagram {
+                payload: b"hello".to_vec()
+            }))
+        );
+        assert_eq!(
+            s.poll_client(),
+            Ok((session_id, Event::WebTransportSessionDrain))
+        );
         assert_eq!(s.poll_client(), Err(Error::Done));
     }
 }
 
+pub mod capsule;
 #[cfg(feature = "ffi")]
 mod ffi;
 #[cfg(feature = "internal")]
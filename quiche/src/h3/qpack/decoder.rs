@@ -29,10 +29,26 @@ use super::Result;
 
 use crate::h3::Header;
 
+use crate::stream::StreamIdHashMap;
+
+use super::dynamic_table::DynamicTable;
+
+use super::encoder::encode_int;
+
 use super::INDEXED;
 use super::INDEXED_WITH_POST_BASE;
+use super::INSERT_COUNT_INCREMENT;
+use super::INSERT_WITH_LITERAL_NAME;
+use super::INSERT_WITH_NAME_REF;
 use super::LITERAL;
 use super::LITERAL_WITH_NAME_REF;
+use super::SECTION_ACKNOWLEDGEMENT;
+use super::SET_DYNAMIC_TABLE_CAPACITY;
+use super::STREAM_CANCELLATION;
+
+// The maximum number of bytes, in addition to the table capacity, that a
+// single encoder instruction can take.
+const MAX_INSTRUCTION_OVERHEAD: u64 = 32;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum Representation {
@@ -67,33 +83,364 @@ impl Representation {
 
 /// A QPACK decoder.
 #[derive(Default)]
-pub struct Decoder {}
+pub struct Decoder {
+    /// The dynamic table, as populated by the peer's encoder.
+    table: DynamicTable,
+
+    /// The maximum number of streams that can be blocked at the same time.
+    max_blocked_streams: u64,
+
+    /// Streams that are waiting for dynamic table insertions, mapped to the
+    /// Required Insert Count of their field section.
+    blocked_streams: StreamIdHashMap<u64>,
+
+    /// The Insert Count that the peer's encoder is known to have received
+    /// acknowledgement for.
+    known_received_count: u64,
+
+    /// Encoder instruction data received but not processed yet, because it
+    /// contains an incomplete instruction.
+    encoder_buf: Vec<u8>,
+
+    /// Decoder instructions waiting to be sent to the peer.
+    instructions: Vec<u8>,
+}
 
 impl Decoder {
     /// Creates a new QPACK decoder.
+    ///
+    /// The decoder doesn't allow the peer to use the dynamic table.
     pub fn new() -> Decoder {
         Decoder::default()
     }
 
+    /// Creates a new QPACK decoder with the given dynamic table limits.
+    ///
+    /// The values must match the `SETTINGS_QPACK_MAX_TABLE_CAPACITY` and
+    /// `SETTINGS_QPACK_BLOCKED_STREAMS` settings advertised to the peer.
+    pub fn with_limits(
+        max_table_capacity: u64, max_blocked_streams: u64,
+    ) -> Decoder {
+        Decoder {
+            table: DynamicTable::new(max_table_capacity),
+            max_blocked_streams,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the number of insertions into the dynamic table.
+    pub fn insert_count(&self) -> u64 {
+        self.table.insert_count()
+    }
+
+    /// Returns the number of streams currently blocked on dynamic table
+    /// updates.
+    pub fn blocked_streams(&self) -> usize {
+        self.blocked_streams.len()
+    }
+
     /// Processes control instructions from the encoder.
-    pub fn control(&mut self, _buf: &mut [u8]) -> Result<()> {
-        // TODO: process control instructions
+    ///
+    /// Instructions can be split across multiple calls, in which case the
+    /// incomplete data is buffered until the rest of it is provided.
+    pub fn control(&mut self, buf: &[u8]) -> Result<()> {
+        let mut data = std::mem::take(&mut self.encoder_buf);
+        data.extend_from_slice(buf);
+
+        let mut b = octets::Octets::with_slice(&data);
+
+        while b.cap() > 0 {
+            let start = b.off();
+
+            match self.process_instruction(&mut b) {
+                Ok(_) => (),
+
+                // Wait for the rest of the instruction to be received.
+                Err(Error::BufferTooShort) => {
+                    let partial = &data[start..];
+
+                    // A valid instruction can't be larger than the table, so
+                    // don't keep buffering data forever.
+                    if partial.len() as u64 >
+                        self.table.max_capacity() + MAX_INSTRUCTION_OVERHEAD
+                    {
+                        return Err(Error::DynamicTableCapacityExceeded);
+                    }
+
+                    self.encoder_buf = partial.to_vec();
+
+                    break;
+                },
+
+                Err(e) => return Err(e),
+            }
+        }
+
         Ok(())
     }
 
     /// Decodes a QPACK header block into a list of headers.
+    ///
+    /// The [`Blocked`] error is returned when the header block references
+    /// dynamic table entries that have not been received yet.
+    ///
+    /// [`Blocked`]: enum.Error.html#variant.Blocked
     pub fn decode(&mut self, buf: &[u8], max_size: u64) -> Result<Vec<Header>> {
+        self.decode_block(None, buf, max_size)
+    }
+
+    /// Decodes a QPACK header block received on the given stream into a list
+    /// of headers.
+    ///
+    /// Unlike [`decode()`], this keeps track of blocked streams and queues the
+    /// required Section Acknowledgement instructions.
+    ///
+    /// When the header block references dynamic table entries that have not
+    /// been received yet, the stream is marked as blocked and the [`Blocked`]
+    /// error is returned. The header block should be decoded again once
+    /// [`next_unblocked_stream()`] returns the stream's ID. If this would
+    /// exceed the blocked streams limit, [`TooManyBlockedStreams`] is returned
+    /// instead.
+    ///
+    /// [`decode()`]: struct.Decoder.html#method.decode
+    /// [`next_unblocked_stream()`]:
+    ///     struct.Decoder.html#method.next_unblocked_stream
+    /// [`Blocked`]: enum.Error.html#variant.Blocked
+    /// [`TooManyBlockedStreams`]: enum.Error.html#variant.TooManyBlockedStreams
+    pub fn decode_stream(
+        &mut self, stream_id: u64, buf: &[u8], max_size: u64,
+    ) -> Result<Vec<Header>> {
+        self.decode_block(Some(stream_id), buf, max_size)
+    }
+
+    /// Returns the ID of a blocked stream that can now be decoded, if any.
+    pub fn next_unblocked_stream(&self) -> Option<u64> {
+        let insert_count = self.table.insert_count();
+
+        self.blocked_streams
+            .iter()
+            .filter(|(_, req_insert_count)| **req_insert_count <= insert_count)
+            .map(|(stream_id, _)| *stream_id)
+            .min()
+    }
+
+    /// Notifies the decoder that the given stream was reset, or that the
+    /// application stopped reading from it.
+    ///
+    /// This queues a Stream Cancellation instruction for the peer's encoder.
+    pub fn cancel_stream(&mut self, stream_id: u64) {
+        self.blocked_streams.remove(&stream_id);
+
+        // A decoder that doesn't allow a dynamic table can omit Stream
+        // Cancellation instructions.
+        if self.table.max_capacity() > 0 {
+            self.queue_instruction(stream_id, STREAM_CANCELLATION, 6);
+        }
+    }
+
+    /// Returns the decoder instructions that need to be sent to the peer on
+    /// the QPACK decoder stream.
+    ///
+    /// Once some of the data is sent, [`instructions_sent()`] must be called
+    /// with the number of bytes written.
+    ///
+    /// [`instructions_sent()`]: struct.Decoder.html#method.instructions_sent
+    pub fn pending_instructions(&mut self) -> &[u8] {
+        let insert_count = self.table.insert_count();
+
+        if insert_count > self.known_received_count {
+            self.queue_instruction(
+                insert_count - self.known_received_count,
+                INSERT_COUNT_INCREMENT,
+                6,
+            );
+
+            self.known_received_count = insert_count;
+        }
+
+        &self.instructions
+    }
+
+    /// Marks `len` bytes of pending decoder instructions as sent.
+    pub fn instructions_sent(&mut self, len: usize) {
+        let len = std::cmp::min(len, self.instructions.len());
+
+        self.instructions.drain(..len);
+    }
+
+    fn queue_instruction(&mut self, v: u64, first: u8, prefix: usize) {
+        let mut d = [0; 16];
+        let mut b = octets::OctetsMut::with_slice(&mut d);
+
+        // The buffer is always large enough for a 64-bit integer.
+        if encode_int(v, first, prefix, &mut b).is_ok() {
+            let off = b.off();
+            self.instructions.extend_from_slice(&d[..off]);
+        }
+    }
+
+    fn process_instruction(&mut self, b: &mut octets::Octets) -> Result<()> {
+        let first = b.peek_u8()?;
+
+        if first & INSERT_WITH_NAME_REF == INSERT_WITH_NAME_REF {
+            const STATIC: u8 = 0x40;
+
+            let s = first & STATIC == STATIC;
+            let name_idx = decode_int(b, 6)?;
+            let value = decode_str(b, 7)?;
+
+            trace!(
+                "Insert With Name Reference name_idx={name_idx} static={s} value={value:?}"
+            );
+
+            let name = if s {
+                lookup_static(name_idx)?.0.to_vec()
+            } else {
+                let abs_idx = self.insert_relative_to_absolute(name_idx)?;
+
+                self.table
+                    .get(abs_idx)
+                    .ok_or(Error::InvalidDynamicTableIndex)?
+                    .0
+                    .to_vec()
+            };
+
+            self.table.insert(name, value)?;
+        } else if first & INSERT_WITH_LITERAL_NAME == INSERT_WITH_LITERAL_NAME {
+            let name = decode_str(b, 5)?;
+            let value = decode_str(b, 7)?;
+
+            trace!("Insert With Literal Name name={name:?} value={value:?}");
+
+            self.table.insert(name, value)?;
+        } else if first & SET_DYNAMIC_TABLE_CAPACITY == SET_DYNAMIC_TABLE_CAPACITY
+        {
+            let capacity = decode_int(b, 5)?;
+
+            trace!("Set Dynamic Table Capacity capacity={capacity}");
+
+            self.table.set_capacity(capacity)?;
+        } else {
+            let idx = decode_int(b, 5)?;
+
+            trace!("Duplicate index={idx}");
+
+            let abs_idx = self.insert_relative_to_absolute(idx)?;
+
+            let (name, value) = self
+                .table
+                .get(abs_idx)
+                .ok_or(Error::InvalidDynamicTableIndex)?;
+
+            let (name, value) = (name.to_vec(), value.to_vec());
+
+            self.table.insert(name, value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Converts an index relative to the current Insert Count, as used by
+    /// encoder instructions, into an absolute index.
+    fn insert_relative_to_absolute(&self, idx: u64) -> Result<u64> {
+        self.table
+            .insert_count()
+            .checked_sub(idx)
+            .and_then(|v| v.checked_sub(1))
+            .ok_or(Error::InvalidDynamicTableIndex)
+    }
+
+    /// Decodes the Required Insert Count from its encoded form, as described
+    /// in [Section 4.5.1.1 of RFC 9204].
+    ///
+    /// [Section 4.5.1.1 of RFC 9204]: https://www.rfc-editor.org/rfc/rfc9204.html#section-4.5.1.1
+    fn decode_req_insert_count(&self, encoded: u64) -> Result<u64> {
+        if encoded == 0 {
+            return Ok(0);
+        }
+
+        let max_entries = self.table.max_entries();
+        let full_range = 2 * max_entries;
+
+        if encoded > full_range {
+            return Err(Error::InvalidHeaderBlockPrefix);
+        }
+
+        let max_value = self.table.insert_count() + max_entries;
+        let max_wrapped = (max_value / full_range) * full_range;
+
+        let mut req_insert_count = max_wrapped + encoded - 1;
+
+        if req_insert_count > max_value {
+            if req_insert_count <= full_range {
+                return Err(Error::InvalidHeaderBlockPrefix);
+            }
+
+            req_insert_count -= full_range;
+        }
+
+        if req_insert_count == 0 {
+            return Err(Error::InvalidHeaderBlockPrefix);
+        }
+
+        Ok(req_insert_count)
+    }
+
+    fn lookup_dynamic(
+        &self, abs_idx: u64, req_insert_count: u64,
+    ) -> Result<(&[u8], &[u8])> {
+        // Field lines can't reference entries beyond the Required Insert Count.
+        if abs_idx >= req_insert_count {
+            return Err(Error::InvalidDynamicTableIndex);
+        }
+
+        self.table
+            .get(abs_idx)
+            .ok_or(Error::InvalidDynamicTableIndex)
+    }
+
+    fn decode_block(
+        &mut self, stream_id: Option<u64>, buf: &[u8], max_size: u64,
+    ) -> Result<Vec<Header>> {
         let mut b = octets::Octets::with_slice(buf);
 
         let mut out = Vec::new();
 
         let mut left = max_size;
 
-        let req_insert_count = decode_int(&mut b, 8)?;
-        let base = decode_int(&mut b, 7)?;
+        let encoded_insert_count = decode_int(&mut b, 8)?;
+        let req_insert_count =
+            self.decode_req_insert_count(encoded_insert_count)?;
+
+        let sign = b.peek_u8()? & 0x80 == 0x80;
+        let delta_base = decode_int(&mut b, 7)?;
+
+        let base = if sign {
+            req_insert_count
+                .checked_sub(delta_base)
+                .and_then(|v| v.checked_sub(1))
+        } else {
+            req_insert_count.checked_add(delta_base)
+        }
+        .ok_or(Error::InvalidHeaderBlockPrefix)?;
 
         trace!("Header count={req_insert_count} base={base}");
 
+        if req_insert_count > self.table.insert_count() {
+            if let Some(stream_id) = stream_id {
+                if !self.blocked_streams.contains_key(&stream_id) &&
+                    self.blocked_streams.len() as u64 >=
+                        self.max_blocked_streams
+                {
+                    return Err(Error::TooManyBlockedStreams);
+                }
+
+                self.blocked_streams.insert(stream_id, req_insert_count);
+            }
+
+            return Err(Error::Blocked);
+        }
+
         while b.cap() > 0 {
             let first = b.peek_u8()?;
 
@@ -106,12 +453,16 @@ impl Decoder {
 
                     trace!("Indexed index={index} static={s}");
 
-                    if !s {
-                        // TODO: implement dynamic table
-                        return Err(Error::InvalidHeaderValue);
-                    }
+                    let (name, value) = if s {
+                        lookup_static(index)?
+                    } else {
+                        let abs_idx = base
+                            .checked_sub(index)
+                            .and_then(|v| v.checked_sub(1))
+                            .ok_or(Error::InvalidDynamicTableIndex)?;
 
-                    let (name, value) = lookup_static(index)?;
+                        self.lookup_dynamic(abs_idx, req_insert_count)?
+                    };
 
                     left = left
                         .checked_sub((name.len() + value.len()) as u64)
@@ -126,24 +477,24 @@ impl Decoder {
 
                     trace!("Indexed With Post Base index={index}");
 
-                    // TODO: implement dynamic table
-                    return Err(Error::InvalidHeaderValue);
-                },
+                    let abs_idx = base
+                        .checked_add(index)
+                        .ok_or(Error::InvalidDynamicTableIndex)?;
 
-                Representation::Literal => {
-                    let name_huff = b.as_ref()[0] & 0x08 == 0x08;
-                    let name_len = decode_int(&mut b, 3)? as usize;
+                    let (name, value) =
+                        self.lookup_dynamic(abs_idx, req_insert_count)?;
 
-                    let mut name = b.get_bytes(name_len)?;
+                    left = left
+                        .checked_sub((name.len() + value.len()) as u64)
+                        .ok_or(Error::HeaderListTooLarge)?;
 
-                    let name = if name_huff {
-                        super::huffman::decode(&mut name)?
-                    } else {
-                        name.to_vec()
-                    };
+                    let hdr = Header::new(name, value);
+                    out.push(hdr);
+                },
 
-                    let name = name.to_vec();
-                    let value = decode_str(&mut b)?;
+                Representation::Literal => {
+                    let name = decode_str(&mut b, 3)?;
+                    let value = decode_str(&mut b, 7)?;
 
                     trace!(
                         "Literal Without Name Reference name={name:?} value={value:?}",
@@ -164,18 +515,22 @@ impl Decoder {
 
                     let s = first & STATIC == STATIC;
                     let name_idx = decode_int(&mut b, 4)?;
-                    let value = decode_str(&mut b)?;
+                    let value = decode_str(&mut b, 7)?;
 
                     trace!(
                         "Literal name_idx={name_idx} static={s} value={value:?}"
                     );
 
-                    if !s {
-                        // TODO: implement dynamic table
-                        return Err(Error::InvalidHeaderValue);
-                    }
+                    let name = if s {
+                        lookup_static(name_idx)?.0
+                    } else {
+                        let abs_idx = base
+                            .checked_sub(name_idx)
+                            .and_then(|v| v.checked_sub(1))
+                            .ok_or(Error::InvalidDynamicTableIndex)?;
 
-                    let (name, _) = lookup_static(name_idx)?;
+                        self.lookup_dynamic(abs_idx, req_insert_count)?.0
+                    };
 
                     left = left
                         .checked_sub((name.len() + value.len()) as u64)
@@ -189,14 +544,42 @@ impl Decoder {
                 },
 
                 Representation::LiteralWithPostBase => {
-                    trace!("Literal With Post Base");
+                    let name_idx = decode_int(&mut b, 3)?;
+                    let value = decode_str(&mut b, 7)?;
+
+                    trace!(
+                        "Literal With Post Base name_idx={name_idx} value={value:?}"
+                    );
 
-                    // TODO: implement dynamic table
-                    return Err(Error::InvalidHeaderValue);
+                    let abs_idx = base
+                        .checked_add(name_idx)
+                        .ok_or(Error::InvalidDynamicTableIndex)?;
+
+                    let (name, _) =
+                        self.lookup_dynamic(abs_idx, req_insert_count)?;
+
+                    left = left
+                        .checked_sub((name.len() + value.len()) as u64)
+                        .ok_or(Error::HeaderListTooLarge)?;
+
+                    let hdr = Header(name.to_vec(), value);
+                    out.push(hdr);
                 },
             }
         }
 
+        if let Some(stream_id) = stream_id {
+            self.blocked_streams.remove(&stream_id);
+
+            // Acknowledge field sections that reference the dynamic table.
+            if req_insert_count > 0 {
+                self.queue_instruction(stream_id, SECTION_ACKNOWLEDGEMENT, 7);
+
+                self.known_received_count =
+                    std::cmp::max(self.known_received_count, req_insert_count);
+            }
+        }
+
         Ok(out)
     }
 }
@@ -209,7 +592,7 @@ fn lookup_static(idx: u64) -> Result<(&'static [u8], &'static [u8])> {
     Ok(super::static_table::STATIC_DECODE_TABLE[idx as usize])
 }
 
-fn decode_int(b: &mut octets::Octets, prefix: usize) -> Result<u64> {
+pub fn decode_int(b: &mut octets::Octets, prefix: usize) -> Result<u64> {
     let mask = 2u64.pow(prefix as u32) - 1;
 
     let mut val = u64::from(b.get_u8()?);
@@ -240,12 +623,12 @@ fn decode_int(b: &mut octets::Octets, prefix: usize) -> Result<u64> {
     Err(Error::BufferTooShort)
 }
 
-fn decode_str(b: &mut octets::Octets) -> Result<Vec<u8>> {
+fn decode_str(b: &mut octets::Octets, prefix: usize) -> Result<Vec<u8>> {
     let first = b.peek_u8()?;
 
-    let huff = first & 0x80 == 0x80;
+    let huff = first & (1 << prefix) != 0;
 
-    let len = decode_int(b, 7)? as usize;
+    let len = decode_int(b, prefix)? as usize;
 
     let mut val = b.get_bytes(len)?;
 
@@ -285,4 +668,133 @@ mod tests {
 
         assert_eq!(decode_int(&mut b, 8), Ok(42));
     }
+
+    fn from_hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn decode_dynamic() {
+        // Examples from Appendix B of RFC 9204.
+        let mut dec = Decoder::with_limits(220, 1);
+
+        // Set Dynamic Table Capacity, Insert With Name Reference.
+        let encoder_stream = from_hex(
+            "3fbd01c00f7777772e6578616d706c652e636f6dc10c2f73616d706c652f70617468",
+        );
+
+        // Header block referencing the two entries above.
+        let block = from_hex("03811011");
+
+        // The entries are not inserted yet, so the stream is blocked.
+        assert_eq!(dec.decode_stream(4, &block, u64::MAX), Err(Error::Blocked));
+        assert_eq!(dec.blocked_streams(), 1);
+        assert_eq!(dec.next_unblocked_stream(), None);
+
+        // A second blocked stream exceeds the limit.
+        assert_eq!(
+            dec.decode_stream(8, &block, u64::MAX),
+            Err(Error::TooManyBlockedStreams)
+        );
+
+        // Instructions split across multiple calls are buffered.
+        dec.control(&encoder_stream[..10]).unwrap();
+        assert_eq!(dec.insert_count(), 0);
+        dec.control(&encoder_stream[10..]).unwrap();
+        assert_eq!(dec.insert_count(), 2);
+
+        assert_eq!(dec.next_unblocked_stream(), Some(4));
+
+        assert_eq!(
+            dec.decode_stream(4, &block, u64::MAX),
+            Ok(vec![
+                Header::new(b":authority", b"www.example.com"),
+                Header::new(b":path", b"/sample/path"),
+            ])
+        );
+        assert_eq!(dec.blocked_streams(), 0);
+        assert_eq!(dec.next_unblocked_stream(), None);
+
+        // Section Acknowledgement for stream 4.
+        assert_eq!(dec.pending_instructions(), &[0x84]);
+        dec.instructions_sent(1);
+        assert!(dec.pending_instructions().is_empty());
+
+        // Insert With Literal Name.
+        dec.control(&from_hex(
+            "4a637573746f6d2d6b65790c637573746f6d2d76616c7565",
+        ))
+        .unwrap();
+
+        // Insert Count Increment.
+        assert_eq!(dec.pending_instructions(), &[0x01]);
+        dec.instructions_sent(1);
+
+        // Duplicate.
+        dec.control(&from_hex("02")).unwrap();
+        assert_eq!(dec.insert_count(), 4);
+
+        assert_eq!(
+            dec.decode_stream(8, &from_hex("050080c181"), u64::MAX),
+            Ok(vec![
+                Header::new(b":authority", b"www.example.com"),
+                Header::new(b":path", b"/"),
+                Header::new(b"custom-key", b"custom-value"),
+            ])
+        );
+
+        // Section Acknowledgement for stream 8.
+        assert_eq!(dec.pending_instructions(), &[0x88]);
+        dec.instructions_sent(1);
+
+        // Stream Cancellation for stream 12.
+        dec.cancel_stream(12);
+        assert_eq!(dec.pending_instructions(), &[0x4c]);
+    }
+
+    #[test]
+    fn decode_dynamic_invalid() {
+        let mut dec = Decoder::with_limits(220, 1);
+
+        // Capacity larger than the maximum.
+        assert_eq!(
+            dec.control(&from_hex("3fbe01")),
+            Err(Error::DynamicTableCapacityExceeded)
+        );
+
+        // Entry larger than the capacity.
+        let mut dec = Decoder::with_limits(220, 1);
+        assert_eq!(
+            dec.control(&from_hex("c00f7777772e6578616d706c652e636f6d")),
+            Err(Error::DynamicTableCapacityExceeded)
+        );
+
+        // Duplicate of a non-existing entry.
+        let mut dec = Decoder::with_limits(220, 1);
+        assert_eq!(
+            dec.control(&from_hex("3fbd0100")),
+            Err(Error::InvalidDynamicTableIndex)
+        );
+
+        // Dynamic references are not allowed without a dynamic table.
+        let mut dec = Decoder::new();
+        assert_eq!(
+            dec.decode(&from_hex("03811011"), u64::MAX),
+            Err(Error::InvalidHeaderBlockPrefix)
+        );
+
+        // Reference beyond the Required Insert Count.
+        let mut dec = Decoder::with_limits(220, 1);
+        dec.control(&from_hex(
+            "3fbd01c00f7777772e6578616d706c652e636f6dc10c2f73616d706c652f70617468",
+        ))
+        .unwrap();
+        assert_eq!(
+            dec.decode(&from_hex("028011"), u64::MAX),
+            Err(Error::InvalidDynamicTableIndex)
+        );
+    }
 }
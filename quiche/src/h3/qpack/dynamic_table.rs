@@ -0,0 +1,237 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::collections::VecDeque;
+
+use super::Error;
+use super::Result;
+
+/// The per-entry overhead used when calculating the size of a dynamic table
+/// entry, as defined in [Section 3.2.1 of RFC 9204].
+///
+/// [Section 3.2.1 of RFC 9204]: https://www.rfc-editor.org/rfc/rfc9204.html#section-3.2.1
+pub const ENTRY_OVERHEAD: u64 = 32;
+
+/// Returns the size of a dynamic table entry with the given name and value.
+pub fn entry_size(name: &[u8], value: &[u8]) -> u64 {
+    name.len() as u64 + value.len() as u64 + ENTRY_OVERHEAD
+}
+
+/// A QPACK dynamic table.
+///
+/// Entries are addressed by their absolute index, that is, the number of
+/// insertions that happened before the entry was inserted. The oldest entry
+/// is at the front of the table and is the first to be evicted.
+#[derive(Default)]
+pub struct DynamicTable {
+    /// The table entries, from oldest to newest.
+    entries: VecDeque<(Vec<u8>, Vec<u8>)>,
+
+    /// The sum of the sizes of all entries currently in the table.
+    size: u64,
+
+    /// The current capacity of the table.
+    capacity: u64,
+
+    /// The upper bound for the table's capacity.
+    max_capacity: u64,
+
+    /// The total number of insertions into the table.
+    insert_count: u64,
+}
+
+impl DynamicTable {
+    /// Creates a new empty table, whose capacity can never exceed
+    /// `max_capacity`.
+    pub fn new(max_capacity: u64) -> DynamicTable {
+        DynamicTable {
+            max_capacity,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the total number of insertions into the table.
+    pub fn insert_count(&self) -> u64 {
+        self.insert_count
+    }
+
+    /// Returns the current capacity of the table.
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// Returns the maximum capacity of the table.
+    pub fn max_capacity(&self) -> u64 {
+        self.max_capacity
+    }
+
+    /// Returns the maximum number of entries the table can hold.
+    pub fn max_entries(&self) -> u64 {
+        self.max_capacity / ENTRY_OVERHEAD
+    }
+
+    /// Returns the absolute index of the oldest entry in the table.
+    pub fn oldest_index(&self) -> u64 {
+        self.insert_count - self.entries.len() as u64
+    }
+
+    /// Returns the entry at the given absolute index, if it is still in the
+    /// table.
+    pub fn get(&self, abs_idx: u64) -> Option<(&[u8], &[u8])> {
+        let rel = abs_idx.checked_sub(self.oldest_index())?;
+
+        self.entries
+            .get(rel as usize)
+            .map(|(n, v)| (n.as_slice(), v.as_slice()))
+    }
+
+    /// Returns the absolute index of the newest entry with the given name and
+    /// value, if any.
+    pub fn find(&self, name: &[u8], value: &[u8]) -> Option<u64> {
+        let rel = self
+            .entries
+            .iter()
+            .rposition(|(n, v)| n == name && v == value)?;
+
+        Some(self.oldest_index() + rel as u64)
+    }
+
+    /// Returns whether an entry of the given size can be inserted without
+    /// evicting any entry whose absolute index is `evict_limit` or greater.
+    pub fn can_insert(&self, size: u64, evict_limit: u64) -> bool {
+        if size > self.capacity {
+            return false;
+        }
+
+        let mut available = self.capacity - self.size;
+
+        for (idx, (n, v)) in (self.oldest_index()..).zip(&self.entries) {
+            if available >= size || idx >= evict_limit {
+                break;
+            }
+
+            available += entry_size(n, v);
+        }
+
+        available >= size
+    }
+
+    /// Sets the capacity of the table, evicting entries if needed.
+    pub fn set_capacity(&mut self, capacity: u64) -> Result<()> {
+        if capacity > self.max_capacity {
+            return Err(Error::DynamicTableCapacityExceeded);
+        }
+
+        self.capacity = capacity;
+
+        self.evict_to(capacity);
+
+        Ok(())
+    }
+
+    /// Inserts a new entry, evicting old entries to make room for it.
+    ///
+    /// On success the absolute index of the new entry is returned.
+    pub fn insert(&mut self, name: Vec<u8>, value: Vec<u8>) -> Result<u64> {
+        let size = entry_size(&name, &value);
+
+        if size > self.capacity {
+            return Err(Error::DynamicTableCapacityExceeded);
+        }
+
+        self.evict_to(self.capacity - size);
+
+        self.entries.push_back((name, value));
+        self.size += size;
+
+        let idx = self.insert_count;
+        self.insert_count += 1;
+
+        Ok(idx)
+    }
+
+    fn evict_to(&mut self, target: u64) {
+        while self.size > target {
+            match self.entries.pop_front() {
+                Some((n, v)) => self.size -= entry_size(&n, &v),
+
+                None => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_and_evict() {
+        let mut t = DynamicTable::new(100);
+        assert_eq!(t.max_entries(), 3);
+
+        assert_eq!(
+            t.insert(b"a".to_vec(), b"b".to_vec()),
+            Err(Error::DynamicTableCapacityExceeded)
+        );
+
+        assert_eq!(
+            t.set_capacity(101),
+            Err(Error::DynamicTableCapacityExceeded)
+        );
+        assert_eq!(t.set_capacity(80), Ok(()));
+
+        // Each entry uses 34 bytes, so only two fit in the table.
+        assert_eq!(t.insert(b"a".to_vec(), b"1".to_vec()), Ok(0));
+        assert_eq!(t.insert(b"b".to_vec(), b"2".to_vec()), Ok(1));
+
+        assert_eq!(t.insert(b"c".to_vec(), b"3".to_vec()), Ok(2));
+        assert_eq!(t.insert_count(), 3);
+        assert_eq!(t.oldest_index(), 1);
+
+        assert_eq!(t.get(0), None);
+        assert_eq!(t.get(1), Some((&b"b"[..], &b"2"[..])));
+        assert_eq!(t.get(2), Some((&b"c"[..], &b"3"[..])));
+        assert_eq!(t.get(3), None);
+
+        // Shrinking the table evicts entries.
+        assert_eq!(t.set_capacity(40), Ok(()));
+        assert_eq!(t.get(1), None);
+        assert_eq!(t.get(2), Some((&b"c"[..], &b"3"[..])));
+
+        assert_eq!(t.find(b"c", b"3"), Some(2));
+        assert_eq!(t.find(b"c", b"4"), None);
+
+        // The oldest entry can only be evicted if it's below the limit.
+        assert!(t.can_insert(34, 3));
+        assert!(!t.can_insert(34, 2));
+        assert!(!t.can_insert(41, 3));
+
+        assert_eq!(t.set_capacity(0), Ok(()));
+        assert_eq!(t.get(2), None);
+        assert_eq!(t.insert_count(), 3);
+    }
+}
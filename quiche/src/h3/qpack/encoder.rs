@@ -24,25 +24,322 @@
 // NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
 // SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+use std::sync::Arc;
+
+use super::Error;
 use super::Result;
 
 use crate::h3::NameValue;
 
+use crate::stream::StreamIdHashMap;
+
+use super::decoder::decode_int;
+
+use super::dynamic_table::entry_size;
+use super::dynamic_table::DynamicTable;
+
 use super::INDEXED;
+use super::INSERT_WITH_LITERAL_NAME;
+use super::INSERT_WITH_NAME_REF;
 use super::LITERAL;
 use super::LITERAL_WITH_NAME_REF;
+use super::SECTION_ACKNOWLEDGEMENT;
+use super::SET_DYNAMIC_TABLE_CAPACITY;
+use super::STREAM_CANCELLATION;
+
+/// The default upper bound for the dynamic table capacity used by the
+/// encoder, regardless of what the peer allows.
+pub const DEFAULT_MAX_TABLE_CAPACITY: u64 = 16384;
+
+// The maximum number of distinct fields whose repetitions are tracked. Once
+// reached, all counts are reset.
+const MAX_TRACKED_FIELDS: usize = 1024;
+
+// The maximum number of bytes that a single decoder instruction can take.
+const MAX_INSTRUCTION_LEN: usize = 16;
+
+// Fields whose value is expected to change on every message, so inserting them
+// into the dynamic table is just a waste of space. Sensitive fields are also
+// never inserted, to avoid exposing them to compression-based attacks.
+const NEVER_INSERT: &[&[u8]] = &[
+    b":path",
+    b"age",
+    b"authorization",
+    b"content-length",
+    b"date",
+    b"etag",
+    b"if-modified-since",
+    b"if-none-match",
+    b"last-modified",
+    b"location",
+    b"proxy-authorization",
+];
+
+/// The strategy used by the QPACK encoder to decide how to use the dynamic
+/// table.
+///
+/// The dynamic table is only used when the peer advertises a non-zero
+/// `SETTINGS_QPACK_MAX_TABLE_CAPACITY`, regardless of the policy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub enum InsertionPolicy {
+    /// Never use the dynamic table. Fields are encoded using the static table
+    /// and literals only.
+    #[default]
+    Disabled            = 0,
+
+    /// Insert repeated fields into the dynamic table, but only reference
+    /// entries once the peer acknowledged them, so that streams are never
+    /// blocked waiting for encoder instructions.
+    NeverBlock          = 1,
+
+    /// Insert repeated fields into the dynamic table and reference them right
+    /// away, as long as the number of blocked streams stays within the limit
+    /// advertised by the peer.
+    MaximizeCompression = 2,
+}
+
+/// Decides which fields the QPACK encoder inserts into the dynamic table.
+///
+/// The filter is only consulted for fields that aren't already in the dynamic
+/// table, and that don't exactly match a static table entry. Fields that
+/// would take more than a quarter of the table are never inserted, regardless
+/// of the filter.
+pub trait InsertionFilter: Send + Sync {
+    /// Returns whether the field should be inserted into the dynamic table.
+    ///
+    /// `name` is lower case. `repeats` is the number of times the same field
+    /// was previously encoded without being inserted.
+    fn should_insert(&self, name: &[u8], value: &[u8], repeats: u64) -> bool;
+}
+
+/// The default [`InsertionFilter`].
+///
+/// Fields are inserted once they have been repeated the configured number of
+/// times, unless they are expected to change on every message, or are
+/// sensitive.
+///
+/// [`InsertionFilter`]: trait.InsertionFilter.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RepeatedFieldFilter {
+    min_repeats: u64,
+}
+
+impl RepeatedFieldFilter {
+    /// Creates a filter that inserts fields after they have been repeated
+    /// `min_repeats` times, so `0` inserts fields the first time they are
+    /// encoded.
+    pub const fn new(min_repeats: u64) -> RepeatedFieldFilter {
+        RepeatedFieldFilter { min_repeats }
+    }
+}
+
+impl Default for RepeatedFieldFilter {
+    /// Inserts fields the second time they are encoded.
+    fn default() -> RepeatedFieldFilter {
+        RepeatedFieldFilter::new(1)
+    }
+}
+
+impl InsertionFilter for RepeatedFieldFilter {
+    fn should_insert(&self, name: &[u8], _value: &[u8], repeats: u64) -> bool {
+        repeats >= self.min_repeats && !NEVER_INSERT.contains(&name)
+    }
+}
+
+/// A header block that references the dynamic table and hasn't been
+/// acknowledged by the peer yet.
+struct Section {
+    /// The Required Insert Count of the header block.
+    req_insert_count: u64,
+
+    /// The absolute index of the oldest entry referenced by the header block.
+    min_index: u64,
+}
 
 /// A QPACK encoder.
-#[derive(Default)]
-pub struct Encoder {}
+pub struct Encoder {
+    /// The dynamic table, as mirrored by the peer's decoder.
+    table: DynamicTable,
+
+    /// The strategy used to populate and reference the dynamic table.
+    policy: InsertionPolicy,
+
+    /// Decides which fields are inserted into the dynamic table.
+    filter: Arc<dyn InsertionFilter>,
+
+    /// The upper bound for the dynamic table capacity.
+    max_capacity: u64,
+
+    /// The number of times fields that weren't inserted were encoded.
+    repeats: HashMap<(Vec<u8>, Vec<u8>), u64>,
+
+    /// The maximum number of streams that the peer allows to be blocked.
+    max_blocked_streams: u64,
+
+    /// The number of insertions acknowledged by the peer's decoder.
+    known_received_count: u64,
+
+    /// Header blocks that reference the dynamic table and haven't been
+    /// acknowledged yet, by stream.
+    sections: StreamIdHashMap<VecDeque<Section>>,
+
+    /// The stream the last tracked header block was encoded for.
+    last_section: Option<u64>,
+
+    /// Decoder instruction data received but not processed yet, because it
+    /// contains an incomplete instruction.
+    decoder_buf: Vec<u8>,
+
+    /// Encoder instructions waiting to be sent to the peer.
+    instructions: Vec<u8>,
+}
+
+impl Default for Encoder {
+    fn default() -> Encoder {
+        Encoder {
+            table: DynamicTable::default(),
+            policy: InsertionPolicy::default(),
+            filter: Arc::new(RepeatedFieldFilter::default()),
+            max_capacity: DEFAULT_MAX_TABLE_CAPACITY,
+            repeats: HashMap::new(),
+            max_blocked_streams: 0,
+            known_received_count: 0,
+            sections: StreamIdHashMap::default(),
+            last_section: None,
+            decoder_buf: Vec::new(),
+            instructions: Vec::new(),
+        }
+    }
+}
 
 impl Encoder {
     /// Creates a new QPACK encoder.
+    ///
+    /// The encoder never uses the dynamic table.
     pub fn new() -> Encoder {
         Encoder::default()
     }
 
+    /// Creates a new QPACK encoder with the given dynamic table insertion
+    /// policy.
+    pub fn with_policy(policy: InsertionPolicy) -> Encoder {
+        Encoder {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the filter deciding which fields are inserted into the dynamic
+    /// table.
+    ///
+    /// The default is [`RepeatedFieldFilter::default()`].
+    ///
+    /// [`RepeatedFieldFilter::default()`]: struct.RepeatedFieldFilter.html
+    pub fn set_insertion_filter(&mut self, filter: Arc<dyn InsertionFilter>) {
+        self.filter = filter;
+    }
+
+    /// Sets the upper bound for the dynamic table capacity, regardless of
+    /// what the peer allows.
+    ///
+    /// This must be called before [`set_peer_limits()`]. The default value is
+    /// [`DEFAULT_MAX_TABLE_CAPACITY`].
+    ///
+    /// [`set_peer_limits()`]: struct.Encoder.html#method.set_peer_limits
+    /// [`DEFAULT_MAX_TABLE_CAPACITY`]: constant.DEFAULT_MAX_TABLE_CAPACITY.html
+    pub fn set_max_table_capacity(&mut self, v: u64) {
+        self.max_capacity = v;
+    }
+
+    /// Configures the dynamic table using the peer's settings.
+    ///
+    /// The values must match the `SETTINGS_QPACK_MAX_TABLE_CAPACITY` and
+    /// `SETTINGS_QPACK_BLOCKED_STREAMS` settings advertised by the peer. This
+    /// queues the Set Dynamic Table Capacity instruction, unless the dynamic
+    /// table is not going to be used.
+    pub fn set_peer_limits(
+        &mut self, max_table_capacity: u64, max_blocked_streams: u64,
+    ) {
+        let capacity = std::cmp::min(max_table_capacity, self.max_capacity);
+
+        if self.policy == InsertionPolicy::Disabled ||
+            capacity == 0 ||
+            self.table.max_capacity() > 0
+        {
+            return;
+        }
+
+        self.table = DynamicTable::new(max_table_capacity);
+        self.max_blocked_streams = max_blocked_streams;
+
+        // The capacity is always within the table's limit.
+        if self.table.set_capacity(capacity).is_ok() {
+            self.queue_int(capacity, SET_DYNAMIC_TABLE_CAPACITY, 5);
+        }
+    }
+
+    /// Returns the number of insertions into the dynamic table.
+    pub fn insert_count(&self) -> u64 {
+        self.table.insert_count()
+    }
+
+    /// Returns the number of insertions acknowledged by the peer.
+    pub fn known_received_count(&self) -> u64 {
+        self.known_received_count
+    }
+
+    /// Returns the number of streams that might currently be blocked on the
+    /// peer's decoder.
+    pub fn blocked_streams(&self) -> usize {
+        self.sections
+            .values()
+            .filter(|s| self.is_blocking(s))
+            .count()
+    }
+
+    /// Processes control instructions from the decoder.
+    ///
+    /// Instructions can be split across multiple calls, in which case the
+    /// incomplete data is buffered until the rest of it is provided.
+    pub fn control(&mut self, buf: &[u8]) -> Result<()> {
+        let mut data = std::mem::take(&mut self.decoder_buf);
+        data.extend_from_slice(buf);
+
+        let mut b = octets::Octets::with_slice(&data);
+
+        while b.cap() > 0 {
+            let start = b.off();
+
+            match self.process_instruction(&mut b) {
+                Ok(_) => (),
+
+                // Wait for the rest of the instruction to be received.
+                Err(Error::BufferTooShort) => {
+                    let partial = &data[start..];
+
+                    if partial.len() > MAX_INSTRUCTION_LEN {
+                        return Err(Error::InvalidDecoderInstruction);
+                    }
+
+                    self.decoder_buf = partial.to_vec();
+
+                    break;
+                },
+
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+
     /// Encodes a list of headers into a QPACK header block.
+    ///
+    /// This only uses the static table and literal representations.
     pub fn encode<T: NameValue>(
         &mut self, headers: &[T], out: &mut [u8],
     ) -> Result<usize> {
@@ -55,33 +352,328 @@ impl Encoder {
         encode_int(0, 0, 7, &mut b)?;
 
         for h in headers {
-            match lookup_static(h) {
-                Some((idx, true)) => {
-                    const STATIC: u8 = 0x40;
+            encode_field(h, &mut b)?;
+        }
 
-                    // Encode as statically indexed.
-                    encode_int(idx, INDEXED | STATIC, 6, &mut b)?;
-                },
+        Ok(b.off())
+    }
 
-                Some((idx, false)) => {
-                    const STATIC: u8 = 0x10;
+    /// Encodes a list of headers to be sent on the given stream into a QPACK
+    /// header block.
+    ///
+    /// Unlike [`encode()`], this can insert fields into the dynamic table and
+    /// reference them, according to the encoder's [`InsertionPolicy`]. Any
+    /// resulting encoder instructions are queued, and must be sent before the
+    /// header block in order to avoid blocking the stream.
+    ///
+    /// If the header block ends up not being sent, [`abort_section()`] must be
+    /// called.
+    ///
+    /// [`encode()`]: struct.Encoder.html#method.encode
+    /// [`InsertionPolicy`]: enum.InsertionPolicy.html
+    /// [`abort_section()`]: struct.Encoder.html#method.abort_section
+    pub fn encode_stream<T: NameValue>(
+        &mut self, stream_id: u64, headers: &[T], out: &mut [u8],
+    ) -> Result<usize> {
+        self.last_section = None;
 
-                    // Encode value as literal with static name reference.
-                    encode_int(idx, LITERAL_WITH_NAME_REF | STATIC, 4, &mut b)?;
-                    encode_str::<false>(h.value(), 0, 7, &mut b)?;
-                },
+        if self.table.capacity() == 0 {
+            return self.encode(headers, out);
+        }
 
-                None => {
-                    // Encode as fully literal.
+        let can_block = self.policy == InsertionPolicy::MaximizeCompression &&
+            (self
+                .sections
+                .get(&stream_id)
+                .is_some_and(|s| self.is_blocking(s)) ||
+                (self.blocked_streams() as u64) < self.max_blocked_streams);
 
-                    encode_str::<true>(h.name(), LITERAL, 3, &mut b)?;
-                    encode_str::<false>(h.value(), 0, 7, &mut b)?;
-                },
+        let mut fields = Vec::with_capacity(headers.len());
+
+        let mut req_insert_count = 0;
+        let mut min_index = u64::MAX;
+
+        for h in headers {
+            let name = h.name().to_ascii_lowercase();
+
+            let idx = match lookup_static(h) {
+                // Fields in the static table are always encoded as indexed.
+                Some((_, true)) => None,
+
+                Some((name_idx, false)) => self.lookup_or_insert(
+                    Some(name_idx),
+                    &name,
+                    h.value(),
+                    min_index,
+                )?,
+
+                None =>
+                    self.lookup_or_insert(None, &name, h.value(), min_index)?,
             };
+
+            let idx =
+                idx.filter(|idx| *idx < self.known_received_count || can_block);
+
+            if let Some(idx) = idx {
+                req_insert_count = std::cmp::max(req_insert_count, idx + 1);
+                min_index = std::cmp::min(min_index, idx);
+            }
+
+            fields.push(idx);
+        }
+
+        let mut b = octets::OctetsMut::with_slice(out);
+
+        if req_insert_count == 0 {
+            encode_int(0, 0, 8, &mut b)?;
+        } else {
+            let max_entries = self.table.max_entries();
+
+            encode_int(req_insert_count % (2 * max_entries) + 1, 0, 8, &mut b)?;
+        }
+
+        // The Base is always the same as the Required Insert Count, so all
+        // dynamic table references are relative to it.
+        encode_int(0, 0, 7, &mut b)?;
+
+        for (h, idx) in headers.iter().zip(fields) {
+            match idx {
+                Some(idx) => {
+                    let rel = req_insert_count - 1 - idx;
+
+                    // Encode as dynamically indexed.
+                    encode_int(rel, INDEXED, 6, &mut b)?;
+                },
+
+                None => encode_field(h, &mut b)?,
+            }
+        }
+
+        if req_insert_count > 0 {
+            self.sections
+                .entry(stream_id)
+                .or_default()
+                .push_back(Section {
+                    req_insert_count,
+                    min_index,
+                });
+
+            self.last_section = Some(stream_id);
         }
 
         Ok(b.off())
     }
+
+    /// Notifies the encoder that the last header block encoded for the given
+    /// stream by [`encode_stream()`] was not sent.
+    ///
+    /// [`encode_stream()`]: struct.Encoder.html#method.encode_stream
+    pub fn abort_section(&mut self, stream_id: u64) {
+        if self.last_section.take() != Some(stream_id) {
+            return;
+        }
+
+        if let Some(sections) = self.sections.get_mut(&stream_id) {
+            sections.pop_back();
+
+            if sections.is_empty() {
+                self.sections.remove(&stream_id);
+            }
+        }
+    }
+
+    /// Returns the encoder instructions that need to be sent to the peer on
+    /// the QPACK encoder stream.
+    ///
+    /// Once (part of) the instructions are sent, [`instructions_sent()`] must
+    /// be called.
+    ///
+    /// [`instructions_sent()`]: struct.Encoder.html#method.instructions_sent
+    pub fn pending_instructions(&self) -> &[u8] {
+        &self.instructions
+    }
+
+    /// Notifies the encoder that `len` bytes of pending instructions were sent.
+    pub fn instructions_sent(&mut self, len: usize) {
+        let len = std::cmp::min(len, self.instructions.len());
+
+        self.instructions.drain(..len);
+    }
+
+    fn is_blocking(&self, sections: &VecDeque<Section>) -> bool {
+        sections
+            .iter()
+            .any(|s| s.req_insert_count > self.known_received_count)
+    }
+
+    fn queue_int(&mut self, v: u64, first: u8, prefix: usize) {
+        let mut d = [0; 16];
+        let mut b = octets::OctetsMut::with_slice(&mut d);
+
+        // The buffer is always large enough for a 64-bit integer.
+        if encode_int(v, first, prefix, &mut b).is_ok() {
+            let off = b.off();
+            self.instructions.extend_from_slice(&d[..off]);
+        }
+    }
+
+    /// Returns the absolute index of the dynamic table entry matching the
+    /// given field, inserting it first if the field is worth it.
+    ///
+    /// Entries referenced by unacknowledged header blocks, or with an index of
+    /// `min_index` or greater, are never evicted.
+    fn lookup_or_insert(
+        &mut self, static_name_idx: Option<u64>, name: &[u8], value: &[u8],
+        min_index: u64,
+    ) -> Result<Option<u64>> {
+        if let Some(idx) = self.table.find(name, value) {
+            return Ok(Some(idx));
+        }
+
+        let size = entry_size(name, value);
+
+        // Avoid filling the table with a single large entry.
+        if size > self.table.capacity() / 4 {
+            return Ok(None);
+        }
+
+        let key = (name.to_vec(), value.to_vec());
+        let repeats = self.repeats.get(&key).copied().unwrap_or(0);
+
+        if !self.filter.should_insert(name, value, repeats) {
+            if self.repeats.len() >= MAX_TRACKED_FIELDS &&
+                !self.repeats.contains_key(&key)
+            {
+                self.repeats.clear();
+            }
+
+            self.repeats.insert(key, repeats + 1);
+
+            return Ok(None);
+        }
+
+        let evict_limit = self
+            .sections
+            .values()
+            .flatten()
+            .map(|s| s.min_index)
+            .fold(min_index, std::cmp::min);
+
+        if !self.table.can_insert(size, evict_limit) {
+            return Ok(None);
+        }
+
+        let mut d = vec![0; size as usize];
+        let mut b = octets::OctetsMut::with_slice(&mut d);
+
+        match static_name_idx {
+            Some(name_idx) => {
+                const STATIC: u8 = 0x40;
+
+                encode_int(name_idx, INSERT_WITH_NAME_REF | STATIC, 6, &mut b)?;
+            },
+
+            None =>
+                encode_str::<true>(name, INSERT_WITH_LITERAL_NAME, 5, &mut b)?,
+        }
+
+        encode_str::<false>(value, 0, 7, &mut b)?;
+
+        let off = b.off();
+
+        self.repeats.remove(&key);
+
+        let (name, value) = key;
+        let idx = self.table.insert(name, value)?;
+
+        self.instructions.extend_from_slice(&d[..off]);
+
+        Ok(Some(idx))
+    }
+
+    fn process_instruction(&mut self, b: &mut octets::Octets) -> Result<()> {
+        let first = b.peek_u8()?;
+
+        if first & SECTION_ACKNOWLEDGEMENT == SECTION_ACKNOWLEDGEMENT {
+            let stream_id = decode_int(b, 7)?;
+
+            trace!("Section Acknowledgement stream={stream_id}");
+
+            let sections = self
+                .sections
+                .get_mut(&stream_id)
+                .ok_or(Error::InvalidDecoderInstruction)?;
+
+            let section = sections
+                .pop_front()
+                .ok_or(Error::InvalidDecoderInstruction)?;
+
+            if sections.is_empty() {
+                self.sections.remove(&stream_id);
+            }
+
+            if self.last_section == Some(stream_id) {
+                self.last_section = None;
+            }
+
+            self.known_received_count = std::cmp::max(
+                self.known_received_count,
+                section.req_insert_count,
+            );
+        } else if first & STREAM_CANCELLATION == STREAM_CANCELLATION {
+            let stream_id = decode_int(b, 6)?;
+
+            trace!("Stream Cancellation stream={stream_id}");
+
+            self.sections.remove(&stream_id);
+
+            if self.last_section == Some(stream_id) {
+                self.last_section = None;
+            }
+        } else {
+            let increment = decode_int(b, 6)?;
+
+            trace!("Insert Count Increment increment={increment}");
+
+            let known_received_count = self
+                .known_received_count
+                .checked_add(increment)
+                .filter(|v| increment > 0 && *v <= self.table.insert_count())
+                .ok_or(Error::InvalidDecoderInstruction)?;
+
+            self.known_received_count = known_received_count;
+        }
+
+        Ok(())
+    }
+}
+
+fn encode_field<T: NameValue>(h: &T, b: &mut octets::OctetsMut) -> Result<()> {
+    match lookup_static(h) {
+        Some((idx, true)) => {
+            const STATIC: u8 = 0x40;
+
+            // Encode as statically indexed.
+            encode_int(idx, INDEXED | STATIC, 6, b)?;
+        },
+
+        Some((idx, false)) => {
+            const STATIC: u8 = 0x10;
+
+            // Encode value as literal with static name reference.
+            encode_int(idx, LITERAL_WITH_NAME_REF | STATIC, 4, b)?;
+            encode_str::<false>(h.value(), 0, 7, b)?;
+        },
+
+        None => {
+            // Encode as fully literal.
+
+            encode_str::<true>(h.name(), LITERAL, 3, b)?;
+            encode_str::<false>(h.value(), 0, 7, b)?;
+        },
+    };
+
+    Ok(())
 }
 
 fn lookup_static<T: NameValue>(h: &T) -> Option<(u64, bool)> {
@@ -158,7 +750,7 @@ pub fn encode_str<const LOWER_CASE: bool>(
             super::huffman::encode::<LOWER_CASE>(v, b)?;
         },
 
-        Err(super::Error::InflatedHuffmanEncoding) => {
+        Err(Error::InflatedHuffmanEncoding) => {
             encode_int(v.len() as u64, first, prefix, b)?;
             if LOWER_CASE {
                 b.put_bytes(&v.to_ascii_lowercase())?;
@@ -34,6 +34,15 @@ pub const INDEXED_WITH_POST_BASE: u8 = 0b0001_0000;
 pub const LITERAL: u8 = 0b0010_0000;
 pub const LITERAL_WITH_NAME_REF: u8 = 0b0100_0000;
 
+pub const INSERT_WITH_NAME_REF: u8 = 0b1000_0000;
+pub const INSERT_WITH_LITERAL_NAME: u8 = 0b0100_0000;
+pub const SET_DYNAMIC_TABLE_CAPACITY: u8 = 0b0010_0000;
+pub const DUPLICATE: u8 = 0b0000_0000;
+
+pub const SECTION_ACKNOWLEDGEMENT: u8 = 0b1000_0000;
+pub const STREAM_CANCELLATION: u8 = 0b0100_0000;
+pub const INSERT_COUNT_INCREMENT: u8 = 0b0000_0000;
+
 /// A specialized [`Result`] type for quiche QPACK operations.
 ///
 /// This type is used throughout quiche's QPACK public API for any operation
@@ -62,6 +71,26 @@ pub enum Error {
 
     /// The decoded header list exceeded the size limit.
     HeaderListTooLarge,
+
+    /// The QPACK dynamic table index provided doesn't exist.
+    InvalidDynamicTableIndex,
+
+    /// The QPACK dynamic table capacity or one of its entries exceeded the
+    /// allowed size.
+    DynamicTableCapacityExceeded,
+
+    /// The QPACK header block prefix is invalid.
+    InvalidHeaderBlockPrefix,
+
+    /// The QPACK header block references dynamic table entries that have not
+    /// been received yet.
+    Blocked,
+
+    /// Decoding the QPACK header block would exceed the blocked streams limit.
+    TooManyBlockedStreams,
+
+    /// The QPACK decoder instruction received from the peer is invalid.
+    InvalidDecoderInstruction,
 }
 
 impl std::fmt::Display for Error {
@@ -88,6 +117,9 @@ mod tests {
 
     use super::*;
 
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
     #[test]
     fn encode_decode() {
         let mut encoded = [0u8; 240];
@@ -111,6 +143,284 @@ mod tests {
         assert_eq!(dec.decode(&encoded, u64::MAX), Ok(headers));
     }
 
+    #[test]
+    fn encode_decode_dynamic() {
+        let mut encoded = [0u8; 240];
+
+        let headers = vec![
+            h3::Header::new(b":status", b"200"),
+            h3::Header::new(b"server", b"quiche"),
+            h3::Header::new(b"content-type", b"text/html; charset=utf-8"),
+            h3::Header::new(b"x-custom", b"some value"),
+        ];
+
+        let mut enc = Encoder::with_policy(InsertionPolicy::MaximizeCompression);
+        enc.set_insertion_filter(Arc::new(RepeatedFieldFilter::new(0)));
+        enc.set_peer_limits(4096, 1);
+
+        let mut dec = Decoder::with_limits(4096, 1);
+
+        // The content-type field is fully in the static table.
+        let len = enc.encode_stream(0, &headers, &mut encoded).unwrap();
+        assert_eq!(enc.insert_count(), 2);
+
+        // Encoder instructions haven't been received yet.
+        assert_eq!(
+            dec.decode_stream(0, &encoded[..len], u64::MAX),
+            Err(super::Error::Blocked)
+        );
+
+        let instructions = enc.pending_instructions().to_vec();
+        enc.instructions_sent(instructions.len());
+        assert!(enc.pending_instructions().is_empty());
+
+        assert_eq!(dec.control(&instructions), Ok(()));
+        assert_eq!(dec.next_unblocked_stream(), Some(0));
+        assert_eq!(
+            dec.decode_stream(0, &encoded[..len], u64::MAX),
+            Ok(headers.clone())
+        );
+
+        // Section Acknowledgement.
+        let instructions = dec.pending_instructions().to_vec();
+        dec.instructions_sent(instructions.len());
+
+        assert_eq!(enc.blocked_streams(), 1);
+        assert_eq!(enc.control(&instructions), Ok(()));
+        assert_eq!(enc.known_received_count(), 2);
+        assert_eq!(enc.blocked_streams(), 0);
+
+        // All fields are now indexed.
+        let len = enc.encode_stream(4, &headers, &mut encoded).unwrap();
+        assert_eq!(len, 6);
+        assert!(enc.pending_instructions().is_empty());
+
+        assert_eq!(dec.decode_stream(4, &encoded[..len], u64::MAX), Ok(headers));
+    }
+
+    #[test]
+    fn encode_decode_dynamic_never_block() {
+        let mut encoded = [0u8; 240];
+
+        let headers = vec![
+            h3::Header::new(b"user-agent", b"quiche"),
+            h3::Header::new(b"cookie", b"session=abcdef"),
+        ];
+
+        let mut enc = Encoder::with_policy(InsertionPolicy::NeverBlock);
+        enc.set_insertion_filter(Arc::new(RepeatedFieldFilter::new(0)));
+        enc.set_peer_limits(4096, 0);
+
+        let mut dec = Decoder::with_limits(4096, 0);
+
+        // Fields are inserted but not referenced.
+        let len = enc.encode_stream(0, &headers, &mut encoded).unwrap();
+        assert_eq!(enc.insert_count(), 2);
+
+        assert_eq!(
+            dec.decode_stream(0, &encoded[..len], u64::MAX),
+            Ok(headers.clone())
+        );
+
+        let instructions = enc.pending_instructions().to_vec();
+        enc.instructions_sent(instructions.len());
+        assert_eq!(dec.control(&instructions), Ok(()));
+
+        // Inserted fields can't be referenced until acknowledged.
+        let unacked_len = enc.encode_stream(4, &headers, &mut encoded).unwrap();
+        assert_eq!(unacked_len, len);
+
+        // Insert Count Increment.
+        let instructions = dec.pending_instructions().to_vec();
+        dec.instructions_sent(instructions.len());
+        assert_eq!(enc.control(&instructions), Ok(()));
+
+        let len = enc.encode_stream(8, &headers, &mut encoded).unwrap();
+        assert!(len < unacked_len);
+
+        assert_eq!(dec.decode_stream(8, &encoded[..len], u64::MAX), Ok(headers));
+    }
+
+    #[test]
+    fn encode_dynamic_blocked_streams_limit() {
+        let mut encoded = [0u8; 240];
+
+        let mut enc = Encoder::with_policy(InsertionPolicy::MaximizeCompression);
+        enc.set_insertion_filter(Arc::new(RepeatedFieldFilter::new(0)));
+        enc.set_peer_limits(4096, 1);
+
+        let mut dec = Decoder::with_limits(4096, 1);
+
+        let headers = vec![h3::Header::new(b"server", b"quiche")];
+
+        enc.encode_stream(0, &headers, &mut encoded).unwrap();
+        assert_eq!(enc.blocked_streams(), 1);
+
+        // The aborted header block doesn't count as blocking anymore.
+        enc.abort_section(0);
+        assert_eq!(enc.blocked_streams(), 0);
+
+        let len = enc.encode_stream(0, &headers, &mut encoded).unwrap();
+        assert_eq!(
+            dec.decode_stream(0, &encoded[..len], u64::MAX),
+            Err(super::Error::Blocked)
+        );
+
+        // Another stream can't be blocked, so new entries aren't referenced.
+        let headers = vec![h3::Header::new(b"x-custom", b"quiche")];
+
+        let len = enc.encode_stream(4, &headers, &mut encoded).unwrap();
+        assert_eq!(enc.insert_count(), 2);
+        assert_eq!(enc.blocked_streams(), 1);
+
+        assert_eq!(dec.decode_stream(4, &encoded[..len], u64::MAX), Ok(headers));
+    }
+
+    #[test]
+    fn encode_dynamic_repeated_fields() {
+        let mut encoded = [0u8; 240];
+
+        let headers = vec![
+            h3::Header::new(b"user-agent", b"quiche"),
+            h3::Header::new(b"date", b"Sat, 17 Oct 2026 10:00:00 GMT"),
+        ];
+
+        let mut enc = Encoder::with_policy(InsertionPolicy::MaximizeCompression);
+        enc.set_peer_limits(4096, 16);
+
+        let mut dec = Decoder::with_limits(4096, 16);
+
+        // Fields aren't inserted the first time they are seen.
+        let len = enc.encode_stream(0, &headers, &mut encoded).unwrap();
+        assert_eq!(enc.insert_count(), 0);
+
+        assert_eq!(
+            dec.decode_stream(0, &encoded[..len], u64::MAX),
+            Ok(headers.clone())
+        );
+
+        // Repeated fields are inserted, except for the ones that change on
+        // every message.
+        let repeated_len = enc.encode_stream(4, &headers, &mut encoded).unwrap();
+        assert_eq!(enc.insert_count(), 1);
+        assert!(repeated_len < len);
+
+        let instructions = enc.pending_instructions().to_vec();
+        enc.instructions_sent(instructions.len());
+        assert_eq!(dec.control(&instructions), Ok(()));
+
+        assert_eq!(
+            dec.decode_stream(4, &encoded[..repeated_len], u64::MAX),
+            Ok(headers.clone())
+        );
+
+        enc.encode_stream(8, &headers, &mut encoded).unwrap();
+        assert_eq!(enc.insert_count(), 1);
+    }
+
+    #[test]
+    fn encode_dynamic_insertion_filter() {
+        #[derive(Default)]
+        struct CookieFilter {
+            seen: Mutex<Vec<(Vec<u8>, u64)>>,
+        }
+
+        impl InsertionFilter for CookieFilter {
+            fn should_insert(
+                &self, name: &[u8], _value: &[u8], repeats: u64,
+            ) -> bool {
+                self.seen.lock().unwrap().push((name.to_vec(), repeats));
+
+                name == b"cookie" && repeats >= 2
+            }
+        }
+
+        let mut encoded = [0u8; 240];
+
+        let headers = vec![
+            h3::Header::new(b"Cookie", b"session=abcdef"),
+            h3::Header::new(b"server", b"quiche"),
+        ];
+
+        let filter = Arc::new(CookieFilter::default());
+
+        let mut enc = Encoder::with_policy(InsertionPolicy::MaximizeCompression);
+        enc.set_insertion_filter(filter.clone());
+        enc.set_peer_limits(4096, 16);
+
+        for stream_id in [0, 4, 8, 12] {
+            enc.encode_stream(stream_id, &headers, &mut encoded)
+                .unwrap();
+        }
+
+        assert_eq!(enc.insert_count(), 1);
+
+        // Once inserted, the cookie is found in the dynamic table, and the
+        // filter isn't consulted anymore.
+        assert_eq!(*filter.seen.lock().unwrap(), vec![
+            (b"cookie".to_vec(), 0),
+            (b"server".to_vec(), 0),
+            (b"cookie".to_vec(), 1),
+            (b"server".to_vec(), 1),
+            (b"cookie".to_vec(), 2),
+            (b"server".to_vec(), 2),
+            (b"server".to_vec(), 3),
+        ]);
+    }
+
+    #[test]
+    fn encode_dynamic_max_table_capacity() {
+        let mut enc = Encoder::with_policy(InsertionPolicy::MaximizeCompression);
+        enc.set_max_table_capacity(1024);
+        enc.set_peer_limits(4096, 1);
+
+        // Set Dynamic Table Capacity to 1024.
+        assert_eq!(enc.pending_instructions(), &[0x3f, 0xe1, 0x07]);
+
+        // The dynamic table is not used at all with a capacity of 0.
+        let mut encoded = [0u8; 240];
+
+        let headers = vec![h3::Header::new(b"server", b"quiche")];
+
+        let mut enc = Encoder::with_policy(InsertionPolicy::MaximizeCompression);
+        enc.set_insertion_filter(Arc::new(RepeatedFieldFilter::new(0)));
+        enc.set_max_table_capacity(0);
+        enc.set_peer_limits(4096, 1);
+
+        assert!(enc.pending_instructions().is_empty());
+
+        enc.encode_stream(0, &headers, &mut encoded).unwrap();
+        assert_eq!(enc.insert_count(), 0);
+        assert!(enc.pending_instructions().is_empty());
+    }
+
+    #[test]
+    fn encode_dynamic_invalid_decoder_instruction() {
+        let mut enc = Encoder::with_policy(InsertionPolicy::MaximizeCompression);
+        enc.set_peer_limits(4096, 1);
+
+        // Section Acknowledgement for a stream without header blocks.
+        assert_eq!(
+            enc.control(&[0x80]),
+            Err(super::Error::InvalidDecoderInstruction)
+        );
+
+        // Insert Count Increment of 0.
+        assert_eq!(
+            enc.control(&[0x00]),
+            Err(super::Error::InvalidDecoderInstruction)
+        );
+
+        // Insert Count Increment beyond the Insert Count.
+        assert_eq!(
+            enc.control(&[0x01]),
+            Err(super::Error::InvalidDecoderInstruction)
+        );
+
+        // Stream Cancellation for unknown streams is ignored.
+        assert_eq!(enc.control(&[0x44]), Ok(()));
+    }
+
     #[test]
     fn lower_case() {
         let mut encoded = [0u8; 35];
@@ -199,8 +509,13 @@ mod tests {
 
 pub use decoder::Decoder;
 pub use encoder::Encoder;
+pub use encoder::InsertionFilter;
+pub use encoder::InsertionPolicy;
+pub use encoder::RepeatedFieldFilter;
+pub use encoder::DEFAULT_MAX_TABLE_CAPACITY;
 
 mod decoder;
+mod dynamic_table;
 mod encoder;
 mod huffman;
 mod static_table;
@@ -35,6 +35,7 @@ pub const HTTP3_CONTROL_STREAM_TYPE_ID: u64 = 0x0;
 pub const HTTP3_PUSH_STREAM_TYPE_ID: u64 = 0x1;
 pub const QPACK_ENCODER_STREAM_TYPE_ID: u64 = 0x2;
 pub const QPACK_DECODER_STREAM_TYPE_ID: u64 = 0x3;
+pub const WEBTRANSPORT_STREAM_TYPE_ID: u64 = 0x54;
 
 const MAX_STATE_BUF_SIZE: usize = (1 << 24) - 1;
 
@@ -45,6 +46,7 @@ pub enum Type {
     Push,
     QpackEncoder,
     QpackDecoder,
+    WebTransport,
     Unknown,
 }
 
@@ -57,7 +59,8 @@ impl Type {
             Type::Push => qlog::events::h3::H3StreamType::Push,
             Type::QpackEncoder => qlog::events::h3::H3StreamType::QpackEncode,
             Type::QpackDecoder => qlog::events::h3::H3StreamType::QpackDecode,
-            Type::Unknown => qlog::events::h3::H3StreamType::Unknown,
+            Type::WebTransport | Type::Unknown =>
+                qlog::events::h3::H3StreamType::Unknown,
         }
     }
 }
@@ -82,9 +85,16 @@ pub enum State {
     /// Reading the push ID.
     PushId,
 
+    /// Reading the WebTransport session ID.
+    WebTransportSessionId,
+
     /// Reading a QPACK instruction.
     QpackInstruction,
 
+    /// Waiting for QPACK dynamic table updates before the last header block
+    /// can be decoded.
+    QpackBlocked,
+
     /// Reading and discarding data.
     Drain,
 
@@ -99,6 +109,7 @@ impl Type {
             HTTP3_PUSH_STREAM_TYPE_ID => Ok(Type::Push),
             QPACK_ENCODER_STREAM_TYPE_ID => Ok(Type::QpackEncoder),
             QPACK_DECODER_STREAM_TYPE_ID => Ok(Type::QpackDecoder),
+            WEBTRANSPORT_STREAM_TYPE_ID => Ok(Type::WebTransport),
 
             _ => Ok(Type::Unknown),
         }
@@ -170,6 +181,19 @@ pub struct Stream {
 
     /// Whether a trailing HEADER field has been received.
     trailers_received: bool,
+
+    /// The HEADERS or PUSH_PROMISE frame, and its payload length, that is
+    /// waiting for QPACK dynamic table updates.
+    qpack_blocked_frame: Option<(frame::Frame, u64)>,
+
+    /// The push ID of a push stream.
+    push_id: Option<u64>,
+
+    /// The ID of the session a WebTransport stream belongs to.
+    webtransport_session_id: Option<u64>,
+
+    /// Whether the stream carries an extended CONNECT request.
+    extended_connect: bool,
 }
 
 impl Stream {
@@ -217,9 +241,41 @@ impl Stream {
 
             trailers_sent: false,
             trailers_received: false,
+
+            qpack_blocked_frame: None,
+
+            push_id: None,
+
+            webtransport_session_id: None,
+
+            extended_connect: false,
         }
     }
 
+    /// Creates a new local HTTP/3 push stream for the given push ID.
+    pub fn new_push(id: u64, push_id: u64) -> Stream {
+        let mut stream = Stream::new(id, true);
+
+        stream.ty = Some(Type::Push);
+        stream.push_id = Some(push_id);
+
+        stream
+    }
+
+    /// Creates a new local WebTransport stream for the given session ID.
+    ///
+    /// Any data received on the stream is returned to the application as is.
+    pub fn new_webtransport(id: u64, session_id: u64) -> Stream {
+        let mut stream = Stream::new(id, true);
+
+        stream.ty = Some(Type::WebTransport);
+        stream.webtransport_session_id = Some(session_id);
+
+        let _ = stream.state_transition(State::Data, usize::MAX, false);
+
+        stream
+    }
+
     pub fn ty(&self) -> Option<Type> {
         self.ty
     }
@@ -239,6 +295,8 @@ impl Stream {
 
             Type::Push => State::PushId,
 
+            Type::WebTransport => State::WebTransportSessionId,
+
             Type::QpackEncoder | Type::QpackDecoder => {
                 self.remote_initialized = true;
 
@@ -254,16 +312,60 @@ impl Stream {
     }
 
     /// Sets the push ID and transitions to the next state.
-    pub fn set_push_id(&mut self, _id: u64) -> Result<()> {
+    pub fn set_push_id(&mut self, id: u64) -> Result<()> {
         assert_eq!(self.state, State::PushId);
 
-        // TODO: implement push ID.
+        self.push_id = Some(id);
 
         self.state_transition(State::FrameType, 1, true)?;
 
         Ok(())
     }
 
+    /// Returns the push ID of a push stream.
+    pub fn push_id(&self) -> Option<u64> {
+        self.push_id
+    }
+
+    /// Turns a peer-initiated request stream into a WebTransport stream, after
+    /// reading the WEBTRANSPORT_STREAM signal in place of the first frame
+    /// type, and transitions to the next state.
+    pub fn set_webtransport(&mut self) -> Result<()> {
+        assert_eq!(self.state, State::FrameType);
+
+        // The signal can only be the very first thing sent on the stream.
+        if self.ty != Some(Type::Request) ||
+            self.is_local ||
+            self.remote_initialized
+        {
+            return Err(Error::FrameUnexpected);
+        }
+
+        self.ty = Some(Type::WebTransport);
+
+        self.state_transition(State::WebTransportSessionId, 1, true)?;
+
+        Ok(())
+    }
+
+    /// Sets the WebTransport session ID and transitions to the next state.
+    pub fn set_webtransport_session_id(&mut self, id: u64) -> Result<()> {
+        assert_eq!(self.state, State::WebTransportSessionId);
+
+        self.webtransport_session_id = Some(id);
+
+        // The rest of the stream is not framed, so there is no limit to the
+        // amount of data that can be read in this state.
+        self.state_transition(State::Data, usize::MAX, false)?;
+
+        Ok(())
+    }
+
+    /// Returns the session ID of a WebTransport stream.
+    pub fn webtransport_session_id(&self) -> Option<u64> {
+        self.webtransport_session_id
+    }
+
     /// Sets the frame type and transitions to the next state.
     pub fn set_frame_type(&mut self, ty: u64) -> Result<()> {
         assert_eq!(self.state, State::FrameType);
@@ -303,13 +405,14 @@ impl Stream {
                 }
             },
 
-            Some(Type::Request) => {
-                // Request stream starts uninitialized and only HEADERS is
-                // accepted. After initialization, DATA and HEADERS frames may
-                // be acceptable, depending on the role and HTTP message phase.
+            Some(Type::Request) | Some(Type::Push) => {
+                // Request and push streams start uninitialized and only HEADERS
+                // is accepted. After initialization, DATA and HEADERS frames
+                // may be acceptable, depending on the role and HTTP message
+                // phase.
                 //
-                // Receiving some other types of known frames on the request
-                // stream is always an error.
+                // Receiving some other types of known frames on the stream is
+                // always an error.
                 if !self.is_local {
                     match (ty, self.remote_initialized) {
                         (frame::HEADERS_FRAME_TYPE_ID, false) => {
@@ -349,6 +452,11 @@ impl Stream {
                         (frame::MAX_PUSH_FRAME_TYPE_ID, _) =>
                             return Err(Error::FrameUnexpected),
 
+                        // Pushed responses can't promise further pushes.
+                        (frame::PUSH_PROMISE_FRAME_TYPE_ID, _)
+                            if self.ty == Some(Type::Push) =>
+                            return Err(Error::FrameUnexpected),
+
                         // All other frames can be ignored regardless of stream
                         // state.
                         _ => (),
@@ -356,28 +464,6 @@ impl Stream {
                 }
             },
 
-            Some(Type::Push) => {
-                match ty {
-                    // Frames that can never be received on request streams.
-                    frame::CANCEL_PUSH_FRAME_TYPE_ID =>
-                        return Err(Error::FrameUnexpected),
-
-                    frame::SETTINGS_FRAME_TYPE_ID =>
-                        return Err(Error::FrameUnexpected),
-
-                    frame::PUSH_PROMISE_FRAME_TYPE_ID =>
-                        return Err(Error::FrameUnexpected),
-
-                    frame::GOAWAY_FRAME_TYPE_ID =>
-                        return Err(Error::FrameUnexpected),
-
-                    frame::MAX_PUSH_FRAME_TYPE_ID =>
-                        return Err(Error::FrameUnexpected),
-
-                    _ => (),
-                }
-            },
-
             _ => return Err(Error::FrameUnexpected),
         }
 
@@ -529,6 +615,17 @@ impl Stream {
         self.trailers_sent
     }
 
+    /// Marks the stream as carrying an extended CONNECT request.
+    pub fn set_extended_connect(&mut self) {
+        self.extended_connect = true;
+    }
+
+    /// Returns whether the stream carries an extended CONNECT request, whose
+    /// body is a sequence of capsules.
+    pub fn is_extended_connect(&self) -> bool {
+        self.extended_connect
+    }
+
     /// Tries to fill the state buffer by reading data from the given cursor.
     ///
     /// This is intended to replace `try_fill_buffer()` in tests, in order to
@@ -627,6 +724,26 @@ impl Stream {
         Ok((len, fin))
     }
 
+    /// Marks the stream as blocked on QPACK dynamic table updates.
+    ///
+    /// The frame carrying the header block is stored until it can be decoded,
+    /// and no more data is read from the stream in the meantime.
+    pub fn set_qpack_blocked(&mut self, frame: frame::Frame, payload_len: u64) {
+        self.qpack_blocked_frame = Some((frame, payload_len));
+
+        let _ = self.state_transition(State::QpackBlocked, 0, false);
+    }
+
+    /// Takes the frame that was blocked on QPACK dynamic table updates, and
+    /// resumes reading frames from the stream.
+    pub fn take_qpack_blocked(&mut self) -> Option<(frame::Frame, u64)> {
+        let blocked = self.qpack_blocked_frame.take()?;
+
+        let _ = self.state_transition(State::FrameType, 1, true);
+
+        Some(blocked)
+    }
+
     /// Marks the stream as finished.
     pub fn finished(&mut self) {
         let _ = self.state_transition(State::Finished, 0, false);
@@ -104,6 +104,10 @@
 //! it is trying to connect to, and for a server that is the address of the
 //! client that initiated the connection.
 //!
+//! Alternatively, servers can use an [`Endpoint`], which accepts connections
+//! and routes incoming packets to them, and also takes care of version
+//! negotiation, address validation and stateless resets.
+//!
 //! ## Handling incoming packets
 //!
 //! Using the connection's [`recv()`] method the application can process
@@ -122,7 +126,11 @@
 //! loop {
 //!     let (read, from) = socket.recv_from(&mut buf).unwrap();
 //!
-//!     let recv_info = quiche::RecvInfo { from, to };
+//!     let recv_info = quiche::RecvInfo {
+//!         from,
+//!         to,
+//!         ecn: quiche::Ecn::NotEct,
+//!     };
 //!
 //!     let read = match conn.recv(&mut buf[..read], recv_info) {
 //!         Ok(v) => v,
@@ -313,6 +321,7 @@
 //! [`with_boring_ssl_ctx_builder()`]: https://docs.quic.tech/quiche/struct.Config.html#method.with_boring_ssl_ctx_builder
 //! [`connect()`]: fn.connect.html
 //! [`accept()`]: fn.accept.html
+//! [`Endpoint`]: struct.Endpoint.html
 //! [`recv()`]: struct.Connection.html#method.recv
 //! [`RecvInfo`]: struct.RecvInfo.html
 //! [`send()`]: struct.Connection.html#method.send
@@ -349,10 +358,46 @@
 //! config.set_cc_algorithm_name("reno").unwrap();
 //! ```
 //!
+//! Applications can also supply their own algorithm by implementing the
+//! [`CongestionController`] trait, and registering a factory creating it for
+//! each path with [`set_custom_cc()`]:
+//!
+//! ```
+//! # use std::sync::Arc;
+//! # use std::time::Instant;
+//! struct FixedWindow(usize);
+//!
+//! impl quiche::CongestionController for FixedWindow {
+//!     fn on_packet_sent(
+//!         &mut self, _sent_time: Instant, _pkt_num: u64, _bytes: usize,
+//!         _bytes_in_flight: usize, _is_retransmissible: bool,
+//!     ) {
+//!     }
+//!
+//!     fn on_packets_acked(&mut self, _event: &quiche::CongestionEvent) {}
+//!
+//!     fn on_congestion_event(&mut self, _event: &quiche::CongestionEvent) {}
+//!
+//!     fn cwnd(&self) -> usize {
+//!         self.0
+//!     }
+//! }
+//!
+//! let mut config = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
+//! config.set_custom_cc(Arc::new(
+//!     |params: &quiche::CongestionControllerParams| {
+//!         Box::new(FixedWindow(params.initial_congestion_window))
+//!             as Box<dyn quiche::CongestionController>
+//!     },
+//! ));
+//! ```
+//!
 //! Note that the CC algorithm should be configured before calling [`connect()`]
 //! or [`accept()`]. Otherwise the connection will use a default CC algorithm.
 //!
 //! [`CongestionControlAlgorithm`]: enum.CongestionControlAlgorithm.html
+//! [`CongestionController`]: trait.CongestionController.html
+//! [`set_custom_cc()`]: struct.Config.html#method.set_custom_cc
 //!
 //! ## Feature flags
 //!
@@ -391,6 +436,8 @@ use std::collections::VecDeque;
 use std::convert::TryInto;
 
 use std::net::SocketAddr;
+use std::net::SocketAddrV4;
+use std::net::SocketAddrV6;
 
 use std::str::FromStr;
 
@@ -406,6 +453,8 @@ use qlog::events::connectivity::TransportOwner;
 #[cfg(feature = "qlog")]
 use qlog::events::quic::RecoveryEventType;
 #[cfg(feature = "qlog")]
+use qlog::events::quic::SecurityEventType;
+#[cfg(feature = "qlog")]
 use qlog::events::quic::TransportEventType;
 #[cfg(feature = "qlog")]
 use qlog::events::DataRecipient;
@@ -423,6 +472,7 @@ use qlog::events::RawInfo;
 use smallvec::SmallVec;
 
 use crate::range_buf::DefaultBufFactory;
+use crate::range_buf::RangeBuf;
 
 use crate::recovery::OnAckReceivedOutcome;
 use crate::recovery::OnLossDetectionTimeoutOutcome;
@@ -436,6 +486,7 @@ pub const PROTOCOL_VERSION: u32 = PROTOCOL_VERSION_V1;
 
 /// Supported QUIC versions.
 const PROTOCOL_VERSION_V1: u32 = 0x0000_0001;
+const PROTOCOL_VERSION_V2: u32 = 0x6b33_43cf;
 
 /// The maximum length of a connection ID.
 pub const MAX_CONN_ID_LEN: usize = packet::MAX_CID_LEN as usize;
@@ -512,6 +563,14 @@ const MAX_CRYPTO_STREAM_OFFSET: u64 = 1 << 16;
 // The send capacity factor.
 const TX_CAP_FACTOR: f64 = 1.0;
 
+// The fraction of the AEAD confidentiality limit after which 1-RTT keys are
+// updated automatically.
+const KEY_UPDATE_LIMIT_DIVISOR: u64 = 2;
+
+// The number of packets kept below the AEAD confidentiality limit to close
+// the connection, if keys couldn't be updated in time.
+const AEAD_LIMIT_CLOSE_MARGIN: u64 = 1024;
+
 /// A specialized [`Result`] type for quiche operations.
 ///
 /// This type is used throughout quiche's public API for any operation that
@@ -600,6 +659,15 @@ pub enum Error {
     /// The peer send an ACK frame for a skipped packet used for Optimistic ACK
     /// mitigation.
     OptimisticAckDetected,
+
+    /// The peer's version negotiation information is inconsistent with the
+    /// version used for the connection, which might indicate a downgrade
+    /// attack.
+    VersionNegotiation,
+
+    /// The confidentiality or integrity limit of the AEAD algorithm used by
+    /// the connection was reached.
+    AeadLimitReached,
 }
 
 /// QUIC error codes sent on the wire.
@@ -666,6 +734,9 @@ pub enum WireErrorCode {
     /// CONNECTION_CLOSE frame carrying this code except when the path does
     /// not support a large enough MTU.
     NoViablePath         = 0x10,
+    /// An endpoint detected an error while performing version negotiation, as
+    /// defined in RFC9368.
+    VersionNegotiationError = 0x11,
 }
 
 impl Error {
@@ -684,6 +755,9 @@ impl Error {
             Error::CryptoBufferExceeded =>
                 WireErrorCode::CryptoBufferExceeded as u64,
             Error::KeyUpdate => WireErrorCode::KeyUpdateError as u64,
+            Error::VersionNegotiation =>
+                WireErrorCode::VersionNegotiationError as u64,
+            Error::AeadLimitReached => WireErrorCode::AeadLimitReached as u64,
             _ => WireErrorCode::ProtocolViolation as u64,
         }
     }
@@ -713,6 +787,8 @@ impl Error {
             Error::CryptoBufferExceeded => -20,
             Error::InvalidAckRange => -21,
             Error::OptimisticAckDetected => -22,
+            Error::VersionNegotiation => -23,
+            Error::AeadLimitReached => -24,
         }
     }
 }
@@ -743,6 +819,12 @@ pub struct RecvInfo {
 
     /// The local address the packet was received on.
     pub to: SocketAddr,
+
+    /// The ECN codepoint of the IP packet the datagram was received in.
+    ///
+    /// Applications that can't retrieve it from the socket should set it to
+    /// [`Ecn::NotEct`].
+    pub ecn: Ecn,
 }
 
 /// Ancillary information about outgoing packets.
@@ -760,6 +842,12 @@ pub struct SendInfo {
     ///
     /// [Pacing]: index.html#pacing
     pub at: Instant,
+
+    /// The ECN codepoint the IP packet carrying the datagram should be marked
+    /// with.
+    ///
+    /// See [`Config::enable_ecn()`] for more details.
+    pub ecn: Ecn,
 }
 
 /// Represents information carried by `CONNECTION_CLOSE` frames.
@@ -819,10 +907,13 @@ pub struct Config {
 
     cc_algorithm: CongestionControlAlgorithm,
     custom_bbr_params: Option<BbrParams>,
+    custom_cc: Option<Arc<dyn CongestionControllerFactory>>,
     initial_congestion_window_packets: usize,
 
     pmtud: bool,
 
+    ecn: bool,
+
     hystart: bool,
 
     pacing: bool,
@@ -845,9 +936,25 @@ pub struct Config {
 
     disable_dcid_reuse: bool,
 
+    disable_preferred_address_migration: bool,
+
     track_unknown_transport_params: Option<usize>,
 
     initial_rtt: Duration,
+
+    path_scheduler: Arc<dyn PathScheduler>,
+
+    anti_replay: Option<Arc<dyn AntiReplay>>,
+
+    ticket_key_ring: Option<Arc<TicketKeyRing>>,
+
+    token_validator: Option<Arc<dyn TokenValidator>>,
+
+    token_cache: Option<Arc<TokenCache>>,
+
+    ech_config_list: Option<Vec<u8>>,
+
+    ech_grease: bool,
 }
 
 // See https://quicwg.org/base-drafts/rfc9000.html#section-15
@@ -896,9 +1003,11 @@ impl Config {
             grease: true,
             cc_algorithm: CongestionControlAlgorithm::CUBIC,
             custom_bbr_params: None,
+            custom_cc: None,
             initial_congestion_window_packets:
                 DEFAULT_INITIAL_CONGESTION_WINDOW_PACKETS,
             pmtud: false,
+            ecn: false,
             hystart: true,
             pacing: true,
             max_pacing_rate: None,
@@ -920,8 +1029,24 @@ impl Config {
 
             disable_dcid_reuse: false,
 
+            disable_preferred_address_migration: false,
+
             track_unknown_transport_params: None,
             initial_rtt: DEFAULT_INITIAL_RTT,
+
+            path_scheduler: Arc::new(MinRttScheduler),
+
+            anti_replay: None,
+
+            ticket_key_ring: None,
+
+            token_validator: None,
+
+            token_cache: None,
+
+            ech_config_list: None,
+
+            ech_grease: true,
         })
     }
 
@@ -1016,6 +1141,22 @@ impl Config {
         self.pmtud = discover;
     }
 
+    /// Configures whether to mark outgoing packets with ECN.
+    ///
+    /// When enabled, the [`SendInfo::ecn`] field is set to the codepoint the
+    /// application needs to set on the outgoing IP packets, and ECN is
+    /// validated on each path as described in [RFC 9000]. Congestion
+    /// Experienced marks reported by the peer are then treated as congestion
+    /// signals. ECN counts of received packets are always reported to the
+    /// peer, as long as the application fills the [`RecvInfo::ecn`] field.
+    ///
+    /// The default value is `false`.
+    ///
+    /// [RFC 9000]: https://www.rfc-editor.org/rfc/rfc9000#section-13.4
+    pub fn enable_ecn(&mut self, v: bool) {
+        self.ecn = v;
+    }
+
     /// Configures whether to send GREASE values.
     ///
     /// The default value is `true`.
@@ -1049,11 +1190,118 @@ impl Config {
         self.tls_ctx.set_ticket_key(key)
     }
 
+    /// Configures the session ticket keys from a key ring.
+    ///
+    /// New tickets are encrypted with the current key of the ring, while
+    /// tickets encrypted with one of its previous keys are still accepted, and
+    /// renewed with the current key. The ring can be rotated with
+    /// [`TicketKeyRing::rotate()`] at any time, including while connections
+    /// created from this configuration are in progress, so that rotating the
+    /// key doesn't invalidate all the outstanding tickets at once.
+    ///
+    /// This overrides any key set with [`set_ticket_key()`].
+    ///
+    /// [`TicketKeyRing::rotate()`]: struct.TicketKeyRing.html#method.rotate
+    /// [`set_ticket_key()`]: struct.Config.html#method.set_ticket_key
+    pub fn set_ticket_key_ring(
+        &mut self, ring: Arc<TicketKeyRing>,
+    ) -> Result<()> {
+        self.tls_ctx.enable_ticket_key_ring()?;
+        self.ticket_key_ring = Some(ring);
+
+        Ok(())
+    }
+
     /// Enables sending or receiving early data.
     pub fn enable_early_data(&mut self) {
         self.tls_ctx.set_early_data_enabled(true);
     }
 
+    /// Sets the store used by servers to detect replayed early data.
+    ///
+    /// Every ClientHello offering early data is recorded in the store, and
+    /// early data is rejected if the store reports it as a replay or fails to
+    /// record it, in which case the handshake falls back to 1-RTT. A
+    /// [`StrikeRegister`] can be used to protect a single server instance,
+    /// while servers sharing session ticket keys need an [`AntiReplay`]
+    /// implementation backed by a shared store.
+    ///
+    /// This replaces the certificate selection callback of the underlying TLS
+    /// context, if any.
+    ///
+    /// [`StrikeRegister`]: struct.StrikeRegister.html
+    /// [`AntiReplay`]: trait.AntiReplay.html
+    pub fn set_anti_replay(&mut self, anti_replay: Arc<dyn AntiReplay>) {
+        self.tls_ctx.enable_anti_replay();
+        self.anti_replay = Some(anti_replay);
+    }
+
+    /// Sets the validator used by servers to issue address validation tokens.
+    ///
+    /// Once the handshake is confirmed, the server sends the client a token
+    /// minted by the validator in a NEW_TOKEN frame. When a later connection
+    /// presents a token accepted by the validator in its first Initial packet,
+    /// the client's address is considered validated: the server isn't limited
+    /// by the anti-amplification limit, and an [`Endpoint`] with Retry
+    /// enabled accepts the connection without sending a Retry packet.
+    ///
+    /// [`Endpoint`]: struct.Endpoint.html
+    pub fn set_token_validator(&mut self, validator: Arc<dyn TokenValidator>) {
+        self.token_validator = Some(validator);
+    }
+
+    /// Sets the cache used by clients to store address validation tokens.
+    ///
+    /// Tokens received in NEW_TOKEN frames are stored in the cache, keyed by
+    /// the server name passed to [`connect()`], and the next connection to the
+    /// same server name includes the token in its Initial packets.
+    ///
+    /// [`connect()`]: fn.connect.html
+    pub fn set_token_cache(&mut self, cache: Arc<TokenCache>) {
+        self.token_cache = Some(cache);
+    }
+
+    /// Configures the Encrypted Client Hello (ECH) configurations offered by
+    /// clients.
+    ///
+    /// `ech_config_list` is a serialized ECHConfigList, as typically
+    /// published in the server's HTTPS DNS record. When it contains a
+    /// supported configuration, the real ClientHello, including the server
+    /// name, is encrypted, and only the public name of the configuration is
+    /// sent in clear.
+    ///
+    /// If the server rejects ECH, the handshake authenticates the server
+    /// against the public name and then fails. The configurations to retry
+    /// with can be retrieved with [`ech_retry_configs()`].
+    ///
+    /// A malformed list causes the creation of client connections to fail.
+    ///
+    /// [`ech_retry_configs()`]: struct.Connection.html#method.ech_retry_configs
+    pub fn set_ech_config_list(&mut self, ech_config_list: &[u8]) {
+        self.ech_config_list = Some(ech_config_list.to_vec());
+    }
+
+    /// Configures whether clients send a GREASE ECH extension when no ECH
+    /// configuration is set, so that connections using ECH can't be told
+    /// apart from those that don't.
+    ///
+    /// The default value is `true`.
+    pub fn set_ech_grease(&mut self, v: bool) {
+        self.ech_grease = v;
+    }
+
+    /// Configures the Encrypted Client Hello (ECH) keys used by servers.
+    ///
+    /// Keys are listed in decreasing order of preference. At least one of
+    /// them must be a retry configuration, which is sent to clients that use
+    /// an unknown or outdated configuration so they can retry the connection.
+    ///
+    /// This may be called while connections created from this configuration
+    /// are in progress, for example to rotate the keys.
+    pub fn set_ech_keys(&mut self, keys: &[EchKey]) -> Result<()> {
+        self.tls_ctx.set_ech_keys(keys)
+    }
+
     /// Configures the list of supported application protocols.
     ///
     /// On the client this configures the list of protocols to send to the
@@ -1302,7 +1550,8 @@ impl Config {
     /// This API is experimental and will be removed in the future.
     ///
     /// Currently this only applies if cc_algorithm is
-    /// `CongestionControlAlgorithm::Bbr2Gcongestion` is set.
+    /// `CongestionControlAlgorithm::Bbr2Gcongestion` or
+    /// `CongestionControlAlgorithm::Bbr3` is set.
     ///
     /// The default value is `None`.
     #[cfg(feature = "internal")]
@@ -1311,6 +1560,22 @@ impl Config {
         self.custom_bbr_params = Some(custom_bbr_settings);
     }
 
+    /// Sets a user-supplied congestion control algorithm.
+    ///
+    /// `factory` is used to create a [`CongestionController`] for each path.
+    /// When set, it takes precedence over the algorithm selected with
+    /// [`set_cc_algorithm()`], and HyStart++ is not used.
+    ///
+    /// The default is to use one of the built-in algorithms.
+    ///
+    /// [`CongestionController`]: trait.CongestionController.html
+    /// [`set_cc_algorithm()`]: struct.Config.html#method.set_cc_algorithm
+    pub fn set_custom_cc(
+        &mut self, factory: Arc<dyn CongestionControllerFactory>,
+    ) {
+        self.custom_cc = Some(factory);
+    }
+
     /// Sets the congestion control algorithm used by string.
     ///
     /// The default value is `cubic`. On error `Error::CongestionControl`
@@ -1423,6 +1688,49 @@ impl Config {
         self.disable_dcid_reuse = v;
     }
 
+    /// Sets the `preferred_address` transport parameter.
+    ///
+    /// This value is only advertised by servers, and tells the client that
+    /// the server would rather be reached at `v4` or `v6` once the handshake
+    /// is confirmed. At least one of the two addresses should be set.
+    ///
+    /// The Connection ID `cid` and its stateless reset token `reset_token` are
+    /// used by the client on the path to the preferred address, so the server
+    /// must be able to route packets carrying `cid` to the connection. As with
+    /// [`set_stateless_reset_token()`], this value is specific to a single
+    /// connection and must be updated before accepting each new one.
+    ///
+    /// The parameter is ignored by connections using zero-length Source
+    /// Connection IDs, and setting it as a client has no effect.
+    ///
+    /// [`set_stateless_reset_token()`]: struct.Config.html#method.set_stateless_reset_token
+    pub fn set_preferred_address(
+        &mut self, v4: Option<SocketAddrV4>, v6: Option<SocketAddrV6>,
+        cid: &ConnectionId, reset_token: u128,
+    ) {
+        self.local_transport_params.preferred_address = Some(PreferredAddress {
+            ipv4: v4,
+            ipv6: v6,
+            connection_id: cid.to_vec().into(),
+            stateless_reset_token: reset_token,
+        });
+    }
+
+    /// Sets whether clients should ignore the server's preferred address.
+    ///
+    /// By default, once the handshake is confirmed, a client probes the
+    /// address advertised by the server in its `preferred_address` transport
+    /// parameter and migrates to it when it is validated. Clients that don't
+    /// want to expose themselves to additional network paths can set this to
+    /// `true` to keep using the address the connection was established with.
+    ///
+    /// This has no effect on servers.
+    ///
+    /// The default value is `false`.
+    pub fn set_disable_preferred_address_migration(&mut self, v: bool) {
+        self.disable_preferred_address_migration = v;
+    }
+
     /// Enables tracking unknown transport parameters.
     ///
     /// Specify the maximum number of bytes used to track unknown transport
@@ -1434,6 +1742,52 @@ impl Config {
     pub fn enable_track_unknown_transport_parameters(&mut self, size: usize) {
         self.track_unknown_transport_params = Some(size);
     }
+
+    /// Enables the multipath extension and sets the `initial_max_path_id`
+    /// transport parameter.
+    ///
+    /// The value is the largest Path ID the peer is allowed to open paths
+    /// with, in addition to the initial path with Path ID 0. Multipath is only
+    /// used when both endpoints enable it, and when neither uses zero-length
+    /// connection IDs.
+    ///
+    /// The value can't exceed 2^32-1.
+    ///
+    /// The default is that multipath is disabled.
+    pub fn set_initial_max_path_id(&mut self, v: u64) {
+        if v <= u32::MAX as u64 {
+            self.local_transport_params.initial_max_path_id = Some(v);
+        }
+    }
+
+    /// Sets the scheduler used to pick the path of the next packet when
+    /// multipath is enabled.
+    ///
+    /// The default is [`MinRttScheduler`].
+    ///
+    /// [`MinRttScheduler`]: struct.MinRttScheduler.html
+    pub fn set_path_scheduler(&mut self, scheduler: Arc<dyn PathScheduler>) {
+        self.path_scheduler = scheduler;
+    }
+
+    /// Enables the ACK Frequency extension and sets the `min_ack_delay`
+    /// transport parameter, in microseconds.
+    ///
+    /// This is the smallest ACK delay the peer is allowed to request with
+    /// ACK_FREQUENCY frames. It can't exceed the `max_ack_delay` transport
+    /// parameter, or the peer will reject it.
+    ///
+    /// Once the extension is negotiated, the congestion controller can ask the
+    /// peer to send fewer ACKs, for example at high bandwidth.
+    ///
+    /// The value can't exceed 2^24-1.
+    ///
+    /// The default is that the extension is disabled.
+    pub fn set_min_ack_delay(&mut self, v: u64) {
+        if v < 2_u64.pow(24) {
+            self.local_transport_params.min_ack_delay = Some(v);
+        }
+    }
 }
 
 /// A QUIC connection.
@@ -1492,6 +1846,9 @@ where
     /// PATH_CHALLENGE receive queue max length.
     path_challenge_recv_max_queue_len: usize,
 
+    /// Whether outgoing packets are marked with ECN on new paths.
+    enable_ecn: bool,
+
     /// Total number of received PATH_CHALLENGE frames.
     path_challenge_rx_count: u64,
 
@@ -1636,6 +1993,24 @@ where
     /// Key phase bit used for outgoing protected packets.
     key_phase: bool,
 
+    /// The first packet number sent with the current 1-RTT keys.
+    key_phase_first_pn: u64,
+
+    /// Whether a packet sent with the current 1-RTT keys was acked.
+    key_phase_acked: bool,
+
+    /// The number of packets protected with the current 1-RTT keys.
+    key_phase_sent: u64,
+
+    /// The number of received packets that failed authentication.
+    aead_auth_failures: u64,
+
+    /// The number of key updates initiated locally.
+    key_update_local_count: u64,
+
+    /// The number of key updates initiated by the peer.
+    key_update_remote_count: u64,
+
     /// Whether an ack-eliciting packet has been sent since last receiving a
     /// packet.
     ack_eliciting_sent: bool,
@@ -1666,6 +2041,16 @@ where
     /// Connection IDs when the peer migrates.
     disable_dcid_reuse: bool,
 
+    /// Whether the client ignores the server's preferred address.
+    disable_preferred_address_migration: bool,
+
+    /// The server's preferred address the client still has to probe.
+    preferred_peer_addr: Option<SocketAddr>,
+
+    /// The path to the server's preferred address being validated, that the
+    /// client migrates to once validated.
+    preferred_path: Option<(SocketAddr, SocketAddr)>,
+
     /// The number of streams reset by local.
     reset_stream_local_count: u64,
 
@@ -1680,6 +2065,38 @@ where
 
     /// The anti-amplification limit factor.
     max_amplification_factor: usize,
+
+    /// Multipath state, only present once the extension is negotiated.
+    multipath: Option<multipath::Multipath>,
+
+    /// The scheduler picking the path of outgoing packets when multipath is
+    /// enabled.
+    path_scheduler: Arc<dyn PathScheduler>,
+
+    /// The store consulted by servers to detect replayed early data.
+    anti_replay: Option<Arc<dyn AntiReplay>>,
+
+    /// The session ticket keys used by servers.
+    ticket_key_ring: Option<Arc<TicketKeyRing>>,
+
+    /// The generation of the key that decrypted the session ticket offered by
+    /// the client, if any.
+    ticket_key_generation: Option<u64>,
+
+    /// The validator used by servers to mint and validate NEW_TOKEN tokens.
+    token_validator: Option<Arc<dyn TokenValidator>>,
+
+    /// Whether a NEW_TOKEN frame needs to be sent.
+    new_token_pending: bool,
+
+    /// The cache storing the NEW_TOKEN tokens received by clients.
+    token_cache: Option<Arc<TokenCache>>,
+
+    /// The name of the server the client is connecting to.
+    server_name: Option<String>,
+
+    /// ACK Frequency extension state.
+    ack_freq: ack_frequency::AckFrequency,
 }
 
 /// Creates a new server-side connection.
@@ -1752,7 +2169,7 @@ pub fn connect(
     let mut conn = Connection::new(scid, None, local, peer, config, false)?;
 
     if let Some(server_name) = server_name {
-        conn.handshake.set_host_name(server_name)?;
+        conn.set_server_name(server_name)?;
     }
 
     Ok(conn)
@@ -1771,7 +2188,7 @@ pub fn connect_with_buffer_factory<F: BufFactory>(
     let mut conn = Connection::new(scid, None, local, peer, config, false)?;
 
     if let Some(server_name) = server_name {
-        conn.handshake.set_host_name(server_name)?;
+        conn.set_server_name(server_name)?;
     }
 
     Ok(conn)
@@ -1874,10 +2291,116 @@ pub fn retry(
     packet::retry(scid, dcid, new_scid, token, version, out)
 }
 
+/// Writes a stateless reset packet.
+///
+/// The `dcid` parameter is the destination connection ID of the received
+/// packet that couldn't be associated with any connection, and `len_hint` is
+/// that packet's size. The token carried by the reset is either the one that
+/// was issued along with `dcid`, or derived from a static key as done by
+/// [`stateless_reset_token()`].
+///
+/// The generated packet is indistinguishable from a short header packet, and
+/// is always smaller than `len_hint` to prevent endpoints from endlessly
+/// resetting each other. The [`Done`] error is returned when the received
+/// packet is too small to be answered with a stateless reset.
+///
+/// [`stateless_reset_token()`]: fn.stateless_reset_token.html
+/// [`Done`]: enum.Error.html#variant.Done
+///
+/// ## Examples:
+///
+/// ```no_run
+/// # let mut buf = [0; 512];
+/// # let mut out = [0; 512];
+/// # let key = [0xba; 32];
+/// # let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+/// let (len, peer) = socket.recv_from(&mut buf).unwrap();
+///
+/// let hdr =
+///     quiche::Header::from_slice(&mut buf[..len], quiche::MAX_CONN_ID_LEN)?;
+///
+/// // No connection found for the short header packet, reset it.
+/// if hdr.ty == quiche::Type::Short {
+///     let secret = quiche::StatelessResetSecret::Key(&key);
+///
+///     let len = quiche::stateless_reset(&hdr.dcid, secret, len, &mut out)?;
+///
+///     socket.send_to(&out[..len], &peer).unwrap();
+/// }
+/// # Ok::<(), quiche::Error>(())
+/// ```
+#[inline]
+pub fn stateless_reset(
+    dcid: &ConnectionId, token_or_key: StatelessResetSecret, len_hint: usize,
+    out: &mut [u8],
+) -> Result<usize> {
+    let reset_token = match token_or_key {
+        StatelessResetSecret::Token(v) => v,
+
+        StatelessResetSecret::Key(key) =>
+            packet::stateless_reset_token(key, dcid)?,
+    };
+
+    packet::stateless_reset(reset_token, len_hint, out)
+}
+
+/// Derives the stateless reset token of a connection ID from a static key.
+///
+/// The token is the first 16 bytes of the HMAC-SHA256 of `cid` keyed with
+/// `key`, so a server can recompute it for any of the connection IDs it
+/// issued, even after losing all connection state. The key should be at least
+/// 32 bytes long and kept secret.
+///
+/// The returned token can be passed to [`Config::set_stateless_reset_token()`]
+/// and [`Connection::new_scid()`] when issuing connection IDs.
+///
+/// [`Config::set_stateless_reset_token()`]: struct.Config.html#method.set_stateless_reset_token
+/// [`Connection::new_scid()`]: struct.Connection.html#method.new_scid
+#[inline]
+pub fn stateless_reset_token(key: &[u8], cid: &ConnectionId) -> Result<u128> {
+    packet::stateless_reset_token(key, cid)
+}
+
 /// Returns true if the given protocol version is supported.
 #[inline]
 pub fn version_is_supported(version: u32) -> bool {
-    matches!(version, PROTOCOL_VERSION_V1)
+    matches!(version, PROTOCOL_VERSION_V1 | PROTOCOL_VERSION_V2)
+}
+
+/// Returns the supported versions, in order of preference.
+///
+/// All the supported versions are compatible with each other, as defined in
+/// RFC9368.
+fn available_versions(preferred: u32) -> Vec<u32> {
+    let mut versions = Vec::with_capacity(2);
+
+    if version_is_supported(preferred) {
+        versions.push(preferred);
+    }
+
+    for v in [PROTOCOL_VERSION_V1, PROTOCOL_VERSION_V2] {
+        if v != preferred {
+            versions.push(v);
+        }
+    }
+
+    versions
+}
+
+/// Selects the version to use out of the ones offered by the peer.
+fn select_version(versions: &[u32]) -> Option<u32> {
+    let mut selected = None;
+
+    for &v in versions.iter().filter(|&&v| version_is_supported(v)) {
+        // The final version takes precedence over draft ones.
+        if v == PROTOCOL_VERSION_V1 {
+            return Some(v);
+        }
+
+        selected = cmp::max(selected, Some(v));
+    }
+
+    selected
 }
 
 /// Pushes a frame to the output packet if there is enough space.
@@ -1921,6 +2444,10 @@ macro_rules! qlog_with_type {
 const QLOG_PARAMS_SET: EventType =
     EventType::TransportEventType(TransportEventType::ParametersSet);
 
+#[cfg(feature = "qlog")]
+const QLOG_VERSION_INFO: EventType =
+    EventType::TransportEventType(TransportEventType::VersionInformation);
+
 #[cfg(feature = "qlog")]
 const QLOG_PACKET_RX: EventType =
     EventType::TransportEventType(TransportEventType::PacketReceived);
@@ -1941,6 +2468,10 @@ const QLOG_METRICS: EventType =
 const QLOG_CONNECTION_CLOSED: EventType =
     EventType::ConnectivityEventType(ConnectivityEventType::ConnectionClosed);
 
+#[cfg(feature = "qlog")]
+const QLOG_KEY_UPDATED: EventType =
+    EventType::SecurityEventType(SecurityEventType::KeyUpdated);
+
 #[cfg(feature = "qlog")]
 struct QlogInfo {
     streamer: Option<qlog::streamer::QlogStreamer>,
@@ -1999,12 +2530,15 @@ impl<F: BufFactory> Connection<F> {
         // Assume clients validate the server's address implicitly.
         path.peer_verified_local_address = is_server;
 
-        // Do not allocate more than the number of active CIDs.
-        let paths = path::PathMap::new(
-            path,
-            config.local_transport_params.active_conn_id_limit as usize,
-            is_server,
-        );
+        // Do not allocate more than the number of active CIDs, plus one path
+        // per additional Path ID when multipath is enabled.
+        let max_paths = config.local_transport_params.active_conn_id_limit +
+            config
+                .local_transport_params
+                .initial_max_path_id
+                .unwrap_or(0);
+
+        let paths = path::PathMap::new(path, max_paths as usize, is_server);
 
         let active_path_id = paths.get_active_path_id()?;
 
@@ -2054,6 +2588,7 @@ impl<F: BufFactory> Connection<F> {
             paths,
             path_challenge_recv_max_queue_len: config
                 .path_challenge_recv_max_queue_len,
+            enable_ecn: config.ecn,
             path_challenge_rx_count: 0,
 
             application_protos: config.application_protos.clone(),
@@ -2139,6 +2674,18 @@ impl<F: BufFactory> Connection<F> {
 
             key_phase: false,
 
+            key_phase_first_pn: 0,
+
+            key_phase_acked: false,
+
+            key_phase_sent: 0,
+
+            aead_auth_failures: 0,
+
+            key_update_local_count: 0,
+
+            key_update_remote_count: 0,
+
             ack_eliciting_sent: false,
 
             closed: false,
@@ -2164,12 +2711,57 @@ impl<F: BufFactory> Connection<F> {
 
             disable_dcid_reuse: config.disable_dcid_reuse,
 
+            disable_preferred_address_migration: config
+                .disable_preferred_address_migration,
+
+            preferred_peer_addr: None,
+
+            preferred_path: None,
+
             reset_stream_local_count: 0,
             stopped_stream_local_count: 0,
             reset_stream_remote_count: 0,
             stopped_stream_remote_count: 0,
 
             max_amplification_factor: config.max_amplification_factor,
+
+            multipath: None,
+
+            path_scheduler: Arc::clone(&config.path_scheduler),
+
+            anti_replay: if is_server {
+                config.anti_replay.clone()
+            } else {
+                None
+            },
+
+            ticket_key_ring: if is_server {
+                config.ticket_key_ring.clone()
+            } else {
+                None
+            },
+
+            ticket_key_generation: None,
+
+            token_validator: if is_server {
+                config.token_validator.clone()
+            } else {
+                None
+            },
+
+            new_token_pending: false,
+
+            token_cache: if is_server {
+                None
+            } else {
+                config.token_cache.clone()
+            },
+
+            server_name: None,
+
+            ack_freq: ack_frequency::AckFrequency::new(
+                config.local_transport_params.min_ack_delay,
+            ),
         };
 
         if let Some(odcid) = odcid {
@@ -2185,10 +2777,48 @@ impl<F: BufFactory> Connection<F> {
         conn.local_transport_params.initial_source_connection_id =
             Some(conn.ids.get_scid(0)?.cid.to_vec().into());
 
+        // The configured version is the preferred one. Servers switch to it
+        // during the handshake if the client's first flight is compatible.
+        conn.local_transport_params.version_information =
+            Some(VersionInformation {
+                chosen_version: config.version,
+                available_versions: available_versions(config.version),
+            });
+
+        // Multipath can't be used with zero-length connection IDs.
+        if conn.ids.zero_length_scid() {
+            conn.local_transport_params.initial_max_path_id = None;
+        }
+
+        // The preferred address is only advertised by servers, and requires
+        // non-zero length connection IDs. Its connection ID is registered with
+        // sequence number 1 without being advertised in a NEW_CONNECTION_ID.
+        if !is_server || conn.ids.zero_length_scid() {
+            conn.local_transport_params.preferred_address = None;
+        }
+
+        if let Some(pa) = &conn.local_transport_params.preferred_address {
+            conn.ids.new_scid(
+                pa.connection_id.clone(),
+                Some(pa.stateless_reset_token),
+                false,
+                None,
+                false,
+            )?;
+        }
+
         conn.handshake.init(is_server)?;
 
         conn.handshake
-            .use_legacy_codepoint(config.version != PROTOCOL_VERSION_V1);
+            .use_legacy_codepoint(!version_is_supported(config.version));
+
+        if !is_server {
+            if let Some(ech_config_list) = &config.ech_config_list {
+                conn.handshake.set_ech_config_list(ech_config_list)?;
+            }
+
+            conn.handshake.set_ech_grease(config.ech_grease);
+        }
 
         conn.encode_transport_params()?;
 
@@ -2397,7 +3027,8 @@ impl<F: BufFactory> Connection<F> {
     /// This API is experimental and will be removed in the future.
     ///
     /// Currently this only applies if cc_algorithm is
-    /// `CongestionControlAlgorithm::Bbr2Gcongestion` is set.
+    /// `CongestionControlAlgorithm::Bbr2Gcongestion` or
+    /// `CongestionControlAlgorithm::Bbr3` is set.
     ///
     /// This function can only be called inside one of BoringSSL's handshake
     /// callbacks, before any packet has been sent. Calling this function any
@@ -2688,6 +3319,7 @@ impl<F: BufFactory> Connection<F> {
     ///     let recv_info = quiche::RecvInfo {
     ///         from,
     ///         to: local,
+    ///         ecn: quiche::Ecn::NotEct,
     ///     };
     ///
     ///     let read = match conn.recv(&mut buf[..read], recv_info) {
@@ -2909,24 +3541,9 @@ impl<F: BufFactory> Connection<F> {
                 return Err(Error::Done);
             }
 
-            let supported_versions =
-                versions.iter().filter(|&&v| version_is_supported(v));
-
-            let mut found_version = false;
-
-            for &v in supported_versions {
-                found_version = true;
-
-                // The final version takes precedence over draft ones.
-                if v == PROTOCOL_VERSION_V1 {
-                    self.version = v;
-                    break;
-                }
-
-                self.version = cmp::max(self.version, v);
-            }
+            self.version = match select_version(&versions) {
+                Some(v) => v,
 
-            if !found_version {
                 // We don't support any of the versions offered.
                 //
                 // While a man-in-the-middle attacker might be able to
@@ -2934,8 +3551,8 @@ impl<F: BufFactory> Connection<F> {
                 // failure, the window of opportunity is very small and
                 // this error is quite useful for debugging, so don't just
                 // ignore the packet.
-                return Err(Error::UnknownVersion);
-            }
+                None => return Err(Error::UnknownVersion),
+            };
 
             self.did_version_negotiation = true;
 
@@ -2956,7 +3573,7 @@ impl<F: BufFactory> Connection<F> {
             self.crypto_ctx[packet::Epoch::Initial].crypto_seal = Some(aead_seal);
 
             self.handshake
-                .use_legacy_codepoint(self.version != PROTOCOL_VERSION_V1);
+                .use_legacy_codepoint(!version_is_supported(self.version));
 
             // Encode transport parameters again, as the new version might be
             // using a different format.
@@ -3031,14 +3648,28 @@ impl<F: BufFactory> Connection<F> {
             self.did_version_negotiation = true;
 
             self.handshake
-                .use_legacy_codepoint(self.version != PROTOCOL_VERSION_V1);
+                .use_legacy_codepoint(!version_is_supported(self.version));
 
             // Encode transport parameters again, as the new version might be
             // using a different format.
             self.encode_transport_params()?;
         }
 
-        if hdr.ty != Type::Short && hdr.version != self.version {
+        // The server might have switched to a compatible version it prefers,
+        // in which case its first packet uses that version instead.
+        if !self.is_server &&
+            !self.got_peer_conn_id &&
+            hdr.ty == Type::Initial &&
+            hdr.version != self.version &&
+            self.is_compatible_version(hdr.version)
+        {
+            self.switch_to_compatible_version(
+                hdr.version,
+                &self.destination_id().into_owned(),
+            )?;
+        }
+
+        if hdr.ty != Type::Short && hdr.version != self.version {
             // At this point version negotiation was already performed, so
             // ignore packets that don't match the connection's version.
             return Err(Error::Done);
@@ -3135,8 +3766,23 @@ impl<F: BufFactory> Connection<F> {
             drop_pkt_on_err(e, self.recv_count, self.is_server, &self.trace_id)
         })?;
 
+        // With multipath, the Destination Connection ID of the packet tells
+        // which Path ID, and so which packet number space, it belongs to.
+        let mp_path_id = match self.multipath.as_ref() {
+            Some(mp) if hdr.ty == Type::Short =>
+                mp.find_scid(&hdr.dcid).map_or(0, |(id, _)| id),
+
+            _ => 0,
+        };
+
         let pn = packet::decode_pkt_num(
-            self.pkt_num_spaces[epoch].largest_rx_pkt_num,
+            pkt_num_space_mut(
+                &mut self.pkt_num_spaces,
+                &mut self.multipath,
+                epoch,
+                mp_path_id,
+            )?
+            .largest_rx_pkt_num,
             hdr.pkt_num,
             hdr.pkt_num_len,
         );
@@ -3162,12 +3808,15 @@ impl<F: BufFactory> Connection<F> {
             hdr.ty != Type::ZeroRTT &&
             hdr.key_phase != self.key_phase
         {
-            // Check if this packet arrived before key update.
+            // Check if this packet arrived before key update. Packet numbers
+            // of other Path IDs can't be compared with the one the update
+            // happened at, so assume that such packets are just late.
             if let Some(key_update) = self.crypto_ctx[epoch]
                 .key_update
                 .as_ref()
                 .and_then(|key_update| {
-                    (pn < key_update.pn_on_update).then_some(key_update)
+                    (mp_path_id > 0 || pn < key_update.pn_on_update)
+                        .then_some(key_update)
                 })
             {
                 aead = &key_update.crypto_open;
@@ -3193,18 +3842,55 @@ impl<F: BufFactory> Connection<F> {
             }
         }
 
-        let mut payload = packet::decrypt_pkt(
-            &mut b,
-            pn,
-            pn_len,
-            payload_len,
-            aead,
-        )
-        .map_err(|e| {
-            drop_pkt_on_err(e, self.recv_count, self.is_server, &self.trace_id)
-        })?;
+        // Packets of other Path IDs use their own nonce, so their key is
+        // derived from the one of the key phase.
+        let mp_aead;
+        let aead = match self.multipath.as_mut() {
+            Some(mp) if mp_path_id > 0 => {
+                let key_phase_changed = hdr.key_phase != self.key_phase;
+
+                if key_phase_changed {
+                    mp_aead = aead.for_path(mp_path_id as u32)?;
+                    &mp_aead
+                } else {
+                    mp.space_mut(mp_path_id)?.open(mp_path_id, aead)?
+                }
+            },
+
+            _ => aead,
+        };
+
+        let mut payload =
+            match packet::decrypt_pkt(&mut b, pn, pn_len, payload_len, aead) {
+                Ok(v) => v,
+
+                Err(e) => {
+                    // Packets failing authentication count towards the AEAD
+                    // integrity limit, across all keys.
+                    self.aead_auth_failures += 1;
+
+                    if self.aead_auth_failures >= aead.alg().integrity_limit() {
+                        return Err(Error::AeadLimitReached);
+                    }
+
+                    return Err(drop_pkt_on_err(
+                        e,
+                        self.recv_count,
+                        self.is_server,
+                        &self.trace_id,
+                    ));
+                },
+            };
 
-        if self.pkt_num_spaces[epoch].recv_pkt_num.contains(pn) {
+        if pkt_num_space_mut(
+            &mut self.pkt_num_spaces,
+            &mut self.multipath,
+            epoch,
+            mp_path_id,
+        )?
+        .recv_pkt_num
+        .contains(pn)
+        {
             trace!("{} ignored duplicate packet {}", self.trace_id, pn);
             return Err(Error::Done);
         }
@@ -3216,7 +3902,12 @@ impl<F: BufFactory> Connection<F> {
 
         // Now that we decrypted the packet, let's see if we can map it to an
         // existing path.
-        let recv_pid = if hdr.ty == Type::Short && self.got_peer_conn_id {
+        let recv_pid = if hdr.ty == Type::Short && mp_path_id > 0 {
+            let pkt_dcid = ConnectionId::from_ref(&hdr.dcid);
+            self.get_or_create_mp_recv_path_id(
+                recv_pid, mp_path_id, &pkt_dcid, buf_len, info,
+            )?
+        } else if hdr.ty == Type::Short && self.got_peer_conn_id {
             let pkt_dcid = ConnectionId::from_ref(&hdr.dcid);
             self.get_or_create_recv_path_id(recv_pid, &pkt_dcid, buf_len, info)?
         } else {
@@ -3238,49 +3929,24 @@ impl<F: BufFactory> Connection<F> {
 
             trace!("{} key update verified", self.trace_id);
 
-            let _ = self.crypto_ctx[epoch].crypto_seal.replace(seal_next);
-
-            let open_prev = self.crypto_ctx[epoch]
-                .crypto_open
-                .replace(open_next)
-                .unwrap();
-
-            let recv_path = self.paths.get_mut(recv_pid)?;
-
-            self.crypto_ctx[epoch].key_update = Some(packet::KeyUpdate {
-                crypto_open: open_prev,
-                pn_on_update: pn,
-                update_acked: false,
-                timer: now + (recv_path.recovery.pto() * 3),
-            });
-
-            self.key_phase = !self.key_phase;
-
-            qlog_with_type!(QLOG_PACKET_RX, self.qlog, q, {
-                let trigger = Some(
-                    qlog::events::security::KeyUpdateOrRetiredTrigger::RemoteUpdate,
-                );
-
-                let ev_data_client =
-                    EventData::KeyUpdated(qlog::events::security::KeyUpdated {
-                        key_type:
-                            qlog::events::security::KeyType::Client1RttSecret,
-                        trigger: trigger.clone(),
-                        ..Default::default()
-                    });
-
-                q.add_event_data_with_instant(ev_data_client, now).ok();
+            let pto = self.paths.get(recv_pid)?.recovery.pto();
 
-                let ev_data_server =
-                    EventData::KeyUpdated(qlog::events::security::KeyUpdated {
-                        key_type:
-                            qlog::events::security::KeyType::Server1RttSecret,
-                        trigger,
-                        ..Default::default()
-                    });
+            self.update_keys(open_next, seal_next, pn, pto, false, now);
+        }
 
-                q.add_event_data_with_instant(ev_data_server, now).ok();
-            });
+        // The peer responded to a locally initiated key update, so packets
+        // sent before this one might still use the previous key.
+        if hdr.ty == Type::Short &&
+            hdr.key_phase == self.key_phase &&
+            mp_path_id == 0
+        {
+            if let Some(key_update) = self.crypto_ctx[epoch]
+                .key_update
+                .as_mut()
+                .filter(|key_update| key_update.pn_on_update == u64::MAX)
+            {
+                key_update.pn_on_update = pn;
+            }
         }
 
         if !self.is_server && !self.got_peer_conn_id {
@@ -3308,6 +3974,16 @@ impl<F: BufFactory> Connection<F> {
                     Some(hdr.dcid.to_vec().into());
 
                 self.encode_transport_params()?;
+
+                let recv_path = self.paths.get_mut(recv_pid)?;
+
+                if token::is_new_token_valid(
+                    self.token_validator.as_deref(),
+                    hdr.token.as_deref(),
+                    &recv_path.peer_addr(),
+                ) {
+                    recv_path.verified_peer_address = true;
+                }
             }
 
             self.got_peer_conn_id = true;
@@ -3326,6 +4002,10 @@ impl<F: BufFactory> Connection<F> {
         // whether this is a non-probing packet.
         let mut probing = true;
 
+        // Whether the peer asked for this packet to be acknowledged right
+        // away.
+        let mut immediate_ack = false;
+
         // Process packet payload.
         while payload.cap() > 0 {
             let frame = frame::Frame::from_bytes(&mut payload, hdr.ty)?;
@@ -3342,6 +4022,10 @@ impl<F: BufFactory> Connection<F> {
                 probing = false;
             }
 
+            if matches!(frame, frame::Frame::ImmediateAck) {
+                immediate_ack = true;
+            }
+
             if let Err(e) = self.process_frame(frame, &hdr, recv_pid, epoch, now)
             {
                 frame_processing_err = Some(e);
@@ -3391,6 +4075,19 @@ impl<F: BufFactory> Connection<F> {
         // established (i.e. after frames have been fully parsed) and only
         // once per connection.
         if self.is_established() {
+            qlog_with_type!(QLOG_VERSION_INFO, self.qlog, q, {
+                if !self.qlog.logged_peer_params {
+                    let ev_data = VersionInformation::to_qlog(
+                        &self.local_transport_params,
+                        &self.peer_transport_params,
+                        self.version,
+                        self.is_server,
+                    );
+
+                    q.add_event_data_with_instant(ev_data, now).ok();
+                }
+            });
+
             qlog_with_type!(QLOG_PARAMS_SET, self.qlog, q, {
                 if !self.qlog.logged_peer_params {
                     let ev_data = self
@@ -3465,6 +4162,23 @@ impl<F: BufFactory> Connection<F> {
                         }
                     },
 
+                    frame::Frame::PathAck {
+                        path_id, ranges, ..
+                    } => {
+                        let space = pkt_num_space_mut(
+                            &mut self.pkt_num_spaces,
+                            &mut self.multipath,
+                            epoch,
+                            path_id,
+                        );
+
+                        if let (Ok(space), Some(largest_acked)) =
+                            (space, ranges.last())
+                        {
+                            space.recv_pkt_need_ack.remove_until(largest_acked);
+                        }
+                    },
+
                     frame::Frame::CryptoHeader { offset, length } => {
                         self.crypto_ctx[epoch]
                             .crypto_stream
@@ -3521,6 +4235,15 @@ impl<F: BufFactory> Connection<F> {
                         self.handshake_done_acked = true;
                     },
 
+                    frame::Frame::AckFrequency {
+                        seq_num,
+                        request_max_ack_delay,
+                        ..
+                    } => {
+                        self.ack_freq
+                            .on_frame_acked(seq_num, request_max_ack_delay);
+                    },
+
                     frame::Frame::ResetStream { stream_id, .. } => {
                         let stream = match self.streams.get_mut(stream_id) {
                             Some(v) => v,
@@ -3542,6 +4265,8 @@ impl<F: BufFactory> Connection<F> {
             }
         }
 
+        self.update_peer_max_ack_delay();
+
         // Now that we processed all the frames, if there is a path that has no
         // Destination CID, try to allocate one.
         let no_dcid = self
@@ -3550,6 +4275,16 @@ impl<F: BufFactory> Connection<F> {
             .filter(|(_, p)| p.active_dcid_seq.is_none());
 
         for (pid, p) in no_dcid {
+            // Paths of other Path IDs use their own connection IDs.
+            if p.mp_path_id > 0 {
+                p.active_dcid_seq = self
+                    .multipath
+                    .as_ref()
+                    .and_then(|mp| mp.lowest_dcid_seq(p.mp_path_id));
+
+                continue;
+            }
+
             if self.ids.zero_length_dcid() {
                 p.active_dcid_seq = Some(0);
                 continue;
@@ -3565,34 +4300,60 @@ impl<F: BufFactory> Connection<F> {
             p.active_dcid_seq = Some(dcid_seq);
         }
 
+        let pkt_num_space = pkt_num_space_mut(
+            &mut self.pkt_num_spaces,
+            &mut self.multipath,
+            epoch,
+            mp_path_id,
+        )?;
+
         // We only record the time of arrival of the largest packet number
         // that still needs to be acked, to be used for ACK delay calculation.
-        if self.pkt_num_spaces[epoch].recv_pkt_need_ack.last() < Some(pn) {
-            self.pkt_num_spaces[epoch].largest_rx_pkt_time = now;
+        if pkt_num_space.recv_pkt_need_ack.last() < Some(pn) {
+            pkt_num_space.largest_rx_pkt_time = now;
         }
 
-        self.pkt_num_spaces[epoch].recv_pkt_num.insert(pn);
+        pkt_num_space.recv_pkt_num.insert(pn);
 
-        self.pkt_num_spaces[epoch].recv_pkt_need_ack.push_item(pn);
+        let reordered = self.ack_freq.on_packet_received(pkt_num_space, pn);
 
-        self.pkt_num_spaces[epoch].ack_elicited =
-            cmp::max(self.pkt_num_spaces[epoch].ack_elicited, ack_elicited);
+        pkt_num_space.recv_pkt_need_ack.push_item(pn);
 
-        self.pkt_num_spaces[epoch].largest_rx_pkt_num =
-            cmp::max(self.pkt_num_spaces[epoch].largest_rx_pkt_num, pn);
+        ecn::on_packet_received(&mut pkt_num_space.ecn_counts, info.ecn);
 
-        if !probing {
-            self.pkt_num_spaces[epoch].largest_rx_non_probing_pkt_num = cmp::max(
-                self.pkt_num_spaces[epoch].largest_rx_non_probing_pkt_num,
-                pn,
+        // ACKs can only be delayed in the application packet number space,
+        // based on the frequency requested by the peer. Congestion signals are
+        // reported to the peer immediately.
+        if ack_elicited {
+            self.ack_freq.on_ack_eliciting_received(
+                pkt_num_space,
+                epoch != packet::Epoch::Application ||
+                    immediate_ack ||
+                    reordered ||
+                    info.ecn == Ecn::Ce,
+                now,
             );
+        }
+
+        pkt_num_space.largest_rx_pkt_num =
+            cmp::max(pkt_num_space.largest_rx_pkt_num, pn);
 
-            // Did the peer migrated to another path?
+        if !probing {
+            pkt_num_space.largest_rx_non_probing_pkt_num =
+                cmp::max(pkt_num_space.largest_rx_non_probing_pkt_num, pn);
+
+            let is_largest_non_probing =
+                pkt_num_space.largest_rx_non_probing_pkt_num == pn;
+
+            // Did the peer migrated to another path? With multipath, only the
+            // initial Path ID can migrate, as other paths are used in
+            // parallel to it.
             let active_path_id = self.paths.get_active_path_id()?;
 
             if self.is_server &&
                 recv_pid != active_path_id &&
-                self.pkt_num_spaces[epoch].largest_rx_non_probing_pkt_num == pn
+                mp_path_id == 0 &&
+                is_largest_non_probing
             {
                 self.on_peer_migrated(recv_pid, self.disable_dcid_reuse, now)?;
             }
@@ -3801,6 +4562,12 @@ impl<F: BufFactory> Connection<F> {
         // take care of terminating the connection as needed.
         let _ = self.process_undecrypted_0rtt_packets();
 
+        // Once the handshake is confirmed, the client can start moving to the
+        // server's preferred address, if any.
+        if self.handshake_confirmed {
+            self.probe_preferred_address();
+        }
+
         // There's no point in trying to send a packet if the Initial secrets
         // have not been derived yet, so return early.
         if !self.derived_initial_secrets {
@@ -3849,12 +4616,16 @@ impl<F: BufFactory> Connection<F> {
             left = cmp::min(left, send_path.max_send_bytes);
         }
 
+        // All packets coalesced in the datagram share its ECN codepoint.
+        let ecn = send_path.ecn.codepoint();
+
         // Generate coalesced packets.
         while left > 0 {
             let (ty, written) = match self.send_single(
                 &mut out[done..done + left],
                 send_pid,
                 has_initial,
+                ecn,
                 now,
             ) {
                 Ok(v) => v,
@@ -3917,13 +4688,15 @@ impl<F: BufFactory> Connection<F> {
             to: send_path.peer_addr(),
 
             at: send_path.recovery.get_packet_send_time(now),
+
+            ecn,
         };
 
         Ok((done, info))
     }
 
     fn send_single(
-        &mut self, out: &mut [u8], send_pid: usize, has_initial: bool,
+        &mut self, out: &mut [u8], send_pid: usize, has_initial: bool, ecn: Ecn,
         now: Instant,
     ) -> Result<(Type, usize)> {
         if out.is_empty() {
@@ -3934,6 +4707,10 @@ impl<F: BufFactory> Connection<F> {
             return Err(Error::Done);
         }
 
+        self.update_peer_max_ack_delay();
+
+        self.check_aead_confidentiality_limit(now);
+
         let is_closing = self.local_error.is_some();
 
         let out_len = out.len();
@@ -4004,10 +4781,32 @@ impl<F: BufFactory> Connection<F> {
                         p.retrans_count += 1;
                     },
 
-                    frame::Frame::ACK { .. } => {
+                    frame::Frame::ACK { .. } |
+                    frame::Frame::PathAck { path_id: 0, .. } => {
                         pkt_space.ack_elicited = true;
                     },
 
+                    frame::Frame::PathAck { path_id, .. } => {
+                        let space = self
+                            .multipath
+                            .as_mut()
+                            .and_then(|mp| mp.space_mut(path_id).ok());
+
+                        if let Some(space) = space {
+                            space.pkt_num_space.ack_elicited = true;
+                        }
+                    },
+
+                    frame::Frame::PathAbandon { .. } |
+                    frame::Frame::PathStatusBackup { .. } |
+                    frame::Frame::PathStatusAvailable { .. } |
+                    frame::Frame::PathNewConnectionId { .. } |
+                    frame::Frame::PathRetireConnectionId { .. } |
+                    frame::Frame::MaxPathId { .. } =>
+                        if let Some(mp) = self.multipath.as_mut() {
+                            mp.on_frame_sent(&lost, true);
+                        },
+
                     frame::Frame::ResetStream {
                         stream_id,
                         error_code,
@@ -4024,6 +4823,11 @@ impl<F: BufFactory> Connection<F> {
                         self.handshake_done_sent = false;
                     },
 
+                    // A fresh token is minted when NEW_TOKEN is retransmitted.
+                    frame::Frame::NewToken { .. } => {
+                        self.new_token_pending = true;
+                    },
+
                     frame::Frame::MaxStreamData { stream_id, .. } => {
                         if self.streams.get(stream_id).is_some() {
                             self.streams.insert_almost_full(stream_id);
@@ -4042,6 +4846,10 @@ impl<F: BufFactory> Connection<F> {
                         self.ids.mark_retire_dcid_seq(seq_num, true)?;
                     },
 
+                    frame::Frame::AckFrequency { seq_num, .. } => {
+                        self.ack_freq.on_frame_lost(seq_num);
+                    },
+
                     frame::Frame::Ping {
                         mtu_probe: Some(failed_probe),
                     } =>
@@ -4070,11 +4878,37 @@ impl<F: BufFactory> Connection<F> {
             b.cap()
         };
 
-        if pkt_num_manager.should_skip_pn(self.handshake_completed) {
-            pkt_num_manager.set_skip_pn(Some(self.next_pkt_num));
-            self.next_pkt_num += 1;
+        let mp_path_id = path.mp_path_id;
+
+        // Paths of other Path IDs only exist once the handshake is confirmed.
+        if mp_path_id > 0 && pkt_type != Type::Short {
+            return Err(Error::Done);
+        }
+
+        // With multipath, any validated path can carry non-probing frames, not
+        // only the active one.
+        let is_active = path.active();
+        let can_send = is_active ||
+            self.multipath
+                .as_ref()
+                .is_some_and(|mp| path.usable() && mp.is_usable(mp_path_id));
+
+        let redundant = can_send &&
+            self.multipath.is_some() &&
+            self.path_scheduler.redundant();
+
+        let pn = match self.multipath.as_mut() {
+            Some(mp) if mp_path_id > 0 => mp.space_mut(mp_path_id)?.next_pkt_num,
+
+            _ => {
+                if pkt_num_manager.should_skip_pn(self.handshake_completed) {
+                    pkt_num_manager.set_skip_pn(Some(self.next_pkt_num));
+                    self.next_pkt_num += 1;
+                };
+
+                self.next_pkt_num
+            },
         };
-        let pn = self.next_pkt_num;
 
         let largest_acked_pkt =
             path.recovery.get_largest_acked_on_epoch(epoch).unwrap_or(0);
@@ -4085,15 +4919,31 @@ impl<F: BufFactory> Connection<F> {
 
         let dcid_seq = path.active_dcid_seq.ok_or(Error::OutOfIdentifiers)?;
 
-        let dcid =
-            ConnectionId::from_ref(self.ids.get_dcid(dcid_seq)?.cid.as_ref());
+        let (dcid, scid) = match self.multipath.as_ref() {
+            // Short header packets don't carry the Source Connection ID, so
+            // there is no need to look it up.
+            Some(mp) if mp_path_id > 0 => (
+                ConnectionId::from_ref(mp.get_dcid(mp_path_id, dcid_seq)?),
+                ConnectionId::default(),
+            ),
 
-        let scid = if let Some(scid_seq) = path.active_scid_seq {
-            ConnectionId::from_ref(self.ids.get_scid(scid_seq)?.cid.as_ref())
-        } else if pkt_type == Type::Short {
-            ConnectionId::default()
-        } else {
-            return Err(Error::InvalidState);
+            _ => {
+                let dcid = ConnectionId::from_ref(
+                    self.ids.get_dcid(dcid_seq)?.cid.as_ref(),
+                );
+
+                let scid = if let Some(scid_seq) = path.active_scid_seq {
+                    ConnectionId::from_ref(
+                        self.ids.get_scid(scid_seq)?.cid.as_ref(),
+                    )
+                } else if pkt_type == Type::Short {
+                    ConnectionId::default()
+                } else {
+                    return Err(Error::InvalidState);
+                };
+
+                (dcid, scid)
+            },
         };
 
         let hdr = Header {
@@ -4210,13 +5060,13 @@ impl<F: BufFactory> Connection<F> {
         // send a packet with PING anyways, even if we haven't received anything
         // ACK eliciting.
         if pkt_space.recv_pkt_need_ack.len() > 0 &&
-            (pkt_space.ack_elicited || ack_elicit_required) &&
+            (pkt_space.ack_pending() || ack_elicit_required) &&
             (!is_closing ||
                 (pkt_type == Type::Handshake &&
                     self.local_error
                         .as_ref()
                         .is_some_and(|le| le.is_app))) &&
-            path.active()
+            can_send
         {
             let ack_delay = pkt_space.largest_rx_pkt_time.elapsed();
 
@@ -4227,7 +5077,7 @@ impl<F: BufFactory> Connection<F> {
             let frame = frame::Frame::ACK {
                 ack_delay,
                 ranges: pkt_space.recv_pkt_need_ack.clone(),
-                ecn_counts: None, // sending ECN is not supported at this time
+                ecn_counts: pkt_space.ack_ecn_counts(),
             };
 
             // When a PING frame needs to be sent, avoid sending the ACK if
@@ -4239,7 +5089,45 @@ impl<F: BufFactory> Connection<F> {
                 // be bundled considering the buffer capacity only, and not the
                 // available cwnd.
                 if push_frame_to_pkt!(b, frames, frame, left) {
-                    pkt_space.ack_elicited = false;
+                    pkt_space.on_ack_sent();
+                }
+            }
+        }
+
+        // Create PATH_ACK frames for the other Path IDs, which can be sent on
+        // any path.
+        if let Some(mp) = self.multipath.as_mut() {
+            let spaces = mp
+                .spaces_mut()
+                .filter(|_| pkt_type == Type::Short && can_send && !is_closing);
+
+            for (path_id, space) in spaces {
+                let pkt_space = &mut space.pkt_num_space;
+
+                if pkt_space.recv_pkt_need_ack.len() == 0 ||
+                    !(pkt_space.ack_pending() || ack_elicit_required)
+                {
+                    continue;
+                }
+
+                let ack_delay = pkt_space.largest_rx_pkt_time.elapsed();
+
+                let ack_delay = ack_delay.as_micros() as u64 /
+                    2_u64.pow(
+                        self.local_transport_params.ack_delay_exponent as u32,
+                    );
+
+                let frame = frame::Frame::PathAck {
+                    path_id,
+                    ack_delay,
+                    ranges: pkt_space.recv_pkt_need_ack.clone(),
+                    ecn_counts: pkt_space.ack_ecn_counts(),
+                };
+
+                if (pkt_space.ack_elicited || frame.wire_len() < cwnd_available) &&
+                    push_frame_to_pkt!(b, frames, frame, left)
+                {
+                    pkt_space.on_ack_sent();
                 }
             }
         }
@@ -4267,13 +5155,17 @@ impl<F: BufFactory> Connection<F> {
             // In addition, the PMTUD probe is only generated when the handshake
             // is confirmed, to avoid interfering with the handshake
             // (e.g. due to the anti-amplification limits).
-            let should_probe_pmtu = active_path.should_send_pmtu_probe(
-                self.handshake_confirmed,
-                self.handshake_completed,
-                out_len,
-                is_closing,
-                frames.is_empty(),
-            );
+            //
+            // With multipath, probes are only sent on the active path, as
+            // packets sent on other paths are accounted separately.
+            let should_probe_pmtu = (self.multipath.is_none() || is_active) &&
+                active_path.should_send_pmtu_probe(
+                    self.handshake_confirmed,
+                    self.handshake_completed,
+                    out_len,
+                    is_closing,
+                    frames.is_empty(),
+                );
 
             if should_probe_pmtu {
                 if let Some(pmtud) = active_path.pmtud.as_mut() {
@@ -4383,7 +5275,43 @@ impl<F: BufFactory> Connection<F> {
             }
         }
 
-        if pkt_type == Type::Short && !is_closing && path.active() {
+        // Create multipath frames as needed.
+        if let Some(mp) = self.multipath.as_mut() {
+            if pkt_type == Type::Short && !is_closing && can_send {
+                while let Some(frame) = mp.next_frame() {
+                    if frame.wire_len() > left {
+                        break;
+                    }
+
+                    mp.on_frame_sent(&frame, false);
+
+                    if push_frame_to_pkt!(b, frames, frame, left) {
+                        ack_eliciting = true;
+                        in_flight = true;
+                    }
+                }
+            }
+        }
+
+        // Create ACK_FREQUENCY frame as needed.
+        if pkt_type == Type::Short &&
+            !is_closing &&
+            can_send &&
+            self.handshake_confirmed
+        {
+            self.ack_freq.update(path.recovery.ack_frequency());
+
+            if let Some(frame) = self.ack_freq.next_frame() {
+                if push_frame_to_pkt!(b, frames, frame, left) {
+                    self.ack_freq.on_frame_sent();
+
+                    ack_eliciting = true;
+                    in_flight = true;
+                }
+            }
+        }
+
+        if pkt_type == Type::Short && !is_closing && can_send {
             // Create HANDSHAKE_DONE frame.
             // self.should_send_handshake_done() but without the need to borrow
             if self.handshake_completed &&
@@ -4400,6 +5328,30 @@ impl<F: BufFactory> Connection<F> {
                 }
             }
 
+            // Create NEW_TOKEN frame.
+            if self.new_token_pending {
+                let peer = path.peer_addr();
+
+                match self.token_validator.as_ref().and_then(|v| v.mint(&peer)) {
+                    Some(v) => {
+                        let mut token = Vec::with_capacity(1 + v.len());
+                        token.push(token::NEW_TOKEN_PREFIX);
+                        token.extend_from_slice(&v);
+
+                        let frame = frame::Frame::NewToken { token };
+
+                        if push_frame_to_pkt!(b, frames, frame, left) {
+                            self.new_token_pending = false;
+
+                            ack_eliciting = true;
+                            in_flight = true;
+                        }
+                    },
+
+                    None => self.new_token_pending = false,
+                }
+            }
+
             // Create MAX_STREAMS_BIDI frame.
             if self.streams.should_update_max_streams_bidi() {
                 let frame = frame::Frame::MaxStreamsBidi {
@@ -4570,7 +5522,7 @@ impl<F: BufFactory> Connection<F> {
                 // packet in which the frame is contained.
                 let dcid_seq = path.active_dcid_seq.ok_or(Error::InvalidState)?;
 
-                if seq_num == dcid_seq {
+                if mp_path_id == 0 && seq_num == dcid_seq {
                     continue;
                 }
 
@@ -4589,7 +5541,7 @@ impl<F: BufFactory> Connection<F> {
 
         // Create CONNECTION_CLOSE frame. Try to send this only on the active
         // path, unless it is the last one available.
-        if path.active() || n_paths == 1 {
+        if can_send || n_paths == 1 {
             if let Some(conn_err) = self.local_error.as_ref() {
                 if conn_err.is_app {
                     // Create ApplicationClose frame.
@@ -4630,7 +5582,7 @@ impl<F: BufFactory> Connection<F> {
         if crypto_ctx.crypto_stream.is_flushable() &&
             left > frame::MAX_CRYPTO_OVERHEAD &&
             !is_closing &&
-            path.active()
+            can_send
         {
             let crypto_off = crypto_ctx.crypto_stream.send.off_front();
 
@@ -4708,11 +5660,43 @@ impl<F: BufFactory> Connection<F> {
             do_dgram = true;
         }
 
+        // Copies of frames sent on other paths by redundant scheduling.
+        let mut redundant_frames: SmallVec<[frame::Frame; 1]> = SmallVec::new();
+
+        // Send pending copies first. They are best effort, so drop those that
+        // don't fit, as well as the ones of streams that were stopped.
+        if let Some(queue) = self
+            .multipath
+            .as_mut()
+            .filter(|_| pkt_type == Type::Short && !is_closing && can_send)
+            .and_then(|mp| mp.redundant_frames(send_pid))
+        {
+            while let Some(frame) = queue.pop_front() {
+                if let frame::Frame::Stream { stream_id, .. } = frame {
+                    if self
+                        .streams
+                        .get(stream_id)
+                        .is_none_or(|s| s.send.is_stopped())
+                    {
+                        continue;
+                    }
+                }
+
+                if !push_frame_to_pkt!(b, frames, frame, left) {
+                    break;
+                }
+
+                ack_eliciting = true;
+                in_flight = true;
+                has_data = true;
+            }
+        }
+
         // Create DATAGRAM frame.
         if (pkt_type == Type::Short || pkt_type == Type::ZeroRTT) &&
             left > frame::MAX_DGRAM_OVERHEAD &&
             !is_closing &&
-            path.active() &&
+            can_send &&
             do_dgram
         {
             if let Some(max_dgram_payload) = max_dgram_len {
@@ -4747,6 +5731,11 @@ impl<F: BufFactory> Connection<F> {
                                 dgram_payload.as_mut()[..len]
                                     .copy_from_slice(&data);
 
+                                if redundant {
+                                    redundant_frames
+                                        .push(frame::Frame::Datagram { data });
+                                }
+
                                 // Encode the frame's header.
                                 //
                                 // Due to how `OctetsMut::split_at()` works,
@@ -4794,7 +5783,7 @@ impl<F: BufFactory> Connection<F> {
         if (pkt_type == Type::Short || pkt_type == Type::ZeroRTT) &&
             left > frame::MAX_STREAM_OVERHEAD &&
             !is_closing &&
-            path.active() &&
+            can_send &&
             !dgram_emitted
         {
             while let Some(priority_key) = self.streams.peek_flushable() {
@@ -4868,6 +5857,19 @@ impl<F: BufFactory> Connection<F> {
                 // Advance the packet buffer's offset.
                 b.skip(hdr_len + len)?;
 
+                if redundant {
+                    let data_off = hdr_off + hdr_len;
+
+                    redundant_frames.push(frame::Frame::Stream {
+                        stream_id,
+                        data: RangeBuf::from(
+                            &b.buf()[data_off..data_off + len],
+                            stream_off,
+                            fin,
+                        ),
+                    });
+                }
+
                 let frame = frame::Frame::StreamHeader {
                     stream_id,
                     offset: stream_off,
@@ -4915,7 +5917,16 @@ impl<F: BufFactory> Connection<F> {
             left >= 1 &&
             !is_closing
         {
-            let frame = frame::Frame::Ping { mtu_probe: None };
+            // When the peer was asked to delay ACKs, make sure loss probes
+            // are acknowledged right away.
+            let frame = if ack_elicit_required &&
+                pkt_type == Type::Short &&
+                self.ack_freq.immediate_ack_enabled()
+            {
+                frame::Frame::ImmediateAck
+            } else {
+                frame::Frame::Ping { mtu_probe: None }
+            };
 
             if push_frame_to_pkt!(b, frames, frame, left) {
                 ack_eliciting = true;
@@ -5042,6 +6053,14 @@ impl<F: BufFactory> Connection<F> {
             None => return Err(Error::InvalidState),
         };
 
+        // Packets of other Path IDs use their own nonce.
+        let aead = match self.multipath.as_mut() {
+            Some(mp) if mp_path_id > 0 =>
+                mp.space_mut(mp_path_id)?.seal(mp_path_id, aead)?,
+
+            _ => aead,
+        };
+
         let written = packet::encrypt_pkt(
             &mut b,
             pn,
@@ -5052,6 +6071,10 @@ impl<F: BufFactory> Connection<F> {
             aead,
         )?;
 
+        if pkt_type == Type::Short {
+            self.key_phase_sent += 1;
+        }
+
         let sent_pkt_has_data = if path.recovery.gcongestion_enabled() {
             has_data || dgram_emitted
         } else {
@@ -5075,13 +6098,19 @@ impl<F: BufFactory> Connection<F> {
             lost: 0,
             has_data: sent_pkt_has_data,
             is_pmtud_probe,
+            ecn_marked: ecn == Ecn::Ect0,
         };
 
         if in_flight && is_app_limited {
             path.recovery.delivery_rate_update_app_limited(true);
         }
 
-        self.next_pkt_num += 1;
+        match self.multipath.as_mut() {
+            Some(mp) if mp_path_id > 0 =>
+                mp.space_mut(mp_path_id)?.next_pkt_num += 1,
+
+            _ => self.next_pkt_num += 1,
+        }
 
         let handshake_status = recovery::HandshakeStatus {
             has_handshake_keys: self.crypto_ctx[packet::Epoch::Handshake]
@@ -5107,6 +6136,8 @@ impl<F: BufFactory> Connection<F> {
         path.sent_count += 1;
         path.sent_bytes += written as u64;
 
+        path.ecn.on_packet_sent(ecn);
+
         if self.dgram_send_queue.byte_size() > path.recovery.cwnd_available() {
             path.recovery.update_app_limited(false);
         }
@@ -5130,6 +6161,10 @@ impl<F: BufFactory> Connection<F> {
             self.ack_eliciting_sent = true;
         }
 
+        if !redundant_frames.is_empty() {
+            self.queue_redundant_frames(send_pid, &redundant_frames);
+        }
+
         let active_path = self.paths.get_active_mut()?;
         if let Some(pmtud) = active_path.pmtud.as_mut() {
             active_path
@@ -5147,15 +6182,26 @@ impl<F: BufFactory> Connection<F> {
     ) -> Result<()> {
         let path = self.paths.get_mut(send_pid)?;
 
-        // It's fine to set the skip counter based on a non-active path's values.
-        let cwnd = path.recovery.cwnd();
-        let max_datagram_size = path.recovery.max_datagram_size();
-        self.pkt_num_spaces[epoch].on_packet_sent(&sent_pkt);
-        self.pkt_num_manager.on_packet_sent(
-            cwnd,
-            max_datagram_size,
-            self.handshake_completed,
-        );
+        if path.mp_path_id > 0 {
+            pkt_num_space_mut(
+                &mut self.pkt_num_spaces,
+                &mut self.multipath,
+                epoch,
+                path.mp_path_id,
+            )?
+            .on_packet_sent(&sent_pkt);
+        } else {
+            // It's fine to set the skip counter based on a non-active path's
+            // values.
+            let cwnd = path.recovery.cwnd();
+            let max_datagram_size = path.recovery.max_datagram_size();
+            self.pkt_num_spaces[epoch].on_packet_sent(&sent_pkt);
+            self.pkt_num_manager.on_packet_sent(
+                cwnd,
+                max_datagram_size,
+                self.handshake_completed,
+            );
+        }
 
         path.recovery.on_packet_sent(
             sent_pkt,
@@ -5168,6 +6214,33 @@ impl<F: BufFactory> Connection<F> {
         Ok(())
     }
 
+    /// Queues copies of the given frames, sent on the path `send_pid`, to be
+    /// sent on all the other usable paths.
+    fn queue_redundant_frames(
+        &mut self, send_pid: usize, frames: &[frame::Frame],
+    ) {
+        let mp = match self.multipath.as_mut() {
+            Some(v) => v,
+
+            None => return,
+        };
+
+        let pids: SmallVec<[usize; 4]> = self
+            .paths
+            .iter()
+            .filter(|(pid, p)| {
+                *pid != send_pid && p.usable() && mp.is_usable(p.mp_path_id)
+            })
+            .map(|(pid, _)| pid)
+            .collect();
+
+        for pid in pids {
+            for frame in frames {
+                mp.push_redundant_frame(pid, frame.clone());
+            }
+        }
+    }
+
     /// Returns the desired send time for the next packet.
     #[inline]
     pub fn get_next_release_time(&self) -> Option<ReleaseDecision> {
@@ -6094,6 +7167,24 @@ impl<F: BufFactory> Connection<F> {
         Ok(())
     }
 
+    /// Initiates an update of the 1-RTT packet protection keys.
+    ///
+    /// The following packets are sent with the next keys, and the peer
+    /// updates its own keys in response. quiche also updates keys on its own
+    /// before reaching the confidentiality limit of the negotiated AEAD.
+    ///
+    /// [`InvalidState`] is returned if the handshake isn't confirmed yet, or
+    /// if the peer didn't acknowledge any packet sent with the current keys,
+    /// which includes any previous key update still in progress.
+    ///
+    /// [`Done`] is returned if the connection is closing.
+    ///
+    /// [`InvalidState`]: enum.Error.html#variant.InvalidState
+    /// [`Done`]: enum.Error.html#variant.Done
+    pub fn initiate_key_update(&mut self) -> Result<()> {
+        self.update_keys_locally(Instant::now())
+    }
+
     /// Reads the first received DATAGRAM.
     ///
     /// On success the DATAGRAM's data is returned along with its size.
@@ -6408,12 +7499,167 @@ impl<F: BufFactory> Connection<F> {
                 .as_ref()
                 .map(|key_update| key_update.timer);
 
-            let timers = [self.idle_timer, path_timer, key_update_timer];
+            let ack_timer = self.ack_timer();
+
+            let timers =
+                [self.idle_timer, path_timer, key_update_timer, ack_timer];
 
             timers.iter().filter_map(|&x| x).min()
         }
     }
 
+    /// Switches to the next 1-RTT keys, and keeps the previous key to open
+    /// packets sent before the update.
+    ///
+    /// `pn_on_update` is the first packet number the peer sent with the new
+    /// keys, or `u64::MAX` if it isn't known yet. The previous key is
+    /// discarded after 3 times `pto`.
+    fn update_keys(
+        &mut self, open_next: crypto::Open, seal_next: crypto::Seal,
+        pn_on_update: u64, pto: Duration, local: bool, now: Instant,
+    ) {
+        let crypto_ctx = &mut self.crypto_ctx[packet::Epoch::Application];
+
+        let _ = crypto_ctx.crypto_seal.replace(seal_next);
+
+        // The 1-RTT keys are always set once the handshake is confirmed.
+        let open_prev = crypto_ctx.crypto_open.replace(open_next).unwrap();
+
+        crypto_ctx.key_update = Some(packet::KeyUpdate {
+            crypto_open: open_prev,
+            pn_on_update,
+            update_acked: false,
+            timer: now + pto * 3,
+        });
+
+        self.key_phase = !self.key_phase;
+        self.key_phase_first_pn = self.next_pkt_num;
+        self.key_phase_acked = false;
+        self.key_phase_sent = 0;
+
+        if local {
+            self.key_update_local_count += 1;
+        } else {
+            self.key_update_remote_count += 1;
+        }
+
+        if let Some(mp) = self.multipath.as_mut() {
+            mp.on_key_update();
+        }
+
+        qlog_with_type!(QLOG_KEY_UPDATED, self.qlog, q, {
+            let trigger = if local {
+                qlog::events::security::KeyUpdateOrRetiredTrigger::LocalUpdate
+            } else {
+                qlog::events::security::KeyUpdateOrRetiredTrigger::RemoteUpdate
+            };
+
+            let key_types = [
+                qlog::events::security::KeyType::Client1RttSecret,
+                qlog::events::security::KeyType::Server1RttSecret,
+            ];
+
+            for key_type in key_types {
+                let ev_data =
+                    EventData::KeyUpdated(qlog::events::security::KeyUpdated {
+                        key_type,
+                        generation: Some(
+                            (self.key_update_local_count +
+                                self.key_update_remote_count)
+                                as u32,
+                        ),
+                        trigger: Some(trigger.clone()),
+                        ..Default::default()
+                    });
+
+                q.add_event_data_with_instant(ev_data, now).ok();
+            }
+        });
+    }
+
+    /// Initiates a key update, see [`initiate_key_update()`].
+    ///
+    /// [`initiate_key_update()`]: struct.Connection.html#method.initiate_key_update
+    fn update_keys_locally(&mut self, now: Instant) -> Result<()> {
+        if self.is_closed() || self.is_draining() || self.local_error.is_some() {
+            return Err(Error::Done);
+        }
+
+        // A new key update can't start before the peer acknowledged a packet
+        // sent with the current keys, which also means the peer is done with
+        // the previous update.
+        if !self.handshake_confirmed || !self.key_phase_acked {
+            return Err(Error::InvalidState);
+        }
+
+        let crypto_ctx = &self.crypto_ctx[packet::Epoch::Application];
+
+        let (open, seal) =
+            match (&crypto_ctx.crypto_open, &crypto_ctx.crypto_seal) {
+                (Some(open), Some(seal)) => (open, seal),
+
+                _ => return Err(Error::InvalidState),
+            };
+
+        let open_next = open.derive_next_packet_key()?;
+        let seal_next = seal.derive_next_packet_key()?;
+
+        trace!("{} local key update", self.trace_id);
+
+        let pto = self.paths.get_active()?.recovery.pto();
+
+        // Until the peer responds to the update, all the packets it sends with
+        // the previous key phase use the previous keys.
+        self.update_keys(open_next, seal_next, u64::MAX, pto, true, now);
+
+        Ok(())
+    }
+
+    /// Updates the 1-RTT keys before they reach the AEAD confidentiality
+    /// limit, or closes the connection if they can't be updated in time.
+    fn check_aead_confidentiality_limit(&mut self, now: Instant) {
+        let limit = match &self.crypto_ctx[packet::Epoch::Application].crypto_seal
+        {
+            Some(seal) => seal.alg().confidentiality_limit(),
+
+            None => return,
+        };
+
+        if self.key_phase_sent < limit / KEY_UPDATE_LIMIT_DIVISOR {
+            return;
+        }
+
+        if self.update_keys_locally(now).is_ok() {
+            return;
+        }
+
+        if self.key_phase_sent >= limit - AEAD_LIMIT_CLOSE_MARGIN {
+            self.close(false, WireErrorCode::AeadLimitReached as u64, b"")
+                .ok();
+        }
+    }
+
+    /// Updates the peer's maximum ACK delay used for loss recovery, after a
+    /// different ACK frequency was requested from the peer.
+    fn update_peer_max_ack_delay(&mut self) {
+        if let Some(max_ack_delay) = self.ack_freq.take_recovery_max_ack_delay() {
+            self.recovery_config.max_ack_delay = max_ack_delay;
+
+            for (_, p) in self.paths.iter_mut() {
+                p.recovery.update_max_ack_delay(max_ack_delay);
+            }
+        }
+    }
+
+    /// Returns when delayed ACKs need to be sent, if any.
+    fn ack_timer(&self) -> Option<Instant> {
+        let app_timer = self.pkt_num_spaces[packet::Epoch::Application].ack_timer;
+
+        let mp_timer = self.multipath.as_ref().and_then(|mp| mp.ack_timer());
+
+        app_timer.into_iter().chain(mp_timer).min()
+    }
+
     /// Returns the amount of time until the next timeout event.
     ///
     /// Once the given duration has elapsed, the [`on_timeout()`] method should
@@ -6474,6 +7720,24 @@ impl<F: BufFactory> Connection<F> {
             }
         }
 
+        // Delayed ACKs are due.
+        if self.ack_timer().is_some_and(|timer| timer <= now) {
+            let app_space = &mut self.pkt_num_spaces[packet::Epoch::Application];
+
+            let mp_spaces = self
+                .multipath
+                .iter_mut()
+                .flat_map(|mp| mp.spaces_mut())
+                .map(|(_, space)| &mut space.pkt_num_space);
+
+            for space in std::iter::once(app_space).chain(mp_spaces) {
+                if space.ack_timer.is_some_and(|timer| timer <= now) {
+                    space.ack_timer = None;
+                    space.ack_elicited = true;
+                }
+            }
+        }
+
         let handshake_status = self.handshake_status();
 
         for (_, p) in self.paths.iter_mut() {
@@ -6558,6 +7822,10 @@ impl<F: BufFactory> Connection<F> {
         // We may want to probe an existing path.
         let pid = match self.paths.path_id_from_addrs(&(local_addr, peer_addr)) {
             Some(pid) => pid,
+
+            None if self.multipath.is_some() =>
+                self.create_mp_path_on_client(local_addr, peer_addr)?,
+
             None => self.create_path_on_client(local_addr, peer_addr)?,
         };
 
@@ -6586,6 +7854,9 @@ impl<F: BufFactory> Connection<F> {
     /// Connection migration can only be initiated by the client. Calling this
     /// method as a server returns [`InvalidState`].
     ///
+    /// When the multipath extension is negotiated, new paths are opened with
+    /// [`probe_path()`] instead, and this method returns [`InvalidState`].
+    ///
     /// To initiate voluntary migration, there should be enough Connection IDs
     /// at both sides. If this requirement is not satisfied, this call returns
     /// [`OutOfIdentifiers`].
@@ -6594,10 +7865,11 @@ impl<F: BufFactory> Connection<F> {
     ///
     /// [`OutOfIdentifiers`]: enum.Error.html#OutOfIdentifiers
     /// [`InvalidState`]: enum.Error.html#InvalidState
+    /// [`probe_path()`]: struct.Connection.html#method.probe_path
     pub fn migrate(
         &mut self, local_addr: SocketAddr, peer_addr: SocketAddr,
     ) -> Result<u64> {
-        if self.is_server {
+        if self.is_server || self.multipath.is_some() {
             return Err(Error::InvalidState);
         }
 
@@ -6802,7 +8074,10 @@ impl<F: BufFactory> Connection<F> {
 
     /// Returns the number of source Connection IDs that are retired.
     pub fn retired_scids(&self) -> usize {
-        self.ids.retired_source_cids()
+        let mp_retired =
+            self.multipath.as_ref().map_or(0, |mp| mp.retired_scids());
+
+        self.ids.retired_source_cids() + mp_retired
     }
 
     /// Returns a source `ConnectionId` that has been retired.
@@ -6812,7 +8087,120 @@ impl<F: BufFactory> Connection<F> {
     ///
     /// [`ConnectionId`]: struct.ConnectionId.html
     pub fn retired_scid_next(&mut self) -> Option<ConnectionId<'static>> {
-        self.ids.pop_retired_scid()
+        if let Some(cid) = self.ids.pop_retired_scid() {
+            return Some(cid);
+        }
+
+        self.multipath.as_mut().and_then(|mp| mp.pop_retired_scid())
+    }
+
+    /// Returns whether the multipath extension was negotiated with the peer.
+    ///
+    /// Multipath is enabled when both endpoints advertise a maximum Path ID
+    /// (see [`set_initial_max_path_id()`]) and use non-zero length connection
+    /// IDs.
+    ///
+    /// [`set_initial_max_path_id()`]: struct.Config.html#method.set_initial_max_path_id
+    #[inline]
+    pub fn is_multipath(&self) -> bool {
+        self.multipath.is_some()
+    }
+
+    /// Returns the largest Path ID that can currently be used, or `None` if
+    /// multipath was not negotiated.
+    pub fn max_path_id(&self) -> Option<u64> {
+        self.multipath.as_ref().map(|mp| mp.max_path_id())
+    }
+
+    /// Provides a source Connection ID that the peer can use to reach this
+    /// host on the given multipath Path ID.
+    ///
+    /// This triggers sending PATH_NEW_CONNECTION_ID frames. Connection IDs for
+    /// Path ID 0 are provided with [`new_scid()`] instead.
+    ///
+    /// A new path can only be opened on a Path ID for which both endpoints
+    /// provided Connection IDs.
+    ///
+    /// If multipath was not negotiated, if `path_id` is 0, exceeds the maximum
+    /// Path ID allowed by the peer or was abandoned, this returns an
+    /// [`InvalidState`]. If the peer's limit of active Connection IDs for the
+    /// Path ID is reached, this returns an [`IdLimit`].
+    ///
+    /// Returns the sequence number associated to the provided Connection ID.
+    ///
+    /// [`new_scid()`]: struct.Connection.html#method.new_scid
+    /// [`InvalidState`]: enum.Error.html#InvalidState
+    /// [`IdLimit`]: enum.Error.html#IdLimit
+    pub fn new_scid_on_path(
+        &mut self, path_id: u64, scid: &ConnectionId, reset_token: u128,
+    ) -> Result<u64> {
+        let mp = self.multipath.as_mut().ok_or(Error::InvalidState)?;
+
+        mp.new_scid(path_id, scid.to_vec().into(), reset_token)
+    }
+
+    /// Sets the status of the multipath path between `local_addr` and
+    /// `peer_addr`, and advertises it to the peer.
+    ///
+    /// Paths with the [`Backup`] status are only used by the default scheduler
+    /// when no available path can be used.
+    ///
+    /// If multipath was not negotiated, or the path doesn't exist or was
+    /// abandoned, this returns an [`InvalidState`].
+    ///
+    /// [`Backup`]: enum.PathStatus.html#variant.Backup
+    /// [`InvalidState`]: enum.Error.html#InvalidState
+    pub fn set_path_status(
+        &mut self, local_addr: SocketAddr, peer_addr: SocketAddr,
+        status: PathStatus,
+    ) -> Result<()> {
+        let pid = self
+            .paths
+            .path_id_from_addrs(&(local_addr, peer_addr))
+            .ok_or(Error::InvalidState)?;
+
+        let mp_path_id = self.paths.get(pid)?.mp_path_id;
+
+        self.multipath
+            .as_mut()
+            .ok_or(Error::InvalidState)?
+            .set_status(mp_path_id, status)
+    }
+
+    /// Abandons the multipath path between `local_addr` and `peer_addr`.
+    ///
+    /// This triggers sending a PATH_ABANDON frame with the given
+    /// `error_code`, and retires all the Connection IDs of the path. Packets in
+    /// flight on the path are declared lost, and a [`PathEvent::Closed`] event
+    /// is generated.
+    ///
+    /// The path used to open the connection can't be abandoned. If the caller
+    /// tries to do so, if multipath was not negotiated, or if the path doesn't
+    /// exist, this returns an [`InvalidState`].
+    ///
+    /// [`PathEvent::Closed`]: enum.PathEvent.html#variant.Closed
+    /// [`InvalidState`]: enum.Error.html#InvalidState
+    pub fn abandon_path(
+        &mut self, local_addr: SocketAddr, peer_addr: SocketAddr, error_code: u64,
+    ) -> Result<()> {
+        let pid = self
+            .paths
+            .path_id_from_addrs(&(local_addr, peer_addr))
+            .ok_or(Error::InvalidState)?;
+
+        let mp_path_id = self.paths.get(pid)?.mp_path_id;
+
+        let mp = self.multipath.as_mut().ok_or(Error::InvalidState)?;
+
+        if mp_path_id == 0 {
+            return Err(Error::InvalidState);
+        }
+
+        if mp.abandon(mp_path_id, error_code) {
+            self.on_mp_path_abandoned(mp_path_id, Instant::now())?;
+        }
+
+        Ok(())
     }
 
     /// Returns the number of spare Destination Connection IDs, i.e.,
@@ -6965,7 +8353,40 @@ impl<F: BufFactory> Connection<F> {
         self.alpn.as_ref()
     }
 
+    /// Returns the QUIC version used by the connection.
+    ///
+    /// This might differ from the configured version as a result of version
+    /// negotiation, or because the server switched to a compatible version it
+    /// prefers.
+    #[inline]
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns the versions the peer advertised as supported, in order of
+    /// preference.
+    ///
+    /// If the peer's transport parameters haven't been processed yet, or the
+    /// peer doesn't support compatible version negotiation, `None` is returned.
+    #[inline]
+    pub fn peer_available_versions(&self) -> Option<&[u32]> {
+        if !self.parsed_peer_transport_params {
+            return None;
+        }
+
+        self.peer_transport_params
+            .version_information
+            .as_ref()
+            .map(|info| info.available_versions.as_slice())
+    }
+
     /// Returns the server name requested by the client.
+    ///
+    /// On the server, this is the server name of the encrypted ClientHello
+    /// when ECH is accepted, and the public name sent in clear otherwise,
+    /// which [`ech_accepted()`] tells apart.
+    ///
+    /// [`ech_accepted()`]: struct.Connection.html#method.ech_accepted
     #[inline]
     pub fn server_name(&self) -> Option<&str> {
         self.handshake.server_name()
@@ -7083,6 +8504,43 @@ impl<F: BufFactory> Connection<F> {
         self.handshake.is_resumed()
     }
 
+    /// Configures the Encrypted Client Hello (ECH) configurations offered by
+    /// the client, overriding the ones set with [`set_ech_config_list()`].
+    ///
+    /// This must be called before the first packet is sent, typically with
+    /// the configurations returned by [`ech_retry_configs()`] on a previous
+    /// connection attempt.
+    ///
+    /// [`set_ech_config_list()`]: struct.Config.html#method.set_ech_config_list
+    /// [`ech_retry_configs()`]: struct.Connection.html#method.ech_retry_configs
+    pub fn set_ech_config_list(&mut self, ech_config_list: &[u8]) -> Result<()> {
+        if self.is_server {
+            return Err(Error::InvalidState);
+        }
+
+        self.handshake.set_ech_config_list(ech_config_list)
+    }
+
+    /// Returns true if Encrypted Client Hello (ECH) was negotiated.
+    #[inline]
+    pub fn ech_accepted(&self) -> bool {
+        self.handshake.ech_accepted()
+    }
+
+    /// Returns the ECH configurations sent by the server when it rejected ECH.
+    ///
+    /// This is only available on the client after the handshake failed
+    /// because ECH was rejected, in which case the connection should be
+    /// retried with the returned configurations, see
+    /// [`Connection::set_ech_config_list()`]. An empty list means the server
+    /// disabled ECH, and the connection should be retried without it.
+    ///
+    /// [`Connection::set_ech_config_list()`]: struct.Connection.html#method.set_ech_config_list
+    #[inline]
+    pub fn ech_retry_configs(&self) -> Option<&[u8]> {
+        self.handshake.ech_retry_configs()
+    }
+
     /// Returns true if the connection has a pending handshake that has
     /// progressed enough to send or receive early data.
     #[inline]
@@ -7196,7 +8654,15 @@ impl<F: BufFactory> Connection<F> {
             reset_stream_count_remote: self.reset_stream_remote_count,
             stopped_stream_count_remote: self.stopped_stream_remote_count,
             path_challenge_rx_count: self.path_challenge_rx_count,
+            key_update_count_local: self.key_update_local_count,
+            key_update_count_remote: self.key_update_remote_count,
             bytes_in_flight_duration: self.bytes_in_flight_duration(),
+            ticket_key_generation: self.ticket_key_generation,
+            ticket_key_stats: self
+                .ticket_key_ring
+                .as_ref()
+                .map(|ring| ring.stats())
+                .unwrap_or_default(),
         }
     }
 
@@ -7230,18 +8696,104 @@ impl<F: BufFactory> Connection<F> {
         self.paths.iter().map(|(_, p)| p.stats())
     }
 
+    /// Returns the capacity measured on the active path.
+    ///
+    /// The record can be stored, for example alongside the session ticket, and
+    /// used to seed a later connection to the same peer with
+    /// [`set_path_capacity_record()`]. It is typically collected right before
+    /// the connection is closed.
+    ///
+    /// `None` is returned if there is no active path, or if no RTT sample was
+    /// taken on it yet.
+    ///
+    /// [`set_path_capacity_record()`]: struct.Connection.html#method.set_path_capacity_record
+    pub fn path_capacity_record(&self) -> Option<PathCapacityRecord> {
+        let path = self.paths.get_active().ok()?;
+
+        Some(PathCapacityRecord {
+            cwnd: path.recovery.cwnd(),
+            min_rtt: path.recovery.min_rtt()?,
+            bandwidth: path.recovery.delivery_rate().to_bytes_per_second(),
+        })
+    }
+
+    /// Seeds the congestion controller of the active path with the capacity
+    /// measured by a previous connection to the same peer.
+    ///
+    /// Instead of growing the congestion window from its initial value, the
+    /// congestion controller jumps to half of the saved capacity once it has
+    /// confirmed that the path's RTT is similar to the saved one, and falls
+    /// back to the capacity actually delivered if the jump causes congestion,
+    /// as described in [Careful Resume].
+    ///
+    /// Careful Resume is only supported by the `reno` and `cubic` congestion
+    /// control algorithms, [`CongestionControl`] is returned otherwise.
+    ///
+    /// [Careful Resume]: https://datatracker.ietf.org/doc/draft-ietf-tsvwg-careful-resume/
+    /// [`CongestionControl`]: enum.Error.html#variant.CongestionControl
+    pub fn set_path_capacity_record(
+        &mut self, record: &PathCapacityRecord,
+    ) -> Result<()> {
+        self.paths
+            .get_active_mut()?
+            .recovery
+            .setup_careful_resume(record)
+    }
+
     /// Returns whether or not this is a server-side connection.
     pub fn is_server(&self) -> bool {
         self.is_server
     }
 
     fn encode_transport_params(&mut self) -> Result<()> {
+        if let Some(info) = &mut self.local_transport_params.version_information {
+            info.chosen_version = self.version;
+        }
+
         self.handshake.set_quic_transport_params(
             &self.local_transport_params,
             self.is_server,
         )
     }
 
+    /// Returns whether the connection can switch to the given version without
+    /// restarting the handshake.
+    fn is_compatible_version(&self, version: u32) -> bool {
+        version_is_supported(self.version) &&
+            self.local_transport_params
+                .version_information
+                .as_ref()
+                .is_some_and(|info| info.available_versions.contains(&version))
+    }
+
+    /// Switches the connection to a compatible version, deriving new Initial
+    /// secrets from the given destination connection ID.
+    fn switch_to_compatible_version(
+        &mut self, version: u32, dcid: &ConnectionId,
+    ) -> Result<()> {
+        trace!(
+            "{} switching to compatible version {:x}",
+            self.trace_id,
+            version
+        );
+
+        self.version = version;
+
+        // Only the client has sent Initial packets with the previous keys
+        // already, so the server's AEAD context doesn't need to be primed.
+        let (aead_open, aead_seal) = crypto::derive_initial_key_material(
+            dcid,
+            self.version,
+            self.is_server,
+            !self.is_server,
+        )?;
+
+        self.crypto_ctx[packet::Epoch::Initial].crypto_open = Some(aead_open);
+        self.crypto_ctx[packet::Epoch::Initial].crypto_seal = Some(aead_seal);
+
+        Ok(())
+    }
+
     fn parse_peer_transport_params(
         &mut self, peer_params: TransportParams,
     ) -> Result<()> {
@@ -7288,6 +8840,62 @@ impl<F: BufFactory> Connection<F> {
             }
         }
 
+        // Validate version_information. The client's chosen version is the one
+        // its first flight was sent with, which at this point is still used by
+        // the server, while the server's is the one it switched to, if any.
+        if let Some(info) = &peer_params.version_information {
+            if info.chosen_version != self.version {
+                return Err(Error::VersionNegotiation);
+            }
+        }
+
+        // Version Negotiation packets are not authenticated, so the client
+        // needs to make sure it would have selected the same version based on
+        // the server's available versions.
+        if !self.is_server && self.did_version_negotiation {
+            let info = peer_params
+                .version_information
+                .as_ref()
+                .ok_or(Error::VersionNegotiation)?;
+
+            if select_version(&info.available_versions) != Some(self.version) {
+                return Err(Error::VersionNegotiation);
+            }
+        }
+
+        // The preferred address' connection ID is the one with sequence number
+        // 1, and can't be sent by a server using zero-length connection IDs.
+        if let Some(pa) = &peer_params.preferred_address {
+            if self.ids.zero_length_dcid() {
+                return Err(Error::InvalidTransportParam);
+            }
+
+            let mut retired_path_ids = SmallVec::new();
+
+            self.ids.new_dcid(
+                pa.connection_id.clone(),
+                1,
+                pa.stateless_reset_token,
+                0,
+                &mut retired_path_ids,
+            )?;
+
+            if !self.disable_preferred_address_migration {
+                let active_path = self.paths.get_active()?;
+
+                // Only consider the address from the same family as the one
+                // the connection was established on.
+                let peer_addr = if active_path.local_addr().is_ipv4() {
+                    pa.ipv4.map(SocketAddr::V4)
+                } else {
+                    pa.ipv6.map(SocketAddr::V6)
+                };
+
+                self.preferred_peer_addr =
+                    peer_addr.filter(|a| *a != active_path.peer_addr());
+            }
+        }
+
         self.process_peer_transport_params(peer_params)?;
 
         self.parsed_peer_transport_params = true;
@@ -7316,6 +8924,9 @@ impl<F: BufFactory> Connection<F> {
 
         active_path.recovery.update_max_ack_delay(max_ack_delay);
 
+        self.ack_freq
+            .on_peer_params(peer_params.min_ack_delay, peer_params.max_ack_delay);
+
         if active_path
             .pmtud
             .as_ref()
@@ -7340,6 +8951,25 @@ impl<F: BufFactory> Connection<F> {
         self.ids
             .set_source_conn_id_limit(peer_params.active_conn_id_limit);
 
+        // Multipath is enabled when both endpoints support it, as long as
+        // neither of them uses zero-length connection IDs.
+        match (
+            self.local_transport_params.initial_max_path_id,
+            peer_params.initial_max_path_id,
+        ) {
+            (Some(local_max), Some(peer_max)) if !self.ids.zero_length_dcid() =>
+                if self.multipath.is_none() {
+                    self.multipath = Some(multipath::Multipath::new(
+                        local_max,
+                        peer_max,
+                        self.local_transport_params.active_conn_id_limit as usize,
+                        peer_params.active_conn_id_limit as usize,
+                    ));
+                },
+
+            _ => self.multipath = None,
+        }
+
         self.peer_transport_params = peer_params;
 
         Ok(())
@@ -7364,12 +8994,20 @@ impl<F: BufFactory> Connection<F> {
 
             local_transport_params: self.local_transport_params.clone(),
 
-            recovery_config: self.recovery_config,
+            recovery_config: self.recovery_config.clone(),
 
             tx_cap_factor: self.tx_cap_factor,
 
             pmtud: None,
 
+            anti_replay: self.anti_replay.as_deref(),
+
+            ticket_key_ring: self.ticket_key_ring.as_deref(),
+
+            ticket_key_generation: &mut self.ticket_key_generation,
+
+            version: self.version,
+
             is_server: self.is_server,
         };
 
@@ -7381,6 +9019,10 @@ impl<F: BufFactory> Connection<F> {
             Ok(_) => (),
 
             Err(Error::Done) => {
+                // The server might have switched to a compatible version while
+                // processing the client's first flight.
+                let version = ex_data.version;
+
                 // Apply in-handshake configuration from callbacks before any
                 // packet has been sent.
                 if self.sent_count == 0 {
@@ -7434,6 +9076,19 @@ impl<F: BufFactory> Connection<F> {
                     self.parse_peer_transport_params(peer_params)?;
                 }
 
+                if version != self.version {
+                    let dcid = match &self
+                        .local_transport_params
+                        .original_destination_connection_id
+                    {
+                        Some(odcid) if !self.did_retry => odcid.clone(),
+
+                        _ => self.source_id().into_owned(),
+                    };
+
+                    self.switch_to_compatible_version(version, &dcid)?;
+                }
+
                 return Ok(());
             },
 
@@ -7470,6 +9125,15 @@ impl<F: BufFactory> Connection<F> {
             // 0-RTT packets anymore, so clear the buffer now.
             self.undecryptable_pkts.clear();
 
+            self.new_token_pending =
+                self.is_server && self.token_validator.is_some();
+
+            if let (Some(ring), Some(generation)) =
+                (&self.ticket_key_ring, self.ticket_key_generation)
+            {
+                ring.on_resumption(generation, self.handshake.is_resumed());
+            }
+
             trace!("{} connection established: proto={:?} cipher={:?} curve={:?} sigalg={:?} resumed={} {:?}",
                    &self.trace_id,
                    std::str::from_utf8(self.application_proto()),
@@ -7552,6 +9216,7 @@ impl<F: BufFactory> Connection<F> {
         let send_path = self.paths.get(send_pid)?;
         if (self.is_established() || self.is_in_early_data()) &&
             (self.should_send_handshake_done() ||
+                self.new_token_pending ||
                 self.almost_full ||
                 self.blocked_limit.is_some() ||
                 self.dgram_send_queue.has_pending() ||
@@ -7567,6 +9232,11 @@ impl<F: BufFactory> Connection<F> {
                 self.streams.has_stopped() ||
                 self.ids.has_new_scids() ||
                 self.ids.has_retire_dcids() ||
+                self.multipath.as_ref().is_some_and(|mp| {
+                    mp.ack_elicited() ||
+                        mp.has_pending_frames() ||
+                        mp.has_redundant_frames(send_pid)
+                }) ||
                 send_path
                     .pmtud
                     .as_ref()
@@ -7612,80 +9282,43 @@ impl<F: BufFactory> Connection<F> {
             frame::Frame::Ping { .. } => (),
 
             frame::Frame::ACK {
-                ranges, ack_delay, ..
+                ranges,
+                ack_delay,
+                ecn_counts,
+            } => {
+                self.on_ack_received(
+                    0,
+                    &ranges,
+                    ack_delay,
+                    ecn_counts.as_ref(),
+                    epoch,
+                    now,
+                )?;
+            },
+
+            frame::Frame::PathAck {
+                path_id,
+                ranges,
+                ack_delay,
+                ecn_counts,
             } => {
-                let ack_delay = ack_delay
-                    .checked_mul(2_u64.pow(
-                        self.peer_transport_params.ack_delay_exponent as u32,
-                    ))
-                    .ok_or(Error::InvalidFrame)?;
-
-                if epoch == packet::Epoch::Handshake ||
-                    (epoch == packet::Epoch::Application &&
-                        self.is_established())
+                if self
+                    .multipath
+                    .as_ref()
+                    .is_none_or(|mp| mp.path(path_id).is_none())
                 {
-                    self.peer_verified_initial_address = true;
+                    return Err(Error::InvalidState);
                 }
 
-                let handshake_status = self.handshake_status();
-
-                let is_app_limited = self.delivery_rate_check_if_app_limited();
-
-                let largest_acked = ranges.last().expect(
-                    "ACK frames should always have at least one ack range",
-                );
-
-                for (_, p) in self.paths.iter_mut() {
-                    if self.pkt_num_spaces[epoch]
-                        .largest_tx_pkt_num
-                        .is_some_and(|largest_sent| largest_sent < largest_acked)
-                    {
-                        // https://www.rfc-editor.org/rfc/rfc9000#section-13.1
-                        // An endpoint SHOULD treat receipt of an acknowledgment
-                        // for a packet it did not send as
-                        // a connection error of type PROTOCOL_VIOLATION
-                        return Err(Error::InvalidAckRange);
-                    }
-
-                    if is_app_limited {
-                        p.recovery.delivery_rate_update_app_limited(true);
-                    }
-
-                    let OnAckReceivedOutcome {
-                        lost_packets,
-                        lost_bytes,
-                        acked_bytes,
-                        spurious_losses,
-                    } = p.recovery.on_ack_received(
-                        &ranges,
-                        ack_delay,
-                        epoch,
-                        handshake_status,
-                        now,
-                        self.pkt_num_manager.skip_pn(),
-                        &self.trace_id,
-                    )?;
-
-                    let skip_pn = self.pkt_num_manager.skip_pn();
-                    let largest_acked =
-                        p.recovery.get_largest_acked_on_epoch(epoch);
-
-                    // Consider the skip_pn validated if the peer has sent an ack
-                    // for a larger pkt number.
-                    if let Some((largest_acked, skip_pn)) =
-                        largest_acked.zip(skip_pn)
-                    {
-                        if largest_acked > skip_pn {
-                            self.pkt_num_manager.set_skip_pn(None);
-                        }
-                    }
-
-                    self.lost_count += lost_packets;
-                    self.lost_bytes += lost_bytes as u64;
-                    self.acked_bytes += acked_bytes as u64;
-                    self.spurious_lost_count += spurious_losses;
-                }
-            },
+                self.on_ack_received(
+                    path_id,
+                    &ranges,
+                    ack_delay,
+                    ecn_counts.as_ref(),
+                    epoch,
+                    now,
+                )?;
+            },
 
             frame::Frame::ResetStream {
                 stream_id,
@@ -7824,11 +9457,17 @@ impl<F: BufFactory> Connection<F> {
 
             frame::Frame::CryptoHeader { .. } => unreachable!(),
 
-            // TODO: implement stateless retry
-            frame::Frame::NewToken { .. } =>
+            frame::Frame::NewToken { token } => {
                 if self.is_server {
                     return Err(Error::InvalidPacket);
-                },
+                }
+
+                if let (Some(cache), Some(server_name)) =
+                    (&self.token_cache, &self.server_name)
+                {
+                    cache.insert(server_name, &token);
+                }
+            },
 
             frame::Frame::Stream { stream_id, data } => {
                 // Peer can't send on our unidirectional streams.
@@ -8060,6 +9699,8 @@ impl<F: BufFactory> Connection<F> {
 
             frame::Frame::PathResponse { data } => {
                 self.paths.on_response_received(data)?;
+
+                self.on_preferred_path_validated();
             },
 
             frame::Frame::ConnectionClose {
@@ -8124,6 +9765,346 @@ impl<F: BufFactory> Connection<F> {
             },
 
             frame::Frame::DatagramHeader { .. } => unreachable!(),
+
+            frame::Frame::AckFrequency {
+                seq_num,
+                ack_eliciting_threshold,
+                request_max_ack_delay,
+                reordering_threshold,
+            } => {
+                self.ack_freq.on_frame_received(
+                    seq_num,
+                    ack_eliciting_threshold,
+                    request_max_ack_delay,
+                    reordering_threshold,
+                )?;
+            },
+
+            // The packet carrying the frame is acknowledged right away.
+            frame::Frame::ImmediateAck =>
+                if !self.ack_freq.recv_enabled() {
+                    return Err(Error::InvalidState);
+                },
+
+            frame::Frame::PathAbandon {
+                path_id,
+                error_code,
+            } => {
+                let mp = self.multipath.as_mut().ok_or(Error::InvalidState)?;
+
+                if path_id > mp.local_max_path_id() {
+                    return Err(Error::InvalidState);
+                }
+
+                trace!(
+                    "{} peer abandoned path ID {} with error code {}",
+                    self.trace_id,
+                    path_id,
+                    error_code
+                );
+
+                // Abandon the path on our side as well.
+                if mp.abandon(path_id, 0) {
+                    self.on_mp_path_abandoned(path_id, now)?;
+                }
+            },
+
+            frame::Frame::PathStatusBackup { path_id, seq_num } => {
+                self.multipath
+                    .as_mut()
+                    .ok_or(Error::InvalidState)?
+                    .on_peer_status(path_id, seq_num, PathStatus::Backup)?;
+            },
+
+            frame::Frame::PathStatusAvailable { path_id, seq_num } => {
+                self.multipath
+                    .as_mut()
+                    .ok_or(Error::InvalidState)?
+                    .on_peer_status(path_id, seq_num, PathStatus::Available)?;
+            },
+
+            frame::Frame::PathNewConnectionId {
+                path_id: 0,
+                seq_num,
+                retire_prior_to,
+                conn_id,
+                reset_token,
+            } => {
+                if self.multipath.is_none() {
+                    return Err(Error::InvalidState);
+                }
+
+                // The initial Path ID uses the connection-wide identifiers.
+                let frame = frame::Frame::NewConnectionId {
+                    seq_num,
+                    retire_prior_to,
+                    conn_id,
+                    reset_token,
+                };
+
+                self.process_frame(frame, hdr, recv_path_id, epoch, now)?;
+            },
+
+            frame::Frame::PathNewConnectionId {
+                path_id,
+                seq_num,
+                retire_prior_to,
+                conn_id,
+                reset_token,
+            } => {
+                let mp = self.multipath.as_mut().ok_or(Error::InvalidState)?;
+
+                let retired = mp.new_dcid(
+                    path_id,
+                    seq_num,
+                    retire_prior_to,
+                    conn_id.into(),
+                    u128::from_be_bytes(reset_token),
+                )?;
+
+                // Paths using a retired connection ID pick a new one once
+                // the packet is processed.
+                for (_, p) in self.paths.iter_mut() {
+                    if p.mp_path_id == path_id &&
+                        p.active_dcid_seq.is_some_and(|s| retired.contains(&s))
+                    {
+                        p.active_dcid_seq = None;
+                    }
+                }
+            },
+
+            frame::Frame::PathRetireConnectionId {
+                path_id: 0,
+                seq_num,
+            } => {
+                if self.multipath.is_none() {
+                    return Err(Error::InvalidState);
+                }
+
+                // The initial Path ID uses the connection-wide identifiers.
+                let frame = frame::Frame::RetireConnectionId { seq_num };
+
+                self.process_frame(frame, hdr, recv_path_id, epoch, now)?;
+            },
+
+            frame::Frame::PathRetireConnectionId { path_id, seq_num } => {
+                self.multipath
+                    .as_mut()
+                    .ok_or(Error::InvalidState)?
+                    .retire_scid(path_id, seq_num, &hdr.dcid)?;
+
+                for (_, p) in self.paths.iter_mut() {
+                    if p.mp_path_id == path_id &&
+                        p.active_scid_seq == Some(seq_num)
+                    {
+                        p.active_scid_seq = None;
+                    }
+                }
+            },
+
+            frame::Frame::MaxPathId { max } => {
+                self.multipath
+                    .as_mut()
+                    .ok_or(Error::InvalidState)?
+                    .set_peer_max_path_id(max)?;
+            },
+
+            frame::Frame::PathsBlocked { .. } |
+            frame::Frame::PathCidsBlocked { .. } =>
+                if self.multipath.is_none() {
+                    return Err(Error::InvalidState);
+                },
+        }
+
+        Ok(())
+    }
+
+    /// Stops using the paths of a multipath Path ID that was abandoned.
+    ///
+    /// The initial Path ID keeps being used for control frames even when
+    /// abandoned by the peer, as it is only removed from the paths that
+    /// schedulers can pick.
+    fn on_mp_path_abandoned(
+        &mut self, mp_path_id: u64, now: Instant,
+    ) -> Result<()> {
+        if mp_path_id == 0 {
+            return Ok(());
+        }
+
+        let pids: SmallVec<[usize; 1]> = self
+            .paths
+            .iter()
+            .filter(|(_, p)| p.mp_path_id == mp_path_id)
+            .map(|(pid, _)| pid)
+            .collect();
+
+        for pid in pids {
+            // Frames in flight on the path are declared lost, so that they are
+            // retransmitted on the remaining paths.
+            let (lost_packets, lost_bytes) = self
+                .paths
+                .get_mut(pid)?
+                .recovery
+                .on_path_change(packet::Epoch::Application, now, &self.trace_id);
+
+            self.lost_count += lost_packets;
+            self.lost_bytes += lost_bytes as u64;
+
+            self.paths.abandon_path(pid)?;
+
+            if let Some(mp) = self.multipath.as_mut() {
+                mp.drop_redundant_frames(pid);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Processes the ranges of an ACK or PATH_ACK frame for the packet number
+    /// space of the given multipath Path ID.
+    fn on_ack_received(
+        &mut self, mp_path_id: u64, ranges: &ranges::RangeSet, ack_delay: u64,
+        ecn_counts: Option<&frame::EcnCounts>, epoch: packet::Epoch,
+        now: Instant,
+    ) -> Result<()> {
+        let ack_delay = ack_delay
+            .checked_mul(
+                2_u64.pow(self.peer_transport_params.ack_delay_exponent as u32),
+            )
+            .ok_or(Error::InvalidFrame)?;
+
+        if epoch == packet::Epoch::Handshake ||
+            (epoch == packet::Epoch::Application && self.is_established())
+        {
+            self.peer_verified_initial_address = true;
+        }
+
+        let handshake_status = self.handshake_status();
+
+        let is_app_limited = self.delivery_rate_check_if_app_limited();
+
+        let largest_acked = ranges
+            .last()
+            .expect("ACK frames should always have at least one ack range");
+
+        let largest_tx_pkt_num = pkt_num_space_mut(
+            &mut self.pkt_num_spaces,
+            &mut self.multipath,
+            epoch,
+            mp_path_id,
+        )?
+        .largest_tx_pkt_num;
+
+        // Paths on which ECN-marked packets were newly acknowledged.
+        let mut ecn_pids: SmallVec<[usize; 1]> = SmallVec::new();
+        let mut ecn_marked_acked_total = 0;
+
+        // Only paths using the acknowledged packet number space are concerned.
+        let paths = self
+            .paths
+            .iter_mut()
+            .filter(|(_, p)| p.mp_path_id == mp_path_id);
+
+        for (pid, p) in paths {
+            if largest_tx_pkt_num
+                .is_some_and(|largest_sent| largest_sent < largest_acked)
+            {
+                // https://www.rfc-editor.org/rfc/rfc9000#section-13.1
+                // An endpoint SHOULD treat receipt of an acknowledgment
+                // for a packet it did not send as
+                // a connection error of type PROTOCOL_VIOLATION
+                return Err(Error::InvalidAckRange);
+            }
+
+            if is_app_limited {
+                p.recovery.delivery_rate_update_app_limited(true);
+            }
+
+            // Packet number skipping only applies to the initial Path ID.
+            let skip_pn = if mp_path_id == 0 {
+                self.pkt_num_manager.skip_pn()
+            } else {
+                None
+            };
+
+            let OnAckReceivedOutcome {
+                lost_packets,
+                lost_bytes,
+                acked_bytes,
+                spurious_losses,
+                ecn_marked_acked,
+            } = p.recovery.on_ack_received(
+                ranges,
+                ack_delay,
+                epoch,
+                handshake_status,
+                now,
+                skip_pn,
+                &self.trace_id,
+            )?;
+
+            let largest_acked = p.recovery.get_largest_acked_on_epoch(epoch);
+
+            // Consider the skip_pn validated if the peer has sent an ack
+            // for a larger pkt number.
+            if let Some((largest_acked, skip_pn)) = largest_acked.zip(skip_pn) {
+                if largest_acked > skip_pn {
+                    self.pkt_num_manager.set_skip_pn(None);
+                }
+            }
+
+            self.lost_count += lost_packets;
+            self.lost_bytes += lost_bytes as u64;
+            self.acked_bytes += acked_bytes as u64;
+            self.spurious_lost_count += spurious_losses;
+
+            if ecn_marked_acked > 0 {
+                ecn_pids.push(pid);
+                ecn_marked_acked_total += ecn_marked_acked;
+            }
+        }
+
+        // Validate the ECN counts reported by the peer, and react to the new
+        // CE marks once the path is known to be ECN-capable.
+        //
+        // See https://www.rfc-editor.org/rfc/rfc9000#section-13.4.2.1
+        let pkt_num_space = pkt_num_space_mut(
+            &mut self.pkt_num_spaces,
+            &mut self.multipath,
+            epoch,
+            mp_path_id,
+        )?;
+
+        let ce_count = ecn::validate_counts(
+            &mut pkt_num_space.peer_ecn_counts,
+            ecn_counts,
+            ecn_marked_acked_total,
+        );
+
+        for pid in ecn_pids {
+            let p = self.paths.get_mut(pid)?;
+
+            p.ecn.on_validation(ce_count.is_some());
+
+            if let Some(ce_count) = ce_count.filter(|_| p.ecn.is_capable()) {
+                p.recovery.on_ecn_ce(ce_count, now);
+            }
+        }
+
+        // The next key update can only happen once a packet sent with the
+        // current keys was acknowledged.
+        if epoch == packet::Epoch::Application &&
+            self.handshake_confirmed &&
+            !self.key_phase_acked
+        {
+            let key_phase_first_pn = match self.multipath.as_mut() {
+                Some(mp) if mp_path_id > 0 =>
+                    mp.space_mut(mp_path_id)?.key_phase_first_pn,
+
+                _ => self.key_phase_first_pn,
+            };
+
+            self.key_phase_acked = largest_acked >= key_phase_first_pn;
         }
 
         Ok(())
@@ -8255,6 +10236,19 @@ impl<F: BufFactory> Connection<F> {
             cwin_available > 0
     }
 
+    /// Sets the name of the server the client is connecting to, and picks the
+    /// NEW_TOKEN token stored for it, if any.
+    fn set_server_name(&mut self, server_name: &str) -> Result<()> {
+        self.handshake.set_host_name(server_name)?;
+
+        if let Some(cache) = &self.token_cache {
+            self.token = cache.take(server_name);
+            self.server_name = Some(server_name.to_string());
+        }
+
+        Ok(())
+    }
+
     fn set_initial_dcid(
         &mut self, cid: ConnectionId<'static>, reset_token: Option<u128>,
         path_id: usize,
@@ -8265,6 +10259,56 @@ impl<F: BufFactory> Connection<F> {
         Ok(())
     }
 
+    /// Selects the path that an incoming packet of the given multipath Path ID
+    /// belongs to, or creates a new one if no path uses that Path ID yet.
+    fn get_or_create_mp_recv_path_id(
+        &mut self, recv_pid: Option<usize>, mp_path_id: u64, dcid: &ConnectionId,
+        buf_len: usize, info: &RecvInfo,
+    ) -> Result<usize> {
+        let mp = self.multipath.as_ref().ok_or(Error::InvalidState)?;
+
+        let (_, in_scid_seq) = mp.find_scid(dcid).ok_or(Error::InvalidState)?;
+
+        if let Some(recv_pid) = recv_pid {
+            let recv_path = self.paths.get_mut(recv_pid)?;
+
+            // The 4-tuple is already used by another Path ID.
+            if recv_path.mp_path_id != mp_path_id {
+                return Err(Error::Done);
+            }
+
+            recv_path.active_scid_seq = Some(in_scid_seq);
+
+            return Ok(recv_pid);
+        }
+
+        // Paths other than the initial one can't migrate to another 4-tuple.
+        if self.paths.iter().any(|(_, p)| p.mp_path_id == mp_path_id) {
+            return Err(Error::Done);
+        }
+
+        let mut path = path::Path::new(
+            info.to,
+            info.from,
+            &self.recovery_config,
+            self.path_challenge_recv_max_queue_len,
+            false,
+            None,
+        );
+
+        path.ecn = ecn::EcnValidation::new(self.enable_ecn);
+
+        path.max_send_bytes = buf_len * self.max_amplification_factor;
+        path.active_scid_seq = Some(in_scid_seq);
+        path.active_dcid_seq = mp.lowest_dcid_seq(mp_path_id);
+        path.mp_path_id = mp_path_id;
+
+        // Automatically probes the new path.
+        path.request_validation();
+
+        self.paths.insert_path(path, self.is_server)
+    }
+
     /// Selects the path that the incoming packet belongs to, or creates a new
     /// one if no existing path matches.
     fn get_or_create_recv_path_id(
@@ -8280,6 +10324,11 @@ impl<F: BufFactory> Connection<F> {
             // If the path observes a change of SCID used, note it.
             let recv_path = self.paths.get_mut(recv_pid)?;
 
+            // The 4-tuple is already used by another multipath Path ID.
+            if recv_path.mp_path_id != 0 {
+                return Err(Error::Done);
+            }
+
             let cid_entry =
                 recv_path.active_scid_seq.and_then(|v| ids.get_scid(v).ok());
 
@@ -8360,6 +10409,8 @@ impl<F: BufFactory> Connection<F> {
             None,
         );
 
+        path.ecn = ecn::EcnValidation::new(self.enable_ecn);
+
         path.max_send_bytes = buf_len * self.max_amplification_factor;
         path.active_scid_seq = Some(in_scid_seq);
 
@@ -8397,6 +10448,10 @@ impl<F: BufFactory> Connection<F> {
             }
         }
 
+        if let Some(pid) = self.get_mp_send_path_id(from, to) {
+            return Ok(pid);
+        }
+
         if let Some((pid, p)) = self.paths.get_active_with_pid() {
             if from.is_some() && Some(p.local_addr()) != from {
                 return Err(Error::Done);
@@ -8412,6 +10467,64 @@ impl<F: BufFactory> Connection<F> {
         Err(Error::InvalidState)
     }
 
+    /// Selects the path on which the next packet must be sent when multipath
+    /// is enabled, among the ones matching the given addresses.
+    fn get_mp_send_path_id(
+        &self, from: Option<SocketAddr>, to: Option<SocketAddr>,
+    ) -> Option<usize> {
+        let mp = self.multipath.as_ref()?;
+
+        if !self.handshake_confirmed {
+            return None;
+        }
+
+        let usable = self
+            .paths
+            .iter()
+            .filter(|(_, p)| from.is_none() || Some(p.local_addr()) == from)
+            .filter(|(_, p)| to.is_none() || Some(p.peer_addr()) == to)
+            .filter(|(_, p)| p.usable() && mp.is_usable(p.mp_path_id));
+
+        let mut candidates: SmallVec<[(usize, PathCandidate); 4]> =
+            SmallVec::new();
+
+        for (pid, p) in usable {
+            // Copies of redundant frames and loss probes need to be sent on
+            // their own path, regardless of what the scheduler thinks.
+            if mp.has_redundant_frames(pid) ||
+                p.needs_ack_eliciting ||
+                p.recovery.loss_probes(packet::Epoch::Application) > 0
+            {
+                return Some(pid);
+            }
+
+            candidates.push((pid, PathCandidate {
+                local_addr: p.local_addr(),
+                peer_addr: p.peer_addr(),
+                path_id: p.mp_path_id,
+                status: mp
+                    .path(p.mp_path_id)
+                    .map(|s| s.peer_status)
+                    .unwrap_or_default(),
+                rtt: p.recovery.rtt(),
+                cwnd_available: p.recovery.cwnd_available(),
+            }));
+        }
+
+        let (first_pid, _) = candidates.first()?;
+
+        let paths: SmallVec<[PathCandidate; 4]> =
+            candidates.iter().map(|(_, c)| *c).collect();
+
+        let pid = self
+            .path_scheduler
+            .select(&paths)
+            .and_then(|i| candidates.get(i))
+            .map_or(*first_pid, |(pid, _)| *pid);
+
+        Some(pid)
+    }
+
     /// Sets the path with identifier 'path_id' to be active.
     fn set_active_path(&mut self, path_id: usize, now: Instant) -> Result<()> {
         if let Ok(old_active_path) = self.paths.get_active_mut() {
@@ -8430,6 +10543,76 @@ impl<F: BufFactory> Connection<F> {
         self.paths.set_active_path(path_id)
     }
 
+    /// Starts validating the path to the server's preferred address.
+    fn probe_preferred_address(&mut self) {
+        let peer_addr = match self.preferred_peer_addr {
+            Some(v) => v,
+
+            None => return,
+        };
+
+        // The preferred address is only used to move the single active path.
+        if self.multipath.is_some() {
+            self.preferred_peer_addr = None;
+            return;
+        }
+
+        let local_addr = match self.paths.get_active() {
+            Ok(p) => p.local_addr(),
+
+            Err(_) => return,
+        };
+
+        match self.probe_path(local_addr, peer_addr) {
+            Ok(_) => {
+                self.preferred_peer_addr = None;
+                self.preferred_path = Some((local_addr, peer_addr));
+            },
+
+            // Try again once the application provides spare connection IDs.
+            Err(Error::OutOfIdentifiers) => (),
+
+            Err(_) => self.preferred_peer_addr = None,
+        }
+    }
+
+    /// Migrates to the server's preferred address once its path is validated.
+    fn on_preferred_path_validated(&mut self) {
+        let (local_addr, peer_addr) = match self.preferred_path {
+            Some(v) => v,
+
+            None => return,
+        };
+
+        let validated = self
+            .paths
+            .path_id_from_addrs(&(local_addr, peer_addr))
+            .and_then(|pid| self.paths.get(pid).ok())
+            .map(|p| p.validated());
+
+        match validated {
+            Some(true) => (),
+
+            // Keep waiting for the validation to complete.
+            Some(false) => return,
+
+            // The path has been closed in the meantime.
+            None => {
+                self.preferred_path = None;
+                return;
+            },
+        }
+
+        self.preferred_path = None;
+
+        if self.migrate(local_addr, peer_addr).is_ok() {
+            self.paths
+                .notify_event(PathEvent::MigratedToPreferredAddress(
+                    local_addr, peer_addr,
+                ));
+        }
+    }
+
     /// Handles potential connection migration.
     fn on_peer_migrated(
         &mut self, new_pid: usize, disable_dcid_reuse: bool, now: Instant,
@@ -8486,6 +10669,8 @@ impl<F: BufFactory> Connection<F> {
             false,
             None,
         );
+
+        path.ecn = ecn::EcnValidation::new(self.enable_ecn);
         path.active_dcid_seq = Some(dcid_seq);
 
         let pid = self
@@ -8497,6 +10682,45 @@ impl<F: BufFactory> Connection<F> {
         Ok(pid)
     }
 
+    /// Selects a Path ID for a new path initiated by the client when multipath
+    /// is enabled.
+    fn create_mp_path_on_client(
+        &mut self, local_addr: SocketAddr, peer_addr: SocketAddr,
+    ) -> Result<usize> {
+        if self.is_server {
+            return Err(Error::InvalidState);
+        }
+
+        let used: Vec<u64> =
+            self.paths.iter().map(|(_, p)| p.mp_path_id).collect();
+
+        let mp = self.multipath.as_ref().ok_or(Error::InvalidState)?;
+
+        let mp_path_id =
+            mp.available_path_id(&used).ok_or(Error::OutOfIdentifiers)?;
+
+        let dcid_seq = mp
+            .lowest_dcid_seq(mp_path_id)
+            .ok_or(Error::OutOfIdentifiers)?;
+
+        let mut path = path::Path::new(
+            local_addr,
+            peer_addr,
+            &self.recovery_config,
+            self.path_challenge_recv_max_queue_len,
+            false,
+            None,
+        );
+
+        path.ecn = ecn::EcnValidation::new(self.enable_ecn);
+        path.mp_path_id = mp_path_id;
+        path.active_dcid_seq = Some(dcid_seq);
+
+        self.paths
+            .insert_path(path, false)
+            .map_err(|_| Error::OutOfIdentifiers)
+    }
+
     // Marks the connection as closed and does any related tidyup.
     fn mark_closed(&mut self) {
         #[cfg(feature = "qlog")]
@@ -8612,6 +10836,22 @@ impl<F: BufFactory> AsMut<boring::ssl::SslRef> for Connection<F> {
 /// This must only be used for errors preceding packet authentication. Failures
 /// happening after a packet has been authenticated should still cause the
 /// connection to be aborted.
+/// Returns the packet number space of the given epoch, or the one of the given
+/// multipath Path ID.
+fn pkt_num_space_mut<'a>(
+    pkt_num_spaces: &'a mut [packet::PktNumSpace; packet::Epoch::count()],
+    multipath: &'a mut Option<multipath::Multipath>, epoch: packet::Epoch,
+    mp_path_id: u64,
+) -> Result<&'a mut packet::PktNumSpace> {
+    if mp_path_id == 0 {
+        return Ok(&mut pkt_num_spaces[epoch]);
+    }
+
+    let mp = multipath.as_mut().ok_or(Error::InvalidState)?;
+
+    Ok(&mut mp.space_mut(mp_path_id)?.pkt_num_space)
+}
+
 fn drop_pkt_on_err(
     e: Error, recv_count: usize, is_server: bool, trace_id: &str,
 ) -> Error {
@@ -8704,9 +10944,23 @@ pub struct Stats {
     /// The total number of PATH_CHALLENGE frames that were received.
     pub path_challenge_rx_count: u64,
 
+    /// The number of key updates initiated by local.
+    pub key_update_count_local: u64,
+
+    /// The number of key updates initiated by remote.
+    pub key_update_count_remote: u64,
+
     /// Total duration during which this side of the connection was
     /// actively sending bytes or waiting for those bytes to be acked.
     pub bytes_in_flight_duration: Duration,
+
+    /// The generation of the session ticket key that decrypted the ticket
+    /// offered by the client, if any.
+    pub ticket_key_generation: Option<u64>,
+
+    /// The resumption statistics of each generation of the session ticket key
+    /// ring, if one is configured.
+    pub ticket_key_stats: Vec<TicketKeyStats>,
 }
 
 impl std::fmt::Debug for Stats {
@@ -8871,9 +11125,202 @@ pub struct TransportParams {
     pub retry_source_connection_id: Option<ConnectionId<'static>>,
     /// DATAGRAM frame extension parameter, if any.
     pub max_datagram_frame_size: Option<u64>,
+    /// Version Information parameter, if any.
+    pub version_information: Option<VersionInformation>,
+    /// The initial maximum Path ID for the multipath extension, if any.
+    pub initial_max_path_id: Option<u64>,
+    /// The minimum ACK delay in microseconds for the ACK Frequency extension,
+    /// if any.
+    pub min_ack_delay: Option<u64>,
     /// Unknown peer transport parameters and values, if any.
     pub unknown_params: Option<UnknownTransportParameters>,
-    // pub preferred_address: ...,
+    /// The server's preferred address, if any.
+    pub preferred_address: Option<PreferredAddress>,
+}
+
+/// An Encrypted Client Hello (ECH) key used by servers.
+///
+/// See [`Config::set_ech_keys()`].
+///
+/// [`Config::set_ech_keys()`]: struct.Config.html#method.set_ech_keys
+#[derive(Clone, PartialEq, Eq)]
+pub struct EchKey {
+    /// The serialized ECHConfig.
+    pub config: Vec<u8>,
+
+    /// The X25519 private key matching the public key of the ECHConfig.
+    pub private_key: Vec<u8>,
+
+    /// Whether the ECHConfig is sent to clients when ECH is rejected.
+    pub retry: bool,
+}
+
+impl EchKey {
+    /// Generates a new X25519 key and the ECHConfig advertising it.
+    ///
+    /// `public_name` is the name clients send in clear, and that the server
+    /// must have a certificate for to handle ECH rejections. `max_name_len`
+    /// is the length of the longest server name hidden behind the public
+    /// name, which is used by clients to pad the encrypted ClientHello.
+    ///
+    /// The generated key is a retry configuration.
+    pub fn generate(
+        config_id: u8, public_name: &str, max_name_len: usize,
+    ) -> Result<EchKey> {
+        tls::generate_ech_key(config_id, public_name, max_name_len)
+    }
+
+    /// Serializes the ECHConfigs of the given keys as an ECHConfigList, e.g.
+    /// to be published in an HTTPS DNS record or passed to
+    /// [`Config::set_ech_config_list()`].
+    ///
+    /// [`Config::set_ech_config_list()`]: struct.Config.html#method.set_ech_config_list
+    pub fn config_list(keys: &[EchKey]) -> Result<Vec<u8>> {
+        let len: usize = keys.iter().map(|k| k.config.len()).sum();
+        let len = u16::try_from(len).map_err(|_| Error::InvalidState)?;
+
+        let mut out = Vec::with_capacity(2 + usize::from(len));
+        out.extend_from_slice(&len.to_be_bytes());
+
+        for key in keys {
+            out.extend_from_slice(&key.config);
+        }
+
+        Ok(out)
+    }
+}
+
+impl std::fmt::Debug for EchKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("EchKey")
+            .field("config", &self.config)
+            .field("retry", &self.retry)
+            .finish()
+    }
+}
+
+/// QUIC Preferred Address transport parameter.
+///
+/// As defined in [RFC9000](https://www.rfc-editor.org/rfc/rfc9000.html#name-preferred-address).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreferredAddress {
+    /// The server's preferred IPv4 address, if any.
+    pub ipv4: Option<SocketAddrV4>,
+    /// The server's preferred IPv6 address, if any.
+    pub ipv6: Option<SocketAddrV6>,
+    /// The Connection ID to use on the path to the preferred address.
+    pub connection_id: ConnectionId<'static>,
+    /// The stateless reset token associated with the Connection ID.
+    pub stateless_reset_token: u128,
+}
+
+impl PreferredAddress {
+    fn decode(val: &mut octets::Octets) -> Result<PreferredAddress> {
+        let ipv4 = std::net::Ipv4Addr::from(val.get_u32()?);
+        let port_v4 = val.get_u16()?;
+
+        let ipv6 = std::net::Ipv6Addr::from(u128::from_be_bytes(
+            val.get_bytes(16)?
+                .to_vec()
+                .try_into()
+                .map_err(|_| Error::BufferTooShort)?,
+        ));
+        let port_v6 = val.get_u16()?;
+
+        let cid_len = val.get_u8()? as usize;
+
+        // The connection ID of the preferred address can't be empty.
+        if cid_len == 0 || cid_len > MAX_CONN_ID_LEN {
+            return Err(Error::InvalidTransportParam);
+        }
+
+        let connection_id = val.get_bytes(cid_len)?.to_vec().into();
+
+        let stateless_reset_token = u128::from_be_bytes(
+            val.get_bytes(16)?
+                .to_vec()
+                .try_into()
+                .map_err(|_| Error::BufferTooShort)?,
+        );
+
+        // Unspecified addresses are used when the server doesn't have a
+        // preferred address of that family.
+        Ok(PreferredAddress {
+            ipv4: (!ipv4.is_unspecified())
+                .then(|| SocketAddrV4::new(ipv4, port_v4)),
+            ipv6: (!ipv6.is_unspecified())
+                .then(|| SocketAddrV6::new(ipv6, port_v6, 0, 0)),
+            connection_id,
+            stateless_reset_token,
+        })
+    }
+
+    fn encode_len(&self) -> usize {
+        4 + 2 + 16 + 2 + 1 + self.connection_id.len() + 16
+    }
+
+    fn encode(&self, b: &mut octets::OctetsMut) -> Result<()> {
+        let ipv4 = self
+            .ipv4
+            .unwrap_or_else(|| SocketAddrV4::new([0; 4].into(), 0));
+        b.put_bytes(&ipv4.ip().octets())?;
+        b.put_u16(ipv4.port())?;
+
+        let ipv6 = self
+            .ipv6
+            .unwrap_or_else(|| SocketAddrV6::new([0; 16].into(), 0, 0, 0));
+        b.put_bytes(&ipv6.ip().octets())?;
+        b.put_u16(ipv6.port())?;
+
+        b.put_u8(self.connection_id.len() as u8)?;
+        b.put_bytes(&self.connection_id)?;
+
+        b.put_bytes(&self.stateless_reset_token.to_be_bytes())?;
+
+        Ok(())
+    }
+}
+
+/// QUIC Version Information transport parameter.
+///
+/// As defined in [RFC9368](https://www.rfc-editor.org/rfc/rfc9368.html#name-version-information).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VersionInformation {
+    /// The version used by the packets carrying the transport parameter.
+    pub chosen_version: u32,
+    /// The versions supported by the endpoint, in order of preference.
+    pub available_versions: Vec<u32>,
+}
+
+impl VersionInformation {
+    /// Creates a qlog event for the versions supported by both endpoints and
+    /// the one chosen for the connection.
+    #[cfg(feature = "qlog")]
+    fn to_qlog(
+        local: &TransportParams, peer: &TransportParams, chosen_version: u32,
+        is_server: bool,
+    ) -> EventData {
+        let versions = |tp: &TransportParams| {
+            tp.version_information.as_ref().map(|info| {
+                info.available_versions
+                    .iter()
+                    .map(|v| format!("{v:08x}"))
+                    .collect()
+            })
+        };
+
+        let (client, server) = if is_server {
+            (peer, local)
+        } else {
+            (local, peer)
+        };
+
+        EventData::VersionInformation(qlog::events::quic::VersionInformation {
+            server_versions: versions(server),
+            client_versions: versions(client),
+            chosen_version: Some(format!("{chosen_version:08x}")),
+        })
+    }
 }
 
 impl Default for TransportParams {
@@ -8896,7 +11343,11 @@ impl Default for TransportParams {
             initial_source_connection_id: None,
             retry_source_connection_id: None,
             max_datagram_frame_size: None,
+            version_information: None,
+            initial_max_path_id: None,
+            min_ack_delay: None,
             unknown_params: Default::default(),
+            preferred_address: None,
         }
     }
 }
@@ -9027,7 +11478,8 @@ impl TransportParams {
                         return Err(Error::InvalidTransportParam);
                     }
 
-                    // TODO: decode preferred_address
+                    tp.preferred_address =
+                        Some(PreferredAddress::decode(&mut val)?);
                 },
 
                 0x000e => {
@@ -9052,10 +11504,58 @@ impl TransportParams {
                     tp.retry_source_connection_id = Some(val.to_vec().into());
                 },
 
+                0x0011 => {
+                    let chosen_version = val.get_u32()?;
+
+                    // Versions can't be zero, as that's reserved for version
+                    // negotiation.
+                    if chosen_version == 0 || val.cap() % 4 != 0 {
+                        return Err(Error::InvalidTransportParam);
+                    }
+
+                    let mut available_versions =
+                        Vec::with_capacity(val.cap() / 4);
+
+                    while val.cap() > 0 {
+                        let v = val.get_u32()?;
+
+                        if v == 0 {
+                            return Err(Error::InvalidTransportParam);
+                        }
+
+                        available_versions.push(v);
+                    }
+
+                    tp.version_information = Some(VersionInformation {
+                        chosen_version,
+                        available_versions,
+                    });
+                },
+
                 0x0020 => {
                     tp.max_datagram_frame_size = Some(val.get_varint()?);
                 },
 
+                0x0f739bbc1b666d0c => {
+                    let max_path_id = val.get_varint()?;
+
+                    if max_path_id > u32::MAX as u64 {
+                        return Err(Error::InvalidTransportParam);
+                    }
+
+                    tp.initial_max_path_id = Some(max_path_id);
+                },
+
+                0xff04de1b => {
+                    let min_ack_delay = val.get_varint()?;
+
+                    if min_ack_delay >= 2_u64.pow(24) {
+                        return Err(Error::InvalidTransportParam);
+                    }
+
+                    tp.min_ack_delay = Some(min_ack_delay);
+                },
+
                 // Track unknown transport parameters specially.
                 unknown_tp_id => {
                     if let Some(unknown_params) = &mut tp.unknown_params {
@@ -9070,6 +11570,14 @@ impl TransportParams {
             }
         }
 
+        // The minimum ACK delay can't be larger than the maximum one.
+        if tp
+            .min_ack_delay
+            .is_some_and(|v| v > tp.max_ack_delay * 1000)
+        {
+            return Err(Error::InvalidTransportParam);
+        }
+
         Ok(tp)
     }
 
@@ -9195,7 +11703,12 @@ impl TransportParams {
             TransportParams::encode_param(&mut b, 0x000c, 0)?;
         }
 
-        // TODO: encode preferred_address
+        if is_server {
+            if let Some(pa) = &tp.preferred_address {
+                TransportParams::encode_param(&mut b, 0x000d, pa.encode_len())?;
+                pa.encode(&mut b)?;
+            }
+        }
 
         if tp.active_conn_id_limit != 2 {
             TransportParams::encode_param(
@@ -9218,6 +11731,19 @@ impl TransportParams {
             }
         }
 
+        if let Some(info) = &tp.version_information {
+            TransportParams::encode_param(
+                &mut b,
+                0x0011,
+                4 + info.available_versions.len() * 4,
+            )?;
+            b.put_u32(info.chosen_version)?;
+
+            for &v in &info.available_versions {
+                b.put_u32(v)?;
+            }
+        }
+
         if let Some(max_datagram_frame_size) = tp.max_datagram_frame_size {
             TransportParams::encode_param(
                 &mut b,
@@ -9227,6 +11753,24 @@ impl TransportParams {
             b.put_varint(max_datagram_frame_size)?;
         }
 
+        if let Some(max_path_id) = tp.initial_max_path_id {
+            TransportParams::encode_param(
+                &mut b,
+                0x0f739bbc1b666d0c,
+                octets::varint_len(max_path_id),
+            )?;
+            b.put_varint(max_path_id)?;
+        }
+
+        if let Some(min_ack_delay) = tp.min_ack_delay {
+            TransportParams::encode_param(
+                &mut b,
+                0xff04de1b,
+                octets::varint_len(min_ack_delay),
+            )?;
+            b.put_varint(min_ack_delay)?;
+        }
+
         let out_len = b.off();
 
         Ok(&mut out[..out_len])
@@ -9275,6 +11819,28 @@ impl TransportParams {
                 initial_max_streams_bidi: Some(self.initial_max_streams_bidi),
                 initial_max_streams_uni: Some(self.initial_max_streams_uni),
 
+                preferred_address: self.preferred_address.as_ref().map(|pa| {
+                    let ipv4 = pa
+                        .ipv4
+                        .unwrap_or_else(|| SocketAddrV4::new([0; 4].into(), 0));
+                    let ipv6 = pa.ipv6.unwrap_or_else(|| {
+                        SocketAddrV6::new([0; 16].into(), 0, 0, 0)
+                    });
+
+                    qlog::events::quic::PreferredAddress {
+                        ip_v4: ipv4.ip().to_string(),
+                        ip_v6: ipv6.ip().to_string(),
+                        port_v4: ipv4.port(),
+                        port_v6: ipv6.port(),
+                        connection_id: qlog::HexSlice::new(&pa.connection_id)
+                            .to_string(),
+                        stateless_reset_token: qlog::HexSlice::new(
+                            &pa.stateless_reset_token.to_be_bytes(),
+                        )
+                        .to_string(),
+                    }
+                }),
+
                 unknown_parameters: self
                     .unknown_params
                     .as_ref()
@@ -9303,34 +11869,73 @@ pub mod test_utils;
 #[cfg(test)]
 mod tests;
 
+pub use crate::anti_replay::AntiReplay;
+pub use crate::anti_replay::StrikeRegister;
+pub use crate::anti_replay::DEFAULT_ANTI_REPLAY_WINDOW;
+
+pub use crate::ecn::Ecn;
+
+pub use crate::endpoint::ConnectionHandle;
+pub use crate::endpoint::Endpoint;
+
 pub use crate::packet::ConnectionId;
 pub use crate::packet::Header;
+pub use crate::packet::StatelessResetSecret;
 pub use crate::packet::Type;
 
 pub use crate::path::PathEvent;
 pub use crate::path::PathStats;
 pub use crate::path::SocketAddrIter;
 
+pub use crate::multipath::MinRttScheduler;
+pub use crate::multipath::PathCandidate;
+pub use crate::multipath::PathScheduler;
+pub use crate::multipath::PathStatus;
+pub use crate::multipath::RedundantScheduler;
+
+pub use crate::recovery::AckedPacket;
+pub use crate::recovery::BandwidthSample;
 pub use crate::recovery::BbrBwLoReductionStrategy;
 pub use crate::recovery::BbrParams;
 pub use crate::recovery::CongestionControlAlgorithm;
+pub use crate::recovery::CongestionController;
+pub use crate::recovery::CongestionControllerFactory;
+pub use crate::recovery::CongestionControllerParams;
+pub use crate::recovery::CongestionEvent;
+pub use crate::recovery::LostPacket;
+pub use crate::recovery::PathCapacityRecord;
+pub use crate::recovery::RttEstimate;
 pub use crate::recovery::StartupExit;
 pub use crate::recovery::StartupExitReason;
 
 pub use crate::stream::StreamIter;
 
+pub use crate::ticket_keys::TicketKeyRing;
+pub use crate::ticket_keys::TicketKeyStats;
+pub use crate::ticket_keys::TICKET_KEY_LEN;
+
+pub use crate::token::HmacTokenValidator;
+pub use crate::token::TokenCache;
+pub use crate::token::TokenValidator;
+pub use crate::token::DEFAULT_NEW_TOKEN_LIFETIME;
+
 pub use crate::range_buf::BufFactory;
 pub use crate::range_buf::BufSplit;
 
+mod ack_frequency;
+mod anti_replay;
 mod cid;
 mod crypto;
 mod dgram;
+mod ecn;
+mod endpoint;
 #[cfg(feature = "ffi")]
 mod ffi;
 mod flowcontrol;
 mod frame;
 pub mod h3;
 mod minmax;
+mod multipath;
 mod packet;
 mod path;
 mod pmtud;
@@ -9339,4 +11944,6 @@ mod range_buf;
 mod ranges;
 mod recovery;
 mod stream;
+mod ticket_keys;
 mod tls;
+mod token;
@@ -0,0 +1,1063 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::VecDeque;
+
+use std::net::SocketAddr;
+
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::Error;
+use crate::Result;
+
+use crate::crypto;
+use crate::frame;
+use crate::packet;
+
+use crate::packet::ConnectionId;
+
+/// The maximum number of frames waiting to be duplicated on a single path by
+/// redundant scheduling.
+const MAX_REDUNDANT_FRAMES: usize = 1024;
+
+/// The status of a path in a multipath connection.
+///
+/// Endpoints advertise the status of a path with PATH_STATUS_AVAILABLE and
+/// PATH_STATUS_BACKUP frames, to let their peer know which paths they would
+/// rather receive data on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathStatus {
+    /// The path can be used to send data.
+    #[default]
+    Available,
+
+    /// The path should only be used to send data when no other path is
+    /// available.
+    Backup,
+}
+
+/// A path that a [`PathScheduler`] can pick to send the next packet on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PathCandidate {
+    /// The local address of the path.
+    pub local_addr: SocketAddr,
+
+    /// The peer address of the path.
+    pub peer_addr: SocketAddr,
+
+    /// The multipath Path ID.
+    pub path_id: u64,
+
+    /// The status of the path, as requested by the peer.
+    pub status: PathStatus,
+
+    /// The smoothed RTT estimate of the path.
+    pub rtt: Duration,
+
+    /// The number of bytes the congestion controller allows sending on the
+    /// path.
+    pub cwnd_available: usize,
+}
+
+/// A packet scheduler for multipath connections.
+///
+/// When multipath is negotiated, the scheduler decides which of the validated
+/// paths is used for the next packet sent with [`send()`]. Packets sent with
+/// [`send_on_path()`] on a specific path bypass the scheduler.
+///
+/// [`send()`]: struct.Connection.html#method.send
+/// [`send_on_path()`]: struct.Connection.html#method.send_on_path
+pub trait PathScheduler: Send + Sync {
+    /// Selects the path on which the next packet is sent, and returns its
+    /// index in `paths`.
+    ///
+    /// `paths` is never empty. Returning `None` or an out of bounds index
+    /// makes quiche use the first path.
+    fn select(&self, paths: &[PathCandidate]) -> Option<usize>;
+
+    /// Returns whether STREAM and DATAGRAM frames sent on a path should also
+    /// be sent on every other usable path.
+    ///
+    /// The default is `false`.
+    fn redundant(&self) -> bool {
+        false
+    }
+}
+
+/// Sends each packet on the available path with the lowest smoothed RTT,
+/// among those that still have room in their congestion window.
+///
+/// Backup paths are only used when no available path is left.
+///
+/// This is the default scheduler.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MinRttScheduler;
+
+impl PathScheduler for MinRttScheduler {
+    fn select(&self, paths: &[PathCandidate]) -> Option<usize> {
+        select_min_rtt(paths)
+    }
+}
+
+/// Schedules packets like [`MinRttScheduler`], but also sends a copy of all
+/// STREAM and DATAGRAM frames on every other usable path.
+///
+/// This trades bandwidth for latency and resilience to the loss of a path.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RedundantScheduler;
+
+impl PathScheduler for RedundantScheduler {
+    fn select(&self, paths: &[PathCandidate]) -> Option<usize> {
+        select_min_rtt(paths)
+    }
+
+    fn redundant(&self) -> bool {
+        true
+    }
+}
+
+fn select_min_rtt(paths: &[PathCandidate]) -> Option<usize> {
+    let has_available = paths.iter().any(|p| p.status == PathStatus::Available);
+
+    paths
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| !has_available || p.status == PathStatus::Available)
+        .min_by_key(|(_, p)| (p.cwnd_available == 0, p.rtt))
+        .map(|(i, _)| i)
+}
+
+/// A connection ID bound to a Path ID.
+struct PathConnectionId {
+    cid: ConnectionId<'static>,
+
+    reset_token: u128,
+}
+
+/// Per-Path ID state that only exists for Path IDs other than 0, as the
+/// initial path uses the connection-wide state.
+pub struct PathSpace {
+    /// The application data packet number space of the path.
+    pub pkt_num_space: packet::PktNumSpace,
+
+    /// The next packet number to send on the path.
+    pub next_pkt_num: u64,
+
+    /// The first packet number sent with the current 1-RTT keys.
+    pub key_phase_first_pn: u64,
+
+    /// Packet protection keys for the current key phase, derived lazily.
+    open: Option<crypto::Open>,
+    seal: Option<crypto::Seal>,
+
+    /// Our connection IDs for the path, by sequence number.
+    scids: BTreeMap<u64, PathConnectionId>,
+
+    /// The peer's connection IDs for the path, by sequence number.
+    dcids: BTreeMap<u64, PathConnectionId>,
+
+    /// The next sequence number for our connection IDs.
+    next_scid_seq: u64,
+
+    /// The largest "Retire Prior To" value received from the peer.
+    largest_retire_prior_to: u64,
+
+    /// Our connection IDs that need to be advertised.
+    advertise_scid_seqs: BTreeSet<u64>,
+
+    /// The peer's connection IDs that need to be retired.
+    retire_dcid_seqs: BTreeSet<u64>,
+}
+
+impl PathSpace {
+    fn new() -> Self {
+        PathSpace {
+            pkt_num_space: packet::PktNumSpace::new(),
+            next_pkt_num: 0,
+            key_phase_first_pn: 0,
+            open: None,
+            seal: None,
+            scids: BTreeMap::new(),
+            dcids: BTreeMap::new(),
+            next_scid_seq: 0,
+            largest_retire_prior_to: 0,
+            advertise_scid_seqs: BTreeSet::new(),
+            retire_dcid_seqs: BTreeSet::new(),
+        }
+    }
+
+    /// Returns the key used to open packets received on the path, derived
+    /// from the connection's current `aead`.
+    pub fn open(
+        &mut self, path_id: u64, aead: &crypto::Open,
+    ) -> Result<&crypto::Open> {
+        if self.open.is_none() {
+            self.open = Some(aead.for_path(path_id as u32)?);
+        }
+
+        self.open.as_ref().ok_or(Error::InvalidState)
+    }
+
+    /// Returns the key used to seal packets sent on the path, derived from
+    /// the connection's current `aead`.
+    pub fn seal(
+        &mut self, path_id: u64, aead: &crypto::Seal,
+    ) -> Result<&crypto::Seal> {
+        if self.seal.is_none() {
+            self.seal = Some(aead.for_path(path_id as u32, self.next_pkt_num)?);
+        }
+
+        self.seal.as_ref().ok_or(Error::InvalidState)
+    }
+}
+
+/// The state of a Path ID.
+pub struct PathIdState {
+    /// Our status of the path.
+    pub status: PathStatus,
+
+    /// The sequence number of the next PATH_STATUS frame to send.
+    status_seq: u64,
+
+    /// Whether our status needs to be advertised.
+    status_pending: bool,
+
+    /// The peer's status of the path.
+    pub peer_status: PathStatus,
+
+    /// The sequence number of the last PATH_STATUS frame received.
+    peer_status_seq: Option<u64>,
+
+    /// The error code of the PATH_ABANDON frame to send, if any.
+    abandon_pending: Option<u64>,
+
+    /// Whether we sent a PATH_ABANDON frame.
+    abandon_sent: bool,
+
+    /// Whether the path was abandoned by either endpoint.
+    pub abandoned: bool,
+
+    /// Path-specific state, `None` for Path ID 0.
+    pub space: Option<PathSpace>,
+}
+
+impl PathIdState {
+    fn new(path_id: u64) -> Self {
+        PathIdState {
+            status: PathStatus::Available,
+            status_seq: 0,
+            status_pending: false,
+            peer_status: PathStatus::Available,
+            peer_status_seq: None,
+            abandon_pending: None,
+            abandon_sent: false,
+            abandoned: false,
+            space: (path_id > 0).then(PathSpace::new),
+        }
+    }
+}
+
+/// The multipath state of a connection, once the extension is negotiated.
+pub struct Multipath {
+    /// The maximum Path ID we allow.
+    local_max_path_id: u64,
+
+    /// Whether a MAX_PATH_ID frame needs to be sent.
+    max_path_id_pending: bool,
+
+    /// The maximum Path ID the peer allows.
+    peer_max_path_id: u64,
+
+    /// The maximum number of active connection IDs per path the peer can
+    /// give us.
+    local_cid_limit: usize,
+
+    /// The maximum number of active connection IDs per path we can give the
+    /// peer.
+    peer_cid_limit: usize,
+
+    /// Per-Path ID state.
+    paths: BTreeMap<u64, PathIdState>,
+
+    /// Our connection IDs retired by the peer, not yet reported to the
+    /// application.
+    retired_scids: VecDeque<ConnectionId<'static>>,
+
+    /// Copies of STREAM and DATAGRAM frames waiting to be sent, keyed by the
+    /// internal identifier of the path they need to be sent on.
+    redundant_frames: BTreeMap<usize, VecDeque<frame::Frame>>,
+}
+
+impl Multipath {
+    pub fn new(
+        local_max_path_id: u64, peer_max_path_id: u64, local_cid_limit: usize,
+        peer_cid_limit: usize,
+    ) -> Self {
+        let mut paths = BTreeMap::new();
+        paths.insert(0, PathIdState::new(0));
+
+        Multipath {
+            local_max_path_id,
+            max_path_id_pending: false,
+            peer_max_path_id,
+            local_cid_limit,
+            peer_cid_limit,
+            paths,
+            retired_scids: VecDeque::new(),
+            redundant_frames: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the largest Path ID that both endpoints allow.
+    pub fn max_path_id(&self) -> u64 {
+        self.local_max_path_id.min(self.peer_max_path_id)
+    }
+
+    /// Returns the largest Path ID the peer is allowed to use.
+    pub fn local_max_path_id(&self) -> u64 {
+        self.local_max_path_id
+    }
+
+    /// Returns the state of the given Path ID, if it is known.
+    pub fn path(&self, path_id: u64) -> Option<&PathIdState> {
+        self.paths.get(&path_id)
+    }
+
+    /// Returns the path-specific state of the given Path ID.
+    pub fn space_mut(&mut self, path_id: u64) -> Result<&mut PathSpace> {
+        self.paths
+            .get_mut(&path_id)
+            .and_then(|p| p.space.as_mut())
+            .ok_or(Error::InvalidState)
+    }
+
+    /// Returns an iterator over the path-specific state of all Path IDs.
+    pub fn spaces_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (u64, &mut PathSpace)> + '_ {
+        self.paths
+            .iter_mut()
+            .filter_map(|(id, p)| p.space.as_mut().map(|s| (*id, s)))
+    }
+
+    /// Returns when the earliest delayed ACK of the Path IDs needs to be sent.
+    pub fn ack_timer(&self) -> Option<Instant> {
+        self.paths
+            .values()
+            .filter_map(|p| p.space.as_ref()?.pkt_num_space.ack_timer)
+            .min()
+    }
+
+    /// Returns whether the given Path ID is usable, i.e. it is known and not
+    /// abandoned.
+    pub fn is_usable(&self, path_id: u64) -> bool {
+        self.paths.get(&path_id).is_some_and(|p| !p.abandoned)
+    }
+
+    /// Returns the state of the given Path ID, creating it if needed.
+    fn get_or_create(&mut self, path_id: u64) -> &mut PathIdState {
+        self.paths
+            .entry(path_id)
+            .or_insert_with(|| PathIdState::new(path_id))
+    }
+
+    /// Updates the maximum Path ID the peer allows.
+    pub fn set_peer_max_path_id(&mut self, max: u64) -> Result<()> {
+        if max > u32::MAX as u64 {
+            return Err(Error::InvalidFrame);
+        }
+
+        self.peer_max_path_id = self.peer_max_path_id.max(max);
+
+        Ok(())
+    }
+
+    /// Adds one of our connection IDs for the given Path ID, and returns its
+    /// sequence number.
+    pub fn new_scid(
+        &mut self, path_id: u64, cid: ConnectionId<'static>, reset_token: u128,
+    ) -> Result<u64> {
+        if path_id == 0 || path_id > self.peer_max_path_id {
+            return Err(Error::InvalidState);
+        }
+
+        let peer_cid_limit = self.peer_cid_limit;
+
+        let path = self.get_or_create(path_id);
+
+        if path.abandoned {
+            return Err(Error::InvalidState);
+        }
+
+        let space = path.space.as_mut().ok_or(Error::InvalidState)?;
+
+        if let Some((&seq, e)) = space.scids.iter().find(|(_, e)| e.cid == cid) {
+            if e.reset_token != reset_token {
+                return Err(Error::InvalidState);
+            }
+
+            return Ok(seq);
+        }
+
+        if space.scids.len() >= peer_cid_limit {
+            return Err(Error::IdLimit);
+        }
+
+        let seq = space.next_scid_seq;
+
+        space
+            .scids
+            .insert(seq, PathConnectionId { cid, reset_token });
+        space.advertise_scid_seqs.insert(seq);
+        space.next_scid_seq += 1;
+
+        Ok(seq)
+    }
+
+    /// Returns the Path ID and sequence number of our connection ID `cid`.
+    pub fn find_scid(&self, cid: &ConnectionId) -> Option<(u64, u64)> {
+        self.paths.iter().find_map(|(&id, p)| {
+            let space = p.space.as_ref()?;
+
+            space
+                .scids
+                .iter()
+                .find(|(_, e)| &e.cid == cid)
+                .map(|(&seq, _)| (id, seq))
+        })
+    }
+
+    /// Returns the peer's connection ID with the given Path ID and sequence
+    /// number.
+    pub fn get_dcid(&self, path_id: u64, seq: u64) -> Result<&ConnectionId<'_>> {
+        self.paths
+            .get(&path_id)
+            .and_then(|p| p.space.as_ref())
+            .and_then(|s| s.dcids.get(&seq))
+            .map(|e| &e.cid)
+            .ok_or(Error::InvalidState)
+    }
+
+    /// Returns the lowest sequence number of the peer's connection IDs for the
+    /// given Path ID.
+    pub fn lowest_dcid_seq(&self, path_id: u64) -> Option<u64> {
+        let space = self.paths.get(&path_id)?.space.as_ref()?;
+
+        space.dcids.keys().next().copied()
+    }
+
+    /// Returns the lowest Path ID, other than 0, that is not in `used`, and for
+    /// which connection IDs were exchanged in both directions.
+    pub fn available_path_id(&self, used: &[u64]) -> Option<u64> {
+        self.paths
+            .iter()
+            .filter(|(id, p)| !p.abandoned && !used.contains(id))
+            .filter_map(|(&id, p)| Some((id, p.space.as_ref()?)))
+            .find(|(_, s)| !s.scids.is_empty() && !s.dcids.is_empty())
+            .map(|(id, _)| id)
+    }
+
+    /// Processes a PATH_NEW_CONNECTION_ID frame.
+    ///
+    /// Returns the sequence numbers of the connection IDs of the path that
+    /// were retired as a result.
+    pub fn new_dcid(
+        &mut self, path_id: u64, seq: u64, retire_prior_to: u64,
+        cid: ConnectionId<'static>, reset_token: u128,
+    ) -> Result<Vec<u64>> {
+        if path_id > self.local_max_path_id {
+            return Err(Error::InvalidState);
+        }
+
+        if retire_prior_to > seq {
+            return Err(Error::InvalidFrame);
+        }
+
+        // Connection IDs for Path ID 0 are handled separately.
+        if path_id == 0 {
+            return Err(Error::InvalidState);
+        }
+
+        let local_cid_limit = self.local_cid_limit;
+
+        let path = self.get_or_create(path_id);
+
+        // The path is gone, there's no point in tracking new connection IDs.
+        if path.abandoned {
+            return Ok(Vec::new());
+        }
+
+        let space = path.space.as_mut().ok_or(Error::InvalidState)?;
+
+        if let Some(e) = space.dcids.get(&seq) {
+            if e.cid != cid || e.reset_token != reset_token {
+                return Err(Error::InvalidState);
+            }
+
+            return Ok(Vec::new());
+        }
+
+        let mut retired = Vec::new();
+
+        if seq < space.largest_retire_prior_to {
+            // The connection ID was already retired, so retire it again.
+            space.retire_dcid_seqs.insert(seq);
+            return Ok(retired);
+        }
+
+        space
+            .dcids
+            .insert(seq, PathConnectionId { cid, reset_token });
+
+        if retire_prior_to > space.largest_retire_prior_to {
+            space.largest_retire_prior_to = retire_prior_to;
+
+            let to_retire: Vec<u64> = space
+                .dcids
+                .range(..retire_prior_to)
+                .map(|(&s, _)| s)
+                .collect();
+
+            for s in to_retire {
+                space.dcids.remove(&s);
+                space.retire_dcid_seqs.insert(s);
+
+                retired.push(s);
+            }
+        }
+
+        if space.dcids.len() > local_cid_limit {
+            return Err(Error::IdLimit);
+        }
+
+        Ok(retired)
+    }
+
+    /// Processes a PATH_RETIRE_CONNECTION_ID frame.
+    ///
+    /// `pkt_dcid` is the destination connection ID of the packet carrying the
+    /// frame, which can't be the one being retired.
+    pub fn retire_scid(
+        &mut self, path_id: u64, seq: u64, pkt_dcid: &ConnectionId,
+    ) -> Result<()> {
+        let space = match self.paths.get_mut(&path_id) {
+            Some(p) => p.space.as_mut().ok_or(Error::InvalidState)?,
+
+            None => return Err(Error::InvalidState),
+        };
+
+        if seq >= space.next_scid_seq {
+            return Err(Error::InvalidState);
+        }
+
+        if let Some(e) = space.scids.get(&seq) {
+            if &e.cid == pkt_dcid {
+                return Err(Error::InvalidState);
+            }
+        }
+
+        if let Some(e) = space.scids.remove(&seq) {
+            space.advertise_scid_seqs.remove(&seq);
+
+            self.retired_scids.push_back(e.cid);
+        }
+
+        Ok(())
+    }
+
+    /// Returns one of our connection IDs retired by the peer.
+    pub fn pop_retired_scid(&mut self) -> Option<ConnectionId<'static>> {
+        self.retired_scids.pop_front()
+    }
+
+    /// Returns the number of our connection IDs retired by the peer.
+    pub fn retired_scids(&self) -> usize {
+        self.retired_scids.len()
+    }
+
+    /// Sets our status of the given Path ID, to be advertised to the peer.
+    pub fn set_status(&mut self, path_id: u64, status: PathStatus) -> Result<()> {
+        let path = self.paths.get_mut(&path_id).ok_or(Error::InvalidState)?;
+
+        if path.abandoned {
+            return Err(Error::InvalidState);
+        }
+
+        if path.status != status {
+            path.status = status;
+            path.status_pending = true;
+        }
+
+        Ok(())
+    }
+
+    /// Processes a PATH_STATUS_AVAILABLE or PATH_STATUS_BACKUP frame.
+    pub fn on_peer_status(
+        &mut self, path_id: u64, seq: u64, status: PathStatus,
+    ) -> Result<()> {
+        if path_id > self.local_max_path_id {
+            return Err(Error::InvalidState);
+        }
+
+        let path = self.get_or_create(path_id);
+
+        // Ignore frames that were reordered.
+        if path.peer_status_seq.is_some_and(|s| s >= seq) {
+            return Ok(());
+        }
+
+        path.peer_status = status;
+        path.peer_status_seq = Some(seq);
+
+        Ok(())
+    }
+
+    /// Abandons the given Path ID, either because the application asked for
+    /// it, or because the peer did.
+    ///
+    /// Returns whether the path wasn't already abandoned.
+    pub fn abandon(&mut self, path_id: u64, error_code: u64) -> bool {
+        let path = self.get_or_create(path_id);
+
+        if !path.abandon_sent {
+            path.abandon_pending = Some(error_code);
+        }
+
+        if path.abandoned {
+            return false;
+        }
+
+        path.abandoned = true;
+        path.status_pending = false;
+
+        // Abandoning a path implicitly retires all of its connection IDs.
+        if let Some(space) = path.space.as_mut() {
+            space.advertise_scid_seqs.clear();
+            space.retire_dcid_seqs.clear();
+            space.dcids.clear();
+
+            let scids = std::mem::take(&mut space.scids);
+            self.retired_scids
+                .extend(scids.into_values().map(|e| e.cid));
+        }
+
+        // Let the peer open another path in place of this one.
+        if self.local_max_path_id < u32::MAX as u64 {
+            self.local_max_path_id += 1;
+            self.max_path_id_pending = true;
+        }
+
+        true
+    }
+
+    /// Returns whether there are frames that need to be sent.
+    pub fn has_pending_frames(&self) -> bool {
+        self.max_path_id_pending ||
+            self.paths.values().any(|p| {
+                p.status_pending ||
+                    p.abandon_pending.is_some() ||
+                    p.space.as_ref().is_some_and(|s| {
+                        !s.advertise_scid_seqs.is_empty() ||
+                            !s.retire_dcid_seqs.is_empty()
+                    })
+            })
+    }
+
+    /// Returns whether any path-specific packet number space needs to be
+    /// acknowledged.
+    pub fn ack_elicited(&self) -> bool {
+        self.paths
+            .values()
+            .any(|p| p.space.as_ref().is_some_and(|s| s.pkt_num_space.ready()))
+    }
+
+    /// Returns the next control frame to send, if any.
+    ///
+    /// The frame is only considered sent once [`on_frame_sent()`] is called.
+    ///
+    /// [`on_frame_sent()`]: Multipath::on_frame_sent
+    pub fn next_frame(&self) -> Option<frame::Frame> {
+        if self.max_path_id_pending {
+            return Some(frame::Frame::MaxPathId {
+                max: self.local_max_path_id,
+            });
+        }
+
+        for (&path_id, p) in &self.paths {
+            if let Some(error_code) = p.abandon_pending {
+                return Some(frame::Frame::PathAbandon {
+                    path_id,
+                    error_code,
+                });
+            }
+
+            if p.status_pending {
+                let seq_num = p.status_seq;
+
+                return Some(match p.status {
+                    PathStatus::Available =>
+                        frame::Frame::PathStatusAvailable { path_id, seq_num },
+
+                    PathStatus::Backup =>
+                        frame::Frame::PathStatusBackup { path_id, seq_num },
+                });
+            }
+
+            let space = match p.space.as_ref() {
+                Some(v) => v,
+
+                None => continue,
+            };
+
+            if let Some(&seq_num) = space.advertise_scid_seqs.first() {
+                let e = space.scids.get(&seq_num)?;
+
+                return Some(frame::Frame::PathNewConnectionId {
+                    path_id,
+                    seq_num,
+                    retire_prior_to: 0,
+                    conn_id: e.cid.to_vec(),
+                    reset_token: e.reset_token.to_be_bytes(),
+                });
+            }
+
+            if let Some(&seq_num) = space.retire_dcid_seqs.first() {
+                return Some(frame::Frame::PathRetireConnectionId {
+                    path_id,
+                    seq_num,
+                });
+            }
+        }
+
+        None
+    }
+
+    /// Records that a frame returned by [`next_frame()`] was sent, or that a
+    /// sent one was lost if `lost` is `true`.
+    ///
+    /// [`next_frame()`]: Multipath::next_frame
+    pub fn on_frame_sent(&mut self, frame: &frame::Frame, lost: bool) {
+        match *frame {
+            frame::Frame::MaxPathId { max } =>
+                if lost {
+                    self.max_path_id_pending |= max == self.local_max_path_id;
+                } else {
+                    self.max_path_id_pending = false;
+                },
+
+            frame::Frame::PathAbandon {
+                path_id,
+                error_code,
+            } =>
+                if let Some(p) = self.paths.get_mut(&path_id) {
+                    if lost {
+                        p.abandon_pending = Some(error_code);
+                    } else {
+                        p.abandon_pending = None;
+                        p.abandon_sent = true;
+                    }
+                },
+
+            frame::Frame::PathStatusAvailable { path_id, seq_num } |
+            frame::Frame::PathStatusBackup { path_id, seq_num } =>
+                if let Some(p) = self.paths.get_mut(&path_id) {
+                    if lost {
+                        // Only the latest status is worth retransmitting.
+                        p.status_pending |=
+                            !p.abandoned && seq_num + 1 == p.status_seq;
+                    } else {
+                        p.status_pending = false;
+                        p.status_seq = seq_num + 1;
+                    }
+                },
+
+            frame::Frame::PathNewConnectionId {
+                path_id, seq_num, ..
+            } =>
+                if let Some(s) =
+                    self.paths.get_mut(&path_id).and_then(|p| p.space.as_mut())
+                {
+                    if !lost {
+                        s.advertise_scid_seqs.remove(&seq_num);
+                    } else if s.scids.contains_key(&seq_num) {
+                        s.advertise_scid_seqs.insert(seq_num);
+                    }
+                },
+
+            frame::Frame::PathRetireConnectionId { path_id, seq_num } =>
+                if let Some(p) = self.paths.get_mut(&path_id) {
+                    if let Some(s) = p.space.as_mut() {
+                        if !lost {
+                            s.retire_dcid_seqs.remove(&seq_num);
+                        } else if !p.abandoned {
+                            s.retire_dcid_seqs.insert(seq_num);
+                        }
+                    }
+                },
+
+            _ => (),
+        }
+    }
+
+    /// Drops the cached packet protection keys of all paths, after a key
+    /// update.
+    pub fn on_key_update(&mut self) {
+        for (_, space) in self.spaces_mut() {
+            space.key_phase_first_pn = space.next_pkt_num;
+            space.open = None;
+            space.seal = None;
+        }
+    }
+
+    /// Queues a copy of a frame to be sent on the path with the given internal
+    /// identifier.
+    pub fn push_redundant_frame(&mut self, pid: usize, frame: frame::Frame) {
+        let queue = self.redundant_frames.entry(pid).or_default();
+
+        if queue.len() < MAX_REDUNDANT_FRAMES {
+            queue.push_back(frame);
+        }
+    }
+
+    /// Returns the frames waiting to be duplicated on the path with the given
+    /// internal identifier.
+    pub fn redundant_frames(
+        &mut self, pid: usize,
+    ) -> Option<&mut VecDeque<frame::Frame>> {
+        self.redundant_frames.get_mut(&pid)
+    }
+
+    /// Drops the frames waiting to be duplicated on the path with the given
+    /// internal identifier.
+    pub fn drop_redundant_frames(&mut self, pid: usize) {
+        self.redundant_frames.remove(&pid);
+    }
+
+    /// Returns whether there are frames waiting to be duplicated on the path
+    /// with the given internal identifier.
+    pub fn has_redundant_frames(&self, pid: usize) -> bool {
+        self.redundant_frames
+            .get(&pid)
+            .is_some_and(|q| !q.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(
+        path_id: u64, status: PathStatus, rtt_ms: u64, cwnd_available: usize,
+    ) -> PathCandidate {
+        PathCandidate {
+            local_addr: "127.0.0.1:1234".parse().unwrap(),
+            peer_addr: "127.0.0.1:4321".parse().unwrap(),
+            path_id,
+            status,
+            rtt: Duration::from_millis(rtt_ms),
+            cwnd_available,
+        }
+    }
+
+    #[test]
+    fn min_rtt_scheduler() {
+        let scheduler = MinRttScheduler;
+
+        let paths = [
+            candidate(0, PathStatus::Available, 50, 10_000),
+            candidate(1, PathStatus::Available, 20, 10_000),
+            candidate(2, PathStatus::Backup, 10, 10_000),
+        ];
+
+        // The backup path is ignored while there is an available one.
+        assert_eq!(scheduler.select(&paths), Some(1));
+        assert!(!scheduler.redundant());
+
+        // Paths without congestion window available are only used as a last
+        // resort.
+        let paths = [
+            candidate(0, PathStatus::Available, 50, 10_000),
+            candidate(1, PathStatus::Available, 20, 0),
+        ];
+
+        assert_eq!(scheduler.select(&paths), Some(0));
+
+        // Only backup paths are left.
+        let paths = [
+            candidate(1, PathStatus::Backup, 30, 10_000),
+            candidate(2, PathStatus::Backup, 10, 10_000),
+        ];
+
+        assert_eq!(scheduler.select(&paths), Some(1));
+    }
+
+    #[test]
+    fn redundant_scheduler() {
+        let scheduler = RedundantScheduler;
+
+        let paths = [
+            candidate(0, PathStatus::Available, 50, 10_000),
+            candidate(1, PathStatus::Available, 20, 10_000),
+        ];
+
+        assert_eq!(scheduler.select(&paths), Some(1));
+        assert!(scheduler.redundant());
+    }
+
+    #[test]
+    fn path_cids() {
+        let mut mp = Multipath::new(2, 2, 2, 2);
+
+        let cid = |v: u8| ConnectionId::from_vec(vec![v; 8]);
+
+        // Path ID 0 uses the connection-wide connection IDs.
+        assert_eq!(mp.new_scid(0, cid(0), 0), Err(Error::InvalidState));
+
+        // Path IDs above the peer's limit can't be used.
+        assert_eq!(mp.new_scid(3, cid(3), 3), Err(Error::InvalidState));
+
+        assert_eq!(mp.new_scid(1, cid(1), 1), Ok(0));
+        assert_eq!(mp.new_scid(1, cid(1), 1), Ok(0));
+        assert_eq!(mp.new_scid(1, cid(2), 2), Ok(1));
+        assert_eq!(mp.new_scid(1, cid(3), 3), Err(Error::IdLimit));
+
+        assert_eq!(mp.find_scid(&cid(2)), Some((1, 1)));
+        assert_eq!(mp.find_scid(&cid(3)), None);
+
+        // No connection IDs from the peer yet.
+        assert_eq!(mp.available_path_id(&[]), None);
+
+        assert_eq!(mp.new_dcid(1, 0, 0, cid(10), 10), Ok(vec![]));
+        assert_eq!(mp.available_path_id(&[]), Some(1));
+        assert_eq!(mp.available_path_id(&[1]), None);
+        assert_eq!(mp.lowest_dcid_seq(1), Some(0));
+
+        // Retire Prior To larger than the sequence number is invalid.
+        assert_eq!(mp.new_dcid(1, 1, 2, cid(11), 11), Err(Error::InvalidFrame));
+
+        assert_eq!(mp.new_dcid(1, 1, 1, cid(11), 11), Ok(vec![0]));
+        assert_eq!(mp.lowest_dcid_seq(1), Some(1));
+        assert_eq!(mp.get_dcid(1, 1), Ok(&cid(11)));
+
+        assert_eq!(
+            mp.next_frame(),
+            Some(frame::Frame::PathNewConnectionId {
+                path_id: 1,
+                seq_num: 0,
+                retire_prior_to: 0,
+                conn_id: cid(1).to_vec(),
+                reset_token: 1_u128.to_be_bytes(),
+            })
+        );
+
+        // The peer retires one of our connection IDs.
+        assert_eq!(mp.retire_scid(1, 0, &cid(2)), Ok(()));
+        assert_eq!(mp.retire_scid(1, 1, &cid(2)), Err(Error::InvalidState));
+        assert_eq!(mp.retire_scid(1, 2, &cid(1)), Err(Error::InvalidState));
+        assert_eq!(mp.pop_retired_scid(), Some(cid(1)));
+        assert_eq!(mp.pop_retired_scid(), None);
+    }
+
+    #[test]
+    fn abandon() {
+        let mut mp = Multipath::new(1, 1, 2, 2);
+
+        let cid = |v: u8| ConnectionId::from_vec(vec![v; 8]);
+
+        assert_eq!(mp.new_scid(1, cid(1), 1), Ok(0));
+        assert_eq!(mp.new_dcid(1, 0, 0, cid(10), 10), Ok(vec![]));
+
+        assert!(mp.abandon(1, 42));
+        assert!(!mp.abandon(1, 42));
+        assert!(!mp.is_usable(1));
+
+        // All of our connection IDs for the path are retired.
+        assert_eq!(mp.pop_retired_scid(), Some(cid(1)));
+        assert_eq!(mp.available_path_id(&[]), None);
+
+        // The peer is allowed to use another Path ID.
+        let frame = mp.next_frame().unwrap();
+        assert_eq!(frame, frame::Frame::MaxPathId { max: 2 });
+        mp.on_frame_sent(&frame, false);
+
+        let frame = mp.next_frame().unwrap();
+        assert_eq!(frame, frame::Frame::PathAbandon {
+            path_id: 1,
+            error_code: 42,
+        });
+        mp.on_frame_sent(&frame, true);
+        assert_eq!(mp.next_frame(), Some(frame.clone()));
+        mp.on_frame_sent(&frame, false);
+
+        assert_eq!(mp.next_frame(), None);
+        assert!(!mp.has_pending_frames());
+    }
+
+    #[test]
+    fn path_status() {
+        let mut mp = Multipath::new(1, 1, 2, 2);
+
+        assert_eq!(
+            mp.set_status(2, PathStatus::Backup),
+            Err(Error::InvalidState)
+        );
+
+        // Nothing to advertise when the status doesn't change.
+        assert_eq!(mp.set_status(0, PathStatus::Available), Ok(()));
+        assert_eq!(mp.next_frame(), None);
+
+        assert_eq!(mp.set_status(0, PathStatus::Backup), Ok(()));
+
+        let frame = mp.next_frame().unwrap();
+        assert_eq!(frame, frame::Frame::PathStatusBackup {
+            path_id: 0,
+            seq_num: 0,
+        });
+        mp.on_frame_sent(&frame, false);
+
+        assert_eq!(mp.set_status(0, PathStatus::Available), Ok(()));
+        assert_eq!(
+            mp.next_frame(),
+            Some(frame::Frame::PathStatusAvailable {
+                path_id: 0,
+                seq_num: 1,
+            })
+        );
+
+        // Reordered status updates from the peer are ignored.
+        assert_eq!(mp.on_peer_status(1, 3, PathStatus::Backup), Ok(()));
+        assert_eq!(mp.on_peer_status(1, 2, PathStatus::Available), Ok(()));
+        assert_eq!(mp.path(1).unwrap().peer_status, PathStatus::Backup);
+
+        assert_eq!(
+            mp.on_peer_status(2, 0, PathStatus::Backup),
+            Err(Error::InvalidState)
+        );
+    }
+}
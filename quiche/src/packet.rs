@@ -37,6 +37,7 @@ use crate::Result;
 use crate::DEFAULT_INITIAL_CONGESTION_WINDOW_PACKETS;
 
 use crate::crypto;
+use crate::frame;
 use crate::rand;
 use crate::ranges;
 use crate::recovery;
@@ -380,11 +381,18 @@ impl<'a> Header<'a> {
         let ty = if version == 0 {
             Type::VersionNegotiation
         } else {
-            match (first & TYPE_MASK) >> 4 {
-                0x00 => Type::Initial,
-                0x01 => Type::ZeroRTT,
-                0x02 => Type::Handshake,
-                0x03 => Type::Retry,
+            // QUIC v2 uses different codepoints for long header packet types
+            // (RFC 9369, Section 3.2).
+            match ((first & TYPE_MASK) >> 4, version) {
+                (0x01, crate::PROTOCOL_VERSION_V2) => Type::Initial,
+                (0x02, crate::PROTOCOL_VERSION_V2) => Type::ZeroRTT,
+                (0x03, crate::PROTOCOL_VERSION_V2) => Type::Handshake,
+                (0x00, crate::PROTOCOL_VERSION_V2) => Type::Retry,
+
+                (0x00, _) => Type::Initial,
+                (0x01, _) => Type::ZeroRTT,
+                (0x02, _) => Type::Handshake,
+                (0x03, _) => Type::Retry,
                 _ => return Err(Error::InvalidPacket),
             }
         };
@@ -478,11 +486,16 @@ impl<'a> Header<'a> {
         }
 
         // Encode long header.
-        let ty: u8 = match self.ty {
-            Type::Initial => 0x00,
-            Type::ZeroRTT => 0x01,
-            Type::Handshake => 0x02,
-            Type::Retry => 0x03,
+        let ty: u8 = match (self.ty, self.version) {
+            (Type::Initial, crate::PROTOCOL_VERSION_V2) => 0x01,
+            (Type::ZeroRTT, crate::PROTOCOL_VERSION_V2) => 0x02,
+            (Type::Handshake, crate::PROTOCOL_VERSION_V2) => 0x03,
+            (Type::Retry, crate::PROTOCOL_VERSION_V2) => 0x00,
+
+            (Type::Initial, _) => 0x00,
+            (Type::ZeroRTT, _) => 0x01,
+            (Type::Handshake, _) => 0x02,
+            (Type::Retry, _) => 0x03,
             _ => return Err(Error::InvalidPacket),
         };
 
@@ -749,6 +762,7 @@ pub fn negotiate_version(
     b.put_u8(dcid.len() as u8)?;
     b.put_bytes(dcid)?;
     b.put_u32(crate::PROTOCOL_VERSION_V1)?;
+    b.put_u32(crate::PROTOCOL_VERSION_V2)?;
 
     Ok(b.off())
 }
@@ -784,6 +798,72 @@ pub fn retry(
     Ok(b.off())
 }
 
+/// The minimum size of a stateless reset packet, as per RFC 9000 Section 10.3.
+pub const MIN_STATELESS_RESET_LEN: usize = 21;
+
+/// Packets up to this size are answered with a stateless reset that is exactly
+/// one byte shorter, as recommended by RFC 9000 Section 10.3.
+const STATELESS_RESET_EXACT_LEN: usize = 43;
+
+/// The secret the token carried by a stateless reset is obtained from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatelessResetSecret<'a> {
+    /// The stateless reset token that was issued along with the connection ID.
+    Token(u128),
+
+    /// A static key the token is derived from, as done by
+    /// [`stateless_reset_token()`].
+    ///
+    /// [`stateless_reset_token()`]: fn.stateless_reset_token.html
+    Key(&'a [u8]),
+}
+
+pub fn stateless_reset_token(key: &[u8], cid: &[u8]) -> Result<u128> {
+    let tag = crypto::hmac_sha256(key, cid)?;
+
+    let mut token = [0; 16];
+    token.copy_from_slice(&tag[..16]);
+
+    Ok(u128::from_be_bytes(token))
+}
+
+pub fn stateless_reset(
+    reset_token: u128, len_hint: usize, out: &mut [u8],
+) -> Result<usize> {
+    // The reset must be smaller than the packet that triggered it, so that
+    // two endpoints can't keep resetting each other forever.
+    let max_len = len_hint.saturating_sub(1);
+
+    if max_len < MIN_STATELESS_RESET_LEN {
+        return Err(Error::Done);
+    }
+
+    // Past the recommended size, randomize the length so the reset can't be
+    // told apart from a regular packet by its size.
+    let len = if max_len <= STATELESS_RESET_EXACT_LEN {
+        max_len
+    } else {
+        let extra = (max_len - STATELESS_RESET_EXACT_LEN) as u64 + 1;
+
+        STATELESS_RESET_EXACT_LEN + rand::rand_u64_uniform(extra) as usize
+    };
+
+    if out.len() < len {
+        return Err(Error::BufferTooShort);
+    }
+
+    let token_off = len - 16;
+
+    // Fill everything but the token with unpredictable bits, so that the
+    // packet can't be distinguished from a regular short header packet.
+    rand::rand_bytes(&mut out[..token_off]);
+
+    out[0] = (out[0] & !FORM_BIT) | FIXED_BIT;
+    out[token_off..len].copy_from_slice(&reset_token.to_be_bytes());
+
+    Ok(len)
+}
+
 pub fn verify_retry_integrity(
     b: &octets::OctetsMut, odcid: &[u8], version: u32,
 ) -> Result<()> {
@@ -809,10 +889,22 @@ fn compute_retry_integrity_tag(
         0x46, 0x15, 0x99, 0xd3, 0x5d, 0x63, 0x2b, 0xf2, 0x23, 0x98, 0x25, 0xbb,
     ];
 
+    const RETRY_INTEGRITY_KEY_V2: [u8; KEY_LEN] = [
+        0x8f, 0xb4, 0xb0, 0x1b, 0x56, 0xac, 0x48, 0xe2, 0x60, 0xfb, 0xcb, 0xce,
+        0xad, 0x7c, 0xcc, 0x92,
+    ];
+
+    const RETRY_INTEGRITY_NONCE_V2: [u8; crypto::MAX_NONCE_LEN] = [
+        0xd8, 0x69, 0x69, 0xbc, 0x2d, 0x7c, 0x6d, 0x99, 0x90, 0xef, 0xb0, 0x4a,
+    ];
+
     let (key, nonce) = match version {
         crate::PROTOCOL_VERSION_V1 =>
             (&RETRY_INTEGRITY_KEY_V1, RETRY_INTEGRITY_NONCE_V1),
 
+        crate::PROTOCOL_VERSION_V2 =>
+            (&RETRY_INTEGRITY_KEY_V2, RETRY_INTEGRITY_NONCE_V2),
+
         _ => (&RETRY_INTEGRITY_KEY_V1, RETRY_INTEGRITY_NONCE_V1),
     };
 
@@ -851,7 +943,8 @@ pub struct KeyUpdate {
 
     /// The packet number triggered the latest key-update.
     ///
-    /// Incoming packets with lower pn should use this (prev) crypto key.
+    /// Incoming packets with lower pn should use this (prev) crypto key. For
+    /// locally initiated updates this is `u64::MAX` until the peer responds.
     pub pn_on_update: u64,
 
     /// Whether ACK frame for key-update has been sent.
@@ -882,6 +975,22 @@ pub struct PktNumSpace {
 
     /// Track if a received packet is ack eliciting.
     pub ack_elicited: bool,
+
+    /// The number of ack-eliciting packets received since the last ACK was
+    /// sent, when ACKs are delayed.
+    pub ack_eliciting_since_ack: u64,
+
+    /// When a delayed ACK needs to be sent.
+    pub ack_timer: Option<Instant>,
+
+    /// The smallest missing packet number not reported in an ACK yet.
+    pub first_unreported_missing: Option<u64>,
+
+    /// The ECN counts of the packets received.
+    pub ecn_counts: frame::EcnCounts,
+
+    /// The largest ECN counts reported by the peer that were validated.
+    pub peer_ecn_counts: frame::EcnCounts,
 }
 
 impl PktNumSpace {
@@ -894,11 +1003,39 @@ impl PktNumSpace {
             recv_pkt_need_ack: ranges::RangeSet::new(crate::MAX_ACK_RANGES),
             recv_pkt_num: PktNumWindow::default(),
             ack_elicited: false,
+            ack_eliciting_since_ack: 0,
+            ack_timer: None,
+            first_unreported_missing: None,
+            ecn_counts: frame::EcnCounts::default(),
+            peer_ecn_counts: frame::EcnCounts::default(),
+        }
+    }
+
+    /// Returns the ECN counts to include in ACK frames, if any ECN-marked
+    /// packet was received.
+    pub fn ack_ecn_counts(&self) -> Option<frame::EcnCounts> {
+        if self.ecn_counts == frame::EcnCounts::default() {
+            return None;
         }
+
+        Some(self.ecn_counts)
     }
 
     pub fn clear(&mut self) {
+        self.on_ack_sent();
+    }
+
+    /// Whether an ACK can be bundled with other frames, because ack-eliciting
+    /// packets were received, even if it doesn't need to be sent yet.
+    pub fn ack_pending(&self) -> bool {
+        self.ack_elicited || self.ack_timer.is_some()
+    }
+
+    pub fn on_ack_sent(&mut self) {
         self.ack_elicited = false;
+        self.ack_eliciting_since_ack = 0;
+        self.ack_timer = None;
+        self.first_unreported_missing = None;
     }
 
     pub fn ready(&self) -> bool {
@@ -1172,6 +1309,55 @@ mod tests {
     use crate::test_utils;
     use crate::MAX_SEND_UDP_PAYLOAD_SIZE;
 
+    #[test]
+    fn stateless_reset() {
+        let mut d = [0; 50];
+
+        // Too small to be answered by a smaller reset.
+        assert_eq!(super::stateless_reset(0xba, 21, &mut d), Err(Error::Done));
+
+        assert_eq!(super::stateless_reset(0xba, 22, &mut d), Ok(21));
+        assert_eq!(super::stateless_reset(0xba, 41, &mut d), Ok(40));
+
+        // Looks like a short header packet.
+        assert_eq!(d[0] & FORM_BIT, 0);
+        assert_eq!(d[0] & FIXED_BIT, FIXED_BIT);
+
+        let hdr = Header::from_slice(&mut d[..40], 16).unwrap();
+        assert_eq!(hdr.ty, Type::Short);
+
+        assert_eq!(&d[24..40], &0xba_u128.to_be_bytes());
+
+        // Larger packets are answered by resets of random size.
+        let mut d = [0; 1350];
+
+        for _ in 0..100 {
+            let len = super::stateless_reset(0xba, 1000, &mut d).unwrap();
+
+            assert!((STATELESS_RESET_EXACT_LEN..1000).contains(&len));
+            assert_eq!(&d[len - 16..len], &0xba_u128.to_be_bytes());
+        }
+
+        assert_eq!(
+            super::stateless_reset(0xba, 1000, &mut d[..20]),
+            Err(Error::BufferTooShort)
+        );
+    }
+
+    #[test]
+    fn stateless_reset_token() {
+        let key = [0xba; 32];
+
+        let token = super::stateless_reset_token(&key, &[0xaa; 16]).unwrap();
+
+        assert_eq!(super::stateless_reset_token(&key, &[0xaa; 16]), Ok(token));
+        assert_ne!(super::stateless_reset_token(&key, &[0xbb; 16]), Ok(token));
+        assert_ne!(
+            super::stateless_reset_token(&[0xbb; 32], &[0xaa; 16]),
+            Ok(token)
+        );
+    }
+
     #[test]
     fn retry() {
         let hdr = Header {
@@ -1199,6 +1385,68 @@ mod tests {
         assert_eq!(Header::from_bytes(&mut b, 9).unwrap(), hdr);
     }
 
+    #[test]
+    fn retry_v2() {
+        // Retry packet from RFC 9369, Appendix A.4.
+        let mut pkt = [
+            0xcf, 0x6b, 0x33, 0x43, 0xcf, 0x00, 0x08, 0xf0, 0x67, 0xa5, 0x50,
+            0x2a, 0x42, 0x62, 0xb5, 0x74, 0x6f, 0x6b, 0x65, 0x6e, 0xc8, 0x64,
+            0x6c, 0xe8, 0xbf, 0xe3, 0x39, 0x52, 0xd9, 0x55, 0x54, 0x36, 0x65,
+            0xdc, 0xc7, 0xb6,
+        ];
+
+        let odcid = [0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08];
+
+        let mut b = octets::OctetsMut::with_slice(&mut pkt);
+
+        let hdr = Header::from_bytes(&mut b, 0).unwrap();
+        assert_eq!(hdr.ty, Type::Retry);
+        assert_eq!(hdr.version, crate::PROTOCOL_VERSION_V2);
+        assert_eq!(hdr.token, Some(b"token".to_vec()));
+
+        assert_eq!(
+            verify_retry_integrity(&b, &odcid, crate::PROTOCOL_VERSION_V2),
+            Ok(())
+        );
+
+        // The same packet isn't valid for QUIC v1.
+        assert_eq!(
+            verify_retry_integrity(&b, &odcid, crate::PROTOCOL_VERSION_V1),
+            Err(Error::CryptoFail)
+        );
+    }
+
+    #[test]
+    fn long_header_types_v2() {
+        for (ty, bits) in [
+            (Type::Initial, 0x01),
+            (Type::ZeroRTT, 0x02),
+            (Type::Handshake, 0x03),
+        ] {
+            let hdr = Header {
+                ty,
+                version: crate::PROTOCOL_VERSION_V2,
+                dcid: vec![0xba; 9].into(),
+                scid: vec![0xbb; 7].into(),
+                pkt_num: 0,
+                pkt_num_len: 0,
+                token: (ty == Type::Initial).then(Vec::new),
+                versions: None,
+                key_phase: false,
+            };
+
+            let mut d = [0; 50];
+
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            assert!(hdr.to_bytes(&mut b).is_ok());
+
+            assert_eq!((d[0] & TYPE_MASK) >> 4, bits);
+
+            let mut b = octets::OctetsMut::with_slice(&mut d);
+            assert_eq!(Header::from_bytes(&mut b, 9).unwrap(), hdr);
+        }
+    }
+
     #[test]
     fn initial() {
         let hdr = Header {
@@ -1718,7 +1966,9 @@ mod tests {
 
         let alg = crypto::Algorithm::ChaCha20_Poly1305;
 
-        let aead = crypto::Open::from_secret(alg, &secret).unwrap();
+        let aead =
+            crypto::Open::from_secret(alg, crate::PROTOCOL_VERSION_V1, &secret)
+                .unwrap();
 
         let mut hdr = Header::from_bytes(&mut b, 0).unwrap();
         assert_eq!(hdr.ty, Type::Short);
@@ -2090,7 +2340,9 @@ mod tests {
 
         let alg = crypto::Algorithm::ChaCha20_Poly1305;
 
-        let aead = crypto::Seal::from_secret(alg, &secret).unwrap();
+        let aead =
+            crypto::Seal::from_secret(alg, crate::PROTOCOL_VERSION_V1, &secret)
+                .unwrap();
 
         let pn = 654_360_564;
         let pn_len = 3;
@@ -41,6 +41,7 @@ use crate::Error;
 use crate::Result;
 use crate::StartupExit;
 
+use crate::ecn;
 use crate::pmtud;
 use crate::recovery;
 use crate::recovery::HandshakeStatus;
@@ -116,6 +117,11 @@ pub enum PathEvent {
     ///
     /// Note that this event is only raised if the path has been validated.
     PeerMigrated(SocketAddr, SocketAddr),
+
+    /// The connection migrated to the server's preferred address over the
+    /// network path denoted by the pair of `SocketAddr`, after having
+    /// validated it. This is a client side only event.
+    MigratedToPreferredAddress(SocketAddr, SocketAddr),
 }
 
 /// A network path on which QUIC packets can be sent.
@@ -145,6 +151,9 @@ pub struct Path {
     /// Path MTU discovery state. None if PMTUD is disabled on the path.
     pub pmtud: Option<pmtud::Pmtud>,
 
+    /// ECN validation state.
+    pub ecn: ecn::EcnValidation,
+
     /// Pending challenge data with the size of the packet containing them and
     /// when they were sent.
     in_flight_challenges: VecDeque<([u8; 8], usize, Instant)>,
@@ -219,6 +228,14 @@ pub struct Path {
 
     /// Whether or not we should force eliciting of an ACK (e.g. via PING frame)
     pub needs_ack_eliciting: bool,
+
+    /// The multipath Path ID of the path. Always 0 unless multipath is
+    /// enabled.
+    pub mp_path_id: u64,
+
+    /// Whether the multipath Path ID of the path was abandoned, and its closure
+    /// already notified.
+    abandoned: bool,
 }
 
 impl Path {
@@ -262,6 +279,7 @@ impl Path {
             active: false,
             recovery: recovery::Recovery::new_with_config(recovery_config),
             pmtud,
+            ecn: ecn::EcnValidation::new(config.is_some_and(|c| c.ecn)),
             in_flight_challenges: VecDeque::new(),
             max_challenge_size: 0,
             probing_lost: 0,
@@ -286,6 +304,8 @@ impl Path {
             failure_notified: false,
             migrating: false,
             needs_ack_eliciting: false,
+            mp_path_id: 0,
+            abandoned: false,
         }
     }
 
@@ -739,7 +759,34 @@ impl PathMap {
         self.addrs_to_paths
             .remove(&(path.local_addr, path.peer_addr));
 
-        self.notify_event(PathEvent::Closed(path.local_addr, path.peer_addr));
+        if !path.abandoned {
+            self.notify_event(PathEvent::Closed(path.local_addr, path.peer_addr));
+        }
+
+        Ok(())
+    }
+
+    /// Stops using the path identified by `path_id` because its multipath Path
+    /// ID was abandoned, and notifies the application that it is closed.
+    ///
+    /// The path is only removed once room is needed for a new one, so that the
+    /// frames it still has in flight can be retransmitted on other paths.
+    pub fn abandon_path(&mut self, path_id: usize) -> Result<()> {
+        let path = self.get_mut(path_id)?;
+
+        if path.abandoned {
+            return Ok(());
+        }
+
+        path.abandoned = true;
+        path.active_scid_seq = None;
+        path.active_dcid_seq = None;
+        path.in_flight_challenges.clear();
+        path.challenge_requested = false;
+
+        let (local_addr, peer_addr) = (path.local_addr, path.peer_addr);
+
+        self.notify_event(PathEvent::Closed(local_addr, peer_addr));
 
         Ok(())
     }
@@ -41,6 +41,7 @@ pub(crate) static BBR: CongestionControlOps = CongestionControlOps {
     on_packet_sent,
     on_packets_acked,
     congestion_event,
+    on_ecn_ce,
     checkpoint,
     rollback,
     has_custom_pacing,
@@ -340,6 +341,13 @@ fn congestion_event(
     }
 }
 
+fn on_ecn_ce(
+    _r: &mut Congestion, _bytes_in_flight: usize, _time_sent: Instant,
+    _now: Instant,
+) {
+    // BBR does not react to ECN.
+}
+
 fn checkpoint(_r: &mut Congestion) {}
 
 fn rollback(_r: &mut Congestion) -> bool {
@@ -460,6 +468,7 @@ mod tests {
                 lost: 0,
                 has_data: false,
                 is_pmtud_probe: false,
+                ecn_marked: false,
             };
 
             r.on_packet_sent(
@@ -495,6 +504,7 @@ mod tests {
                 lost_bytes: 2 * mss,
                 acked_bytes: mss,
                 spurious_losses: 0,
+                ecn_marked_acked: 0,
             },
         );
 
@@ -534,6 +544,7 @@ mod tests {
                 lost: 0,
                 has_data: false,
                 is_pmtud_probe: false,
+                ecn_marked: false,
             };
 
             r.on_packet_sent(
@@ -569,6 +580,7 @@ mod tests {
                     lost_bytes: 0,
                     acked_bytes: mss,
                     spurious_losses: 0,
+                    ecn_marked_acked: 0,
                 },
             );
         }
@@ -591,6 +603,7 @@ mod tests {
                 lost: 0,
                 has_data: false,
                 is_pmtud_probe: false,
+                ecn_marked: false,
             };
 
             r.on_packet_sent(
@@ -628,6 +641,7 @@ mod tests {
                 lost_bytes: 0,
                 acked_bytes: mss,
                 spurious_losses: 0,
+                ecn_marked_acked: 0,
             },
         );
 
@@ -667,6 +681,7 @@ mod tests {
                 lost: 0,
                 has_data: false,
                 is_pmtud_probe: false,
+                ecn_marked: false,
             };
 
             r.on_packet_sent(
@@ -699,6 +714,7 @@ mod tests {
                     lost_bytes: 0,
                     acked_bytes: mss,
                     spurious_losses: 0,
+                    ecn_marked_acked: 0,
                 },
             );
         }
@@ -743,6 +759,7 @@ mod tests {
                 lost: 0,
                 has_data: false,
                 is_pmtud_probe: false,
+                ecn_marked: false,
             };
 
             r.on_packet_sent(
@@ -777,6 +794,7 @@ mod tests {
                     lost_bytes: 0,
                     acked_bytes: mss,
                     spurious_losses: 0,
+                    ecn_marked_acked: 0,
                 },
             );
         }
@@ -804,6 +822,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -840,6 +859,7 @@ mod tests {
                 lost_bytes: 0,
                 acked_bytes: mss,
                 spurious_losses: 0,
+                ecn_marked_acked: 0,
             },
         );
 
@@ -39,6 +39,7 @@ pub(crate) static BBR2: CongestionControlOps = CongestionControlOps {
     on_packet_sent,
     on_packets_acked,
     congestion_event,
+    on_ecn_ce,
     checkpoint,
     rollback,
     has_custom_pacing,
@@ -614,6 +615,13 @@ fn congestion_event(
     }
 }
 
+fn on_ecn_ce(
+    r: &mut Congestion, _bytes_in_flight: usize, _time_sent: Instant,
+    _now: Instant,
+) {
+    per_loss::bbr2_update_on_ecn_ce(r);
+}
+
 fn checkpoint(_r: &mut Congestion) {}
 
 fn rollback(_r: &mut Congestion) -> bool {
@@ -736,6 +744,7 @@ mod tests {
                 lost: 0,
                 has_data: false,
                 is_pmtud_probe: false,
+                ecn_marked: false,
             };
 
             r.on_packet_sent(
@@ -770,6 +779,7 @@ mod tests {
                 lost_bytes: 0,
                 acked_bytes: mss * 5,
                 spurious_losses: 0,
+                ecn_marked_acked: 0,
             }
         );
 
@@ -814,6 +824,7 @@ mod tests {
                 lost: 0,
                 has_data: false,
                 is_pmtud_probe: false,
+                ecn_marked: false,
             };
 
             r.on_packet_sent(
@@ -849,6 +860,7 @@ mod tests {
                 lost_bytes: 2 * mss,
                 acked_bytes: mss,
                 spurious_losses: 0,
+                ecn_marked_acked: 0,
             }
         );
 
@@ -892,6 +904,7 @@ mod tests {
                 lost: 0,
                 has_data: false,
                 is_pmtud_probe: false,
+                ecn_marked: false,
             };
 
             r.on_packet_sent(
@@ -927,6 +940,7 @@ mod tests {
                     lost_bytes: 0,
                     acked_bytes: mss,
                     spurious_losses: 0,
+                    ecn_marked_acked: 0,
                 }
             );
         }
@@ -950,6 +964,7 @@ mod tests {
                 lost: 0,
                 has_data: false,
                 is_pmtud_probe: false,
+                ecn_marked: false,
             };
 
             r.on_packet_sent(
@@ -988,6 +1003,7 @@ mod tests {
                 lost_bytes: 0,
                 acked_bytes: mss,
                 spurious_losses: 0,
+                ecn_marked_acked: 0,
             }
         );
 
@@ -1028,6 +1044,7 @@ mod tests {
                 lost: 0,
                 has_data: false,
                 is_pmtud_probe: false,
+                ecn_marked: false,
             };
 
             r.on_packet_sent(
@@ -1062,6 +1079,7 @@ mod tests {
                     lost_bytes: 0,
                     acked_bytes: mss,
                     spurious_losses: 0,
+                    ecn_marked_acked: 0,
                 }
             );
         }
@@ -1092,6 +1110,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -1128,6 +1147,7 @@ mod tests {
                 lost_bytes: 0,
                 acked_bytes: mss,
                 spurious_losses: 0,
+                ecn_marked_acked: 0,
             }
         );
 
@@ -129,6 +129,13 @@ pub fn bbr2_advance_latest_delivery_signals(r: &mut Congestion) {
     }
 }
 
+// Like losses, CE marks reduce the lower bounds of the model at the end of
+// the round.
+pub fn bbr2_update_on_ecn_ce(r: &mut Congestion) {
+    r.bbr2_state.loss_in_round = true;
+    r.bbr2_state.loss_events_in_round += 1;
+}
+
 pub fn bbr2_reset_congestion_signals(r: &mut Congestion) {
     let bbr = &mut r.bbr2_state;
 
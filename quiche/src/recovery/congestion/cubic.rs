@@ -50,6 +50,7 @@ pub(crate) static CUBIC: CongestionControlOps = CongestionControlOps {
     on_packet_sent,
     on_packets_acked,
     congestion_event,
+    on_ecn_ce: on_congestion_event,
     checkpoint,
     rollback,
     has_custom_pacing,
@@ -333,7 +334,14 @@ fn congestion_event(
     r: &mut Congestion, bytes_in_flight: usize, _lost_bytes: usize,
     largest_lost_pkt: &Sent, now: Instant,
 ) {
-    let time_sent = largest_lost_pkt.time_sent;
+    on_congestion_event(r, bytes_in_flight, largest_lost_pkt.time_sent, now);
+}
+
+/// Reacts to a congestion signal (a loss or an ECN-CE mark) on a packet sent
+/// at `time_sent`.
+fn on_congestion_event(
+    r: &mut Congestion, bytes_in_flight: usize, time_sent: Instant, now: Instant,
+) {
     let in_congestion_recovery = r.in_congestion_recovery(time_sent);
 
     // Start a new congestion event if packet was sent after the
@@ -519,6 +519,7 @@ mod tests {
             lost_bytes: 0,
             acked_bytes: mss * packet_count,
             spurious_losses: 0,
+            ecn_marked_acked: 0,
         });
     }
 }
@@ -0,0 +1,418 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! LEDBAT++ Congestion Control
+//!
+//! A scavenger congestion controller that yields to other traffic when the
+//! queueing delay grows, based on the following documents:
+//! <https://www.rfc-editor.org/rfc/rfc6817>
+//! <https://datatracker.ietf.org/doc/html/draft-irtf-iccrg-ledbat-plus-plus-01>
+//!
+//! As in LEDBAT++, the delay is measured with round-trip times instead of
+//! one-way delays. Periodic slowdowns are not implemented, the base delay is
+//! instead refreshed by the expiry of the base delay history.
+
+use std::cmp;
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::time::Instant;
+
+use super::rtt::RttStats;
+use super::Acked;
+use super::Sent;
+
+use super::Congestion;
+use super::CongestionControlOps;
+use crate::recovery::LOSS_REDUCTION_FACTOR;
+use crate::recovery::MINIMUM_WINDOW_PACKETS;
+
+pub(crate) static LEDBAT: CongestionControlOps = CongestionControlOps {
+    on_init,
+    on_packet_sent,
+    on_packets_acked,
+    congestion_event,
+    on_ecn_ce: on_congestion_event,
+    checkpoint,
+    rollback,
+    has_custom_pacing,
+    #[cfg(feature = "qlog")]
+    state_str,
+    debug_fmt,
+};
+
+/// The queueing delay LEDBAT++ tries not to exceed.
+const TARGET: Duration = Duration::from_millis(60);
+
+/// The largest inverse of the gain applied to window increases.
+const MAX_GAIN_INVERSE: f64 = 16.0;
+
+/// The multiplicative decrease factor applied when the queueing delay is
+/// above the target.
+const DECREASE_CONSTANT: f64 = 1.0;
+
+/// The number of per-minute minimum delays kept to compute the base delay.
+const BASE_HISTORY: usize = 10;
+
+/// The interval at which a new base delay bucket is started.
+const BASE_HISTORY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The number of recent delay samples the current delay is the minimum of.
+const CURRENT_FILTER: usize = 4;
+
+/// LEDBAT++ State Variables.
+#[derive(Debug, Default)]
+pub struct State {
+    // Minimum delays observed in each of the last BASE_HISTORY intervals.
+    base_history: VecDeque<Duration>,
+
+    // Start of the newest base delay interval.
+    last_rollover: Option<Instant>,
+
+    // Most recent delay samples.
+    current_filter: VecDeque<Duration>,
+}
+
+impl State {
+    fn update(&mut self, delay: Duration, now: Instant) {
+        let rollover = match self.last_rollover {
+            Some(t) => now.saturating_duration_since(t) >= BASE_HISTORY_INTERVAL,
+
+            None => true,
+        };
+
+        if rollover {
+            self.base_history.push_back(delay);
+            self.last_rollover = Some(now);
+
+            if self.base_history.len() > BASE_HISTORY {
+                self.base_history.pop_front();
+            }
+        } else if let Some(base) = self.base_history.back_mut() {
+            *base = cmp::min(*base, delay);
+        }
+
+        self.current_filter.push_back(delay);
+
+        if self.current_filter.len() > CURRENT_FILTER {
+            self.current_filter.pop_front();
+        }
+    }
+
+    fn base_delay(&self) -> Option<Duration> {
+        self.base_history.iter().min().copied()
+    }
+
+    fn current_delay(&self) -> Option<Duration> {
+        self.current_filter.iter().min().copied()
+    }
+
+    /// Returns the current delay in excess of the base delay.
+    fn queueing_delay(&self) -> Duration {
+        match (self.current_delay(), self.base_delay()) {
+            (Some(current), Some(base)) => current.saturating_sub(base),
+
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Returns the gain applied to window increases, which makes LEDBAT++
+    /// ramp up more slowly when the base delay is small.
+    fn gain(&self) -> f64 {
+        let base = match self.base_delay() {
+            Some(v) if !v.is_zero() => v,
+
+            _ => return 1.0 / MAX_GAIN_INVERSE,
+        };
+
+        let inverse = (2.0 * TARGET.as_secs_f64() / base.as_secs_f64()).ceil();
+
+        1.0 / inverse.clamp(1.0, MAX_GAIN_INVERSE)
+    }
+}
+
+pub fn on_init(_r: &mut Congestion) {}
+
+pub fn on_packet_sent(
+    _r: &mut Congestion, _sent_bytes: usize, _bytes_in_flight: usize,
+    _now: Instant,
+) {
+}
+
+fn on_packets_acked(
+    r: &mut Congestion, _bytes_in_flight: usize, packets: &mut Vec<Acked>,
+    now: Instant, rtt_stats: &RttStats,
+) {
+    if packets.is_empty() {
+        return;
+    }
+
+    r.ledbat_state.update(rtt_stats.latest_rtt, now);
+
+    let bytes_acked: usize = packets
+        .drain(..)
+        .filter(|pkt| !r.in_congestion_recovery(pkt.time_sent))
+        .map(|pkt| pkt.size)
+        .sum();
+
+    if bytes_acked == 0 {
+        return;
+    }
+
+    let queueing_delay = r.ledbat_state.queueing_delay();
+    let gain = r.ledbat_state.gain();
+
+    if r.congestion_window < r.ssthresh.get() {
+        // Exit slow start before the queueing delay reaches the target.
+        if queueing_delay > TARGET * 3 / 4 {
+            r.ssthresh.update(r.congestion_window, true);
+        } else {
+            if !r.app_limited {
+                r.congestion_window += (gain * bytes_acked as f64) as usize;
+            }
+
+            return;
+        }
+    }
+
+    // Congestion avoidance.
+    let cwnd_packets = r.congestion_window as f64 / r.max_datagram_size as f64;
+
+    let delta = if queueing_delay <= TARGET {
+        if r.app_limited {
+            return;
+        }
+
+        gain
+    } else {
+        let excess = queueing_delay.as_secs_f64() / TARGET.as_secs_f64() - 1.0;
+
+        (gain - DECREASE_CONSTANT * cwnd_packets * excess)
+            .max(-cwnd_packets / 2.0)
+    };
+
+    r.bytes_acked_ca += bytes_acked;
+
+    let cwnd_inc = delta * r.bytes_acked_ca as f64 / cwnd_packets;
+
+    // Small increases are accumulated until they amount to a full byte.
+    if cwnd_inc.abs() >= 1.0 {
+        r.bytes_acked_ca = 0;
+
+        let cwnd = r.congestion_window as f64 + cwnd_inc;
+
+        r.congestion_window =
+            cmp::max(cwnd as usize, r.max_datagram_size * MINIMUM_WINDOW_PACKETS);
+    }
+}
+
+fn congestion_event(
+    r: &mut Congestion, bytes_in_flight: usize, _lost_bytes: usize,
+    largest_lost_pkt: &Sent, now: Instant,
+) {
+    on_congestion_event(r, bytes_in_flight, largest_lost_pkt.time_sent, now);
+}
+
+/// Reacts to a congestion signal (a loss or an ECN-CE mark) on a packet sent
+/// at `time_sent`, by halving the window once per round trip like Reno.
+fn on_congestion_event(
+    r: &mut Congestion, _bytes_in_flight: usize, time_sent: Instant, now: Instant,
+) {
+    if r.in_congestion_recovery(time_sent) {
+        return;
+    }
+
+    r.congestion_recovery_start_time = Some(now);
+
+    r.congestion_window = cmp::max(
+        (r.congestion_window as f64 * LOSS_REDUCTION_FACTOR) as usize,
+        r.max_datagram_size * MINIMUM_WINDOW_PACKETS,
+    );
+
+    r.bytes_acked_ca = 0;
+
+    r.ssthresh.update(r.congestion_window, false);
+}
+
+fn checkpoint(_r: &mut Congestion) {}
+
+fn rollback(_r: &mut Congestion) -> bool {
+    true
+}
+
+fn has_custom_pacing() -> bool {
+    false
+}
+
+#[cfg(feature = "qlog")]
+fn state_str(r: &Congestion, now: Instant) -> &'static str {
+    super::reno::state_str(r, now)
+}
+
+fn debug_fmt(r: &Congestion, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(
+        f,
+        "ledbat={{ base_delay={:?} queueing_delay={:?} }} ",
+        r.ledbat_state.base_delay(),
+        r.ledbat_state.queueing_delay()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::CongestionControlAlgorithm;
+    use crate::StartupExitReason;
+
+    use crate::recovery::congestion::recovery::LegacyRecovery;
+    use crate::recovery::congestion::test_sender::TestSender;
+    use crate::recovery::RecoveryOps;
+
+    fn test_sender() -> TestSender {
+        TestSender::new(CongestionControlAlgorithm::Ledbat, false)
+    }
+
+    #[test]
+    fn ledbat_init() {
+        let mut cfg = crate::Config::new(crate::PROTOCOL_VERSION).unwrap();
+        cfg.set_cc_algorithm(CongestionControlAlgorithm::Ledbat);
+
+        let r = LegacyRecovery::new(&cfg);
+
+        assert!(r.cwnd() > 0);
+        assert_eq!(r.bytes_in_flight(), 0);
+    }
+
+    #[test]
+    fn ledbat_slow_start() {
+        let mut sender = test_sender();
+        let size = sender.max_datagram_size;
+
+        // A 100ms base delay gives a gain of 1/2.
+        sender.update_rtt(Duration::from_millis(100));
+
+        // Send initcwnd full MSS packets to become no longer app limited
+        for _ in 0..sender.initial_congestion_window_packets {
+            sender.send_packet(size);
+        }
+
+        let cwnd_prev = sender.congestion_window;
+
+        sender.ack_n_packets(2, size);
+
+        // Check if cwnd increased by the gain times the acked bytes.
+        assert_eq!(sender.congestion_window, cwnd_prev + size);
+    }
+
+    #[test]
+    fn ledbat_backs_off_on_growing_rtt() {
+        let mut sender = test_sender();
+        let size = sender.max_datagram_size;
+        let base_rtt = Duration::from_millis(50);
+
+        sender.update_rtt(base_rtt);
+
+        for _ in 0..sender.initial_congestion_window_packets {
+            sender.send_packet(size);
+        }
+
+        sender.ack_n_packets(1, size);
+
+        let cwnd_prev = sender.congestion_window;
+        assert_eq!(sender.ssthresh.get(), usize::MAX);
+
+        // The RTT keeps growing as a queue builds up at the bottleneck.
+        for i in 1..=10 {
+            sender.advance_time(base_rtt);
+            sender.update_rtt(base_rtt + Duration::from_millis(20 * i));
+
+            sender.send_packet(size);
+            sender.ack_n_packets(1, size);
+        }
+
+        // Slow start exited because of the queueing delay, and the window
+        // shrunk below where it was before the queue built up.
+        assert_eq!(
+            sender.ssthresh.startup_exit().map(|e| e.reason),
+            Some(StartupExitReason::PersistentQueue)
+        );
+        assert!(sender.congestion_window < cwnd_prev);
+
+        // The window never goes below the minimum.
+        for _ in 0..100 {
+            sender.send_packet(size);
+            sender.ack_n_packets(1, size);
+        }
+
+        assert_eq!(sender.congestion_window, size * MINIMUM_WINDOW_PACKETS);
+    }
+
+    #[test]
+    fn ledbat_congestion_avoidance() {
+        let mut sender = test_sender();
+        let size = sender.max_datagram_size;
+        let rtt = Duration::from_millis(100);
+
+        sender.update_rtt(rtt);
+
+        sender.send_packet(size);
+        sender.lose_n_packets(1, size, None);
+
+        let cwnd_prev = sender.congestion_window;
+        assert!(cwnd_prev < sender.initial_congestion_window_packets * size);
+
+        sender.advance_time(rtt);
+
+        // Fill the window to become no longer app limited.
+        let n = cwnd_prev / size;
+        for _ in 0..n {
+            sender.send_packet(size);
+        }
+
+        // Acking a full window with no queueing delay grows the window by
+        // the gain times the MSS.
+        sender.ack_n_packets(n, size);
+
+        assert_eq!(sender.congestion_window, cwnd_prev + size / 2);
+    }
+
+    #[test]
+    fn ledbat_congestion_event() {
+        let mut sender = test_sender();
+        let size = sender.max_datagram_size;
+
+        let prev_cwnd = sender.congestion_window;
+
+        sender.send_packet(size);
+        sender.lose_n_packets(1, size, None);
+
+        assert_eq!(prev_cwnd / 2, sender.congestion_window);
+        assert_eq!(
+            sender.ssthresh.startup_exit().map(|e| e.reason),
+            Some(StartupExitReason::Loss)
+        );
+    }
+}
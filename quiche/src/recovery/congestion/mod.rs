@@ -29,6 +29,7 @@ use std::time::Duration;
 use std::time::Instant;
 
 use self::recovery::Acked;
+use self::resume::PathCapacityRecord;
 use super::bandwidth::Bandwidth;
 use super::RecoveryConfig;
 use super::Sent;
@@ -90,6 +91,11 @@ pub struct Congestion {
 
     cubic_state: cubic::State,
 
+    ledbat_state: ledbat::State,
+
+    // Careful Resume.
+    resume_state: resume::State,
+
     // HyStart++.
     pub(crate) hystart: hystart::Hystart,
 
@@ -153,6 +159,10 @@ impl Congestion {
 
             cubic_state: cubic::State::default(),
 
+            ledbat_state: ledbat::State::default(),
+
+            resume_state: resume::State::default(),
+
             app_limited: false,
 
             lost_count: 0,
@@ -243,7 +253,11 @@ impl Congestion {
         }
 
         // Pacing: Set the pacing rate if CC doesn't do its own.
-        if !(self.cc_ops.has_custom_pacing)() && rtt_stats.has_first_rtt_sample {
+        if let Some(rate) = resume::pacing_rate(self) {
+            self.set_pacing_rate(rate, now);
+        } else if !(self.cc_ops.has_custom_pacing)() &&
+            rtt_stats.has_first_rtt_sample
+        {
             let rate = PACING_MULTIPLIER * self.congestion_window as f64 /
                 rtt_stats.smoothed_rtt.as_secs_f64();
             self.set_pacing_rate(rate as u64, now);
@@ -253,6 +267,15 @@ impl Congestion {
 
         pkt.time_sent = self.get_packet_send_time();
 
+        if in_flight {
+            resume::on_packet_sent(
+                self,
+                sent_bytes,
+                bytes_in_flight,
+                pkt.time_sent,
+            );
+        }
+
         // bytes_in_flight is already updated. Use previous value.
         self.delivery_rate
             .on_packet_sent(pkt, bytes_in_flight, bytes_lost);
@@ -270,6 +293,9 @@ impl Congestion {
         // Fill in a rate sample.
         self.delivery_rate.generate_rate_sample(*rtt_stats.min_rtt);
 
+        let acked_bytes: usize = acked.iter().map(|p| p.size).sum();
+        let largest_time_sent = acked.iter().map(|p| p.time_sent).max();
+
         // Call congestion control hooks.
         (self.cc_ops.on_packets_acked)(
             self,
@@ -278,6 +304,38 @@ impl Congestion {
             now,
             rtt_stats,
         );
+
+        if let Some(largest_time_sent) = largest_time_sent {
+            resume::on_packets_acked(
+                self,
+                bytes_in_flight.saturating_sub(acked_bytes),
+                acked_bytes,
+                largest_time_sent,
+                rtt_stats,
+            );
+        }
+    }
+
+    /// Starts Careful Resume using the capacity saved in `record`.
+    ///
+    /// Only the Reno and CUBIC congestion controllers are supported.
+    pub(crate) fn setup_careful_resume(
+        &mut self, record: &PathCapacityRecord,
+    ) -> crate::Result<()> {
+        if !std::ptr::eq(self.cc_ops, &reno::RENO) &&
+            !std::ptr::eq(self.cc_ops, &cubic::CUBIC)
+        {
+            return Err(crate::Error::CongestionControl);
+        }
+
+        self.resume_state = resume::State::new(record);
+
+        Ok(())
+    }
+
+    /// Reacts to a congestion signal after the congestion controller did.
+    pub(crate) fn on_congestion_event(&mut self) {
+        resume::on_congestion_event(self);
     }
 
     fn schedule_next_packet(&mut self, now: Instant, packet_size: usize) {
@@ -328,6 +386,13 @@ pub(crate) struct CongestionControlOps {
         now: Instant,
     ),
 
+    pub on_ecn_ce: fn(
+        r: &mut Congestion,
+        bytes_in_flight: usize,
+        time_sent: Instant,
+        now: Instant,
+    ),
+
     pub checkpoint: fn(r: &mut Congestion),
 
     pub rollback: fn(r: &mut Congestion) -> bool,
@@ -350,7 +415,9 @@ impl From<CongestionControlAlgorithm> for &'static CongestionControlOps {
             CongestionControlAlgorithm::CUBIC => &cubic::CUBIC,
             CongestionControlAlgorithm::BBR => &bbr::BBR,
             CongestionControlAlgorithm::BBR2 => &bbr2::BBR2,
-            CongestionControlAlgorithm::Bbr2Gcongestion => {
+            CongestionControlAlgorithm::Ledbat => &ledbat::LEDBAT,
+            CongestionControlAlgorithm::Bbr2Gcongestion |
+            CongestionControlAlgorithm::Bbr3 => {
                 debug_panic!("legacy implementation, not gcongestion");
                 &bbr2::BBR2
             },
@@ -413,10 +480,12 @@ mod bbr2;
 mod cubic;
 mod delivery_rate;
 mod hystart;
+mod ledbat;
 pub(crate) mod pacer;
 mod prr;
 pub(crate) mod recovery;
 mod reno;
+pub(crate) mod resume;
 
 #[cfg(test)]
 mod test_sender;
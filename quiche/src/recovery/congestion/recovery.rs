@@ -36,9 +36,11 @@ use super::Sent;
 
 use crate::packet::Epoch;
 use crate::ranges::RangeSet;
+use crate::recovery::AckFrequency;
 use crate::recovery::Bandwidth;
 use crate::recovery::HandshakeStatus;
 use crate::recovery::OnLossDetectionTimeoutOutcome;
+use crate::recovery::PathCapacityRecord;
 use crate::recovery::RecoveryOps;
 use crate::recovery::StartupExit;
 use crate::Error;
@@ -98,6 +100,7 @@ struct RecoveryEpoch {
 struct AckedDetectionResult {
     acked_bytes: usize,
     spurious_losses: usize,
+    ecn_marked_acked: u64,
     spurious_pkt_thresh: Option<u64>,
     has_ack_eliciting: bool,
     has_in_flight_spurious_loss: bool,
@@ -121,6 +124,7 @@ impl RecoveryEpoch {
 
         let mut acked_bytes = 0;
         let mut spurious_losses = 0;
+        let mut ecn_marked_acked = 0;
         let mut spurious_pkt_thresh = None;
         let mut has_ack_eliciting = false;
         let mut has_in_flight_spurious_loss = false;
@@ -181,6 +185,10 @@ impl RecoveryEpoch {
                         acked_bytes += unacked.size;
                     }
 
+                    if unacked.ecn_marked {
+                        ecn_marked_acked += 1;
+                    }
+
                     newly_acked.push(Acked {
                         pkt_num: unacked.pkt_num,
                         time_sent: unacked.time_sent,
@@ -209,6 +217,7 @@ impl RecoveryEpoch {
         Ok(AckedDetectionResult {
             acked_bytes,
             spurious_losses,
+            ecn_marked_acked,
             spurious_pkt_thresh,
             has_ack_eliciting,
             has_in_flight_spurious_loss,
@@ -358,6 +367,10 @@ pub struct LegacyRecovery {
 
     /// A resusable list of acks.
     newly_acked: Vec<Acked>,
+
+    /// The time the largest packet acknowledged by the last ACK frame was
+    /// sent, used to react to ECN-CE marks reported in that frame.
+    largest_newly_acked_time_sent: Option<Instant>,
 }
 
 impl LegacyRecovery {
@@ -399,6 +412,8 @@ impl LegacyRecovery {
             congestion: Congestion::from_config(recovery_config),
 
             newly_acked: Vec::new(),
+
+            largest_newly_acked_time_sent: None,
         }
     }
 
@@ -523,6 +538,8 @@ impl LegacyRecovery {
                 now,
             );
 
+            self.congestion.on_congestion_event();
+
             self.bytes_in_flight
                 .saturating_subtract(loss.lost_bytes, now);
         };
@@ -642,6 +659,7 @@ impl RecoveryOps for LegacyRecovery {
         let AckedDetectionResult {
             acked_bytes,
             spurious_losses,
+            ecn_marked_acked,
             spurious_pkt_thresh,
             has_ack_eliciting,
             has_in_flight_spurious_loss,
@@ -671,6 +689,8 @@ impl RecoveryOps for LegacyRecovery {
 
         let largest_newly_acked = self.newly_acked.last().unwrap();
 
+        self.largest_newly_acked_time_sent = Some(largest_newly_acked.time_sent);
+
         // Update `largest_acked_packet` based on the validated `newly_acked`
         // value.
         let largest_acked_pkt_num = self.epochs[epoch]
@@ -718,9 +738,27 @@ impl RecoveryOps for LegacyRecovery {
             lost_bytes,
             acked_bytes,
             spurious_losses,
+            ecn_marked_acked,
         })
     }
 
+    fn on_ecn_ce(&mut self, ce_count: u64, now: Instant) {
+        let time_sent = match self.largest_newly_acked_time_sent {
+            Some(v) if ce_count > 0 => v,
+
+            _ => return,
+        };
+
+        (self.congestion.cc_ops.on_ecn_ce)(
+            &mut self.congestion,
+            self.bytes_in_flight.get(),
+            time_sent,
+            now,
+        );
+
+        self.congestion.on_congestion_event();
+    }
+
     fn on_loss_detection_timeout(
         &mut self, handshake_status: HandshakeStatus, now: Instant,
         trace_id: &str,
@@ -1045,6 +1083,16 @@ impl RecoveryOps for LegacyRecovery {
         false
     }
 
+    fn ack_frequency(&self) -> Option<AckFrequency> {
+        None
+    }
+
+    fn setup_careful_resume(
+        &mut self, record: &PathCapacityRecord,
+    ) -> Result<()> {
+        self.congestion.setup_careful_resume(record)
+    }
+
     fn lost_count(&self) -> usize {
         self.congestion.lost_count
     }
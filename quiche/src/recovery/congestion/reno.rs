@@ -45,6 +45,7 @@ pub(crate) static RENO: CongestionControlOps = CongestionControlOps {
     on_packet_sent,
     on_packets_acked,
     congestion_event,
+    on_ecn_ce: on_congestion_event,
     checkpoint,
     rollback,
     has_custom_pacing,
@@ -108,13 +109,19 @@ fn on_packet_acked(
 }
 
 fn congestion_event(
-    r: &mut Congestion, _bytes_in_flight: usize, _lost_bytes: usize,
+    r: &mut Congestion, bytes_in_flight: usize, _lost_bytes: usize,
     largest_lost_pkt: &Sent, now: Instant,
+) {
+    on_congestion_event(r, bytes_in_flight, largest_lost_pkt.time_sent, now);
+}
+
+/// Reacts to a congestion signal (a loss or an ECN-CE mark) on a packet sent
+/// at `time_sent`.
+fn on_congestion_event(
+    r: &mut Congestion, _bytes_in_flight: usize, time_sent: Instant, now: Instant,
 ) {
     // Start a new congestion event if packet was sent after the
     // start of the previous congestion recovery period.
-    let time_sent = largest_lost_pkt.time_sent;
-
     if !r.in_congestion_recovery(time_sent) {
         r.congestion_recovery_start_time = Some(now);
 
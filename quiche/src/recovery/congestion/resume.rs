@@ -0,0 +1,561 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Careful Resume.
+//!
+//! This implements [Careful Resume], which lets a new connection reuse the
+//! capacity measured on a path by a previous connection to the same peer,
+//! instead of starting from the initial congestion window.
+//!
+//! The sender first observes the path using the normal congestion controller
+//! (Reconnaissance), then jumps to half of the saved capacity (Unvalidated)
+//! and checks that the jump did not cause congestion (Validating). If it did,
+//! the congestion window is reduced to what was actually delivered (Safe
+//! Retreat).
+//!
+//! [Careful Resume]: https://datatracker.ietf.org/doc/draft-ietf-tsvwg-careful-resume/
+
+use std::cmp;
+use std::time::Duration;
+use std::time::Instant;
+
+use super::rtt::RttStats;
+use super::Congestion;
+use crate::recovery::MINIMUM_WINDOW_PACKETS;
+use crate::Error;
+use crate::Result;
+
+/// The version of the [`PathCapacityRecord`] encoding.
+const RECORD_VERSION: u64 = 1;
+
+/// The characteristics of a path measured by a previous connection.
+///
+/// A record can be obtained from a connection with
+/// [`Connection::path_capacity_record()`], stored alongside the session ticket
+/// using [`to_bytes()`], and used to seed a later connection to the same peer
+/// with [`Connection::set_path_capacity_record()`].
+///
+/// [`Connection::path_capacity_record()`]: struct.Connection.html#method.path_capacity_record
+/// [`Connection::set_path_capacity_record()`]: struct.Connection.html#method.set_path_capacity_record
+/// [`to_bytes()`]: struct.PathCapacityRecord.html#method.to_bytes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PathCapacityRecord {
+    /// The congestion window, in bytes.
+    pub cwnd: usize,
+
+    /// The minimum round-trip time.
+    pub min_rtt: Duration,
+
+    /// The delivery rate, in bytes per second, or 0 if unknown.
+    pub bandwidth: u64,
+}
+
+impl PathCapacityRecord {
+    /// Serializes the record.
+    ///
+    /// The minimum RTT is encoded with a microsecond precision.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = [0; 32];
+        let mut b = octets::OctetsMut::with_slice(&mut buf);
+
+        // The buffer is large enough for four varints.
+        b.put_varint(RECORD_VERSION).unwrap();
+        b.put_varint(self.cwnd as u64).unwrap();
+        b.put_varint(self.min_rtt.as_micros() as u64).unwrap();
+        b.put_varint(self.bandwidth).unwrap();
+
+        let len = b.off();
+        buf[..len].to_vec()
+    }
+
+    /// Deserializes a record serialized with [`to_bytes()`].
+    ///
+    /// [`to_bytes()`]: struct.PathCapacityRecord.html#method.to_bytes
+    pub fn from_bytes(buf: &[u8]) -> Result<Self> {
+        let mut b = octets::Octets::with_slice(buf);
+
+        if b.get_varint()? != RECORD_VERSION {
+            return Err(Error::UnknownVersion);
+        }
+
+        Ok(PathCapacityRecord {
+            cwnd: b.get_varint()? as usize,
+            min_rtt: Duration::from_micros(b.get_varint()?),
+            bandwidth: b.get_varint()?,
+        })
+    }
+
+    /// The capacity of the path in bytes, that is, the congestion window,
+    /// capped by the bandwidth-delay product when the bandwidth is known.
+    fn capacity(&self) -> usize {
+        if self.bandwidth == 0 {
+            return self.cwnd;
+        }
+
+        let bdp = self.bandwidth as f64 * self.min_rtt.as_secs_f64();
+
+        self.cwnd.min(bdp as usize)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Phase {
+    /// Careful Resume is not in use.
+    #[default]
+    Normal,
+
+    /// The path is being observed before using the saved capacity.
+    Reconnaissance,
+
+    /// The congestion window was set to the jump window, and packets sent in
+    /// this phase are not yet acknowledged.
+    Unvalidated,
+
+    /// Waiting for the acknowledgement of the last packet sent in the
+    /// Unvalidated phase.
+    Validating,
+
+    /// Congestion was detected after the jump, waiting for the
+    /// acknowledgement of the last packet sent in the Unvalidated phase.
+    SafeRetreat,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct State {
+    phase: Phase,
+
+    /// The capacity of the path saved by a previous connection.
+    saved_cwnd: usize,
+
+    /// The minimum RTT of the path saved by a previous connection.
+    saved_rtt: Duration,
+
+    /// The congestion window used in the Unvalidated phase.
+    jump_cwnd: usize,
+
+    /// The number of bytes acknowledged since the start of the Unvalidated
+    /// phase, plus the bytes in flight at that time.
+    pipesize: usize,
+
+    /// The time the Unvalidated phase started.
+    unvalidated_start: Option<Instant>,
+
+    /// The time the last packet was sent in the Unvalidated phase.
+    last_unvalidated_sent: Option<Instant>,
+}
+
+impl State {
+    pub(crate) fn new(record: &PathCapacityRecord) -> Self {
+        State {
+            phase: Phase::Reconnaissance,
+            saved_cwnd: record.capacity(),
+            saved_rtt: record.min_rtt,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn phase(&self) -> Phase {
+        self.phase
+    }
+}
+
+/// Called when an in-flight packet is sent at `time_sent`.
+pub(crate) fn on_packet_sent(
+    r: &mut Congestion, sent_bytes: usize, bytes_in_flight: usize,
+    time_sent: Instant,
+) {
+    if r.resume_state.phase != Phase::Unvalidated {
+        return;
+    }
+
+    r.resume_state.unvalidated_start.get_or_insert(time_sent);
+    r.resume_state.last_unvalidated_sent = Some(time_sent);
+
+    // The whole jump window was used.
+    if bytes_in_flight + sent_bytes >= r.resume_state.jump_cwnd {
+        r.resume_state.phase = Phase::Validating;
+    }
+}
+
+/// Called after the congestion controller processed newly acknowledged
+/// packets, with the total size and the largest send time of those packets.
+///
+/// `bytes_in_flight` excludes the newly acknowledged packets.
+pub(crate) fn on_packets_acked(
+    r: &mut Congestion, bytes_in_flight: usize, acked_bytes: usize,
+    largest_time_sent: Instant, rtt_stats: &RttStats,
+) {
+    match r.resume_state.phase {
+        Phase::Normal => (),
+
+        Phase::Reconnaissance => maybe_jump(r, bytes_in_flight, rtt_stats),
+
+        Phase::Unvalidated => {
+            r.resume_state.pipesize += acked_bytes;
+
+            // The congestion window does not grow until the jump is
+            // validated.
+            r.congestion_window = r.resume_state.jump_cwnd;
+
+            // The first packets sent after the jump are acknowledged, limit the
+            // congestion window to what is actually used.
+            if is_unvalidated(r, largest_time_sent) {
+                r.congestion_window = cmp::max(
+                    cmp::max(bytes_in_flight, r.resume_state.pipesize),
+                    r.max_datagram_size * r.initial_congestion_window_packets,
+                );
+                r.resume_state.phase = Phase::Validating;
+            }
+        },
+
+        Phase::Validating => {
+            r.resume_state.pipesize += acked_bytes;
+
+            if is_last_unvalidated(r, largest_time_sent) {
+                r.resume_state.phase = Phase::Normal;
+            }
+        },
+
+        Phase::SafeRetreat => {
+            r.resume_state.pipesize += acked_bytes;
+
+            if is_last_unvalidated(r, largest_time_sent) {
+                r.ssthresh.update(r.resume_state.pipesize, false);
+                r.resume_state.phase = Phase::Normal;
+            }
+        },
+    }
+}
+
+/// Called after the congestion controller reacted to a loss or an ECN-CE
+/// mark.
+pub(crate) fn on_congestion_event(r: &mut Congestion) {
+    match r.resume_state.phase {
+        Phase::Normal | Phase::SafeRetreat => (),
+
+        // The path changed, don't use the saved capacity.
+        Phase::Reconnaissance => r.resume_state.phase = Phase::Normal,
+
+        Phase::Unvalidated | Phase::Validating => {
+            r.congestion_window = cmp::max(
+                r.resume_state.pipesize / 2,
+                r.max_datagram_size * MINIMUM_WINDOW_PACKETS,
+            );
+            r.ssthresh.update(r.congestion_window, false);
+
+            r.resume_state.phase =
+                if r.resume_state.last_unvalidated_sent.is_some() {
+                    Phase::SafeRetreat
+                } else {
+                    Phase::Normal
+                };
+        },
+    }
+}
+
+/// The pacing rate to use instead of the congestion controller's one, in
+/// bytes per second.
+pub(crate) fn pacing_rate(r: &Congestion) -> Option<u64> {
+    if r.resume_state.phase != Phase::Unvalidated {
+        return None;
+    }
+
+    Some(
+        (r.resume_state.jump_cwnd as f64 / r.resume_state.saved_rtt.as_secs_f64())
+            as u64,
+    )
+}
+
+fn maybe_jump(r: &mut Congestion, bytes_in_flight: usize, rtt_stats: &RttStats) {
+    // Wait until the sender is limited by the congestion window.
+    if r.app_limited {
+        return;
+    }
+
+    let saved_rtt = r.resume_state.saved_rtt;
+    let min_rtt = *rtt_stats.min_rtt;
+
+    // The RTT is too different from the saved one for the saved capacity to
+    // be relevant.
+    if min_rtt < saved_rtt / 2 || min_rtt > saved_rtt * 10 {
+        r.resume_state.phase = Phase::Normal;
+        return;
+    }
+
+    let jump_cwnd = r.resume_state.saved_cwnd / 2;
+
+    if jump_cwnd <= r.congestion_window {
+        r.resume_state.phase = Phase::Normal;
+        return;
+    }
+
+    r.congestion_window = jump_cwnd;
+
+    r.resume_state.jump_cwnd = jump_cwnd;
+    r.resume_state.pipesize = bytes_in_flight;
+    r.resume_state.phase = Phase::Unvalidated;
+}
+
+/// Whether a packet sent at `time_sent` was sent in the Unvalidated phase.
+fn is_unvalidated(r: &Congestion, time_sent: Instant) -> bool {
+    r.resume_state
+        .unvalidated_start
+        .is_some_and(|start| time_sent >= start)
+}
+
+/// Whether a packet sent at `time_sent` was sent after the last packet sent in
+/// the Unvalidated phase.
+fn is_last_unvalidated(r: &Congestion, time_sent: Instant) -> bool {
+    r.resume_state
+        .last_unvalidated_sent
+        .is_none_or(|last| time_sent >= last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::CongestionControlAlgorithm;
+
+    use crate::recovery::congestion::test_sender::TestSender;
+
+    fn test_sender(saved_rtt: Duration) -> TestSender {
+        let mut sender =
+            TestSender::new(CongestionControlAlgorithm::CUBIC, false);
+        let size = sender.max_datagram_size;
+
+        let record = PathCapacityRecord {
+            cwnd: size * 100,
+            min_rtt: saved_rtt,
+            bandwidth: 0,
+        };
+
+        assert_eq!(sender.setup_careful_resume(&record), Ok(()));
+        assert_eq!(sender.resume_state.phase(), Phase::Reconnaissance);
+
+        sender.update_rtt(Duration::from_millis(50));
+
+        sender
+    }
+
+    /// Sends and acknowledges the initial window, which triggers the jump.
+    fn jump(sender: &mut TestSender) {
+        let size = sender.max_datagram_size;
+
+        for _ in 0..sender.initial_congestion_window_packets {
+            sender.send_packet(size);
+        }
+
+        sender.advance_time(Duration::from_millis(50));
+        sender.ack_n_packets(sender.initial_congestion_window_packets, size);
+
+        assert_eq!(sender.resume_state.phase(), Phase::Unvalidated);
+        assert_eq!(sender.congestion_window, size * 50);
+    }
+
+    #[test]
+    fn record_encoding() {
+        let record = PathCapacityRecord {
+            cwnd: 1_200_000,
+            min_rtt: Duration::from_micros(23_456),
+            bandwidth: 25_000_000,
+        };
+
+        let buf = record.to_bytes();
+        assert_eq!(PathCapacityRecord::from_bytes(&buf), Ok(record));
+
+        assert_eq!(
+            PathCapacityRecord::from_bytes(&buf[..buf.len() - 1]),
+            Err(Error::BufferTooShort)
+        );
+
+        let mut buf = buf;
+        buf[0] = 2;
+        assert_eq!(
+            PathCapacityRecord::from_bytes(&buf),
+            Err(Error::UnknownVersion)
+        );
+    }
+
+    #[test]
+    fn record_capacity() {
+        let record = PathCapacityRecord {
+            cwnd: 1_000_000,
+            min_rtt: Duration::from_millis(100),
+            bandwidth: 0,
+        };
+        assert_eq!(record.capacity(), 1_000_000);
+
+        // The bandwidth-delay product is smaller than the congestion window.
+        let record = PathCapacityRecord {
+            bandwidth: 5_000_000,
+            ..record
+        };
+        assert_eq!(record.capacity(), 500_000);
+    }
+
+    #[test]
+    fn unsupported_congestion_control() {
+        let mut sender = TestSender::new(CongestionControlAlgorithm::BBR2, false);
+
+        let record = PathCapacityRecord {
+            cwnd: 1_000_000,
+            min_rtt: Duration::from_millis(100),
+            bandwidth: 0,
+        };
+
+        assert_eq!(
+            sender.setup_careful_resume(&record),
+            Err(Error::CongestionControl)
+        );
+        assert_eq!(sender.resume_state.phase(), Phase::Normal);
+    }
+
+    #[test]
+    fn validated_jump() {
+        let mut sender = test_sender(Duration::from_millis(40));
+        let size = sender.max_datagram_size;
+
+        jump(&mut sender);
+
+        // Use the whole jump window.
+        for _ in 0..50 {
+            sender.send_packet(size);
+        }
+
+        assert_eq!(sender.resume_state.phase(), Phase::Validating);
+        assert_eq!(sender.congestion_window, size * 50);
+
+        sender.advance_time(Duration::from_millis(50));
+        sender.ack_n_packets(50, size);
+
+        assert_eq!(sender.resume_state.phase(), Phase::Normal);
+        assert!(sender.congestion_window > size * 50);
+    }
+
+    #[test]
+    fn partially_used_jump() {
+        let mut sender = test_sender(Duration::from_millis(40));
+        let size = sender.max_datagram_size;
+
+        jump(&mut sender);
+
+        for _ in 0..40 {
+            sender.send_packet(size);
+        }
+
+        assert_eq!(sender.resume_state.phase(), Phase::Unvalidated);
+
+        // The congestion window doesn't grow until the jump is validated, and
+        // is then reduced to the flight size.
+        sender.advance_time(Duration::from_millis(50));
+        sender.ack_n_packets(1, size);
+
+        assert_eq!(sender.resume_state.phase(), Phase::Validating);
+        assert_eq!(sender.congestion_window, size * 39);
+
+        sender.ack_n_packets(39, size);
+
+        assert_eq!(sender.resume_state.phase(), Phase::Normal);
+    }
+
+    #[test]
+    fn safe_retreat() {
+        let mut sender = test_sender(Duration::from_millis(40));
+        let size = sender.max_datagram_size;
+
+        jump(&mut sender);
+
+        for _ in 0..30 {
+            sender.send_packet(size);
+        }
+
+        sender.advance_time(Duration::from_millis(50));
+        sender.ack_n_packets(20, size);
+
+        assert_eq!(sender.resume_state.phase(), Phase::Validating);
+
+        // The jump caused congestion.
+        sender.lose_n_packets(10, size, None);
+
+        assert_eq!(sender.resume_state.phase(), Phase::SafeRetreat);
+        assert_eq!(sender.congestion_window, size * 10);
+        assert_eq!(sender.ssthresh.get(), size * 10);
+
+        // The retreat ends when packets sent after the jump are acknowledged.
+        sender.send_packet(size);
+        sender.advance_time(Duration::from_millis(50));
+        sender.ack_n_packets(1, size);
+
+        assert_eq!(sender.resume_state.phase(), Phase::Normal);
+        assert_eq!(sender.ssthresh.get(), size * 21);
+    }
+
+    #[test]
+    fn rtt_mismatch() {
+        let mut sender = test_sender(Duration::from_millis(500));
+        let size = sender.max_datagram_size;
+
+        for _ in 0..sender.initial_congestion_window_packets {
+            sender.send_packet(size);
+        }
+
+        sender.advance_time(Duration::from_millis(50));
+        sender.ack_n_packets(sender.initial_congestion_window_packets, size);
+
+        // The saved capacity is ignored.
+        assert_eq!(sender.resume_state.phase(), Phase::Normal);
+        assert!(sender.congestion_window < size * 50);
+    }
+
+    #[test]
+    fn congestion_in_reconnaissance() {
+        let mut sender = test_sender(Duration::from_millis(40));
+        let size = sender.max_datagram_size;
+
+        for _ in 0..sender.initial_congestion_window_packets {
+            sender.send_packet(size);
+        }
+
+        sender.lose_n_packets(1, size, None);
+
+        assert_eq!(sender.resume_state.phase(), Phase::Normal);
+    }
+
+    #[test]
+    fn app_limited() {
+        let mut sender = test_sender(Duration::from_millis(40));
+        let size = sender.max_datagram_size;
+
+        sender.send_packet(size);
+        sender.advance_time(Duration::from_millis(50));
+        sender.ack_n_packets(1, size);
+
+        // Wait until the sender is limited by the congestion window.
+        assert_eq!(sender.resume_state.phase(), Phase::Reconnaissance);
+    }
+}
@@ -86,6 +86,7 @@ impl TestSender {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         self.cc.on_packet_sent(
@@ -173,6 +174,8 @@ impl TestSender {
             self.time,
         );
 
+        self.cc.on_congestion_event();
+
         self.cc.lost_count += n;
         self.bytes_in_flight -= n * bytes;
     }
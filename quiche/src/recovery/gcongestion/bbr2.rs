@@ -41,7 +41,10 @@ use std::time::Instant;
 use network_model::BBRv2NetworkModel;
 
 use crate::recovery::gcongestion::Bandwidth;
+use crate::recovery::AckFrequency;
 use crate::recovery::RecoveryStats;
+use crate::recovery::GRANULARITY;
+use crate::recovery::INITIAL_PACKET_THRESHOLD;
 
 use self::mode::Mode;
 use self::mode::ModeImpl;
@@ -56,6 +59,16 @@ use super::RttStats;
 
 const MAX_MODE_CHANGES_PER_CONGESTION_EVENT: usize = 4;
 
+/// The bandwidth estimate above which the peer is asked to send fewer ACKs.
+const ACK_FREQUENCY_MIN_BANDWIDTH: Bandwidth =
+    Bandwidth::from_mbits_per_second(100);
+
+/// The number of ACKs per round trip the peer is asked to send at least.
+const ACK_FREQUENCY_ACKS_PER_RTT: usize = 4;
+
+/// The largest ack-eliciting threshold requested from the peer.
+const ACK_FREQUENCY_MAX_THRESHOLD: u64 = 10;
+
 #[derive(Debug)]
 struct Params {
     // STARTUP parameters.
@@ -99,6 +112,11 @@ struct Params {
     /// Minimum duration for BBR-native probes.
     probe_bw_probe_base_duration: Duration,
 
+    /// Upper bound of the random duration added to
+    /// `probe_bw_probe_base_duration`, to desynchronize the probes of flows
+    /// sharing a bottleneck.
+    probe_bw_probe_rand_duration: Duration,
+
     /// The minimum number of loss marking events to exit the PROBE_UP phase.
     probe_bw_full_loss_count: usize,
 
@@ -110,6 +128,9 @@ struct Params {
     probe_bw_cwnd_gain: f32,
 
     // PROBE_UP parameters.
+    /// The gain for CWND in PROBE_UP. Uses `probe_bw_cwnd_gain` if unset.
+    probe_bw_up_cwnd_gain: Option<f32>,
+
     probe_up_ignore_inflight_hi: bool,
 
     /// Number of rounds to stay in PROBE_UP when there's a sufficient queue
@@ -141,6 +162,24 @@ struct Params {
     /// `bandwidth_lo``, `inflight_lo`` and `inflight_hi`` upon losses.
     beta: f32,
 
+    // ECN parameters.
+    /// The gain of the moving average of the fraction of CE-marked bytes per
+    /// round. Zero disables the BBRv3 ECN response, and CE-marked bytes are
+    /// accounted as lost instead.
+    ecn_alpha_gain: f32,
+
+    /// The largest fraction of `inflight_lo` cut per round, scaled by the
+    /// average fraction of CE-marked bytes.
+    ecn_factor: f32,
+
+    /// Estimate startup/bw probing has gone too far if the fraction of
+    /// CE-marked bytes in a round exceeds this.
+    ecn_threshold: f32,
+
+    /// The number of consecutive rounds with too many CE-marked bytes to exit
+    /// STARTUP.
+    startup_full_ecn_count: usize,
+
     // Experimental flags.
     add_ack_height_to_queueing_threshold: bool,
 
@@ -202,6 +241,14 @@ impl Params {
         apply_override!(use_bytes_delivered_for_inflight_hi);
         apply_override!(decrease_startup_pacing_at_end_of_round);
         apply_override!(ignore_app_limited_for_no_bandwidth_growth);
+        apply_override!(ecn_alpha_gain);
+        apply_override!(ecn_factor);
+        apply_override!(ecn_threshold);
+        apply_override!(startup_full_ecn_count);
+
+        if let Some(custom_value) = custom_bbr_settings.probe_bw_up_cwnd_gain {
+            self.probe_bw_up_cwnd_gain = Some(custom_value);
+        }
 
         if let Some(custom_value) = custom_bbr_settings.bw_lo_reduction_strategy {
             self.bw_lo_mode = custom_value.into();
@@ -236,6 +283,8 @@ const DEFAULT_PARAMS: Params = Params {
 
     probe_bw_probe_base_duration: Duration::from_millis(2000),
 
+    probe_bw_probe_rand_duration: Duration::ZERO,
+
     probe_bw_full_loss_count: 2,
 
     probe_bw_probe_up_pacing_gain: 1.25,
@@ -246,6 +295,8 @@ const DEFAULT_PARAMS: Params = Params {
 
     probe_bw_cwnd_gain: 2.25, // BBRv3
 
+    probe_bw_up_cwnd_gain: None,
+
     probe_up_ignore_inflight_hi: false,
 
     max_probe_up_queue_rounds: 2,
@@ -264,6 +315,14 @@ const DEFAULT_PARAMS: Params = Params {
 
     beta: 0.3,
 
+    ecn_alpha_gain: 0.0,
+
+    ecn_factor: 0.0,
+
+    ecn_threshold: 0.5,
+
+    startup_full_ecn_count: 2,
+
     add_ack_height_to_queueing_threshold: false,
 
     avoid_unnecessary_probe_rtt: true,
@@ -285,6 +344,33 @@ const DEFAULT_PARAMS: Params = Params {
     ignore_app_limited_for_no_bandwidth_growth: false,
 };
 
+/// BBRv3 parameters, from
+/// <https://datatracker.ietf.org/doc/html/draft-ietf-ccwg-bbr> and the Linux
+/// TCP implementation.
+///
+/// Compared to the BBRv2 parameters, BBRv3 exits STARTUP after fewer loss
+/// events, tolerates a slightly higher loss rate, only uses the larger cwnd
+/// gain while probing for bandwidth, randomizes the time between probes, and
+/// responds to ECN marks proportionally to their frequency instead of treating
+/// them as losses.
+const BBRV3_PARAMS: Params = Params {
+    startup_full_loss_count: 6,
+
+    probe_bw_probe_rand_duration: Duration::from_millis(1000),
+
+    probe_bw_cwnd_gain: 2.0,
+
+    probe_bw_up_cwnd_gain: Some(2.25),
+
+    loss_threshold: 0.02,
+
+    ecn_alpha_gain: 1.0 / 16.0,
+
+    ecn_factor: 1.0 / 3.0,
+
+    ..DEFAULT_PARAMS
+};
+
 #[derive(Debug, PartialEq)]
 enum BwLoMode {
     Default,
@@ -410,12 +496,44 @@ impl BBRv2 {
         initial_congestion_window: usize, max_congestion_window: usize,
         max_segment_size: usize, smoothed_rtt: Duration,
         custom_bbr_params: Option<&BbrParams>,
+    ) -> Self {
+        Self::with_params(
+            DEFAULT_PARAMS,
+            initial_congestion_window,
+            max_congestion_window,
+            max_segment_size,
+            smoothed_rtt,
+            custom_bbr_params,
+        )
+    }
+
+    /// Creates a BBRv3 sender, which shares the BBRv2 state machine with
+    /// different parameters.
+    pub fn new_v3(
+        initial_congestion_window: usize, max_congestion_window: usize,
+        max_segment_size: usize, smoothed_rtt: Duration,
+        custom_bbr_params: Option<&BbrParams>,
+    ) -> Self {
+        Self::with_params(
+            BBRV3_PARAMS,
+            initial_congestion_window,
+            max_congestion_window,
+            max_segment_size,
+            smoothed_rtt,
+            custom_bbr_params,
+        )
+    }
+
+    fn with_params(
+        params: Params, initial_congestion_window: usize,
+        max_congestion_window: usize, max_segment_size: usize,
+        smoothed_rtt: Duration, custom_bbr_params: Option<&BbrParams>,
     ) -> Self {
         let cwnd = initial_congestion_window * max_segment_size;
         let params = if let Some(custom_bbr_settings) = custom_bbr_params {
-            DEFAULT_PARAMS.with_overrides(custom_bbr_settings)
+            params.with_overrides(custom_bbr_settings)
         } else {
-            DEFAULT_PARAMS
+            params
         };
 
         BBRv2 {
@@ -663,4 +781,444 @@ impl CongestionControl for BBRv2 {
     fn limit_cwnd(&mut self, max_cwnd: usize) {
         self.cwnd_limits.hi = max_cwnd
     }
+
+    fn on_ecn_ce(&mut self, ce_count: u64, _event_time: Instant) {
+        self.mode
+            .on_ecn_ce(ce_count as usize * self.mss, &self.params);
+    }
+
+    fn ack_frequency(&self) -> Option<AckFrequency> {
+        let bandwidth = self.mode.bandwidth_estimate();
+
+        // Keep the default frequency while the bandwidth is still being
+        // discovered, or when it is too low for ACKs to matter.
+        if matches!(self.mode, Mode::Startup(_)) ||
+            bandwidth < ACK_FREQUENCY_MIN_BANDWIDTH
+        {
+            return None;
+        }
+
+        // Still get a few ACKs per round trip, so that bandwidth samples and
+        // loss detection remain timely.
+        let bdp_packets = self.mode.bdp1(bandwidth) / self.mss;
+
+        let threshold = ((bdp_packets / ACK_FREQUENCY_ACKS_PER_RTT) as u64)
+            .min(ACK_FREQUENCY_MAX_THRESHOLD);
+
+        if threshold <= 1 {
+            return None;
+        }
+
+        // Round the delay to the millisecond, so that small variations of the
+        // min RTT don't cause new requests.
+        let max_ack_delay =
+            self.mode.min_rtt() / ACK_FREQUENCY_ACKS_PER_RTT as u32;
+        let max_ack_delay =
+            Duration::from_millis(max_ack_delay.as_millis() as u64)
+                .max(GRANULARITY);
+
+        Some(AckFrequency {
+            ack_eliciting_threshold: threshold,
+            max_ack_delay,
+            reordering_threshold: INITIAL_PACKET_THRESHOLD,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::recovery::gcongestion::bbr2::mode::CyclePhase;
+    use crate::recovery::gcongestion::bbr2::probe_bw::ProbeBW;
+    use crate::recovery::StartupExitReason;
+
+    const MSS: usize = 1200;
+
+    const RTT: Duration = Duration::from_millis(100);
+
+    /// Time for a packet to leave the bottleneck, i.e. a 9.6 Mbps link with a
+    /// BDP of 100 packets.
+    const SERIALIZATION_TIME: Duration = Duration::from_millis(1);
+
+    /// Drives a sender through a single bottleneck link.
+    struct TestSender {
+        bbr: BBRv2,
+        rtt_stats: RttStats,
+        recovery_stats: RecoveryStats,
+        now: Instant,
+        next_pkt_num: u64,
+    }
+
+    impl TestSender {
+        fn new(bbr: BBRv2) -> Self {
+            TestSender {
+                bbr,
+                rtt_stats: RttStats::new(RTT, Duration::ZERO),
+                recovery_stats: RecoveryStats::default(),
+                now: Instant::now(),
+                next_pkt_num: 0,
+            }
+        }
+
+        /// Sends a congestion window worth of packets at once, and receives
+        /// their ACKs as they leave the bottleneck. Packets of the round whose
+        /// index is in `lost` are declared lost by the following ACK, and the
+        /// first `ce` acknowledged packets are reported as CE-marked.
+        fn round(&mut self, lost: &[usize], ce: usize) {
+            let count = self.bbr.get_congestion_window() / MSS;
+            let first = self.next_pkt_num;
+            let sent_time = self.now;
+
+            for i in 0..count {
+                self.bbr.on_packet_sent(
+                    sent_time,
+                    i * MSS,
+                    first + i as u64,
+                    MSS,
+                    true,
+                    &self.rtt_stats,
+                );
+            }
+
+            self.next_pkt_num += count as u64;
+
+            let mut bytes_in_flight = count * MSS;
+            let mut lost_packets = Vec::new();
+            let mut ce = ce;
+
+            for i in 0..count {
+                let pkt_num = first + i as u64;
+
+                self.now = sent_time + RTT + SERIALIZATION_TIME * (i as u32 + 1);
+
+                if lost.contains(&i) {
+                    lost_packets.push(Lost {
+                        packet_number: pkt_num,
+                        bytes_lost: MSS,
+                    });
+                    continue;
+                }
+
+                let acked = [Acked {
+                    pkt_num,
+                    time_sent: sent_time,
+                }];
+
+                let prior_in_flight = bytes_in_flight;
+                bytes_in_flight -= MSS * (1 + lost_packets.len());
+
+                self.bbr.on_congestion_event(
+                    true,
+                    prior_in_flight,
+                    bytes_in_flight,
+                    self.now,
+                    &acked,
+                    &lost_packets,
+                    pkt_num + 1,
+                    &self.rtt_stats,
+                    &mut self.recovery_stats,
+                );
+
+                lost_packets.clear();
+
+                if ce > 0 {
+                    self.bbr.on_ecn_ce(1, self.now);
+                    ce -= 1;
+                }
+            }
+
+            if !lost_packets.is_empty() {
+                self.bbr.on_congestion_event(
+                    false,
+                    bytes_in_flight,
+                    0,
+                    self.now,
+                    &[],
+                    &lost_packets,
+                    self.next_pkt_num,
+                    &self.rtt_stats,
+                    &mut self.recovery_stats,
+                );
+            }
+        }
+
+        /// Runs rounds without losses until PROBE_BW starts cruising.
+        fn cruise(&mut self) {
+            for _ in 0..20 {
+                self.round(&[], 0);
+
+                if let Mode::ProbeBW(probe_bw) = &self.bbr.mode {
+                    if probe_bw.cycle.phase == CyclePhase::Cruise {
+                        return;
+                    }
+                }
+            }
+
+            panic!("didn't reach PROBE_CRUISE: {:?}", self.bbr.mode);
+        }
+
+        /// Runs rounds without losses until PROBE_BW enters `phase`.
+        fn rounds_until(&mut self, phase: CyclePhase) {
+            for _ in 0..100 {
+                self.round(&[], 0);
+
+                if self.probe_bw().cycle.phase == phase {
+                    return;
+                }
+            }
+
+            panic!("didn't reach {phase:?}: {:?}", self.bbr.mode);
+        }
+
+        fn probe_bw(&self) -> &ProbeBW {
+            match &self.bbr.mode {
+                Mode::ProbeBW(probe_bw) => probe_bw,
+                mode => panic!("not in PROBE_BW: {mode:?}"),
+            }
+        }
+    }
+
+    fn bbr2() -> TestSender {
+        TestSender::new(BBRv2::new(10, 1000, MSS, RTT, None))
+    }
+
+    fn bbr3() -> TestSender {
+        TestSender::new(BBRv2::new_v3(10, 1000, MSS, RTT, None))
+    }
+
+    #[test]
+    fn bbr3_startup_gain() {
+        let mut s = bbr3();
+
+        let mut prior_cwnd = s.bbr.get_congestion_window();
+
+        for _ in 0..4 {
+            s.round(&[], 0);
+
+            assert!(matches!(s.bbr.mode, Mode::Startup(_)));
+            assert_eq!(s.bbr.mode.pacing_gain(), 2.773);
+            assert_eq!(s.bbr.mode.cwnd_gain(), 2.0);
+
+            // The pacing rate is the bandwidth estimate scaled by the STARTUP
+            // gain, and the window keeps growing.
+            assert_eq!(
+                s.bbr.pacing_rate,
+                s.bbr.mode.bandwidth_estimate() * 2.773
+            );
+            assert!(s.bbr.get_congestion_window() > prior_cwnd);
+
+            prior_cwnd = s.bbr.get_congestion_window();
+        }
+
+        assert_eq!(s.recovery_stats.startup_exit, None);
+    }
+
+    #[test]
+    fn bbr3_startup_exit_on_loss() {
+        let mut v2 = bbr2();
+        let mut v3 = bbr3();
+
+        // Let bandwidth growth slow down, so that losses are checked.
+        for _ in 0..6 {
+            v2.round(&[], 0);
+            v3.round(&[], 0);
+        }
+
+        // Seven loss events, which is enough for BBRv3 to leave STARTUP, but
+        // not BBRv2.
+        let lost = [10, 20, 30, 40, 50, 60, 70];
+
+        for s in [&mut v2, &mut v3] {
+            s.round(&lost, 0);
+            s.round(&[], 0);
+        }
+
+        assert!(matches!(v2.bbr.mode, Mode::Startup(_)));
+        assert_eq!(v2.recovery_stats.startup_exit, None);
+
+        assert!(!matches!(v3.bbr.mode, Mode::Startup(_)));
+        assert_eq!(
+            v3.recovery_stats.startup_exit.map(|e| e.reason),
+            Some(StartupExitReason::Loss)
+        );
+    }
+
+    #[test]
+    fn bbr3_probe_cadence() {
+        let mut s = bbr3();
+        s.cruise();
+
+        let probe_wait_time = s.probe_bw().cycle.probe_wait_time.unwrap();
+
+        assert!(probe_wait_time >= Duration::from_secs(2));
+        assert!(probe_wait_time < Duration::from_secs(3));
+
+        // BBRv3 only uses the higher cwnd gain while probing for bandwidth.
+        assert_eq!(s.bbr.mode.cwnd_gain(), 2.0);
+
+        let cycle_start = s.probe_bw().cycle.start_time;
+        let mut round_end = s.now;
+
+        while s.probe_bw().cycle.phase == CyclePhase::Cruise {
+            round_end = s.now;
+            s.round(&[], 0);
+        }
+
+        // Bandwidth is probed once the probe wait time has elapsed, and not
+        // before.
+        assert_eq!(s.probe_bw().cycle.phase, CyclePhase::Refill);
+        assert!(round_end - cycle_start <= probe_wait_time);
+        assert!(s.now - cycle_start > probe_wait_time);
+
+        s.rounds_until(CyclePhase::Up);
+
+        assert_eq!(s.bbr.mode.pacing_gain(), 1.25);
+        assert_eq!(s.bbr.mode.cwnd_gain(), 2.25);
+
+        // BBRv2 always waits for the same duration, with the same cwnd gain
+        // in every phase.
+        let mut s = bbr2();
+        s.cruise();
+
+        assert_eq!(
+            s.probe_bw().cycle.probe_wait_time,
+            Some(Duration::from_micros(2_000_500))
+        );
+        assert_eq!(s.bbr.mode.cwnd_gain(), 2.25);
+    }
+
+    #[test]
+    fn bbr3_probe_wait_time_randomized() {
+        let mut probe_wait_times = Vec::new();
+
+        for _ in 0..10 {
+            let mut s = bbr3();
+            s.cruise();
+
+            probe_wait_times.push(s.probe_bw().cycle.probe_wait_time.unwrap());
+        }
+
+        probe_wait_times.sort();
+        probe_wait_times.dedup();
+
+        assert!(probe_wait_times.len() > 1);
+    }
+
+    #[test]
+    fn bbr3_loss_in_probe_up() {
+        let mut v2 = bbr2();
+        let mut v3 = bbr3();
+
+        for s in [&mut v2, &mut v3] {
+            s.cruise();
+            s.rounds_until(CyclePhase::Up);
+        }
+
+        // About 1.8% of a round's packets are lost, which is more than the
+        // BBRv2 loss threshold, but less than the BBRv3 one.
+        let lost = [210, 212, 214, 216];
+
+        let v2_cwnd = v2.bbr.get_congestion_window();
+        let v3_cwnd = v3.bbr.get_congestion_window();
+        let v3_inflight_hi = v3.bbr.mode.inflight_hi();
+
+        v2.round(&lost, 0);
+        v3.round(&lost, 0);
+
+        // The probe stopped, and inflight is bounded.
+        assert_ne!(v2.probe_bw().cycle.phase, CyclePhase::Up);
+        assert!(v2.probe_bw().cycle.last_cycle_probed_too_high);
+        assert!(v2.bbr.mode.inflight_hi() < v2_cwnd);
+        assert!(v2.bbr.get_congestion_window() < v2_cwnd);
+
+        // BBRv3 keeps probing.
+        assert_eq!(v3.probe_bw().cycle.phase, CyclePhase::Up);
+        assert_eq!(v3.bbr.mode.inflight_hi(), v3_inflight_hi);
+
+        // Higher losses stop BBRv3's probe too, and bound inflight.
+        v3.round(&[10, 20, 30, 40, 50, 60], 0);
+
+        assert_ne!(v3.probe_bw().cycle.phase, CyclePhase::Up);
+        assert!(v3.probe_bw().cycle.last_cycle_probed_too_high);
+        assert!(v3.bbr.mode.inflight_hi() < v3_cwnd);
+        assert!(v3.bbr.get_congestion_window() < v3_cwnd);
+        assert!(v3.bbr.pacing_rate <= v3.bbr.mode.bandwidth_estimate());
+    }
+
+    #[test]
+    fn bbr3_ecn_reduces_inflight() {
+        let mut v2 = bbr2();
+        let mut v3 = bbr3();
+
+        for s in [&mut v2, &mut v3] {
+            s.cruise();
+            s.round(&[], 0);
+        }
+
+        let cwnd = v3.bbr.get_congestion_window();
+
+        // Every packet of the round is CE-marked.
+        v2.round(&[], 1000);
+        v3.round(&[], 1000);
+
+        // BBRv2 accounts CE marks as losses.
+        assert!(v2.bbr.mode.loss_events_in_round() > 0);
+
+        // BBRv3 doesn't, but cuts inflight_lo at the end of the round,
+        // proportionally to the fraction of CE-marked bytes.
+        assert_eq!(v3.bbr.mode.loss_events_in_round(), 0);
+        assert_eq!(v3.bbr.mode.inflight_lo(), usize::MAX);
+
+        v3.round(&[], 0);
+
+        assert_eq!(v3.probe_bw().cycle.phase, CyclePhase::Cruise);
+        assert!(v3.bbr.mode.inflight_lo() < cwnd);
+        assert!(v3.bbr.mode.inflight_lo() > cwnd / 2);
+        assert_eq!(v3.bbr.get_congestion_window(), v3.bbr.mode.inflight_lo());
+
+        // Rounds without CE marks leave inflight_lo alone.
+        let inflight_lo = v3.bbr.mode.inflight_lo();
+
+        v3.round(&[], 0);
+
+        assert_eq!(v3.bbr.mode.inflight_lo(), inflight_lo);
+    }
+
+    #[test]
+    fn bbr3_custom_params() {
+        let custom_bbr_params = BbrParams {
+            startup_full_loss_count: Some(10),
+            probe_bw_up_cwnd_gain: Some(3.0),
+            ecn_alpha_gain: Some(0.0),
+            ..Default::default()
+        };
+
+        let bbr = BBRv2::new_v3(10, 1000, MSS, RTT, Some(&custom_bbr_params));
+
+        // Overridden parameters.
+        assert_eq!(bbr.params.startup_full_loss_count, 10);
+        assert_eq!(bbr.params.probe_bw_up_cwnd_gain, Some(3.0));
+        assert_eq!(bbr.params.ecn_alpha_gain, 0.0);
+
+        // Other parameters keep their BBRv3 values.
+        assert_eq!(bbr.params.probe_bw_cwnd_gain, 2.0);
+        assert_eq!(bbr.params.loss_threshold, 0.02);
+        assert_eq!(bbr.params.ecn_factor, 1.0 / 3.0);
+        assert_eq!(
+            bbr.params.probe_bw_probe_rand_duration,
+            Duration::from_millis(1000)
+        );
+
+        let mut s = TestSender::new(bbr);
+        s.cruise();
+        s.rounds_until(CyclePhase::Up);
+
+        assert_eq!(s.bbr.mode.cwnd_gain(), 3.0);
+
+        // Without the ECN response, CE marks are accounted as losses.
+        s.round(&[], 1000);
+
+        assert!(s.bbr.mode.loss_events_in_round() > 0);
+    }
 }
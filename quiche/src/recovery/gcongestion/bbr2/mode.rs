@@ -65,6 +65,15 @@ impl CyclePhase {
             _ => params.probe_bw_default_pacing_gain,
         }
     }
+
+    pub(super) fn cwnd_gain(&self, params: &Params) -> f32 {
+        match self {
+            CyclePhase::Up => params
+                .probe_bw_up_cwnd_gain
+                .unwrap_or(params.probe_bw_cwnd_gain),
+            _ => params.probe_bw_cwnd_gain,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -144,6 +144,15 @@ pub(super) struct BBRv2NetworkModel {
     bytes_lost_in_round: usize,
     /// Number of loss marking events in the current round.
     loss_events_in_round: usize,
+    /// Bytes acked in the current round.
+    bytes_acked_in_round: usize,
+    /// Bytes reported as CE-marked by the peer in the current round. Only
+    /// used when the BBRv3 ECN response is enabled.
+    ce_bytes_in_round: usize,
+    /// Moving average of the fraction of CE-marked bytes per round.
+    ecn_alpha: f32,
+    /// Number of consecutive rounds in STARTUP with too many CE-marked bytes.
+    rounds_with_high_ecn: usize,
 
     /// A max of bytes delivered among all congestion events in the current
     /// round. A congestions event's bytes delivered is the total bytes
@@ -217,6 +226,10 @@ impl BBRv2NetworkModel {
             full_bandwidth_reached: false,
             bytes_lost_in_round: 0,
             loss_events_in_round: 0,
+            bytes_acked_in_round: 0,
+            ce_bytes_in_round: 0,
+            ecn_alpha: 1.0,
+            rounds_with_high_ecn: 0,
             max_bytes_delivered_in_round: 0,
             bandwidth_latest: Bandwidth::zero(),
             bandwidth_lo: None,
@@ -364,6 +377,8 @@ impl BBRv2NetworkModel {
             self.loss_events_in_round += 1;
         }
 
+        self.bytes_acked_in_round += congestion_event.bytes_acked;
+
         if congestion_event.bytes_acked > 0 &&
             congestion_event.last_packet_send_state.is_valid &&
             self.total_bytes_acked() >
@@ -391,6 +406,7 @@ impl BBRv2NetworkModel {
 
         // Adapt lower bounds(bandwidth_lo and inflight_lo).
         self.adapt_lower_bounds(congestion_event, params);
+        self.adapt_lower_bounds_to_ecn(congestion_event, params);
 
         if !congestion_event.end_of_round_trip {
             return;
@@ -529,6 +545,34 @@ impl BBRv2NetworkModel {
         // These modes ignore inflight_lo as well.
     }
 
+    /// Updates the moving average of the fraction of CE-marked bytes at the
+    /// end of each round and, unless probing for bandwidth, cuts `inflight_lo`
+    /// proportionally to it.
+    fn adapt_lower_bounds_to_ecn(
+        &mut self, congestion_event: &BBRv2CongestionEvent, params: &Params,
+    ) {
+        if params.ecn_alpha_gain == 0.0 || !congestion_event.end_of_round_trip {
+            return;
+        }
+
+        self.ecn_alpha = (1.0 - params.ecn_alpha_gain) * self.ecn_alpha +
+            params.ecn_alpha_gain * self.ce_ratio_in_round();
+
+        if self.ce_bytes_in_round == 0 ||
+            congestion_event.is_probing_for_bandwidth
+        {
+            return;
+        }
+
+        if self.inflight_lo == usize::MAX {
+            self.inflight_lo = congestion_event.prior_cwnd;
+        }
+
+        self.inflight_lo = (self.inflight_lo as f32 *
+            (1.0 - params.ecn_factor * self.ecn_alpha))
+            as usize;
+    }
+
     pub(super) fn on_congestion_event_finish(
         &mut self, least_unacked_packet: u64,
         congestion_event: &BBRv2CongestionEvent,
@@ -593,6 +637,52 @@ impl BBRv2NetworkModel {
         false
     }
 
+    /// Whether the fraction of CE-marked bytes in the current round exceeds
+    /// `ecn_threshold`. Always false when the BBRv3 ECN response is disabled.
+    pub(super) fn is_ecn_too_high(&self, params: &Params) -> bool {
+        params.ecn_alpha_gain > 0.0 &&
+            self.ce_bytes_in_round > 0 &&
+            self.ce_ratio_in_round() > params.ecn_threshold
+    }
+
+    /// Called at the end of each STARTUP round, returns true once the fraction
+    /// of CE-marked bytes has been too high for `startup_full_ecn_count`
+    /// consecutive rounds.
+    pub(super) fn check_ecn_too_high_in_startup(
+        &mut self, params: &Params,
+    ) -> bool {
+        if self.is_ecn_too_high(params) {
+            self.rounds_with_high_ecn += 1;
+        } else {
+            self.rounds_with_high_ecn = 0;
+        }
+
+        params.startup_full_ecn_count > 0 &&
+            self.rounds_with_high_ecn >= params.startup_full_ecn_count
+    }
+
+    fn ce_ratio_in_round(&self) -> f32 {
+        if self.bytes_acked_in_round == 0 {
+            return 0.0;
+        }
+
+        (self.ce_bytes_in_round as f32 / self.bytes_acked_in_round as f32)
+            .min(1.0)
+    }
+
+    /// Accounts CE-marked bytes in the current round. Without the BBRv3 ECN
+    /// response they are accounted as lost, so that they bound the inflight
+    /// and bandwidth estimates like losses do.
+    pub(super) fn on_ecn_ce(&mut self, ce_bytes: usize, params: &Params) {
+        if params.ecn_alpha_gain > 0.0 {
+            self.ce_bytes_in_round += ce_bytes;
+            return;
+        }
+
+        self.bytes_lost_in_round += ce_bytes;
+        self.loss_events_in_round += 1;
+    }
+
     pub(super) fn restart_round_early(&mut self) {
         self.on_new_round();
         self.round_trip_counter.restart_round();
@@ -602,6 +692,8 @@ impl BBRv2NetworkModel {
     fn on_new_round(&mut self) {
         self.bytes_lost_in_round = 0;
         self.loss_events_in_round = 0;
+        self.bytes_acked_in_round = 0;
+        self.ce_bytes_in_round = 0;
         self.max_bytes_delivered_in_round = 0;
         self.min_bytes_in_flight_in_round = usize::MAX;
         self.inflight_hi_limited_in_round = false;
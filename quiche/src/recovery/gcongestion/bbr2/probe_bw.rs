@@ -32,6 +32,7 @@ use std::ops::Add;
 use std::time::Duration;
 use std::time::Instant;
 
+use crate::rand;
 use crate::recovery::gcongestion::bbr2::Params;
 use crate::recovery::gcongestion::Acked;
 use crate::recovery::gcongestion::Lost;
@@ -145,7 +146,7 @@ impl ModeImpl for ProbeBW {
         // set when `ProbeRTT::enter` is called.
         if !switch_to_probe_rtt {
             self.model.set_pacing_gain(self.cycle.phase.gain(params));
-            self.model.set_cwnd_gain(params.probe_bw_cwnd_gain);
+            self.model.set_cwnd_gain(self.cycle.phase.cwnd_gain(params));
         }
 
         if switch_to_probe_rtt {
@@ -219,11 +220,16 @@ impl ProbeBW {
         }
 
         // Pick probe wait time.
-        // TODO(vlad): actually pick time
         cycle.rounds_since_probe = 0;
-        cycle.probe_wait_time = Some(
-            params.probe_bw_probe_base_duration + Duration::from_micros(500),
-        );
+        let rand_duration = if params.probe_bw_probe_rand_duration.is_zero() {
+            Duration::from_micros(500)
+        } else {
+            Duration::from_micros(rand::rand_u64_uniform(
+                params.probe_bw_probe_rand_duration.as_micros() as u64,
+            ))
+        };
+        cycle.probe_wait_time =
+            Some(params.probe_bw_probe_base_duration + rand_duration);
 
         cycle.probe_up_bytes = None;
         cycle.probe_up_app_limited_since_inflight_hi_limited = false;
@@ -486,7 +492,8 @@ impl ProbeBW {
                 congestion_event,
                 params.probe_bw_full_loss_count,
                 params,
-            ) {
+            ) || self.model.is_ecn_too_high(params)
+            {
                 self.cycle.is_sample_from_probing = false;
                 if !send_state.is_app_limited ||
                     params.max_probe_up_queue_rounds > 0
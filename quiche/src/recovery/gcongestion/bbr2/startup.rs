@@ -116,6 +116,17 @@ impl ModeImpl for Startup {
             }
         }
 
+        if !self.model.full_bandwidth_reached() &&
+            self.model.check_ecn_too_high_in_startup(params)
+        {
+            self.model.set_inflight_hi(self.model.bdp0());
+            self.model.set_full_bandwidth_reached();
+            recovery_stats.set_startup_exit(StartupExit::new(
+                cwnd,
+                StartupExitReason::Loss,
+            ));
+        }
+
         if self.model.full_bandwidth_reached() {
             self.into_drain(event_time, Some(congestion_event), params)
         } else {
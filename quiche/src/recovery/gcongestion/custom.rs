@@ -0,0 +1,486 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Support for congestion control algorithms supplied by the application.
+
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use super::bbr::BandwidthSampler;
+use super::Acked;
+use super::Bandwidth;
+use super::CongestionControl;
+use super::Lost;
+use super::RttStats;
+
+use crate::recovery::RecoveryStats;
+
+/// The window used by the bandwidth sampler to track ack aggregation, in
+/// round trips.
+const MAX_ACK_HEIGHT_FILTER_WINDOW: usize = 10;
+
+/// The pacing gain applied to `cwnd / smoothed_rtt` when the congestion
+/// controller doesn't provide its own pacing rate.
+const DEFAULT_PACING_GAIN: f32 = 1.25;
+
+/// The RTT estimates of a path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RttEstimate {
+    /// The most recent RTT sample.
+    pub latest_rtt: Duration,
+
+    /// The smoothed RTT.
+    pub smoothed_rtt: Duration,
+
+    /// The RTT variation.
+    pub rttvar: Duration,
+
+    /// The minimum RTT observed, or `None` before the first RTT sample.
+    pub min_rtt: Option<Duration>,
+
+    /// The peer's maximum ACK delay.
+    pub max_ack_delay: Duration,
+}
+
+impl From<&RttStats> for RttEstimate {
+    fn from(rtt_stats: &RttStats) -> Self {
+        RttEstimate {
+            latest_rtt: rtt_stats.latest_rtt,
+            smoothed_rtt: rtt_stats.smoothed_rtt,
+            rttvar: rtt_stats.rttvar,
+            min_rtt: rtt_stats.min_rtt(),
+            max_ack_delay: rtt_stats.max_ack_delay,
+        }
+    }
+}
+
+/// A delivery rate sample, computed from the packets acknowledged by a
+/// congestion event.
+///
+/// This is produced by the same bandwidth sampler used by the built-in BBR
+/// implementation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BandwidthSample {
+    /// The largest delivery rate measured among the acknowledged packets, in
+    /// bytes per second, if any of them produced a valid sample.
+    pub delivery_rate: Option<u64>,
+
+    /// Whether the delivery rate was measured while the sender was
+    /// application limited, in which case it underestimates the available
+    /// bandwidth.
+    pub is_app_limited: bool,
+
+    /// The smallest RTT measured among the acknowledged packets.
+    pub rtt: Option<Duration>,
+
+    /// The largest number of bytes acknowledged while one of the acknowledged
+    /// packets was in flight.
+    pub max_inflight: usize,
+}
+
+/// A congestion event, caused either by an incoming ACK or by a loss detection
+/// timeout.
+#[derive(Debug)]
+pub struct CongestionEvent<'a> {
+    /// The time of the event.
+    pub time: Instant,
+
+    /// The newly acknowledged packets, in increasing packet number order.
+    pub acked_packets: &'a [Acked],
+
+    /// The packets newly declared lost.
+    pub lost_packets: &'a [Lost],
+
+    /// The number of bytes newly acknowledged.
+    pub bytes_acked: usize,
+
+    /// The number of bytes newly declared lost.
+    pub bytes_lost: usize,
+
+    /// The number of bytes in flight before the event.
+    pub prior_bytes_in_flight: usize,
+
+    /// The number of bytes in flight after the event.
+    pub bytes_in_flight: usize,
+
+    /// The RTT estimates after the event.
+    pub rtt: RttEstimate,
+
+    /// The delivery rate sample produced by the acknowledged packets.
+    pub bandwidth_sample: BandwidthSample,
+}
+
+impl Acked {
+    /// Returns the packet number of the acknowledged packet.
+    pub fn pkt_num(&self) -> u64 {
+        self.pkt_num
+    }
+
+    /// Returns the time the acknowledged packet was sent.
+    pub fn time_sent(&self) -> Instant {
+        self.time_sent
+    }
+}
+
+impl Lost {
+    /// Returns the packet number of the lost packet.
+    pub fn pkt_num(&self) -> u64 {
+        self.packet_number
+    }
+
+    /// Returns the size of the lost packet in bytes.
+    pub fn size(&self) -> usize {
+        self.bytes_lost
+    }
+}
+
+/// The parameters a [`CongestionController`] is created with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CongestionControllerParams {
+    /// The maximum size of the packets sent on the path, in bytes.
+    pub max_datagram_size: usize,
+
+    /// The initial congestion window, in bytes.
+    pub initial_congestion_window: usize,
+
+    /// The largest congestion window quiche is expected to use, in bytes.
+    pub max_congestion_window: usize,
+
+    /// The RTT assumed before the first RTT sample is taken.
+    pub initial_rtt: Duration,
+}
+
+/// A congestion control algorithm supplied by the application.
+///
+/// A congestion controller is created for each path of a connection by the
+/// [`CongestionControllerFactory`] configured with [`set_custom_cc()`]. Loss
+/// detection, RTT estimation and pacing are still performed by quiche, the
+/// controller only decides how many bytes can be in flight and, optionally,
+/// how fast they are sent.
+///
+/// All sizes are in bytes.
+///
+/// [`set_custom_cc()`]: struct.Config.html#method.set_custom_cc
+pub trait CongestionController: Send + Sync {
+    /// Returns the name of the current state of the algorithm, which is used
+    /// to annotate qlog congestion state updates.
+    ///
+    /// The default is `"custom"`.
+    fn state_str(&self) -> &'static str {
+        "custom"
+    }
+
+    /// Called for every packet sent on the path. `bytes_in_flight` is the
+    /// number of bytes in flight before the packet was sent.
+    fn on_packet_sent(
+        &mut self, sent_time: Instant, pkt_num: u64, bytes: usize,
+        bytes_in_flight: usize, is_retransmissible: bool,
+    );
+
+    /// Called when a congestion event produced a new RTT sample, before the
+    /// other hooks are called for that event.
+    fn on_rtt_update(&mut self, _rtt: &RttEstimate, _now: Instant) {}
+
+    /// Called when a congestion event acknowledges new packets.
+    fn on_packets_acked(&mut self, event: &CongestionEvent);
+
+    /// Called when a congestion event declares packets lost, before
+    /// [`on_packets_acked()`] is called for the same event.
+    ///
+    /// [`on_packets_acked()`]: trait.CongestionController.html#tymethod.on_packets_acked
+    fn on_congestion_event(&mut self, event: &CongestionEvent);
+
+    /// Called when the peer reports that `ce_count` packets were marked with
+    /// ECN Congestion Experienced.
+    fn on_ecn_ce(&mut self, _ce_count: u64, _now: Instant) {}
+
+    /// Called when the probe timeout fires. `packets_retransmitted` is whether
+    /// any data was scheduled for retransmission.
+    fn on_retransmission_timeout(&mut self, _packets_retransmitted: bool) {}
+
+    /// Called when the application doesn't have enough data to fill the
+    /// congestion window.
+    fn on_app_limited(&mut self, _bytes_in_flight: usize) {}
+
+    /// Called when the maximum datagram size of the path changes.
+    fn update_mss(&mut self, _max_datagram_size: usize) {}
+
+    /// Returns whether the algorithm is in a recovery period.
+    ///
+    /// The default is `false`.
+    fn is_in_recovery(&self) -> bool {
+        false
+    }
+
+    /// Returns the congestion window.
+    fn cwnd(&self) -> usize;
+
+    /// Returns the pacing rate in bytes per second, or `None` to let quiche
+    /// pace at 1.25 times the congestion window per smoothed RTT.
+    ///
+    /// The default is `None`.
+    fn pacing_rate(
+        &self, _bytes_in_flight: usize, _rtt: &RttEstimate,
+    ) -> Option<u64> {
+        None
+    }
+}
+
+/// Creates a [`CongestionController`] for each new path.
+///
+/// The factory is called both for the paths of new connections and when an
+/// existing connection resets its congestion state, e.g. after migrating to a
+/// new path.
+///
+/// It is implemented for closures taking the [`CongestionControllerParams`]
+/// and returning a boxed controller.
+pub trait CongestionControllerFactory: Send + Sync {
+    /// Creates a new congestion controller.
+    fn new_controller(
+        &self, params: &CongestionControllerParams,
+    ) -> Box<dyn CongestionController>;
+}
+
+impl<F> CongestionControllerFactory for F
+where
+    F: Fn(&CongestionControllerParams) -> Box<dyn CongestionController>
+        + Send
+        + Sync,
+{
+    fn new_controller(
+        &self, params: &CongestionControllerParams,
+    ) -> Box<dyn CongestionController> {
+        self(params)
+    }
+}
+
+/// A [`CongestionControllerFactory`] shared between configurations, which is
+/// compared by identity.
+#[derive(Clone)]
+pub(crate) struct CustomCc(pub(crate) Arc<dyn CongestionControllerFactory>);
+
+impl PartialEq for CustomCc {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Adapts a [`CongestionController`] to the interface used by the gcongestion
+/// recovery.
+pub(crate) struct CustomSender {
+    controller: Box<dyn CongestionController>,
+
+    bandwidth_sampler: BandwidthSampler,
+
+    /// The latest delivery rate sample that wasn't application limited, or a
+    /// larger application limited one.
+    bandwidth_estimate: Bandwidth,
+
+    mss: usize,
+}
+
+impl std::fmt::Debug for CustomSender {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("CustomSender")
+            .field("state", &self.controller.state_str())
+            .field("cwnd", &self.controller.cwnd())
+            .field("bandwidth_estimate", &self.bandwidth_estimate)
+            .finish()
+    }
+}
+
+impl CustomSender {
+    pub(super) fn new(
+        factory: &CustomCc, params: &CongestionControllerParams,
+    ) -> Self {
+        CustomSender {
+            controller: factory.0.new_controller(params),
+            bandwidth_sampler: BandwidthSampler::new(
+                MAX_ACK_HEIGHT_FILTER_WINDOW,
+                false,
+                false,
+            ),
+            bandwidth_estimate: Bandwidth::zero(),
+            mss: params.max_datagram_size,
+        }
+    }
+}
+
+impl CongestionControl for CustomSender {
+    #[cfg(feature = "qlog")]
+    fn state_str(&self) -> &'static str {
+        self.controller.state_str()
+    }
+
+    fn get_congestion_window(&self) -> usize {
+        self.controller.cwnd()
+    }
+
+    fn get_congestion_window_in_packets(&self) -> usize {
+        self.controller.cwnd() / self.mss
+    }
+
+    fn can_send(&self, bytes_in_flight: usize) -> bool {
+        bytes_in_flight < self.controller.cwnd()
+    }
+
+    fn on_packet_sent(
+        &mut self, sent_time: Instant, bytes_in_flight: usize,
+        packet_number: u64, bytes: usize, is_retransmissible: bool,
+        _rtt_stats: &RttStats,
+    ) {
+        self.bandwidth_sampler.on_packet_sent(
+            sent_time,
+            packet_number,
+            bytes,
+            bytes_in_flight,
+            is_retransmissible,
+        );
+
+        self.controller.on_packet_sent(
+            sent_time,
+            packet_number,
+            bytes,
+            bytes_in_flight,
+            is_retransmissible,
+        );
+    }
+
+    fn on_packet_neutered(&mut self, packet_number: u64) {
+        self.bandwidth_sampler.on_packet_neutered(packet_number);
+    }
+
+    fn on_congestion_event(
+        &mut self, rtt_updated: bool, prior_in_flight: usize,
+        bytes_in_flight: usize, event_time: Instant, acked_packets: &[Acked],
+        lost_packets: &[Lost], least_unacked: u64, rtt_stats: &RttStats,
+        _recovery_stats: &mut RecoveryStats,
+    ) {
+        let prior_bytes_acked = self.bandwidth_sampler.total_bytes_acked();
+
+        let sample = self.bandwidth_sampler.on_congestion_event(
+            event_time,
+            acked_packets,
+            lost_packets,
+            None,
+            Bandwidth::infinite(),
+            0,
+        );
+
+        self.bandwidth_sampler
+            .remove_obsolete_packets(least_unacked);
+
+        if let Some(rate) = sample.sample_max_bandwidth {
+            if !sample.sample_is_app_limited || rate > self.bandwidth_estimate {
+                self.bandwidth_estimate = rate;
+            }
+        }
+
+        let rtt = RttEstimate::from(rtt_stats);
+
+        if rtt_updated {
+            self.controller.on_rtt_update(&rtt, event_time);
+        }
+
+        let event = CongestionEvent {
+            time: event_time,
+            acked_packets,
+            lost_packets,
+            bytes_acked: self.bandwidth_sampler.total_bytes_acked() -
+                prior_bytes_acked,
+            bytes_lost: lost_packets.iter().map(|p| p.bytes_lost).sum(),
+            prior_bytes_in_flight: prior_in_flight,
+            bytes_in_flight,
+            rtt,
+            bandwidth_sample: BandwidthSample {
+                delivery_rate: sample
+                    .sample_max_bandwidth
+                    .map(Bandwidth::to_bytes_per_second),
+                is_app_limited: sample.sample_is_app_limited,
+                rtt: sample.sample_rtt,
+                max_inflight: sample.sample_max_inflight,
+            },
+        };
+
+        if !lost_packets.is_empty() {
+            self.controller.on_congestion_event(&event);
+        }
+
+        if !acked_packets.is_empty() {
+            self.controller.on_packets_acked(&event);
+        }
+    }
+
+    fn on_retransmission_timeout(&mut self, packets_retransmitted: bool) {
+        self.controller
+            .on_retransmission_timeout(packets_retransmitted);
+    }
+
+    fn on_connection_migration(&mut self) {}
+
+    fn is_in_recovery(&self) -> bool {
+        self.controller.is_in_recovery()
+    }
+
+    fn is_cwnd_limited(&self, bytes_in_flight: usize) -> bool {
+        bytes_in_flight >= self.controller.cwnd()
+    }
+
+    fn pacing_rate(
+        &self, bytes_in_flight: usize, rtt_stats: &RttStats,
+    ) -> Bandwidth {
+        let rtt = RttEstimate::from(rtt_stats);
+
+        match self.controller.pacing_rate(bytes_in_flight, &rtt) {
+            Some(rate) => Bandwidth::from_bytes_per_second(rate),
+
+            None =>
+                Bandwidth::from_bytes_and_time_delta(
+                    self.controller.cwnd(),
+                    rtt.smoothed_rtt,
+                ) * DEFAULT_PACING_GAIN,
+        }
+    }
+
+    fn bandwidth_estimate(&self, _rtt_stats: &RttStats) -> Bandwidth {
+        self.bandwidth_estimate
+    }
+
+    fn update_mss(&mut self, new_mss: usize) {
+        self.mss = new_mss;
+        self.controller.update_mss(new_mss);
+    }
+
+    fn on_app_limited(&mut self, bytes_in_flight: usize) {
+        self.bandwidth_sampler.on_app_limited();
+        self.controller.on_app_limited(bytes_in_flight);
+    }
+
+    fn on_ecn_ce(&mut self, ce_count: u64, event_time: Instant) {
+        self.controller.on_ecn_ce(ce_count, event_time);
+    }
+}
@@ -26,6 +26,7 @@
 
 mod bbr;
 mod bbr2;
+mod custom;
 pub mod pacer;
 mod recovery;
 
@@ -33,19 +34,29 @@ use std::fmt::Debug;
 use std::str::FromStr;
 use std::time::Instant;
 
+pub use self::custom::BandwidthSample;
+pub use self::custom::CongestionController;
+pub use self::custom::CongestionControllerFactory;
+pub use self::custom::CongestionControllerParams;
+pub use self::custom::CongestionEvent;
+pub(crate) use self::custom::CustomCc;
+pub use self::custom::RttEstimate;
 pub use self::recovery::GRecovery;
 use crate::recovery::bandwidth::Bandwidth;
 
 use crate::recovery::rtt::RttStats;
+use crate::recovery::AckFrequency;
 use crate::recovery::RecoveryConfig;
 use crate::recovery::RecoveryStats;
 
+/// A packet declared lost by a [`CongestionEvent`].
 #[derive(Debug)]
 pub struct Lost {
     pub(super) packet_number: u64,
     pub(super) bytes_lost: usize,
 }
 
+/// A packet acknowledged by a [`CongestionEvent`].
 #[derive(Debug)]
 pub struct Acked {
     pub(super) pkt_num: u64,
@@ -57,6 +68,7 @@ pub struct Acked {
 #[derive(Debug)]
 pub(crate) enum Congestion {
     BBRv2(bbr2::BBRv2),
+    Custom(custom::CustomSender),
 }
 
 impl Congestion {
@@ -72,6 +84,36 @@ impl Congestion {
             recovery_config.custom_bbr_params.as_ref(),
         ))
     }
+
+    pub(super) fn bbrv3(
+        initial_tcp_congestion_window: usize, max_congestion_window: usize,
+        recovery_config: &RecoveryConfig,
+    ) -> Self {
+        Congestion::BBRv2(bbr2::BBRv2::new_v3(
+            initial_tcp_congestion_window,
+            max_congestion_window,
+            recovery_config.max_send_udp_payload_size,
+            recovery_config.initial_rtt,
+            recovery_config.custom_bbr_params.as_ref(),
+        ))
+    }
+
+    pub(super) fn custom(
+        factory: &CustomCc, initial_tcp_congestion_window: usize,
+        max_congestion_window: usize, recovery_config: &RecoveryConfig,
+    ) -> Self {
+        let mss = recovery_config.max_send_udp_payload_size;
+
+        Congestion::Custom(custom::CustomSender::new(
+            factory,
+            &CongestionControllerParams {
+                max_datagram_size: mss,
+                initial_congestion_window: initial_tcp_congestion_window * mss,
+                max_congestion_window: max_congestion_window * mss,
+                initial_rtt: recovery_config.initial_rtt,
+            },
+        ))
+    }
 }
 
 #[enum_dispatch::enum_dispatch]
@@ -148,6 +190,16 @@ pub(super) trait CongestionControl: Debug {
 
     fn on_app_limited(&mut self, _bytes_in_flight: usize) {}
 
+    /// Called when the peer reports that `ce_count` packets were marked with
+    /// ECN Congestion Experienced.
+    fn on_ecn_ce(&mut self, _ce_count: u64, _event_time: Instant) {}
+
+    /// Returns the acknowledgement frequency the sender would like the peer to
+    /// use, or `None` to keep the default one.
+    fn ack_frequency(&self) -> Option<AckFrequency> {
+        None
+    }
+
     #[cfg(feature = "qlog")]
     fn ssthresh(&self) -> Option<u64> {
         None
@@ -224,6 +276,25 @@ pub struct BbrParams {
     /// Determines whether app limited rounds with no bandwidth growth count
     /// towards the rounds threshold to exit startup.
     pub ignore_app_limited_for_no_bandwidth_growth: Option<bool>,
+
+    /// Controls the BBR probe up cwnd gain.
+    pub probe_bw_up_cwnd_gain: Option<f32>,
+
+    /// Controls the gain of the moving average of the fraction of CE-marked
+    /// bytes. Setting it to zero makes BBR account CE marks as losses.
+    pub ecn_alpha_gain: Option<f32>,
+
+    /// Controls the largest fraction of inflight_lo cut per round in response
+    /// to CE marks.
+    pub ecn_factor: Option<f32>,
+
+    /// Controls the fraction of CE-marked bytes in a round above which
+    /// inflight is considered too high.
+    pub ecn_threshold: Option<f32>,
+
+    /// Controls the number of rounds with too many CE-marked bytes necessary
+    /// to exit startup.
+    pub startup_full_ecn_count: Option<usize>,
 }
 
 /// Controls BBR's bandwidth reduction strategy on congestion event.
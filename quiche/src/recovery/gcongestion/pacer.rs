@@ -32,6 +32,7 @@ use std::time::Instant;
 
 use crate::recovery::gcongestion::Bandwidth;
 use crate::recovery::rtt::RttStats;
+use crate::recovery::AckFrequency;
 use crate::recovery::RecoveryStats;
 use crate::recovery::ReleaseDecision;
 use crate::recovery::ReleaseTime;
@@ -255,11 +256,19 @@ impl Pacer {
         self.sender.bandwidth_estimate(rtt_stats)
     }
 
+    pub fn ack_frequency(&self) -> Option<AckFrequency> {
+        self.sender.ack_frequency()
+    }
+
     pub fn on_app_limited(&mut self, bytes_in_flight: usize) {
         self.pacing_limited = false;
         self.sender.on_app_limited(bytes_in_flight);
     }
 
+    pub fn on_ecn_ce(&mut self, ce_count: u64, event_time: Instant) {
+        self.sender.on_ecn_ce(ce_count, event_time)
+    }
+
     pub fn update_mss(&mut self, new_mss: usize) {
         self.sender.update_mss(new_mss)
     }
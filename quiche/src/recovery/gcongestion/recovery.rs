@@ -18,10 +18,12 @@ use crate::frame;
 use crate::recovery::bytes_in_flight::BytesInFlight;
 use crate::recovery::gcongestion::Bandwidth;
 use crate::recovery::rtt::RttStats;
+use crate::recovery::AckFrequency;
 use crate::recovery::CongestionControlAlgorithm;
 use crate::recovery::HandshakeStatus;
 use crate::recovery::LossDetectionTimer;
 use crate::recovery::OnAckReceivedOutcome;
+use crate::recovery::PathCapacityRecord;
 use crate::recovery::RangeSet;
 use crate::recovery::RecoveryConfig;
 use crate::recovery::RecoveryOps;
@@ -60,6 +62,7 @@ enum SentStatus {
         in_flight: bool,
         has_data: bool,
         is_pmtud_probe: bool,
+        ecn_marked: bool,
         sent_bytes: usize,
         frames: SmallVec<[frame::Frame; 1]>,
     },
@@ -112,6 +115,7 @@ struct RecoveryEpoch {
 struct AckedDetectionResult {
     acked_bytes: usize,
     spurious_losses: usize,
+    ecn_marked_acked: u64,
     spurious_pkt_thresh: Option<u64>,
     has_ack_eliciting: bool,
 }
@@ -169,6 +173,7 @@ impl RecoveryEpoch {
 
         let mut acked_bytes = 0;
         let mut spurious_losses = 0;
+        let mut ecn_marked_acked = 0;
         let mut spurious_pkt_thresh = None;
         let mut has_ack_eliciting = false;
 
@@ -214,12 +219,17 @@ impl RecoveryEpoch {
                             sent_bytes,
                             frames,
                             ack_eliciting,
+                            ecn_marked,
                             ..
                         } => {
                             if in_flight {
                                 self.pkts_in_flight -= 1;
                                 acked_bytes += sent_bytes;
                             }
+
+                            if ecn_marked {
+                                ecn_marked_acked += 1;
+                            }
                             newly_acked.push(Acked {
                                 pkt_num: *pkt_num,
                                 time_sent,
@@ -251,6 +261,7 @@ impl RecoveryEpoch {
         Ok(AckedDetectionResult {
             acked_bytes,
             spurious_losses,
+            ecn_marked_acked,
             spurious_pkt_thresh,
             has_ack_eliciting,
         })
@@ -398,8 +409,21 @@ pub struct GRecovery {
 
 impl GRecovery {
     pub fn new(recovery_config: &RecoveryConfig) -> Option<Self> {
-        let cc = match recovery_config.cc_algorithm {
-            CongestionControlAlgorithm::Bbr2Gcongestion => Congestion::bbrv2(
+        let cc = match (&recovery_config.custom_cc, recovery_config.cc_algorithm)
+        {
+            (Some(factory), _) => Congestion::custom(
+                factory,
+                recovery_config.initial_congestion_window_packets,
+                MAX_WINDOW_PACKETS,
+                recovery_config,
+            ),
+            (None, CongestionControlAlgorithm::Bbr2Gcongestion) =>
+                Congestion::bbrv2(
+                    recovery_config.initial_congestion_window_packets,
+                    MAX_WINDOW_PACKETS,
+                    recovery_config,
+                ),
+            (None, CongestionControlAlgorithm::Bbr3) => Congestion::bbrv3(
                 recovery_config.initial_congestion_window_packets,
                 MAX_WINDOW_PACKETS,
                 recovery_config,
@@ -635,6 +659,7 @@ impl RecoveryOps for GRecovery {
             ack_eliciting,
             in_flight,
             is_pmtud_probe,
+            ecn_marked: pkt.ecn_marked,
             has_data: pkt.has_data,
             sent_bytes,
             frames: pkt.frames,
@@ -691,6 +716,7 @@ impl RecoveryOps for GRecovery {
         let AckedDetectionResult {
             acked_bytes,
             spurious_losses,
+            ecn_marked_acked,
             spurious_pkt_thresh,
             has_ack_eliciting,
         } = self.epochs[epoch].detect_and_remove_acked_packets(
@@ -762,9 +788,16 @@ impl RecoveryOps for GRecovery {
             lost_bytes,
             acked_bytes,
             spurious_losses,
+            ecn_marked_acked,
         })
     }
 
+    fn on_ecn_ce(&mut self, ce_count: u64, now: Instant) {
+        if ce_count > 0 {
+            self.pacer.on_ecn_ce(ce_count, now);
+        }
+    }
+
     fn on_loss_detection_timeout(
         &mut self, handshake_status: HandshakeStatus, now: Instant,
         trace_id: &str,
@@ -1035,6 +1068,16 @@ impl RecoveryOps for GRecovery {
         true
     }
 
+    fn ack_frequency(&self) -> Option<AckFrequency> {
+        self.pacer.ack_frequency()
+    }
+
+    fn setup_careful_resume(
+        &mut self, _record: &PathCapacityRecord,
+    ) -> Result<()> {
+        Err(Error::CongestionControl)
+    }
+
     #[cfg(feature = "qlog")]
     fn state_str(&self, _now: Instant) -> &'static str {
         self.pacer.state_str()
@@ -41,9 +41,19 @@ use qlog::events::EventData;
 use smallvec::SmallVec;
 
 use self::congestion::recovery::LegacyRecovery;
+pub use self::congestion::resume::PathCapacityRecord;
 use self::gcongestion::GRecovery;
+pub use gcongestion::Acked as AckedPacket;
+pub use gcongestion::BandwidthSample;
 pub use gcongestion::BbrBwLoReductionStrategy;
 pub use gcongestion::BbrParams;
+pub use gcongestion::CongestionController;
+pub use gcongestion::CongestionControllerFactory;
+pub use gcongestion::CongestionControllerParams;
+pub use gcongestion::CongestionEvent;
+pub(crate) use gcongestion::CustomCc;
+pub use gcongestion::Lost as LostPacket;
+pub use gcongestion::RttEstimate;
 
 // Loss Recovery
 const INITIAL_PACKET_THRESHOLD: u64 = 3;
@@ -96,7 +106,7 @@ impl std::fmt::Debug for LossDetectionTimer {
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, PartialEq)]
 pub struct RecoveryConfig {
     pub initial_rtt: Duration,
     pub max_send_udp_payload_size: usize,
@@ -107,6 +117,7 @@ pub struct RecoveryConfig {
     pub pacing: bool,
     pub max_pacing_rate: Option<u64>,
     pub initial_congestion_window_packets: usize,
+    pub custom_cc: Option<CustomCc>,
 }
 
 impl RecoveryConfig {
@@ -122,6 +133,7 @@ impl RecoveryConfig {
             max_pacing_rate: config.max_pacing_rate,
             initial_congestion_window_packets: config
                 .initial_congestion_window_packets,
+            custom_cc: config.custom_cc.clone().map(CustomCc),
         }
     }
 }
@@ -140,6 +152,7 @@ pub struct OnAckReceivedOutcome {
     pub lost_bytes: usize,
     pub acked_bytes: usize,
     pub spurious_losses: usize,
+    pub ecn_marked_acked: u64,
 }
 
 #[derive(Debug, Default)]
@@ -183,6 +196,10 @@ pub trait RecoveryOps {
         trace_id: &str,
     ) -> Result<OnAckReceivedOutcome>;
 
+    /// Reacts to `ce_count` new ECN-CE marks reported by the ACK frame last
+    /// passed to `on_ack_received()`.
+    fn on_ecn_ce(&mut self, ce_count: u64, now: Instant);
+
     fn on_loss_detection_timeout(
         &mut self, handshake_status: HandshakeStatus, now: Instant,
         trace_id: &str,
@@ -278,6 +295,14 @@ pub trait RecoveryOps {
     fn get_next_release_time(&self) -> ReleaseDecision;
 
     fn gcongestion_enabled(&self) -> bool;
+
+    /// Returns the acknowledgement frequency the congestion controller would
+    /// like the peer to use, or `None` to use the default one.
+    fn ack_frequency(&self) -> Option<AckFrequency>;
+
+    /// Starts Careful Resume using the path capacity saved in `record`.
+    fn setup_careful_resume(&mut self, record: &PathCapacityRecord)
+        -> Result<()>;
 }
 
 impl Recovery {
@@ -335,6 +360,12 @@ pub enum CongestionControlAlgorithm {
     /// BBRv2 congestion control algorithm implementation from gcongestion
     /// branch. `bbr2_gcongestion` in a string form.
     Bbr2Gcongestion = 4,
+    /// LEDBAT++ scavenger congestion control algorithm, which yields to other
+    /// traffic when the queueing delay grows. `ledbat` in a string form.
+    Ledbat          = 5,
+    /// BBRv3 congestion control algorithm, built on the gcongestion BBRv2
+    /// implementation. `bbr3` in a string form.
+    Bbr3            = 6,
 }
 
 impl FromStr for CongestionControlAlgorithm {
@@ -353,6 +384,8 @@ impl FromStr for CongestionControlAlgorithm {
             #[cfg(feature = "gcongestion")]
             "bbr2" => Ok(CongestionControlAlgorithm::Bbr2Gcongestion),
             "bbr2_gcongestion" => Ok(CongestionControlAlgorithm::Bbr2Gcongestion),
+            "ledbat" => Ok(CongestionControlAlgorithm::Ledbat),
+            "bbr3" => Ok(CongestionControlAlgorithm::Bbr3),
             _ => Err(crate::Error::CongestionControl),
         }
     }
@@ -391,6 +424,8 @@ pub struct Sent {
     pub has_data: bool,
 
     pub is_pmtud_probe: bool,
+
+    pub ecn_marked: bool,
 }
 
 impl std::fmt::Debug for Sent {
@@ -405,7 +440,8 @@ impl std::fmt::Debug for Sent {
         write!(f, "tx_in_flight={} ", self.tx_in_flight)?;
         write!(f, "lost={} ", self.lost)?;
         write!(f, "has_data={} ", self.has_data)?;
-        write!(f, "is_pmtud_probe={}", self.is_pmtud_probe)?;
+        write!(f, "is_pmtud_probe={} ", self.is_pmtud_probe)?;
+        write!(f, "ecn_marked={}", self.ecn_marked)?;
 
         Ok(())
     }
@@ -616,6 +652,22 @@ impl ReleaseDecision {
     }
 }
 
+/// The acknowledgement frequency requested from the peer with the ACK
+/// Frequency extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AckFrequency {
+    /// The number of ack-eliciting packets the peer can receive before it
+    /// needs to send an ACK.
+    pub ack_eliciting_threshold: u64,
+
+    /// The maximum time the peer can delay sending an ACK.
+    pub max_ack_delay: Duration,
+
+    /// The number of out-of-order packets that trigger an immediate ACK, or 0
+    /// to ignore reordering.
+    pub reordering_threshold: u64,
+}
+
 /// Recovery statistics
 #[derive(Default, Debug)]
 pub struct RecoveryStats {
@@ -707,6 +759,14 @@ mod tests {
             CongestionControlAlgorithm::from_str("bbr2_gcongestion").unwrap();
         assert_eq!(algo, CongestionControlAlgorithm::Bbr2Gcongestion);
         assert!(recovery_for_alg(algo).gcongestion_enabled());
+
+        let algo = CongestionControlAlgorithm::from_str("ledbat").unwrap();
+        assert_eq!(algo, CongestionControlAlgorithm::Ledbat);
+        assert!(!recovery_for_alg(algo).gcongestion_enabled());
+
+        let algo = CongestionControlAlgorithm::from_str("bbr3").unwrap();
+        assert_eq!(algo, CongestionControlAlgorithm::Bbr3);
+        assert!(recovery_for_alg(algo).gcongestion_enabled());
     }
 
     #[test]
@@ -719,7 +779,7 @@ mod tests {
 
     #[rstest]
     fn loss_on_pto(
-        #[values("reno", "cubic", "bbr", "bbr2", "bbr2_gcongestion")]
+        #[values("reno", "cubic", "bbr", "bbr2", "bbr2_gcongestion", "bbr3")]
         cc_algorithm_name: &str,
     ) {
         let mut cfg = Config::new(crate::PROTOCOL_VERSION).unwrap();
@@ -749,6 +809,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -780,6 +841,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -811,6 +873,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -841,6 +904,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -877,6 +941,7 @@ mod tests {
                 lost_bytes: 0,
                 acked_bytes: 2 * 1000,
                 spurious_losses: 0,
+                ecn_marked_acked: 0,
             }
         );
 
@@ -911,6 +976,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -941,6 +1007,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -978,6 +1045,7 @@ mod tests {
                 lost_bytes: 2000,
                 acked_bytes: 2 * 1000,
                 spurious_losses: 0,
+                ecn_marked_acked: 0,
             }
         );
 
@@ -1006,7 +1074,7 @@ mod tests {
 
     #[rstest]
     fn loss_on_timer(
-        #[values("reno", "cubic", "bbr", "bbr2", "bbr2_gcongestion")]
+        #[values("reno", "cubic", "bbr", "bbr2", "bbr2_gcongestion", "bbr3")]
         cc_algorithm_name: &str,
     ) {
         let mut cfg = Config::new(crate::PROTOCOL_VERSION).unwrap();
@@ -1036,6 +1104,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -1066,6 +1135,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -1096,6 +1166,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -1126,6 +1197,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -1163,6 +1235,7 @@ mod tests {
                 lost_bytes: 0,
                 acked_bytes: 3 * 1000,
                 spurious_losses: 0,
+                ecn_marked_acked: 0,
             }
         );
 
@@ -1203,7 +1276,7 @@ mod tests {
 
     #[rstest]
     fn loss_on_reordering(
-        #[values("reno", "cubic", "bbr", "bbr2", "bbr2_gcongestion")]
+        #[values("reno", "cubic", "bbr", "bbr2", "bbr2_gcongestion", "bbr3")]
         cc_algorithm_name: &str,
     ) {
         let mut cfg = Config::new(crate::PROTOCOL_VERSION).unwrap();
@@ -1233,6 +1306,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -1263,6 +1337,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -1293,6 +1368,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -1323,6 +1399,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -1359,6 +1436,7 @@ mod tests {
                 lost_bytes: 1000,
                 acked_bytes: 1000 * 2,
                 spurious_losses: 0,
+                ecn_marked_acked: 0,
             }
         );
 
@@ -1385,6 +1463,7 @@ mod tests {
                 lost_bytes: 0,
                 acked_bytes: 1000,
                 spurious_losses: 1,
+                ecn_marked_acked: 0,
             }
         );
 
@@ -1449,6 +1528,7 @@ mod tests {
                 lost: 0,
                 has_data: true,
                 is_pmtud_probe: false,
+                ecn_marked: false,
             };
 
             r.on_packet_sent(
@@ -1497,6 +1577,7 @@ mod tests {
                 lost_bytes: 0,
                 acked_bytes: 12000,
                 spurious_losses: 0,
+                ecn_marked_acked: 0,
             }
         );
 
@@ -1526,6 +1607,7 @@ mod tests {
             lost: 0,
             has_data: true,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -1566,6 +1648,7 @@ mod tests {
             lost: 0,
             has_data: true,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -1598,6 +1681,7 @@ mod tests {
             lost: 0,
             has_data: true,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -1671,7 +1755,8 @@ mod tests {
 
     #[rstest]
     fn validate_ack_range_on_ack_received(
-        #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+        #[values("cubic", "bbr2", "bbr2_gcongestion", "bbr3")]
+        cc_algorithm_name: &str,
     ) {
         let mut cfg = Config::new(crate::PROTOCOL_VERSION).unwrap();
         cfg.set_cc_algorithm_name(cc_algorithm_name).unwrap();
@@ -1716,6 +1801,7 @@ mod tests {
                 lost_bytes: 0,
                 acked_bytes: 2 * 1000,
                 spurious_losses: 0,
+                ecn_marked_acked: 0,
             }
         );
 
@@ -1744,6 +1830,7 @@ mod tests {
                 lost_bytes: 0,
                 acked_bytes: 2 * 1000,
                 spurious_losses: 0,
+                ecn_marked_acked: 0,
             }
         );
         assert_eq!(r.sent_packets_len(epoch), 0);
@@ -1755,7 +1842,7 @@ mod tests {
 
     #[rstest]
     fn pmtud_loss_on_timer(
-        #[values("reno", "cubic", "bbr", "bbr2", "bbr2_gcongestion")]
+        #[values("reno", "cubic", "bbr", "bbr2", "bbr2_gcongestion", "bbr3")]
         cc_algorithm_name: &str,
     ) {
         let mut cfg = Config::new(crate::PROTOCOL_VERSION).unwrap();
@@ -1786,6 +1873,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -1818,6 +1906,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: true,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -1847,6 +1936,7 @@ mod tests {
             lost: 0,
             has_data: false,
             is_pmtud_probe: false,
+            ecn_marked: false,
         };
 
         r.on_packet_sent(
@@ -1883,6 +1973,7 @@ mod tests {
                 lost_bytes: 0,
                 acked_bytes: 2 * 1000,
                 spurious_losses: 0,
+                ecn_marked_acked: 0,
             }
         );
 
@@ -1976,6 +2067,7 @@ mod tests {
                 lost_bytes: 0,
                 acked_bytes: total_bytes_sent,
                 spurious_losses: 0,
+                ecn_marked_acked: 0,
             }
         );
         assert_eq!(r.delivery_rate().to_bytes_per_second(), 1000);
@@ -1987,6 +2079,95 @@ mod tests {
         );
         assert_eq!(r.startup_exit(), None);
     }
+
+    #[rstest]
+    fn ecn_ce_startup_exit(
+        #[values("bbr2_gcongestion", "bbr3")] cc_algorithm_name: &str,
+        #[values(None, Some(0.0))] ecn_alpha_gain: Option<f32>,
+    ) {
+        let mut cfg = Config::new(crate::PROTOCOL_VERSION).unwrap();
+        assert_eq!(cfg.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
+
+        // Same as `Config::set_custom_bbr_params()`.
+        let mut recovery_config = RecoveryConfig::from_config(&cfg);
+        recovery_config.custom_bbr_params = Some(BbrParams {
+            ecn_alpha_gain,
+            ..Default::default()
+        });
+
+        let mut r = Recovery::new_with_config(&recovery_config);
+
+        let mut now = Instant::now();
+        let mut pkt_num = 0;
+
+        // Every packet of each round is reported as CE-marked.
+        for _ in 0..4 {
+            let start = pkt_num;
+
+            // Fill the congestion window, so that bandwidth keeps growing.
+            for _ in 0..r.cwnd_available() / 1200 {
+                let p = Sent {
+                    pkt_num,
+                    frames: smallvec![],
+                    time_sent: now,
+                    time_acked: None,
+                    time_lost: None,
+                    size: 1200,
+                    ack_eliciting: true,
+                    in_flight: true,
+                    delivered: 0,
+                    delivered_time: now,
+                    first_sent_time: now,
+                    is_app_limited: false,
+                    tx_in_flight: 0,
+                    lost: 0,
+                    has_data: true,
+                    is_pmtud_probe: false,
+                    ecn_marked: true,
+                };
+
+                r.on_packet_sent(
+                    p,
+                    packet::Epoch::Application,
+                    HandshakeStatus::default(),
+                    now,
+                    "",
+                );
+
+                pkt_num += 1;
+            }
+
+            now += Duration::from_millis(50);
+
+            let mut acked = RangeSet::default();
+            acked.insert(start..pkt_num);
+
+            r.on_ack_received(
+                &acked,
+                0,
+                packet::Epoch::Application,
+                HandshakeStatus::default(),
+                now,
+                None,
+                "",
+            )
+            .unwrap();
+
+            r.on_ecn_ce(pkt_num - start, now);
+        }
+
+        assert_eq!(r.lost_count(), 0);
+
+        if cc_algorithm_name == "bbr3" && ecn_alpha_gain.is_none() {
+            // BBRv3 leaves STARTUP after two rounds with too many CE marks.
+            assert!(r.startup_exit().is_some());
+            assert_eq!(r.startup_exit().unwrap().reason, StartupExitReason::Loss);
+        } else {
+            // BBRv2, and BBRv3 without its ECN response, account CE marks as
+            // losses, and need more loss events.
+            assert_eq!(r.startup_exit(), None);
+        }
+    }
 }
 
 mod bandwidth;
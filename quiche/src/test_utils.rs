@@ -262,6 +262,7 @@ impl Pipe {
         let info = RecvInfo {
             to: server_path.peer_addr(),
             from: server_path.local_addr(),
+            ecn: Ecn::NotEct,
         };
 
         self.client.recv(buf, info)
@@ -272,6 +273,7 @@ impl Pipe {
         let info = RecvInfo {
             to: client_path.peer_addr(),
             from: client_path.local_addr(),
+            ecn: Ecn::NotEct,
         };
 
         self.server.recv(buf, info)
@@ -323,6 +325,7 @@ pub fn recv_send<F: BufFactory>(
     let info = RecvInfo {
         to: active_path.local_addr(),
         from: active_path.peer_addr(),
+        ecn: Ecn::NotEct,
     };
 
     conn.recv(&mut buf[..len], info)?;
@@ -347,6 +350,7 @@ pub fn process_flight(
         let info = RecvInfo {
             to: si.to,
             from: si.from,
+            ecn: si.ecn,
         };
 
         conn.recv(&mut pkt, info)?;
@@ -522,6 +526,33 @@ pub fn create_cid_and_reset_token(
     (cid, reset_token)
 }
 
+/// Creates a config with the certificate, ALPN and flow control limits used
+/// by the extension tests, and then applies `setup` to it.
+pub fn config_with(
+    cc_algorithm_name: &str, setup: impl FnOnce(&mut Config),
+) -> Config {
+    let mut config = Config::new(PROTOCOL_VERSION).unwrap();
+    assert_eq!(config.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
+    config
+        .load_cert_chain_from_pem_file("examples/cert.crt")
+        .unwrap();
+    config
+        .load_priv_key_from_pem_file("examples/cert.key")
+        .unwrap();
+    config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    config.set_initial_max_data(100_000);
+    config.set_initial_max_stream_data_bidi_local(100_000);
+    config.set_initial_max_stream_data_bidi_remote(100_000);
+    config.set_initial_max_streams_bidi(3);
+    config.verify_peer(false);
+
+    setup(&mut config);
+
+    config
+}
+
 pub fn helper_packet_sent(pkt_num: u64, now: Instant, size: usize) -> Sent {
     Sent {
         pkt_num,
@@ -540,5 +571,6 @@ pub fn helper_packet_sent(pkt_num: u64, now: Instant, size: usize) -> Sent {
         lost: 0,
         has_data: false,
         is_pmtud_probe: false,
+        ecn_marked: false,
     }
 }
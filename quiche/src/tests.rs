@@ -28,6 +28,8 @@ use super::*;
 
 use crate::range_buf::RangeBuf;
 
+use std::sync::Mutex;
+
 use rstest::rstest;
 
 #[test]
@@ -51,12 +53,21 @@ fn transport_params() {
         initial_source_connection_id: Some(b"woot woot".to_vec().into()),
         retry_source_connection_id: Some(b"retry".to_vec().into()),
         max_datagram_frame_size: Some(32),
+        version_information: None,
+        initial_max_path_id: None,
+        min_ack_delay: None,
         unknown_params: Default::default(),
+        preferred_address: Some(PreferredAddress {
+            ipv4: Some("192.0.2.1:443".parse().unwrap()),
+            ipv6: None,
+            connection_id: b"preferred".to_vec().into(),
+            stateless_reset_token: u128::from_be_bytes([0xab; 16]),
+        }),
     };
 
     let mut raw_params = [42; 256];
     let raw_params = TransportParams::encode(&tp, true, &mut raw_params).unwrap();
-    assert_eq!(raw_params.len(), 94);
+    assert_eq!(raw_params.len(), 146);
 
     let new_tp = TransportParams::decode(raw_params, false, None).unwrap();
 
@@ -81,19 +92,114 @@ fn transport_params() {
         initial_source_connection_id: Some(b"woot woot".to_vec().into()),
         retry_source_connection_id: None,
         max_datagram_frame_size: Some(32),
+        version_information: Some(VersionInformation {
+            chosen_version: PROTOCOL_VERSION_V1,
+            available_versions: vec![PROTOCOL_VERSION_V1, PROTOCOL_VERSION_V2],
+        }),
+        initial_max_path_id: Some(2),
+        min_ack_delay: Some(1000),
         unknown_params: Default::default(),
+        preferred_address: None,
     };
 
     let mut raw_params = [42; 256];
     let raw_params =
         TransportParams::encode(&tp, false, &mut raw_params).unwrap();
-    assert_eq!(raw_params.len(), 69);
+    assert_eq!(raw_params.len(), 104);
 
     let new_tp = TransportParams::decode(raw_params, true, None).unwrap();
 
     assert_eq!(new_tp, tp);
 }
 
+#[test]
+fn transport_params_preferred_address() {
+    let tp = TransportParams {
+        preferred_address: Some(PreferredAddress {
+            ipv4: None,
+            ipv6: Some("[2001:db8::1]:443".parse().unwrap()),
+            connection_id: b"preferred".to_vec().into(),
+            stateless_reset_token: u128::from_be_bytes([0xab; 16]),
+        }),
+        ..Default::default()
+    };
+
+    let mut raw_params = [42; 256];
+    let raw_params = TransportParams::encode(&tp, true, &mut raw_params).unwrap();
+
+    let new_tp = TransportParams::decode(raw_params, false, None).unwrap();
+    assert_eq!(new_tp.preferred_address, tp.preferred_address);
+
+    // Only servers can send the preferred address.
+    assert_eq!(
+        TransportParams::decode(raw_params, true, None),
+        Err(Error::InvalidTransportParam)
+    );
+
+    // Clients never encode it.
+    let mut raw_params = [42; 256];
+    let raw_params =
+        TransportParams::encode(&tp, false, &mut raw_params).unwrap();
+
+    let new_tp = TransportParams::decode(raw_params, true, None).unwrap();
+    assert_eq!(new_tp.preferred_address, None);
+
+    // The connection ID can't be empty.
+    let tp = TransportParams {
+        preferred_address: Some(PreferredAddress {
+            ipv4: Some("192.0.2.1:443".parse().unwrap()),
+            ipv6: None,
+            connection_id: ConnectionId::default(),
+            stateless_reset_token: 0,
+        }),
+        ..Default::default()
+    };
+
+    let mut raw_params = [42; 256];
+    let raw_params = TransportParams::encode(&tp, true, &mut raw_params).unwrap();
+
+    assert_eq!(
+        TransportParams::decode(raw_params, false, None),
+        Err(Error::InvalidTransportParam)
+    );
+}
+
+#[test]
+fn transport_params_version_information() {
+    // Chosen version only.
+    let raw_params = [0x11, 4, 0x6b, 0x33, 0x43, 0xcf];
+    let tp = TransportParams::decode(&raw_params, true, None).unwrap();
+
+    assert_eq!(
+        tp.version_information,
+        Some(VersionInformation {
+            chosen_version: PROTOCOL_VERSION_V2,
+            available_versions: vec![],
+        })
+    );
+
+    // Chosen version can't be zero.
+    let raw_params = [0x11, 4, 0, 0, 0, 0];
+    assert_eq!(
+        TransportParams::decode(&raw_params, true, None),
+        Err(Error::InvalidTransportParam)
+    );
+
+    // Available versions can't be zero.
+    let raw_params = [0x11, 8, 0, 0, 0, 1, 0, 0, 0, 0];
+    assert_eq!(
+        TransportParams::decode(&raw_params, true, None),
+        Err(Error::InvalidTransportParam)
+    );
+
+    // Available versions must be 32-bit values.
+    let raw_params = [0x11, 6, 0, 0, 0, 1, 0, 0];
+    assert_eq!(
+        TransportParams::decode(&raw_params, true, None),
+        Err(Error::InvalidTransportParam)
+    );
+}
+
 #[test]
 fn transport_params_forbid_duplicates() {
     // Given an encoded param.
@@ -266,26 +372,152 @@ fn version_negotiation() {
 }
 
 #[test]
-fn verify_custom_root() {
-    let mut config = Config::new(PROTOCOL_VERSION).unwrap();
-    config.verify_peer(true);
+fn version_negotiation_lists_v2() {
+    let mut buf = [0; 128];
+
+    let scid = ConnectionId::from_ref(&[0xba; 16]);
+    let dcid = ConnectionId::from_ref(&[0xbb; 16]);
+
+    let len = negotiate_version(&scid, &dcid, &mut buf).unwrap();
+
+    let hdr = Header::from_slice(&mut buf[..len], 0).unwrap();
+    assert_eq!(hdr.ty, Type::VersionNegotiation);
+    assert_eq!(
+        hdr.versions,
+        Some(vec![PROTOCOL_VERSION_V1, PROTOCOL_VERSION_V2])
+    );
+}
+
+#[test]
+fn handshake_v2() {
+    let mut b = [0; 15];
+
+    let mut config = Config::new(0x6b3343cf).unwrap();
     config
-        .load_verify_locations_from_file("examples/rootca.crt")
+        .load_cert_chain_from_pem_file("examples/cert.crt")
+        .unwrap();
+    config
+        .load_priv_key_from_pem_file("examples/cert.key")
         .unwrap();
     config
         .set_application_protos(&[b"proto1", b"proto2"])
         .unwrap();
+    config.set_initial_max_data(30);
+    config.set_initial_max_stream_data_bidi_local(15);
+    config.set_initial_max_stream_data_bidi_remote(15);
+    config.set_initial_max_streams_bidi(3);
+    config.verify_peer(false);
 
-    let mut pipe = test_utils::Pipe::with_client_config(&mut config).unwrap();
+    let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
     assert_eq!(pipe.handshake(), Ok(()));
+
+    assert_eq!(pipe.client.version, PROTOCOL_VERSION_V2);
+    assert_eq!(pipe.server.version, PROTOCOL_VERSION_V2);
+
+    assert_eq!(pipe.client.stream_send(4, b"hello", false), Ok(5));
+    assert_eq!(pipe.advance(), Ok(()));
+
+    assert_eq!(pipe.server.stream_recv(4, &mut b), Ok((5, false)));
+    assert_eq!(&b[..5], b"hello");
+
+    // Keys are updated using the QUIC v2 label.
+    assert_eq!(pipe.client_update_key(), Ok(()));
+    assert_eq!(pipe.client.stream_send(4, b"world", false), Ok(5));
+    assert_eq!(pipe.advance(), Ok(()));
+
+    assert_eq!(pipe.server.stream_recv(4, &mut b), Ok((5, false)));
+    assert_eq!(&b[..5], b"world");
+}
+
+#[rstest]
+fn retry_v2(
+    #[values(PROTOCOL_VERSION_V1, PROTOCOL_VERSION_V2)] server_version: u32,
+) {
+    let mut buf = [0; 65535];
+
+    let mut client_config = Config::new(PROTOCOL_VERSION_V2).unwrap();
+    client_config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    client_config.verify_peer(false);
+
+    let mut server_config = Config::new(server_version).unwrap();
+    server_config
+        .load_cert_chain_from_pem_file("examples/cert.crt")
+        .unwrap();
+    server_config
+        .load_priv_key_from_pem_file("examples/cert.key")
+        .unwrap();
+    server_config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
+
+    // Client sends initial flight.
+    let (mut len, _) = pipe.client.send(&mut buf).unwrap();
+
+    // Server sends Retry packet.
+    let hdr = Header::from_slice(&mut buf[..len], MAX_CONN_ID_LEN).unwrap();
+    assert_eq!(hdr.ty, Type::Initial);
+    assert_eq!(hdr.version, PROTOCOL_VERSION_V2);
+
+    let odcid = hdr.dcid.clone();
+
+    let mut scid = [0; MAX_CONN_ID_LEN];
+    rand::rand_bytes(&mut scid[..]);
+    let scid = ConnectionId::from_ref(&scid);
+
+    let token = b"quiche test retry token";
+
+    len =
+        packet::retry(&hdr.scid, &hdr.dcid, &scid, token, hdr.version, &mut buf)
+            .unwrap();
+
+    // Client receives Retry and sends new Initial.
+    assert_eq!(pipe.client_recv(&mut buf[..len]), Ok(len));
+
+    let (len, send_info) = pipe.client.send(&mut buf).unwrap();
+
+    let hdr = Header::from_slice(&mut buf[..len], MAX_CONN_ID_LEN).unwrap();
+    assert_eq!(&hdr.token.unwrap(), token);
+
+    // Server accepts connection.
+    pipe.server = accept(
+        &scid,
+        Some(&odcid),
+        test_utils::Pipe::server_addr(),
+        send_info.from,
+        &mut server_config,
+    )
+    .unwrap();
+    assert_eq!(pipe.server_recv(&mut buf[..len]), Ok(len));
+
+    assert_eq!(pipe.advance(), Ok(()));
+
+    assert!(pipe.client.is_established());
+    assert!(pipe.server.is_established());
+
+    // The server switches to its preferred version after the retry, if needed.
+    assert_eq!(pipe.client.version, server_version);
+    assert_eq!(pipe.server.version, server_version);
 }
 
-// Disable this for openssl as it seems to fail for some reason. It could be
-// because of the way the get_certs API differs from bssl.
-#[cfg(not(feature = "openssl"))]
 #[test]
-fn verify_client_invalid() {
-    let mut server_config = Config::new(PROTOCOL_VERSION).unwrap();
+fn compatible_version_negotiation() {
+    let mut buf = [0; 65535];
+
+    let mut client_config = Config::new(PROTOCOL_VERSION_V1).unwrap();
+    client_config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    client_config.verify_peer(false);
+
+    let mut server_config = Config::new(PROTOCOL_VERSION_V2).unwrap();
     server_config
         .load_cert_chain_from_pem_file("examples/cert.crt")
         .unwrap();
@@ -300,45 +532,93 @@ fn verify_client_invalid() {
     server_config.set_initial_max_stream_data_bidi_remote(15);
     server_config.set_initial_max_streams_bidi(3);
 
-    // The server shouldn't be able to verify the client's certificate due
-    // to missing CA.
-    server_config.verify_peer(true);
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
 
-    let mut client_config = Config::new(PROTOCOL_VERSION).unwrap();
+    // Client sends its first flight using v1.
+    let (len, _) = pipe.client.send(&mut buf).unwrap();
+
+    let hdr = Header::from_slice(&mut buf[..len], MAX_CONN_ID_LEN).unwrap();
+    assert_eq!(hdr.version, PROTOCOL_VERSION_V1);
+
+    assert_eq!(pipe.server_recv(&mut buf[..len]), Ok(len));
+
+    // Server replies using v2 straight away.
+    let (len, _) = pipe.server.send(&mut buf).unwrap();
+
+    let hdr = Header::from_slice(&mut buf[..len], MAX_CONN_ID_LEN).unwrap();
+    assert_eq!(hdr.ty, Type::Initial);
+    assert_eq!(hdr.version, PROTOCOL_VERSION_V2);
+
+    assert_eq!(pipe.client_recv(&mut buf[..len]), Ok(len));
+    assert_eq!(pipe.client.version(), PROTOCOL_VERSION_V2);
+
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    assert_eq!(pipe.client.version(), PROTOCOL_VERSION_V2);
+    assert_eq!(pipe.server.version(), PROTOCOL_VERSION_V2);
+
+    assert_eq!(
+        pipe.client.peer_available_versions(),
+        Some(&[PROTOCOL_VERSION_V2, PROTOCOL_VERSION_V1][..])
+    );
+    assert_eq!(
+        pipe.server.peer_available_versions(),
+        Some(&[PROTOCOL_VERSION_V1, PROTOCOL_VERSION_V2][..])
+    );
+
+    assert_eq!(pipe.client.stream_send(4, b"hello", false), Ok(5));
+    assert_eq!(pipe.advance(), Ok(()));
+
+    let mut b = [0; 15];
+    assert_eq!(pipe.server.stream_recv(4, &mut b), Ok((5, false)));
+    assert_eq!(&b[..5], b"hello");
+}
+
+#[test]
+fn compatible_version_negotiation_not_available() {
+    let mut client_config = Config::new(PROTOCOL_VERSION_V1).unwrap();
     client_config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    client_config.verify_peer(false);
+
+    let mut server_config = Config::new(PROTOCOL_VERSION_V2).unwrap();
+    server_config
         .load_cert_chain_from_pem_file("examples/cert.crt")
         .unwrap();
-    client_config
+    server_config
         .load_priv_key_from_pem_file("examples/cert.key")
         .unwrap();
-    client_config
+    server_config
         .set_application_protos(&[b"proto1", b"proto2"])
         .unwrap();
-    client_config.set_initial_max_data(30);
-    client_config.set_initial_max_stream_data_bidi_local(15);
-    client_config.set_initial_max_stream_data_bidi_remote(15);
-    client_config.set_initial_max_streams_bidi(3);
-
-    // The client is able to verify the server's certificate with the
-    // appropriate CA.
-    client_config
-        .load_verify_locations_from_file("examples/rootca.crt")
-        .unwrap();
-    client_config.verify_peer(true);
 
     let mut pipe = test_utils::Pipe::with_client_and_server_config(
         &mut client_config,
         &mut server_config,
     )
     .unwrap();
-    assert_eq!(pipe.handshake(), Err(Error::TlsFail));
 
-    // Client did send a certificate.
-    assert!(pipe.server.peer_cert().is_some());
+    // Client only supports v1.
+    pipe.client.local_transport_params.version_information =
+        Some(VersionInformation {
+            chosen_version: PROTOCOL_VERSION_V1,
+            available_versions: vec![PROTOCOL_VERSION_V1],
+        });
+    pipe.client.encode_transport_params().unwrap();
+
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    assert_eq!(pipe.client.version(), PROTOCOL_VERSION_V1);
+    assert_eq!(pipe.server.version(), PROTOCOL_VERSION_V1);
 }
 
 #[test]
-fn verify_client_anonymous() {
+fn compatible_version_negotiation_downgrade() {
     let mut config = Config::new(PROTOCOL_VERSION).unwrap();
     config
         .load_cert_chain_from_pem_file("examples/cert.crt")
@@ -349,28 +629,169 @@ fn verify_client_anonymous() {
     config
         .set_application_protos(&[b"proto1", b"proto2"])
         .unwrap();
-    config.set_initial_max_data(30);
-    config.set_initial_max_stream_data_bidi_local(15);
-    config.set_initial_max_stream_data_bidi_remote(15);
-    config.set_initial_max_streams_bidi(3);
+    config.verify_peer(false);
 
-    // Try to validate client certificate.
-    config.verify_peer(true);
+    let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
 
-    let mut pipe = test_utils::Pipe::with_server_config(&mut config).unwrap();
-    assert_eq!(pipe.handshake(), Ok(()));
+    // Client claims to have chosen a different version than the one it's
+    // actually using.
+    let mut tp = pipe.client.local_transport_params.clone();
+    tp.version_information = Some(VersionInformation {
+        chosen_version: PROTOCOL_VERSION_V2,
+        available_versions: vec![PROTOCOL_VERSION_V2, PROTOCOL_VERSION_V1],
+    });
+    pipe.client
+        .handshake
+        .set_quic_transport_params(&tp, false)
+        .unwrap();
 
-    // Client didn't send a certificate.
-    assert!(pipe.server.peer_cert().is_none());
+    assert_eq!(pipe.handshake(), Err(Error::VersionNegotiation));
 }
 
-#[rstest]
-fn missing_initial_source_connection_id(
-    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
-) {
+#[test]
+fn version_negotiation_downgrade() {
     let mut buf = [0; 65535];
 
-    let mut pipe = test_utils::Pipe::new(cc_algorithm_name).unwrap();
+    let mut config = Config::new(0xbabababa).unwrap();
+    config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    config.verify_peer(false);
+
+    let mut pipe = test_utils::Pipe::with_client_config(&mut config).unwrap();
+
+    let (mut len, _) = pipe.client.send(&mut buf).unwrap();
+
+    let hdr = Header::from_slice(&mut buf[..len], 0).unwrap();
+    len = negotiate_version(&hdr.scid, &hdr.dcid, &mut buf).unwrap();
+
+    assert_eq!(pipe.client_recv(&mut buf[..len]), Ok(len));
+    assert_eq!(pipe.client.version(), PROTOCOL_VERSION_V1);
+
+    pipe.client.local_transport_params.version_information =
+        Some(VersionInformation {
+            chosen_version: PROTOCOL_VERSION_V1,
+            available_versions: vec![PROTOCOL_VERSION_V1],
+        });
+    pipe.client.encode_transport_params().unwrap();
+
+    // Server claims to only support v2, so the client would have picked that
+    // had the Version Negotiation packet not been tampered with.
+    pipe.server.local_transport_params.version_information =
+        Some(VersionInformation {
+            chosen_version: PROTOCOL_VERSION_V1,
+            available_versions: vec![PROTOCOL_VERSION_V2],
+        });
+
+    assert_eq!(pipe.handshake(), Err(Error::VersionNegotiation));
+}
+
+#[test]
+fn verify_custom_root() {
+    let mut config = Config::new(PROTOCOL_VERSION).unwrap();
+    config.verify_peer(true);
+    config
+        .load_verify_locations_from_file("examples/rootca.crt")
+        .unwrap();
+    config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+
+    let mut pipe = test_utils::Pipe::with_client_config(&mut config).unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+}
+
+// Disable this for openssl as it seems to fail for some reason. It could be
+// because of the way the get_certs API differs from bssl.
+#[cfg(not(feature = "openssl"))]
+#[test]
+fn verify_client_invalid() {
+    let mut server_config = Config::new(PROTOCOL_VERSION).unwrap();
+    server_config
+        .load_cert_chain_from_pem_file("examples/cert.crt")
+        .unwrap();
+    server_config
+        .load_priv_key_from_pem_file("examples/cert.key")
+        .unwrap();
+    server_config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    server_config.set_initial_max_data(30);
+    server_config.set_initial_max_stream_data_bidi_local(15);
+    server_config.set_initial_max_stream_data_bidi_remote(15);
+    server_config.set_initial_max_streams_bidi(3);
+
+    // The server shouldn't be able to verify the client's certificate due
+    // to missing CA.
+    server_config.verify_peer(true);
+
+    let mut client_config = Config::new(PROTOCOL_VERSION).unwrap();
+    client_config
+        .load_cert_chain_from_pem_file("examples/cert.crt")
+        .unwrap();
+    client_config
+        .load_priv_key_from_pem_file("examples/cert.key")
+        .unwrap();
+    client_config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    client_config.set_initial_max_data(30);
+    client_config.set_initial_max_stream_data_bidi_local(15);
+    client_config.set_initial_max_stream_data_bidi_remote(15);
+    client_config.set_initial_max_streams_bidi(3);
+
+    // The client is able to verify the server's certificate with the
+    // appropriate CA.
+    client_config
+        .load_verify_locations_from_file("examples/rootca.crt")
+        .unwrap();
+    client_config.verify_peer(true);
+
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
+    assert_eq!(pipe.handshake(), Err(Error::TlsFail));
+
+    // Client did send a certificate.
+    assert!(pipe.server.peer_cert().is_some());
+}
+
+#[test]
+fn verify_client_anonymous() {
+    let mut config = Config::new(PROTOCOL_VERSION).unwrap();
+    config
+        .load_cert_chain_from_pem_file("examples/cert.crt")
+        .unwrap();
+    config
+        .load_priv_key_from_pem_file("examples/cert.key")
+        .unwrap();
+    config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    config.set_initial_max_data(30);
+    config.set_initial_max_stream_data_bidi_local(15);
+    config.set_initial_max_stream_data_bidi_remote(15);
+    config.set_initial_max_streams_bidi(3);
+
+    // Try to validate client certificate.
+    config.verify_peer(true);
+
+    let mut pipe = test_utils::Pipe::with_server_config(&mut config).unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    // Client didn't send a certificate.
+    assert!(pipe.server.peer_cert().is_none());
+}
+
+#[rstest]
+fn missing_initial_source_connection_id(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut buf = [0; 65535];
+
+    let mut pipe = test_utils::Pipe::new(cc_algorithm_name).unwrap();
 
     // Reset initial_source_connection_id.
     pipe.client
@@ -613,42 +1034,11 @@ fn handshake_resumption(
     assert!(pipe.server.is_resumed());
 }
 
-#[rstest]
-fn handshake_alpn_mismatch(
-    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
-) {
-    let mut buf = [0; 65535];
-
-    let mut config = Config::new(PROTOCOL_VERSION).unwrap();
-    assert_eq!(config.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
-    config
-        .set_application_protos(&[b"proto3\x06proto4"])
-        .unwrap();
-    config.verify_peer(false);
-
-    let mut pipe = test_utils::Pipe::with_client_config(&mut config).unwrap();
-    assert_eq!(pipe.handshake(), Err(Error::TlsFail));
-
-    assert_eq!(pipe.client.application_proto(), b"");
-    assert_eq!(pipe.server.application_proto(), b"");
-
-    // Server should only send one packet in response to ALPN mismatch.
-    let (len, _) = pipe.server.send(&mut buf).unwrap();
-    assert_eq!(len, 1200);
-
-    assert_eq!(pipe.server.send(&mut buf), Err(Error::Done));
-    assert_eq!(pipe.server.sent_count, 1);
-}
-
-#[cfg(not(feature = "openssl"))] // 0-RTT not supported when using openssl/quictls
-#[rstest]
-fn handshake_0rtt(
-    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
-) {
-    let mut buf = [0; 65535];
+#[test]
+fn handshake_resumption_ticket_key_ring() {
+    let ring = Arc::new(TicketKeyRing::new(&[0xa; TICKET_KEY_LEN], 1).unwrap());
 
     let mut config = Config::new(PROTOCOL_VERSION).unwrap();
-    assert_eq!(config.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
     config
         .load_cert_chain_from_pem_file("examples/cert.crt")
         .unwrap();
@@ -662,127 +1052,394 @@ fn handshake_0rtt(
     config.set_initial_max_stream_data_bidi_local(15);
     config.set_initial_max_stream_data_bidi_remote(15);
     config.set_initial_max_streams_bidi(3);
-    config.enable_early_data();
-    config.verify_peer(false);
+    assert_eq!(config.set_ticket_key_ring(Arc::clone(&ring)), Ok(()));
 
-    // Perform initial handshake.
-    let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
+    // Perform initial handshake, the ticket is encrypted with generation 0.
+    let mut pipe = test_utils::Pipe::with_server_config(&mut config).unwrap();
     assert_eq!(pipe.handshake(), Ok(()));
 
-    // Extract session,
-    let session = pipe.client.session().unwrap();
+    assert!(!pipe.server.is_resumed());
+    assert_eq!(pipe.server.stats().ticket_key_generation, None);
 
-    // Configure session on new connection.
-    let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
-    assert_eq!(pipe.client.set_session(session), Ok(()));
+    let session_gen0 = pipe.client.session().unwrap().to_vec();
 
-    // Client sends initial flight.
-    let (len, _) = pipe.client.send(&mut buf).unwrap();
-    assert_eq!(pipe.server_recv(&mut buf[..len]), Ok(len));
+    // Rotate the key while the configuration is in use. The previous key is
+    // still accepted, and the ticket renewed with the current key.
+    assert_eq!(ring.rotate(&[0xb; TICKET_KEY_LEN]), Ok(1));
 
-    // Client sends 0-RTT packet.
-    let pkt_type = Type::ZeroRTT;
+    let mut pipe = test_utils::Pipe::with_server_config(&mut config).unwrap();
+    assert_eq!(pipe.client.set_session(&session_gen0), Ok(()));
+    assert_eq!(pipe.handshake(), Ok(()));
 
-    let frames = [frame::Frame::Stream {
-        stream_id: 4,
-        data: <RangeBuf>::from(b"aaaaa", 0, true),
-    }];
+    assert!(pipe.client.is_resumed());
+    assert!(pipe.server.is_resumed());
+    assert_eq!(pipe.server.stats().ticket_key_generation, Some(0));
 
-    assert_eq!(
-        pipe.send_pkt_to_server(pkt_type, &frames, &mut buf),
-        Ok(1200)
-    );
+    let session_gen1 = pipe.client.session().unwrap().to_vec();
 
-    assert_eq!(pipe.server.undecryptable_pkts.len(), 0);
+    // Rotate again, generation 0 is dropped from the ring.
+    assert_eq!(ring.rotate(&[0xc; TICKET_KEY_LEN]), Ok(2));
 
-    // 0-RTT stream data is readable.
-    let mut r = pipe.server.readable();
-    assert_eq!(r.next(), Some(4));
-    assert_eq!(r.next(), None);
+    let mut pipe = test_utils::Pipe::with_server_config(&mut config).unwrap();
+    assert_eq!(pipe.client.set_session(&session_gen0), Ok(()));
+    assert_eq!(pipe.handshake(), Ok(()));
 
-    let mut b = [0; 15];
-    assert_eq!(pipe.server.stream_recv(4, &mut b), Ok((5, true)));
-    assert_eq!(&b[..5], b"aaaaa");
+    assert!(!pipe.client.is_resumed());
+    assert!(!pipe.server.is_resumed());
+    assert_eq!(pipe.server.stats().ticket_key_generation, None);
+    assert_eq!(ring.unknown_key_count(), 1);
+
+    // The renewed ticket is still accepted.
+    let mut pipe = test_utils::Pipe::with_server_config(&mut config).unwrap();
+    assert_eq!(pipe.client.set_session(&session_gen1), Ok(()));
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    assert!(pipe.client.is_resumed());
+    assert!(pipe.server.is_resumed());
+
+    let stats = pipe.server.stats();
+    assert_eq!(stats.ticket_key_generation, Some(1));
+    assert_eq!(stats.ticket_key_stats, [
+        TicketKeyStats {
+            generation: 2,
+            resumed: 0,
+            failed: 0,
+        },
+        TicketKeyStats {
+            generation: 1,
+            resumed: 1,
+            failed: 0,
+        },
+    ]);
 }
 
-#[cfg(not(feature = "openssl"))] // 0-RTT not supported when using openssl/quictls
-#[rstest]
-fn handshake_0rtt_reordered(
-    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
-) {
-    let mut buf = [0; 65535];
+#[cfg(not(feature = "openssl"))] // ECH not supported when using openssl/quictls
+#[test]
+fn ech() {
+    let key = EchKey::generate(1, "public.example", 32).unwrap();
+    let ech_config_list =
+        EchKey::config_list(std::slice::from_ref(&key)).unwrap();
 
-    let mut config = Config::new(PROTOCOL_VERSION).unwrap();
-    assert_eq!(config.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
-    config
+    let mut server_config = Config::new(PROTOCOL_VERSION).unwrap();
+    server_config
         .load_cert_chain_from_pem_file("examples/cert.crt")
         .unwrap();
-    config
+    server_config
         .load_priv_key_from_pem_file("examples/cert.key")
         .unwrap();
-    config
+    server_config
         .set_application_protos(&[b"proto1", b"proto2"])
         .unwrap();
-    config.set_initial_max_data(30);
-    config.set_initial_max_stream_data_bidi_local(15);
-    config.set_initial_max_stream_data_bidi_remote(15);
-    config.set_initial_max_streams_bidi(3);
-    config.enable_early_data();
-    config.verify_peer(false);
+    assert_eq!(server_config.set_ech_keys(&[key]), Ok(()));
 
-    // Perform initial handshake.
-    let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
+    let mut client_config = Config::new(PROTOCOL_VERSION).unwrap();
+    client_config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    client_config.verify_peer(false);
+    client_config.set_ech_config_list(&ech_config_list);
+
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
     assert_eq!(pipe.handshake(), Ok(()));
 
-    // Extract session,
-    let session = pipe.client.session().unwrap();
+    assert!(pipe.client.ech_accepted());
+    assert!(pipe.server.ech_accepted());
+    assert_eq!(pipe.client.ech_retry_configs(), None);
 
-    // Configure session on new connection.
-    let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
-    assert_eq!(pipe.client.set_session(session), Ok(()));
+    // The server sees the inner server name, not the public name.
+    assert_eq!(pipe.server.server_name(), Some("quic.tech"));
+}
 
-    // Client sends initial flight.
-    let (len, _) = pipe.client.send(&mut buf).unwrap();
-    let mut initial = buf[..len].to_vec();
+#[cfg(not(feature = "openssl"))] // ECH not supported when using openssl/quictls
+#[test]
+fn ech_rejected() {
+    let key = EchKey::generate(1, "public.example", 32).unwrap();
+    let ech_config_list =
+        EchKey::config_list(std::slice::from_ref(&key)).unwrap();
 
-    // Client sends 0-RTT packet.
-    let pkt_type = Type::ZeroRTT;
+    let stale_key = EchKey::generate(2, "public.example", 32).unwrap();
+    let stale_ech_config_list = EchKey::config_list(&[stale_key]).unwrap();
 
-    let frames = [frame::Frame::Stream {
-        stream_id: 4,
-        data: <RangeBuf>::from(b"aaaaa", 0, true),
-    }];
+    let mut server_config = Config::new(PROTOCOL_VERSION).unwrap();
+    server_config
+        .load_cert_chain_from_pem_file("examples/cert.crt")
+        .unwrap();
+    server_config
+        .load_priv_key_from_pem_file("examples/cert.key")
+        .unwrap();
+    server_config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    assert_eq!(server_config.set_ech_keys(&[key]), Ok(()));
 
-    let len =
-        test_utils::encode_pkt(&mut pipe.client, pkt_type, &frames, &mut buf)
-            .unwrap();
-    let mut zrtt = buf[..len].to_vec();
+    let mut client_config = Config::new(PROTOCOL_VERSION).unwrap();
+    client_config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    client_config.verify_peer(false);
+    client_config.set_ech_config_list(&stale_ech_config_list);
 
-    // 0-RTT packet is received before the Initial one.
-    assert_eq!(pipe.server_recv(&mut zrtt), Ok(zrtt.len()));
+    // The server can't decrypt the ClientHello, and handles the handshake
+    // using the public name, which the client then aborts.
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
+    assert_eq!(pipe.handshake(), Err(Error::TlsFail));
 
-    assert_eq!(pipe.server.undecryptable_pkts.len(), 1);
-    assert_eq!(pipe.server.undecryptable_pkts[0].0.len(), zrtt.len());
+    assert!(!pipe.client.ech_accepted());
+    assert_eq!(pipe.server.server_name(), Some("public.example"));
+    assert_eq!(
+        pipe.client.ech_retry_configs(),
+        Some(ech_config_list.as_slice())
+    );
 
-    let mut r = pipe.server.readable();
-    assert_eq!(r.next(), None);
+    // Retrying with the configurations sent by the server succeeds.
+    let retry_configs = pipe.client.ech_retry_configs().unwrap().to_vec();
 
-    // Initial packet is also received.
-    assert_eq!(pipe.server_recv(&mut initial), Ok(initial.len()));
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
+    assert_eq!(pipe.client.set_ech_config_list(&retry_configs), Ok(()));
+    assert_eq!(
+        pipe.server.set_ech_config_list(&retry_configs),
+        Err(Error::InvalidState)
+    );
+    assert_eq!(pipe.handshake(), Ok(()));
 
-    // 0-RTT stream data is readable.
-    let mut r = pipe.server.readable();
-    assert_eq!(r.next(), Some(4));
-    assert_eq!(r.next(), None);
+    assert!(pipe.client.ech_accepted());
+    assert_eq!(pipe.server.server_name(), Some("quic.tech"));
+}
 
-    let mut b = [0; 15];
-    assert_eq!(pipe.server.stream_recv(4, &mut b), Ok((5, true)));
-    assert_eq!(&b[..5], b"aaaaa");
+/// Returns whether the client's ClientHello carries an ECH extension.
+#[cfg(not(feature = "openssl"))]
+fn client_hello_has_ech(pipe: &mut test_utils::Pipe) -> bool {
+    const ECH_EXTENSION_TYPE: u16 = 0xfe0d;
+
+    let mut buf = [0; 65535];
+
+    assert_eq!(pipe.client.do_handshake(Instant::now()), Ok(()));
+
+    let (len, _) = pipe.client.crypto_ctx[packet::Epoch::Initial]
+        .crypto_stream
+        .send
+        .emit(&mut buf)
+        .unwrap();
+
+    let mut b = octets::Octets::with_slice(&buf[..len]);
+
+    // Handshake message header, for a ClientHello.
+    assert_eq!(b.get_u8(), Ok(1));
+    let hello_len = b.get_u24().unwrap() as usize;
+    let mut hello = b.get_bytes(hello_len).unwrap();
+
+    // Version and random.
+    hello.skip(2 + 32).unwrap();
+
+    // Session ID, cipher suites and compression methods.
+    hello.get_bytes_with_u8_length().unwrap();
+    hello.get_bytes_with_u16_length().unwrap();
+    hello.get_bytes_with_u8_length().unwrap();
+
+    let mut extensions = hello.get_bytes_with_u16_length().unwrap();
+
+    while extensions.cap() > 0 {
+        let ty = extensions.get_u16().unwrap();
+        extensions.get_bytes_with_u16_length().unwrap();
+
+        if ty == ECH_EXTENSION_TYPE {
+            return true;
+        }
+    }
+
+    false
+}
+
+#[cfg(not(feature = "openssl"))] // ECH not supported when using openssl/quictls
+#[test]
+fn ech_grease() {
+    let mut client_config = Config::new(PROTOCOL_VERSION).unwrap();
+    client_config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    client_config.verify_peer(false);
+
+    for grease in [true, false] {
+        client_config.set_ech_grease(grease);
+
+        // A GREASE ECH extension is only sent when enabled.
+        let mut pipe =
+            test_utils::Pipe::with_client_config(&mut client_config).unwrap();
+        assert_eq!(client_hello_has_ech(&mut pipe), grease);
+
+        // The server ignores it, and the handshake completes without ECH.
+        let mut pipe =
+            test_utils::Pipe::with_client_config(&mut client_config).unwrap();
+        assert_eq!(pipe.handshake(), Ok(()));
+
+        assert!(!pipe.client.ech_accepted());
+        assert!(!pipe.server.ech_accepted());
+        assert_eq!(pipe.client.ech_retry_configs(), None);
+    }
+
+    // A real ECH configuration replaces the GREASE extension.
+    let key = EchKey::generate(1, "public.example", 32).unwrap();
+    let ech_config_list = EchKey::config_list(&[key]).unwrap();
+
+    client_config.set_ech_grease(false);
+    client_config.set_ech_config_list(&ech_config_list);
+
+    let mut pipe =
+        test_utils::Pipe::with_client_config(&mut client_config).unwrap();
+    assert!(client_hello_has_ech(&mut pipe));
+}
+
+#[test]
+fn new_token() {
+    let mut server_config = Config::new(PROTOCOL_VERSION).unwrap();
+    server_config
+        .load_cert_chain_from_pem_file("examples/cert.crt")
+        .unwrap();
+    server_config
+        .load_priv_key_from_pem_file("examples/cert.key")
+        .unwrap();
+    server_config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    server_config.set_initial_max_data(30);
+    server_config.set_initial_max_stream_data_bidi_local(15);
+    server_config.set_initial_max_stream_data_bidi_remote(15);
+    server_config.set_initial_max_streams_bidi(3);
+    server_config
+        .set_token_validator(Arc::new(HmacTokenValidator::new(&[0xa; 32])));
+
+    let cache = Arc::new(TokenCache::new(8));
+
+    let mut client_config = Config::new(PROTOCOL_VERSION).unwrap();
+    client_config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    client_config.verify_peer(false);
+    client_config.set_token_cache(Arc::clone(&cache));
+
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
+
+    // The client's address isn't validated without a token.
+    let flight = test_utils::emit_flight(&mut pipe.client).unwrap();
+    test_utils::process_flight(&mut pipe.server, flight).unwrap();
+    assert!(
+        !pipe
+            .server
+            .paths
+            .get_active()
+            .unwrap()
+            .verified_peer_address
+    );
+
+    assert_eq!(pipe.advance(), Ok(()));
+    assert!(pipe.client.is_established());
+    assert!(!pipe.server.new_token_pending);
+    assert_eq!(cache.len(), 1);
+
+    // The next connection to the same server uses the token, which validates
+    // the client's address from its first Initial packet.
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
+    assert!(cache.is_empty());
+
+    let flight = test_utils::emit_flight(&mut pipe.client).unwrap();
+    test_utils::process_flight(&mut pipe.server, flight).unwrap();
+    assert!(
+        pipe.server
+            .paths
+            .get_active()
+            .unwrap()
+            .verified_peer_address
+    );
+
+    assert_eq!(pipe.advance(), Ok(()));
+    assert!(pipe.client.is_established());
+    assert_eq!(cache.len(), 1);
+
+    // A server that can't validate the token ignores it.
+    let mut server_config = Config::new(PROTOCOL_VERSION).unwrap();
+    server_config
+        .load_cert_chain_from_pem_file("examples/cert.crt")
+        .unwrap();
+    server_config
+        .load_priv_key_from_pem_file("examples/cert.key")
+        .unwrap();
+    server_config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    server_config
+        .set_token_validator(Arc::new(HmacTokenValidator::new(&[0xb; 32])));
+
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
+
+    let flight = test_utils::emit_flight(&mut pipe.client).unwrap();
+    test_utils::process_flight(&mut pipe.server, flight).unwrap();
+    assert!(
+        !pipe
+            .server
+            .paths
+            .get_active()
+            .unwrap()
+            .verified_peer_address
+    );
+
+    assert_eq!(pipe.advance(), Ok(()));
+    assert!(pipe.client.is_established());
+}
+
+#[rstest]
+fn handshake_alpn_mismatch(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut buf = [0; 65535];
+
+    let mut config = Config::new(PROTOCOL_VERSION).unwrap();
+    assert_eq!(config.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
+    config
+        .set_application_protos(&[b"proto3\x06proto4"])
+        .unwrap();
+    config.verify_peer(false);
+
+    let mut pipe = test_utils::Pipe::with_client_config(&mut config).unwrap();
+    assert_eq!(pipe.handshake(), Err(Error::TlsFail));
+
+    assert_eq!(pipe.client.application_proto(), b"");
+    assert_eq!(pipe.server.application_proto(), b"");
+
+    // Server should only send one packet in response to ALPN mismatch.
+    let (len, _) = pipe.server.send(&mut buf).unwrap();
+    assert_eq!(len, 1200);
+
+    assert_eq!(pipe.server.send(&mut buf), Err(Error::Done));
+    assert_eq!(pipe.server.sent_count, 1);
 }
 
 #[cfg(not(feature = "openssl"))] // 0-RTT not supported when using openssl/quictls
 #[rstest]
-fn handshake_0rtt_truncated(
+fn handshake_0rtt(
     #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
 ) {
     let mut buf = [0; 65535];
@@ -817,7 +1474,8 @@ fn handshake_0rtt_truncated(
     assert_eq!(pipe.client.set_session(session), Ok(()));
 
     // Client sends initial flight.
-    pipe.client.send(&mut buf).unwrap();
+    let (len, _) = pipe.client.send(&mut buf).unwrap();
+    assert_eq!(pipe.server_recv(&mut buf[..len]), Ok(len));
 
     // Client sends 0-RTT packet.
     let pkt_type = Type::ZeroRTT;
@@ -827,29 +1485,29 @@ fn handshake_0rtt_truncated(
         data: <RangeBuf>::from(b"aaaaa", 0, true),
     }];
 
-    let len =
-        test_utils::encode_pkt(&mut pipe.client, pkt_type, &frames, &mut buf)
-            .unwrap();
-
-    // Simulate a truncated packet by sending one byte less.
-    let mut zrtt = buf[..len - 1].to_vec();
-
-    // 0-RTT packet is received before the Initial one.
-    assert_eq!(pipe.server_recv(&mut zrtt), Err(Error::InvalidPacket));
+    assert_eq!(
+        pipe.send_pkt_to_server(pkt_type, &frames, &mut buf),
+        Ok(1200)
+    );
 
     assert_eq!(pipe.server.undecryptable_pkts.len(), 0);
 
-    assert!(pipe.server.is_closed());
+    // 0-RTT stream data is readable.
+    let mut r = pipe.server.readable();
+    assert_eq!(r.next(), Some(4));
+    assert_eq!(r.next(), None);
+
+    let mut b = [0; 15];
+    assert_eq!(pipe.server.stream_recv(4, &mut b), Ok((5, true)));
+    assert_eq!(&b[..5], b"aaaaa");
 }
 
-#[rstest]
-fn crypto_limit(
-    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
-) {
+#[cfg(not(feature = "openssl"))] // 0-RTT not supported when using openssl/quictls
+#[test]
+fn handshake_0rtt_anti_replay() {
     let mut buf = [0; 65535];
 
     let mut config = Config::new(PROTOCOL_VERSION).unwrap();
-    assert_eq!(config.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
     config
         .load_cert_chain_from_pem_file("examples/cert.crt")
         .unwrap();
@@ -866,26 +1524,273 @@ fn crypto_limit(
     config.enable_early_data();
     config.verify_peer(false);
 
+    let anti_replay = Arc::new(StrikeRegister::new(16));
+    config.set_anti_replay(anti_replay.clone());
+
     // Perform initial handshake.
     let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
     assert_eq!(pipe.handshake(), Ok(()));
+    assert!(anti_replay.is_empty());
 
-    // Client send a 1-byte frame that starts from the crypto stream offset
-    // limit.
-    let frames = [frame::Frame::Crypto {
-        data: RangeBuf::from(b"a", MAX_CRYPTO_STREAM_OFFSET, false),
-    }];
+    // Extract session,
+    let session = pipe.client.session().unwrap();
 
-    let pkt_type = Type::Short;
+    // Configure session on new connection.
+    let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
+    assert_eq!(pipe.client.set_session(session), Ok(()));
 
-    let written =
-        test_utils::encode_pkt(&mut pipe.client, pkt_type, &frames, &mut buf)
-            .unwrap();
+    // Client sends initial flight, and the server accepts early data.
+    let (len, _) = pipe.client.send(&mut buf).unwrap();
+    let mut initial = buf[..len].to_vec();
+
+    assert_eq!(pipe.server_recv(&mut buf[..len]), Ok(len));
+    assert!(pipe.server.is_in_early_data());
+    assert_eq!(anti_replay.len(), 1);
+
+    // The same flight replayed to another server is recorded as a replay, and
+    // early data is rejected.
+    let mut replay_pipe = test_utils::Pipe::with_config(&mut config).unwrap();
+    assert_eq!(replay_pipe.server_recv(&mut initial), Ok(initial.len()));
+    assert!(!replay_pipe.server.is_in_early_data());
+    assert_eq!(anti_replay.len(), 1);
+
+    // The original handshake completes.
+    assert_eq!(pipe.advance(), Ok(()));
+    assert!(pipe.client.is_established());
+    assert!(pipe.client.is_resumed());
+}
+
+#[cfg(not(feature = "openssl"))] // 0-RTT not supported when using openssl/quictls
+#[test]
+fn handshake_0rtt_anti_replay_unavailable() {
+    struct Unavailable;
+
+    impl AntiReplay for Unavailable {
+        fn record(&self, _key: &[u8]) -> bool {
+            false
+        }
+    }
+
+    let mut buf = [0; 65535];
+
+    let mut config = Config::new(PROTOCOL_VERSION).unwrap();
+    config
+        .load_cert_chain_from_pem_file("examples/cert.crt")
+        .unwrap();
+    config
+        .load_priv_key_from_pem_file("examples/cert.key")
+        .unwrap();
+    config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    config.set_initial_max_data(30);
+    config.set_initial_max_stream_data_bidi_local(15);
+    config.set_initial_max_stream_data_bidi_remote(15);
+    config.set_initial_max_streams_bidi(3);
+    config.enable_early_data();
+    config.verify_peer(false);
+    config.set_anti_replay(Arc::new(Unavailable));
+
+    let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    let session = pipe.client.session().unwrap();
+
+    let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
+    assert_eq!(pipe.client.set_session(session), Ok(()));
+
+    let (len, _) = pipe.client.send(&mut buf).unwrap();
+    assert_eq!(pipe.server_recv(&mut buf[..len]), Ok(len));
+    assert!(!pipe.server.is_in_early_data());
+
+    // The handshake falls back to 1-RTT.
+    assert_eq!(pipe.advance(), Ok(()));
+    assert!(pipe.client.is_established());
+    assert!(pipe.client.is_resumed());
+}
+
+#[cfg(not(feature = "openssl"))] // 0-RTT not supported when using openssl/quictls
+#[rstest]
+fn handshake_0rtt_reordered(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut buf = [0; 65535];
+
+    let mut config = Config::new(PROTOCOL_VERSION).unwrap();
+    assert_eq!(config.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
+    config
+        .load_cert_chain_from_pem_file("examples/cert.crt")
+        .unwrap();
+    config
+        .load_priv_key_from_pem_file("examples/cert.key")
+        .unwrap();
+    config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    config.set_initial_max_data(30);
+    config.set_initial_max_stream_data_bidi_local(15);
+    config.set_initial_max_stream_data_bidi_remote(15);
+    config.set_initial_max_streams_bidi(3);
+    config.enable_early_data();
+    config.verify_peer(false);
+
+    // Perform initial handshake.
+    let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    // Extract session,
+    let session = pipe.client.session().unwrap();
+
+    // Configure session on new connection.
+    let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
+    assert_eq!(pipe.client.set_session(session), Ok(()));
+
+    // Client sends initial flight.
+    let (len, _) = pipe.client.send(&mut buf).unwrap();
+    let mut initial = buf[..len].to_vec();
+
+    // Client sends 0-RTT packet.
+    let pkt_type = Type::ZeroRTT;
+
+    let frames = [frame::Frame::Stream {
+        stream_id: 4,
+        data: <RangeBuf>::from(b"aaaaa", 0, true),
+    }];
+
+    let len =
+        test_utils::encode_pkt(&mut pipe.client, pkt_type, &frames, &mut buf)
+            .unwrap();
+    let mut zrtt = buf[..len].to_vec();
+
+    // 0-RTT packet is received before the Initial one.
+    assert_eq!(pipe.server_recv(&mut zrtt), Ok(zrtt.len()));
+
+    assert_eq!(pipe.server.undecryptable_pkts.len(), 1);
+    assert_eq!(pipe.server.undecryptable_pkts[0].0.len(), zrtt.len());
+
+    let mut r = pipe.server.readable();
+    assert_eq!(r.next(), None);
+
+    // Initial packet is also received.
+    assert_eq!(pipe.server_recv(&mut initial), Ok(initial.len()));
+
+    // 0-RTT stream data is readable.
+    let mut r = pipe.server.readable();
+    assert_eq!(r.next(), Some(4));
+    assert_eq!(r.next(), None);
+
+    let mut b = [0; 15];
+    assert_eq!(pipe.server.stream_recv(4, &mut b), Ok((5, true)));
+    assert_eq!(&b[..5], b"aaaaa");
+}
+
+#[cfg(not(feature = "openssl"))] // 0-RTT not supported when using openssl/quictls
+#[rstest]
+fn handshake_0rtt_truncated(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut buf = [0; 65535];
+
+    let mut config = Config::new(PROTOCOL_VERSION).unwrap();
+    assert_eq!(config.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
+    config
+        .load_cert_chain_from_pem_file("examples/cert.crt")
+        .unwrap();
+    config
+        .load_priv_key_from_pem_file("examples/cert.key")
+        .unwrap();
+    config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    config.set_initial_max_data(30);
+    config.set_initial_max_stream_data_bidi_local(15);
+    config.set_initial_max_stream_data_bidi_remote(15);
+    config.set_initial_max_streams_bidi(3);
+    config.enable_early_data();
+    config.verify_peer(false);
+
+    // Perform initial handshake.
+    let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    // Extract session,
+    let session = pipe.client.session().unwrap();
+
+    // Configure session on new connection.
+    let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
+    assert_eq!(pipe.client.set_session(session), Ok(()));
+
+    // Client sends initial flight.
+    pipe.client.send(&mut buf).unwrap();
+
+    // Client sends 0-RTT packet.
+    let pkt_type = Type::ZeroRTT;
+
+    let frames = [frame::Frame::Stream {
+        stream_id: 4,
+        data: <RangeBuf>::from(b"aaaaa", 0, true),
+    }];
+
+    let len =
+        test_utils::encode_pkt(&mut pipe.client, pkt_type, &frames, &mut buf)
+            .unwrap();
+
+    // Simulate a truncated packet by sending one byte less.
+    let mut zrtt = buf[..len - 1].to_vec();
+
+    // 0-RTT packet is received before the Initial one.
+    assert_eq!(pipe.server_recv(&mut zrtt), Err(Error::InvalidPacket));
+
+    assert_eq!(pipe.server.undecryptable_pkts.len(), 0);
+
+    assert!(pipe.server.is_closed());
+}
+
+#[rstest]
+fn crypto_limit(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut buf = [0; 65535];
+
+    let mut config = Config::new(PROTOCOL_VERSION).unwrap();
+    assert_eq!(config.set_cc_algorithm_name(cc_algorithm_name), Ok(()));
+    config
+        .load_cert_chain_from_pem_file("examples/cert.crt")
+        .unwrap();
+    config
+        .load_priv_key_from_pem_file("examples/cert.key")
+        .unwrap();
+    config
+        .set_application_protos(&[b"proto1", b"proto2"])
+        .unwrap();
+    config.set_initial_max_data(30);
+    config.set_initial_max_stream_data_bidi_local(15);
+    config.set_initial_max_stream_data_bidi_remote(15);
+    config.set_initial_max_streams_bidi(3);
+    config.enable_early_data();
+    config.verify_peer(false);
+
+    // Perform initial handshake.
+    let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    // Client send a 1-byte frame that starts from the crypto stream offset
+    // limit.
+    let frames = [frame::Frame::Crypto {
+        data: RangeBuf::from(b"a", MAX_CRYPTO_STREAM_OFFSET, false),
+    }];
+
+    let pkt_type = Type::Short;
+
+    let written =
+        test_utils::encode_pkt(&mut pipe.client, pkt_type, &frames, &mut buf)
+            .unwrap();
 
     let active_path = pipe.server.paths.get_active().unwrap();
     let info = RecvInfo {
         to: active_path.local_addr(),
         from: active_path.peer_addr(),
+        ecn: Ecn::NotEct,
     };
 
     assert_eq!(
@@ -1233,38 +2138,187 @@ fn update_key_request_twice_error(
 }
 
 #[rstest]
-/// Tests that receiving a MAX_STREAM_DATA frame for a receive-only
-/// unidirectional stream is forbidden.
-fn max_stream_data_receive_uni(
+fn initiate_key_update(
     #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
 ) {
-    let mut buf = [0; 65535];
+    let mut b = [0; 15];
 
     let mut pipe = test_utils::Pipe::new(cc_algorithm_name).unwrap();
+
+    // Keys can't be updated before the handshake is confirmed.
+    assert_eq!(pipe.client.initiate_key_update(), Err(Error::InvalidState));
+
     assert_eq!(pipe.handshake(), Ok(()));
 
-    // Client opens unidirectional stream.
-    assert_eq!(pipe.client.stream_send(2, b"hello", false), Ok(5));
+    // The server acknowledges a packet sent with the current keys.
+    assert_eq!(pipe.client.stream_send(4, b"hello", false), Ok(5));
     assert_eq!(pipe.advance(), Ok(()));
+    assert_eq!(pipe.server.stream_recv(4, &mut b), Ok((5, false)));
 
-    // Client sends MAX_STREAM_DATA on local unidirectional stream.
-    let frames = [frame::Frame::MaxStreamData {
-        stream_id: 2,
-        max: 1024,
-    }];
+    assert_eq!(pipe.client.initiate_key_update(), Ok(()));
 
-    let pkt_type = Type::Short;
-    assert_eq!(
-        pipe.send_pkt_to_server(pkt_type, &frames, &mut buf),
-        Err(Error::InvalidStreamState(2)),
-    );
-}
+    // The previous update needs to be acknowledged first.
+    assert_eq!(pipe.client.initiate_key_update(), Err(Error::InvalidState));
 
-#[rstest]
-fn empty_payload(
-    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
-) {
-    let mut buf = [0; 65535];
+    assert_eq!(pipe.client.stream_send(4, b"hello", false), Ok(5));
+    assert_eq!(pipe.advance(), Ok(()));
+
+    // The server updated its keys too.
+    assert_eq!(pipe.server.stream_recv(4, &mut b), Ok((5, false)));
+    assert_eq!(&b[..5], b"hello");
+    assert_eq!(pipe.server.key_phase, pipe.client.key_phase);
+
+    assert_eq!(pipe.server.stream_send(4, b"world", false), Ok(5));
+    assert_eq!(pipe.advance(), Ok(()));
+
+    assert_eq!(pipe.client.stream_recv(4, &mut b), Ok((5, false)));
+    assert_eq!(&b[..5], b"world");
+
+    // Keys can be updated again.
+    assert_eq!(pipe.client.initiate_key_update(), Ok(()));
+
+    assert_eq!(pipe.client.stream_send(4, b"hello", false), Ok(5));
+    assert_eq!(pipe.advance(), Ok(()));
+    assert_eq!(pipe.server.stream_recv(4, &mut b), Ok((5, false)));
+
+    assert_eq!(pipe.client.stats().key_update_count_local, 2);
+    assert_eq!(pipe.client.stats().key_update_count_remote, 0);
+    assert_eq!(pipe.server.stats().key_update_count_local, 0);
+    assert_eq!(pipe.server.stats().key_update_count_remote, 2);
+}
+
+#[rstest]
+fn aead_confidentiality_limit(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut b = [0; 15];
+
+    let mut pipe = test_utils::Pipe::new(cc_algorithm_name).unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    assert_eq!(pipe.client.stream_send(4, b"hello", false), Ok(5));
+    assert_eq!(pipe.advance(), Ok(()));
+    assert_eq!(pipe.server.stream_recv(4, &mut b), Ok((5, false)));
+
+    let limit = pipe.client.crypto_ctx[packet::Epoch::Application]
+        .crypto_seal
+        .as_ref()
+        .unwrap()
+        .alg()
+        .confidentiality_limit();
+
+    // Keys are updated before reaching the limit.
+    pipe.client.key_phase_sent = limit / KEY_UPDATE_LIMIT_DIVISOR;
+
+    assert_eq!(pipe.client.stream_send(4, b"hello", false), Ok(5));
+    assert_eq!(pipe.advance(), Ok(()));
+    assert_eq!(pipe.server.stream_recv(4, &mut b), Ok((5, false)));
+
+    assert_eq!(pipe.client.stats().key_update_count_local, 1);
+    assert_eq!(pipe.server.stats().key_update_count_remote, 1);
+    assert!(pipe.client.key_phase_sent < 10);
+
+    // The connection is closed when keys can't be updated in time.
+    pipe.client.key_phase_sent = limit - AEAD_LIMIT_CLOSE_MARGIN;
+    pipe.client.key_phase_acked = false;
+
+    assert_eq!(pipe.client.stream_send(4, b"hello", false), Ok(5));
+    assert_eq!(pipe.advance(), Ok(()));
+
+    assert_eq!(
+        pipe.server.peer_error(),
+        Some(&ConnectionError {
+            is_app: false,
+            error_code: WireErrorCode::AeadLimitReached as u64,
+            reason: vec![],
+        })
+    );
+}
+
+#[rstest]
+fn aead_integrity_limit(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut buf = [0; 65535];
+
+    let mut pipe = test_utils::Pipe::new(cc_algorithm_name).unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    let frames = [frame::Frame::Ping { mtu_probe: None }];
+
+    let auth_failures = pipe.server.aead_auth_failures;
+
+    // Packets failing authentication are dropped.
+    let written =
+        test_utils::encode_pkt(&mut pipe.client, Type::Short, &frames, &mut buf)
+            .unwrap();
+    buf[written - 1] ^= 0xff;
+
+    assert_eq!(pipe.server_recv(&mut buf[..written]), Ok(written));
+    assert_eq!(pipe.server.aead_auth_failures, auth_failures + 1);
+
+    let limit = pipe.server.crypto_ctx[packet::Epoch::Application]
+        .crypto_open
+        .as_ref()
+        .unwrap()
+        .alg()
+        .integrity_limit();
+
+    // Until the integrity limit is reached.
+    pipe.server.aead_auth_failures = limit - 1;
+
+    let written =
+        test_utils::encode_pkt(&mut pipe.client, Type::Short, &frames, &mut buf)
+            .unwrap();
+    buf[written - 1] ^= 0xff;
+
+    assert_eq!(
+        pipe.server_recv(&mut buf[..written]),
+        Err(Error::AeadLimitReached)
+    );
+    assert_eq!(
+        pipe.server.local_error(),
+        Some(&ConnectionError {
+            is_app: false,
+            error_code: WireErrorCode::AeadLimitReached as u64,
+            reason: vec![],
+        })
+    );
+}
+
+#[rstest]
+/// Tests that receiving a MAX_STREAM_DATA frame for a receive-only
+/// unidirectional stream is forbidden.
+fn max_stream_data_receive_uni(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut buf = [0; 65535];
+
+    let mut pipe = test_utils::Pipe::new(cc_algorithm_name).unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    // Client opens unidirectional stream.
+    assert_eq!(pipe.client.stream_send(2, b"hello", false), Ok(5));
+    assert_eq!(pipe.advance(), Ok(()));
+
+    // Client sends MAX_STREAM_DATA on local unidirectional stream.
+    let frames = [frame::Frame::MaxStreamData {
+        stream_id: 2,
+        max: 1024,
+    }];
+
+    let pkt_type = Type::Short;
+    assert_eq!(
+        pipe.send_pkt_to_server(pkt_type, &frames, &mut buf),
+        Err(Error::InvalidStreamState(2)),
+    );
+}
+
+#[rstest]
+fn empty_payload(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut buf = [0; 65535];
 
     let mut pipe = test_utils::Pipe::new(cc_algorithm_name).unwrap();
     assert_eq!(pipe.handshake(), Ok(()));
@@ -4758,6 +5812,9 @@ fn sends_ack_only_pkt_when_full_cwnd_and_ack_elicited(
     config.set_initial_max_streams_uni(3);
     config.set_max_recv_udp_payload_size(1200);
     config.verify_peer(false);
+    // The GREASE ECH extension has a random length, which would make the
+    // BBR bandwidth estimate depend on it.
+    config.set_ech_grease(false);
 
     let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
     assert_eq!(pipe.handshake(), Ok(()));
@@ -4770,9 +5827,9 @@ fn sends_ack_only_pkt_when_full_cwnd_and_ack_elicited(
         if cc_algorithm_name == "cubic" {
             Ok(12000)
         } else if cfg!(feature = "openssl") {
-            Ok(12345)
+            Ok(12359)
         } else {
-            Ok(12299)
+            Ok(12313)
         }
     );
 
@@ -4835,6 +5892,9 @@ fn sends_ack_only_pkt_when_full_cwnd_and_ack_elicited_despite_max_unacknowledgin
     config.set_initial_max_streams_uni(3);
     config.set_max_recv_udp_payload_size(1200);
     config.verify_peer(false);
+    // The GREASE ECH extension has a random length, which would make the
+    // BBR bandwidth estimate depend on it.
+    config.set_ech_grease(false);
 
     let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
     assert_eq!(pipe.handshake(), Ok(()));
@@ -4847,9 +5907,9 @@ fn sends_ack_only_pkt_when_full_cwnd_and_ack_elicited_despite_max_unacknowledgin
         if cc_algorithm_name == "cubic" {
             Ok(12000)
         } else if cfg!(feature = "openssl") {
-            Ok(12345)
+            Ok(12359)
         } else {
-            Ok(12299)
+            Ok(12313)
         }
     );
 
@@ -6173,7 +7233,7 @@ fn handshake_packet_type_corruption(
     let active_pid = pipe.client.paths.get_active_path_id().expect("no active");
     let (ty, len) = pipe
         .client
-        .send_single(&mut buf, active_pid, false, Instant::now())
+        .send_single(&mut buf, active_pid, false, Ecn::NotEct, Instant::now())
         .unwrap();
     assert_eq!(ty, Type::Initial);
 
@@ -6182,7 +7242,7 @@ fn handshake_packet_type_corruption(
     // Client sends Handshake packet.
     let (ty, len) = pipe
         .client
-        .send_single(&mut buf, active_pid, false, Instant::now())
+        .send_single(&mut buf, active_pid, false, Ecn::NotEct, Instant::now())
         .unwrap();
     assert_eq!(ty, Type::Handshake);
 
@@ -7082,9 +8142,9 @@ fn update_max_datagram_size(
         if cc_algorithm_name == "cubic" {
             12000
         } else if cfg!(feature = "openssl") {
-            13437
+            13451
         } else {
-            13421
+            13435
         },
     );
 }
@@ -7146,9 +8206,9 @@ fn send_capacity(
         if cc_algorithm_name == "cubic" {
             12000
         } else if cfg!(feature = "openssl") {
-            13959
+            13973
         } else {
-            13873
+            13887
         }
     );
 
@@ -7159,9 +8219,9 @@ fn send_capacity(
         if cc_algorithm_name == "cubic" {
             Ok(2000)
         } else if cfg!(feature = "openssl") {
-            Ok(3959)
+            Ok(3973)
         } else {
-            Ok(3873)
+            Ok(3887)
         }
     );
 
@@ -7371,9 +8431,9 @@ fn initial_cwnd(
         // by TLS implementation and OS target.
         let expected = CUSTOM_INITIAL_CONGESTION_WINDOW_PACKETS * 1200 +
             if cfg!(feature = "openssl") {
-                1463
+                1477
             } else {
-                1447
+                1461
             };
 
         assert!(
@@ -7565,6 +8625,7 @@ fn connection_id_zero(
     let info = RecvInfo {
         to: active_path.local_addr(),
         from: active_path.peer_addr(),
+        ecn: Ecn::NotEct,
     };
 
     assert_eq!(
@@ -7639,6 +8700,7 @@ fn connection_id_invalid_max_len(
     let info = RecvInfo {
         to: active_path.local_addr(),
         from: active_path.peer_addr(),
+        ecn: Ecn::NotEct,
     };
 
     assert_eq!(
@@ -7928,6 +8990,7 @@ fn connection_id_retire_limit(
     let info = RecvInfo {
         to: active_path.local_addr(),
         from: active_path.peer_addr(),
+        ecn: Ecn::NotEct,
     };
 
     assert_eq!(
@@ -8462,6 +9525,7 @@ fn send_on_path_test(
     let ri = RecvInfo {
         to: si.to,
         from: si.from,
+        ecn: Ecn::NotEct,
     };
     assert_eq!(pipe.server.recv(&mut buf[..sent], ri), Ok(sent));
 
@@ -8506,6 +9570,7 @@ fn send_on_path_test(
     let ri = RecvInfo {
         to: si.to,
         from: si.from,
+        ecn: Ecn::NotEct,
     };
     assert_eq!(pipe.server.recv(&mut buf[..sent], ri), Ok(sent));
 
@@ -8523,6 +9588,7 @@ fn send_on_path_test(
     let ri = RecvInfo {
         to: si.to,
         from: si.from,
+        ecn: Ecn::NotEct,
     };
     assert_eq!(pipe.server.recv(&mut buf[..sent], ri), Ok(sent));
 
@@ -8541,6 +9607,7 @@ fn send_on_path_test(
     let ri = RecvInfo {
         to: si.to,
         from: si.from,
+        ecn: Ecn::NotEct,
     };
     assert_eq!(pipe.server.recv(&mut buf[..sent], ri), Ok(sent));
 
@@ -8558,6 +9625,7 @@ fn send_on_path_test(
     let ri = RecvInfo {
         to: si.to,
         from: si.from,
+        ecn: Ecn::NotEct,
     };
     assert_eq!(pipe.server.recv(&mut buf[..sent], ri), Ok(sent));
 
@@ -8897,6 +9965,107 @@ fn connection_migration_zero_length_cid(
     );
 }
 
+fn preferred_address_config(disable_migration: bool) -> Config {
+    let (cid, reset_token) = test_utils::create_cid_and_reset_token(16);
+
+    test_utils::config_with("cubic", |config| {
+        config.set_disable_preferred_address_migration(disable_migration);
+        config.set_preferred_address(
+            Some("127.0.0.1:5678".parse().unwrap()),
+            Some("[::1]:5678".parse().unwrap()),
+            &cid,
+            reset_token,
+        );
+    })
+}
+
+#[test]
+fn preferred_address_migration() {
+    let mut config = preferred_address_config(false);
+
+    let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    let client_addr = test_utils::Pipe::client_addr();
+    let server_addr = test_utils::Pipe::server_addr();
+    let preferred_addr = "127.0.0.1:5678".parse().unwrap();
+
+    // The preferred address' connection ID is available to the client.
+    assert_eq!(pipe.client.available_dcids(), 1);
+
+    // The client needs a spare connection ID to probe the new path.
+    let (cid, reset_token) = test_utils::create_cid_and_reset_token(16);
+    assert_eq!(pipe.client.new_scid(&cid, reset_token, true), Ok(1));
+    assert_eq!(pipe.advance(), Ok(()));
+
+    assert_eq!(
+        pipe.client.path_event_next(),
+        Some(PathEvent::Validated(client_addr, preferred_addr))
+    );
+    assert_eq!(
+        pipe.client.path_event_next(),
+        Some(PathEvent::MigratedToPreferredAddress(
+            client_addr,
+            preferred_addr
+        ))
+    );
+    assert_eq!(pipe.client.path_event_next(), None);
+
+    let active_path = pipe.client.paths.get_active().expect("no active");
+    assert_eq!(active_path.local_addr(), client_addr);
+    assert_eq!(active_path.peer_addr(), preferred_addr);
+
+    assert_eq!(pipe.client.stream_send(0, b"data", true), Ok(4));
+    assert_eq!(pipe.advance(), Ok(()));
+
+    assert_eq!(
+        pipe.server.path_event_next(),
+        Some(PathEvent::New(preferred_addr, client_addr))
+    );
+    assert_eq!(
+        pipe.server.path_event_next(),
+        Some(PathEvent::Validated(preferred_addr, client_addr))
+    );
+    assert_eq!(
+        pipe.server.path_event_next(),
+        Some(PathEvent::PeerMigrated(preferred_addr, client_addr))
+    );
+    assert_eq!(pipe.server.path_event_next(), None);
+
+    let active_path = pipe.server.paths.get_active().expect("no active");
+    assert_eq!(active_path.local_addr(), preferred_addr);
+    assert_eq!(active_path.peer_addr(), client_addr);
+
+    // The original path is left untouched.
+    assert_eq!(
+        pipe.client.is_path_validated(client_addr, server_addr),
+        Ok(true)
+    );
+}
+
+#[test]
+fn preferred_address_migration_disabled() {
+    let mut config = preferred_address_config(true);
+
+    let mut pipe = test_utils::Pipe::with_config(&mut config).unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    let client_addr = test_utils::Pipe::client_addr();
+    let server_addr = test_utils::Pipe::server_addr();
+
+    let (cid, reset_token) = test_utils::create_cid_and_reset_token(16);
+    assert_eq!(pipe.client.new_scid(&cid, reset_token, true), Ok(1));
+    assert_eq!(pipe.advance(), Ok(()));
+
+    assert_eq!(pipe.client.path_event_next(), None);
+    assert_eq!(pipe.server.path_event_next(), None);
+
+    let active_path = pipe.client.paths.get_active().expect("no active");
+    assert_eq!(active_path.local_addr(), client_addr);
+    assert_eq!(active_path.peer_addr(), server_addr);
+    assert_eq!(pipe.client.paths.len(), 1);
+}
+
 #[rstest]
 fn connection_migration_reordered_non_probing(
     #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
@@ -9001,13 +10170,13 @@ fn resilience_against_migration_attack(
     let send1_bytes = pipe.server.stream_send(1, &buf, true).unwrap();
     assert_eq!(send1_bytes, match cc_algorithm_name {
         #[cfg(feature = "openssl")]
-        "bbr2" => 14041,
+        "bbr2" => 14055,
         #[cfg(not(feature = "openssl"))]
-        "bbr2" => 13955,
+        "bbr2" => 13969,
         #[cfg(feature = "openssl")]
-        "bbr2_gcongestion" => 13966,
+        "bbr2_gcongestion" => 13980,
         #[cfg(not(feature = "openssl"))]
-        "bbr2_gcongestion" => 13880,
+        "bbr2_gcongestion" => 13894,
         _ => 12000,
     });
     assert_eq!(
@@ -9428,78 +10597,607 @@ fn challenge_no_cids(
             .unwrap();
     assert_eq!(pipe.handshake(), Ok(()));
 
-    // Server send CIDs to client
-    let mut server_cids = Vec::new();
-    for _ in 0..2 {
-        let (cid, reset_token) = test_utils::create_cid_and_reset_token(16);
-        pipe.server
-            .new_scid(&cid, reset_token, true)
-            .expect("server issue cid");
-        server_cids.push(cid);
-    }
-    assert_eq!(pipe.advance(), Ok(()));
+    // Server send CIDs to client
+    let mut server_cids = Vec::new();
+    for _ in 0..2 {
+        let (cid, reset_token) = test_utils::create_cid_and_reset_token(16);
+        pipe.server
+            .new_scid(&cid, reset_token, true)
+            .expect("server issue cid");
+        server_cids.push(cid);
+    }
+    assert_eq!(pipe.advance(), Ok(()));
+
+    let server_addr = test_utils::Pipe::server_addr();
+    let client_addr_2 = "127.0.0.1:5678".parse().unwrap();
+
+    // Client probes path before sending CIDs (simulating race condition)
+    let frames = [frame::Frame::PathChallenge {
+        data: [0, 1, 2, 3, 4, 5, 6, 7],
+    }];
+    let mut pkt_buf = [0u8; 1500];
+    let mut b = octets::OctetsMut::with_slice(&mut pkt_buf);
+    let epoch = Type::Short.to_epoch().unwrap();
+    let crypto_ctx = &mut pipe.client.crypto_ctx[epoch];
+    let pn = pipe.client.next_pkt_num;
+    let pn_len = 4;
+
+    let hdr = Header {
+        ty: Type::Short,
+        version: pipe.client.version,
+        dcid: server_cids[0].clone(),
+        scid: ConnectionId::from_ref(&[5, 4, 3, 2, 1]),
+        pkt_num: 0,
+        pkt_num_len: pn_len,
+        token: pipe.client.token.clone(),
+        versions: None,
+        key_phase: pipe.client.key_phase,
+    };
+    hdr.to_bytes(&mut b).expect("encode header");
+    let payload_len = frames.iter().fold(0, |acc, x| acc + x.wire_len());
+    b.put_u32(pn as u32).expect("put pn");
+
+    let payload_offset = b.off();
+
+    for frame in frames {
+        frame.to_bytes(&mut b).expect("encode frames");
+    }
+
+    let aead = crypto_ctx.crypto_seal.as_ref().expect("crypto seal");
+
+    let written = packet::encrypt_pkt(
+        &mut b,
+        pn,
+        pn_len,
+        payload_len,
+        payload_offset,
+        None,
+        aead,
+    )
+    .expect("packet encrypt");
+    pipe.client.next_pkt_num += 1;
+
+    pipe.server
+        .recv(&mut pkt_buf[..written], RecvInfo {
+            to: server_addr,
+            from: client_addr_2,
+            ecn: Ecn::NotEct,
+        })
+        .expect("server receive path challenge");
+
+    // Show that the new path is not considered a destination path by quiche
+    assert!(!pipe
+        .server
+        .paths_iter(server_addr)
+        .any(|path| path == client_addr_2));
+}
+
+fn multipath_config(
+    cc_algorithm_name: &str, initial_max_path_id: Option<u64>,
+) -> Config {
+    test_utils::config_with(cc_algorithm_name, |config| {
+        config.set_active_connection_id_limit(2);
+
+        if let Some(v) = initial_max_path_id {
+            config.set_initial_max_path_id(v);
+        }
+    })
+}
+
+/// Completes the handshake and opens a second path on Path ID 1 between
+/// `client_addr_2` and the server.
+fn multipath_pipe_with_second_path(
+    client_config: &mut Config, server_config: &mut Config,
+    client_addr_2: SocketAddr,
+) -> test_utils::Pipe {
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        client_config,
+        server_config,
+    )
+    .unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    assert!(pipe.client.is_multipath());
+    assert!(pipe.server.is_multipath());
+
+    let server_addr = test_utils::Pipe::server_addr();
+
+    // Connection IDs need to be exchanged on the Path ID first.
+    assert_eq!(
+        pipe.client.probe_path(client_addr_2, server_addr),
+        Err(Error::OutOfIdentifiers)
+    );
+
+    let (c_cid, c_reset_token) = test_utils::create_cid_and_reset_token(16);
+    assert_eq!(
+        pipe.client.new_scid_on_path(1, &c_cid, c_reset_token),
+        Ok(0)
+    );
+
+    let (s_cid, s_reset_token) = test_utils::create_cid_and_reset_token(16);
+    assert_eq!(
+        pipe.server.new_scid_on_path(1, &s_cid, s_reset_token),
+        Ok(0)
+    );
+
+    assert_eq!(pipe.advance(), Ok(()));
+
+    assert_eq!(pipe.client.probe_path(client_addr_2, server_addr), Ok(0));
+
+    assert_eq!(pipe.advance(), Ok(()));
+
+    assert_eq!(
+        pipe.client.path_event_next(),
+        Some(PathEvent::Validated(client_addr_2, server_addr))
+    );
+    assert_eq!(pipe.client.path_event_next(), None);
+
+    assert_eq!(
+        pipe.server.path_event_next(),
+        Some(PathEvent::New(server_addr, client_addr_2))
+    );
+    assert_eq!(
+        pipe.server.path_event_next(),
+        Some(PathEvent::Validated(server_addr, client_addr_2))
+    );
+    assert_eq!(pipe.server.path_event_next(), None);
+
+    pipe
+}
+
+#[rstest]
+fn multipath_negotiation(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut client_config = multipath_config(cc_algorithm_name, Some(2));
+    let mut server_config = multipath_config(cc_algorithm_name, Some(4));
+
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    assert!(pipe.client.is_multipath());
+    assert!(pipe.server.is_multipath());
+    assert_eq!(pipe.client.max_path_id(), Some(2));
+    assert_eq!(pipe.server.max_path_id(), Some(2));
+
+    // Multipath is only enabled when both endpoints support it.
+    let mut client_config = multipath_config(cc_algorithm_name, Some(2));
+    let mut server_config = multipath_config(cc_algorithm_name, None);
+
+    // Out of range values are ignored.
+    server_config.set_initial_max_path_id(u64::MAX);
+    assert_eq!(
+        server_config.local_transport_params.initial_max_path_id,
+        None
+    );
+
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    assert!(!pipe.client.is_multipath());
+    assert!(!pipe.server.is_multipath());
+    assert_eq!(pipe.client.max_path_id(), None);
+
+    let (c_cid, c_reset_token) = test_utils::create_cid_and_reset_token(16);
+    assert_eq!(
+        pipe.client.new_scid_on_path(1, &c_cid, c_reset_token),
+        Err(Error::InvalidState)
+    );
+}
+
+#[rstest]
+fn multipath_data_on_both_paths(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut client_config = multipath_config(cc_algorithm_name, Some(2));
+    let mut server_config = multipath_config(cc_algorithm_name, Some(2));
+
+    let server_addr = test_utils::Pipe::server_addr();
+    let client_addr = test_utils::Pipe::client_addr();
+    let client_addr_2 = "127.0.0.1:5678".parse().unwrap();
+
+    let mut pipe = multipath_pipe_with_second_path(
+        &mut client_config,
+        &mut server_config,
+        client_addr_2,
+    );
+
+    let pid_1 = pipe
+        .client
+        .paths
+        .path_id_from_addrs(&(client_addr_2, server_addr))
+        .unwrap();
+    assert_eq!(pipe.client.paths.get(pid_1).unwrap().mp_path_id, 1);
+
+    // Migration is replaced by opening new paths.
+    assert_eq!(
+        pipe.client.migrate(client_addr_2, server_addr),
+        Err(Error::InvalidState)
+    );
+
+    // Send data on each path explicitly.
+    assert_eq!(pipe.client.stream_send(0, &[1; 1000], false), Ok(1000));
+    let flight = test_utils::emit_flight_on_path(
+        &mut pipe.client,
+        Some(client_addr),
+        Some(server_addr),
+    )
+    .unwrap();
+    test_utils::process_flight(&mut pipe.server, flight).unwrap();
+
+    assert_eq!(pipe.client.stream_send(0, &[2; 1000], true), Ok(1000));
+    let flight = test_utils::emit_flight_on_path(
+        &mut pipe.client,
+        Some(client_addr_2),
+        Some(server_addr),
+    )
+    .unwrap();
+    test_utils::process_flight(&mut pipe.server, flight).unwrap();
+
+    let mut b = [0; 2000];
+    assert_eq!(pipe.server.stream_recv(0, &mut b), Ok((2000, true)));
+    assert_eq!(&b[..1000], &[1; 1000]);
+    assert_eq!(&b[1000..], &[2; 1000]);
+
+    // Both paths are acknowledged, using PATH_ACK frames for Path ID 1.
+    assert_eq!(pipe.advance(), Ok(()));
+
+    for (_, path) in pipe.client.paths.iter() {
+        assert_eq!(path.recovery.bytes_in_flight(), 0);
+    }
+
+    let stats = pipe.client.path_stats().collect::<Vec<_>>();
+    assert_eq!(stats.len(), 2);
+    assert!(stats.iter().all(|s| s.sent > 0 && s.lost == 0));
+}
+
+#[rstest]
+fn multipath_path_ack_encoding(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut client_config = multipath_config(cc_algorithm_name, Some(2));
+    let mut server_config = multipath_config(cc_algorithm_name, Some(2));
+
+    let server_addr = test_utils::Pipe::server_addr();
+    let client_addr = test_utils::Pipe::client_addr();
+    let client_addr_2 = "127.0.0.1:5678".parse().unwrap();
+
+    let mut pipe = multipath_pipe_with_second_path(
+        &mut client_config,
+        &mut server_config,
+        client_addr_2,
+    );
+
+    assert_eq!(pipe.client.stream_send(0, b"hello", true), Ok(5));
+    let flight = test_utils::emit_flight_on_path(
+        &mut pipe.client,
+        Some(client_addr_2),
+        Some(server_addr),
+    )
+    .unwrap();
+    test_utils::process_flight(&mut pipe.server, flight).unwrap();
+
+    // The server acknowledges the packet with a PATH_ACK frame, which can be
+    // sent on any path.
+    let mut buf = [0; 65535];
+    let (len, _) = pipe
+        .server
+        .send_on_path(&mut buf, Some(server_addr), Some(client_addr))
+        .unwrap();
+
+    let frames =
+        test_utils::decode_pkt(&mut pipe.client, &mut buf[..len]).unwrap();
+
+    assert!(frames
+        .iter()
+        .any(|f| matches!(f, frame::Frame::PathAck { path_id: 1, .. })));
+}
+
+#[rstest]
+fn multipath_abandon_path(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut client_config = multipath_config(cc_algorithm_name, Some(2));
+    let mut server_config = multipath_config(cc_algorithm_name, Some(2));
+
+    let server_addr = test_utils::Pipe::server_addr();
+    let client_addr = test_utils::Pipe::client_addr();
+    let client_addr_2 = "127.0.0.1:5678".parse().unwrap();
+
+    let mut pipe = multipath_pipe_with_second_path(
+        &mut client_config,
+        &mut server_config,
+        client_addr_2,
+    );
+
+    // The initial path can't be abandoned.
+    assert_eq!(
+        pipe.client.abandon_path(client_addr, server_addr, 0),
+        Err(Error::InvalidState)
+    );
+
+    assert_eq!(
+        pipe.client.abandon_path(client_addr_2, server_addr, 42),
+        Ok(())
+    );
+
+    assert_eq!(
+        pipe.client.path_event_next(),
+        Some(PathEvent::Closed(client_addr_2, server_addr))
+    );
+    assert_eq!(pipe.client.path_event_next(), None);
+
+    assert_eq!(pipe.advance(), Ok(()));
+
+    assert_eq!(
+        pipe.server.path_event_next(),
+        Some(PathEvent::Closed(server_addr, client_addr_2))
+    );
+    assert_eq!(pipe.server.path_event_next(), None);
+
+    // The connection IDs of the path are retired on both sides.
+    assert_eq!(pipe.client.retired_scids(), 1);
+    assert!(pipe.client.retired_scid_next().is_some());
+    assert_eq!(pipe.server.retired_scids(), 1);
+
+    // Each endpoint allows the peer to open another path in place of the
+    // abandoned one.
+    assert_eq!(pipe.client.max_path_id(), Some(3));
+    assert_eq!(pipe.server.max_path_id(), Some(3));
+
+    // Data keeps flowing on the remaining path.
+    assert_eq!(pipe.client.stream_send(0, b"hello", true), Ok(5));
+    assert_eq!(pipe.advance(), Ok(()));
+
+    let mut b = [0; 5];
+    assert_eq!(pipe.server.stream_recv(0, &mut b), Ok((5, true)));
+}
+
+#[rstest]
+fn multipath_path_status(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut client_config = multipath_config(cc_algorithm_name, Some(2));
+    let mut server_config = multipath_config(cc_algorithm_name, Some(2));
+
+    let server_addr = test_utils::Pipe::server_addr();
+    let client_addr_2 = "127.0.0.1:5678".parse().unwrap();
+
+    let mut pipe = multipath_pipe_with_second_path(
+        &mut client_config,
+        &mut server_config,
+        client_addr_2,
+    );
+
+    assert_eq!(
+        pipe.client.set_path_status(
+            client_addr_2,
+            server_addr,
+            PathStatus::Backup
+        ),
+        Ok(())
+    );
+
+    assert_eq!(pipe.advance(), Ok(()));
+
+    let mp = pipe.server.multipath.as_ref().unwrap();
+    assert_eq!(mp.path(1).unwrap().peer_status, PathStatus::Backup);
+
+    // Unknown paths can't have a status.
+    assert_eq!(
+        pipe.client.set_path_status(
+            "127.0.0.1:9999".parse().unwrap(),
+            server_addr,
+            PathStatus::Backup
+        ),
+        Err(Error::InvalidState)
+    );
+}
+
+#[rstest]
+fn multipath_redundant_scheduler(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut client_config = multipath_config(cc_algorithm_name, Some(2));
+    client_config.set_path_scheduler(Arc::new(RedundantScheduler));
+    let mut server_config = multipath_config(cc_algorithm_name, Some(2));
+
+    let client_addr_2 = "127.0.0.1:5678".parse().unwrap();
+
+    let mut pipe = multipath_pipe_with_second_path(
+        &mut client_config,
+        &mut server_config,
+        client_addr_2,
+    );
+
+    assert_eq!(pipe.client.stream_send(0, b"hello", true), Ok(5));
+
+    let flight = test_utils::emit_flight(&mut pipe.client).unwrap();
+
+    // The data is sent on both paths.
+    let mut sources: Vec<SocketAddr> =
+        flight.iter().map(|(_, si)| si.from).collect();
+    sources.dedup();
+    assert_eq!(sources.len(), 2);
+
+    test_utils::process_flight(&mut pipe.server, flight).unwrap();
+
+    let mut b = [0; 10];
+    assert_eq!(pipe.server.stream_recv(0, &mut b), Ok((5, true)));
+    assert_eq!(&b[..5], b"hello");
+
+    assert_eq!(pipe.advance(), Ok(()));
+}
+
+fn ack_frequency_config(
+    cc_algorithm_name: &str, min_ack_delay: Option<u64>,
+) -> Config {
+    test_utils::config_with(cc_algorithm_name, |config| {
+        if let Some(v) = min_ack_delay {
+            config.set_min_ack_delay(v);
+        }
+    })
+}
+
+#[rstest]
+fn ack_frequency_negotiation(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut client_config = ack_frequency_config(cc_algorithm_name, Some(1000));
+    let mut server_config = ack_frequency_config(cc_algorithm_name, Some(2000));
+
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    assert_eq!(pipe.client.peer_transport_params.min_ack_delay, Some(2000));
+    assert_eq!(pipe.server.peer_transport_params.min_ack_delay, Some(1000));
+    assert!(pipe.client.ack_freq.send_enabled());
+    assert!(pipe.server.ack_freq.send_enabled());
+
+    // The extension is only used when both endpoints support it.
+    let mut client_config = ack_frequency_config(cc_algorithm_name, Some(1000));
+    let mut server_config = ack_frequency_config(cc_algorithm_name, None);
+
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    assert!(!pipe.client.ack_freq.send_enabled());
+    assert!(!pipe.server.ack_freq.send_enabled());
+
+    // min_ack_delay can't exceed max_ack_delay.
+    let tp = TransportParams {
+        max_ack_delay: 25,
+        min_ack_delay: Some(30_000),
+        ..Default::default()
+    };
+
+    let mut raw_params = [42; 256];
+    let raw_params =
+        TransportParams::encode(&tp, false, &mut raw_params).unwrap();
+
+    assert_eq!(
+        TransportParams::decode(raw_params, true, None),
+        Err(Error::InvalidTransportParam)
+    );
+}
+
+#[rstest]
+fn ack_frequency_delayed_acks(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut buf = [0; 65535];
+
+    let mut client_config = ack_frequency_config(cc_algorithm_name, Some(1000));
+    let mut server_config = ack_frequency_config(cc_algorithm_name, Some(1000));
+
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    // Ask the server to acknowledge every 10 packets, or after 25ms.
+    let frames = [frame::Frame::AckFrequency {
+        seq_num: 0,
+        ack_eliciting_threshold: 10,
+        request_max_ack_delay: 25_000,
+        reordering_threshold: 0,
+    }];
+
+    let pkt_type = Type::Short;
+    assert!(pipe.send_pkt_to_server(pkt_type, &frames, &mut buf).is_ok());
+
+    let frames = [frame::Frame::Ping { mtu_probe: None }];
+
+    // The ACK is delayed.
+    assert_eq!(pipe.send_pkt_to_server(pkt_type, &frames, &mut buf), Ok(0));
+
+    let space = &pipe.server.pkt_num_spaces[packet::Epoch::Application];
+    assert!(!space.ack_elicited);
+    assert!(space.ack_timer.is_some());
+
+    let timer = pipe.server.timeout().unwrap();
+    assert!(timer <= Duration::from_millis(25));
+
+    // The ACK is sent once the timer fires.
+    std::thread::sleep(timer + Duration::from_millis(1));
+    pipe.server.on_timeout();
+
+    let space = &pipe.server.pkt_num_spaces[packet::Epoch::Application];
+    assert!(space.ack_timer.is_none());
+    assert!(space.ack_elicited);
+
+    let (len, _) = pipe.server.send(&mut buf).unwrap();
+
+    let frames =
+        test_utils::decode_pkt(&mut pipe.client, &mut buf[..len]).unwrap();
+    assert!(frames.iter().any(|f| matches!(f, frame::Frame::ACK { .. })));
+
+    // IMMEDIATE_ACK frames are acknowledged right away.
+    let frames = [frame::Frame::Ping { mtu_probe: None }];
+    assert_eq!(pipe.send_pkt_to_server(pkt_type, &frames, &mut buf), Ok(0));
 
-    let server_addr = test_utils::Pipe::server_addr();
-    let client_addr_2 = "127.0.0.1:5678".parse().unwrap();
+    let frames = [frame::Frame::ImmediateAck];
+    let len = pipe
+        .send_pkt_to_server(pkt_type, &frames, &mut buf)
+        .unwrap();
+    assert!(len > 0);
 
-    // Client probes path before sending CIDs (simulating race condition)
-    let frames = [frame::Frame::PathChallenge {
-        data: [0, 1, 2, 3, 4, 5, 6, 7],
-    }];
-    let mut pkt_buf = [0u8; 1500];
-    let mut b = octets::OctetsMut::with_slice(&mut pkt_buf);
-    let epoch = Type::Short.to_epoch().unwrap();
-    let crypto_ctx = &mut pipe.client.crypto_ctx[epoch];
-    let pn = pipe.client.next_pkt_num;
-    let pn_len = 4;
+    let frames =
+        test_utils::decode_pkt(&mut pipe.client, &mut buf[..len]).unwrap();
+    assert!(frames.iter().any(|f| matches!(f, frame::Frame::ACK { .. })));
 
-    let hdr = Header {
-        ty: Type::Short,
-        version: pipe.client.version,
-        dcid: server_cids[0].clone(),
-        scid: ConnectionId::from_ref(&[5, 4, 3, 2, 1]),
-        pkt_num: 0,
-        pkt_num_len: pn_len,
-        token: pipe.client.token.clone(),
-        versions: None,
-        key_phase: pipe.client.key_phase,
-    };
-    hdr.to_bytes(&mut b).expect("encode header");
-    let payload_len = frames.iter().fold(0, |acc, x| acc + x.wire_len());
-    b.put_u32(pn as u32).expect("put pn");
+    let space = &pipe.server.pkt_num_spaces[packet::Epoch::Application];
+    assert!(space.ack_timer.is_none());
+}
 
-    let payload_offset = b.off();
+#[rstest]
+fn ack_frequency_not_negotiated(
+    #[values("cubic", "bbr2", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut buf = [0; 65535];
 
-    for frame in frames {
-        frame.to_bytes(&mut b).expect("encode frames");
-    }
+    let mut pipe = test_utils::Pipe::new(cc_algorithm_name).unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
 
-    let aead = crypto_ctx.crypto_seal.as_ref().expect("crypto seal");
+    let frames = [frame::Frame::AckFrequency {
+        seq_num: 0,
+        ack_eliciting_threshold: 10,
+        request_max_ack_delay: 25_000,
+        reordering_threshold: 0,
+    }];
 
-    let written = packet::encrypt_pkt(
-        &mut b,
-        pn,
-        pn_len,
-        payload_len,
-        payload_offset,
-        None,
-        aead,
-    )
-    .expect("packet encrypt");
-    pipe.client.next_pkt_num += 1;
+    let pkt_type = Type::Short;
+    assert_eq!(
+        pipe.send_pkt_to_server(pkt_type, &frames, &mut buf),
+        Err(Error::InvalidState)
+    );
 
-    pipe.server
-        .recv(&mut pkt_buf[..written], RecvInfo {
-            to: server_addr,
-            from: client_addr_2,
-        })
-        .expect("server receive path challenge");
+    let mut pipe = test_utils::Pipe::new(cc_algorithm_name).unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
 
-    // Show that the new path is not considered a destination path by quiche
-    assert!(!pipe
-        .server
-        .paths_iter(server_addr)
-        .any(|path| path == client_addr_2));
+    let frames = [frame::Frame::ImmediateAck];
+    assert_eq!(
+        pipe.send_pkt_to_server(pkt_type, &frames, &mut buf),
+        Err(Error::InvalidState)
+    );
 }
 
 #[rstest]
@@ -9876,3 +11574,345 @@ fn disable_pmtud_mid_handshake(
     let active_path = pipe.server.paths.get_active_mut().unwrap();
     assert!(active_path.pmtud.is_none());
 }
+
+fn ecn_config(cc_algorithm_name: &str, enable_ecn: bool) -> Config {
+    test_utils::config_with(cc_algorithm_name, |config| {
+        config.enable_ecn(enable_ecn)
+    })
+}
+
+/// Delivers all the packets `from` has to send to `to`, with the codepoint of
+/// each datagram rewritten to `ecn` when it is set, as a remarking network
+/// would do.
+fn ecn_transfer(
+    from: &mut Connection, to: &mut Connection, ecn: Option<Ecn>,
+) -> Result<()> {
+    let mut flight = match test_utils::emit_flight(from) {
+        Ok(v) => v,
+
+        Err(Error::Done) => return Ok(()),
+
+        Err(e) => return Err(e),
+    };
+
+    if let Some(ecn) = ecn {
+        for (_, si) in flight.iter_mut() {
+            si.ecn = ecn;
+        }
+    }
+
+    test_utils::process_flight(to, flight)
+}
+
+#[rstest]
+fn ecn_marking_and_validation(
+    #[values("cubic", "bbr2", "bbr2_gcongestion", "bbr3")]
+    cc_algorithm_name: &str,
+) {
+    let mut buf = [0; 65535];
+
+    let mut client_config = ecn_config(cc_algorithm_name, true);
+    let mut server_config = ecn_config(cc_algorithm_name, true);
+
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
+
+    // The first packets are marked while ECN is being tested.
+    let flight = test_utils::emit_flight(&mut pipe.client).unwrap();
+    assert!(flight.iter().all(|(_, si)| si.ecn == Ecn::Ect0));
+    assert_eq!(test_utils::process_flight(&mut pipe.server, flight), Ok(()));
+
+    let space = &pipe.server.pkt_num_spaces[packet::Epoch::Initial];
+    assert_eq!(space.ecn_counts.ect0_count, 1);
+
+    let flight = test_utils::emit_flight(&mut pipe.server).unwrap();
+    assert_eq!(test_utils::process_flight(&mut pipe.client, flight), Ok(()));
+
+    assert_eq!(pipe.handshake(), Ok(()));
+    assert_eq!(pipe.advance(), Ok(()));
+
+    assert!(pipe.client.paths.get_active().unwrap().ecn.is_capable());
+    assert!(pipe.server.paths.get_active().unwrap().ecn.is_capable());
+
+    assert_eq!(pipe.client.stream_send(0, b"hello", true), Ok(5));
+
+    let (len, info) = pipe.client.send(&mut buf).unwrap();
+    assert_eq!(info.ecn, Ecn::Ect0);
+
+    let info = RecvInfo {
+        to: info.to,
+        from: info.from,
+        ecn: info.ecn,
+    };
+    assert_eq!(pipe.server.recv(&mut buf[..len], info), Ok(len));
+
+    let space = &pipe.server.pkt_num_spaces[packet::Epoch::Application];
+    assert!(space.ecn_counts.ect0_count > 0);
+    assert_eq!(space.ecn_counts.ect1_count, 0);
+    assert_eq!(space.ecn_counts.ecn_ce_count, 0);
+
+    // The counts are reported in ACK_ECN frames.
+    let (len, _) = pipe.server.send(&mut buf).unwrap();
+
+    let frames =
+        test_utils::decode_pkt(&mut pipe.client, &mut buf[..len]).unwrap();
+    assert!(frames.iter().any(|f| matches!(f, frame::Frame::ACK {
+        ecn_counts: Some(counts),
+        ..
+    } if counts.ect0_count > 0)));
+}
+
+#[rstest]
+fn ecn_validation_failure(
+    #[values("cubic", "bbr2", "bbr2_gcongestion", "bbr3")]
+    cc_algorithm_name: &str,
+) {
+    let mut client_config = ecn_config(cc_algorithm_name, true);
+    let mut server_config = ecn_config(cc_algorithm_name, false);
+
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
+
+    // The network clears the marks of the client's packets.
+    while !pipe.client.is_established() || !pipe.server.is_established() {
+        ecn_transfer(&mut pipe.client, &mut pipe.server, Some(Ecn::NotEct))
+            .unwrap();
+        ecn_transfer(&mut pipe.server, &mut pipe.client, None).unwrap();
+    }
+
+    let path = pipe.client.paths.get_active().unwrap();
+    assert!(!path.ecn.is_capable());
+    assert_eq!(path.ecn.codepoint(), Ecn::NotEct);
+
+    // ECN is disabled on the server.
+    let path = pipe.server.paths.get_active().unwrap();
+    assert_eq!(path.ecn.codepoint(), Ecn::NotEct);
+
+    // No marks made it through the network.
+    let space = &pipe.client.pkt_num_spaces[packet::Epoch::Handshake];
+    assert_eq!(space.ecn_counts, frame::EcnCounts::default());
+
+    let space = &pipe.server.pkt_num_spaces[packet::Epoch::Handshake];
+    assert_eq!(space.ecn_counts, frame::EcnCounts::default());
+}
+
+#[rstest]
+fn ecn_ce_congestion_response(
+    #[values("reno", "cubic")] cc_algorithm_name: &str,
+) {
+    let mut client_config = ecn_config(cc_algorithm_name, true);
+    let mut server_config = ecn_config(cc_algorithm_name, true);
+
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    assert!(pipe.client.paths.get_active().unwrap().ecn.is_capable());
+
+    let cwnd_prev = pipe.client.paths.get_active().unwrap().recovery.cwnd();
+
+    // The network marks the client's packets with CE.
+    assert_eq!(pipe.client.stream_send(0, b"hello", true), Ok(5));
+
+    ecn_transfer(&mut pipe.client, &mut pipe.server, Some(Ecn::Ce)).unwrap();
+
+    let space = &pipe.server.pkt_num_spaces[packet::Epoch::Application];
+    assert!(space.ecn_counts.ecn_ce_count > 0);
+
+    // CE-marked packets are acknowledged immediately.
+    ecn_transfer(&mut pipe.server, &mut pipe.client, None).unwrap();
+
+    let space = &pipe.client.pkt_num_spaces[packet::Epoch::Application];
+    assert!(space.peer_ecn_counts.ecn_ce_count > 0);
+
+    let cwnd = pipe.client.paths.get_active().unwrap().recovery.cwnd();
+    assert!(cwnd < cwnd_prev);
+
+    assert_eq!(pipe.advance(), Ok(()));
+    assert!(pipe.client.paths.get_active().unwrap().ecn.is_capable());
+}
+
+/// The hooks called on a [`FixedWindow`] congestion controller.
+#[derive(Default)]
+struct FixedWindowEvents {
+    sent: usize,
+    acked: usize,
+    lost: usize,
+    rtt_updates: usize,
+    delivery_rate_samples: usize,
+}
+
+/// A congestion controller with a constant congestion window.
+struct FixedWindow {
+    cwnd: usize,
+    events: Arc<Mutex<FixedWindowEvents>>,
+}
+
+impl CongestionController for FixedWindow {
+    fn on_packet_sent(
+        &mut self, _sent_time: Instant, _pkt_num: u64, _bytes: usize,
+        _bytes_in_flight: usize, _is_retransmissible: bool,
+    ) {
+        self.events.lock().unwrap().sent += 1;
+    }
+
+    fn on_rtt_update(&mut self, rtt: &RttEstimate, _now: Instant) {
+        assert!(rtt.min_rtt.is_some());
+
+        self.events.lock().unwrap().rtt_updates += 1;
+    }
+
+    fn on_packets_acked(&mut self, event: &CongestionEvent) {
+        assert!(!event.acked_packets.is_empty());
+        assert!(event.bytes_in_flight <= event.prior_bytes_in_flight);
+
+        let mut events = self.events.lock().unwrap();
+        events.acked += event.acked_packets.len();
+
+        if event.bandwidth_sample.delivery_rate.is_some() {
+            events.delivery_rate_samples += 1;
+        }
+    }
+
+    fn on_congestion_event(&mut self, event: &CongestionEvent) {
+        assert_eq!(
+            event.bytes_lost,
+            event.lost_packets.iter().map(|p| p.size()).sum::<usize>()
+        );
+
+        self.events.lock().unwrap().lost += event.lost_packets.len();
+    }
+
+    fn cwnd(&self) -> usize {
+        self.cwnd
+    }
+}
+
+fn custom_cc_config(events: &Arc<Mutex<FixedWindowEvents>>) -> Config {
+    let events = Arc::clone(events);
+
+    test_utils::config_with("cubic", |config| {
+        config.set_custom_cc(Arc::new(
+            move |params: &CongestionControllerParams| {
+                Box::new(FixedWindow {
+                    cwnd: 4 * params.max_datagram_size,
+                    events: Arc::clone(&events),
+                }) as Box<dyn CongestionController>
+            },
+        ))
+    })
+}
+
+#[test]
+fn custom_cc() {
+    let client_events = Arc::new(Mutex::new(FixedWindowEvents::default()));
+    let server_events = Arc::new(Mutex::new(FixedWindowEvents::default()));
+
+    let mut client_config = custom_cc_config(&client_events);
+    let mut server_config = custom_cc_config(&server_events);
+
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    let cwnd = 4 * pipe.client.max_send_udp_payload_size();
+    assert_eq!(pipe.client.path_stats().next().unwrap().cwnd, cwnd);
+
+    // The congestion window of the custom controller limits the flight.
+    assert_eq!(pipe.client.stream_send(0, &[0; 50_000], false), Ok(cwnd));
+
+    let flight = test_utils::emit_flight(&mut pipe.client).unwrap();
+    let sent: usize = flight.iter().map(|(p, _)| p.len()).sum();
+    assert!(sent <= cwnd);
+
+    assert_eq!(test_utils::process_flight(&mut pipe.server, flight), Ok(()));
+    assert_eq!(pipe.advance(), Ok(()));
+
+    let mut b = [0; 50_000];
+    assert_eq!(pipe.server.stream_recv(0, &mut b), Ok((cwnd, false)));
+
+    let events = client_events.lock().unwrap();
+    assert!(events.sent > 0);
+    assert!(events.acked > 0);
+    assert!(events.rtt_updates > 0);
+    assert!(events.delivery_rate_samples > 0);
+    assert_eq!(events.lost, 0);
+
+    assert!(server_events.lock().unwrap().sent > 0);
+}
+
+#[test]
+fn custom_cc_loss() {
+    let client_events = Arc::new(Mutex::new(FixedWindowEvents::default()));
+    let server_events = Arc::new(Mutex::new(FixedWindowEvents::default()));
+
+    let mut client_config = custom_cc_config(&client_events);
+    let mut server_config = custom_cc_config(&server_events);
+
+    let mut pipe = test_utils::Pipe::with_client_and_server_config(
+        &mut client_config,
+        &mut server_config,
+    )
+    .unwrap();
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    assert_eq!(pipe.client.stream_send(0, &[0; 4_000], true), Ok(4_000));
+
+    // The first packet of the flight is dropped by the network.
+    let mut flight = test_utils::emit_flight(&mut pipe.client).unwrap();
+    assert!(flight.len() > 3);
+    flight.remove(0);
+
+    assert_eq!(test_utils::process_flight(&mut pipe.server, flight), Ok(()));
+    assert_eq!(pipe.advance(), Ok(()));
+
+    assert!(client_events.lock().unwrap().lost > 0);
+
+    let mut b = [0; 4_000];
+    assert_eq!(pipe.server.stream_recv(0, &mut b), Ok((4_000, true)));
+}
+
+#[rstest]
+fn path_capacity_record(
+    #[values("reno", "cubic", "bbr2_gcongestion")] cc_algorithm_name: &str,
+) {
+    let mut pipe = test_utils::Pipe::new(cc_algorithm_name).unwrap();
+
+    // No RTT sample was taken yet.
+    assert_eq!(pipe.client.path_capacity_record(), None);
+
+    assert_eq!(pipe.handshake(), Ok(()));
+
+    let record = pipe.client.path_capacity_record().unwrap();
+    assert_eq!(record.cwnd, pipe.client.path_stats().next().unwrap().cwnd);
+
+    let decoded = PathCapacityRecord::from_bytes(&record.to_bytes()).unwrap();
+    assert_eq!(decoded.cwnd, record.cwnd);
+    assert_eq!(decoded.min_rtt.as_micros(), record.min_rtt.as_micros());
+    assert_eq!(decoded.bandwidth, record.bandwidth);
+
+    // Seed a new connection with the record.
+    let mut pipe = test_utils::Pipe::new(cc_algorithm_name).unwrap();
+
+    let res = pipe.client.set_path_capacity_record(&record);
+
+    if cc_algorithm_name == "bbr2_gcongestion" {
+        assert_eq!(res, Err(Error::CongestionControl));
+    } else {
+        assert_eq!(res, Ok(()));
+    }
+
+    assert_eq!(pipe.handshake(), Ok(()));
+}
@@ -0,0 +1,328 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Session ticket key rotation.
+//!
+//! A [`TicketKeyRing`] holds the key used to encrypt new session tickets, along
+//! with a bounded number of previous keys that are only used to decrypt the
+//! tickets issued before the last rotations. Tickets decrypted with a previous
+//! key are renewed with the current one, so rotating the key doesn't
+//! invalidate the tickets held by clients at once.
+
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+use crate::Error;
+use crate::Result;
+
+/// The length of the public name identifying a ticket key.
+pub(crate) const TICKET_KEY_NAME_LEN: usize = 16;
+
+/// The length of the HMAC and AES keys of a ticket key.
+const TICKET_SECRET_LEN: usize = 16;
+
+/// The length of the key material of a ticket key.
+pub const TICKET_KEY_LEN: usize = TICKET_KEY_NAME_LEN + 2 * TICKET_SECRET_LEN;
+
+/// A session ticket key.
+#[derive(Clone)]
+pub(crate) struct TicketKey {
+    pub generation: u64,
+
+    pub name: [u8; TICKET_KEY_NAME_LEN],
+
+    pub hmac_key: [u8; TICKET_SECRET_LEN],
+
+    pub aes_key: [u8; TICKET_SECRET_LEN],
+
+    counters: Arc<TicketKeyCounters>,
+}
+
+#[derive(Default)]
+struct TicketKeyCounters {
+    resumed: AtomicU64,
+
+    failed: AtomicU64,
+}
+
+impl TicketKey {
+    fn from_slice(key: &[u8], generation: u64) -> Result<TicketKey> {
+        if key.len() != TICKET_KEY_LEN {
+            return Err(Error::TlsFail);
+        }
+
+        let (name, secrets) = key.split_at(TICKET_KEY_NAME_LEN);
+        let (hmac_key, aes_key) = secrets.split_at(TICKET_SECRET_LEN);
+
+        Ok(TicketKey {
+            generation,
+            name: name.try_into().map_err(|_| Error::TlsFail)?,
+            hmac_key: hmac_key.try_into().map_err(|_| Error::TlsFail)?,
+            aes_key: aes_key.try_into().map_err(|_| Error::TlsFail)?,
+            counters: Arc::default(),
+        })
+    }
+}
+
+/// Resumption statistics of a session ticket key generation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TicketKeyStats {
+    /// The generation of the key.
+    pub generation: u64,
+
+    /// The number of connections resumed with a ticket decrypted by the key.
+    pub resumed: u64,
+
+    /// The number of connections that offered a ticket decrypted by the key,
+    /// but that could not be resumed.
+    pub failed: u64,
+}
+
+/// A set of session ticket keys.
+///
+/// The ring holds one current key, used to encrypt new session tickets, and up
+/// to `max_previous` previous keys, only used to decrypt tickets. Each key is
+/// identified by the generation assigned to it when it entered the ring,
+/// starting at 0 for the initial key.
+///
+/// Keys are 48 bytes long: a 16-byte public name that identifies the key in
+/// the tickets it encrypts, followed by a 16-byte HMAC-SHA256 key and a 16-byte
+/// AES-128 key. The key names must be unique within the ring.
+///
+/// The ring can be shared between multiple [`Config`]s, and [`rotate()`] can be
+/// called at any time from any thread, for example from the callback notifying
+/// the application of a new key from its key management system.
+///
+/// [`Config`]: struct.Config.html
+/// [`rotate()`]: struct.TicketKeyRing.html#method.rotate
+pub struct TicketKeyRing {
+    keys: RwLock<VecDeque<TicketKey>>,
+
+    max_previous: usize,
+
+    unknown: AtomicU64,
+}
+
+impl TicketKeyRing {
+    /// Creates a ring with `key` as the current key, keeping up to
+    /// `max_previous` previous keys when it is rotated.
+    ///
+    /// Returns [`TlsFail`] if the key is not 48 bytes long.
+    ///
+    /// [`TlsFail`]: enum.Error.html#variant.TlsFail
+    pub fn new(key: &[u8], max_previous: usize) -> Result<TicketKeyRing> {
+        let mut keys = VecDeque::with_capacity(max_previous + 1);
+        keys.push_back(TicketKey::from_slice(key, 0)?);
+
+        Ok(TicketKeyRing {
+            keys: RwLock::new(keys),
+            max_previous,
+            unknown: AtomicU64::new(0),
+        })
+    }
+
+    /// Makes `key` the current key, and returns its generation.
+    ///
+    /// The previous current key is kept to decrypt tickets, while the oldest
+    /// key is dropped if there are more than `max_previous` previous keys, at
+    /// which point the tickets it encrypted can't be used anymore.
+    ///
+    /// Returns [`TlsFail`] if the key is not 48 bytes long, and
+    /// [`InvalidState`] if its name is already used by a key of the ring.
+    ///
+    /// [`TlsFail`]: enum.Error.html#variant.TlsFail
+    /// [`InvalidState`]: enum.Error.html#variant.InvalidState
+    pub fn rotate(&self, key: &[u8]) -> Result<u64> {
+        let mut keys = self.keys.write().map_err(|_| Error::InvalidState)?;
+
+        let generation = keys.front().map_or(0, |k| k.generation + 1);
+        let key = TicketKey::from_slice(key, generation)?;
+
+        if keys.iter().any(|k| k.name == key.name) {
+            return Err(Error::InvalidState);
+        }
+
+        keys.push_front(key);
+        keys.truncate(self.max_previous + 1);
+
+        Ok(generation)
+    }
+
+    /// Returns the generation of the current key.
+    pub fn generation(&self) -> u64 {
+        self.current().map_or(0, |k| k.generation)
+    }
+
+    /// Returns the resumption statistics of the keys of the ring, starting
+    /// with the current key.
+    pub fn stats(&self) -> Vec<TicketKeyStats> {
+        let keys = match self.keys.read() {
+            Ok(v) => v,
+
+            Err(_) => return Vec::new(),
+        };
+
+        keys.iter()
+            .map(|k| TicketKeyStats {
+                generation: k.generation,
+                resumed: k.counters.resumed.load(Ordering::Relaxed),
+                failed: k.counters.failed.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    /// Returns the number of tickets offered by clients that were encrypted
+    /// with a key that isn't part of the ring, e.g. because it was dropped.
+    pub fn unknown_key_count(&self) -> u64 {
+        self.unknown.load(Ordering::Relaxed)
+    }
+
+    /// Returns the key used to encrypt new tickets.
+    pub(crate) fn current(&self) -> Option<TicketKey> {
+        self.keys.read().ok()?.front().cloned()
+    }
+
+    /// Returns the key with the given name, and whether it is the current one.
+    pub(crate) fn find(&self, name: &[u8]) -> Option<(TicketKey, bool)> {
+        let keys = self.keys.read().ok()?;
+
+        let key = keys.iter().position(|k| k.name == name).map(|i| {
+            let key = keys[i].clone();
+            (key, i == 0)
+        });
+
+        if key.is_none() {
+            self.unknown.fetch_add(1, Ordering::Relaxed);
+        }
+
+        key
+    }
+
+    /// Records the outcome of a resumption attempted with a ticket decrypted
+    /// by the key of the given generation.
+    pub(crate) fn on_resumption(&self, generation: u64, resumed: bool) {
+        let keys = match self.keys.read() {
+            Ok(v) => v,
+
+            Err(_) => return,
+        };
+
+        if let Some(k) = keys.iter().find(|k| k.generation == generation) {
+            let counter = if resumed {
+                &k.counters.resumed
+            } else {
+                &k.counters.failed
+            };
+
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl std::fmt::Debug for TicketKeyRing {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TicketKeyRing")
+            .field("generation", &self.generation())
+            .field("max_previous", &self.max_previous)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: u8) -> [u8; TICKET_KEY_LEN] {
+        let mut key = [0xa; TICKET_KEY_LEN];
+        key[..TICKET_KEY_NAME_LEN].fill(name);
+        key
+    }
+
+    #[test]
+    fn invalid_key() {
+        assert!(TicketKeyRing::new(&[0; 32], 1).is_err());
+
+        let ring = TicketKeyRing::new(&key(0), 1).unwrap();
+        assert_eq!(ring.rotate(&[0; 80]), Err(Error::TlsFail));
+        assert_eq!(ring.rotate(&key(0)), Err(Error::InvalidState));
+        assert_eq!(ring.generation(), 0);
+    }
+
+    #[test]
+    fn rotate() {
+        let ring = TicketKeyRing::new(&key(0), 2).unwrap();
+        assert_eq!(ring.generation(), 0);
+
+        assert_eq!(ring.rotate(&key(1)), Ok(1));
+        assert_eq!(ring.rotate(&key(2)), Ok(2));
+
+        assert_eq!(ring.current().unwrap().name, [2; TICKET_KEY_NAME_LEN]);
+        assert!(!ring.find(&[0; TICKET_KEY_NAME_LEN]).unwrap().1);
+        assert!(ring.find(&[2; TICKET_KEY_NAME_LEN]).unwrap().1);
+
+        // The oldest key is dropped once there are too many previous keys.
+        assert_eq!(ring.rotate(&key(3)), Ok(3));
+        assert!(ring.find(&[0; TICKET_KEY_NAME_LEN]).is_none());
+        assert_eq!(ring.unknown_key_count(), 1);
+
+        // Names of dropped keys can be reused.
+        assert_eq!(ring.rotate(&key(0)), Ok(4));
+
+        let generations: Vec<u64> =
+            ring.stats().iter().map(|s| s.generation).collect();
+        assert_eq!(generations, [4, 3, 2]);
+    }
+
+    #[test]
+    fn stats() {
+        let ring = TicketKeyRing::new(&key(0), 1).unwrap();
+        assert_eq!(ring.rotate(&key(1)), Ok(1));
+
+        ring.on_resumption(0, true);
+        ring.on_resumption(0, false);
+        ring.on_resumption(1, true);
+        ring.on_resumption(1, true);
+
+        // Unknown generations are ignored.
+        ring.on_resumption(5, true);
+
+        assert_eq!(ring.stats(), [
+            TicketKeyStats {
+                generation: 1,
+                resumed: 2,
+                failed: 0,
+            },
+            TicketKeyStats {
+                generation: 0,
+                resumed: 1,
+                failed: 1,
+            },
+        ]);
+    }
+}
@@ -8,6 +8,31 @@ struct CRYPTO_BUFFER {
     _unused: c_void,
 }
 
+#[allow(non_camel_case_types)]
+#[repr(transparent)]
+struct SSL_ECH_KEYS {
+    _unused: c_void,
+}
+
+#[allow(non_camel_case_types)]
+#[repr(transparent)]
+struct EVP_HPKE_KEY {
+    _unused: c_void,
+}
+
+#[allow(non_camel_case_types)]
+#[repr(transparent)]
+struct EVP_HPKE_KEM {
+    _unused: c_void,
+}
+
+// Only the first field is ever accessed, the remaining ones are omitted.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct SSL_CLIENT_HELLO {
+    ssl: *mut SSL,
+}
+
 #[repr(C)]
 #[allow(non_camel_case_types)]
 pub(super) struct SSL_QUIC_METHOD {
@@ -92,6 +117,18 @@ pub(super) static QUICHE_STREAM_METHOD: SSL_QUIC_METHOD = SSL_QUIC_METHOD {
     send_alert: Some(send_alert),
 };
 
+const TLSEXT_TYPE_PRE_SHARED_KEY: u16 = 41;
+const TLSEXT_TYPE_EARLY_DATA: u16 = 42;
+
+// ssl_select_cert_success
+const SSL_SELECT_CERT_SUCCESS: c_int = 1;
+
+const ERR_LIB_SSL: c_uint = 16;
+const SSL_R_ECH_REJECTED: c_uint = 319;
+
+// EVP_HPKE_MAX_PRIVATE_KEY_LENGTH
+const HPKE_MAX_PRIVATE_KEY_LEN: usize = 32;
+
 impl Context {
     pub fn set_early_data_enabled(&mut self, _enabled: bool) {
         unsafe {
@@ -101,6 +138,154 @@ impl Context {
             );
         }
     }
+
+    pub fn set_ech_keys(&mut self, keys: &[crate::EchKey]) -> Result<()> {
+        unsafe {
+            let ech_keys = SSL_ECH_KEYS_new();
+
+            if ech_keys.is_null() {
+                return Err(Error::TlsFail);
+            }
+
+            let mut rc = 1;
+
+            for key in keys {
+                let hpke_key = EVP_HPKE_KEY_new();
+
+                if hpke_key.is_null() {
+                    rc = 0;
+                    break;
+                }
+
+                rc = EVP_HPKE_KEY_init(
+                    hpke_key,
+                    EVP_hpke_x25519_hkdf_sha256(),
+                    key.private_key.as_ptr(),
+                    key.private_key.len(),
+                );
+
+                if rc == 1 {
+                    rc = SSL_ECH_KEYS_add(
+                        ech_keys,
+                        c_int::from(key.retry),
+                        key.config.as_ptr(),
+                        key.config.len(),
+                        hpke_key,
+                    );
+                }
+
+                EVP_HPKE_KEY_free(hpke_key);
+
+                if rc != 1 {
+                    break;
+                }
+            }
+
+            if rc == 1 {
+                rc = SSL_CTX_set1_ech_keys(self.as_mut_ptr(), ech_keys);
+            }
+
+            SSL_ECH_KEYS_free(ech_keys);
+
+            map_result(rc)
+        }
+    }
+
+    pub fn enable_anti_replay(&mut self) {
+        unsafe {
+            SSL_CTX_set_select_certificate_cb(
+                self.as_mut_ptr(),
+                Some(select_certificate),
+            );
+        }
+    }
+}
+
+pub(super) unsafe fn init_ticket_hmac(
+    hmac_ctx: *mut HMAC_CTX, key: &[u8],
+) -> c_int {
+    HMAC_Init_ex(
+        hmac_ctx,
+        key.as_ptr() as *const c_void,
+        key.len(),
+        EVP_sha256(),
+        ptr::null_mut(),
+    )
+}
+
+extern "C" fn select_certificate(client_hello: *const SSL_CLIENT_HELLO) -> c_int {
+    let ssl = unsafe { (*client_hello).ssl };
+
+    let early_data_offered =
+        client_hello_extension(client_hello, TLSEXT_TYPE_EARLY_DATA).is_some();
+
+    if !early_data_offered {
+        return SSL_SELECT_CERT_SUCCESS;
+    }
+
+    let accept = match ExData::from_ssl_ptr(ssl) {
+        Some(ExData {
+            anti_replay: Some(anti_replay),
+            trace_id,
+            ..
+        }) => {
+            let key =
+                client_hello_extension(client_hello, TLSEXT_TYPE_PRE_SHARED_KEY)
+                    .and_then(early_data_key);
+
+            let accept = key.is_some_and(|key| anti_replay.record(&key));
+
+            if !accept {
+                trace!("{trace_id} rejecting replayed early data");
+            }
+
+            accept
+        },
+
+        Some(_) => true,
+
+        None => false,
+    };
+
+    if !accept {
+        unsafe { SSL_set_early_data_enabled(ssl, 0) };
+    }
+
+    SSL_SELECT_CERT_SUCCESS
+}
+
+fn client_hello_extension<'a>(
+    client_hello: *const SSL_CLIENT_HELLO, ty: u16,
+) -> Option<&'a [u8]> {
+    let mut ptr: *const u8 = ptr::null();
+    let mut len: usize = 0;
+
+    let found = unsafe {
+        SSL_early_callback_ctx_extension_get(client_hello, ty, &mut ptr, &mut len)
+    };
+
+    if found != 1 {
+        return None;
+    }
+
+    Some(unsafe { slice::from_raw_parts(ptr, len) })
+}
+
+/// Builds the key identifying an early data attempt from the contents of the
+/// `pre_shared_key` extension: the first PSK identity, which is the one early
+/// data is sent with, followed by its obfuscated ticket age.
+fn early_data_key(psk: &[u8]) -> Option<Vec<u8>> {
+    let mut b = octets::Octets::with_slice(psk);
+
+    let mut identities = b.get_bytes_with_u16_length().ok()?;
+
+    let identity = identities.get_bytes_with_u16_length().ok()?;
+    let obfuscated_ticket_age = identities.get_u32().ok()?;
+
+    let mut key = identity.to_vec();
+    key.extend_from_slice(&obfuscated_ticket_age.to_be_bytes());
+
+    Some(key)
 }
 
 impl Handshake {
@@ -271,11 +456,111 @@ impl Handshake {
         }
     }
 
+    pub fn set_ech_config_list(&mut self, ech_config_list: &[u8]) -> Result<()> {
+        map_result(unsafe {
+            SSL_set1_ech_config_list(
+                self.as_mut_ptr(),
+                ech_config_list.as_ptr(),
+                ech_config_list.len(),
+            )
+        })
+    }
+
+    pub fn set_ech_grease(&mut self, enable: bool) {
+        unsafe {
+            SSL_set_enable_ech_grease(self.as_mut_ptr(), c_int::from(enable))
+        }
+    }
+
+    pub fn ech_accepted(&self) -> bool {
+        unsafe { SSL_ech_accepted(self.as_ptr()) == 1 }
+    }
+
+    pub fn ech_retry_configs(&self) -> Option<&[u8]> {
+        // The retry configs can only be queried once the rejection has been
+        // authenticated, i.e. when the handshake failed because of it.
+        if !self.ech_rejected {
+            return None;
+        }
+
+        let mut out: *const u8 = ptr::null();
+        let mut out_len: usize = 0;
+
+        unsafe {
+            SSL_get0_ech_retry_configs(self.as_ptr(), &mut out, &mut out_len);
+
+            if out_len == 0 {
+                return Some(&[]);
+            }
+
+            Some(slice::from_raw_parts(out, out_len))
+        }
+    }
+
     pub fn is_in_early_data(&self) -> bool {
         unsafe { SSL_in_early_data(self.as_ptr()) == 1 }
     }
 }
 
+pub(super) fn is_ech_rejected_error(err: c_uint) -> bool {
+    (err >> 24) & 0xff == ERR_LIB_SSL && err & 0xfff == SSL_R_ECH_REJECTED
+}
+
+pub(crate) fn generate_ech_key(
+    config_id: u8, public_name: &str, max_name_len: usize,
+) -> Result<crate::EchKey> {
+    let public_name =
+        ffi::CString::new(public_name).map_err(|_| Error::TlsFail)?;
+
+    unsafe {
+        let hpke_key = EVP_HPKE_KEY_new();
+
+        if hpke_key.is_null() {
+            return Err(Error::TlsFail);
+        }
+
+        let mut private_key = vec![0; HPKE_MAX_PRIVATE_KEY_LEN];
+        let mut private_key_len = 0;
+
+        let mut config: *mut u8 = ptr::null_mut();
+        let mut config_len = 0;
+
+        let rc = EVP_HPKE_KEY_generate(hpke_key, EVP_hpke_x25519_hkdf_sha256()) ==
+            1 &&
+            EVP_HPKE_KEY_private_key(
+                hpke_key,
+                private_key.as_mut_ptr(),
+                &mut private_key_len,
+                private_key.len(),
+            ) == 1 &&
+            SSL_marshal_ech_config(
+                &mut config,
+                &mut config_len,
+                config_id,
+                hpke_key,
+                public_name.as_ptr(),
+                max_name_len,
+            ) == 1;
+
+        EVP_HPKE_KEY_free(hpke_key);
+
+        if !rc {
+            return Err(Error::TlsFail);
+        }
+
+        let config_bytes = slice::from_raw_parts(config, config_len).to_vec();
+        OPENSSL_free(config as *mut c_void);
+
+        private_key.truncate(private_key_len);
+
+        Ok(crate::EchKey {
+            config: config_bytes,
+            private_key,
+            retry: true,
+        })
+    }
+}
+
 pub(super) fn get_session_bytes(session: *mut SSL_SESSION) -> Result<Vec<u8>> {
     let session_bytes = unsafe {
         let mut out: *mut u8 = ptr::null_mut();
@@ -298,8 +583,37 @@ extern "C" {
     pub(super) fn SSL_CTX_set_tlsext_ticket_keys(
         ctx: *mut SSL_CTX, key: *const u8, key_len: usize,
     ) -> c_int;
+    pub(super) fn SSL_CTX_set_tlsext_ticket_key_cb(
+        ctx: *mut SSL_CTX,
+        cb: Option<
+            extern "C" fn(
+                ssl: *mut SSL,
+                key_name: *mut u8,
+                iv: *mut u8,
+                cipher_ctx: *mut EVP_CIPHER_CTX,
+                hmac_ctx: *mut HMAC_CTX,
+                encrypt: c_int,
+            ) -> c_int,
+        >,
+    ) -> c_int;
+
     fn SSL_CTX_set_early_data_enabled(ctx: *mut SSL_CTX, enabled: i32);
 
+    fn SSL_CTX_set1_ech_keys(ctx: *mut SSL_CTX, keys: *mut SSL_ECH_KEYS)
+        -> c_int;
+
+    fn SSL_CTX_set_select_certificate_cb(
+        ctx: *mut SSL_CTX,
+        cb: Option<extern "C" fn(client_hello: *const SSL_CLIENT_HELLO) -> c_int>,
+    );
+
+    fn SSL_early_callback_ctx_extension_get(
+        client_hello: *const SSL_CLIENT_HELLO, extension_type: u16,
+        out_data: *mut *const u8, out_len: *mut usize,
+    ) -> c_int;
+
+    fn SSL_set_early_data_enabled(ssl: *mut SSL, enabled: c_int);
+
     pub(super) fn SSL_CTX_set_session_cache_mode(
         ctx: *mut SSL_CTX, mode: c_int,
     ) -> c_int;
@@ -308,6 +622,56 @@ extern "C" {
         dup_unused: *const c_void, free_func: *const c_void,
     ) -> c_int;
 
+    fn SSL_set1_ech_config_list(
+        ssl: *mut SSL, ech_config_list: *const u8, ech_config_list_len: usize,
+    ) -> c_int;
+
+    fn SSL_set_enable_ech_grease(ssl: *mut SSL, enable: c_int);
+
+    fn SSL_ech_accepted(ssl: *const SSL) -> c_int;
+
+    fn SSL_get0_ech_retry_configs(
+        ssl: *const SSL, out_retry_configs: *mut *const u8,
+        out_retry_configs_len: *mut usize,
+    );
+
+    fn SSL_marshal_ech_config(
+        out: *mut *mut u8, out_len: *mut usize, config_id: u8,
+        key: *const EVP_HPKE_KEY, public_name: *const c_char,
+        max_name_len: usize,
+    ) -> c_int;
+
+    // SSL_ECH_KEYS
+    fn SSL_ECH_KEYS_new() -> *mut SSL_ECH_KEYS;
+
+    fn SSL_ECH_KEYS_free(keys: *mut SSL_ECH_KEYS);
+
+    fn SSL_ECH_KEYS_add(
+        keys: *mut SSL_ECH_KEYS, is_retry_config: c_int, ech_config: *const u8,
+        ech_config_len: usize, key: *const EVP_HPKE_KEY,
+    ) -> c_int;
+
+    // EVP_HPKE
+    fn EVP_hpke_x25519_hkdf_sha256() -> *const EVP_HPKE_KEM;
+
+    fn EVP_HPKE_KEY_new() -> *mut EVP_HPKE_KEY;
+
+    fn EVP_HPKE_KEY_free(key: *mut EVP_HPKE_KEY);
+
+    fn EVP_HPKE_KEY_init(
+        key: *mut EVP_HPKE_KEY, kem: *const EVP_HPKE_KEM, priv_key: *const u8,
+        priv_key_len: usize,
+    ) -> c_int;
+
+    fn EVP_HPKE_KEY_generate(
+        key: *mut EVP_HPKE_KEY, kem: *const EVP_HPKE_KEM,
+    ) -> c_int;
+
+    fn EVP_HPKE_KEY_private_key(
+        key: *const EVP_HPKE_KEY, out: *mut u8, out_len: *mut usize,
+        max_out: usize,
+    ) -> c_int;
+
     fn SSL_get_curve_id(ssl: *const SSL) -> u16;
     fn SSL_get_curve_name(curve: u16) -> *const c_char;
 
@@ -351,6 +715,12 @@ extern "C" {
 
     // STACK_OF
 
+    // HMAC
+    fn HMAC_Init_ex(
+        ctx: *mut HMAC_CTX, key: *const c_void, key_len: usize,
+        md: *const EVP_MD, engine: *mut ENGINE,
+    ) -> c_int;
+
     fn sk_num(stack: *const STACK_OF) -> usize;
 
     fn sk_value(stack: *const STACK_OF, idx: usize) -> *mut c_void;
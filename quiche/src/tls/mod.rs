@@ -46,10 +46,15 @@ use crate::ConnectionError;
 use crate::crypto;
 use crate::packet;
 
+use crate::ticket_keys::TICKET_KEY_NAME_LEN;
+
 const TLS1_3_VERSION: u16 = 0x0304;
 const TLS_ALERT_ERROR: u64 = 0x100;
 const INTERNAL_ERROR: u64 = 0x01;
 
+// The IV length of AES-128-CBC, used to encrypt session tickets.
+const TICKET_IV_LEN: usize = 16;
+
 #[allow(non_camel_case_types)]
 #[repr(transparent)]
 struct SSL_METHOD {
@@ -112,6 +117,36 @@ struct STACK_OF {
     _unused: c_void,
 }
 
+#[allow(non_camel_case_types)]
+#[repr(transparent)]
+struct EVP_CIPHER_CTX {
+    _unused: c_void,
+}
+
+#[allow(non_camel_case_types)]
+#[repr(transparent)]
+struct EVP_CIPHER {
+    _unused: c_void,
+}
+
+#[allow(non_camel_case_types)]
+#[repr(transparent)]
+struct EVP_MD {
+    _unused: c_void,
+}
+
+#[allow(non_camel_case_types)]
+#[repr(transparent)]
+struct HMAC_CTX {
+    _unused: c_void,
+}
+
+#[allow(non_camel_case_types)]
+#[repr(transparent)]
+struct ENGINE {
+    _unused: c_void,
+}
+
 #[cfg(test)]
 #[repr(C)]
 #[allow(non_camel_case_types)]
@@ -328,6 +363,12 @@ impl Context {
         })
     }
 
+    pub fn enable_ticket_key_ring(&mut self) -> Result<()> {
+        map_result(unsafe {
+            SSL_CTX_set_tlsext_ticket_key_cb(self.as_mut_ptr(), Some(ticket_key))
+        })
+    }
+
     fn as_mut_ptr(&mut self) -> *mut SSL_CTX {
         self.0
     }
@@ -353,6 +394,9 @@ pub struct Handshake {
     /// SSL_process_quic_post_handshake should be called when whenever
     /// SSL_provide_quic_data is called to process the provided data.
     provided_data_outstanding: bool,
+    /// Whether the handshake failed because the server rejected ECH.
+    #[cfg_attr(feature = "openssl", allow(dead_code))]
+    ech_rejected: bool,
 }
 
 impl Handshake {
@@ -367,6 +411,7 @@ impl Handshake {
         Handshake {
             ptr,
             provided_data_outstanding: false,
+            ech_rejected: false,
         }
     }
 
@@ -455,7 +500,7 @@ impl Handshake {
     pub fn set_quic_transport_params(
         &mut self, params: &crate::TransportParams, is_server: bool,
     ) -> Result<()> {
-        let mut raw_params = [0; 128];
+        let mut raw_params = [0; 256];
 
         let raw_params =
             crate::TransportParams::encode(params, is_server, &mut raw_params)?;
@@ -607,6 +652,9 @@ impl Handshake {
                     1 => {
                         log_ssl_error();
 
+                        self.ech_rejected =
+                            is_ech_rejected_error(unsafe { ERR_peek_error() });
+
                         Err(Error::TlsFail)
                     },
 
@@ -708,6 +756,14 @@ pub struct ExData<'a> {
 
     pub pmtud: Option<bool>,
 
+    pub anti_replay: Option<&'a dyn crate::AntiReplay>,
+
+    pub ticket_key_ring: Option<&'a crate::TicketKeyRing>,
+
+    pub ticket_key_generation: &'a mut Option<u64>,
+
+    pub version: u32,
+
     pub is_server: bool,
 }
 
@@ -781,7 +837,8 @@ extern "C" fn set_read_secret(
     if level != crypto::Level::ZeroRTT || ex_data.is_server {
         let secret = unsafe { slice::from_raw_parts(secret, secret_len) };
 
-        let open = match crypto::Open::from_secret(aead, secret) {
+        let open = match crypto::Open::from_secret(aead, ex_data.version, secret)
+        {
             Ok(v) => v,
 
             Err(_) => return 0,
@@ -830,7 +887,8 @@ extern "C" fn set_write_secret(
     if level != crypto::Level::ZeroRTT || !ex_data.is_server {
         let secret = unsafe { slice::from_raw_parts(secret, secret_len) };
 
-        let seal = match crypto::Seal::from_secret(aead, secret) {
+        let seal = match crypto::Seal::from_secret(aead, ex_data.version, secret)
+        {
             Ok(v) => v,
 
             Err(_) => return 0,
@@ -953,6 +1011,13 @@ extern "C" fn select_alpn(
         return TLS_ERROR;
     }
 
+    // The client's transport parameters are available at this point, while
+    // the server's haven't been written yet, so this is where the server can
+    // switch to a compatible version.
+    if ex_data.is_server && negotiate_compatible_version(ssl, ex_data).is_err() {
+        return TLS_ERROR;
+    }
+
     let mut protos = octets::Octets::with_slice(unsafe {
         slice::from_raw_parts(inp, in_len as usize)
     });
@@ -987,6 +1052,66 @@ extern "C" fn select_alpn(
     TLS_ERROR
 }
 
+/// Switches the connection to the server's most preferred version that is
+/// compatible with the client's chosen version and that the client supports,
+/// as per RFC 9368.
+fn negotiate_compatible_version(
+    ssl: *mut SSL, ex_data: &mut ExData,
+) -> Result<()> {
+    // The `Handshake` object must not be dropped, as that would free the
+    // underlying SSL structure.
+    let mut handshake = std::mem::ManuallyDrop::new(Handshake::new(ssl));
+
+    // Invalid parameters are rejected later, when the connection parses them.
+    let peer_params = match crate::TransportParams::decode(
+        handshake.quic_transport_params(),
+        true,
+        None,
+    ) {
+        Ok(v) => v,
+
+        Err(_) => return Ok(()),
+    };
+
+    let client_versions = match peer_params.version_information {
+        Some(v) => v.available_versions,
+
+        None => return Ok(()),
+    };
+
+    let info = match &mut ex_data.local_transport_params.version_information {
+        Some(v) => v,
+
+        None => return Ok(()),
+    };
+
+    // All supported versions are compatible with each other, so just pick the
+    // first one in the server's order of preference.
+    let version = info.available_versions.iter().find(|v| {
+        crate::version_is_supported(**v) && client_versions.contains(v)
+    });
+
+    match version {
+        Some(&v) if v != ex_data.version => {
+            trace!(
+                "{} switching to compatible version {:x}",
+                ex_data.trace_id,
+                v
+            );
+
+            info.chosen_version = v;
+            ex_data.version = v;
+
+            handshake.set_quic_transport_params(
+                &ex_data.local_transport_params,
+                ex_data.is_server,
+            )
+        },
+
+        _ => Ok(()),
+    }
+}
+
 extern "C" fn new_session(ssl: *mut SSL, session: *mut SSL_SESSION) -> c_int {
     let ex_data = match ExData::from_ssl_ptr(ssl) {
         Some(v) => v,
@@ -1038,6 +1163,83 @@ extern "C" fn new_session(ssl: *mut SSL, session: *mut SSL_SESSION) -> c_int {
     0
 }
 
+extern "C" fn ticket_key(
+    ssl: *mut SSL, key_name: *mut u8, iv: *mut u8,
+    cipher_ctx: *mut EVP_CIPHER_CTX, hmac_ctx: *mut HMAC_CTX, encrypt: c_int,
+) -> c_int {
+    let ex_data = match ExData::from_ssl_ptr(ssl) {
+        Some(v) => v,
+
+        None => return -1,
+    };
+
+    let ring = match ex_data.ticket_key_ring {
+        Some(v) => v,
+
+        None => return -1,
+    };
+
+    let key_name =
+        unsafe { slice::from_raw_parts_mut(key_name, TICKET_KEY_NAME_LEN) };
+    let iv = unsafe { slice::from_raw_parts_mut(iv, TICKET_IV_LEN) };
+
+    if encrypt == 1 {
+        let key = match ring.current() {
+            Some(v) => v,
+
+            None => return -1,
+        };
+
+        key_name.copy_from_slice(&key.name);
+        crate::rand::rand_bytes(iv);
+
+        let rc = unsafe {
+            EVP_EncryptInit_ex(
+                cipher_ctx,
+                EVP_aes_128_cbc(),
+                ptr::null_mut(),
+                key.aes_key.as_ptr(),
+                iv.as_ptr(),
+            ) == 1 &&
+                init_ticket_hmac(hmac_ctx, &key.hmac_key) == 1
+        };
+
+        return if rc { 1 } else { -1 };
+    }
+
+    // An unknown key name isn't fatal, the ticket is simply ignored and a full
+    // handshake performed instead.
+    let (key, current) = match ring.find(key_name) {
+        Some(v) => v,
+
+        None => return 0,
+    };
+
+    let rc = unsafe {
+        EVP_DecryptInit_ex(
+            cipher_ctx,
+            EVP_aes_128_cbc(),
+            ptr::null_mut(),
+            key.aes_key.as_ptr(),
+            iv.as_ptr(),
+        ) == 1 &&
+            init_ticket_hmac(hmac_ctx, &key.hmac_key) == 1
+    };
+
+    if !rc {
+        return -1;
+    }
+
+    *ex_data.ticket_key_generation = Some(key.generation);
+
+    // Tickets encrypted with a previous key are renewed with the current one.
+    if current {
+        1
+    } else {
+        2
+    }
+}
+
 pub fn map_result(bssl_result: c_int) -> Result<()> {
     match bssl_result {
         1 => Ok(()),
@@ -1242,14 +1444,33 @@ extern "C" {
     #[allow(dead_code)]
     fn OPENSSL_free(ptr: *mut c_void);
 
+    // EVP
+    fn EVP_aes_128_cbc() -> *const EVP_CIPHER;
+
+    fn EVP_sha256() -> *const EVP_MD;
+
+    fn EVP_EncryptInit_ex(
+        ctx: *mut EVP_CIPHER_CTX, cipher: *const EVP_CIPHER, engine: *mut ENGINE,
+        key: *const u8, iv: *const u8,
+    ) -> c_int;
+
+    fn EVP_DecryptInit_ex(
+        ctx: *mut EVP_CIPHER_CTX, cipher: *const EVP_CIPHER, engine: *mut ENGINE,
+        key: *const u8, iv: *const u8,
+    ) -> c_int;
+
 }
 
 #[cfg(not(feature = "openssl"))]
 mod boringssl;
 #[cfg(not(feature = "openssl"))]
+pub(crate) use boringssl::generate_ech_key;
+#[cfg(not(feature = "openssl"))]
 use boringssl::*;
 
 #[cfg(feature = "openssl")]
 mod openssl_quictls;
 #[cfg(feature = "openssl")]
+pub(crate) use openssl_quictls::generate_ech_key;
+#[cfg(feature = "openssl")]
 use openssl_quictls::*;
@@ -55,6 +55,15 @@ impl Context {
     pub fn set_early_data_enabled(&mut self, _enabled: bool) {
         // not yet supported
     }
+
+    pub fn enable_anti_replay(&mut self) {
+        // early data is not supported, so there is nothing to protect
+    }
+
+    pub fn set_ech_keys(&mut self, _keys: &[crate::EchKey]) -> Result<()> {
+        // ECH is not supported
+        Err(Error::TlsFail)
+    }
 }
 
 impl Handshake {
@@ -63,6 +72,23 @@ impl Handshake {
         map_result(1)
     }
 
+    pub fn set_ech_config_list(&mut self, _ech_config_list: &[u8]) -> Result<()> {
+        // ECH is not supported
+        Err(Error::TlsFail)
+    }
+
+    pub fn set_ech_grease(&mut self, _enable: bool) {
+        // ECH is not supported
+    }
+
+    pub fn ech_accepted(&self) -> bool {
+        false
+    }
+
+    pub fn ech_retry_configs(&self) -> Option<&[u8]> {
+        None
+    }
+
     pub fn curve(&self) -> Option<String> {
         let curve = unsafe {
             let curve_id = SSL_get_negotiated_group(self.as_ptr());
@@ -233,6 +259,64 @@ pub(super) unsafe fn SSL_CTX_set_tlsext_ticket_keys(
     ) as c_int
 }
 
+pub(super) fn is_ech_rejected_error(_err: c_uint) -> bool {
+    false
+}
+
+pub(crate) fn generate_ech_key(
+    _config_id: u8, _public_name: &str, _max_name_len: usize,
+) -> Result<crate::EchKey> {
+    // ECH is not supported
+    Err(Error::TlsFail)
+}
+
+#[allow(non_snake_case)]
+pub(super) unsafe fn SSL_CTX_set_tlsext_ticket_key_cb(
+    ctx: *mut SSL_CTX,
+    cb: Option<
+        extern "C" fn(
+            ssl: *mut SSL,
+            key_name: *mut u8,
+            iv: *mut u8,
+            cipher_ctx: *mut EVP_CIPHER_CTX,
+            hmac_ctx: *mut HMAC_CTX,
+            encrypt: c_int,
+        ) -> c_int,
+    >,
+) -> c_int {
+    const SSL_CTRL_SET_TLSEXT_TICKET_KEY_CB: c_int = 72;
+
+    SSL_CTX_callback_ctrl(
+        ctx,
+        SSL_CTRL_SET_TLSEXT_TICKET_KEY_CB,
+        std::mem::transmute::<
+            Option<
+                extern "C" fn(
+                    *mut SSL,
+                    *mut u8,
+                    *mut u8,
+                    *mut EVP_CIPHER_CTX,
+                    *mut HMAC_CTX,
+                    c_int,
+                ) -> c_int,
+            >,
+            Option<extern "C" fn()>,
+        >(cb),
+    ) as c_int
+}
+
+pub(super) unsafe fn init_ticket_hmac(
+    hmac_ctx: *mut HMAC_CTX, key: &[u8],
+) -> c_int {
+    HMAC_Init_ex(
+        hmac_ctx,
+        key.as_ptr() as *const c_void,
+        key.len() as c_int,
+        EVP_sha256(),
+        ptr::null_mut(),
+    )
+}
+
 #[allow(non_snake_case)]
 pub(super) unsafe fn SSL_set_min_proto_version(
     s: *mut SSL, version: u16,
@@ -324,6 +408,10 @@ extern "C" {
         ctx: *mut SSL_CTX, cmd: c_int, larg: c_long, parg: *mut c_void,
     ) -> c_long;
 
+    fn SSL_CTX_callback_ctrl(
+        ctx: *mut SSL_CTX, cmd: c_int, fp: Option<extern "C" fn()>,
+    ) -> c_long;
+
     fn SSL_get_peer_cert_chain(ssl: *const SSL) -> *mut STACK_OF;
 
     fn SSL_get0_peer_certificate(ssl: *const SSL) -> *mut X509;
@@ -338,6 +426,12 @@ extern "C" {
 
     fn OPENSSL_sk_value(stack: *const OPENSSL_STACK, idx: usize) -> *mut c_void;
 
+    // HMAC
+    fn HMAC_Init_ex(
+        ctx: *mut HMAC_CTX, key: *const c_void, key_len: c_int,
+        md: *const EVP_MD, engine: *mut ENGINE,
+    ) -> c_int;
+
     // CRYPTO
 
     fn CRYPTO_get_ex_new_index(
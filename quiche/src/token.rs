@@ -0,0 +1,347 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Address validation tokens sent in NEW_TOKEN frames.
+//!
+//! Once the handshake is confirmed, a server can send the client a token that
+//! the client includes in the Initial packets of a later connection, as
+//! described in [RFC 9000 Section 8.1.3]. A valid token lets the server
+//! consider the client's address as validated without a Retry round-trip, and
+//! without limiting the amount of data it sends before the handshake completes.
+//!
+//! [RFC 9000 Section 8.1.3]: https://www.rfc-editor.org/rfc/rfc9000#section-8.1.3
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::crypto;
+use crate::endpoint::ip_octets;
+use crate::endpoint::unix_time_secs;
+
+/// The default amount of time the tokens minted by an [`HmacTokenValidator`]
+/// are accepted for.
+pub const DEFAULT_NEW_TOKEN_LIFETIME: Duration = Duration::from_secs(24 * 3600);
+
+/// The first byte of the tokens sent in NEW_TOKEN frames, which distinguishes
+/// them from Retry tokens.
+pub(crate) const NEW_TOKEN_PREFIX: u8 = 0x4e;
+
+/// The length of the key of an [`HmacTokenValidator`].
+const KEY_LEN: usize = 32;
+
+/// The length of the authentication tag of the tokens minted by an
+/// [`HmacTokenValidator`].
+const TAG_LEN: usize = 16;
+
+/// Mints and validates the tokens sent by servers in NEW_TOKEN frames.
+///
+/// When configured with [`Config::set_token_validator()`], a server mints a
+/// token for the client once the handshake is confirmed, and validates the
+/// token found in the first Initial packet of later connections. Tokens only
+/// need to be recognized by the validator that minted them, or by the ones
+/// sharing its secrets, e.g. across servers behind the same address.
+///
+/// [`Config::set_token_validator()`]: struct.Config.html#method.set_token_validator
+pub trait TokenValidator: Send + Sync {
+    /// Returns a token for the client at the given address, or `None` if no
+    /// token should be sent.
+    fn mint(&self, peer: &SocketAddr) -> Option<Vec<u8>>;
+
+    /// Returns whether `token`, received from the given address, was minted
+    /// by [`mint()`] for that address and is still valid.
+    ///
+    /// [`mint()`]: trait.TokenValidator.html#tymethod.mint
+    fn validate(&self, token: &[u8], peer: &SocketAddr) -> bool;
+}
+
+/// A [`TokenValidator`] that authenticates tokens with a static key.
+///
+/// Tokens are bound to the IP address of the client and carry their expiry
+/// time, so no state needs to be kept to validate them. Validators that need
+/// to recognize each other's tokens should be created with the same key.
+pub struct HmacTokenValidator {
+    key: [u8; KEY_LEN],
+
+    lifetime: Duration,
+}
+
+impl HmacTokenValidator {
+    /// Creates a validator authenticating tokens with the given key.
+    ///
+    /// The key must be kept secret, and should only be shared with servers
+    /// that are expected to recognize each other's tokens.
+    pub fn new(key: &[u8; KEY_LEN]) -> HmacTokenValidator {
+        HmacTokenValidator {
+            key: *key,
+            lifetime: DEFAULT_NEW_TOKEN_LIFETIME,
+        }
+    }
+
+    /// Sets the amount of time tokens are accepted for after being minted.
+    ///
+    /// The default value is [`DEFAULT_NEW_TOKEN_LIFETIME`].
+    ///
+    /// [`DEFAULT_NEW_TOKEN_LIFETIME`]: constant.DEFAULT_NEW_TOKEN_LIFETIME.html
+    pub fn with_lifetime(mut self, lifetime: Duration) -> HmacTokenValidator {
+        self.lifetime = lifetime;
+        self
+    }
+
+    fn tag(&self, body: &[u8], peer: &SocketAddr) -> Option<[u8; 32]> {
+        crypto::hmac_sha256(
+            &self.key,
+            &[&b"new_token"[..], &ip_octets(peer), body].concat(),
+        )
+        .ok()
+    }
+}
+
+impl TokenValidator for HmacTokenValidator {
+    fn mint(&self, peer: &SocketAddr) -> Option<Vec<u8>> {
+        let expiry = unix_time_secs() + self.lifetime.as_secs();
+
+        let mut token = Vec::with_capacity(8 + TAG_LEN);
+        token.extend_from_slice(&expiry.to_be_bytes());
+
+        let tag = self.tag(&token, peer)?;
+        token.extend_from_slice(&tag[..TAG_LEN]);
+
+        Some(token)
+    }
+
+    fn validate(&self, token: &[u8], peer: &SocketAddr) -> bool {
+        if token.len() != 8 + TAG_LEN {
+            return false;
+        }
+
+        let (body, tag) = token.split_at(8);
+
+        let expected = match self.tag(body, peer) {
+            Some(v) => v,
+
+            None => return false,
+        };
+
+        if crypto::verify_slices_are_equal(&expected[..TAG_LEN], tag).is_err() {
+            return false;
+        }
+
+        let expiry = u64::from_be_bytes(body.try_into().unwrap_or_default());
+
+        unix_time_secs() <= expiry
+    }
+}
+
+impl std::fmt::Debug for HmacTokenValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("HmacTokenValidator")
+            .field("lifetime", &self.lifetime)
+            .finish()
+    }
+}
+
+/// A cache of the tokens received by clients in NEW_TOKEN frames.
+///
+/// When configured with [`Config::set_token_cache()`], a client stores the
+/// tokens it receives keyed by the name of the server, and includes the token
+/// for that name in the Initial packets of its next connection to it. Each
+/// token is only used once, as reusing it would let the connections be linked
+/// to each other.
+///
+/// Once the cache is full, the oldest token is evicted to make room for new
+/// ones.
+///
+/// [`Config::set_token_cache()`]: struct.Config.html#method.set_token_cache
+pub struct TokenCache {
+    capacity: usize,
+
+    tokens: Mutex<Tokens>,
+}
+
+#[derive(Default)]
+struct Tokens {
+    by_name: HashMap<String, Vec<u8>>,
+
+    order: VecDeque<String>,
+}
+
+impl TokenCache {
+    /// Creates a cache holding up to `capacity` tokens.
+    pub fn new(capacity: usize) -> TokenCache {
+        TokenCache {
+            capacity,
+            tokens: Mutex::new(Tokens::default()),
+        }
+    }
+
+    /// Stores the token received from the given server, replacing the
+    /// previous one, if any.
+    pub fn insert(&self, server_name: &str, token: &[u8]) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut tokens = match self.tokens.lock() {
+            Ok(v) => v,
+
+            Err(_) => return,
+        };
+
+        if let Some(v) = tokens.by_name.get_mut(server_name) {
+            *v = token.to_vec();
+            return;
+        }
+
+        while tokens.by_name.len() >= self.capacity {
+            let Some(oldest) = tokens.order.pop_front() else {
+                break;
+            };
+
+            tokens.by_name.remove(&oldest);
+        }
+
+        tokens.order.push_back(server_name.to_string());
+        tokens
+            .by_name
+            .insert(server_name.to_string(), token.to_vec());
+    }
+
+    /// Removes and returns the token stored for the given server.
+    pub fn take(&self, server_name: &str) -> Option<Vec<u8>> {
+        let mut tokens = self.tokens.lock().ok()?;
+
+        let token = tokens.by_name.remove(server_name)?;
+        tokens.order.retain(|name| name != server_name);
+
+        Some(token)
+    }
+
+    /// Returns the number of tokens in the cache.
+    pub fn len(&self) -> usize {
+        self.tokens.lock().map_or(0, |tokens| tokens.by_name.len())
+    }
+
+    /// Returns true if the cache holds no tokens.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl std::fmt::Debug for TokenCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TokenCache")
+            .field("capacity", &self.capacity)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Returns whether `token`, received in an Initial packet from `peer`, was
+/// sent in a NEW_TOKEN frame and is accepted by `validator`.
+pub(crate) fn is_new_token_valid(
+    validator: Option<&dyn TokenValidator>, token: Option<&[u8]>,
+    peer: &SocketAddr,
+) -> bool {
+    match (validator, token) {
+        (Some(validator), Some([NEW_TOKEN_PREFIX, token @ ..])) =>
+            validator.validate(token, peer),
+
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate() {
+        let peer = "127.0.0.1:1234".parse().unwrap();
+
+        let validator = HmacTokenValidator::new(&[0xa; KEY_LEN]);
+        let token = validator.mint(&peer).unwrap();
+
+        assert!(validator.validate(&token, &peer));
+
+        // The port of the client doesn't matter.
+        assert!(validator.validate(&token, &"127.0.0.1:5678".parse().unwrap()));
+
+        // Tokens are bound to the address they were minted for.
+        assert!(!validator.validate(&token, &"127.0.0.2:1234".parse().unwrap()));
+
+        // And to the key that minted them.
+        let other = HmacTokenValidator::new(&[0xb; KEY_LEN]);
+        assert!(!other.validate(&token, &peer));
+
+        let mut tampered = token.clone();
+        tampered[0] ^= 0xff;
+        assert!(!validator.validate(&tampered, &peer));
+
+        assert!(!validator.validate(&token[1..], &peer));
+        assert!(!validator.validate(b"", &peer));
+    }
+
+    #[test]
+    fn expired() {
+        let peer = "127.0.0.1:1234".parse().unwrap();
+
+        let validator = HmacTokenValidator::new(&[0xa; KEY_LEN]);
+
+        let mut body = (unix_time_secs() - 1).to_be_bytes().to_vec();
+        let tag = validator.tag(&body, &peer).unwrap();
+        body.extend_from_slice(&tag[..TAG_LEN]);
+
+        assert!(!validator.validate(&body, &peer));
+    }
+
+    #[test]
+    fn cache() {
+        let cache = TokenCache::new(2);
+        assert!(cache.is_empty());
+
+        cache.insert("a.example", b"token-a");
+        cache.insert("b.example", b"token-b");
+        cache.insert("a.example", b"token-a2");
+        assert_eq!(cache.len(), 2);
+
+        // The oldest token is evicted once the cache is full.
+        cache.insert("c.example", b"token-c");
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.take("a.example"), None);
+
+        // Tokens are only returned once.
+        assert_eq!(cache.take("b.example"), Some(b"token-b".to_vec()));
+        assert_eq!(cache.take("b.example"), None);
+
+        cache.insert("d.example", b"token-d");
+        assert_eq!(cache.take("c.example"), Some(b"token-c".to_vec()));
+        assert_eq!(cache.take("d.example"), Some(b"token-d".to_vec()));
+        assert!(cache.is_empty());
+    }
+}
@@ -588,6 +588,17 @@ impl<H: DriverHooks> H3Driver<H> {
 
             h3::Event::PriorityUpdate => Ok(()),
             h3::Event::GoAway => Err(H3ConnectionError::GoAway),
+
+            // Server push is never enabled.
+            h3::Event::PushPromise { .. } |
+            h3::Event::PushStream { .. } |
+            h3::Event::CancelPush => Ok(()),
+
+            // WebTransport is never enabled.
+            h3::Event::WebTransportStream { .. } |
+            h3::Event::WebTransportDatagram { .. } |
+            h3::Event::WebTransportSessionClosed { .. } |
+            h3::Event::WebTransportSessionDrain => Ok(()),
         }
     }
 
@@ -0,0 +1,634 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::future::Future;
+use std::io;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::sync::Arc;
+
+use foundations::telemetry::log;
+use quiche::h3;
+use quiche::h3::capsule::AssignedAddress;
+use quiche::h3::capsule::Capsule;
+use quiche::h3::capsule::IpAddressRange;
+use tokio::select;
+
+use super::header_value;
+use super::percent_decode;
+use super::percent_encode;
+use super::Flows;
+use super::Tunnel;
+use super::TunnelClient;
+use super::TunnelEvent;
+use crate::http3::driver::H3Event;
+use crate::http3::driver::InboundFrameStream;
+use crate::http3::driver::OutboundFrameSender;
+use crate::http3::driver::ServerH3Event;
+
+/// The path prefix of the default CONNECT-IP URI template,
+/// `/.well-known/masque/ip/{target}/{ipproto}/`.
+pub const WELL_KNOWN_PATH_PREFIX: &str = "/.well-known/masque/ip/";
+
+/// The scope of a CONNECT-IP request.
+///
+/// Both fields are `None` when the corresponding template variable is the
+/// `*` wildcard.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConnectIpScope {
+    /// The hostname, IP address or IP prefix (such as `192.0.2.0/24`) the
+    /// tunnel is limited to.
+    pub target: Option<String>,
+    /// The IP protocol the tunnel is limited to.
+    pub ip_protocol: Option<u8>,
+}
+
+impl ConnectIpScope {
+    /// Parses a scope from a request path following the default URI
+    /// template.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let path = path.strip_prefix(WELL_KNOWN_PATH_PREFIX)?;
+
+        let mut parts = path.split('/');
+
+        let target = match parts.next()? {
+            "" => return None,
+            "*" => None,
+            target => Some(percent_decode(target)?),
+        };
+
+        let ip_protocol = match parts.next()? {
+            "*" => None,
+            proto => Some(proto.parse().ok()?),
+        };
+
+        // The template ends with a slash.
+        if !parts.next()?.is_empty() || parts.next().is_some() {
+            return None;
+        }
+
+        Some(Self {
+            target,
+            ip_protocol,
+        })
+    }
+
+    /// Expands the default URI template into a request path.
+    pub fn to_path(&self) -> String {
+        let mut path = WELL_KNOWN_PATH_PREFIX.to_string();
+
+        match &self.target {
+            Some(target) => percent_encode(&mut path, target),
+            None => path.push('*'),
+        }
+
+        match self.ip_protocol {
+            Some(proto) => path.push_str(&format!("/{proto}/")),
+            None => path.push_str("/*/"),
+        }
+
+        path
+    }
+
+    /// Builds the headers of a CONNECT-IP request for this scope, sent to the
+    /// proxy at `authority`.
+    pub fn request_headers(&self, authority: &str) -> Vec<h3::Header> {
+        vec![
+            h3::Header::new(b":method", b"CONNECT"),
+            h3::Header::new(b":protocol", b"connect-ip"),
+            h3::Header::new(b":scheme", b"https"),
+            h3::Header::new(b":authority", authority.as_bytes()),
+            h3::Header::new(b":path", self.to_path().as_bytes()),
+            h3::Header::new(b"capsule-protocol", b"?1"),
+        ]
+    }
+}
+
+/// The network side of a CONNECT-IP tunnel, such as a TUN device or a
+/// user-space IP stack.
+pub trait PacketEndpoint: Send + 'static {
+    /// Forwards an IP packet received from the client.
+    fn send_packet(
+        &mut self, packet: &[u8],
+    ) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// Receives the next IP packet to forward to the client.
+    ///
+    /// `None` closes the tunnel. The returned future must be cancel safe.
+    fn recv_packet(
+        &mut self,
+    ) -> impl Future<Output = io::Result<Option<Vec<u8>>>> + Send;
+
+    /// Handles an address requested by the client with an ADDRESS_REQUEST
+    /// capsule.
+    ///
+    /// Returns the address to assign, or `None` to reject the request. The
+    /// request ID of the returned address is ignored. By default all requests
+    /// are rejected.
+    fn assign_address(
+        &mut self, request: &AssignedAddress,
+    ) -> Option<AssignedAddress> {
+        let _ = request;
+        None
+    }
+}
+
+/// An accepted CONNECT-IP tunnel, returned by [`ConnectIpHandler::open()`].
+pub struct ConnectIpSession<E> {
+    /// Where packets received from the client are forwarded to.
+    pub endpoint: E,
+    /// Addresses assigned to the client when the tunnel is established.
+    pub addresses: Vec<AssignedAddress>,
+    /// Routes advertised to the client when the tunnel is established.
+    ///
+    /// Ranges must be ordered and must not overlap.
+    pub routes: Vec<IpAddressRange>,
+}
+
+/// Accepts CONNECT-IP tunnels on behalf of a [`ConnectIpServer`].
+pub trait ConnectIpHandler: Send + Sync + 'static {
+    /// The [`PacketEndpoint`] of the tunnels opened by this handler.
+    type Endpoint: PacketEndpoint;
+
+    /// Opens a tunnel for a request with the given scope.
+    ///
+    /// A [`PermissionDenied`](io::ErrorKind::PermissionDenied) error rejects
+    /// the request with a 403 status, other errors with a 502 status.
+    fn open(
+        &self, scope: &ConnectIpScope,
+    ) -> impl Future<Output = io::Result<ConnectIpSession<Self::Endpoint>>> + Send;
+}
+
+/// A CONNECT-IP proxy for a [ServerH3Driver](crate::ServerH3Driver)'s
+/// connection.
+///
+/// All events received from the driver's controller must be passed to
+/// [`ConnectIpServer::handle_event()`]. CONNECT-IP requests are served in
+/// tasks spawned on the current tokio runtime, each relaying packets between
+/// the tunnel and the [`PacketEndpoint`] opened by the handler.
+///
+/// Packets from the client are only forwarded if their source address was
+/// assigned to the client, and packets to the client only if their
+/// destination address was.
+pub struct ConnectIpServer<H> {
+    handler: Arc<H>,
+    flows: Flows,
+}
+
+impl<H: ConnectIpHandler> ConnectIpServer<H> {
+    /// Creates a new [`ConnectIpServer`] which opens tunnels with `handler`.
+    pub fn new(handler: H) -> Self {
+        Self {
+            handler: Arc::new(handler),
+            flows: Flows::default(),
+        }
+    }
+
+    /// Processes an event from the driver. Returns the event if it wasn't
+    /// consumed.
+    ///
+    /// [`H3Event::NewFlow`] events are always consumed. The flow of an
+    /// extended CONNECT request that isn't handled by the proxy can be
+    /// retrieved with [`ConnectIpServer::take_flow()`].
+    pub fn handle_event(
+        &mut self, event: ServerH3Event,
+    ) -> Option<ServerH3Event> {
+        let ServerH3Event::Core(event) = event;
+
+        match self.flows.handle_event(event)? {
+            H3Event::IncomingHeaders(headers)
+                if is_connect_ip(&headers.headers) =>
+            {
+                let scope = header_value(&headers.headers, b":path")
+                    .and_then(|v| std::str::from_utf8(v).ok())
+                    .and_then(ConnectIpScope::from_path)
+                    .filter(|_| {
+                        header_value(&headers.headers, b":scheme").is_some() &&
+                            header_value(&headers.headers, b":authority")
+                                .is_some()
+                    });
+
+                let tunnel = self.flows.tunnel(headers)?;
+                let handler = Arc::clone(&self.handler);
+
+                tokio::spawn(serve_tunnel(tunnel, scope, handler));
+
+                None
+            },
+
+            event => Some(event.into()),
+        }
+    }
+
+    /// Removes the DATAGRAM flow of the extended CONNECT request on
+    /// `stream_id`.
+    pub fn take_flow(
+        &mut self, stream_id: u64,
+    ) -> Option<(OutboundFrameSender, InboundFrameStream)> {
+        self.flows.take(stream_id)
+    }
+}
+
+fn is_connect_ip(headers: &[h3::Header]) -> bool {
+    header_value(headers, b":method") == Some(b"CONNECT") &&
+        header_value(headers, b":protocol") == Some(b"connect-ip")
+}
+
+fn status(code: u16) -> Vec<h3::Header> {
+    vec![h3::Header::new(b":status", code.to_string().as_bytes())]
+}
+
+async fn serve_tunnel<H: ConnectIpHandler>(
+    mut tunnel: Tunnel, scope: Option<ConnectIpScope>, handler: Arc<H>,
+) {
+    let Some(scope) = scope else {
+        let _ = tunnel.respond(status(400), true).await;
+        return;
+    };
+
+    let session = match handler.open(&scope).await {
+        Ok(v) => v,
+
+        Err(e) => {
+            log::debug!(
+                "failed to open CONNECT-IP tunnel";
+                "stream_id" => tunnel.stream_id(),
+                "scope" => ?scope,
+                "error" => %e,
+            );
+
+            let code = match e.kind() {
+                io::ErrorKind::PermissionDenied => 403,
+                _ => 502,
+            };
+
+            let _ = tunnel.respond(status(code), true).await;
+            return;
+        },
+    };
+
+    let mut headers = status(200);
+    headers.push(h3::Header::new(b"capsule-protocol", b"?1"));
+
+    if tunnel.respond(headers, false).await.is_err() {
+        return;
+    }
+
+    if let Err(e) = relay(&mut tunnel, session).await {
+        log::debug!(
+            "CONNECT-IP tunnel failed";
+            "stream_id" => tunnel.stream_id(),
+            "error" => %e,
+        );
+    }
+
+    let _ = tunnel.finish().await;
+}
+
+/// Relays packets between `tunnel` and the session's endpoint until either
+/// side closes.
+async fn relay<E: PacketEndpoint>(
+    tunnel: &mut Tunnel, session: ConnectIpSession<E>,
+) -> io::Result<()> {
+    let ConnectIpSession {
+        mut endpoint,
+        mut addresses,
+        routes,
+    } = session;
+
+    if !addresses.is_empty() {
+        tunnel
+            .send_capsule(&Capsule::AddressAssign {
+                addresses: addresses.clone(),
+            })
+            .await?;
+    }
+
+    if !routes.is_empty() {
+        tunnel
+            .send_capsule(&Capsule::RouteAdvertisement { ranges: routes })
+            .await?;
+    }
+
+    loop {
+        select! {
+            ev = tunnel.recv() => match ev? {
+                Some(TunnelEvent::Datagram { context_id: 0, payload }) => {
+                    let allowed = packet_addrs(&payload)
+                        .is_some_and(|(src, _)| is_assigned(&addresses, src));
+
+                    if allowed {
+                        endpoint.send_packet(&payload).await?;
+                    }
+                },
+
+                Some(TunnelEvent::Capsule(Capsule::AddressRequest {
+                    addresses: requests,
+                })) => {
+                    let assign = assign_addresses(
+                        &mut endpoint,
+                        &mut addresses,
+                        &requests,
+                    );
+
+                    tunnel
+                        .send_capsule(&Capsule::AddressAssign { addresses: assign })
+                        .await?;
+                },
+
+                // Unknown contexts and other capsules are ignored.
+                Some(_) => continue,
+
+                None => return Ok(()),
+            },
+
+            packet = endpoint.recv_packet() => match packet? {
+                Some(packet) => {
+                    let allowed = packet_addrs(&packet)
+                        .is_some_and(|(_, dst)| is_assigned(&addresses, dst));
+
+                    if allowed {
+                        tunnel.send_datagram(0, &packet).await?;
+                    }
+                },
+
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+/// Handles the client's address requests, adding the addresses assigned by
+/// `endpoint` to `assigned`.
+///
+/// Returns the contents of the ADDRESS_ASSIGN capsule to respond with: all
+/// assigned addresses, plus an all-zero address for each rejected request.
+fn assign_addresses<E: PacketEndpoint>(
+    endpoint: &mut E, assigned: &mut Vec<AssignedAddress>,
+    requests: &[AssignedAddress],
+) -> Vec<AssignedAddress> {
+    let mut rejected = Vec::new();
+
+    for request in requests {
+        match endpoint.assign_address(request) {
+            Some(address) => assigned.push(AssignedAddress {
+                request_id: request.request_id,
+                ..address
+            }),
+
+            None => {
+                let (ip, prefix_len) = match request.ip {
+                    IpAddr::V4(_) => (Ipv4Addr::UNSPECIFIED.into(), 32),
+                    IpAddr::V6(_) => (Ipv6Addr::UNSPECIFIED.into(), 128),
+                };
+
+                rejected.push(AssignedAddress {
+                    request_id: request.request_id,
+                    ip,
+                    prefix_len,
+                });
+            },
+        }
+    }
+
+    assigned.iter().copied().chain(rejected).collect()
+}
+
+/// Returns the source and destination addresses of an IP packet.
+fn packet_addrs(packet: &[u8]) -> Option<(IpAddr, IpAddr)> {
+    match packet.first()? >> 4 {
+        4 if packet.len() >= 20 => {
+            let src: [u8; 4] = packet[12..16].try_into().ok()?;
+            let dst: [u8; 4] = packet[16..20].try_into().ok()?;
+
+            Some((src.into(), dst.into()))
+        },
+
+        6 if packet.len() >= 40 => {
+            let src: [u8; 16] = packet[8..24].try_into().ok()?;
+            let dst: [u8; 16] = packet[24..40].try_into().ok()?;
+
+            Some((src.into(), dst.into()))
+        },
+
+        _ => None,
+    }
+}
+
+/// Returns whether `ip` is covered by one of the `assigned` prefixes.
+fn is_assigned(assigned: &[AssignedAddress], ip: IpAddr) -> bool {
+    assigned.iter().any(|a| prefix_contains(a, ip))
+}
+
+fn prefix_contains(prefix: &AssignedAddress, ip: IpAddr) -> bool {
+    let (net, ip, bits) = match (prefix.ip, ip) {
+        (IpAddr::V4(net), IpAddr::V4(ip)) =>
+            (u32::from(net) as u128, u32::from(ip) as u128, 32),
+
+        (IpAddr::V6(net), IpAddr::V6(ip)) =>
+            (u128::from(net), u128::from(ip), 128),
+
+        _ => return false,
+    };
+
+    let host_bits = bits - u32::from(prefix.prefix_len.min(bits as u8));
+    let mask = u128::MAX.checked_shl(host_bits).unwrap_or(0);
+
+    net & mask == ip & mask
+}
+
+/// Data received on a [`ConnectIpTunnel`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum ConnectIpEvent {
+    /// An IP packet.
+    Packet(Vec<u8>),
+    /// The proxy replaced the set of addresses assigned to the client.
+    ///
+    /// Rejected address requests are reported with an all-zero address.
+    AddressAssign(Vec<AssignedAddress>),
+    /// The proxy replaced the set of routes advertised to the client.
+    RouteAdvertisement(Vec<IpAddressRange>),
+}
+
+/// A CONNECT-IP tunnel opened with [`TunnelClient::open_connect_ip()`].
+pub struct ConnectIpTunnel {
+    tunnel: Tunnel,
+    next_request_id: u64,
+}
+
+impl ConnectIpTunnel {
+    /// Returns the ID of the tunnel's request stream.
+    pub fn stream_id(&self) -> u64 {
+        self.tunnel.stream_id()
+    }
+
+    /// Sends an IP packet through the tunnel.
+    pub async fn send_packet(&mut self, packet: &[u8]) -> io::Result<()> {
+        self.tunnel.send_datagram(0, packet).await
+    }
+
+    /// Requests an address from the proxy. An unspecified `ip` lets the proxy
+    /// pick any address of that family.
+    ///
+    /// On success the request ID is returned. It is echoed in the
+    /// [`ConnectIpEvent::AddressAssign`] answering the request.
+    pub async fn request_address(
+        &mut self, ip: IpAddr, prefix_len: u8,
+    ) -> io::Result<u64> {
+        let request_id = self.next_request_id;
+
+        let request = Capsule::AddressRequest {
+            addresses: vec![AssignedAddress {
+                request_id,
+                ip,
+                prefix_len,
+            }],
+        };
+
+        self.tunnel.send_capsule(&request).await?;
+        self.next_request_id += 1;
+
+        Ok(request_id)
+    }
+
+    /// Receives the next packet or address configuration from the proxy.
+    ///
+    /// `None` is returned once the proxy closed the tunnel. This method is
+    /// cancel safe.
+    pub async fn recv(&mut self) -> io::Result<Option<ConnectIpEvent>> {
+        loop {
+            let ev = match self.tunnel.recv().await? {
+                Some(TunnelEvent::Datagram {
+                    context_id: 0,
+                    payload,
+                }) => ConnectIpEvent::Packet(payload),
+
+                Some(TunnelEvent::Capsule(Capsule::AddressAssign {
+                    addresses,
+                })) => ConnectIpEvent::AddressAssign(addresses),
+
+                Some(TunnelEvent::Capsule(Capsule::RouteAdvertisement {
+                    ranges,
+                })) => ConnectIpEvent::RouteAdvertisement(ranges),
+
+                Some(_) => continue,
+
+                None => return Ok(None),
+            };
+
+            return Ok(Some(ev));
+        }
+    }
+
+    /// Gracefully closes the tunnel.
+    pub async fn finish(self) -> io::Result<()> {
+        self.tunnel.finish().await
+    }
+}
+
+impl TunnelClient {
+    /// Opens a CONNECT-IP tunnel with the given scope through the proxy at
+    /// `authority`.
+    ///
+    /// See [`TunnelClient::open()`] for details.
+    pub fn open_connect_ip(
+        &mut self, authority: &str, scope: &ConnectIpScope,
+    ) -> impl Future<Output = io::Result<ConnectIpTunnel>> + Send + 'static {
+        let tunnel = self.open(scope.request_headers(authority));
+
+        async move {
+            Ok(ConnectIpTunnel {
+                tunnel: tunnel.await?,
+                // Request ID 0 is reserved for unsolicited assignments.
+                next_request_id: 1,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scope_path() {
+        let scope = ConnectIpScope::default();
+        assert_eq!(scope.to_path(), "/.well-known/masque/ip/*/*/");
+        assert_eq!(ConnectIpScope::from_path(&scope.to_path()), Some(scope));
+
+        let scope = ConnectIpScope {
+            target: Some("192.0.2.0/24".to_string()),
+            ip_protocol: Some(17),
+        };
+        assert_eq!(scope.to_path(), "/.well-known/masque/ip/192.0.2.0%2F24/17/");
+        assert_eq!(ConnectIpScope::from_path(&scope.to_path()), Some(scope));
+
+        for path in [
+            "/.well-known/masque/ip/*/*",
+            "/.well-known/masque/ip/*/*/extra",
+            "/.well-known/masque/ip//*/",
+            "/.well-known/masque/ip/*/256/",
+            "/.well-known/masque/udp/*/*/",
+        ] {
+            assert_eq!(ConnectIpScope::from_path(path), None, "{path}");
+        }
+    }
+
+    #[test]
+    fn packet_filter() {
+        let mut packet = [0; 20];
+        packet[0] = 0x45;
+        packet[12..16].copy_from_slice(&[10, 0, 0, 2]);
+        packet[16..20].copy_from_slice(&[192, 0, 2, 1]);
+
+        let (src, dst) = packet_addrs(&packet).unwrap();
+        assert_eq!(src, IpAddr::from([10, 0, 0, 2]));
+        assert_eq!(dst, IpAddr::from([192, 0, 2, 1]));
+
+        assert_eq!(packet_addrs(&packet[..19]), None);
+        assert_eq!(packet_addrs(&[0x60; 20]), None);
+
+        let assigned = [AssignedAddress {
+            request_id: 0,
+            ip: IpAddr::from([10, 0, 0, 0]),
+            prefix_len: 24,
+        }];
+
+        assert!(is_assigned(&assigned, src));
+        assert!(!is_assigned(&assigned, dst));
+        assert!(!is_assigned(&assigned, IpAddr::from([0xa00; 8])));
+
+        let any = AssignedAddress {
+            request_id: 0,
+            ip: Ipv6Addr::UNSPECIFIED.into(),
+            prefix_len: 0,
+        };
+
+        assert!(prefix_contains(&any, IpAddr::from([0xa00; 8])));
+        assert!(!prefix_contains(&any, src));
+    }
+}
@@ -0,0 +1,464 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::fmt::Write;
+use std::future::Future;
+use std::io;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use foundations::telemetry::log;
+use quiche::h3;
+use tokio::net::UdpSocket;
+use tokio::select;
+use tokio::time::Instant;
+
+use super::header_value;
+use super::percent_decode;
+use super::percent_encode;
+use super::Flows;
+use super::Tunnel;
+use super::TunnelClient;
+use super::TunnelEvent;
+use crate::http3::driver::H3Event;
+use crate::http3::driver::InboundFrameStream;
+use crate::http3::driver::OutboundFrameSender;
+use crate::http3::driver::ServerH3Event;
+
+/// The path prefix of the default CONNECT-UDP URI template,
+/// `/.well-known/masque/udp/{target_host}/{target_port}/`.
+pub const WELL_KNOWN_PATH_PREFIX: &str = "/.well-known/masque/udp/";
+
+/// The largest payload of a UDP datagram.
+const MAX_UDP_PAYLOAD_SIZE: usize = 65527;
+
+/// The target of a CONNECT-UDP request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConnectUdpTarget {
+    /// The target's hostname or IP address.
+    pub host: String,
+    /// The target's UDP port.
+    pub port: u16,
+}
+
+impl ConnectUdpTarget {
+    /// Creates a new [`ConnectUdpTarget`].
+    pub fn new(host: impl Into<String>, port: u16) -> Self {
+        Self {
+            host: host.into(),
+            port,
+        }
+    }
+
+    /// Parses a target from a request path following the default URI
+    /// template.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let path = path.strip_prefix(WELL_KNOWN_PATH_PREFIX)?;
+
+        let mut parts = path.split('/');
+
+        let host = percent_decode(parts.next()?)?;
+        let port = parts.next()?.parse().ok().filter(|port| *port != 0)?;
+
+        // The template ends with a slash.
+        if !parts.next()?.is_empty() || parts.next().is_some() || host.is_empty()
+        {
+            return None;
+        }
+
+        Some(Self { host, port })
+    }
+
+    /// Expands the default URI template into a request path.
+    pub fn to_path(&self) -> String {
+        let mut path = WELL_KNOWN_PATH_PREFIX.to_string();
+
+        percent_encode(&mut path, &self.host);
+        let _ = write!(path, "/{}/", self.port);
+
+        path
+    }
+
+    /// Builds the headers of a CONNECT-UDP request for this target, sent to
+    /// the proxy at `authority`.
+    pub fn request_headers(&self, authority: &str) -> Vec<h3::Header> {
+        vec![
+            h3::Header::new(b":method", b"CONNECT"),
+            h3::Header::new(b":protocol", b"connect-udp"),
+            h3::Header::new(b":scheme", b"https"),
+            h3::Header::new(b":authority", authority.as_bytes()),
+            h3::Header::new(b":path", self.to_path().as_bytes()),
+            h3::Header::new(b"capsule-protocol", b"?1"),
+        ]
+    }
+
+    /// Resolves the target to a list of socket addresses.
+    async fn resolve(&self) -> io::Result<Vec<SocketAddr>> {
+        if let Ok(ip) = self.host.parse::<IpAddr>() {
+            return Ok(vec![SocketAddr::new(ip, self.port)]);
+        }
+
+        Ok(tokio::net::lookup_host((self.host.as_str(), self.port))
+            .await?
+            .collect())
+    }
+}
+
+/// Decides which targets CONNECT-UDP tunnels may be opened to.
+///
+/// The policy is consulted for each address the target resolves to, and the
+/// first allowed address is used. Closures with a matching signature
+/// implement this trait.
+pub trait ConnectUdpPolicy: Send + Sync + 'static {
+    /// Returns whether a tunnel to `addr`, which `target` resolved to, may be
+    /// opened.
+    fn allow(&self, target: &ConnectUdpTarget, addr: SocketAddr) -> bool;
+}
+
+impl<F> ConnectUdpPolicy for F
+where
+    F: Fn(&ConnectUdpTarget, SocketAddr) -> bool + Send + Sync + 'static,
+{
+    fn allow(&self, target: &ConnectUdpTarget, addr: SocketAddr) -> bool {
+        self(target, addr)
+    }
+}
+
+/// Configuration for a [`ConnectUdpServer`].
+#[derive(Clone, Debug)]
+pub struct ConnectUdpSettings {
+    /// Closes a tunnel if no payload was relayed in either direction for this
+    /// long.
+    ///
+    /// Defaults to 2 minutes.
+    pub idle_timeout: Duration,
+}
+
+impl Default for ConnectUdpSettings {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Duration::from_secs(120),
+        }
+    }
+}
+
+/// A CONNECT-UDP proxy for a [ServerH3Driver](crate::ServerH3Driver)'s
+/// connection.
+///
+/// All events received from the driver's controller must be passed to
+/// [`ConnectUdpServer::handle_event()`]. CONNECT-UDP requests are served in
+/// tasks spawned on the current tokio runtime, each relaying payloads between
+/// the tunnel and a UDP socket connected to the target.
+pub struct ConnectUdpServer<P> {
+    settings: ConnectUdpSettings,
+    policy: Arc<P>,
+    flows: Flows,
+}
+
+impl<P: ConnectUdpPolicy> ConnectUdpServer<P> {
+    /// Creates a new [`ConnectUdpServer`] which checks targets against
+    /// `policy`.
+    pub fn new(settings: ConnectUdpSettings, policy: P) -> Self {
+        Self {
+            settings,
+            policy: Arc::new(policy),
+            flows: Flows::default(),
+        }
+    }
+
+    /// Processes an event from the driver. Returns the event if it wasn't
+    /// consumed.
+    ///
+    /// [`H3Event::NewFlow`] events are always consumed. The flow of an
+    /// extended CONNECT request that isn't handled by the proxy can be
+    /// retrieved with [`ConnectUdpServer::take_flow()`].
+    pub fn handle_event(
+        &mut self, event: ServerH3Event,
+    ) -> Option<ServerH3Event> {
+        let ServerH3Event::Core(event) = event;
+
+        match self.flows.handle_event(event)? {
+            H3Event::IncomingHeaders(headers)
+                if is_connect_udp(&headers.headers) =>
+            {
+                let target = header_value(&headers.headers, b":path")
+                    .and_then(|v| std::str::from_utf8(v).ok())
+                    .and_then(ConnectUdpTarget::from_path)
+                    .filter(|_| {
+                        header_value(&headers.headers, b":scheme").is_some() &&
+                            header_value(&headers.headers, b":authority")
+                                .is_some()
+                    });
+
+                let tunnel = self.flows.tunnel(headers)?;
+
+                let settings = self.settings.clone();
+                let policy = Arc::clone(&self.policy);
+
+                tokio::spawn(serve_tunnel(tunnel, target, settings, policy));
+
+                None
+            },
+
+            event => Some(event.into()),
+        }
+    }
+
+    /// Removes the DATAGRAM flow of the extended CONNECT request on
+    /// `stream_id`.
+    pub fn take_flow(
+        &mut self, stream_id: u64,
+    ) -> Option<(OutboundFrameSender, InboundFrameStream)> {
+        self.flows.take(stream_id)
+    }
+}
+
+fn is_connect_udp(headers: &[h3::Header]) -> bool {
+    header_value(headers, b":method") == Some(b"CONNECT") &&
+        header_value(headers, b":protocol") == Some(b"connect-udp")
+}
+
+fn status(code: u16) -> Vec<h3::Header> {
+    vec![h3::Header::new(b":status", code.to_string().as_bytes())]
+}
+
+async fn serve_tunnel<P: ConnectUdpPolicy>(
+    mut tunnel: Tunnel, target: Option<ConnectUdpTarget>,
+    settings: ConnectUdpSettings, policy: Arc<P>,
+) {
+    let Some(target) = target else {
+        let _ = tunnel.respond(status(400), true).await;
+        return;
+    };
+
+    let socket = match connect_socket(&target, &*policy).await {
+        Ok(v) => v,
+
+        Err(e) => {
+            log::debug!(
+                "failed to open CONNECT-UDP tunnel";
+                "stream_id" => tunnel.stream_id(),
+                "target" => ?target,
+                "error" => %e,
+            );
+
+            let code = match e.kind() {
+                io::ErrorKind::PermissionDenied => 403,
+                _ => 502,
+            };
+
+            let _ = tunnel.respond(status(code), true).await;
+            return;
+        },
+    };
+
+    let mut headers = status(200);
+    headers.push(h3::Header::new(b"capsule-protocol", b"?1"));
+
+    if tunnel.respond(headers, false).await.is_err() {
+        return;
+    }
+
+    if let Err(e) = relay(&mut tunnel, &socket, settings.idle_timeout).await {
+        log::debug!(
+            "CONNECT-UDP tunnel failed";
+            "stream_id" => tunnel.stream_id(),
+            "error" => %e,
+        );
+    }
+
+    let _ = tunnel.finish().await;
+}
+
+/// Opens a UDP socket connected to the first address of `target` allowed by
+/// `policy`.
+async fn connect_socket<P: ConnectUdpPolicy>(
+    target: &ConnectUdpTarget, policy: &P,
+) -> io::Result<UdpSocket> {
+    let addr = target
+        .resolve()
+        .await?
+        .into_iter()
+        .find(|addr| policy.allow(target, *addr))
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::PermissionDenied, "target not allowed")
+        })?;
+
+    let local: SocketAddr = match addr {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(addr).await?;
+
+    Ok(socket)
+}
+
+/// Relays payloads between `tunnel` and `socket` until either side closes or
+/// the tunnel is idle for `idle_timeout`.
+async fn relay(
+    tunnel: &mut Tunnel, socket: &UdpSocket, idle_timeout: Duration,
+) -> io::Result<()> {
+    let mut buf = vec![0; MAX_UDP_PAYLOAD_SIZE];
+
+    let idle = tokio::time::sleep(idle_timeout);
+    tokio::pin!(idle);
+
+    loop {
+        select! {
+            ev = tunnel.recv() => match ev? {
+                Some(TunnelEvent::Datagram { context_id: 0, payload }) => {
+                    // Errors caused by ICMP messages from the target are not
+                    // fatal to the tunnel.
+                    let _ = socket.send(&payload).await;
+                },
+
+                // Unknown contexts and capsules are ignored.
+                Some(_) => continue,
+
+                None => return Ok(()),
+            },
+
+            res = socket.recv(&mut buf) => match res {
+                Ok(len) => tunnel.send_datagram(0, &buf[..len]).await?,
+
+                Err(_) => continue,
+            },
+
+            _ = &mut idle => return Ok(()),
+        }
+
+        idle.as_mut().reset(Instant::now() + idle_timeout);
+    }
+}
+
+/// A CONNECT-UDP tunnel opened with [`TunnelClient::open_connect_udp()`].
+pub struct ConnectUdpTunnel {
+    tunnel: Tunnel,
+}
+
+impl ConnectUdpTunnel {
+    /// Returns the ID of the tunnel's request stream.
+    pub fn stream_id(&self) -> u64 {
+        self.tunnel.stream_id()
+    }
+
+    /// Sends a UDP payload to the target.
+    pub async fn send(&mut self, payload: &[u8]) -> io::Result<()> {
+        self.tunnel.send_datagram(0, payload).await
+    }
+
+    /// Receives the next UDP payload from the target.
+    ///
+    /// `None` is returned once the proxy closed the tunnel. This method is
+    /// cancel safe.
+    pub async fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            match self.tunnel.recv().await? {
+                Some(TunnelEvent::Datagram {
+                    context_id: 0,
+                    payload,
+                }) => return Ok(Some(payload)),
+
+                Some(_) => continue,
+
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Gracefully closes the tunnel.
+    pub async fn finish(self) -> io::Result<()> {
+        self.tunnel.finish().await
+    }
+}
+
+impl TunnelClient {
+    /// Opens a CONNECT-UDP tunnel to `target` through the proxy at
+    /// `authority`.
+    ///
+    /// See [`TunnelClient::open()`] for details.
+    pub fn open_connect_udp(
+        &mut self, authority: &str, target: &ConnectUdpTarget,
+    ) -> impl Future<Output = io::Result<ConnectUdpTunnel>> + Send + 'static {
+        let tunnel = self.open(target.request_headers(authority));
+
+        async move {
+            Ok(ConnectUdpTunnel {
+                tunnel: tunnel.await?,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_path() {
+        let target = ConnectUdpTarget::new("example.com", 443);
+        assert_eq!(target.to_path(), "/.well-known/masque/udp/example.com/443/");
+        assert_eq!(ConnectUdpTarget::from_path(&target.to_path()), Some(target));
+
+        let target = ConnectUdpTarget::new("2001:db8::42", 53);
+        assert_eq!(
+            target.to_path(),
+            "/.well-known/masque/udp/2001%3Adb8%3A%3A42/53/"
+        );
+        assert_eq!(ConnectUdpTarget::from_path(&target.to_path()), Some(target));
+
+        assert_eq!(
+            ConnectUdpTarget::from_path(
+                "/.well-known/masque/udp/2001%3adb8%3a%3a42/53/"
+            ),
+            Some(ConnectUdpTarget::new("2001:db8::42", 53))
+        );
+    }
+
+    #[test]
+    fn invalid_target_path() {
+        for path in [
+            "/",
+            "/.well-known/masque/udp/",
+            "/.well-known/masque/udp/example.com/443",
+            "/.well-known/masque/udp/example.com/443/extra",
+            "/.well-known/masque/udp/example.com/0/",
+            "/.well-known/masque/udp/example.com/65536/",
+            "/.well-known/masque/udp//443/",
+            "/.well-known/masque/udp/example%2/443/",
+            "/.well-known/masque/udp/example%zz/443/",
+            "/.well-known/masque/tcp/example.com/443/",
+        ] {
+            assert_eq!(ConnectUdpTarget::from_path(path), None, "{path}");
+        }
+    }
+}
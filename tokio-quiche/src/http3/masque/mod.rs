@@ -0,0 +1,502 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! MASQUE proxying on top of the [H3Driver](crate::http3::driver::H3Driver).
+//!
+//! MASQUE protocols establish a tunnel with an extended CONNECT request. The
+//! tunnel then carries HTTP Datagrams, either in QUIC DATAGRAM frames or, if
+//! the peer didn't negotiate them, in DATAGRAM capsules on the request stream
+//! ([RFC 9297]).
+//!
+//! A [`Tunnel`] hides this distinction. Servers obtain tunnels from a
+//! protocol-specific component such as
+//! [`ConnectUdpServer`](connect_udp::ConnectUdpServer) or
+//! [`ConnectIpServer`](connect_ip::ConnectIpServer), while clients open
+//! them with a [`TunnelClient`]. Both are fed the events received from the
+//! driver's controller, and hand back the events they don't handle.
+//!
+//! [RFC 9297]: https://www.rfc-editor.org/rfc/rfc9297.html
+
+/// CONNECT-IP ([RFC 9484](https://www.rfc-editor.org/rfc/rfc9484.html)).
+pub mod connect_ip;
+/// CONNECT-UDP ([RFC 9298](https://www.rfc-editor.org/rfc/rfc9298.html)).
+pub mod connect_udp;
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::future::Future;
+use std::io;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use futures::SinkExt;
+use quiche::h3;
+use quiche::h3::capsule::Capsule;
+use quiche::h3::capsule::CapsuleDecoder;
+use quiche::h3::NameValue;
+use tokio::select;
+use tokio::sync::oneshot;
+
+use crate::buf_factory::BufFactory;
+use crate::http3::driver::ClientH3Event;
+use crate::http3::driver::ClientRequestSender;
+use crate::http3::driver::H3Event;
+use crate::http3::driver::InboundFrame;
+use crate::http3::driver::InboundFrameStream;
+use crate::http3::driver::IncomingH3Headers;
+use crate::http3::driver::NewClientRequest;
+use crate::http3::driver::OutboundFrame;
+use crate::http3::driver::OutboundFrameSender;
+
+/// The HTTP/3 SETTINGS_H3_DATAGRAM parameter.
+const SETTINGS_H3_DATAGRAM: u64 = 0x33;
+
+/// Returns whether the peer's raw HTTP/3 settings enable HTTP Datagrams.
+fn h3_datagrams_enabled(settings: &[(u64, u64)]) -> bool {
+    settings.contains(&(SETTINGS_H3_DATAGRAM, 1))
+}
+
+/// Returns the value of the first header named `name`.
+fn header_value<'a>(headers: &'a [h3::Header], name: &[u8]) -> Option<&'a [u8]> {
+    headers
+        .iter()
+        .find_map(|h| (h.name() == name).then(|| h.value()))
+}
+
+/// Appends `s` to `out`, percent-encoding everything but unreserved
+/// characters.
+fn percent_encode(out: &mut String, s: &str) {
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            out.push(b as char);
+        } else {
+            let _ = write!(out, "%{b:02X}");
+        }
+    }
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let mut out = Vec::with_capacity(s.len());
+    let mut bytes = s.bytes();
+
+    while let Some(b) = bytes.next() {
+        if b != b'%' {
+            out.push(b);
+            continue;
+        }
+
+        let hi = (bytes.next()? as char).to_digit(16)?;
+        let lo = (bytes.next()? as char).to_digit(16)?;
+
+        out.push((hi * 16 + lo) as u8);
+    }
+
+    String::from_utf8(out).ok()
+}
+
+fn channel_closed<T>(_: T) -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "tunnel closed")
+}
+
+/// A DATAGRAM flow's channels, as announced by [`H3Event::NewFlow`].
+struct Flow {
+    send: OutboundFrameSender,
+    recv: InboundFrameStream,
+}
+
+/// Tracks DATAGRAM flows and the peer's support for HTTP Datagrams.
+///
+/// [`H3Event::NewFlow`] is emitted right before the headers of the request
+/// that created the flow, so flows are kept until the headers are handled.
+#[derive(Default)]
+struct Flows {
+    flows: HashMap<u64, Flow>,
+    h3_datagrams: Arc<AtomicBool>,
+}
+
+impl Flows {
+    /// Records state from `event`. Returns the event if it wasn't consumed.
+    fn handle_event(&mut self, event: H3Event) -> Option<H3Event> {
+        match event {
+            H3Event::NewFlow {
+                flow_id,
+                send,
+                recv,
+            } => {
+                self.flows.insert(flow_id, Flow { send, recv });
+                None
+            },
+
+            H3Event::IncomingSettings { ref settings } => {
+                if h3_datagrams_enabled(settings) {
+                    self.h3_datagrams.store(true, Ordering::Relaxed);
+                }
+
+                Some(event)
+            },
+
+            H3Event::ResetStream { stream_id } |
+            H3Event::StreamClosed { stream_id } => {
+                self.flows.remove(&(stream_id / 4));
+                Some(event)
+            },
+
+            event => Some(event),
+        }
+    }
+
+    /// Removes the DATAGRAM flow associated with `stream_id`.
+    fn take(
+        &mut self, stream_id: u64,
+    ) -> Option<(OutboundFrameSender, InboundFrameStream)> {
+        self.flows
+            .remove(&(stream_id / 4))
+            .map(|flow| (flow.send, flow.recv))
+    }
+
+    /// Builds a [`Tunnel`] for the extended CONNECT stream described by
+    /// `headers`.
+    fn tunnel(&mut self, headers: IncomingH3Headers) -> Option<Tunnel> {
+        let flow_id = headers.stream_id / 4;
+        let flow = self.flows.remove(&flow_id)?;
+
+        Some(Tunnel {
+            stream_id: headers.stream_id,
+            flow_id,
+            stream_send: headers.send,
+            stream_recv: headers.recv,
+            flow_send: flow.send,
+            flow_recv: flow.recv,
+            capsules: CapsuleDecoder::default(),
+            h3_datagrams: Arc::clone(&self.h3_datagrams),
+            finished: headers.read_fin,
+        })
+    }
+}
+
+/// Data received on a [`Tunnel`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum TunnelEvent {
+    /// An HTTP Datagram, received either in a QUIC DATAGRAM frame or in a
+    /// DATAGRAM capsule.
+    Datagram {
+        /// The context ID the payload is associated with.
+        context_id: u64,
+        /// The datagram's payload, following the context ID.
+        payload: Vec<u8>,
+    },
+    /// A capsule other than DATAGRAM.
+    Capsule(Capsule),
+}
+
+/// An established extended CONNECT tunnel.
+///
+/// HTTP Datagrams are sent in QUIC DATAGRAM frames if the peer negotiated
+/// them, and in DATAGRAM capsules otherwise.
+pub struct Tunnel {
+    stream_id: u64,
+    flow_id: u64,
+    stream_send: OutboundFrameSender,
+    stream_recv: InboundFrameStream,
+    flow_send: OutboundFrameSender,
+    flow_recv: InboundFrameStream,
+    capsules: CapsuleDecoder,
+    h3_datagrams: Arc<AtomicBool>,
+    finished: bool,
+}
+
+impl Tunnel {
+    /// Returns the ID of the tunnel's request stream.
+    pub fn stream_id(&self) -> u64 {
+        self.stream_id
+    }
+
+    /// Sends an HTTP Datagram with the given context ID.
+    pub async fn send_datagram(
+        &mut self, context_id: u64, payload: &[u8],
+    ) -> io::Result<()> {
+        let mut dgram = vec![0; octets::varint_len(context_id) + payload.len()];
+        let mut b = octets::OctetsMut::with_slice(&mut dgram);
+        b.put_varint(context_id).map_err(io::Error::other)?;
+        b.put_bytes(payload).map_err(io::Error::other)?;
+
+        if self.h3_datagrams.load(Ordering::Relaxed) {
+            let dgram = BufFactory::dgram_from_vec(dgram);
+
+            self.flow_send
+                .send(OutboundFrame::Datagram(dgram, self.flow_id))
+                .await
+                .map_err(channel_closed)
+        } else {
+            self.send_capsule(&Capsule::Datagram { payload: dgram })
+                .await
+        }
+    }
+
+    /// Sends a capsule on the tunnel's request stream.
+    pub async fn send_capsule(&mut self, capsule: &Capsule) -> io::Result<()> {
+        let mut buf = vec![0; capsule.wire_len()];
+        let mut b = octets::OctetsMut::with_slice(&mut buf);
+        capsule.to_bytes(&mut b).map_err(io::Error::other)?;
+
+        let body = OutboundFrame::body(BufFactory::buf_from_slice(&buf), false);
+
+        self.stream_send.send(body).await.map_err(channel_closed)
+    }
+
+    /// Receives the next HTTP Datagram or capsule.
+    ///
+    /// `None` is returned once the peer finished the request stream. This
+    /// method is cancel safe.
+    pub async fn recv(&mut self) -> io::Result<Option<TunnelEvent>> {
+        loop {
+            match self.capsules.next_capsule() {
+                Ok(Capsule::Datagram { payload }) => {
+                    // Datagrams without a context ID are silently dropped.
+                    if let Some(ev) = parse_datagram(&payload) {
+                        return Ok(Some(ev));
+                    }
+
+                    continue;
+                },
+
+                Ok(capsule) => return Ok(Some(TunnelEvent::Capsule(capsule))),
+
+                Err(h3::Error::Done) => (),
+
+                Err(e) => return Err(io::Error::other(e)),
+            }
+
+            if self.finished {
+                if !self.capsules.is_empty() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "truncated capsule",
+                    ));
+                }
+
+                return Ok(None);
+            }
+
+            select! {
+                frame = self.stream_recv.recv() => match frame {
+                    Some(InboundFrame::Body(buf, fin)) => {
+                        self.capsules.push(&buf);
+                        self.finished = fin;
+                    },
+
+                    Some(InboundFrame::Datagram(_)) => (),
+
+                    None => self.finished = true,
+                },
+
+                Some(frame) = self.flow_recv.recv() => {
+                    if let InboundFrame::Datagram(dgram) = frame {
+                        if let Some(ev) = parse_datagram(&dgram) {
+                            return Ok(Some(ev));
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    /// Sends response headers on the tunnel's request stream.
+    pub(crate) async fn respond(
+        &mut self, headers: Vec<h3::Header>, fin: bool,
+    ) -> io::Result<()> {
+        self.stream_send
+            .send(OutboundFrame::Headers(headers, None))
+            .await
+            .map_err(channel_closed)?;
+
+        if fin {
+            self.stream_send
+                .send(OutboundFrame::body(BufFactory::get_empty_buf(), true))
+                .await
+                .map_err(channel_closed)?;
+        }
+
+        Ok(())
+    }
+
+    /// Gracefully closes the tunnel by finishing the request stream.
+    pub async fn finish(mut self) -> io::Result<()> {
+        let fin = OutboundFrame::body(BufFactory::get_empty_buf(), true);
+
+        self.stream_send.send(fin).await.map_err(channel_closed)
+    }
+}
+
+fn parse_datagram(dgram: &[u8]) -> Option<TunnelEvent> {
+    let mut b = octets::Octets::with_slice(dgram);
+    let context_id = b.get_varint().ok()?;
+
+    Some(TunnelEvent::Datagram {
+        context_id,
+        payload: b.as_ref().to_vec(),
+    })
+}
+
+struct PendingTunnel {
+    stream_id: Option<u64>,
+    tunnel: oneshot::Sender<io::Result<Tunnel>>,
+}
+
+/// Opens MASQUE tunnels over a [ClientH3Driver](crate::ClientH3Driver)'s
+/// connection.
+///
+/// All events received from the driver's controller must be passed to
+/// [`TunnelClient::handle_event()`], which returns those that don't belong to
+/// a tunnel.
+pub struct TunnelClient {
+    requests: ClientRequestSender,
+    next_request_id: u64,
+    pending: HashMap<u64, PendingTunnel>,
+    flows: Flows,
+}
+
+impl TunnelClient {
+    /// Creates a new [`TunnelClient`] sending requests with `requests`.
+    ///
+    /// Request IDs used by the tunnel client start at `u64::MAX` and count
+    /// down, to keep clear of IDs used by the application.
+    pub fn new(requests: ClientRequestSender) -> Self {
+        Self {
+            requests,
+            next_request_id: u64::MAX,
+            pending: HashMap::new(),
+            flows: Flows::default(),
+        }
+    }
+
+    /// Sends an extended CONNECT request.
+    ///
+    /// The returned future resolves to the established [`Tunnel`] once the
+    /// proxy responds with a 2xx status, or to an error otherwise. It only
+    /// makes progress while events are passed to
+    /// [`TunnelClient::handle_event()`].
+    pub fn open(
+        &mut self, headers: Vec<h3::Header>,
+    ) -> impl Future<Output = io::Result<Tunnel>> + Send + 'static {
+        let (tx, rx) = oneshot::channel();
+
+        let request_id = self.next_request_id;
+        self.next_request_id -= 1;
+
+        // The request body is sent through the tunnel, but a body writer is
+        // still required to keep the request stream open.
+        let (body_writer, _) = oneshot::channel();
+
+        let sent = self.requests.send(NewClientRequest {
+            request_id,
+            headers,
+            body_writer: Some(body_writer),
+        });
+
+        if sent.is_ok() {
+            self.pending.insert(request_id, PendingTunnel {
+                stream_id: None,
+                tunnel: tx,
+            });
+        }
+
+        async move { rx.await.map_err(channel_closed)? }
+    }
+
+    /// Processes an event from the driver. Returns the event if it wasn't
+    /// consumed.
+    pub fn handle_event(
+        &mut self, event: ClientH3Event,
+    ) -> Option<ClientH3Event> {
+        let event = match event {
+            ClientH3Event::NewOutboundRequest {
+                stream_id,
+                request_id,
+            } => {
+                let Some(pending) = self.pending.get_mut(&request_id) else {
+                    return Some(event);
+                };
+
+                pending.stream_id = Some(stream_id);
+                return None;
+            },
+
+            ClientH3Event::Core(event) => self.flows.handle_event(event)?,
+        };
+
+        match event {
+            H3Event::IncomingHeaders(headers) => {
+                let Some(request_id) = self.request_id(headers.stream_id) else {
+                    return Some(H3Event::IncomingHeaders(headers).into());
+                };
+
+                let pending = self.pending.remove(&request_id)?;
+
+                let status = header_value(&headers.headers, b":status")
+                    .and_then(|v| std::str::from_utf8(v).ok())
+                    .and_then(|v| v.parse::<u16>().ok());
+
+                let res = match status {
+                    Some(200..=299) => self
+                        .flows
+                        .tunnel(headers)
+                        .ok_or_else(|| io::Error::other("missing flow")),
+
+                    _ => Err(io::Error::new(
+                        io::ErrorKind::ConnectionRefused,
+                        format!("tunnel rejected with status {status:?}"),
+                    )),
+                };
+
+                let _ = pending.tunnel.send(res);
+                None
+            },
+
+            H3Event::ResetStream { stream_id } |
+            H3Event::StreamClosed { stream_id } => {
+                if let Some(request_id) = self.request_id(stream_id) {
+                    let pending = self.pending.remove(&request_id)?;
+                    let _ = pending.tunnel.send(Err(io::Error::new(
+                        io::ErrorKind::ConnectionReset,
+                        "tunnel request reset",
+                    )));
+                }
+
+                Some(event.into())
+            },
+
+            event => Some(event.into()),
+        }
+    }
+
+    fn request_id(&self, stream_id: u64) -> Option<u64> {
+        self.pending
+            .iter()
+            .find_map(|(id, p)| (p.stream_id == Some(stream_id)).then_some(*id))
+    }
+}
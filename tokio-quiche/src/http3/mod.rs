@@ -29,6 +29,8 @@
 /// An [`ApplicationOverQuic`](crate::ApplicationOverQuic) to build clients
 /// and servers on top of.
 pub mod driver;
+/// MASQUE tunnels, such as CONNECT-UDP, built on the HTTP/3 driver.
+pub mod masque;
 /// Configuration for HTTP/3 connections.
 pub mod settings;
 mod stats;
@@ -33,7 +33,10 @@ use crate::QuicResult;
 /// For QUIC servers, it can be useful to encode additional information in the
 /// source connection ID. This trait allows users to implement their own logic
 /// for that purpose. The crate also provides [`SimpleConnectionIdGenerator`]
-/// if no such customization is needed.
+/// if no such customization is needed, and [`QuicLbConnectionIdGenerator`]
+/// for connection IDs that QUIC-LB load balancers can route.
+///
+/// [`QuicLbConnectionIdGenerator`]: crate::quic::QuicLbConnectionIdGenerator
 ///
 /// Clients currently can't configure a [`ConnectionIdGenerator`] and always use
 /// the [`SimpleConnectionIdGenerator`].
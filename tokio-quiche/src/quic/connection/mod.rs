@@ -27,11 +27,17 @@
 mod error;
 mod id;
 mod map;
+mod quic_lb;
 
 pub use self::error::HandshakeError;
 pub use self::id::ConnectionIdGenerator;
 pub use self::id::SimpleConnectionIdGenerator;
 pub(crate) use self::map::ConnectionMap;
+pub use self::quic_lb::QuicLbConfig;
+pub use self::quic_lb::QuicLbConnectionIdGenerator;
+pub use self::quic_lb::QuicLbDecoder;
+pub use self::quic_lb::QUIC_LB_KEY_LEN;
+pub use self::quic_lb::QUIC_LB_UNROUTABLE_CONFIG_ID;
 
 use boring::ssl::SslRef;
 use datagram_socket::AsSocketStats;
@@ -0,0 +1,656 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! QUIC-LB connection ID encoding.
+//!
+//! Implements the connection ID formats of [draft-ietf-quic-load-balancers],
+//! which let layer-4 load balancers route packets to the right server by
+//! extracting a server ID from the connection ID alone.
+//!
+//! Servers issue connection IDs with a [`QuicLbConnectionIdGenerator`], while
+//! load balancers use a [`QuicLbDecoder`] sharing the same [`QuicLbConfig`]s to
+//! recover the server ID.
+//!
+//! [draft-ietf-quic-load-balancers]: https://datatracker.ietf.org/doc/draft-ietf-quic-load-balancers/
+
+use std::fmt;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use boring::symm::Cipher;
+use boring::symm::Crypter;
+use boring::symm::Mode;
+use quiche::ConnectionId;
+use quiche::MAX_CONN_ID_LEN;
+
+use super::ConnectionIdGenerator;
+use crate::QuicResult;
+
+/// The config rotation codepoint reserved for unroutable connection IDs.
+pub const QUIC_LB_UNROUTABLE_CONFIG_ID: u8 = 0b111;
+
+/// The number of config rotation codepoints available to configurations.
+const CONFIG_ID_COUNT: usize = QUIC_LB_UNROUTABLE_CONFIG_ID as usize;
+
+const MIN_SERVER_ID_LEN: usize = 1;
+const MAX_SERVER_ID_LEN: usize = 15;
+
+const MIN_NONCE_LEN: usize = 4;
+const MAX_NONCE_LEN: usize = 18;
+
+/// The maximum combined length of the server ID and nonce.
+const MAX_PLAINTEXT_LEN: usize = 19;
+
+/// The length of the AES-128 key used by encrypted configurations.
+pub const QUIC_LB_KEY_LEN: usize = 16;
+
+const BLOCK_LEN: usize = 16;
+
+const MAX_HALF_LEN: usize = MAX_PLAINTEXT_LEN.div_ceil(2);
+
+/// A QUIC-LB configuration shared by servers and load balancers.
+///
+/// A configuration is identified by its config rotation codepoint, encoded in
+/// the three most significant bits of the first octet of each connection ID,
+/// so that up to 7 configurations can be in use at the same time during a
+/// rotation.
+///
+/// Configurations with a key encrypt the server ID and nonce with AES-128,
+/// using the single-pass algorithm when they add up to 16 octets and the
+/// four-pass algorithm otherwise. Configurations without a key encode them in
+/// plaintext.
+#[derive(Clone, PartialEq, Eq)]
+pub struct QuicLbConfig {
+    config_id: u8,
+    server_id_len: usize,
+    nonce_len: usize,
+    key: Option<[u8; QUIC_LB_KEY_LEN]>,
+    length_self_encoding: bool,
+}
+
+impl QuicLbConfig {
+    /// Creates a new configuration.
+    ///
+    /// `config_id` must be lower than [`QUIC_LB_UNROUTABLE_CONFIG_ID`],
+    /// `server_id_len` between 1 and 15 octets, and `nonce_len` between 4 and
+    /// 18 octets, with their sum not exceeding 19 octets.
+    pub fn new(
+        config_id: u8, server_id_len: usize, nonce_len: usize,
+        key: Option<[u8; QUIC_LB_KEY_LEN]>,
+    ) -> QuicResult<Self> {
+        if config_id >= QUIC_LB_UNROUTABLE_CONFIG_ID {
+            return Err("config rotation codepoint is out of range".into());
+        }
+
+        if !(MIN_SERVER_ID_LEN..=MAX_SERVER_ID_LEN).contains(&server_id_len) {
+            return Err("server ID length is out of range".into());
+        }
+
+        if !(MIN_NONCE_LEN..=MAX_NONCE_LEN).contains(&nonce_len) {
+            return Err("nonce length is out of range".into());
+        }
+
+        if server_id_len + nonce_len > MAX_PLAINTEXT_LEN {
+            return Err("server ID and nonce are too long".into());
+        }
+
+        Ok(QuicLbConfig {
+            config_id,
+            server_id_len,
+            nonce_len,
+            key,
+            length_self_encoding: false,
+        })
+    }
+
+    /// Configures whether the connection ID length is encoded in the first
+    /// octet, instead of random bits.
+    ///
+    /// The default value is `false`.
+    pub fn set_length_self_encoding(&mut self, v: bool) {
+        self.length_self_encoding = v;
+    }
+
+    /// Returns the config rotation codepoint of the configuration.
+    pub fn config_id(&self) -> u8 {
+        self.config_id
+    }
+
+    /// Returns the length of the server IDs encoded by the configuration.
+    pub fn server_id_len(&self) -> usize {
+        self.server_id_len
+    }
+
+    /// Returns the length of the nonces encoded by the configuration.
+    pub fn nonce_len(&self) -> usize {
+        self.nonce_len
+    }
+
+    /// Returns the minimum length of the connection IDs encoded by the
+    /// configuration.
+    pub fn min_cid_len(&self) -> usize {
+        1 + self.plaintext_len()
+    }
+
+    fn plaintext_len(&self) -> usize {
+        self.server_id_len + self.nonce_len
+    }
+
+    /// Encodes `server_id` and `nonce` into a connection ID of `cid_len`
+    /// octets.
+    ///
+    /// Octets past [`min_cid_len()`](Self::min_cid_len) are filled with random
+    /// bytes, and ignored when decoding.
+    pub fn encode(
+        &self, server_id: &[u8], nonce: &[u8], cid_len: usize,
+    ) -> QuicResult<ConnectionId<'static>> {
+        if server_id.len() != self.server_id_len {
+            return Err("server ID length doesn't match the config".into());
+        }
+
+        if nonce.len() != self.nonce_len {
+            return Err("nonce length doesn't match the config".into());
+        }
+
+        if !(self.min_cid_len()..=MAX_CONN_ID_LEN).contains(&cid_len) {
+            return Err("connection ID length is out of range".into());
+        }
+
+        let mut cid = vec![0; cid_len];
+        boring::rand::rand_bytes(&mut cid)?;
+
+        let low_bits = if self.length_self_encoding {
+            (cid_len - 1) as u8
+        } else {
+            cid[0] & 0x1f
+        };
+
+        cid[0] = (self.config_id << 5) | low_bits;
+
+        let plaintext = &mut cid[1..self.min_cid_len()];
+        plaintext[..self.server_id_len].copy_from_slice(server_id);
+        plaintext[self.server_id_len..].copy_from_slice(nonce);
+
+        if let Some(key) = &self.key {
+            if plaintext.len() == BLOCK_LEN {
+                let mut cipher = Aes128Ecb::new(key, Mode::Encrypt)?;
+                let block = cipher.apply(plaintext)?;
+
+                plaintext.copy_from_slice(&block);
+            } else {
+                four_pass(key, plaintext, false)?;
+            }
+        }
+
+        Ok(ConnectionId::from_vec(cid))
+    }
+
+    /// Decodes the server ID and nonce of a connection ID encoded with this
+    /// configuration.
+    pub fn decode(&self, cid: &[u8]) -> QuicResult<(Vec<u8>, Vec<u8>)> {
+        if cid.len() < self.min_cid_len() {
+            return Err("connection ID is too short".into());
+        }
+
+        if cid[0] >> 5 != self.config_id {
+            return Err("connection ID uses a different config".into());
+        }
+
+        let mut plaintext = cid[1..self.min_cid_len()].to_vec();
+
+        if let Some(key) = &self.key {
+            if plaintext.len() == BLOCK_LEN {
+                let mut cipher = Aes128Ecb::new(key, Mode::Decrypt)?;
+                let block = cipher.apply(&plaintext)?;
+
+                plaintext.copy_from_slice(&block);
+            } else {
+                four_pass(key, &mut plaintext, true)?;
+            }
+        }
+
+        let nonce = plaintext.split_off(self.server_id_len);
+
+        Ok((plaintext, nonce))
+    }
+}
+
+impl fmt::Debug for QuicLbConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuicLbConfig")
+            .field("config_id", &self.config_id)
+            .field("server_id_len", &self.server_id_len)
+            .field("nonce_len", &self.nonce_len)
+            .field("encrypted", &self.key.is_some())
+            .field("length_self_encoding", &self.length_self_encoding)
+            .finish()
+    }
+}
+
+/// A [`ConnectionIdGenerator`] which creates QUIC-LB connection IDs carrying
+/// the server's ID.
+///
+/// Connection IDs are 20 octets long, the length the
+/// [`QuicListener`](crate::socket::QuicListener) routes packets on. Nonces are
+/// taken from a counter starting at a random value, which is shared by all
+/// clones of the generator. Configurations with a nonce shorter than 8 octets
+/// must be rotated before `2^(8 * nonce_len)` connection IDs have been issued,
+/// as nonces would repeat afterwards. Any `socket_cookie` value is ignored.
+#[derive(Clone, Debug)]
+pub struct QuicLbConnectionIdGenerator {
+    config: QuicLbConfig,
+    server_id: Vec<u8>,
+    nonce: Arc<AtomicU64>,
+}
+
+impl QuicLbConnectionIdGenerator {
+    /// Creates a generator encoding `server_id` with the given configuration.
+    pub fn new(config: QuicLbConfig, server_id: &[u8]) -> QuicResult<Self> {
+        if server_id.len() != config.server_id_len {
+            return Err("server ID length doesn't match the config".into());
+        }
+
+        let mut seed = [0; 8];
+        boring::rand::rand_bytes(&mut seed)?;
+
+        Ok(QuicLbConnectionIdGenerator {
+            config,
+            server_id: server_id.to_vec(),
+            nonce: Arc::new(AtomicU64::new(u64::from_be_bytes(seed))),
+        })
+    }
+
+    fn next_nonce(&self) -> Vec<u8> {
+        let counter = self.nonce.fetch_add(1, Ordering::Relaxed).to_be_bytes();
+
+        let nonce_len = self.config.nonce_len;
+        let counter_len = nonce_len.min(counter.len());
+
+        let mut nonce = vec![0; nonce_len];
+        nonce[nonce_len - counter_len..]
+            .copy_from_slice(&counter[counter.len() - counter_len..]);
+
+        nonce
+    }
+}
+
+impl ConnectionIdGenerator<'static> for QuicLbConnectionIdGenerator {
+    fn new_connection_id(&self, _socket_cookie: u64) -> ConnectionId<'static> {
+        let nonce = self.next_nonce();
+
+        self.config
+            .encode(&self.server_id, &nonce, MAX_CONN_ID_LEN)
+            .unwrap()
+    }
+
+    /// Verifies that `cid` carries this server's ID.
+    fn verify_connection_id(
+        &self, _socket_cookie: u64, cid: &ConnectionId<'_>,
+    ) -> QuicResult<()> {
+        let (server_id, _) = self.config.decode(cid)?;
+
+        if server_id != self.server_id {
+            return Err("connection ID belongs to another server".into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Extracts server IDs from QUIC-LB connection IDs, for use by load
+/// balancers.
+#[derive(Clone, Debug, Default)]
+pub struct QuicLbDecoder {
+    configs: [Option<QuicLbConfig>; CONFIG_ID_COUNT],
+}
+
+impl QuicLbDecoder {
+    /// Creates a decoder without any configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a configuration, replacing the one using the same config rotation
+    /// codepoint if any.
+    pub fn set_config(&mut self, config: QuicLbConfig) {
+        let idx = config.config_id as usize;
+
+        self.configs[idx] = Some(config);
+    }
+
+    /// Removes the configuration using the given config rotation codepoint.
+    pub fn remove_config(&mut self, config_id: u8) {
+        if let Some(config) = self.configs.get_mut(config_id as usize) {
+            *config = None;
+        }
+    }
+
+    /// Returns the server ID encoded in `cid`.
+    ///
+    /// `None` is returned for unroutable connection IDs, including those
+    /// using an unknown configuration, which should be routed by other means
+    /// (e.g. by hashing the 4-tuple).
+    pub fn server_id(&self, cid: &[u8]) -> Option<Vec<u8>> {
+        let config_id = *cid.first()? >> 5;
+
+        let config = self.configs.get(config_id as usize)?.as_ref()?;
+
+        config.decode(cid).ok().map(|(server_id, _)| server_id)
+    }
+}
+
+/// A single-block AES-128-ECB cipher.
+struct Aes128Ecb(Crypter);
+
+impl Aes128Ecb {
+    fn new(key: &[u8; QUIC_LB_KEY_LEN], mode: Mode) -> QuicResult<Self> {
+        let mut crypter = Crypter::new(Cipher::aes_128_ecb(), mode, key, None)?;
+        crypter.set_padding(false);
+
+        Ok(Aes128Ecb(crypter))
+    }
+
+    fn apply(&mut self, block: &[u8]) -> QuicResult<[u8; BLOCK_LEN]> {
+        // The output needs room for an extra block, even without padding.
+        let mut out = [0; 2 * BLOCK_LEN];
+
+        let len = self.0.update(block, &mut out)?;
+
+        if len != BLOCK_LEN {
+            return Err("unexpected AES-ECB output length".into());
+        }
+
+        let mut res = [0; BLOCK_LEN];
+        res.copy_from_slice(&out[..BLOCK_LEN]);
+
+        Ok(res)
+    }
+}
+
+/// Encrypts or decrypts `buf` in place with the four-pass algorithm, used
+/// when the server ID and nonce don't add up to exactly one AES block.
+///
+/// `buf` is split into two halves, each one being in turn expanded to a block,
+/// encrypted, and XORed into the other half. When `buf` has an odd length,
+/// the halves share the middle octet, the left one owning its most
+/// significant nibble.
+fn four_pass(
+    key: &[u8; QUIC_LB_KEY_LEN], buf: &mut [u8], decrypt: bool,
+) -> QuicResult<()> {
+    let len = buf.len();
+    let half_len = len.div_ceil(2);
+    let odd = len % 2 == 1;
+
+    let mut cipher = Aes128Ecb::new(key, Mode::Encrypt)?;
+
+    let mut left = [0; MAX_HALF_LEN];
+    let mut right = [0; MAX_HALF_LEN];
+
+    let left = &mut left[..half_len];
+    let right = &mut right[..half_len];
+
+    left.copy_from_slice(&buf[..half_len]);
+    right.copy_from_slice(&buf[len - half_len..]);
+
+    if odd {
+        left[half_len - 1] &= 0xf0;
+        right[0] &= 0x0f;
+    }
+
+    // Decryption runs the same passes in reverse order.
+    let passes = if decrypt { [4, 3, 2, 1] } else { [1, 2, 3, 4] };
+
+    for index in passes {
+        // Odd passes encrypt the left half into the right one, and even passes
+        // the other way around.
+        let (src, dst) = if index % 2 == 1 {
+            (&*left, &mut *right)
+        } else {
+            (&*right, &mut *left)
+        };
+
+        let mut block = [0; BLOCK_LEN];
+        block[..half_len].copy_from_slice(src);
+        block[BLOCK_LEN - 2] = len as u8;
+        block[BLOCK_LEN - 1] = index;
+
+        let mask = cipher.apply(&block)?;
+
+        for (d, m) in dst.iter_mut().zip(mask.iter()) {
+            *d ^= m;
+        }
+
+        if odd {
+            if index % 2 == 1 {
+                dst[0] &= 0x0f;
+            } else {
+                dst[half_len - 1] &= 0xf0;
+            }
+        }
+    }
+
+    if odd {
+        buf[..half_len - 1].copy_from_slice(&left[..half_len - 1]);
+        buf[half_len - 1] = left[half_len - 1] | right[0];
+        buf[half_len..].copy_from_slice(&right[1..]);
+    } else {
+        buf[..half_len].copy_from_slice(left);
+        buf[half_len..].copy_from_slice(right);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    // Test vectors from draft-ietf-quic-load-balancers, Appendix B.2.
+    const KEY: &str = "8f95f09245765f80256934e50c66207f";
+
+    const VECTORS: &[(u8, bool, &str, &str, &str)] = &[
+        // Four-pass, odd length.
+        (0, true, "ed793a", "ee080dbf", "0720b1d07b359d3c"),
+        // Four-pass, odd length, server ID longer than the nonce.
+        (
+            1,
+            false,
+            "ed793a51d49b8f5fab65",
+            "ee080dbf48",
+            "2fcc381bc74cb4fbad2823a3d1f8fed2",
+        ),
+        // Single-pass.
+        (
+            2,
+            true,
+            "ed793a51d49b8f5f",
+            "ee080dbf48c0d1e5",
+            "504dd2d05a7b0de9b2b9907afb5ecf8cc3",
+        ),
+        // Four-pass, even length.
+        (
+            0,
+            true,
+            "ed793a51d49b8f5fab",
+            "ee080dbf48c0d1e55d",
+            "125779c9cc86beb3a3a4a3ca96fce4bfe0cdbc",
+        ),
+    ];
+
+    fn key() -> [u8; QUIC_LB_KEY_LEN] {
+        hex(KEY).try_into().unwrap()
+    }
+
+    #[test]
+    fn encrypted_vectors() {
+        for &(config_id, lse, sid, nonce, cid) in VECTORS {
+            let (sid, nonce, cid) = (hex(sid), hex(nonce), hex(cid));
+
+            let mut config =
+                QuicLbConfig::new(config_id, sid.len(), nonce.len(), Some(key()))
+                    .unwrap();
+            config.set_length_self_encoding(lse);
+
+            let encoded = config.encode(&sid, &nonce, cid.len()).unwrap();
+
+            // Without length self-encoding the first octet's low bits are
+            // random.
+            if lse {
+                assert_eq!(encoded[0], cid[0]);
+            } else {
+                assert_eq!(encoded[0] >> 5, cid[0] >> 5);
+            }
+
+            assert_eq!(encoded[1..], cid[1..]);
+
+            assert_eq!(config.decode(&cid).unwrap(), (sid, nonce));
+        }
+    }
+
+    #[test]
+    fn plaintext() {
+        let mut config = QuicLbConfig::new(0, 3, 4, None).unwrap();
+        config.set_length_self_encoding(true);
+
+        let cid = config.encode(&hex("ed793a"), &hex("ee080dbf"), 8).unwrap();
+        assert_eq!(cid.as_ref(), hex("07ed793aee080dbf"));
+
+        // Trailing octets are random and ignored when decoding.
+        let cid = config.encode(&hex("ed793a"), &hex("ee080dbf"), 20).unwrap();
+        assert_eq!(cid[0], 0x13);
+        assert_eq!(cid[1..8], hex("ed793aee080dbf"));
+
+        assert_eq!(
+            config.decode(&cid).unwrap(),
+            (hex("ed793a"), hex("ee080dbf"))
+        );
+
+        let config = QuicLbConfig::new(5, 3, 4, None).unwrap();
+
+        let cid = config.encode(&hex("ed793a"), &hex("ee080dbf"), 8).unwrap();
+        assert_eq!(cid[0] >> 5, 5);
+        assert_eq!(cid[1..], hex("ed793aee080dbf"));
+    }
+
+    #[test]
+    fn invalid_config() {
+        assert!(
+            QuicLbConfig::new(QUIC_LB_UNROUTABLE_CONFIG_ID, 3, 4, None).is_err()
+        );
+        assert!(QuicLbConfig::new(0, 0, 4, None).is_err());
+        assert!(QuicLbConfig::new(0, 16, 4, None).is_err());
+        assert!(QuicLbConfig::new(0, 3, 3, None).is_err());
+        assert!(QuicLbConfig::new(0, 1, 19, None).is_err());
+        assert!(QuicLbConfig::new(0, 10, 10, None).is_err());
+        assert!(QuicLbConfig::new(0, 15, 4, None).is_ok());
+
+        let config = QuicLbConfig::new(0, 3, 4, None).unwrap();
+
+        assert!(config.encode(&[0; 2], &[0; 4], 8).is_err());
+        assert!(config.encode(&[0; 3], &[0; 5], 8).is_err());
+        assert!(config.encode(&[0; 3], &[0; 4], 7).is_err());
+        assert!(config.encode(&[0; 3], &[0; 4], 21).is_err());
+        assert!(config.decode(&[0; 7]).is_err());
+    }
+
+    #[test]
+    fn generator() {
+        let mut config = QuicLbConfig::new(1, 4, 8, Some(key())).unwrap();
+        config.set_length_self_encoding(true);
+
+        let server_id = hex("c0ffee01");
+
+        let generator =
+            QuicLbConnectionIdGenerator::new(config.clone(), &server_id).unwrap();
+
+        let other =
+            QuicLbConnectionIdGenerator::new(config.clone(), &hex("c0ffee02"))
+                .unwrap();
+
+        let mut decoder = QuicLbDecoder::new();
+        decoder.set_config(config);
+
+        let mut nonces = std::collections::HashSet::new();
+
+        for _ in 0..100 {
+            let cid = generator.new_connection_id(0);
+
+            assert_eq!(cid.len(), MAX_CONN_ID_LEN);
+            assert_eq!(cid[0], 0x33);
+
+            assert!(generator.verify_connection_id(0, &cid).is_ok());
+            assert!(other.verify_connection_id(0, &cid).is_err());
+
+            assert_eq!(decoder.server_id(&cid), Some(server_id.clone()));
+
+            let (_, nonce) =
+                decoder.configs[1].as_ref().unwrap().decode(&cid).unwrap();
+            assert!(nonces.insert(nonce));
+        }
+
+        // Clones share the nonce counter.
+        let clone = generator.clone();
+        let cid = clone.new_connection_id(0);
+        let (_, nonce) =
+            decoder.configs[1].as_ref().unwrap().decode(&cid).unwrap();
+        assert!(nonces.insert(nonce));
+    }
+
+    #[test]
+    fn decoder_unroutable() {
+        let config = QuicLbConfig::new(2, 3, 4, Some(key())).unwrap();
+
+        let generator =
+            QuicLbConnectionIdGenerator::new(config.clone(), &hex("ed793a"))
+                .unwrap();
+
+        let mut decoder = QuicLbDecoder::new();
+
+        let cid = generator.new_connection_id(0);
+        assert_eq!(decoder.server_id(&cid), None);
+
+        decoder.set_config(config);
+        assert_eq!(decoder.server_id(&cid), Some(hex("ed793a")));
+
+        let mut unroutable = cid.to_vec();
+        unroutable[0] |= QUIC_LB_UNROUTABLE_CONFIG_ID << 5;
+        assert_eq!(decoder.server_id(&unroutable), None);
+
+        assert_eq!(decoder.server_id(&cid[..5]), None);
+        assert_eq!(decoder.server_id(&[]), None);
+
+        decoder.remove_config(2);
+        assert_eq!(decoder.server_id(&cid), None);
+    }
+}
@@ -562,6 +562,7 @@ where
         let recv_info = quiche::RecvInfo {
             from: pkt.peer_addr,
             to: pkt.local_addr,
+            ecn: quiche::Ecn::NotEct,
         };
 
         if let Some(gro) = pkt.gro {
@@ -121,7 +121,12 @@ pub use self::connection::HandshakeInfo;
 pub use self::connection::Incoming;
 pub use self::connection::QuicCommand;
 pub use self::connection::QuicConnectionStats;
+pub use self::connection::QuicLbConfig;
+pub use self::connection::QuicLbConnectionIdGenerator;
+pub use self::connection::QuicLbDecoder;
 pub use self::connection::SimpleConnectionIdGenerator;
+pub use self::connection::QUIC_LB_KEY_LEN;
+pub use self::connection::QUIC_LB_UNROUTABLE_CONFIG_ID;
 pub use self::hooks::ConnectionHook;
 
 /// Alias of [quiche::Connection] used internally by the crate.
@@ -153,6 +153,7 @@ where
         let recv_info = quiche::RecvInfo {
             from: incoming.peer_addr,
             to: incoming.local_addr,
+            ecn: quiche::Ecn::NotEct,
         };
 
         if let Some(gro) = incoming.gro {
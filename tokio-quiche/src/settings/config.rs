@@ -27,6 +27,7 @@
 use foundations::telemetry::log;
 use std::borrow::Cow;
 use std::fs::File;
+use std::sync::Arc;
 use std::time::Duration;
 
 use crate::result::QuicResult;
@@ -129,6 +130,10 @@ fn make_quiche_config(
 
     let quic_settings = &params.settings;
 
+    if !quic_settings.ech_keys.0.is_empty() {
+        config.set_ech_keys(&quic_settings.ech_keys.0)?;
+    }
+
     let alpns: Vec<&[u8]> =
         quic_settings.alpn.iter().map(Vec::as_slice).collect();
     config.set_application_protos(&alpns).unwrap();
@@ -165,6 +170,9 @@ fn make_quiche_config(
     config
         .set_active_connection_id_limit(quic_settings.active_connection_id_limit);
     config.set_cc_algorithm_name(quic_settings.cc_algorithm.as_str())?;
+    if let Some(factory) = &quic_settings.custom_cc.0 {
+        config.set_custom_cc(Arc::clone(factory));
+    }
     config.set_initial_congestion_window_packets(
         quic_settings.initial_congestion_window_packets,
     );
@@ -183,6 +191,12 @@ fn make_quiche_config(
     config.set_max_connection_window(quic_settings.max_connection_window);
     config.set_max_stream_window(quic_settings.max_stream_window);
     config.grease(quic_settings.grease);
+
+    if let Some(ech_config_list) = &quic_settings.ech_config_list {
+        config.set_ech_config_list(ech_config_list);
+    }
+
+    config.set_ech_grease(quic_settings.ech_grease);
     config.set_max_amplification_factor(quic_settings.max_amplification_factor);
     config.set_ack_delay_exponent(quic_settings.ack_delay_exponent);
     config.set_max_ack_delay(quic_settings.max_ack_delay);
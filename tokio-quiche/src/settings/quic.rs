@@ -25,8 +25,14 @@
 // SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use foundations::settings::settings;
+use foundations::settings::Settings;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
 use serde_with::serde_as;
 use serde_with::DurationMilliSeconds;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// QUIC configuration parameters.
@@ -155,6 +161,15 @@ pub struct QuicSettings {
     #[serde(default = "QuicSettings::default_cc_algorithm")]
     pub cc_algorithm: String,
 
+    /// A user-supplied congestion control algorithm, which takes precedence
+    /// over `cc_algorithm` when set.
+    ///
+    /// Defaults to `None`. See [`set_custom_cc()`] for more.
+    ///
+    /// [`set_custom_cc()`]: https://docs.rs/quiche/latest/quiche/struct.Config.html#method.set_custom_cc
+    #[serde(skip)]
+    pub custom_cc: CustomCongestionControl,
+
     /// The default initial congestion window size in terms of packet count.
     ///
     /// Defaults to 10.
@@ -245,6 +260,31 @@ pub struct QuicSettings {
     #[serde(default = "QuicSettings::default_grease")]
     pub grease: bool,
 
+    /// Configures the Encrypted Client Hello configurations offered by
+    /// client-side connections, as a serialized ECHConfigList.
+    ///
+    /// Defaults to `None`. See [`set_ech_config_list()`] for more.
+    ///
+    /// [`set_ech_config_list()`]: https://docs.rs/quiche/latest/quiche/struct.Config.html#method.set_ech_config_list
+    #[serde(skip)]
+    pub ech_config_list: Option<Vec<u8>>,
+
+    /// Configures whether client-side connections send a GREASE ECH
+    /// extension when no ECH configuration is set.
+    ///
+    /// Defaults to true.
+    #[serde(default = "QuicSettings::default_ech_grease")]
+    pub ech_grease: bool,
+
+    /// Encrypted Client Hello keys of server-side connections. ECH is
+    /// disabled if empty.
+    ///
+    /// Defaults to no keys. See [`set_ech_keys()`] for more.
+    ///
+    /// [`set_ech_keys()`]: https://docs.rs/quiche/latest/quiche/struct.Config.html#method.set_ech_keys
+    #[serde(skip)]
+    pub ech_keys: EchKeys,
+
     /// Sets the anti-amplification limit factor.
     ///
     /// Defaults to 3.
@@ -389,6 +429,11 @@ impl QuicSettings {
         true
     }
 
+    #[inline]
+    fn default_ech_grease() -> bool {
+        true
+    }
+
     #[inline]
     fn default_amplification_factor() -> usize {
         3
@@ -415,6 +460,87 @@ impl QuicSettings {
     }
 }
 
+/// A factory for user-supplied congestion controllers, used by
+/// [`QuicSettings::custom_cc`].
+///
+/// The factory can't be expressed in a settings file, so it serializes to
+/// nothing and is ignored when deserializing.
+#[derive(Clone, Default)]
+pub struct CustomCongestionControl(
+    pub Option<Arc<dyn quiche::CongestionControllerFactory>>,
+);
+
+impl CustomCongestionControl {
+    /// Uses the congestion controllers created by `factory`.
+    pub fn new(factory: Arc<dyn quiche::CongestionControllerFactory>) -> Self {
+        Self(Some(factory))
+    }
+}
+
+impl std::fmt::Debug for CustomCongestionControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.0 {
+            Some(_) => f.write_str("enabled"),
+            None => f.write_str("disabled"),
+        }
+    }
+}
+
+impl Serialize for CustomCongestionControl {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_none()
+    }
+}
+
+impl<'de> Deserialize<'de> for CustomCongestionControl {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        serde::de::IgnoredAny::deserialize(deserializer)?;
+        Ok(Self::default())
+    }
+}
+
+impl Settings for CustomCongestionControl {}
+
+/// Encrypted Client Hello keys of server-side connections, used by
+/// [`QuicSettings::ech_keys`].
+///
+/// Private keys don't belong in a settings file, so the keys serialize to
+/// nothing and are ignored when deserializing.
+#[derive(Clone, Default)]
+pub struct EchKeys(pub Vec<quiche::EchKey>);
+
+impl EchKeys {
+    /// Uses `keys` to decrypt Encrypted Client Hellos.
+    pub fn new(keys: Vec<quiche::EchKey>) -> Self {
+        Self(keys)
+    }
+}
+
+impl std::fmt::Debug for EchKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} keys", self.0.len())
+    }
+}
+
+impl Serialize for EchKeys {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_none()
+    }
+}
+
+impl<'de> Deserialize<'de> for EchKeys {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        serde::de::IgnoredAny::deserialize(deserializer)?;
+        Ok(Self::default())
+    }
+}
+
+impl Settings for EchKeys {}
+
 #[cfg(test)]
 mod test {
     use super::QuicSettings;
@@ -0,0 +1,337 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::io;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+use tokio_quiche::http3::driver::H3Event;
+use tokio_quiche::http3::driver::ServerH3Event;
+use tokio_quiche::http3::masque::connect_ip::ConnectIpEvent;
+use tokio_quiche::http3::masque::connect_ip::ConnectIpHandler;
+use tokio_quiche::http3::masque::connect_ip::ConnectIpScope;
+use tokio_quiche::http3::masque::connect_ip::ConnectIpServer;
+use tokio_quiche::http3::masque::connect_ip::ConnectIpSession;
+use tokio_quiche::http3::masque::connect_ip::ConnectIpTunnel;
+use tokio_quiche::http3::masque::connect_ip::PacketEndpoint;
+use tokio_quiche::http3::masque::TunnelClient;
+use tokio_quiche::quic::connect;
+use tokio_quiche::quiche::h3::capsule::AssignedAddress;
+use tokio_quiche::quiche::h3::capsule::IpAddressRange;
+use tokio_quiche::ClientH3Controller;
+
+use crate::fixtures::*;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+const CLIENT_ADDR: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 2);
+const REMOTE_ADDR: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 1);
+
+/// The network side of a tunnel, as seen by the test.
+struct Network {
+    from_client: mpsc::UnboundedReceiver<Vec<u8>>,
+    to_client: mpsc::UnboundedSender<Vec<u8>>,
+}
+
+/// An in-memory [`PacketEndpoint`] connected to a [`Network`].
+struct PacketQueue {
+    to_network: mpsc::UnboundedSender<Vec<u8>>,
+    from_network: mpsc::UnboundedReceiver<Vec<u8>>,
+    next_host: u8,
+}
+
+impl PacketEndpoint for PacketQueue {
+    async fn send_packet(&mut self, packet: &[u8]) -> io::Result<()> {
+        self.to_network
+            .send(packet.to_vec())
+            .map_err(|_| io::ErrorKind::BrokenPipe.into())
+    }
+
+    async fn recv_packet(&mut self) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.from_network.recv().await)
+    }
+
+    fn assign_address(
+        &mut self, request: &AssignedAddress,
+    ) -> Option<AssignedAddress> {
+        // Only IPv4 hosts are handed out.
+        if !request.ip.is_ipv4() {
+            return None;
+        }
+
+        let ip = Ipv4Addr::new(10, 0, 0, self.next_host);
+        self.next_host += 1;
+
+        Some(AssignedAddress {
+            request_id: request.request_id,
+            ip: ip.into(),
+            prefix_len: 32,
+        })
+    }
+}
+
+/// Accepts tunnels with a wildcard scope, assigning [`CLIENT_ADDR`] and a
+/// default route.
+#[derive(Clone)]
+struct TestHandler {
+    networks: mpsc::UnboundedSender<Network>,
+}
+
+impl ConnectIpHandler for TestHandler {
+    type Endpoint = PacketQueue;
+
+    async fn open(
+        &self, scope: &ConnectIpScope,
+    ) -> io::Result<ConnectIpSession<PacketQueue>> {
+        if *scope != ConnectIpScope::default() {
+            return Err(io::ErrorKind::PermissionDenied.into());
+        }
+
+        let (to_network, from_client) = mpsc::unbounded_channel();
+        let (to_client, from_network) = mpsc::unbounded_channel();
+
+        self.networks
+            .send(Network {
+                from_client,
+                to_client,
+            })
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+
+        Ok(ConnectIpSession {
+            endpoint: PacketQueue {
+                to_network,
+                from_network,
+                next_host: 3,
+            },
+            addresses: vec![assigned(0, CLIENT_ADDR.into(), 32)],
+            routes: vec![default_route()],
+        })
+    }
+}
+
+fn assigned(request_id: u64, ip: IpAddr, prefix_len: u8) -> AssignedAddress {
+    AssignedAddress {
+        request_id,
+        ip,
+        prefix_len,
+    }
+}
+
+fn default_route() -> IpAddressRange {
+    IpAddressRange {
+        start: Ipv4Addr::UNSPECIFIED.into(),
+        end: Ipv4Addr::BROADCAST.into(),
+        ip_protocol: 0,
+    }
+}
+
+/// Builds a minimal IPv4 packet.
+fn ipv4_packet(src: Ipv4Addr, dst: Ipv4Addr, payload: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0; 20];
+    packet[0] = 0x45;
+    packet[2..4].copy_from_slice(&(20 + payload.len() as u16).to_be_bytes());
+    packet[8] = 64;
+    packet[9] = 17;
+    packet[12..16].copy_from_slice(&src.octets());
+    packet[16..20].copy_from_slice(&dst.octets());
+    packet.extend_from_slice(payload);
+
+    packet
+}
+
+async fn serve_connect_ip(
+    mut connection: ServerH3Connection, handler: TestHandler,
+) {
+    let mut proxy = ConnectIpServer::new(handler);
+
+    let events = connection.h3_controller.event_receiver_mut();
+
+    while let Some(event) = events.recv().await {
+        if let Some(ServerH3Event::Core(
+            H3Event::ConnectionError(_) | H3Event::ConnectionShutdown(_),
+        )) = proxy.handle_event(event)
+        {
+            break;
+        }
+    }
+}
+
+/// Starts a CONNECT-IP proxy and returns its address, along with the network
+/// side of each tunnel it opens.
+fn start_proxy() -> (SocketAddr, mpsc::UnboundedReceiver<Network>) {
+    let (networks, rx) = mpsc::unbounded_channel();
+    let handler = TestHandler { networks };
+
+    let http3_settings = Http3Settings {
+        enable_extended_connect: true,
+        ..Default::default()
+    };
+
+    let url = start_server_with_settings(
+        QuicSettings::default(),
+        http3_settings,
+        TestConnectionHook::new(),
+        move |conn| serve_connect_ip(conn, handler.clone()),
+    );
+
+    (url.trim_start_matches("http://").parse().unwrap(), rx)
+}
+
+/// Connects to `proxy` and opens a CONNECT-IP tunnel with `scope`.
+async fn open_tunnel(
+    proxy: SocketAddr, scope: ConnectIpScope,
+) -> (ClientH3Controller, io::Result<ConnectIpTunnel>) {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    socket.connect(proxy).await.unwrap();
+
+    let (_, mut controller) = connect(socket, None).await.unwrap();
+
+    let mut client = TunnelClient::new(controller.request_sender());
+    let tunnel = client.open_connect_ip(&proxy.to_string(), &scope);
+
+    let mut events = controller.take_event_receiver();
+
+    tokio::spawn(async move {
+        while let Some(event) = events.recv().await {
+            client.handle_event(event);
+        }
+    });
+
+    let tunnel = timeout(TIMEOUT, tunnel).await.expect("tunnel timed out");
+
+    (controller, tunnel)
+}
+
+async fn recv_event(tunnel: &mut ConnectIpTunnel) -> ConnectIpEvent {
+    timeout(TIMEOUT, tunnel.recv())
+        .await
+        .expect("tunnel timed out")
+        .unwrap()
+        .expect("tunnel closed")
+}
+
+#[tokio::test]
+async fn connect_ip_packets() {
+    let (proxy, mut networks) = start_proxy();
+
+    let (_controller, tunnel) =
+        open_tunnel(proxy, ConnectIpScope::default()).await;
+    let mut tunnel = tunnel.unwrap();
+
+    let mut network = networks.recv().await.unwrap();
+
+    assert_eq!(
+        recv_event(&mut tunnel).await,
+        ConnectIpEvent::AddressAssign(vec![assigned(0, CLIENT_ADDR.into(), 32)])
+    );
+    assert_eq!(
+        recv_event(&mut tunnel).await,
+        ConnectIpEvent::RouteAdvertisement(vec![default_route()])
+    );
+
+    // Packets with a source address that wasn't assigned are dropped.
+    let spoofed = ipv4_packet(Ipv4Addr::new(10, 0, 0, 9), REMOTE_ADDR, b"no");
+    let packet = ipv4_packet(CLIENT_ADDR, REMOTE_ADDR, b"hello");
+
+    tunnel.send_packet(&spoofed).await.unwrap();
+    tunnel.send_packet(&packet).await.unwrap();
+
+    let received = timeout(TIMEOUT, network.from_client.recv()).await.unwrap();
+    assert_eq!(received, Some(packet));
+
+    // So are packets to addresses that weren't assigned.
+    let misrouted = ipv4_packet(REMOTE_ADDR, Ipv4Addr::new(10, 0, 0, 9), b"no");
+    let reply = ipv4_packet(REMOTE_ADDR, CLIENT_ADDR, b"world");
+
+    network.to_client.send(misrouted).unwrap();
+    network.to_client.send(reply.clone()).unwrap();
+
+    assert_eq!(recv_event(&mut tunnel).await, ConnectIpEvent::Packet(reply));
+
+    tunnel.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn connect_ip_address_request() {
+    let (proxy, _networks) = start_proxy();
+
+    let (_controller, tunnel) =
+        open_tunnel(proxy, ConnectIpScope::default()).await;
+    let mut tunnel = tunnel.unwrap();
+
+    // Skip the initial configuration.
+    recv_event(&mut tunnel).await;
+    recv_event(&mut tunnel).await;
+
+    let v4 = tunnel
+        .request_address(Ipv4Addr::UNSPECIFIED.into(), 32)
+        .await
+        .unwrap();
+
+    let mut addresses = vec![
+        assigned(0, CLIENT_ADDR.into(), 32),
+        assigned(v4, Ipv4Addr::new(10, 0, 0, 3).into(), 32),
+    ];
+
+    assert_eq!(
+        recv_event(&mut tunnel).await,
+        ConnectIpEvent::AddressAssign(addresses.clone())
+    );
+
+    // Rejected requests are answered with an all-zero address.
+    let v6 = tunnel
+        .request_address(Ipv6Addr::UNSPECIFIED.into(), 64)
+        .await
+        .unwrap();
+
+    addresses.push(assigned(v6, Ipv6Addr::UNSPECIFIED.into(), 128));
+
+    assert_eq!(
+        recv_event(&mut tunnel).await,
+        ConnectIpEvent::AddressAssign(addresses)
+    );
+}
+
+#[tokio::test]
+async fn connect_ip_scope_rejected() {
+    let (proxy, _networks) = start_proxy();
+
+    let scope = ConnectIpScope {
+        target: Some("192.0.2.0/24".to_string()),
+        ip_protocol: None,
+    };
+
+    let (_controller, tunnel) = open_tunnel(proxy, scope).await;
+
+    let err = tunnel.err().expect("tunnel was allowed");
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+}
@@ -0,0 +1,196 @@
+// Copyright (C) 2026, Cloudflare, Inc.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS
+// IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO,
+// THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR
+// PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+use tokio_quiche::http3::driver::H3Event;
+use tokio_quiche::http3::driver::ServerH3Event;
+use tokio_quiche::http3::masque::connect_udp::ConnectUdpServer;
+use tokio_quiche::http3::masque::connect_udp::ConnectUdpSettings;
+use tokio_quiche::http3::masque::connect_udp::ConnectUdpTarget;
+use tokio_quiche::http3::masque::connect_udp::ConnectUdpTunnel;
+use tokio_quiche::http3::masque::TunnelClient;
+use tokio_quiche::quic::connect;
+use tokio_quiche::ClientH3Controller;
+
+use crate::fixtures::*;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn serve_connect_udp(
+    mut connection: ServerH3Connection, settings: ConnectUdpSettings,
+) {
+    // Only tunnels to the local host are allowed.
+    let mut proxy = ConnectUdpServer::new(
+        settings,
+        |_: &ConnectUdpTarget, addr: SocketAddr| addr.ip().is_loopback(),
+    );
+
+    let events = connection.h3_controller.event_receiver_mut();
+
+    while let Some(event) = events.recv().await {
+        if let Some(ServerH3Event::Core(
+            H3Event::ConnectionError(_) | H3Event::ConnectionShutdown(_),
+        )) = proxy.handle_event(event)
+        {
+            break;
+        }
+    }
+}
+
+/// Starts a CONNECT-UDP proxy and returns its address.
+fn start_proxy(
+    quic_settings: QuicSettings, settings: ConnectUdpSettings,
+) -> SocketAddr {
+    let http3_settings = Http3Settings {
+        enable_extended_connect: true,
+        ..Default::default()
+    };
+
+    let url = start_server_with_settings(
+        quic_settings,
+        http3_settings,
+        TestConnectionHook::new(),
+        move |conn| serve_connect_udp(conn, settings.clone()),
+    );
+
+    url.trim_start_matches("http://").parse().unwrap()
+}
+
+/// Starts a UDP echo server and returns its address.
+async fn start_echo_server() -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let mut buf = [0; 1500];
+
+        while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+            let _ = socket.send_to(&buf[..len], peer).await;
+        }
+    });
+
+    addr
+}
+
+/// Connects to `proxy` and opens a CONNECT-UDP tunnel to `target`.
+async fn open_tunnel(
+    proxy: SocketAddr, target: ConnectUdpTarget,
+) -> (ClientH3Controller, io::Result<ConnectUdpTunnel>) {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    socket.connect(proxy).await.unwrap();
+
+    let (_, mut controller) = connect(socket, None).await.unwrap();
+
+    let mut client = TunnelClient::new(controller.request_sender());
+    let tunnel = client.open_connect_udp(&proxy.to_string(), &target);
+
+    let mut events = controller.take_event_receiver();
+
+    tokio::spawn(async move {
+        while let Some(event) = events.recv().await {
+            client.handle_event(event);
+        }
+    });
+
+    let tunnel = timeout(TIMEOUT, tunnel).await.expect("tunnel timed out");
+
+    (controller, tunnel)
+}
+
+async fn echo(tunnel: &mut ConnectUdpTunnel, payload: &[u8]) {
+    tunnel.send(payload).await.unwrap();
+
+    let echoed = timeout(TIMEOUT, tunnel.recv()).await.unwrap().unwrap();
+    assert_eq!(echoed.as_deref(), Some(payload));
+}
+
+#[tokio::test]
+async fn connect_udp_datagrams() {
+    let proxy = start_proxy(QuicSettings::default(), Default::default());
+    let echo_server = start_echo_server().await;
+
+    let target = ConnectUdpTarget::new("127.0.0.1", echo_server.port());
+    let (_controller, tunnel) = open_tunnel(proxy, target).await;
+    let mut tunnel = tunnel.unwrap();
+
+    echo(&mut tunnel, b"hello").await;
+    echo(&mut tunnel, &[42; 1000]).await;
+
+    tunnel.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn connect_udp_capsule_fallback() {
+    // Without QUIC DATAGRAM support, the proxy doesn't enable HTTP Datagrams
+    // and the client falls back to DATAGRAM capsules.
+    let mut quic_settings = QuicSettings::default();
+    quic_settings.enable_dgram = false;
+
+    let proxy = start_proxy(quic_settings, Default::default());
+    let echo_server = start_echo_server().await;
+
+    let target = ConnectUdpTarget::new("127.0.0.1", echo_server.port());
+    let (_controller, tunnel) = open_tunnel(proxy, target).await;
+    let mut tunnel = tunnel.unwrap();
+
+    echo(&mut tunnel, b"hello").await;
+}
+
+#[tokio::test]
+async fn connect_udp_policy() {
+    let proxy = start_proxy(QuicSettings::default(), Default::default());
+
+    let target = ConnectUdpTarget::new("192.0.2.1", 53);
+    let (_controller, tunnel) = open_tunnel(proxy, target).await;
+
+    let err = tunnel.err().expect("tunnel was allowed");
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+}
+
+#[tokio::test]
+async fn connect_udp_idle_timeout() {
+    let settings = ConnectUdpSettings {
+        idle_timeout: Duration::from_millis(200),
+    };
+
+    let proxy = start_proxy(QuicSettings::default(), settings);
+    let echo_server = start_echo_server().await;
+
+    let target = ConnectUdpTarget::new("127.0.0.1", echo_server.port());
+    let (_controller, tunnel) = open_tunnel(proxy, target).await;
+    let mut tunnel = tunnel.unwrap();
+
+    echo(&mut tunnel, b"hello").await;
+
+    // The proxy closes the tunnel once it's idle.
+    let closed = timeout(TIMEOUT, tunnel.recv()).await.unwrap().unwrap();
+    assert_eq!(closed, None);
+}
@@ -42,6 +42,8 @@ use tokio_quiche::ConnectionParams;
 use tokio_quiche::InitialQuicConnection;
 
 pub mod async_callbacks;
+pub mod connect_ip;
+pub mod connect_udp;
 pub mod connection_close;
 pub mod headers;
 pub mod timeouts;
@@ -187,6 +187,7 @@ pub fn run(
             let recv_info = quiche::RecvInfo {
                 from,
                 to: local_addr,
+                ecn: quiche::Ecn::NotEct,
             };
 
             // Process potentially coalesced packets.
@@ -360,6 +361,21 @@ pub fn run(
 
                     Ok((_goaway_id, quiche::h3::Event::GoAway)) => (),
 
+                    // Server push is never enabled.
+                    Ok((_, quiche::h3::Event::PushPromise { .. })) |
+                    Ok((_, quiche::h3::Event::PushStream { .. })) |
+                    Ok((_, quiche::h3::Event::CancelPush)) => unreachable!(),
+
+                    // WebTransport is never enabled.
+                    Ok((_, quiche::h3::Event::WebTransportStream { .. })) |
+                    Ok((_, quiche::h3::Event::WebTransportDatagram { .. })) |
+                    Ok((
+                        _,
+                        quiche::h3::Event::WebTransportSessionClosed { .. },
+                    )) |
+                    Ok((_, quiche::h3::Event::WebTransportSessionDrain)) =>
+                        unreachable!(),
+
                     Err(quiche::h3::Error::Done) => {
                         break;
                     },